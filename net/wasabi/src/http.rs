@@ -2,12 +2,12 @@ extern crate alloc;
 use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
-use alloc::vec::Vec;
 use noli::net::lookup_host;
 use noli::net::SocketAddr;
 use noli::net::TcpStream;
 use saba_core::error::Error;
-use saba_core::http::HttpResponse;
+use saba_core::http::{HttpRequest, HttpResponse, HttpResponseParser};
+use saba_core::url::Url;
 
 pub struct HttpClient {}
 
@@ -16,7 +16,37 @@ impl HttpClient {
         Self {}
     }
 
+    // pathは先頭の/があってもなくてもよい
     pub fn get(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+        let path = path.strip_prefix('/').unwrap_or(&path);
+        let request = HttpRequest::get(format!("http://{}:{}/{}", host, port, path));
+        self.send(&request)
+    }
+
+    // リクエストのURLのホストに接続して送る。Acceptは指定がなければ補い、接続は1回ごとに閉じる
+    pub fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let url = match Url::new(request.url()).parse() {
+            Ok(url) => url,
+            Err(e) => return Err(Error::UnexpectedInput(e)),
+        };
+        let host = url.host();
+        let port = match url.port().parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                return Err(Error::UnexpectedInput(format!(
+                    "port number should be u16 but got {}",
+                    url.port()
+                )))
+            }
+        };
+
+        let mut request = request.clone();
+        if request.header_value("Accept").is_none() {
+            request.set_header("Accept", "text/html")?;
+        }
+        request.set_header("Connection", "close")?;
+        let request = request.to_bytes()?;
+
         let ips = match lookup_host(&host) {
             Ok(ips) => ips,
             Err(e) => {
@@ -42,19 +72,7 @@ impl HttpClient {
             }
         };
 
-        let mut request = String::from("GET /");
-        request.push_str(&path);
-        request.push_str(" HTTP/1.1\n");
-
-        // ヘッダの追加
-        request.push_str("Host: ");
-        request.push_str(&host);
-        request.push('\n');
-        request.push_str("Accept: text/html\n");
-        request.push_str("Connection: close\n");
-        request.push('\n');
-
-        let _bytes_written = match stream.write(request.as_bytes()) {
+        let _bytes_written = match stream.write(&request) {
             Ok(bytes) => bytes,
             Err(_) => {
                 return Err(Error::Network(
@@ -63,7 +81,8 @@ impl HttpClient {
             }
         };
 
-        let mut received = Vec::new();
+        // 受信したバイト列を順に解析し、レスポンスの終わりまで読んだら接続が閉じるのを待たない
+        let mut parser = HttpResponseParser::new();
         loop {
             let mut buf = [0u8; 4096];
             let bytes_read = match stream.read(&mut buf) {
@@ -77,12 +96,11 @@ impl HttpClient {
             if bytes_read == 0 {
                 break;
             }
-            received.extend_from_slice(&buf[..bytes_read]);
+            if parser.feed(&buf[..bytes_read])? {
+                break;
+            }
         }
 
-        match core::str::from_utf8(&received) {
-            Ok(response) => HttpResponse::new(response.to_string()),
-            Err(e) => Err(Error::Network(format!("Invalid received response: {}", e))),
-        }
+        parser.finish()
     }
 }
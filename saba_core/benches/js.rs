@@ -0,0 +1,78 @@
+//! 同じスクリプトを、ASTを直接評価するインタプリタとバイトコードのVMで実行した時間を比べる
+//! cargo bench --bench js
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use saba_core::renderer::{
+    dom::node::{Node, NodeKind},
+    js::{
+        ast::{JsParser, Program},
+        runtime::JsRuntime,
+        token::JsLexer,
+    },
+};
+
+const ITERATIONS: u32 = 5;
+
+const SCRIPTS: [(&str, &str); 3] = [
+    (
+        "fib",
+        "function fib(n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); } var result = fib(20);",
+    ),
+    (
+        "loops",
+        "var sum = 0; for (let i = 0; i < 20000; i = i + 1) { let j = 0; while (j < 10) { sum = sum + i * j; j = j + 1; } }",
+    ),
+    (
+        "string concat",
+        "var s = \"\"; for (let i = 0; i < 20000; i = i + 1) { s = s + \"x\" + i; } var result = s.length;",
+    ),
+];
+
+fn parse(script: &str) -> Program {
+    JsParser::new(JsLexer::new(script.to_string()))
+        .parse_ast()
+        .expect("failed to parse")
+}
+
+fn new_runtime() -> JsRuntime {
+    JsRuntime::new(Rc::new(RefCell::new(Node::new(NodeKind::Document))))
+}
+
+fn report(name: &str, engine: &str, total: Duration) {
+    println!(
+        "{:<16} {:<12} {:>10.3} ms/iter",
+        name,
+        engine,
+        total.as_secs_f64() * 1000.0 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    for (name, script) in SCRIPTS {
+        let program = parse(script);
+
+        let mut interpreter = Duration::ZERO;
+        for _ in 0..ITERATIONS {
+            let mut runtime = new_runtime();
+            let start = Instant::now();
+            runtime.interpret(&program).expect("failed to interpret");
+            interpreter += start.elapsed();
+        }
+        report(name, "interpreter", interpreter);
+
+        // コンパイルの時間も含める
+        let mut vm = Duration::ZERO;
+        for _ in 0..ITERATIONS {
+            let mut runtime = new_runtime();
+            let start = Instant::now();
+            runtime.execute(&program).expect("failed to execute");
+            vm += start.elapsed();
+        }
+        report(name, "vm", vm);
+    }
+}
@@ -0,0 +1,75 @@
+//! 大きな文書に対して、全体のレイアウトと変更部分だけの再レイアウトの時間を比べる
+//! cargo bench --bench relayout
+
+use std::time::{Duration, Instant};
+
+use saba_core::renderer::{
+    css::{cssom::CssParser, token::CssTokenizer},
+    dom::api::{get_element_by_id, get_style_content, set_attribute, set_text_content},
+    html::{parser::HtmlParser, token::HtmlTokenizer},
+    layout::layout_view::LayoutView,
+};
+
+const SECTIONS: usize = 500;
+const ITERATIONS: u32 = 20;
+
+fn create_html() -> String {
+    let mut html = String::from(
+        "<html><head><style>.item { width: 300px; } .wide { width: 400px; }</style></head><body>",
+    );
+    for i in 0..SECTIONS {
+        html.push_str(&format!(
+            "<div id=\"section{i}\"><h1>section {i}</h1><p class=\"item\">paragraph {i} with some text</p><p><a href=\"#{i}\">link {i}</a></p></div>",
+        ));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn report(name: &str, total: Duration) {
+    println!(
+        "{:<24} {:>10.3} ms/iter",
+        name,
+        total.as_secs_f64() * 1000.0 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    let html = create_html();
+    let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+    let dom = window.borrow().document();
+    let cssom =
+        CssParser::new(CssTokenizer::new(get_style_content(dom.clone()))).parse_stylesheet();
+
+    let mut full = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let view = LayoutView::new(dom.clone(), &cssom);
+        full += start.elapsed();
+        assert!(view.root().is_some());
+    }
+    report("full layout", full);
+
+    let mut view = LayoutView::new(dom.clone(), &cssom);
+    let target = get_element_by_id(Some(dom.clone()), &format!("section{}", SECTIONS / 2))
+        .expect("target should exist");
+
+    let mut text = Duration::ZERO;
+    for i in 0..ITERATIONS {
+        set_text_content(&target, &format!("changed {i}"));
+        let start = Instant::now();
+        view.relayout(dom.clone(), &cssom);
+        text += start.elapsed();
+    }
+    report("incremental (text)", text);
+
+    let mut attribute = Duration::ZERO;
+    for i in 0..ITERATIONS {
+        let class = if i % 2 == 0 { "wide" } else { "item" };
+        set_attribute(&target, "class", class);
+        let start = Instant::now();
+        view.relayout(dom.clone(), &cssom);
+        attribute += start.elapsed();
+    }
+    report("incremental (attribute)", attribute);
+}
@@ -0,0 +1,122 @@
+//! 画面を持たずにHTMLファイルを読み込み、スクリプトがconsoleに出力したメッセージを表示する
+//! cargo run --example headless -- test.html [実行する時間(ミリ秒)]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    rc::Rc,
+};
+
+use saba_core::{
+    error::Error,
+    http::{HttpResponse, Transport},
+    renderer::{
+        js::{
+            console::{ConsoleListener, ConsoleMessage},
+            event_loop::ManualClock,
+        },
+        page::Page,
+    },
+};
+
+// ファイルはこのオリジンのURLとして読み込む
+const ORIGIN: &str = "http://localhost/";
+
+// イベントループを1回まわすごとに進める時間
+const FRAME_MS: u64 = 16;
+
+const DEFAULT_DURATION_MS: u64 = 1000;
+
+#[derive(Debug)]
+// URLのパスをrootからの相対パスとしてファイルを返す
+struct FileTransport {
+    root: PathBuf,
+}
+
+impl Transport for FileTransport {
+    fn fetch(&self, url: String) -> Result<HttpResponse, Error> {
+        let path = match url.strip_prefix(ORIGIN) {
+            Some(path) => path.split(['?', '#']).next().unwrap_or_default(),
+            None => return Err(Error::Network(format!("failed to connect to {}", url))),
+        };
+        let raw = match fs::read_to_string(self.root.join(path)) {
+            Ok(body) => format!(
+                "HTTP/1.1 200 OK\nContent-Type: {}\n\n{}",
+                content_type(path),
+                body
+            ),
+            Err(_) => "HTTP/1.1 404 Not Found\nContent-Type: text/plain\n\nnot found".to_string(),
+        };
+        HttpResponse::new(raw)
+    }
+}
+
+fn content_type(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("html") | Some("htm") => "text/html",
+        Some("js") | Some("mjs") => "text/javascript",
+        Some("css") => "text/css",
+        _ => "text/plain",
+    }
+}
+
+fn print_message(message: &ConsoleMessage) {
+    println!("{}", message);
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    let file = match args.get(1) {
+        Some(file) => file,
+        None => {
+            eprintln!("usage: headless <html file> [duration ms]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let duration = match args.get(2).map(|ms| ms.parse::<u64>()) {
+        Some(Ok(ms)) => ms,
+        Some(Err(_)) => {
+            eprintln!("duration should be a number of milliseconds");
+            return ExitCode::FAILURE;
+        }
+        None => DEFAULT_DURATION_MS,
+    };
+
+    let path = Path::new(file);
+    let root = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.to_string(),
+        None => {
+            eprintln!("invalid file name: {}", file);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let clock = ManualClock::new();
+    let mut page = Page::new();
+    let listener: fn(&ConsoleMessage) = print_message;
+    let listener: Rc<dyn ConsoleListener> = Rc::new(listener);
+    page.set_console_listener(listener);
+    page.set_clock(Rc::new(clock.clone()));
+    page.set_transport(Rc::new(FileTransport { root }));
+    if let Err(e) = page.navigate(format!("{}{}", ORIGIN, name)) {
+        eprintln!("failed to load {}: {:?}", file, e);
+        return ExitCode::FAILURE;
+    }
+
+    // タイマーとrequestAnimationFrameのために、時計を進めながらイベントループをまわす
+    let mut elapsed = 0;
+    while elapsed < duration {
+        page.run_event_loop();
+        clock.advance(FRAME_MS);
+        elapsed += FRAME_MS;
+    }
+    page.run_event_loop();
+
+    if page.js_errors().is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
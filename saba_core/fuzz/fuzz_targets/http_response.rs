@@ -0,0 +1,21 @@
+//! HTTPレスポンスの解析が任意のバイト列でpanicしないことと、分割して与えても結果が変わらないことを確かめる
+//! cargo +nightly fuzz run http_response
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use saba_core::http::{HttpResponse, HttpResponseParser};
+
+fuzz_target!(|data: &[u8]| {
+    let whole = HttpResponse::from_bytes(data);
+
+    // 最初のバイトで決めた位置で分割して与える
+    let split = data.first().map_or(0, |b| *b as usize % (data.len() + 1));
+    let mut parser = HttpResponseParser::new();
+    let parts = parser
+        .feed(&data[..split])
+        .and_then(|_| parser.feed(&data[split..]))
+        .and_then(|_| parser.finish());
+
+    assert_eq!(whole, parts);
+});
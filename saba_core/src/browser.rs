@@ -2,7 +2,13 @@ use core::cell::RefCell;
 
 use alloc::{rc::Rc, vec::Vec};
 
-use crate::renderer::page::Page;
+use crate::{
+    http::Transport,
+    renderer::{
+        js::{console::ConsoleListener, event_loop::Clock},
+        page::Page,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct Browser {
@@ -28,4 +34,25 @@ impl Browser {
     pub fn current_page(&self) -> Rc<RefCell<Page>> {
         self.pages[self.active_page_index].clone()
     }
+
+    // すべてのページのイベントループが使う時計を設定する
+    pub fn set_clock(&self, clock: Rc<dyn Clock>) {
+        for page in &self.pages {
+            page.borrow_mut().set_clock(clock.clone());
+        }
+    }
+
+    // すべてのページが移動とスクリプトのリクエストで使うTransportを設定する
+    pub fn set_transport(&self, transport: Rc<dyn Transport>) {
+        for page in &self.pages {
+            page.borrow_mut().set_transport(transport.clone());
+        }
+    }
+
+    // すべてのページのconsoleのメッセージを受け取るリスナーを設定する
+    pub fn set_console_listener(&self, listener: Rc<dyn ConsoleListener>) {
+        for page in &self.pages {
+            page.borrow_mut().set_console_listener(listener.clone());
+        }
+    }
 }
@@ -10,6 +10,10 @@ pub static TOOLBAR_HEIGHT: i64 = 26;
 pub static CONTENT_AREA_WIDTH: i64 = WINDOW_WIDTH - WINDOW_PADDING * 2;
 pub static CONTENT_AREA_HEIGHT: i64 = TITLE_BAR_HEIGHT - WINDOW_PADDING * 2;
 
+// ページが描画される領域（ビューポート）の高さ
+pub static VIEWPORT_HEIGHT: i64 =
+    WINDOW_HEIGHT - TITLE_BAR_HEIGHT - TOOLBAR_HEIGHT - WINDOW_PADDING * 2;
+
 pub static CHAR_WIDTH: i64 = 8;
 pub static CHAR_HEIGHT: i64 = 16;
 pub static CHAR_HEIGHT_WITH_PADDING: i64 = CHAR_HEIGHT + 4;
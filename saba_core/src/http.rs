@@ -1,16 +1,17 @@
-use crate::{alloc::string::ToString, error::Error};
+use crate::{alloc::string::ToString, error::Error, url::Url};
 use alloc::{format, string::String, vec::Vec};
+use core::fmt::Debug;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HttpResponse {
     version: String,
     status_code: u32,
     reason: String,
     headers: Vec<Header>,
-    body: String,
+    body: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header {
     name: String,
     value: String,
@@ -20,47 +21,390 @@ impl Header {
     pub fn new(name: String, value: String) -> Self {
         Self { name, value }
     }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
 }
 
-impl HttpResponse {
-    pub fn new(raw_response: String) -> Result<Self, Error> {
-        let preproceed_response = raw_response.trim_start().replace("\n\r", "\n");
-
-        let (status_line, remaining) = match preproceed_response.split_once("\n") {
-            Some((s, r)) => (s, r),
-            None => {
-                return Err(Error::Network(format!(
-                    "invalid http response: {}",
-                    preproceed_response,
-                )))
+// URLを受け取ってHTTPレスポンスを返す。ページの移動とスクリプトのfetchは同じTransportを使う
+pub trait Transport: Debug {
+    fn fetch(&self, url: String) -> Result<HttpResponse, Error>;
+
+    // メソッド、ヘッダ、本文を持つリクエストを送る。既定ではGETのURLだけをfetchに渡す
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        if request.method() != "GET" {
+            return Err(Error::Network(format!(
+                "method {} is not supported by this transport",
+                request.method()
+            )));
+        }
+        self.fetch(request.url())
+    }
+}
+
+// 埋め込み側がURLを処理する関数をそのままTransportとして使える
+impl Transport for fn(String) -> Result<HttpResponse, Error> {
+    fn fetch(&self, url: String) -> Result<HttpResponse, Error> {
+        self(url)
+    }
+}
+
+// リクエストを処理する関数の場合は、GET以外のメソッドも送れる
+impl Transport for fn(&HttpRequest) -> Result<HttpResponse, Error> {
+    fn fetch(&self, url: String) -> Result<HttpResponse, Error> {
+        self(&HttpRequest::get(url))
+    }
+
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        self(request)
+    }
+}
+
+// 1行の最大の長さ。ステータス行、ヘッダ、チャンクの大きさの行に適用する
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// レスポンスのどこまで解析したか
+enum ParseState {
+    StatusLine,
+    Headers,
+    // Content-Lengthで長さが決まる本文。残りのバイト数
+    Body(usize),
+    // 接続が閉じるまでを本文とする
+    UntilClose,
+    ChunkSize,
+    // チャンクの残りのバイト数
+    ChunkData(usize),
+    // チャンクのデータの後の改行
+    ChunkEnd,
+    // 最後のチャンクの後のトレーラー
+    Trailers,
+    Done,
+}
+
+#[derive(Debug, Clone)]
+// 受信したバイト列を少しずつ受け取り、HTTP/1.1のレスポンスを解析する
+// 改行はCRLFとLFのどちらも受け付ける
+pub struct HttpResponseParser {
+    state: ParseState,
+    // まだ解析していないバイト列
+    buffer: Vec<u8>,
+    version: String,
+    status_code: u32,
+    reason: String,
+    headers: Vec<Header>,
+    body: Vec<u8>,
+}
+
+impl HttpResponseParser {
+    pub fn new() -> Self {
+        Self {
+            state: ParseState::StatusLine,
+            buffer: Vec::new(),
+            version: String::new(),
+            status_code: 0,
+            reason: String::new(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    // 受信したバイト列を解析する。レスポンスの終わりまで読んだ場合はtrue
+    pub fn feed(&mut self, data: &[u8]) -> Result<bool, Error> {
+        if self.state == ParseState::Done {
+            return Ok(true);
+        }
+        self.buffer.extend_from_slice(data);
+        while self.step()? {}
+        Ok(self.is_complete())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.state == ParseState::Done
+    }
+
+    // 接続が閉じた時に呼び出す。本文の長さが決まっていない場合は、ここまでに受信したものが本文になる
+    pub fn finish(mut self) -> Result<HttpResponse, Error> {
+        match self.state {
+            ParseState::Done | ParseState::UntilClose => {}
+            ParseState::StatusLine if self.buffer.iter().all(u8::is_ascii_whitespace) => {
+                return Err(invalid("empty response"));
             }
-        };
+            _ => return Err(invalid("the response ended unexpectedly")),
+        }
+        self.state = ParseState::Done;
+        Ok(HttpResponse {
+            version: self.version,
+            status_code: self.status_code,
+            reason: self.reason,
+            headers: self.headers,
+            body: self.body,
+        })
+    }
 
-        let (headers, body) = match remaining.split_once("\n\n") {
-            Some((h, b)) => {
-                let mut headers = Vec::new();
-                for header in h.split("\n") {
-                    let splitted_header: Vec<&str> = header.splitn(2, ":").collect();
-                    headers.push(Header::new(
-                        String::from(splitted_header[0].trim()),
-                        String::from(splitted_header[1].trim()),
-                    ));
+    // 解析を1段階進める。データが足りずに進めなかった場合はfalse
+    fn step(&mut self) -> Result<bool, Error> {
+        match self.state {
+            ParseState::StatusLine => {
+                let line = match self.take_line()? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                };
+                // ステータス行の前の空行は無視する
+                if !line.trim().is_empty() {
+                    self.parse_status_line(&line)?;
+                    self.state = ParseState::Headers;
+                }
+            }
+            ParseState::Headers => {
+                let line = match self.take_line()? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                };
+                if line.is_empty() {
+                    self.state = self.body_state()?;
+                } else {
+                    self.parse_header_line(&line)?;
                 }
-                (headers, b)
             }
-            None => (Vec::new(), remaining),
+            ParseState::Body(remaining) => {
+                if self.buffer.is_empty() {
+                    return Ok(false);
+                }
+                let n = remaining.min(self.buffer.len());
+                self.body.extend(self.buffer.drain(..n));
+                self.state = if n == remaining {
+                    ParseState::Done
+                } else {
+                    ParseState::Body(remaining - n)
+                };
+            }
+            ParseState::UntilClose => {
+                if self.buffer.is_empty() {
+                    return Ok(false);
+                }
+                self.body.append(&mut self.buffer);
+            }
+            ParseState::ChunkSize => {
+                let line = match self.take_line()? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                };
+                // ;以降はチャンク拡張
+                let size = line.split(';').next().unwrap_or_default().trim();
+                let size = parse_chunk_size(size)
+                    .ok_or_else(|| invalid(&format!("invalid chunk size: {}", line)))?;
+                self.state = if size == 0 {
+                    ParseState::Trailers
+                } else {
+                    ParseState::ChunkData(size)
+                };
+            }
+            ParseState::ChunkData(remaining) => {
+                if self.buffer.is_empty() {
+                    return Ok(false);
+                }
+                let n = remaining.min(self.buffer.len());
+                self.body.extend(self.buffer.drain(..n));
+                self.state = if n == remaining {
+                    ParseState::ChunkEnd
+                } else {
+                    ParseState::ChunkData(remaining - n)
+                };
+            }
+            ParseState::ChunkEnd => match self.take_line()? {
+                Some(line) if line.is_empty() => self.state = ParseState::ChunkSize,
+                Some(_) => return Err(invalid("missing line break after chunk data")),
+                None => return Ok(false),
+            },
+            ParseState::Trailers => {
+                // トレーラーは形式だけ確かめて捨てる
+                match self.take_line()? {
+                    Some(line) if line.is_empty() => self.state = ParseState::Done,
+                    Some(line) => {
+                        parse_field(&line)?;
+                    }
+                    None => return Ok(false),
+                }
+            }
+            ParseState::Done => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // 改行までを取り出す。改行がまだ届いていない場合はNone
+    fn take_line(&mut self) -> Result<Option<String>, Error> {
+        let end = match self.buffer.iter().position(|b| *b == b'\n') {
+            Some(end) => end,
+            None if self.buffer.len() > MAX_LINE_LENGTH => {
+                return Err(invalid("line is too long"));
+            }
+            None => return Ok(None),
         };
+        if end > MAX_LINE_LENGTH {
+            return Err(invalid("line is too long"));
+        }
+        let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
 
-        let statuses: Vec<&str> = status_line.split(' ').collect();
+    // HTTP-version SP status-code SP reason-phrase。理由句は省略されることがある
+    fn parse_status_line(&mut self, line: &str) -> Result<(), Error> {
+        let line = line.trim_start();
+        let (version, rest) = line.split_once(' ').unwrap_or((line, ""));
+        if !version.starts_with("HTTP/") {
+            return Err(invalid(&format!("invalid status line: {}", line)));
+        }
+        let (code, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+        if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid(&format!("invalid status code: {}", line)));
+        }
+        self.version = version.to_string();
+        self.status_code = code.parse().unwrap_or_default();
+        self.reason = reason.trim().to_string();
+        Ok(())
+    }
 
-        Ok(Self {
-            version: statuses[0].to_string(),
-            status_code: statuses[1].parse().unwrap_or(404),
-            reason: statuses[2].to_string(),
-            headers,
-            body: body.to_string(),
+    fn parse_header_line(&mut self, line: &str) -> Result<(), Error> {
+        // 空白で始まる行は前のヘッダの値の続き（obs-fold）
+        if line.starts_with([' ', '\t']) {
+            let header = self
+                .headers
+                .last_mut()
+                .ok_or_else(|| invalid("header continuation without a header"))?;
+            let value = line.trim_matches([' ', '\t']);
+            if !value.is_empty() {
+                if !header.value.is_empty() {
+                    header.value.push(' ');
+                }
+                header.value.push_str(value);
+            }
+            return Ok(());
+        }
+        let header = parse_field(line)?;
+        self.headers.push(header);
+        Ok(())
+    }
+
+    // ヘッダを読み終えた後、本文の長さの決め方を選ぶ
+    fn body_state(&mut self) -> Result<ParseState, Error> {
+        // 1xxは最終的なレスポンスの前に送られる。101以外は読み飛ばす
+        if (100..200).contains(&self.status_code) && self.status_code != 101 {
+            self.headers.clear();
+            return Ok(ParseState::StatusLine);
+        }
+        if (100..200).contains(&self.status_code)
+            || self.status_code == 204
+            || self.status_code == 304
+        {
+            return Ok(ParseState::Done);
+        }
+
+        let encodings = header_values(&self.headers, "Transfer-Encoding");
+        if !encodings.is_empty() {
+            // 最後に適用された符号化がchunkedでない場合は、接続が閉じるまでが本文
+            let last = encodings
+                .iter()
+                .flat_map(|v| v.split(','))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .last()
+                .unwrap_or_default();
+            return Ok(if last.eq_ignore_ascii_case("chunked") {
+                ParseState::ChunkSize
+            } else {
+                ParseState::UntilClose
+            });
+        }
+
+        let mut length: Option<usize> = None;
+        for value in header_values(&self.headers, "Content-Length")
+            .iter()
+            .flat_map(|v| v.split(','))
+        {
+            let value = value.trim();
+            let n = match value.parse::<usize>() {
+                Ok(n) if value.bytes().all(|b| b.is_ascii_digit()) => n,
+                _ => return Err(invalid(&format!("invalid Content-Length: {}", value))),
+            };
+            if length.is_some_and(|length| length != n) {
+                return Err(invalid("conflicting Content-Length headers"));
+            }
+            length = Some(n);
+        }
+        Ok(match length {
+            Some(0) => ParseState::Done,
+            Some(n) => ParseState::Body(n),
+            None => ParseState::UntilClose,
         })
     }
+}
+
+impl Default for HttpResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Network(format!("invalid http response: {}", message))
+}
+
+// 名前: 値 の形式のフィールドを解析する。名前はtokenの文字だけからなる
+fn parse_field(line: &str) -> Result<Header, Error> {
+    let (name, value) = line
+        .split_once(':')
+        .ok_or_else(|| invalid(&format!("header without a colon: {}", line)))?;
+    if name.is_empty() || !name.bytes().all(is_token_char) {
+        return Err(invalid(&format!("invalid header name: {}", name)));
+    }
+    Ok(Header::new(
+        name.to_string(),
+        value.trim_matches([' ', '\t']).to_string(),
+    ))
+}
+
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+// 16進数のチャンクの大きさ。usizeに収まらない場合はNone
+fn parse_chunk_size(s: &str) -> Option<usize> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    usize::from_str_radix(s, 16).ok()
+}
+
+// 名前が一致するヘッダの値。名前は大文字と小文字を区別しない
+fn header_values(headers: &[Header], name: &str) -> Vec<String> {
+    headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.clone())
+        .collect()
+}
+
+impl HttpResponse {
+    // レスポンス全体を受け取って解析する
+    pub fn new(raw_response: String) -> Result<Self, Error> {
+        Self::from_bytes(raw_response.as_bytes())
+    }
+
+    pub fn from_bytes(raw_response: &[u8]) -> Result<Self, Error> {
+        let mut parser = HttpResponseParser::new();
+        parser.feed(raw_response)?;
+        parser.finish()
+    }
 
     pub fn version(&self) -> String {
         self.version.clone()
@@ -78,21 +422,355 @@ impl HttpResponse {
         self.headers.clone()
     }
 
+    // 本文をUTF-8として読む。不正なバイト列は置換文字になる
     pub fn body(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn body_bytes(&self) -> Vec<u8> {
         self.body.clone()
     }
 
+    // 同じ名前のヘッダが複数ある場合は、値を", "でつなげる
     pub fn header_value(&self, name: &str) -> Result<String, String> {
+        let values = header_values(&self.headers, name);
+        if values.is_empty() {
+            return Err(format!("failed to find {} in headers", name));
+        }
+        Ok(values.join(", "))
+    }
+
+    // Set-Cookieのように、つなげると意味が変わるヘッダは個別に取得する
+    pub fn header_values(&self, name: &str) -> Vec<String> {
+        header_values(&self.headers, name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// 送信するHTTPリクエスト。urlは絶対URL
+pub struct HttpRequest {
+    method: String,
+    url: String,
+    headers: Vec<Header>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    // メソッドはtokenの文字だけからなる
+    pub fn new(method: &str, url: String) -> Result<Self, Error> {
+        if method.is_empty() || !method.bytes().all(is_token_char) {
+            return Err(Error::UnexpectedInput(format!(
+                "invalid http method: {}",
+                method
+            )));
+        }
+        Ok(Self {
+            method: method.to_string(),
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        })
+    }
+
+    pub fn get(url: String) -> Self {
+        Self {
+            method: "GET".to_string(),
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    // application/x-www-form-urlencodedの本文を持つPOST
+    pub fn urlencoded(url: String, fields: &[FormField]) -> Self {
+        let mut request = Self::get(url);
+        request.method = "POST".to_string();
+        request.set_body(
+            urlencode(fields).into_bytes(),
+            "application/x-www-form-urlencoded",
+        );
+        request
+    }
+
+    // multipart/form-dataの本文を持つPOST。境界は本文に現れない文字列を選ぶ
+    pub fn multipart(url: String, fields: &[FormField]) -> Self {
+        let boundary = choose_boundary(fields);
+        let mut request = Self::get(url);
+        request.method = "POST".to_string();
+        request.set_body(
+            multipart_body(fields, &boundary),
+            &format!("multipart/form-data; boundary={}", boundary),
+        );
+        request
+    }
+
+    // <form>の送信。GETはactionのクエリを項目で置き換え、POSTはenctypeに従って本文にする
+    pub fn form(
+        action: &str,
+        method: &str,
+        enctype: &str,
+        fields: &[FormField],
+    ) -> Result<Self, Error> {
+        if !method.eq_ignore_ascii_case("post") {
+            let (url, fragment) = match action.split_once('#') {
+                Some((url, fragment)) => (url, format!("#{}", fragment)),
+                None => (action, String::new()),
+            };
+            let url = url.split('?').next().unwrap_or_default();
+            return Ok(Self::get(format!(
+                "{}?{}{}",
+                url,
+                urlencode(fields),
+                fragment
+            )));
+        }
+        let action = action.to_string();
+        match enctype.to_ascii_lowercase().as_str() {
+            "multipart/form-data" => Ok(Self::multipart(action, fields)),
+            "text/plain" => {
+                let mut body = String::new();
+                for field in fields {
+                    body.push_str(&field.name);
+                    body.push('=');
+                    body.push_str(&field.as_text());
+                    body.push_str("\r\n");
+                }
+                let mut request = Self::new("POST", action)?;
+                request.set_body(body.into_bytes(), "text/plain");
+                Ok(request)
+            }
+            _ => Ok(Self::urlencoded(action, fields)),
+        }
+    }
+
+    pub fn method(&self) -> String {
+        self.method.clone()
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn headers(&self) -> Vec<Header> {
+        self.headers.clone()
+    }
+
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+
+    pub fn header_value(&self, name: &str) -> Option<String> {
+        let values = header_values(&self.headers, name);
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.join(", "))
+        }
+    }
+
+    // 同じ名前のヘッダがあっても追加する
+    pub fn append_header(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        if name.is_empty() || !name.bytes().all(is_token_char) {
+            return Err(Error::UnexpectedInput(format!(
+                "invalid header name: {}",
+                name
+            )));
+        }
+        // 改行を含む値はヘッダを偽装できてしまう
+        if value.bytes().any(|b| b == b'\r' || b == b'\n' || b == 0) {
+            return Err(Error::UnexpectedInput(format!(
+                "invalid header value for {}",
+                name
+            )));
+        }
+        self.headers.push(Header::new(
+            name.to_string(),
+            value.trim_matches([' ', '\t']).to_string(),
+        ));
+        Ok(())
+    }
+
+    // 同じ名前のヘッダを置き換える
+    pub fn set_header(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let headers = core::mem::take(&mut self.headers);
+        self.headers = headers
+            .into_iter()
+            .filter(|h| !h.name.eq_ignore_ascii_case(name))
+            .collect();
+        self.append_header(name, value)
+    }
+
+    // Content-Typeがまだ設定されていなければcontent_typeを設定する
+    pub fn set_body(&mut self, body: Vec<u8>, content_type: &str) {
+        if self.header_value("Content-Type").is_none() {
+            self.headers.push(Header::new(
+                "Content-Type".to_string(),
+                content_type.to_string(),
+            ));
+        }
+        self.body = body;
+    }
+
+    // 送信するバイト列。改行はCRLFで、HostとContent-Lengthはここで決める
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let url = self.url.split('#').next().unwrap_or_default();
+        let url = Url::new(url.to_string())
+            .parse()
+            .map_err(Error::UnexpectedInput)?;
+        let mut target = format!("/{}", url.path());
+        if !url.searchpart().is_empty() {
+            target.push('?');
+            target.push_str(&url.searchpart());
+        }
+        let host = if url.port() == "80" {
+            url.host()
+        } else {
+            format!("{}:{}", url.host(), url.port())
+        };
+
+        let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", self.method, target, host);
         for h in &self.headers {
-            if h.name == name {
-                return Ok(h.value.clone());
+            if h.name.eq_ignore_ascii_case("Host") || h.name.eq_ignore_ascii_case("Content-Length")
+            {
+                continue;
             }
+            head.push_str(&format!("{}: {}\r\n", h.name, h.value));
+        }
+        if !self.body.is_empty() || ["POST", "PUT", "PATCH"].contains(&self.method.as_str()) {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
         }
+        head.push_str("\r\n");
 
-        Err(format!("failed to find {} in headers", name))
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        Ok(bytes)
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+// フォームの1つの項目。ファイルの場合はファイル名とContent-Typeを持つ
+pub struct FormField {
+    name: String,
+    value: Vec<u8>,
+    filename: Option<String>,
+    content_type: Option<String>,
+}
+
+impl FormField {
+    pub fn text(name: String, value: String) -> Self {
+        Self {
+            name,
+            value: value.into_bytes(),
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    pub fn file(name: String, filename: String, content_type: String, value: Vec<u8>) -> Self {
+        Self {
+            name,
+            value,
+            filename: Some(filename),
+            content_type: Some(content_type),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn value(&self) -> Vec<u8> {
+        self.value.clone()
+    }
+
+    pub fn filename(&self) -> Option<String> {
+        self.filename.clone()
+    }
+
+    // multipart以外ではファイルはファイル名として送る
+    fn as_text(&self) -> String {
+        match &self.filename {
+            Some(filename) => filename.clone(),
+            None => String::from_utf8_lossy(&self.value).into_owned(),
+        }
+    }
+}
+
+// application/x-www-form-urlencodedの形式にする。空白は+になる
+pub fn urlencode(fields: &[FormField]) -> String {
+    let mut encoded = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            encoded.push('&');
+        }
+        encode_component(&mut encoded, &field.name);
+        encoded.push('=');
+        encode_component(&mut encoded, &field.as_text());
+    }
+    encoded
+}
+
+fn encode_component(encoded: &mut String, s: &str) {
+    for b in s.bytes() {
+        match b {
+            b' ' => encoded.push('+'),
+            b if b.is_ascii_alphanumeric() || b"*-._".contains(&b) => encoded.push(b as char),
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+// 境界の候補に番号をつけ、どの項目にも現れないものを使う
+fn choose_boundary(fields: &[FormField]) -> String {
+    let mut n = 0u32;
+    loop {
+        let boundary = format!("----SabaFormBoundary{:08x}", n);
+        let appears = fields.iter().any(|f| {
+            [f.name.as_bytes(), &f.value].iter().any(|bytes| {
+                bytes
+                    .windows(boundary.len())
+                    .any(|w| w == boundary.as_bytes())
+            })
+        });
+        if !appears {
+            return boundary;
+        }
+        n += 1;
+    }
+}
+
+// nameとfilenameの中の"と改行はパーセントエンコードする
+fn escape_disposition(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn multipart_body(fields: &[FormField], boundary: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    for field in fields {
+        let mut head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            boundary,
+            escape_disposition(&field.name)
+        );
+        if let Some(filename) = &field.filename {
+            head.push_str(&format!("; filename=\"{}\"", escape_disposition(filename)));
+            let content_type = field
+                .content_type
+                .clone()
+                .unwrap_or("application/octet-stream".to_string());
+            head.push_str(&format!("\r\nContent-Type: {}", content_type));
+        }
+        head.push_str("\r\n\r\n");
+        body.extend_from_slice(head.as_bytes());
+        body.extend_from_slice(&field.value);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,7 +796,10 @@ mod tests {
 
     #[test]
     fn test_two_headers_with_white_space() {
-        let raw = "HTTP/1.1 200 OK\nDate:xx xx xx\nContent-Length: 42\n\n".to_string();
+        let raw = format!(
+            "HTTP/1.1 200 OK\nDate:xx xx xx\nContent-Length: 42\n\n{}",
+            "x".repeat(42)
+        );
         let res = HttpResponse::new(raw).expect("failed to parse http response");
         assert_eq!(res.version(), "HTTP/1.1");
         assert_eq!(res.status_code(), 200);
@@ -146,4 +827,225 @@ mod tests {
         // 改行文字がない文字列は不正
         assert!(HttpResponse::new(raw).is_err());
     }
+
+    #[test]
+    fn test_crlf_and_content_length() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello, extra".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse http response");
+        assert_eq!(res.header_value("Content-Length"), Ok("5".to_string()));
+        // Content-Lengthより後のバイト列は本文に含めない
+        assert_eq!(res.body(), "hello".to_string());
+    }
+
+    #[test]
+    fn test_incremental_chunked() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, Chunked\r\n\r\n5;name=value\r\nhello\r\n7\r\n, world\r\n0\r\nExpires: never\r\n\r\n";
+        let mut parser = HttpResponseParser::new();
+        for (i, b) in raw.iter().enumerate() {
+            let complete = parser.feed(&[*b]).expect("failed to parse http response");
+            assert_eq!(i == raw.len() - 1, complete);
+        }
+        let res = parser.finish().expect("failed to parse http response");
+        assert_eq!(res.body(), "hello, world".to_string());
+        // トレーラーはヘッダに含めない
+        assert!(res.header_value("Expires").is_err());
+    }
+
+    #[test]
+    fn test_headers() {
+        let raw = "HTTP/1.1 200 OK\nSet-Cookie: a=1\ncontent-type: text/html;\n charset=utf-8\nSET-COOKIE: b=2\nX-Empty:\n\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse http response");
+        // 名前は大文字と小文字を区別せず、複数ある場合はつなげる
+        assert_eq!(res.header_value("Set-Cookie"), Ok("a=1, b=2".to_string()));
+        assert_eq!(
+            res.header_values("set-cookie"),
+            Vec::from(["a=1".to_string(), "b=2".to_string()])
+        );
+        // 折り返された値は空白1つでつなげる
+        assert_eq!(
+            res.header_value("Content-Type"),
+            Ok("text/html; charset=utf-8".to_string())
+        );
+        assert_eq!(res.header_value("x-empty"), Ok("".to_string()));
+    }
+
+    #[test]
+    fn test_binary_body() {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[0x89, 0x50, 0x00, 0xff]);
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse http response");
+        assert_eq!(res.body_bytes(), Vec::from([0x89, 0x50, 0x00, 0xff]));
+    }
+
+    #[test]
+    fn test_status_without_body() {
+        // 100 Continueは読み飛ばす
+        let raw =
+            "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\nX-A: 1\r\n\r\n".to_string();
+        let mut parser = HttpResponseParser::new();
+        assert_eq!(Ok(true), parser.feed(raw.as_bytes()));
+        let res = parser.finish().expect("failed to parse http response");
+        assert_eq!(res.status_code(), 204);
+        assert_eq!(res.reason(), "No Content");
+        assert_eq!(
+            res.headers(),
+            Vec::from([Header::new("X-A".to_string(), "1".to_string())])
+        );
+
+        let res = HttpResponse::new("HTTP/1.1 404\n\n".to_string()).expect("failed to parse");
+        assert_eq!(res.status_code(), 404);
+        assert_eq!(res.reason(), "");
+    }
+
+    #[test]
+    fn test_errors() {
+        for raw in [
+            "",
+            "\r\n",
+            "HTTP/1.1\n\n",
+            "HTTP/1.1 2000 OK\n\n",
+            "FTP/1.1 200 OK\n\n",
+            "HTTP/1.1 200 OK\nno colon\n\n",
+            "HTTP/1.1 200 OK\nBad Name: x\n\n",
+            "HTTP/1.1 200 OK\n folded\n\n",
+            "HTTP/1.1 200 OK\nContent-Length: 1, 2\n\nab",
+            "HTTP/1.1 200 OK\nContent-Length: +1\n\na",
+            "HTTP/1.1 200 OK\nContent-Length: 10\n\nshort",
+            "HTTP/1.1 200 OK\nTransfer-Encoding: chunked\n\nzz\n",
+            "HTTP/1.1 200 OK\nTransfer-Encoding: chunked\n\nffffffffffffffffffff\n",
+            "HTTP/1.1 200 OK\nTransfer-Encoding: chunked\n\n2\nabc\n0\n\n",
+            "HTTP/1.1 200 OK\nTransfer-Encoding: chunked\n\n2\nab\n",
+        ] {
+            assert!(
+                HttpResponse::new(raw.to_string()).is_err(),
+                "{:?} should be invalid",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_input_does_not_panic() {
+        let sample = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nA: b\r\n c\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        let bytes = b"\r\n :;0f\x00\xff";
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        // サンプルのバイトを書き換えたものを、任意の位置で分割して与えても一度に与えた場合と同じ結果になる
+        for _ in 0..2000 {
+            let mut input = sample.to_vec();
+            for _ in 0..(next() % 4 + 1) {
+                let i = (next() as usize) % input.len();
+                input[i] = bytes[(next() as usize) % bytes.len()];
+            }
+            let split = (next() as usize) % (input.len() + 1);
+            let whole = HttpResponse::from_bytes(&input);
+            let mut parser = HttpResponseParser::new();
+            let parts = parser
+                .feed(&input[..split])
+                .and_then(|_| parser.feed(&input[split..]))
+                .and_then(|_| parser.finish());
+            assert_eq!(whole, parts);
+        }
+    }
+    #[test]
+    fn test_request_to_bytes() {
+        let request = HttpRequest::get("http://example.com/a/b.html?q=1#top".to_string());
+        assert_eq!(
+            b"GET /a/b.html?q=1 HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec(),
+            request.to_bytes().expect("failed to serialize")
+        );
+
+        let mut request =
+            HttpRequest::new("PUT", "http://localhost:8000".to_string()).expect("valid method");
+        request.append_header("Accept", " text/html ").unwrap();
+        request.append_header("Host", "evil.test").unwrap();
+        request.set_body(b"{}".to_vec(), "application/json");
+        request.set_body(b"{\"a\":1}".to_vec(), "text/plain");
+        assert_eq!(
+            "PUT / HTTP/1.1\r\nHost: localhost:8000\r\nAccept: text/html\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\r\n{\"a\":1}",
+            String::from_utf8(request.to_bytes().unwrap()).unwrap()
+        );
+
+        assert!(HttpRequest::new("GET /", "http://example.com".to_string()).is_err());
+        assert!(request.append_header("X-A", "a\r\nX-B: b").is_err());
+        assert!(request.append_header("X A", "a").is_err());
+        request.set_header("accept", "*/*").unwrap();
+        assert_eq!(Some("*/*".to_string()), request.header_value("Accept"));
+        assert!(HttpRequest::get("file:///etc/passwd".to_string())
+            .to_bytes()
+            .is_err());
+    }
+
+    #[test]
+    fn test_form_urlencoded() {
+        let fields = [
+            FormField::text("q".to_string(), "a b&c=d".to_string()),
+            FormField::text("名前".to_string(), "*-._~".to_string()),
+            FormField::file(
+                "f".to_string(),
+                "x.txt".to_string(),
+                "text/plain".to_string(),
+                b"data".to_vec(),
+            ),
+        ];
+        assert_eq!(
+            "q=a+b%26c%3Dd&%E5%90%8D%E5%89%8D=*-._%7E&f=x.txt",
+            urlencode(&fields)
+        );
+
+        let request = HttpRequest::form("http://example.com/s?old=1#r", "get", "", &fields[..1])
+            .expect("failed to create request");
+        assert_eq!("GET", request.method());
+        assert_eq!("http://example.com/s?q=a+b%26c%3Dd#r", request.url());
+
+        let request = HttpRequest::form("http://example.com/s", "POST", "", &fields[..1])
+            .expect("failed to create request");
+        assert_eq!(
+            "POST /s HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 13\r\n\r\nq=a+b%26c%3Dd",
+            String::from_utf8(request.to_bytes().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_form_multipart() {
+        let fields = [
+            FormField::text(
+                "say\"hi\"".to_string(),
+                "----SabaFormBoundary00000000".to_string(),
+            ),
+            FormField::file(
+                "upload".to_string(),
+                "a.bin".to_string(),
+                "application/octet-stream".to_string(),
+                Vec::from([0u8, 0xff]),
+            ),
+        ];
+        let request = HttpRequest::form(
+            "http://example.com/upload",
+            "post",
+            "multipart/form-data",
+            &fields,
+        )
+        .expect("failed to create request");
+        assert_eq!(
+            Some("multipart/form-data; boundary=----SabaFormBoundary00000001".to_string()),
+            request.header_value("Content-Type")
+        );
+        let mut expected = b"------SabaFormBoundary00000001\r\nContent-Disposition: form-data; name=\"say%22hi%22\"\r\n\r\n----SabaFormBoundary00000000\r\n------SabaFormBoundary00000001\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"a.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n".to_vec();
+        expected.extend_from_slice(&[0, 0xff]);
+        expected.extend_from_slice(b"\r\n------SabaFormBoundary00000001--\r\n");
+        assert_eq!(expected, request.body());
+
+        let request = HttpRequest::form("http://example.com/", "POST", "text/plain", &fields[..1])
+            .expect("failed to create request");
+        assert_eq!(
+            b"say\"hi\"=----SabaFormBoundary00000000\r\n".to_vec(),
+            request.body()
+        );
+    }
 }
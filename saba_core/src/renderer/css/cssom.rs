@@ -2,6 +2,7 @@ use core::iter::Peekable;
 
 use alloc::{
     string::{String, ToString},
+    vec,
     vec::Vec,
 };
 
@@ -25,6 +26,11 @@ impl CssParser {
         sheet
     }
 
+    // style属性のような、{}で囲まれていない宣言の一覧を解釈する
+    pub fn parse_declaration_list(&mut self) -> Vec<Declaration> {
+        self.consume_list_of_declarations()
+    }
+
     fn consume_list_of_rules(&mut self) -> Vec<QualifiedRule> {
         let mut rules = Vec::new();
 
@@ -158,9 +164,23 @@ impl CssParser {
             None => return None,
         }
 
+        // 値が省略された場合は宣言を無視する
+        match self.t.peek() {
+            None | Some(CssToken::SemiColon) | Some(CssToken::CloseCurly) => return None,
+            _ => {}
+        }
+
         // 値にコンポーネント値を設定
         declaration.set_value(self.consume_component_value());
 
+        // 1fr 2frのように複数のコンポーネント値を持つ場合、宣言の終わりまで値を追加する
+        while let Some(token) = self.t.peek() {
+            match token {
+                CssToken::SemiColon | CssToken::CloseCurly => break,
+                _ => declaration.add_value(self.consume_component_value()),
+            }
+        }
+
         Some(declaration)
     }
 
@@ -240,7 +260,10 @@ pub enum Selector {
 // プロパティ、値のセットを表現
 pub struct Declaration {
     pub property: String,
+    // 最初のコンポーネント値
     pub value: ComponentValue,
+    // 宣言に含まれるすべてのコンポーネント値
+    pub values: Vec<ComponentValue>,
 }
 
 impl Declaration {
@@ -248,6 +271,7 @@ impl Declaration {
         Self {
             property: String::new(),
             value: ComponentValue::Ident(String::new()),
+            values: Vec::new(),
         }
     }
 
@@ -256,7 +280,12 @@ impl Declaration {
     }
 
     pub fn set_value(&mut self, value: ComponentValue) {
-        self.value = value;
+        self.value = value.clone();
+        self.values = vec![value];
+    }
+
+    pub fn add_value(&mut self, value: ComponentValue) {
+        self.values.push(value);
     }
 }
 
@@ -267,8 +296,6 @@ pub type ComponentValue = CssToken;
 
 #[cfg(test)]
 mod tests {
-    use alloc::vec;
-
     use super::*;
 
     #[test]
@@ -381,4 +408,38 @@ mod tests {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_multiple_values() {
+        let style = "div { grid-template-columns: 100px 1fr; }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let mut rule = QualifiedRule::new();
+        rule.set_selector(Selector::TypeSelector("div".to_string()));
+        let mut declaration = Declaration::new();
+        declaration.set_property("grid-template-columns".to_string());
+        declaration.set_value(ComponentValue::Dimension(100.0, "px".to_string()));
+        declaration.add_value(ComponentValue::Dimension(1.0, "fr".to_string()));
+        rule.set_declarations(vec![declaration]);
+
+        assert_eq!(cssom.rules.len(), 1);
+        assert_eq!(rule, cssom.rules[0]);
+    }
+
+    #[test]
+    fn test_declaration_list() {
+        let style = "color: red; width:; display: none".to_string();
+        let t = CssTokenizer::new(style);
+        let declarations = CssParser::new(t).parse_declaration_list();
+
+        let mut declaration1 = Declaration::new();
+        declaration1.set_property("color".to_string());
+        declaration1.set_value(ComponentValue::Ident("red".to_string()));
+        let mut declaration2 = Declaration::new();
+        declaration2.set_property("display".to_string());
+        declaration2.set_value(ComponentValue::Ident("none".to_string()));
+
+        assert_eq!(vec![declaration1, declaration2], declarations);
+    }
 }
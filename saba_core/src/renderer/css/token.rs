@@ -5,6 +5,10 @@ pub enum CssToken {
     HashToken(String),
     Delim(char),
     Number(f64),
+    // 10px, 1frのように単位がついた数値
+    Dimension(f64, String),
+    // 50%のようなパーセント値
+    Percentage(f64),
     Colon,
     SemiColon,
     OpenParenthesis,
@@ -39,6 +43,7 @@ impl Iterator for CssTokenizer {
                 ')' => CssToken::CloseParenthesis,
                 ',' => CssToken::Delim(','),
                 '.' => CssToken::Delim('.'),
+                '/' => CssToken::Delim('/'),
                 ':' => CssToken::Colon,
                 ';' => CssToken::SemiColon,
                 '{' => CssToken::OpenCurly,
@@ -52,7 +57,7 @@ impl Iterator for CssTokenizer {
                     CssToken::StringToken(value)
                 }
                 '0'..='9' => {
-                    let t = CssToken::Number(self.consume_numeric_token());
+                    let t = self.consume_numeric_or_dimension_token();
                     self.pos -= 1;
                     t
                 }
@@ -62,8 +67,22 @@ impl Iterator for CssTokenizer {
                     self.pos -= 1;
                     CssToken::HashToken(value)
                 }
+                '-' if self.pos + 1 < self.input.len()
+                    && self.input[self.pos + 1].is_ascii_digit() =>
+                {
+                    // -10pxのような負の数
+                    self.pos += 1;
+                    let t = match self.consume_numeric_or_dimension_token() {
+                        CssToken::Number(n) => CssToken::Number(-n),
+                        CssToken::Dimension(n, unit) => CssToken::Dimension(-n, unit),
+                        CssToken::Percentage(n) => CssToken::Percentage(-n),
+                        t => t,
+                    };
+                    self.pos -= 1;
+                    t
+                }
                 '-' => {
-                    // 今回は負の数は取り扱わないため、ハイフンは識別子の一つとして扱う
+                    // 数字が続かないハイフンは識別子の一つとして扱う
                     let t = CssToken::Ident(self.consume_ident_token());
                     self.pos -= 1;
 
@@ -158,12 +177,40 @@ impl CssTokenizer {
         num
     }
 
+    // 数値の直後に単位や%が続く場合はDimensionまたはPercentageとして扱う
+    fn consume_numeric_or_dimension_token(&mut self) -> CssToken {
+        let num = self.consume_numeric_token();
+
+        if self.pos >= self.input.len() {
+            return CssToken::Number(num);
+        }
+
+        match self.input[self.pos] {
+            '%' => {
+                self.pos += 1;
+                CssToken::Percentage(num)
+            }
+            'a'..='z' | 'A'..='Z' => {
+                let mut unit = String::new();
+                while self.pos < self.input.len() && self.input[self.pos].is_ascii_alphabetic() {
+                    unit.push(self.input[self.pos]);
+                    self.pos += 1;
+                }
+                CssToken::Dimension(num, unit)
+            }
+            _ => CssToken::Number(num),
+        }
+    }
+
     fn consume_ident_token(&mut self) -> String {
         let mut s = String::new();
         s.push(self.input[self.pos]);
 
         loop {
             self.pos += 1;
+            if self.pos >= self.input.len() {
+                break;
+            }
             let c = self.input[self.pos];
             match c {
                 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => {
@@ -278,4 +325,31 @@ mod tests {
         }
         assert!(t.next().is_none());
     }
+
+    #[test]
+    fn test_dimension_and_percentage() {
+        let style = "p { width: 50%; margin: -8px; gap: 0; }".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("p".to_string()),
+            CssToken::OpenCurly,
+            CssToken::Ident("width".to_string()),
+            CssToken::Colon,
+            CssToken::Percentage(50.0),
+            CssToken::SemiColon,
+            CssToken::Ident("margin".to_string()),
+            CssToken::Colon,
+            CssToken::Dimension(-8.0, "px".to_string()),
+            CssToken::SemiColon,
+            CssToken::Ident("gap".to_string()),
+            CssToken::Colon,
+            CssToken::Number(0.0),
+            CssToken::SemiColon,
+            CssToken::CloseCurly,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
 }
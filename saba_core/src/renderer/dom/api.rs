@@ -1,12 +1,18 @@
 use core::cell::RefCell;
 
 use alloc::{
-    rc::Rc,
+    format,
+    rc::{Rc, Weak},
     string::{String, ToString},
     vec::Vec,
 };
 
 use crate::renderer::dom::node::{Element, ElementKind, Node, NodeKind};
+use crate::renderer::dom::selector::SelectorList;
+use crate::renderer::html::{
+    parser::{HtmlParser, VOID_ELEMENTS},
+    token::HtmlTokenizer,
+};
 
 pub fn get_target_element_node(
     node: Option<Rc<RefCell<Node>>>,
@@ -20,7 +26,7 @@ pub fn get_target_element_node(
                 return Some(n.clone());
             }
             // 子どものノードに対して実行
-            let result1 = get_target_element_node(n.borrow().first_child(), element_kind);
+            let result1 = get_target_element_node(n.borrow().first_child(), element_kind.clone());
             // さらに兄弟のノードに対して実行
             let result2 = get_target_element_node(n.borrow().next_sibling(), element_kind);
             if result1.is_none() && result2.is_none() {
@@ -76,18 +82,330 @@ pub fn get_element_by_id(
     }
 }
 
-pub fn get_js_content(root: Rc<RefCell<Node>>) -> String {
-    let js_node = match get_target_element_node(Some(root), ElementKind::Script) {
-        Some(node) => node,
-        None => return "".to_string(),
+// ノードのスタイルとレイアウトを再計算する必要があることを記録する
+pub fn mark_dirty(node: &Rc<RefCell<Node>>) {
+    node.borrow_mut().set_dirty(true);
+    let parent = node.borrow().parent().upgrade();
+    if let Some(p) = parent {
+        mark_child_dirty(&p);
+    }
+}
+
+// 子孫が変更されたことを祖先に伝える
+pub fn mark_child_dirty(node: &Rc<RefCell<Node>>) {
+    let mut current = Some(node.clone());
+    while let Some(n) = current {
+        // すでに記録されている場合、祖先にも記録されている
+        if n.borrow().is_child_dirty() {
+            return;
+        }
+        n.borrow_mut().set_child_dirty(true);
+        current = n.borrow().parent().upgrade();
+    }
+}
+
+// 再計算が必要なことを表すフラグを、変更されたノードをたどって消す
+pub fn clear_dirty(node: &Rc<RefCell<Node>>) {
+    if node.borrow().is_child_dirty() {
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            clear_dirty(&c);
+            child = c.borrow().next_sibling();
+        }
+    }
+    node.borrow_mut().set_dirty(false);
+    node.borrow_mut().set_child_dirty(false);
+}
+
+pub fn set_attribute(node: &Rc<RefCell<Node>>, name: &str, value: &str) {
+    if let NodeKind::Element(ref mut e) = node.borrow_mut().kind {
+        e.set_attribute(name, value);
+    }
+    mark_dirty(node);
+}
+
+// 子孫のテキストノードの文字列をつなげたもの
+pub fn get_text_content(node: &Rc<RefCell<Node>>) -> String {
+    if let NodeKind::Text(ref text) = node.borrow().kind {
+        return text.clone();
+    }
+
+    let mut text = String::new();
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        text.push_str(&get_text_content(&c));
+        child = c.borrow().next_sibling();
+    }
+    text
+}
+
+// 子ノードを1つのテキストノードに置き換える。テキストノードの場合は文字列を置き換える
+pub fn set_text_content(node: &Rc<RefCell<Node>>, text: &str) {
+    let is_text = matches!(node.borrow().kind, NodeKind::Text(_));
+    if is_text {
+        node.borrow_mut().kind = NodeKind::Text(text.to_string());
+    } else {
+        let text_node = Rc::new(RefCell::new(Node::new(NodeKind::Text(text.to_string()))));
+        text_node.borrow_mut().set_parent(Rc::downgrade(node));
+        node.borrow_mut().set_first_child(Some(text_node.clone()));
+        node.borrow_mut().set_last_child(Rc::downgrade(&text_node));
+    }
+    mark_dirty(node);
+}
+
+// 子ノードの中からnodeの直前の兄弟ノードを探す
+fn find_previous_sibling(
+    parent: &Rc<RefCell<Node>>,
+    node: &Rc<RefCell<Node>>,
+) -> Option<Rc<RefCell<Node>>> {
+    let mut child = parent.borrow().first_child();
+    while let Some(c) = child {
+        let next = c.borrow().next_sibling();
+        if let Some(ref n) = next {
+            if Rc::ptr_eq(n, node) {
+                return Some(c);
+            }
+        }
+        child = next;
+    }
+    None
+}
+
+fn find_last_child(parent: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    let mut last = parent.borrow().first_child();
+    while let Some(l) = last.clone() {
+        match l.borrow().next_sibling() {
+            Some(next) => last = Some(next),
+            None => break,
+        }
+    }
+    last
+}
+
+// referenceの直前にchildを挿入する。referenceがNoneの場合は最後の子ノードとして追加する
+pub fn insert_before(
+    parent: &Rc<RefCell<Node>>,
+    child: &Rc<RefCell<Node>>,
+    reference: Option<&Rc<RefCell<Node>>>,
+) {
+    // すでにツリーの中にあるノードは移動する
+    let old_parent = child.borrow().parent().upgrade();
+    if let Some(p) = old_parent {
+        remove_child(&p, child);
+    }
+
+    let previous = match reference {
+        Some(r) => find_previous_sibling(parent, r),
+        None => find_last_child(parent),
     };
-    let text_node = match js_node.borrow().first_child() {
-        Some(node) => node,
-        None => return "".to_string(),
+    match &previous {
+        Some(p) => p.borrow_mut().set_next_sibling(Some(child.clone())),
+        None => parent.borrow_mut().set_first_child(Some(child.clone())),
+    }
+    child.borrow_mut().set_next_sibling(reference.cloned());
+    if let Some(r) = reference {
+        r.borrow_mut().set_previous_sibling(Rc::downgrade(child));
+    } else {
+        parent.borrow_mut().set_last_child(Rc::downgrade(child));
+    }
+    child.borrow_mut().set_previous_sibling(match &previous {
+        Some(p) => Rc::downgrade(p),
+        None => Weak::new(),
+    });
+    child.borrow_mut().set_parent(Rc::downgrade(parent));
+
+    mark_dirty(child);
+}
+
+pub fn append_child(parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>) {
+    insert_before(parent, child, None);
+}
+
+pub fn remove_child(parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>) {
+    let next = child.borrow().next_sibling();
+    match find_previous_sibling(parent, child) {
+        Some(p) => p.borrow_mut().set_next_sibling(next.clone()),
+        None => {
+            let is_first_child = match parent.borrow().first_child() {
+                Some(f) => Rc::ptr_eq(&f, child),
+                None => false,
+            };
+            // 子ノードではない場合は何もしない
+            if !is_first_child {
+                return;
+            }
+            parent.borrow_mut().set_first_child(next.clone());
+        }
+    }
+
+    let previous = find_previous_sibling(parent, child);
+    match &next {
+        Some(n) => n.borrow_mut().set_previous_sibling(match &previous {
+            Some(p) => Rc::downgrade(p),
+            None => Weak::new(),
+        }),
+        None => {
+            let last = find_last_child(parent);
+            parent.borrow_mut().set_last_child(match &last {
+                Some(l) => Rc::downgrade(l),
+                None => Weak::new(),
+            });
+        }
+    }
+
+    child.borrow_mut().set_parent(Weak::new());
+    child.borrow_mut().set_next_sibling(None);
+    child.borrow_mut().set_previous_sibling(Weak::new());
+
+    mark_child_dirty(parent);
+}
+
+// 子ノードを順に返す
+pub fn child_nodes(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut children = Vec::new();
+    let mut child = node.borrow().first_child();
+    while let Some(c) = child {
+        child = c.borrow().next_sibling();
+        children.push(c);
+    }
+    children
+}
+
+// otherがnode自身かその子孫か
+pub fn contains(node: &Rc<RefCell<Node>>, other: &Rc<RefCell<Node>>) -> bool {
+    let mut current = Some(other.clone());
+    while let Some(c) = current {
+        if Rc::ptr_eq(&c, node) {
+            return true;
+        }
+        current = c.borrow().parent().upgrade();
+    }
+    false
+}
+
+// 子孫の要素のうち、セレクタに一致するものを文書の順に返す
+pub fn query_selector_all(
+    node: &Rc<RefCell<Node>>,
+    selectors: &SelectorList,
+) -> Vec<Rc<RefCell<Node>>> {
+    let mut result = Vec::new();
+    for child in child_nodes(node) {
+        if selectors.matches(&child) {
+            result.push(child.clone());
+        }
+        result.extend(query_selector_all(&child, selectors));
+    }
+    result
+}
+
+// 子ノードをHTMLの文字列にする
+pub fn get_inner_html(node: &Rc<RefCell<Node>>) -> String {
+    let mut html = String::new();
+    for child in child_nodes(node) {
+        serialize(&child, &mut html);
+    }
+    html
+}
+
+fn serialize(node: &Rc<RefCell<Node>>, html: &mut String) {
+    match node.borrow().kind() {
+        NodeKind::Document => html.push_str(&get_inner_html(node)),
+        NodeKind::Element(e) => {
+            html.push_str(&format!("<{}", e.kind()));
+            for attr in e.attributes() {
+                html.push_str(&format!(
+                    " {}=\"{}\"",
+                    attr.name(),
+                    escape(&attr.value(), true)
+                ));
+            }
+            html.push('>');
+            // 空要素は終了タグを出力しない
+            if VOID_ELEMENTS.contains(&e.kind().to_string().as_str()) {
+                return;
+            }
+            html.push_str(&get_inner_html(node));
+            html.push_str(&format!("</{}>", e.kind()));
+        }
+        NodeKind::Text(text) => {
+            // styleとscriptの中身はそのまま出力する
+            let parent = node.borrow().parent().upgrade();
+            let is_raw_text = matches!(
+                parent.and_then(|p| p.borrow().element_kind()),
+                Some(ElementKind::Style | ElementKind::Script)
+            );
+            if is_raw_text {
+                html.push_str(&text);
+            } else {
+                html.push_str(&escape(&text, false));
+            }
+        }
+    }
+}
+
+fn escape(text: &str, is_attribute: bool) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' if !is_attribute => escaped.push_str("&lt;"),
+            '>' if !is_attribute => escaped.push_str("&gt;"),
+            '"' if is_attribute => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// 子ノードを、HTMLの文字列を解釈した結果に置き換える
+pub fn set_inner_html(node: &Rc<RefCell<Node>>, html: &str) {
+    for child in child_nodes(node) {
+        remove_child(node, &child);
+    }
+
+    // bodyの中身として解釈する
+    let html = format!("<html><head></head><body>{}</body></html>", html);
+    let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+    let document = window.borrow().document();
+    let body = match get_target_element_node(Some(document), ElementKind::Body) {
+        Some(body) => body,
+        None => return,
     };
-    let content = match &text_node.borrow().kind() {
-        NodeKind::Text(ref s) => s.clone(),
-        _ => "".to_string(),
+    for child in child_nodes(&body) {
+        append_child(node, &child);
+    }
+}
+
+// style属性の宣言を、プロパティ名と値の組にする
+pub fn get_style_declarations(node: &Rc<RefCell<Node>>) -> Vec<(String, String)> {
+    let style = match node.borrow().get_element() {
+        Some(e) => e.get_attribute("style").unwrap_or_default(),
+        None => return Vec::new(),
     };
-    content
+    style
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+        .collect()
+}
+
+// style属性のプロパティを変更する。値が空文字列の場合は削除する
+pub fn set_style_property(node: &Rc<RefCell<Node>>, name: &str, value: &str) {
+    let mut declarations = get_style_declarations(node);
+    let value = value.trim();
+    match declarations.iter().position(|(n, _)| n == name) {
+        Some(i) if value.is_empty() => {
+            declarations.remove(i);
+        }
+        Some(i) => declarations[i].1 = value.to_string(),
+        None if value.is_empty() => {}
+        None => declarations.push((name.to_string(), value.to_string())),
+    }
+    let style = declarations
+        .iter()
+        .map(|(name, value)| format!("{}: {};", name, value))
+        .collect::<Vec<String>>()
+        .join(" ");
+    set_attribute(node, "style", &style);
 }
@@ -7,7 +7,7 @@ use core::{
 use alloc::{
     format,
     rc::{Rc, Weak},
-    string::String,
+    string::{String, ToString},
     vec::Vec,
 };
 
@@ -42,6 +42,10 @@ pub struct Node {
     last_child: Weak<RefCell<Node>>,
     previous_sibling: Weak<RefCell<Node>>,
     next_sibling: Option<Rc<RefCell<Node>>>,
+    // このノードのスタイルとレイアウトを再計算する必要があるか
+    dirty: bool,
+    // 子孫に再計算が必要なノードがあるか、子ノードが追加または削除されたか
+    child_dirty: bool,
 }
 
 impl PartialEq for Node {
@@ -60,6 +64,8 @@ impl Node {
             last_child: Weak::new(),
             previous_sibling: Weak::new(),
             next_sibling: None,
+            dirty: false,
+            child_dirty: false,
         }
     }
 
@@ -103,6 +109,22 @@ impl Node {
         self.next_sibling.as_ref().cloned()
     }
 
+    pub fn set_dirty(&mut self, dirty: bool) {
+        self.dirty = dirty;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn set_child_dirty(&mut self, child_dirty: bool) {
+        self.child_dirty = child_dirty;
+    }
+
+    pub fn is_child_dirty(&self) -> bool {
+        self.child_dirty
+    }
+
     pub fn set_window(&mut self, window: Weak<RefCell<Window>>) {
         self.window = window;
     }
@@ -160,18 +182,22 @@ impl Element {
     pub fn new(element_name: &str, attributes: Vec<Attribute>) -> Self {
         Self {
             kind: ElementKind::from_str(element_name)
-                .expect("failed to convert string to ElementKind"),
+                .unwrap_or_else(|_| ElementKind::Other(element_name.to_string())),
             attributes,
         }
     }
 
     pub fn kind(&self) -> ElementKind {
-        self.kind
+        self.kind.clone()
     }
 
     pub fn is_block_element(&self) -> bool {
-        match self.kind {
-            ElementKind::Body | ElementKind::H1 | ElementKind::H2 | ElementKind::P => true,
+        match &self.kind {
+            ElementKind::Body
+            | ElementKind::H1
+            | ElementKind::H2
+            | ElementKind::P
+            | ElementKind::Div => true,
             __ => false,
         }
     }
@@ -189,9 +215,25 @@ impl Element {
         }
         None
     }
+
+    // 属性の値を変更する。属性が存在しない場合は追加する
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        let mut attr = Attribute::new();
+        for c in name.chars() {
+            attr.add_char(c, true);
+        }
+        for c in value.chars() {
+            attr.add_char(c, false);
+        }
+
+        match self.attributes.iter().position(|a| a.name() == name) {
+            Some(i) => self.attributes[i] = attr,
+            None => self.attributes.push(attr),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ElementKind {
     Html,
     Head,
@@ -203,6 +245,16 @@ pub enum ElementKind {
     H1,
     H2,
     A,
+    Div,
+    Table,
+    Thead,
+    Tbody,
+    Tfoot,
+    Tr,
+    Td,
+    Th,
+    // li、bのように、このブラウザが個別に扱わない要素。タグ名を保持する
+    Other(String),
 }
 
 impl FromStr for ElementKind {
@@ -219,6 +271,14 @@ impl FromStr for ElementKind {
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
             "a" => Ok(ElementKind::A),
+            "div" => Ok(ElementKind::Div),
+            "table" => Ok(ElementKind::Table),
+            "thead" => Ok(ElementKind::Thead),
+            "tbody" => Ok(ElementKind::Tbody),
+            "tfoot" => Ok(ElementKind::Tfoot),
+            "tr" => Ok(ElementKind::Tr),
+            "td" => Ok(ElementKind::Td),
+            "th" => Ok(ElementKind::Th),
 
             _ => Err(format!("unimplemented element name {:?}", s)),
         }
@@ -237,6 +297,15 @@ impl Display for ElementKind {
             ElementKind::H2 => "h2",
             ElementKind::P => "p",
             ElementKind::A => "a",
+            ElementKind::Div => "div",
+            ElementKind::Table => "table",
+            ElementKind::Thead => "thead",
+            ElementKind::Tbody => "tbody",
+            ElementKind::Tfoot => "tfoot",
+            ElementKind::Tr => "tr",
+            ElementKind::Td => "td",
+            ElementKind::Th => "th",
+            ElementKind::Other(name) => name,
         };
         write!(f, "{}", s)
     }
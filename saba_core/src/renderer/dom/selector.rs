@@ -0,0 +1,302 @@
+// querySelectorで使うセレクタ
+// タイプ、クラス、ID、属性のセレクタを並べた複合セレクタと、子孫結合子、子結合子、カンマ区切りのリストに対応する
+
+use core::cell::RefCell;
+
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::renderer::dom::node::{Element, Node};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SimpleSelector {
+    // *
+    Universal,
+    Type(String),
+    Class(String),
+    Id(String),
+    // [name]または[name=value]
+    Attribute { name: String, value: Option<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    // div p
+    Descendant,
+    // div > p
+    Child,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// 複合セレクタの並び。compounds[i]とcompounds[i + 1]はcombinators[i]で結合される
+struct ComplexSelector {
+    compounds: Vec<Vec<SimpleSelector>>,
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// カンマで区切られたセレクタのリスト。いずれかに一致する要素を選択する
+pub struct SelectorList {
+    selectors: Vec<ComplexSelector>,
+}
+
+impl SelectorList {
+    // 解釈できない場合はエラーメッセージを返す
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut parser = SelectorParser {
+            input: input.chars().collect(),
+            pos: 0,
+        };
+        parser
+            .selector_list()
+            .ok_or_else(|| format!("'{}' is not a valid selector", input))
+    }
+
+    pub fn matches(&self, node: &Rc<RefCell<Node>>) -> bool {
+        self.selectors
+            .iter()
+            .any(|selector| selector.matches(selector.compounds.len() - 1, node))
+    }
+}
+
+impl ComplexSelector {
+    // 右側の複合セレクタから順に、祖先の要素をたどって一致するか調べる
+    fn matches(&self, index: usize, node: &Rc<RefCell<Node>>) -> bool {
+        let element = match node.borrow().get_element() {
+            Some(element) => element,
+            None => return false,
+        };
+        if !self.compounds[index]
+            .iter()
+            .all(|simple| simple.matches(&element))
+        {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+
+        let mut ancestor = node.borrow().parent().upgrade();
+        while let Some(a) = ancestor {
+            if self.matches(index - 1, &a) {
+                return true;
+            }
+            if self.combinators[index - 1] == Combinator::Child {
+                return false;
+            }
+            ancestor = a.borrow().parent().upgrade();
+        }
+        false
+    }
+}
+
+impl SimpleSelector {
+    fn matches(&self, element: &Element) -> bool {
+        match self {
+            SimpleSelector::Universal => true,
+            SimpleSelector::Type(name) => element.kind().to_string() == *name,
+            // class属性は空白で区切られた複数のクラスを持つ
+            SimpleSelector::Class(name) => element
+                .get_attribute("class")
+                .map(|classes| classes.split_whitespace().any(|c| c == name))
+                .unwrap_or(false),
+            SimpleSelector::Id(id) => element.get_attribute("id").as_ref() == Some(id),
+            SimpleSelector::Attribute { name, value } => match element.get_attribute(name) {
+                Some(v) => value.as_ref().map(|value| *value == v).unwrap_or(true),
+                None => false,
+            },
+        }
+    }
+}
+
+struct SelectorParser {
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl SelectorParser {
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    // 空白を読み飛ばし、空白があったかどうかを返す
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn selector_list(&mut self) -> Option<SelectorList> {
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.complex_selector()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                None => return Some(SelectorList { selectors }),
+                Some(_) => return None,
+            }
+        }
+    }
+
+    fn complex_selector(&mut self) -> Option<ComplexSelector> {
+        let mut compounds = Vec::from([self.compound_selector()?]);
+        let mut combinators = Vec::new();
+        loop {
+            let has_whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                None | Some(',') => break,
+                Some('>') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(_) if has_whitespace => Combinator::Descendant,
+                Some(_) => return None,
+            };
+            combinators.push(combinator);
+            compounds.push(self.compound_selector()?);
+        }
+        Some(ComplexSelector {
+            compounds,
+            combinators,
+        })
+    }
+
+    fn compound_selector(&mut self) -> Option<Vec<SimpleSelector>> {
+        let mut compound = Vec::new();
+        loop {
+            let simple = match self.peek() {
+                Some('*') if compound.is_empty() => {
+                    self.pos += 1;
+                    SimpleSelector::Universal
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    SimpleSelector::Class(self.ident()?)
+                }
+                Some('#') => {
+                    self.pos += 1;
+                    SimpleSelector::Id(self.ident()?)
+                }
+                Some('[') => self.attribute()?,
+                Some(c) if is_ident_char(c) && compound.is_empty() => {
+                    SimpleSelector::Type(self.ident()?.to_lowercase())
+                }
+                _ => break,
+            };
+            compound.push(simple);
+        }
+        if compound.is_empty() {
+            None
+        } else {
+            Some(compound)
+        }
+    }
+
+    fn attribute(&mut self) -> Option<SimpleSelector> {
+        self.pos += 1;
+        self.skip_whitespace();
+        let name = self.ident()?;
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some('=') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                let value = match self.peek() {
+                    Some(quote @ ('"' | '\'')) => self.quoted(quote)?,
+                    _ => self.ident()?,
+                };
+                self.skip_whitespace();
+                Some(value)
+            }
+            _ => None,
+        };
+        if self.peek() != Some(']') {
+            return None;
+        }
+        self.pos += 1;
+        Some(SimpleSelector::Attribute { name, value })
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if is_ident_char(c)) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return None;
+        }
+        Some(self.input[start..self.pos].iter().collect())
+    }
+
+    fn quoted(&mut self, quote: char) -> Option<String> {
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek()? != quote {
+            self.pos += 1;
+        }
+        let value = self.input[start..self.pos].iter().collect();
+        self.pos += 1;
+        Some(value)
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::html::{parser::HtmlParser, token::HtmlTokenizer};
+
+    // 祖先のノードが解放されないように、文書も返す
+    fn element(html: &str, id: &str) -> (Rc<RefCell<Node>>, Rc<RefCell<Node>>) {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let dom = window.borrow().document();
+        let element =
+            get_element_by_id(Some(dom.clone()), &id.to_string()).expect("element should exist");
+        (dom, element)
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(SelectorList::parse("").is_err());
+        assert!(SelectorList::parse("div >").is_err());
+        assert!(SelectorList::parse("p,").is_err());
+        assert!(SelectorList::parse("[id=").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let html = r#"<html><head></head><body><div id="outer" class="box main"><p id="target" class="note" data-x="1">text</p></div></body></html>"#;
+        let (_dom, target) = element(html, "target");
+        let matches = |selector: &str| {
+            SelectorList::parse(selector)
+                .expect("failed to parse selector")
+                .matches(&target)
+        };
+
+        assert!(matches("p"));
+        assert!(matches("*"));
+        assert!(matches("p.note#target"));
+        assert!(matches("[data-x]"));
+        assert!(matches("[data-x=\"1\"]"));
+        assert!(matches(".main p"));
+        assert!(matches("body > div > p"));
+        assert!(matches("a, .box > .note"));
+        assert!(!matches("div"));
+        assert!(!matches(".box"));
+        assert!(!matches("body > p"));
+        assert!(!matches("[data-x=2]"));
+    }
+}
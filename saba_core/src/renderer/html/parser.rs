@@ -1,6 +1,10 @@
 use core::{cell::RefCell, str::FromStr};
 
-use alloc::{rc::Rc, string::String, vec::Vec};
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
 
 use crate::renderer::dom::node::{Element, ElementKind, Node, NodeKind, Window};
 
@@ -9,6 +13,12 @@ use super::{
     token::{HtmlToken, HtmlTokenizer},
 };
 
+// 終了タグを持たない空要素
+pub const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
 #[derive(Debug, Clone)]
 pub struct HtmlParser {
     window: Rc<RefCell<Window>>,
@@ -30,6 +40,18 @@ impl HtmlParser {
     }
 
     pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
+        while self.parse_until_script().is_some() {}
+        self.window.clone()
+    }
+
+    // 構築中のDOMツリーを持つWindow
+    pub fn window(&self) -> Rc<RefCell<Window>> {
+        self.window.clone()
+    }
+
+    // </script>までDOMツリーを構築し、閉じたscript要素を返す。最後まで構築した場合はNone
+    // 呼び出し元はscript要素を実行してから、続きを構築する
+    pub fn parse_until_script(&mut self) -> Option<Rc<RefCell<Node>>> {
         let mut token = self.t.next();
 
         while token.is_some() {
@@ -66,7 +88,7 @@ impl HtmlParser {
                             }
                         }
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
                         _ => {}
                     }
@@ -95,7 +117,7 @@ impl HtmlParser {
                             }
                         }
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
                         _ => {}
                     }
@@ -120,6 +142,9 @@ impl HtmlParser {
                         }) => {
                             if tag == "style" || tag == "script" {
                                 self.insert_element(tag, attributes.to_vec());
+                                if tag == "script" {
+                                    self.t.switch_to_script_data();
+                                }
                                 self.original_insertion_mode = self.mode;
                                 self.mode = InsertionMode::Text;
                                 token = self.t.next();
@@ -151,7 +176,7 @@ impl HtmlParser {
                         }
 
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
                     }
                     // <meta>, <title>などのサポートしていないタグは無視する
@@ -183,7 +208,7 @@ impl HtmlParser {
                         }
 
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
 
                         _ => {}
@@ -200,7 +225,7 @@ impl HtmlParser {
                         self_closing: _,
                         ref attributes,
                     }) => match tag.as_str() {
-                        "p" => {
+                        "p" | "div" => {
                             self.insert_element(tag, attributes.to_vec());
                             token = self.t.next();
                             continue;
@@ -217,6 +242,59 @@ impl HtmlParser {
                             token = self.t.next();
                             continue;
                         }
+
+                        "table" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+
+                        "script" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            self.t.switch_to_script_data();
+                            self.original_insertion_mode = self.mode;
+                            self.mode = InsertionMode::Text;
+                            token = self.t.next();
+                            continue;
+                        }
+
+                        // 終了タグが省略された場合、開いている同じ種類の要素を閉じる
+                        "thead" | "tbody" | "tfoot" => {
+                            self.close_element_in_scope(
+                                &[ElementKind::Thead, ElementKind::Tbody, ElementKind::Tfoot],
+                                ElementKind::Table,
+                            );
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+
+                        "tr" => {
+                            self.close_element_in_scope(&[ElementKind::Tr], ElementKind::Table);
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+
+                        "td" | "th" => {
+                            self.close_element_in_scope(
+                                &[ElementKind::Td, ElementKind::Th],
+                                ElementKind::Tr,
+                            );
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // li、bのように個別に扱わない要素は、タグ名を保持した要素として追加する
+                        _ if ElementKind::from_str(tag).is_err() => {
+                            self.insert_element(tag, attributes.to_vec());
+                            // 空要素は終了タグを持たないため、すぐに閉じる
+                            if VOID_ELEMENTS.contains(&tag.as_str()) {
+                                self.stack_of_open_elements.pop();
+                            }
+                            token = self.t.next();
+                            continue;
+                        }
                         _ => {
                             token = self.t.next();
                         }
@@ -243,7 +321,7 @@ impl HtmlParser {
                             continue;
                         }
 
-                        "p" => {
+                        "p" | "div" => {
                             let element_kind = ElementKind::from_str(tag)
                                 .expect("failed to convert string to ElementKind");
 
@@ -269,13 +347,34 @@ impl HtmlParser {
                             continue;
                         }
 
+                        "table" | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th" => {
+                            let element_kind = ElementKind::from_str(tag)
+                                .expect("failed to convert string to ElementKind");
+
+                            token = self.t.next();
+                            // 省略された終了タグによってすでに閉じられている場合は無視する
+                            if self.contain_in_stack(element_kind.clone()) {
+                                self.pop_until(element_kind);
+                            }
+                            continue;
+                        }
+
+                        _ if ElementKind::from_str(tag).is_err() => {
+                            let element_kind = ElementKind::Other(tag.to_string());
+                            token = self.t.next();
+                            if self.contain_in_stack(element_kind.clone()) {
+                                self.pop_until(element_kind);
+                            }
+                            continue;
+                        }
+
                         _ => {
                             token = self.t.next();
                         }
                     },
 
                     Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
+                        return None;
                     }
 
                     Some(HtmlToken::Char(c)) => {
@@ -288,7 +387,7 @@ impl HtmlParser {
                 InsertionMode::Text => {
                     match token {
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
 
                         Some(HtmlToken::EndTag { ref tag }) => {
@@ -300,10 +399,18 @@ impl HtmlParser {
                             }
 
                             if tag == "script" {
+                                // テキストノードも開いている要素のスタックにあるため、script要素を探す
+                                let script = self
+                                    .stack_of_open_elements
+                                    .iter()
+                                    .rev()
+                                    .find(|n| {
+                                        n.borrow().element_kind() == Some(ElementKind::Script)
+                                    })
+                                    .cloned();
                                 self.pop_until(ElementKind::Script);
                                 self.mode = self.original_insertion_mode;
-                                token = self.t.next();
-                                continue;
+                                return script;
                             }
                         }
 
@@ -334,7 +441,7 @@ impl HtmlParser {
                             }
                         }
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
                         _ => {}
                     }
@@ -349,7 +456,7 @@ impl HtmlParser {
                         }
 
                         Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                            return None;
                         }
 
                         _ => {}
@@ -359,7 +466,7 @@ impl HtmlParser {
                 }
             }
         }
-        self.window.clone()
+        None
     }
 
     fn create_element(&self, tag: &str, attributes: Vec<Attribute>) -> Node {
@@ -367,6 +474,14 @@ impl HtmlParser {
     }
 
     fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
+        // 要素が始まるとテキストノードは終わるため、スタックから取り除く
+        while let Some(n) = self.stack_of_open_elements.last() {
+            if !matches!(n.borrow().kind(), NodeKind::Text(_)) {
+                break;
+            }
+            self.stack_of_open_elements.pop();
+        }
+
         let window = self.window.borrow();
         let current = match self.stack_of_open_elements.last() {
             Some(n) => n.clone(),
@@ -390,16 +505,12 @@ impl HtmlParser {
                 }
             }
 
+            let last_sibling = last_sibling.expect("failed to get the last sibling");
             last_sibling
-                .unwrap()
                 .borrow_mut()
                 .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                    .borrow()
-                    .first_child()
-                    .expect("failed to get a first child"),
-            ))
+            node.borrow_mut()
+                .set_previous_sibling(Rc::downgrade(&last_sibling))
         } else {
             current.borrow_mut().set_first_child(Some(node.clone()));
         }
@@ -416,7 +527,7 @@ impl HtmlParser {
             None => return false,
         };
 
-        if current.borrow().element_kind() == Some(element_kind) {
+        if current.borrow().element_kind().as_ref() == Some(&element_kind) {
             self.stack_of_open_elements.pop();
             return true;
         }
@@ -425,7 +536,7 @@ impl HtmlParser {
 
     fn pop_until(&mut self, element_kind: ElementKind) {
         assert!(
-            self.contain_in_stack(element_kind),
+            self.contain_in_stack(element_kind.clone()),
             "stack doesn't have an element {:?}",
             element_kind,
         );
@@ -436,15 +547,36 @@ impl HtmlParser {
                 None => return,
             };
 
-            if current.borrow().element_kind() == Some(element_kind) {
+            if current.borrow().element_kind().as_ref() == Some(&element_kind) {
                 return;
             }
         }
     }
 
+    // scopeの要素より上に開いているelement_kindsのいずれかの要素があれば、その要素まで閉じる
+    fn close_element_in_scope(&mut self, element_kinds: &[ElementKind], scope: ElementKind) {
+        for i in (0..self.stack_of_open_elements.len()).rev() {
+            let kind = self.stack_of_open_elements[i].borrow().element_kind();
+            if let Some(kind) = kind {
+                if element_kinds.contains(&kind) {
+                    self.stack_of_open_elements.truncate(i);
+                    return;
+                }
+                if kind == scope || kind == ElementKind::Table {
+                    return;
+                }
+            }
+        }
+    }
+
     fn contain_in_stack(&mut self, element_kind: ElementKind) -> bool {
         for i in 0..self.stack_of_open_elements.len() {
-            if self.stack_of_open_elements[i].borrow().element_kind() == Some(element_kind) {
+            if self.stack_of_open_elements[i]
+                .borrow()
+                .element_kind()
+                .as_ref()
+                == Some(&element_kind)
+            {
                 return true;
             }
         }
@@ -476,19 +608,12 @@ impl HtmlParser {
 
         let node = Rc::new(RefCell::new(self.create_char(c)));
 
-        if current.borrow().first_child().is_some() {
-            current
-                .borrow()
-                .first_child()
-                .unwrap()
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                    .borrow()
-                    .first_child()
-                    .expect("failed to get a first child"),
-            ));
+        // 閉じた要素の後に続くテキストは、最後の子ノードの兄弟として追加する
+        let last_child = current.borrow().last_child().upgrade();
+        if let Some(last_child) = last_child {
+            last_child.borrow_mut().set_next_sibling(Some(node.clone()));
+            node.borrow_mut()
+                .set_previous_sibling(Rc::downgrade(&last_child));
         } else {
             current.borrow_mut().set_first_child(Some(node.clone()));
         }
@@ -518,6 +643,7 @@ mod tests {
     use alloc::string::ToString;
 
     use super::*;
+    use crate::renderer::dom::api::get_text_content;
     use alloc::vec;
 
     #[test]
@@ -691,4 +817,124 @@ mod tests {
             text
         );
     }
+
+    #[test]
+    fn test_other_elements_and_mixed_text() {
+        let html = "<html><head></head><body><p>a<b>b</b>c<br>d</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let body = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .last_child()
+            .upgrade()
+            .expect("failed to get a last child of html");
+        let p = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+
+        // テキストと要素が混ざった子ノードは、すべてpの子として順に並ぶ
+        let mut children = Vec::new();
+        let mut child = p.borrow().first_child();
+        while let Some(c) = child {
+            assert!(Rc::ptr_eq(
+                &p,
+                &c.borrow()
+                    .parent()
+                    .upgrade()
+                    .expect("failed to get a parent")
+            ));
+            child = c.borrow().next_sibling();
+            children.push(c.borrow().kind());
+        }
+        assert_eq!(
+            vec![
+                NodeKind::Text("a".to_string()),
+                NodeKind::Element(Element::new("b", Vec::new())),
+                NodeKind::Text("c".to_string()),
+                NodeKind::Element(Element::new("br", Vec::new())),
+                NodeKind::Text("d".to_string()),
+            ],
+            children
+        );
+        assert_eq!(
+            Some(ElementKind::Other("b".to_string())),
+            p.borrow()
+                .first_child()
+                .and_then(|a| a.borrow().next_sibling())
+                .and_then(|b| b.borrow().element_kind())
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let html = "<html><head></head><body><table><tbody><tr><td>a<td colspan=2>b<tr><th>c</th></tr></tbody></table></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let table = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .last_child()
+            .upgrade()
+            .expect("failed to get a body")
+            .borrow()
+            .first_child()
+            .expect("failed to get a table");
+        assert_eq!(Some(ElementKind::Table), table.borrow().element_kind());
+
+        let tbody = table.borrow().first_child().expect("failed to get a tbody");
+        assert_eq!(Some(ElementKind::Tbody), tbody.borrow().element_kind());
+
+        // 省略された</td>と</tr>は次の開始タグによって補われる
+        let tr1 = tbody.borrow().first_child().expect("failed to get a tr");
+        let tr2 = tr1.borrow().next_sibling().expect("failed to get a tr");
+        assert_eq!(Some(ElementKind::Tr), tr1.borrow().element_kind());
+        assert_eq!(Some(ElementKind::Tr), tr2.borrow().element_kind());
+
+        let td1 = tr1.borrow().first_child().expect("failed to get a td");
+        let td2 = td1.borrow().next_sibling().expect("failed to get a td");
+        assert_eq!(Some(ElementKind::Td), td1.borrow().element_kind());
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Text("a".to_string())))),
+            td1.borrow().first_child().expect("failed to get a text")
+        );
+        match td2.borrow().kind() {
+            NodeKind::Element(e) => assert_eq!(Some("2".to_string()), e.get_attribute("colspan")),
+            _ => panic!("td should be an element"),
+        }
+
+        let th = tr2.borrow().first_child().expect("failed to get a th");
+        assert_eq!(Some(ElementKind::Th), th.borrow().element_kind());
+    }
+
+    #[test]
+    fn test_parse_until_script() {
+        let html = r#"<html><head><script>var a = 1 < 2;</script></head><body><p>text</p><script src="b.js">"</p>"</script><p>after</p></body></html>"#.to_string();
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+        let first = parser.parse_until_script().expect("failed to get a script");
+        assert_eq!("var a = 1 < 2;", get_text_content(&first));
+
+        // scriptの終了タグまでしか構築されていない
+        let second = parser.parse_until_script().expect("failed to get a script");
+        let element = second.borrow().get_element().expect("should be an element");
+        assert_eq!(Some("b.js".to_string()), element.get_attribute("src"));
+        assert_eq!("\"</p>\"", get_text_content(&second));
+        assert!(second.borrow().next_sibling().is_none());
+
+        assert!(parser.parse_until_script().is_none());
+        let after = second
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of script");
+        assert_eq!("after", get_text_content(&after));
+    }
 }
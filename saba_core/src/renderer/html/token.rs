@@ -65,6 +65,11 @@ impl HtmlTokenizer {
         }
     }
 
+    // <script>の開始タグの後、</script>までを文字として読む
+    pub fn switch_to_script_data(&mut self) {
+        self.state = State::ScriptData;
+    }
+
     fn is_eof(&self) -> bool {
         self.pos > self.input.len()
     }
@@ -453,7 +458,7 @@ impl Iterator for HtmlTokenizer {
                 }
 
                 State::ScriptDataEndTagName => {
-                    if c == '>' {
+                    if c == '>' && self.buf.eq_ignore_ascii_case("script") {
                         self.state = State::Data;
                         return self.take_latest_token();
                     }
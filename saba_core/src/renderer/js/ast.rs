@@ -1,10 +1,39 @@
-use core::iter::Peekable;
-
-use alloc::{rc::Rc, string::String, vec::Vec};
+use core::fmt::{Display, Formatter};
+
+use alloc::{
+    collections::VecDeque,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::renderer::js::regexp::Regex;
+use crate::renderer::js::token::{Goal, JsLexer, Position, Rewind, Span, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// 変数宣言に使われた予約語
+pub enum DeclarationKind {
+    // 関数スコープ
+    Var,
+    // ブロックスコープで再代入できる
+    Let,
+    // ブロックスコープで再代入できない
+    Const,
+}
 
-use crate::renderer::js::token::{JsLexer, Token};
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// クラスやオブジェクトリテラルの中で定義されるメソッドの種類
+pub enum MethodKind {
+    Constructor,
+    Method,
+    // get x() {}
+    Get,
+    // set x(v) {}
+    Set,
+}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     ExpressionStatement(Option<Rc<Node>>),
     AdditiveExpression {
@@ -12,17 +41,87 @@ pub enum Node {
         left: Option<Rc<Node>>,
         right: Option<Rc<Node>>,
     },
-    AssignmentExpression {
+    // * / %
+    MultiplicativeExpression {
         operator: char,
         left: Option<Rc<Node>>,
         right: Option<Rc<Node>>,
     },
+    // < > <= >= instanceof in
+    RelationalExpression {
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    // == != === !==
+    EqualityExpression {
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    // && ||
+    LogicalExpression {
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    // ** << >> >>> & | ^
+    BinaryExpression {
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    // ! - + ~ typeof void delete
+    UnaryExpression {
+        operator: String,
+        argument: Option<Rc<Node>>,
+    },
+    // a, bのように,で区切られた式。最後の式の値が結果になる
+    SequenceExpression {
+        expressions: Vec<Option<Rc<Node>>>,
+    },
+    // test ? consequent : alternate
+    ConditionalExpression {
+        test: Option<Rc<Node>>,
+        consequent: Option<Rc<Node>>,
+        alternate: Option<Rc<Node>>,
+    },
+    // =と、+=や-=のような複合代入。operatorは=か演算子の記号
+    AssignmentExpression {
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    // ++aやa--。prefixがtrueの場合は更新後の値、falseの場合は更新前の値になる
+    UpdateExpression {
+        operator: char,
+        prefix: bool,
+        argument: Option<Rc<Node>>,
+    },
+    // computedがtrueの場合はobject[property]、falseの場合はobject.propertyを表す
     MemberExpression {
         object: Option<Rc<Node>>,
         property: Option<Rc<Node>>,
+        computed: bool,
+    },
+    NumericLiteral(f64),
+    BooleanLiteral(bool),
+    NullLiteral,
+    // [1, 2, 3]のような配列リテラルを表す
+    ArrayExpression {
+        elements: Vec<Option<Rc<Node>>>,
+    },
+    // {a: 1, b: 2}のようなオブジェクトリテラルを表す
+    ObjectExpression {
+        properties: Vec<Option<Rc<Node>>>,
+    },
+    // オブジェクトリテラルの中のkey: valueを表す
+    Property {
+        key: String,
+        value: Option<Rc<Node>>,
     },
-    NumericLiteral(u64),
     VariableDeclaration {
+        kind: DeclarationKind,
         declarations: Vec<Option<Rc<Node>>>,
     },
     VariableDeclarator {
@@ -31,6 +130,21 @@ pub enum Node {
     },
     Identifier(String),
     StringLiteral(String),
+    // `a${b}c`のようなテンプレートリテラル。quasisはexpressionsより1つ多い
+    TemplateLiteral {
+        quasis: Vec<String>,
+        expressions: Vec<Option<Rc<Node>>>,
+    },
+    // タグ付きテンプレートの関数に渡す文字列の配列。rawプロパティにエスケープを解釈しない文字列を持つ
+    TemplateObject {
+        cooked: Vec<String>,
+        raw: Vec<String>,
+    },
+    // /pattern/flagsの正規表現リテラル
+    RegExpLiteral {
+        pattern: String,
+        flags: String,
+    },
     // {}で囲まれるブロックを表す
     BlockStatement {
         body: Vec<Option<Rc<Node>>>,
@@ -39,20 +153,215 @@ pub enum Node {
     ReturnStatement {
         argument: Option<Rc<Node>>,
     },
-    // functionの予約語から始まる文を表す
+    // functionの予約語から始まる文を表す。async functionとfunction*も含む
     FunctionDeclaration {
         id: Option<Rc<Node>>,
         params: Vec<Option<Rc<Node>>>,
         body: Option<Rc<Node>>,
+        is_async: bool,
+        is_generator: bool,
+    },
+    // 式の中で定義される関数を表す。idは省略できる
+    FunctionExpression {
+        id: Option<Rc<Node>>,
+        params: Vec<Option<Rc<Node>>>,
+        body: Option<Rc<Node>>,
+        is_async: bool,
+        is_generator: bool,
+    },
+    // (a, b) => a + bのようなアロー関数を表す。式の本体はreturn文を持つブロックに変換する
+    ArrowFunctionExpression {
+        params: Vec<Option<Rc<Node>>>,
+        body: Option<Rc<Node>>,
+        is_async: bool,
+    },
+    // ジェネレータの中のyield。値は省略できる。yield*の場合はdelegateがtrueになる
+    YieldExpression {
+        argument: Option<Rc<Node>>,
+        delegate: bool,
+    },
+    // async関数の中のawait
+    AwaitExpression {
+        argument: Option<Rc<Node>>,
     },
-    // 関数呼び出しを表す
+    ThisExpression,
+    // super(args)やsuper.method()のsuper
+    Super,
+    // 関数呼び出しを表す。positionは実行時エラーの位置として使う
     CallExpression {
         callee: Option<Rc<Node>>,
         arguments: Vec<Option<Rc<Node>>>,
+        position: Position,
+    },
+    // new F(args)を表す。引数の()は省略できる
+    NewExpression {
+        callee: Option<Rc<Node>>,
+        arguments: Vec<Option<Rc<Node>>>,
+        position: Position,
+    },
+    // classの予約語から始まる文を表す
+    ClassDeclaration {
+        id: Option<Rc<Node>>,
+        super_class: Option<Rc<Node>>,
+        body: Vec<Option<Rc<Node>>>,
+    },
+    // 式の中で定義されるクラスを表す。idは省略できる
+    ClassExpression {
+        id: Option<Rc<Node>>,
+        super_class: Option<Rc<Node>>,
+        body: Vec<Option<Rc<Node>>>,
+    },
+    // クラスやオブジェクトリテラルの中のメソッドを表す。valueはFunctionExpression
+    MethodDefinition {
+        key: String,
+        value: Option<Rc<Node>>,
+        kind: MethodKind,
+        is_static: bool,
+    },
+    // ifの予約語から始まる文を表す
+    IfStatement {
+        test: Option<Rc<Node>>,
+        consequent: Option<Rc<Node>>,
+        alternate: Option<Rc<Node>>,
+    },
+    // whileの予約語から始まる文を表す
+    WhileStatement {
+        test: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    // forの予約語から始まる文を表す
+    ForStatement {
+        init: Option<Rc<Node>>,
+        test: Option<Rc<Node>>,
+        update: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    // do body while (test)
+    DoWhileStatement {
+        body: Option<Rc<Node>>,
+        test: Option<Rc<Node>>,
+    },
+    // for (left in right)。leftは初期値のない変数宣言か、代入できる式
+    ForInStatement {
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    // for (left of right)
+    ForOfStatement {
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    // switchの予約語から始まる文を表す。casesはSwitchCase
+    SwitchStatement {
+        discriminant: Option<Rc<Node>>,
+        cases: Vec<Option<Rc<Node>>>,
+    },
+    // case test:の後に続く文。testがない場合はdefault節
+    SwitchCase {
+        test: Option<Rc<Node>>,
+        consequent: Vec<Option<Rc<Node>>>,
+    },
+    // label: bodyのようにラベルが付いた文
+    LabeledStatement {
+        label: String,
+        body: Option<Rc<Node>>,
+    },
+    // labelがある場合は、そのラベルが付いた文から抜ける
+    BreakStatement {
+        label: Option<String>,
+    },
+    // labelがある場合は、そのラベルが付いた繰り返し文の次の繰り返しへ移る
+    ContinueStatement {
+        label: Option<String>,
+    },
+    // throwの予約語から始まる文を表す
+    ThrowStatement {
+        argument: Option<Rc<Node>>,
+        position: Position,
+    },
+    // tryの予約語から始まる文を表す。catch節の仮引数は省略できる
+    TryStatement {
+        block: Option<Rc<Node>>,
+        param: Option<Rc<Node>>,
+        handler: Option<Rc<Node>>,
+        finalizer: Option<Rc<Node>>,
     },
+    // モジュールのimport文。specifiersは(読み込む名前, モジュール内の名前)の組
+    // 読み込む名前が*の場合は名前空間オブジェクトを表す。import "m";のように空の場合もある
+    ImportDeclaration {
+        specifiers: Vec<(String, String)>,
+        source: String,
+    },
+    // export {a as b}やexport const a = 1を表す。specifiersは(モジュール内の名前, 公開する名前)の組
+    // sourceがある場合は、そのモジュールの名前を公開し直す
+    ExportNamedDeclaration {
+        declaration: Option<Rc<Node>>,
+        specifiers: Vec<(String, String)>,
+        source: Option<String>,
+    },
+    // export defaultの後の式か、名前を省略できる関数宣言とクラス宣言
+    ExportDefaultDeclaration {
+        declaration: Option<Rc<Node>>,
+    },
+    // export * from "m"
+    ExportAllDeclaration {
+        source: String,
+    },
+}
+
+// 1 + 1 + ... + 1のような長い式を解放してもスタックが溢れないように、左辺に続く式を順に取り出して解放する
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut left = self.take_binary_left();
+        while let Some(node) = left {
+            left = match Rc::try_unwrap(node) {
+                Ok(mut node) => node.take_binary_left(),
+                Err(_) => None,
+            };
+        }
+    }
 }
 
 impl Node {
+    // 二項演算子と論理演算子の式の場合、左辺を返す
+    pub fn binary_left(&self) -> Option<&Option<Rc<Node>>> {
+        match self {
+            Node::AdditiveExpression { left, .. }
+            | Node::MultiplicativeExpression { left, .. }
+            | Node::RelationalExpression { left, .. }
+            | Node::EqualityExpression { left, .. }
+            | Node::LogicalExpression { left, .. }
+            | Node::BinaryExpression { left, .. } => Some(left),
+            _ => None,
+        }
+    }
+
+    fn take_binary_left(&mut self) -> Option<Rc<Node>> {
+        match self {
+            Node::AdditiveExpression { left, .. }
+            | Node::MultiplicativeExpression { left, .. }
+            | Node::RelationalExpression { left, .. }
+            | Node::EqualityExpression { left, .. }
+            | Node::LogicalExpression { left, .. }
+            | Node::BinaryExpression { left, .. } => left.take(),
+            _ => None,
+        }
+    }
+
+    // a + b - cのように左辺に続く二項演算子の式を、外側から順に返す。最後の要素の左辺が一番左の値になる
+    pub fn binary_chain(&self) -> Vec<&Node> {
+        let mut chain = Vec::from([self]);
+        while let Some(Some(left)) = chain[chain.len() - 1].binary_left() {
+            if left.binary_left().is_none() {
+                break;
+            }
+            chain.push(left);
+        }
+        chain
+    }
+
     pub fn new_expression_statement(expression: Option<Rc<Self>>) -> Option<Rc<Self>> {
         Some(Rc::new(Node::ExpressionStatement(expression)))
     }
@@ -69,10 +378,90 @@ impl Node {
         }))
     }
 
-    pub fn new_assignment_expression(
+    pub fn new_multiplicative_expression(
         operator: char,
         left: Option<Rc<Node>>,
         right: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::MultiplicativeExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_relational_expression(
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::RelationalExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_equality_expression(
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::EqualityExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_logical_expression(
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::LogicalExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_binary_expression(
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::BinaryExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_unary_expression(operator: String, argument: Option<Rc<Node>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::UnaryExpression { operator, argument }))
+    }
+
+    pub fn new_sequence_expression(expressions: Vec<Option<Rc<Node>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SequenceExpression { expressions }))
+    }
+
+    pub fn new_conditional_expression(
+        test: Option<Rc<Node>>,
+        consequent: Option<Rc<Node>>,
+        alternate: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+        }))
+    }
+
+    pub fn new_assignment_expression(
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
     ) -> Option<Rc<Self>> {
         Some(Rc::new(Node::AssignmentExpression {
             operator,
@@ -81,17 +470,50 @@ impl Node {
         }))
     }
 
+    pub fn new_update_expression(
+        operator: char,
+        prefix: bool,
+        argument: Option<Rc<Node>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::UpdateExpression {
+            operator,
+            prefix,
+            argument,
+        }))
+    }
+
     pub fn new_member_expression(
         object: Option<Rc<Self>>,
         property: Option<Rc<Self>>,
+        computed: bool,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::MemberExpression { object, property }))
+        Some(Rc::new(Node::MemberExpression {
+            object,
+            property,
+            computed,
+        }))
     }
 
-    pub fn new_numeric_literal(value: u64) -> Option<Rc<Self>> {
+    pub fn new_numeric_literal(value: f64) -> Option<Rc<Self>> {
         Some(Rc::new(Node::NumericLiteral(value)))
     }
 
+    pub fn new_array_expression(elements: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ArrayExpression { elements }))
+    }
+
+    pub fn new_object_expression(properties: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ObjectExpression { properties }))
+    }
+
+    pub fn new_property(key: String, value: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::Property { key, value }))
+    }
+
+    pub fn new_boolean_literal(value: bool) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::BooleanLiteral(value)))
+    }
+
     pub fn new_variable_declarator(
         id: Option<Rc<Self>>,
         init: Option<Rc<Self>>,
@@ -99,8 +521,11 @@ impl Node {
         Some(Rc::new(Node::VariableDeclarator { id, init }))
     }
 
-    pub fn new_variable_declaration(declarations: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::VariableDeclaration { declarations }))
+    pub fn new_variable_declaration(
+        kind: DeclarationKind,
+        declarations: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::VariableDeclaration { kind, declarations }))
     }
 
     pub fn new_identifier(name: String) -> Option<Rc<Self>> {
@@ -111,6 +536,24 @@ impl Node {
         Some(Rc::new(Node::StringLiteral(value)))
     }
 
+    pub fn new_template_literal(
+        quasis: Vec<String>,
+        expressions: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::TemplateLiteral {
+            quasis,
+            expressions,
+        }))
+    }
+
+    pub fn new_template_object(cooked: Vec<String>, raw: Vec<String>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::TemplateObject { cooked, raw }))
+    }
+
+    pub fn new_regexp_literal(pattern: String, flags: String) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::RegExpLiteral { pattern, flags }))
+    }
+
     pub fn new_block_statement(body: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
         Some(Rc::new(Node::BlockStatement { body }))
     }
@@ -123,82 +566,889 @@ impl Node {
         id: Option<Rc<Self>>,
         params: Vec<Option<Rc<Self>>>,
         body: Option<Rc<Self>>,
+        is_async: bool,
+        is_generator: bool,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::FunctionDeclaration { id, params, body }))
+        Some(Rc::new(Node::FunctionDeclaration {
+            id,
+            params,
+            body,
+            is_async,
+            is_generator,
+        }))
     }
 
-    pub fn new_call_expression(
-        callee: Option<Rc<Self>>,
-        arguments: Vec<Option<Rc<Self>>>,
+    pub fn new_function_expression(
+        id: Option<Rc<Self>>,
+        params: Vec<Option<Rc<Self>>>,
+        body: Option<Rc<Self>>,
+        is_async: bool,
+        is_generator: bool,
     ) -> Option<Rc<Self>> {
-        Some(Rc::new(Node::CallExpression { callee, arguments }))
+        Some(Rc::new(Node::FunctionExpression {
+            id,
+            params,
+            body,
+            is_async,
+            is_generator,
+        }))
     }
-}
 
-pub struct JsParser {
-    t: Peekable<JsLexer>,
-}
+    pub fn new_arrow_function_expression(
+        params: Vec<Option<Rc<Self>>>,
+        body: Option<Rc<Self>>,
+        is_async: bool,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ArrowFunctionExpression {
+            params,
+            body,
+            is_async,
+        }))
+    }
 
-impl JsParser {
-    pub fn new(t: JsLexer) -> Self {
-        Self { t: t.peekable() }
+    pub fn new_yield_expression(argument: Option<Rc<Self>>, delegate: bool) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::YieldExpression { argument, delegate }))
     }
 
-    pub fn parse_ast(&mut self) -> Program {
-        let mut program = Program::new();
+    pub fn new_await_expression(argument: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::AwaitExpression { argument }))
+    }
 
-        let mut body = Vec::new();
+    pub fn new_call_expression(
+        callee: Option<Rc<Self>>,
+        arguments: Vec<Option<Rc<Self>>>,
+        position: Position,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::CallExpression {
+            callee,
+            arguments,
+            position,
+        }))
+    }
 
-        loop {
-            let node = self.source_element();
+    pub fn new_new_expression(
+        callee: Option<Rc<Self>>,
+        arguments: Vec<Option<Rc<Self>>>,
+        position: Position,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::NewExpression {
+            callee,
+            arguments,
+            position,
+        }))
+    }
 
-            match node {
-                Some(n) => body.push(n),
-                None => {
-                    program.set_body(body);
-                    return program;
-                }
-            }
-        }
+    pub fn new_class_declaration(
+        id: Option<Rc<Self>>,
+        super_class: Option<Rc<Self>>,
+        body: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ClassDeclaration {
+            id,
+            super_class,
+            body,
+        }))
     }
 
-    // SourceElementはStatementで構成されることを表現
-    fn source_element(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.peek() {
-            Some(t) => t,
-            None => return None,
-        };
+    pub fn new_class_expression(
+        id: Option<Rc<Self>>,
+        super_class: Option<Rc<Self>>,
+        body: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ClassExpression {
+            id,
+            super_class,
+            body,
+        }))
+    }
 
-        match t {
-            Token::Keyword(keyword) => {
-                if keyword == "function" {
-                    assert!(self.t.next().is_some());
-                    self.function_declaration()
-                } else {
-                    self.statement()
-                }
-            }
-            _ => self.statement(),
-        }
+    pub fn new_method_definition(
+        key: String,
+        value: Option<Rc<Self>>,
+        kind: MethodKind,
+        is_static: bool,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::MethodDefinition {
+            key,
+            value,
+            kind,
+            is_static,
+        }))
     }
 
-    fn function_declaration(&mut self) -> Option<Rc<Node>> {
-        let id = self.identifier();
-        let params = self.parameter_list();
-        Node::new_function_declaration(id, params, self.function_body())
+    pub fn new_if_statement(
+        test: Option<Rc<Self>>,
+        consequent: Option<Rc<Self>>,
+        alternate: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::IfStatement {
+            test,
+            consequent,
+            alternate,
+        }))
     }
 
-    fn parameter_list(&mut self) -> Vec<Option<Rc<Node>>> {
-        let mut params = Vec::new();
+    pub fn new_while_statement(test: Option<Rc<Self>>, body: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::WhileStatement { test, body }))
+    }
 
-        // ( を消費する。次のトークンが(でない場合エラー
-        match self.t.next() {
-            Some(t) => match t {
-                Token::Punctuator(c) => assert!(c == '('),
-                _ => unimplemented!("function should have `(` but got {:?}", t),
-            },
-            None => unimplemented!("function should have `(` but got None"),
-        }
+    pub fn new_for_statement(
+        init: Option<Rc<Self>>,
+        test: Option<Rc<Self>>,
+        update: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ForStatement {
+            init,
+            test,
+            update,
+            body,
+        }))
+    }
+
+    pub fn new_do_while_statement(
+        body: Option<Rc<Self>>,
+        test: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::DoWhileStatement { body, test }))
+    }
+
+    // is_ofがtrueの場合はfor-of、falseの場合はfor-inの文を作る
+    pub fn new_for_in_of_statement(
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+        is_of: bool,
+    ) -> Option<Rc<Self>> {
+        if is_of {
+            Some(Rc::new(Node::ForOfStatement { left, right, body }))
+        } else {
+            Some(Rc::new(Node::ForInStatement { left, right, body }))
+        }
+    }
+
+    pub fn new_switch_statement(
+        discriminant: Option<Rc<Self>>,
+        cases: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SwitchStatement {
+            discriminant,
+            cases,
+        }))
+    }
+
+    pub fn new_switch_case(
+        test: Option<Rc<Self>>,
+        consequent: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::SwitchCase { test, consequent }))
+    }
+
+    pub fn new_labeled_statement(label: String, body: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::LabeledStatement { label, body }))
+    }
+
+    pub fn new_throw_statement(argument: Option<Rc<Self>>, position: Position) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ThrowStatement { argument, position }))
+    }
+
+    pub fn new_try_statement(
+        block: Option<Rc<Self>>,
+        param: Option<Rc<Self>>,
+        handler: Option<Rc<Self>>,
+        finalizer: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::TryStatement {
+            block,
+            param,
+            handler,
+            finalizer,
+        }))
+    }
+
+    pub fn new_import_declaration(
+        specifiers: Vec<(String, String)>,
+        source: String,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ImportDeclaration { specifiers, source }))
+    }
+
+    pub fn new_export_named_declaration(
+        declaration: Option<Rc<Self>>,
+        specifiers: Vec<(String, String)>,
+        source: Option<String>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ExportNamedDeclaration {
+            declaration,
+            specifiers,
+            source,
+        }))
+    }
+
+    pub fn new_export_default_declaration(declaration: Option<Rc<Self>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ExportDefaultDeclaration { declaration }))
+    }
+
+    pub fn new_export_all_declaration(source: String) -> Option<Rc<Self>> {
+        Some(Rc::new(Node::ExportAllDeclaration { source }))
+    }
+}
+
+fn declaration_kind(keyword: &str) -> Option<DeclarationKind> {
+    match keyword {
+        "var" => Some(DeclarationKind::Var),
+        "let" => Some(DeclarationKind::Let),
+        "const" => Some(DeclarationKind::Const),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// 構文エラー。最初に見つかったエラーでパースを終える
+pub struct SyntaxError {
+    message: String,
+    position: Position,
+}
+
+impl SyntaxError {
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "SyntaxError: {} ({})", self.message, self.position)
+    }
+}
+
+// 先読みしたトークン
+struct PeekedToken {
+    token: Token,
+    span: Span,
+    // トークンの前に改行があったか
+    newline_before: bool,
+    // /から始まるトークンを読み直すための状態
+    rewind: Option<Rewind>,
+}
+
+// 先読みできるトークン列。構文エラーの位置を知るため、トークンの位置も保持する
+struct TokenStream {
+    lexer: JsLexer,
+    // 先読みしたトークン。async functionを見分けるため、2つまで先読みする
+    peeked: VecDeque<Option<PeekedToken>>,
+    // 最後に消費したトークンの位置
+    position: Position,
+    // 構文エラーが見つかった後は、パースを終えるためにトークンを返さない
+    finished: bool,
+}
+
+impl TokenStream {
+    fn new(lexer: JsLexer) -> Self {
+        Self {
+            lexer,
+            peeked: VecDeque::new(),
+            position: Position::new(1, 1),
+            finished: false,
+        }
+    }
+
+    fn read(&mut self) -> Option<PeekedToken> {
+        let (token, span) = self.lexer.next_token()?;
+        Some(PeekedToken {
+            token,
+            span,
+            newline_before: self.lexer.newline_before(),
+            rewind: self.lexer.take_rewind(),
+        })
+    }
+
+    // 次のトークンが/から始まる場合、パーサーが求める読み方で読み直す
+    // レキサーは直前のトークンから読み方を推測するため、if (x) /a/のような正規表現リテラルを除算として読むことがある
+    fn read_again(&mut self, goal: Goal) {
+        if self.finished {
+            return;
+        }
+        self.peek();
+        let rewind = match self.peeked.front() {
+            Some(Some(PeekedToken {
+                rewind: Some(rewind),
+                ..
+            })) if rewind.goal() != goal => rewind.clone(),
+            _ => return,
+        };
+        // 後ろに先読みしたトークンは、読み直したトークンの続きから読み直す
+        self.peeked.clear();
+        let (token, span) = self.lexer.read_again(rewind, goal);
+        self.peeked.push_back(Some(PeekedToken {
+            token,
+            span,
+            newline_before: self.lexer.newline_before(),
+            rewind: self.lexer.take_rewind(),
+        }));
+    }
+
+    // n番目(0から数える)のトークンを先読みする
+    fn peek_nth(&mut self, n: usize) -> Option<&Token> {
+        if self.finished {
+            return None;
+        }
+        while self.peeked.len() <= n {
+            let token = self.read();
+            self.peeked.push_back(token);
+        }
+        self.peeked[n].as_ref().map(|peeked| &peeked.token)
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        if self.finished {
+            return None;
+        }
+        let next = match self.peeked.pop_front() {
+            Some(peeked) => peeked,
+            None => self.read(),
+        };
+        next.map(|peeked| {
+            self.position = peeked.span.start;
+            peeked.token
+        })
+    }
+
+    // 次のトークンの位置。トークンが残っていない場合は最後に消費したトークンの位置
+    fn position(&mut self) -> Position {
+        self.peek();
+        match self.peeked.front() {
+            Some(Some(peeked)) => peeked.span.start,
+            _ => self.position,
+        }
+    }
+
+    // 次のトークンの前に改行があるか
+    fn newline_before(&mut self) -> bool {
+        self.peek();
+        matches!(self.peeked.front(), Some(Some(peeked)) if peeked.newline_before)
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+// 式と文の入れ子の深さの上限。これより深いASTはパース、コンパイル、評価でスタックを使い切るため構文エラーにする
+// a + b + cのように同じ優先順位の演算子が続く場合は、左側に積み上げても深さとして数えない
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+// +=のような複合代入の演算子
+static COMPOUND_ASSIGNMENT_OPERATORS: [&str; 12] = [
+    "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=",
+];
+
+pub struct JsParser {
+    t: TokenStream,
+    error: Option<SyntaxError>,
+    // async関数の中ではawait、ジェネレータの中ではyieldを式として読む
+    in_async: bool,
+    in_generator: bool,
+    // モジュールとしてパースする場合、トップレベルのimportとexportを読む
+    in_module: bool,
+    // breakとcontinueで移動できる文。関数の中に入ると空になる
+    jumps: JumpTargets,
+    // 読んでいる途中の式と文の入れ子の深さ
+    depth: usize,
+    // forの初期化式を読んでいる間は、inを演算子ではなくfor-inの区切りとして扱う
+    no_in: bool,
+    // 外側から順に、読んでいる途中のスコープで宣言された名前
+    scopes: Vec<DeclaredNames>,
+}
+
+#[derive(Debug, Clone, Default)]
+// 1つのスコープで宣言された名前。letとconstとクラスは同じスコープで宣言し直せない
+struct DeclaredNames {
+    lexical: Vec<String>,
+    // varと仮引数の名前。内側のブロックで宣言されたvarも含む
+    vars: Vec<String>,
+    // 関数とプログラムのスコープ。varはここまで巻き上げられる
+    is_function: bool,
+}
+
+impl DeclaredNames {
+    fn new(is_function: bool, vars: Vec<String>) -> Self {
+        Self {
+            lexical: Vec::new(),
+            vars,
+            is_function,
+        }
+    }
+}
+
+// 仮引数の名前
+fn parameter_names(params: &[Option<Rc<Node>>]) -> Vec<String> {
+    params
+        .iter()
+        .filter_map(|param| match param.as_deref() {
+            Some(Node::Identifier(name)) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// 代入できる式か。変数とプロパティにだけ代入できる
+fn is_assignment_target(node: &Option<Rc<Node>>) -> bool {
+    matches!(
+        node.as_deref(),
+        Some(Node::Identifier(_)) | Some(Node::MemberExpression { .. })
+    )
+}
+
+#[derive(Debug, Clone, Default)]
+// 外側にある繰り返し文、switch文、ラベル
+struct JumpTargets {
+    loops: usize,
+    switches: usize,
+    // ラベルと、そのラベルが繰り返し文に付いているか
+    labels: Vec<(String, bool)>,
+}
+
+impl JsParser {
+    pub fn new(t: JsLexer) -> Self {
+        Self {
+            t: TokenStream::new(t),
+            error: None,
+            in_async: false,
+            in_generator: false,
+            in_module: false,
+            jumps: JumpTargets::default(),
+            depth: 0,
+            no_in: false,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn parse_ast(&mut self) -> Result<Program, SyntaxError> {
+        let mut program = Program::new();
+        self.scopes = Vec::from([DeclaredNames::new(true, Vec::new())]);
+
+        let mut body = Vec::new();
+
+        loop {
+            let node = if self.in_module {
+                self.module_item()
+            } else {
+                self.source_element()
+            };
+
+            if let Some(error) = self.error.take() {
+                return Err(error);
+            }
+
+            match node {
+                Some(n) => body.push(n),
+                None => {
+                    program.set_body(body);
+                    return Ok(program);
+                }
+            }
+        }
+    }
+
+    // ESモジュールとしてパースする。トップレベルでimportとexportを使える
+    pub fn parse_module(&mut self) -> Result<Program, SyntaxError> {
+        self.in_module = true;
+        self.t.lexer.set_module();
+        let result = self.parse_ast();
+        self.in_module = false;
+        result
+    }
+
+    // 構文エラーを記録し、残りのトークンを読まずにパースを終える
+    fn syntax_error(&mut self, message: String, position: Position) -> Option<Rc<Node>> {
+        if self.error.is_none() {
+            self.error = Some(SyntaxError { message, position });
+        }
+        self.t.finish();
+        None
+    }
+
+    // 入れ子を1段深くする。上限を超える場合は構文エラーにしてfalseを返す
+    fn deepen(&mut self) -> bool {
+        if self.depth >= MAX_NESTING_DEPTH {
+            let position = self.t.position();
+            self.syntax_error("too deeply nested".to_string(), position);
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
+    // 入れ子を1段深くしてparseを呼ぶ
+    fn nested(&mut self, parse: fn(&mut Self) -> Option<Rc<Node>>) -> Option<Rc<Node>> {
+        if !self.deepen() {
+            return None;
+        }
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    // 次のトークンが予期しないものだった場合の構文エラー
+    // 新しいスコープの中でparseを呼び出す
+    fn scoped(
+        &mut self,
+        scope: DeclaredNames,
+        parse: impl FnOnce(&mut Self) -> Option<Rc<Node>>,
+    ) -> Option<Rc<Node>> {
+        self.scopes.push(scope);
+        let node = parse(self);
+        self.scopes.pop();
+        node
+    }
+
+    // let、const、クラスの名前を宣言する。同じスコープで宣言済みの場合は構文エラーにする
+    fn declare_lexical(&mut self, name: &str, position: Position) {
+        let scope = self.scopes.last_mut().expect("scope should exist");
+        if scope.lexical.iter().chain(&scope.vars).any(|n| n == name) {
+            self.redeclaration_error(name, position);
+            return;
+        }
+        scope.lexical.push(name.to_string());
+    }
+
+    // varの名前を関数のスコープまで巻き上げて宣言する。途中のスコープのletやconstとは重複できない
+    fn declare_var(&mut self, name: &str, position: Position) {
+        for i in (0..self.scopes.len()).rev() {
+            let scope = &mut self.scopes[i];
+            if scope.lexical.iter().any(|n| n == name) {
+                self.redeclaration_error(name, position);
+                return;
+            }
+            scope.vars.push(name.to_string());
+            if scope.is_function {
+                break;
+            }
+        }
+    }
+
+    fn redeclaration_error(&mut self, name: &str, position: Position) {
+        self.syntax_error(
+            format!("identifier '{}' has already been declared", name),
+            position,
+        );
+    }
+
+    fn unexpected_token(&mut self) -> Option<Rc<Node>> {
+        let position = self.t.position();
+        let message = match self.t.peek() {
+            // 字句のエラーはそのメッセージを使う
+            Some(Token::Error(message)) => message.clone(),
+            Some(t) => format!("unexpected token '{}'", t),
+            None => "unexpected end of input".to_string(),
+        };
+        self.syntax_error(message, position)
+    }
+
+    // 文の終わりの;を消費する。;がなくても、}や入力の終わりの前と、改行の後では;が自動的に挿入される
+    fn consume_semicolon(&mut self) {
+        let newline = self.t.newline_before();
+        match self.t.peek() {
+            Some(Token::Punctuator(';')) => {
+                assert!(self.t.next().is_some());
+            }
+            Some(Token::Punctuator('}')) | None => {}
+            Some(_) if newline => {}
+            Some(_) => {
+                self.unexpected_token();
+            }
+        }
+    }
+
+    // SourceElementはStatementで構成されることを表現
+    fn source_element(&mut self) -> Option<Rc<Node>> {
+        // async function f() {}
+        if self.is_next_contextual("async")
+            && matches!(self.t.peek_nth(1), Some(Token::Keyword(k)) if k == "function")
+        {
+            assert!(self.t.next().is_some());
+            assert!(self.t.next().is_some());
+            return self.function_declaration(true);
+        }
+
+        let t = match self.t.peek() {
+            Some(t) => t,
+            None => return None,
+        };
+
+        match t {
+            Token::Keyword(keyword) => {
+                if keyword == "function" {
+                    assert!(self.t.next().is_some());
+                    self.function_declaration(false)
+                } else if keyword == "class" {
+                    assert!(self.t.next().is_some());
+                    let position = self.t.position();
+                    let id = self.identifier();
+                    if let Some(Node::Identifier(name)) = id.as_deref() {
+                        self.declare_lexical(name, position);
+                    }
+                    let (super_class, body) = self.class_tail();
+                    Node::new_class_declaration(id, super_class, body)
+                } else {
+                    self.statement()
+                }
+            }
+            _ => self.statement(),
+        }
+    }
+
+    // モジュールのトップレベルの文。import文とexport文はここでだけ読める
+    fn module_item(&mut self) -> Option<Rc<Node>> {
+        let keyword = match self.t.peek() {
+            Some(Token::Keyword(keyword)) => keyword.clone(),
+            _ => return self.source_element(),
+        };
+        let node = if keyword == "import" {
+            assert!(self.t.next().is_some());
+            self.import_declaration()
+        } else if keyword == "export" {
+            assert!(self.t.next().is_some());
+            self.export_declaration()
+        } else {
+            return self.source_element();
+        };
+        // 宣言を含まないimport文とexport文は;で終わる
+        let has_declaration = match node.as_deref() {
+            Some(Node::ExportNamedDeclaration { declaration, .. }) => declaration.is_some(),
+            Some(Node::ExportDefaultDeclaration { declaration }) => matches!(
+                declaration.as_deref(),
+                Some(Node::FunctionDeclaration { .. }) | Some(Node::ClassDeclaration { .. })
+            ),
+            _ => false,
+        };
+        if !has_declaration {
+            self.consume_semicolon();
+        }
+        node
+    }
+
+    // importの後から読む
+    fn import_declaration(&mut self) -> Option<Rc<Node>> {
+        // import "m";は読み込んだモジュールを評価するだけ
+        if let Some(Token::StringLiteral(_)) = self.t.peek() {
+            let source = self.module_specifier()?;
+            return Node::new_import_declaration(Vec::new(), source);
+        }
+
+        let mut specifiers = Vec::new();
+        // import d from "m"はデフォルトエクスポートを読み込む
+        if let Some(Token::Identifier(_)) = self.t.peek() {
+            let local = self.binding_name()?;
+            specifiers.push(("default".to_string(), local));
+            if !self.is_next_punctuator(',') {
+                return self.import_from(specifiers);
+            }
+            assert!(self.t.next().is_some());
+        }
+
+        if self.is_next_punctuator('*') {
+            // import * as ns from "m"
+            assert!(self.t.next().is_some());
+            if !self.consume_contextual("as") {
+                return None;
+            }
+            let local = self.binding_name()?;
+            specifiers.push(("*".to_string(), local));
+        } else {
+            // import {a, b as c} from "m"
+            for (imported, local) in self.specifier_list()? {
+                specifiers.push((imported, local));
+            }
+        }
+        self.import_from(specifiers)
+    }
+
+    fn import_from(&mut self, specifiers: Vec<(String, String)>) -> Option<Rc<Node>> {
+        if !self.consume_contextual("from") {
+            return None;
+        }
+        let source = self.module_specifier()?;
+        Node::new_import_declaration(specifiers, source)
+    }
+
+    // exportの後から読む
+    fn export_declaration(&mut self) -> Option<Rc<Node>> {
+        if self.is_next_punctuator('*') {
+            // export * from "m"
+            assert!(self.t.next().is_some());
+            if !self.consume_contextual("from") {
+                return None;
+            }
+            let source = self.module_specifier()?;
+            return Node::new_export_all_declaration(source);
+        }
+
+        if self.is_next_punctuator('{') {
+            // export {a, b as c}とexport {a} from "m"
+            let specifiers = self.specifier_list()?;
+            let source = if self.is_next_contextual("from") {
+                assert!(self.t.next().is_some());
+                Some(self.module_specifier()?)
+            } else {
+                None
+            };
+            return Node::new_export_named_declaration(None, specifiers, source);
+        }
+
+        if self.is_next_contextual("default") {
+            assert!(self.t.next().is_some());
+            return self.export_default_declaration();
+        }
+
+        // export var a = 1;、export function f() {}、export class C {}
+        let is_declaration = match self.t.peek() {
+            Some(Token::Keyword(keyword)) => {
+                declaration_kind(keyword).is_some() || keyword == "function" || keyword == "class"
+            }
+            Some(Token::Identifier(name)) => {
+                name == "async"
+                    && matches!(self.t.peek_nth(1), Some(Token::Keyword(k)) if k == "function")
+            }
+            _ => false,
+        };
+        if !is_declaration {
+            return self.unexpected_token();
+        }
+        let declaration = self.source_element();
+        Node::new_export_named_declaration(declaration, Vec::new(), None)
+    }
+
+    // export defaultの後の関数宣言とクラス宣言は名前を省略できる
+    fn export_default_declaration(&mut self) -> Option<Rc<Node>> {
+        let is_async = self.is_next_contextual("async")
+            && matches!(self.t.peek_nth(1), Some(Token::Keyword(k)) if k == "function");
+        if is_async {
+            assert!(self.t.next().is_some());
+        }
+        let declaration = match self.t.peek() {
+            Some(Token::Keyword(keyword)) if keyword == "function" => {
+                assert!(self.t.next().is_some());
+                let is_generator = self.generator_star(is_async);
+                let id = self.optional_identifier();
+                let params = self.parameter_list();
+                let body = self.function_body_of(&params, is_async, is_generator);
+                Node::new_function_declaration(id, params, body, is_async, is_generator)
+            }
+            Some(Token::Keyword(keyword)) if keyword == "class" => {
+                assert!(self.t.next().is_some());
+                let id = self.optional_identifier();
+                let (super_class, body) = self.class_tail();
+                Node::new_class_declaration(id, super_class, body)
+            }
+            _ => self.assignment_expression(),
+        };
+        Node::new_export_default_declaration(declaration)
+    }
+
+    fn optional_identifier(&mut self) -> Option<Rc<Node>> {
+        match self.t.peek() {
+            Some(Token::Identifier(_)) => self.identifier(),
+            _ => None,
+        }
+    }
+
+    // {a, b as c}を読み、(元の名前, 別名)の組を返す。別名を省略した場合は元の名前と同じ
+    fn specifier_list(&mut self) -> Option<Vec<(String, String)>> {
+        if !self.consume_punctuator('{') {
+            return None;
+        }
+        let mut specifiers = Vec::new();
+        loop {
+            if self.is_next_punctuator('}') {
+                assert!(self.t.next().is_some());
+                return Some(specifiers);
+            }
+            // defaultのような予約語も名前として使える
+            let name = self.property_name()?;
+            let alias = if self.is_next_contextual("as") {
+                assert!(self.t.next().is_some());
+                self.property_name()?
+            } else {
+                name.clone()
+            };
+            specifiers.push((name, alias));
+            if !self.is_next_punctuator('}') && !self.consume_punctuator(',') {
+                return None;
+            }
+        }
+    }
+
+    // importで宣言される変数名
+    fn binding_name(&mut self) -> Option<String> {
+        match self.identifier().as_deref() {
+            Some(Node::Identifier(name)) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    // fromやasのような、文脈によって予約語になる名前を消費する。違う場合は構文エラー
+    fn consume_contextual(&mut self, name: &str) -> bool {
+        if self.is_next_contextual(name) {
+            assert!(self.t.next().is_some());
+            return true;
+        }
+        self.unexpected_token();
+        false
+    }
+
+    // "./m.js"のようなモジュールの指定子
+    fn module_specifier(&mut self) -> Option<String> {
+        match self.t.peek() {
+            Some(Token::StringLiteral(_)) => match self.t.next() {
+                Some(Token::StringLiteral(source)) => Some(source),
+                _ => None,
+            },
+            _ => {
+                self.unexpected_token();
+                None
+            }
+        }
+    }
+
+    fn function_declaration(&mut self, is_async: bool) -> Option<Rc<Node>> {
+        let is_generator = self.generator_star(is_async);
+        let id = self.identifier();
+        let params = self.parameter_list();
+        let body = self.function_body_of(&params, is_async, is_generator);
+        Node::new_function_declaration(id, params, body, is_async, is_generator)
+    }
+
+    // function*の*を読む。asyncのジェネレータには対応していない
+    fn generator_star(&mut self, is_async: bool) -> bool {
+        if !self.is_next_punctuator('*') {
+            return false;
+        }
+        if is_async {
+            let position = self.t.position();
+            self.syntax_error("async generators are not supported".to_string(), position);
+            return false;
+        }
+        assert!(self.t.next().is_some());
+        true
+    }
+
+    fn parameter_list(&mut self) -> Vec<Option<Rc<Node>>> {
+        let mut params = Vec::new();
+
+        // ( を消費する。次のトークンが(でない場合エラー
+        if !self.consume_punctuator('(') {
+            return params;
+        }
 
         loop {
             // ) に到達するまでparamsに仮引数となる変数を追加する
@@ -210,45 +1460,391 @@ impl JsParser {
                             assert!(self.t.next().is_some());
                             return params;
                         }
-                        if c == &',' {
-                            assert!(self.t.next().is_some());
+                        if c != &',' {
+                            self.unexpected_token();
+                            return params;
                         }
+                        assert!(self.t.next().is_some());
                     }
                     _ => params.push(self.identifier()),
                 },
-                None => return params,
+                None => {
+                    self.unexpected_token();
+                    return params;
+                }
+            }
+        }
+    }
+
+    fn function_body(&mut self) -> Option<Rc<Node>> {
+        if !self.consume_punctuator('{') {
+            return None;
+        }
+
+        self.statement_list()
+    }
+
+    // 関数の本体を読む。awaitとyieldを式として読むかは、その関数の種類で決まる
+    fn function_body_of(
+        &mut self,
+        params: &[Option<Rc<Node>>],
+        is_async: bool,
+        is_generator: bool,
+    ) -> Option<Rc<Node>> {
+        let outer = (self.in_async, self.in_generator);
+        self.in_async = is_async;
+        self.in_generator = is_generator;
+        let jumps = core::mem::take(&mut self.jumps);
+        let no_in = core::mem::replace(&mut self.no_in, false);
+        let scope = DeclaredNames::new(true, parameter_names(params));
+        let body = self.scoped(scope, Self::function_body);
+        (self.in_async, self.in_generator) = outer;
+        self.jumps = jumps;
+        self.no_in = no_in;
+        body
+    }
+
+    // 次のトークンが文脈によって予約語になる名前か
+    fn is_next_contextual(&mut self, name: &str) -> bool {
+        matches!(self.t.peek(), Some(Token::Identifier(n)) if n == name)
+    }
+
+    // {の後から}までの文を読み、BlockStatementを返す
+    fn statement_list(&mut self) -> Option<Rc<Node>> {
+        let mut body = Vec::new();
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator('}')) => {
+                    assert!(self.t.next().is_some());
+                    return Node::new_block_statement(body);
+                }
+                Some(_) => {}
+                // }で閉じられていない
+                None => return self.unexpected_token(),
+            }
+            body.push(self.source_element());
+        }
+    }
+
+    // 次のトークンが記号cであることを確認して消費する。違う場合は構文エラーにしてfalseを返す
+    fn consume_punctuator(&mut self, c: char) -> bool {
+        if self.is_next_punctuator(c) {
+            assert!(self.t.next().is_some());
+            return true;
+        }
+        self.unexpected_token();
+        false
+    }
+
+    fn is_next_punctuator(&mut self, c: char) -> bool {
+        matches!(self.t.peek(), Some(Token::Punctuator(p)) if *p == c)
+    }
+
+    fn if_statement(&mut self) -> Option<Rc<Node>> {
+        self.consume_punctuator('(');
+        let test = self.expression();
+        self.consume_punctuator(')');
+        let consequent = self.statement();
+
+        let alternate = match self.t.peek() {
+            Some(Token::Keyword(keyword)) if keyword == "else" => {
+                // elseの予約語を消費
+                assert!(self.t.next().is_some());
+                self.statement()
+            }
+            _ => None,
+        };
+
+        Node::new_if_statement(test, consequent, alternate)
+    }
+
+    fn try_statement(&mut self) -> Option<Rc<Node>> {
+        let block = self.scoped(DeclaredNames::default(), Self::function_body);
+
+        let mut param = None;
+        let mut handler = None;
+        if matches!(self.t.peek(), Some(Token::Keyword(keyword)) if keyword == "catch") {
+            assert!(self.t.next().is_some());
+            // catch {}のように仮引数を省略できる
+            if self.is_next_punctuator('(') {
+                assert!(self.t.next().is_some());
+                param = self.identifier();
+                self.consume_punctuator(')');
+            }
+            // catchの仮引数はブロックの中のletやconstと重複できない
+            let scope = DeclaredNames::new(false, parameter_names(&[param.clone()]));
+            handler = self.scoped(scope, Self::function_body);
+        }
+
+        let mut finalizer = None;
+        if matches!(self.t.peek(), Some(Token::Keyword(keyword)) if keyword == "finally") {
+            assert!(self.t.next().is_some());
+            finalizer = self.scoped(DeclaredNames::default(), Self::function_body);
+        }
+
+        // catchとfinallyの少なくとも一方が必要
+        if handler.is_none() && finalizer.is_none() {
+            return self.unexpected_token();
+        }
+
+        Node::new_try_statement(block, param, handler, finalizer)
+    }
+
+    // 繰り返し文の本体。本体の中ではラベルのないbreakとcontinueを使える
+    fn loop_body(&mut self) -> Option<Rc<Node>> {
+        self.jumps.loops += 1;
+        let body = self.statement();
+        self.jumps.loops -= 1;
+        body
+    }
+
+    fn while_statement(&mut self) -> Option<Rc<Node>> {
+        self.consume_punctuator('(');
+        let test = self.expression();
+        self.consume_punctuator(')');
+        Node::new_while_statement(test, self.loop_body())
+    }
+
+    fn do_while_statement(&mut self) -> Option<Rc<Node>> {
+        let body = self.loop_body();
+        if !matches!(self.t.peek(), Some(Token::Keyword(keyword)) if keyword == "while") {
+            return self.unexpected_token();
+        }
+        assert!(self.t.next().is_some());
+        self.consume_punctuator('(');
+        let test = self.expression();
+        self.consume_punctuator(')');
+        // do-whileの後の;は、改行がなくても省略できる
+        if self.is_next_punctuator(';') {
+            assert!(self.t.next().is_some());
+        }
+        Node::new_do_while_statement(body, test)
+    }
+
+    // for (の後にinかofが続く場合、左辺を確かめてfor-inかfor-ofの文を読む
+    fn for_in_of_statement(&mut self, init: Option<Rc<Node>>) -> Option<Option<Rc<Node>>> {
+        let is_of = match self.t.peek() {
+            Some(Token::Keyword(keyword)) if keyword == "in" => false,
+            Some(Token::Identifier(name)) if name == "of" => true,
+            _ => return None,
+        };
+        let position = self.t.position();
+        assert!(self.t.next().is_some());
+
+        let left = match init.as_deref() {
+            // for (const x of xs)のように、初期値のない変数を1つだけ宣言できる
+            Some(Node::VariableDeclaration { declarations, .. })
+                if declarations.len() == 1
+                    && matches!(
+                        declarations[0].as_deref(),
+                        Some(Node::VariableDeclarator { init: None, .. })
+                    ) =>
+            {
+                init
+            }
+            Some(Node::ExpressionStatement(Some(expression)))
+                if matches!(
+                    expression.as_ref(),
+                    Node::Identifier(_) | Node::MemberExpression { .. }
+                ) =>
+            {
+                Some(expression.clone())
+            }
+            _ => {
+                return Some(
+                    self.syntax_error("invalid left-hand side in for loop".to_string(), position),
+                )
+            }
+        };
+        let right = self.assignment_expression();
+        self.consume_punctuator(')');
+        Some(Node::new_for_in_of_statement(
+            left,
+            right,
+            self.loop_body(),
+            is_of,
+        ))
+    }
+
+    fn for_statement(&mut self) -> Option<Rc<Node>> {
+        self.consume_punctuator('(');
+
+        self.no_in = true;
+        let init = match self.t.peek() {
+            Some(Token::Punctuator(';')) => None,
+            Some(Token::Keyword(keyword)) if declaration_kind(keyword).is_some() => {
+                let kind = declaration_kind(keyword).expect("declaration kind should exist");
+                assert!(self.t.next().is_some());
+                self.variable_declaration(kind)
+            }
+            _ => Node::new_expression_statement(self.expression()),
+        };
+        self.no_in = false;
+        if let Some(statement) = self.for_in_of_statement(init.clone()) {
+            return statement;
+        }
+        self.consume_punctuator(';');
+
+        let test = if self.is_next_punctuator(';') {
+            None
+        } else {
+            self.expression()
+        };
+        self.consume_punctuator(';');
+
+        let update = if self.is_next_punctuator(')') {
+            None
+        } else {
+            self.expression()
+        };
+        self.consume_punctuator(')');
+
+        Node::new_for_statement(init, test, update, self.loop_body())
+    }
+
+    fn switch_statement(&mut self) -> Option<Rc<Node>> {
+        self.consume_punctuator('(');
+        let discriminant = self.expression();
+        self.consume_punctuator(')');
+        if !self.consume_punctuator('{') {
+            return None;
+        }
+
+        self.jumps.switches += 1;
+        let mut cases = Vec::new();
+        let mut has_default = false;
+        loop {
+            let is_default = !has_default && self.is_next_default_clause();
+            let test = match self.t.peek() {
+                Some(Token::Punctuator('}')) => {
+                    assert!(self.t.next().is_some());
+                    break;
+                }
+                Some(Token::Keyword(keyword)) if keyword == "case" => {
+                    assert!(self.t.next().is_some());
+                    self.expression()
+                }
+                // default節は1つだけ書ける
+                _ if is_default => {
+                    assert!(self.t.next().is_some());
+                    has_default = true;
+                    None
+                }
+                _ => {
+                    self.unexpected_token();
+                    break;
+                }
+            };
+            self.consume_punctuator(':');
+
+            let mut consequent = Vec::new();
+            loop {
+                let is_default = self.is_next_default_clause();
+                match self.t.peek() {
+                    Some(Token::Keyword(keyword)) if keyword == "case" => break,
+                    Some(Token::Punctuator('}')) | None => break,
+                    _ if is_default => break,
+                    _ => consequent.push(self.source_element()),
+                }
             }
+            cases.push(Node::new_switch_case(test, consequent));
         }
+        self.jumps.switches -= 1;
+
+        Node::new_switch_statement(discriminant, cases)
     }
 
-    fn function_body(&mut self) -> Option<Rc<Node>> {
-        match self.t.next() {
-            Some(t) => match t {
-                Token::Punctuator(c) => assert!(c == '{'),
-                _ => unimplemented!("function should have open curly blacket but got {:?}", t),
-            },
-            None => unimplemented!("function should have open curly blacket but got None"),
+    // defaultは予約語として扱わないため、後に:が続く場合だけdefault節とする
+    fn is_next_default_clause(&mut self) -> bool {
+        self.is_next_contextual("default")
+            && matches!(self.t.peek_nth(1), Some(Token::Punctuator(':')))
+    }
+
+    // label: bodyを読む。ラベルが繰り返し文に付いている場合、continueでも指定できる
+    fn labeled_statement(&mut self, label: String) -> Option<Rc<Node>> {
+        let position = self.t.position();
+        // ラベルの名前と:を消費する
+        assert!(self.t.next().is_some());
+        assert!(self.t.next().is_some());
+        if self.jumps.labels.iter().any(|(l, _)| *l == label) {
+            return self.syntax_error(
+                format!("label '{}' has already been declared", label),
+                position,
+            );
         }
 
-        let mut body = Vec::new();
-        loop {
-            match self.t.peek() {
-                Some(t) => match t {
-                    Token::Punctuator(c) => {
-                        if c == &'}' {
-                            assert!(self.t.next().is_some());
-                            return Node::new_block_statement(body);
-                        }
-                    }
-                    _ => {}
-                },
-                None => {}
+        // a: b: while () {}のように続くラベルも同じ繰り返し文に付く
+        let mut n = 0;
+        while matches!(self.t.peek_nth(n), Some(Token::Identifier(_)))
+            && matches!(self.t.peek_nth(n + 1), Some(Token::Punctuator(':')))
+        {
+            n += 2;
+        }
+        let is_loop = matches!(
+            self.t.peek_nth(n),
+            Some(Token::Keyword(keyword)) if ["do", "while", "for"].contains(&keyword.as_str())
+        );
+
+        self.jumps.labels.push((label.clone(), is_loop));
+        let body = self.statement();
+        self.jumps.labels.pop();
+        Node::new_labeled_statement(label, body)
+    }
+
+    // breakとcontinueの後のラベル。改行の後の名前は次の文として扱う
+    fn jump_statement(&mut self, is_continue: bool) -> Option<Rc<Node>> {
+        let position = self.t.position();
+        // breakかcontinueの予約語を消費する
+        assert!(self.t.next().is_some());
+
+        let newline = self.t.newline_before();
+        let label = match self.t.peek() {
+            Some(Token::Identifier(name)) if !newline => Some(name.clone()),
+            _ => None,
+        };
+        let error = match &label {
+            Some(label) => {
+                assert!(self.t.next().is_some());
+                match self.jumps.labels.iter().find(|(l, _)| l == label) {
+                    Some((_, false)) if is_continue => Some(format!(
+                        "label '{}' is not on an iteration statement",
+                        label
+                    )),
+                    Some(_) => None,
+                    None => Some(format!("undefined label '{}'", label)),
+                }
             }
-            body.push(self.source_element());
+            None if is_continue && self.jumps.loops == 0 => {
+                Some("illegal continue statement".to_string())
+            }
+            None if self.jumps.loops == 0 && self.jumps.switches == 0 => {
+                Some("illegal break statement".to_string())
+            }
+            None => None,
+        };
+        if let Some(message) = error {
+            return self.syntax_error(message, position);
+        }
+
+        if is_continue {
+            Some(Rc::new(Node::ContinueStatement { label }))
+        } else {
+            Some(Rc::new(Node::BreakStatement { label }))
         }
     }
 
     fn statement(&mut self) -> Option<Rc<Node>> {
+        self.nested(Self::statement_body)
+    }
+
+    fn statement_body(&mut self) -> Option<Rc<Node>> {
+        if let Some(Token::Identifier(name)) = self.t.peek() {
+            let label = name.clone();
+            if matches!(self.t.peek_nth(1), Some(Token::Punctuator(':'))) {
+                return self.labeled_statement(label);
+            }
+        }
+
         let t = match self.t.peek() {
             Some(t) => t,
             None => return None,
@@ -256,92 +1852,360 @@ impl JsParser {
 
         let node = match t {
             Token::Keyword(keyword) => {
-                if keyword == "var" {
-                    // varの予約語を消費
+                if let Some(kind) = declaration_kind(keyword) {
+                    // var、let、constの予約語を消費
                     assert!(self.t.next().is_some());
 
-                    self.variable_declaration()
+                    self.variable_declaration(kind)
                 } else if keyword == "return" {
                     // return の予約語を消費する
                     assert!(self.t.next().is_some());
 
-                    Node::new_return_statement(self.assignment_expression())
+                    // return;のように値が省略された場合はundefinedを返す。returnの後の改行も;になる
+                    let newline = self.t.newline_before();
+                    match self.t.peek() {
+                        Some(Token::Punctuator(';')) | Some(Token::Punctuator('}')) | None => {
+                            Node::new_return_statement(None)
+                        }
+                        _ if newline => Node::new_return_statement(None),
+                        _ => Node::new_return_statement(self.expression()),
+                    }
+                } else if keyword == "if" {
+                    assert!(self.t.next().is_some());
+                    return self.if_statement();
+                } else if keyword == "while" {
+                    assert!(self.t.next().is_some());
+                    return self.while_statement();
+                } else if keyword == "for" {
+                    assert!(self.t.next().is_some());
+                    // for (let i ...)のiはfor文のスコープで宣言される
+                    return self.scoped(DeclaredNames::default(), Self::for_statement);
+                } else if keyword == "do" {
+                    assert!(self.t.next().is_some());
+                    return self.do_while_statement();
+                } else if keyword == "switch" {
+                    assert!(self.t.next().is_some());
+                    return self.scoped(DeclaredNames::default(), Self::switch_statement);
+                } else if keyword == "break" {
+                    self.jump_statement(false)
+                } else if keyword == "continue" {
+                    self.jump_statement(true)
+                } else if keyword == "throw" {
+                    let position = self.t.position();
+                    assert!(self.t.next().is_some());
+                    if self.t.newline_before() {
+                        let position = self.t.position();
+                        return self
+                            .syntax_error("illegal newline after throw".to_string(), position);
+                    }
+                    Node::new_throw_statement(self.expression(), position)
+                } else if keyword == "try" {
+                    assert!(self.t.next().is_some());
+                    return self.try_statement();
                 } else {
-                    None
+                    // trueやthisのように式の一部となる予約語
+                    Node::new_expression_statement(self.expression())
                 }
             }
-            _ => Node::new_expression_statement(self.assignment_expression()),
+            Token::Punctuator('{') => {
+                assert!(self.t.next().is_some());
+                return self.scoped(DeclaredNames::default(), Self::statement_list);
+            }
+            // ;だけの空の文
+            Token::Punctuator(';') => Node::new_expression_statement(None),
+            _ => Node::new_expression_statement(self.expression()),
         };
 
-        if let Some(t) = self.t.peek() {
-            if let Token::Punctuator(c) = t {
-                // ';'を消費する
-                if c == &';' {
-                    assert!(self.t.next().is_some());
-                }
-            }
-        }
+        // ブロックで終わらない文は;で終わる
+        self.consume_semicolon();
         node
     }
 
+    // ,で区切られた式。1つだけの場合はその式を返す
+    fn expression(&mut self) -> Option<Rc<Node>> {
+        let first = self.assignment_expression();
+        if !self.is_next_punctuator(',') {
+            return first;
+        }
+        let mut expressions = Vec::from([first]);
+        while self.is_next_punctuator(',') {
+            assert!(self.t.next().is_some());
+            expressions.push(self.assignment_expression());
+        }
+        Node::new_sequence_expression(expressions)
+    }
+
     fn assignment_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.additive_expression();
+        self.nested(Self::assignment_expression_body)
+    }
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
-        };
+    fn assignment_expression_body(&mut self) -> Option<Rc<Node>> {
+        if self.in_generator && self.is_next_contextual("yield") {
+            assert!(self.t.next().is_some());
+            return self.yield_expression();
+        }
 
-        match t {
-            Token::Punctuator('=') => {
-                // = を消費する
-                assert!(self.t.next().is_some());
-                Node::new_assignment_expression('=', expr, self.assignment_expression())
+        let position = self.t.position();
+        let expr = self.conditional_expression();
+
+        // a += 1はa = a + 1と同じ値を代入する。値の後の/=は除算の代入
+        self.t.read_again(Goal::Div);
+        let operator = match self.t.peek() {
+            Some(Token::Punctuator('=')) => "=".to_string(),
+            Some(Token::MultiCharPunctuator(p))
+                if COMPOUND_ASSIGNMENT_OPERATORS.contains(&p.as_str()) =>
+            {
+                p[..p.len() - 1].to_string()
             }
-            _ => expr,
+            _ => return expr,
+        };
+        if !is_assignment_target(&expr) {
+            return self.syntax_error("invalid left-hand side in assignment".to_string(), position);
         }
+        // 代入演算子を消費する
+        assert!(self.t.next().is_some());
+        Node::new_assignment_expression(operator, expr, self.assignment_expression())
     }
 
-    fn additive_expression(&mut self) -> Option<Rc<Node>> {
-        let left = self.left_hand_side_expression();
+    // yieldの後の式を読む。)や;のように式が続かない場合は値を省略したものとして扱う
+    // yield*の後の式は省略できない
+    fn yield_expression(&mut self) -> Option<Rc<Node>> {
+        if self.is_next_punctuator('*') {
+            assert!(self.t.next().is_some());
+            return Node::new_yield_expression(self.assignment_expression(), true);
+        }
+        let argument = match self.t.peek() {
+            Some(Token::Punctuator(')' | ']' | '}' | ',' | ';' | ':')) | None => None,
+            _ => self.assignment_expression(),
+        };
+        Node::new_yield_expression(argument, false)
+    }
 
-        let t = match self.t.peek() {
-            Some(token) => token.clone(),
-            None => return left,
+    fn conditional_expression(&mut self) -> Option<Rc<Node>> {
+        let test = self.logical_or_expression();
+
+        if !self.is_next_punctuator('?') {
+            return test;
+        }
+        // ?を消費する
+        assert!(self.t.next().is_some());
+        let consequent = self.assignment_expression();
+        self.consume_punctuator(':');
+        let alternate = self.assignment_expression();
+
+        Node::new_conditional_expression(test, consequent, alternate)
+    }
+
+    // 次のトークンがoperatorsのいずれかの記号の場合、消費してその記号を返す
+    fn consume_operator(&mut self, operators: &[&str]) -> Option<String> {
+        // 演算子は値の後に現れるため、/は除算
+        self.t.read_again(Goal::Div);
+        let operator = match self.t.peek() {
+            Some(Token::Punctuator(c)) => c.to_string(),
+            Some(Token::MultiCharPunctuator(p)) => p.clone(),
+            // instanceofのように予約語の演算子
+            Some(Token::Keyword(k)) => k.clone(),
+            _ => return None,
         };
 
-        match t {
-            Token::Punctuator(c) => match c {
-                '+' | '-' => {
-                    // + or -の記号を消費する
-                    assert!(self.t.next().is_some());
-                    Node::new_additive_expression(c, left, self.assignment_expression())
-                }
-                _ => left,
-            },
-            _ => left,
+        if operators.contains(&operator.as_str()) {
+            assert!(self.t.next().is_some());
+            Some(operator)
+        } else {
+            None
         }
     }
 
-    fn left_hand_side_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.member_expression();
+    // a || b || cのように左側に積み上げたノードは、コンパイラとインタプリタが再帰せずに処理するため入れ子として数えない
+    fn logical_or_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.logical_and_expression();
+        while let Some(operator) = self.consume_operator(&["||"]) {
+            left = Node::new_logical_expression(operator, left, self.logical_and_expression());
+        }
+        left
+    }
 
-        let t = match self.t.peek() {
-            Some(token) => token,
-            None => return expr,
+    fn logical_and_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.bitwise_or_expression();
+        while let Some(operator) = self.consume_operator(&["&&"]) {
+            left = Node::new_logical_expression(operator, left, self.bitwise_or_expression());
+        }
+        left
+    }
+
+    fn bitwise_or_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.bitwise_xor_expression();
+        while let Some(operator) = self.consume_operator(&["|"]) {
+            left = Node::new_binary_expression(operator, left, self.bitwise_xor_expression());
+        }
+        left
+    }
+
+    fn bitwise_xor_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.bitwise_and_expression();
+        while let Some(operator) = self.consume_operator(&["^"]) {
+            left = Node::new_binary_expression(operator, left, self.bitwise_and_expression());
+        }
+        left
+    }
+
+    fn bitwise_and_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.equality_expression();
+        while let Some(operator) = self.consume_operator(&["&"]) {
+            left = Node::new_binary_expression(operator, left, self.equality_expression());
+        }
+        left
+    }
+
+    fn equality_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.relational_expression();
+        while let Some(operator) = self.consume_operator(&["==", "!=", "===", "!=="]) {
+            left = Node::new_equality_expression(operator, left, self.relational_expression());
+        }
+        left
+    }
+
+    fn relational_expression(&mut self) -> Option<Rc<Node>> {
+        let operators: &[&str] = if self.no_in {
+            &["<", ">", "<=", ">=", "instanceof"]
+        } else {
+            &["<", ">", "<=", ">=", "instanceof", "in"]
         };
+        let mut left = self.shift_expression();
+        while let Some(operator) = self.consume_operator(operators) {
+            left = Node::new_relational_expression(operator, left, self.shift_expression());
+        }
+        left
+    }
 
-        match t {
-            Token::Punctuator(c) => {
-                if c == &'(' {
-                    assert!(self.t.next().is_some());
-                    // 関数呼び出しのためCallExpressionノードを返す
-                    return Node::new_call_expression(expr, self.arguments());
-                }
-                expr
+    fn shift_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.additive_expression();
+        while let Some(operator) = self.consume_operator(&["<<", ">>", ">>>"]) {
+            left = Node::new_binary_expression(operator, left, self.additive_expression());
+        }
+        left
+    }
+
+    // 左結合になるように、同じ優先順位の演算子が続く間は左側に積み上げる
+    fn additive_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.multiplicative_expression();
+        while let Some(operator) = self.consume_operator(&["+", "-"]) {
+            let c = operator
+                .chars()
+                .next()
+                .expect("operator should not be empty");
+            left = Node::new_additive_expression(c, left, self.multiplicative_expression());
+        }
+        left
+    }
+
+    fn multiplicative_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.exponentiation_expression();
+        while let Some(operator) = self.consume_operator(&["*", "/", "%"]) {
+            let c = operator
+                .chars()
+                .next()
+                .expect("operator should not be empty");
+            left = Node::new_multiplicative_expression(c, left, self.exponentiation_expression());
+        }
+        left
+    }
+
+    // **は右結合。-2 ** 2のように単項演算子の直後に続けることはできない
+    fn exponentiation_expression(&mut self) -> Option<Rc<Node>> {
+        let is_unary = match self.t.peek() {
+            Some(Token::Punctuator(c)) => matches!(c, '!' | '-' | '+' | '~'),
+            Some(Token::Keyword(k)) => matches!(k.as_str(), "typeof" | "void" | "delete"),
+            _ => self.in_async && self.is_next_contextual("await"),
+        };
+        let left = self.unary_expression();
+        if !matches!(self.t.peek(), Some(Token::MultiCharPunctuator(p)) if p == "**") {
+            return left;
+        }
+        if is_unary {
+            let position = self.t.position();
+            return self.syntax_error(
+                "unary operator before '**' needs parentheses".to_string(),
+                position,
+            );
+        }
+        assert!(self.t.next().is_some());
+        let right = self.nested(Self::exponentiation_expression);
+        Node::new_binary_expression("**".to_string(), left, right)
+    }
+
+    fn unary_expression(&mut self) -> Option<Rc<Node>> {
+        if let Some(operator) =
+            self.consume_operator(&["!", "-", "+", "~", "typeof", "void", "delete"])
+        {
+            return Node::new_unary_expression(operator, self.nested(Self::unary_expression));
+        }
+        if let Some(operator) = self.consume_operator(&["++", "--"]) {
+            let c = operator
+                .chars()
+                .next()
+                .expect("operator should not be empty");
+            let position = self.t.position();
+            let argument = self.nested(Self::unary_expression);
+            if !is_assignment_target(&argument) {
+                return self.syntax_error(
+                    "invalid left-hand side expression in prefix operation".to_string(),
+                    position,
+                );
+            }
+            return Node::new_update_expression(c, true, argument);
+        }
+        if self.in_async && self.is_next_contextual("await") {
+            assert!(self.t.next().is_some());
+            return Node::new_await_expression(self.nested(Self::unary_expression));
+        }
+        self.postfix_expression()
+    }
+
+    // a++やa--。改行の後の++は次の文の前置演算子になる
+    fn postfix_expression(&mut self) -> Option<Rc<Node>> {
+        let position = self.t.position();
+        let expr = self.left_hand_side_expression();
+        if self.t.newline_before() {
+            return expr;
+        }
+        match self.consume_operator(&["++", "--"]) {
+            Some(_) if !is_assignment_target(&expr) => self.syntax_error(
+                "invalid left-hand side expression in postfix operation".to_string(),
+                position,
+            ),
+            Some(operator) => {
+                let c = operator
+                    .chars()
+                    .next()
+                    .expect("operator should not be empty");
+                Node::new_update_expression(c, false, expr)
+            }
+            None => expr,
+        }
+    }
+
+    fn left_hand_side_expression(&mut self) -> Option<Rc<Node>> {
+        let position = self.t.position();
+        let depth = self.depth;
+        let mut expr = self.member_expression();
+
+        loop {
+            if self.is_next_punctuator('(') {
+                assert!(self.t.next().is_some());
+                // 関数呼び出しのためCallExpressionノードを作る
+                expr = Node::new_call_expression(expr, self.arguments(), position);
+            } else if let Some(member) = self.member_access(expr.clone()) {
+                expr = member;
+            } else {
+                break;
+            }
+            if !self.deepen() {
+                break;
             }
-            _ => expr,
         }
+        self.depth = depth;
+        expr
     }
 
     fn arguments(&mut self) -> Vec<Option<Rc<Node>>> {
@@ -357,91 +2221,515 @@ impl JsParser {
                         }
                         if c == &',' {
                             assert!(self.t.next().is_some());
+                        } else {
+                            // f(-1)やf([1])のように記号から始まる引数
+                            arguments.push(self.assignment_expression());
                         }
                     }
                     _ => arguments.push(self.assignment_expression()),
                 },
-                None => return arguments,
+                None => {
+                    self.unexpected_token();
+                    return arguments;
+                }
             }
         }
     }
 
     fn member_expression(&mut self) -> Option<Rc<Node>> {
-        let expr = self.primary_expression();
+        let mut expr = if matches!(self.t.peek(), Some(Token::Keyword(k)) if k == "new") {
+            let position = self.t.position();
+            assert!(self.t.next().is_some());
+            // new a.B()のように、呼び出し以外のプロパティアクセスまでがコンストラクタになる
+            let callee = self.nested(Self::member_expression);
+            let arguments = if self.is_next_punctuator('(') {
+                assert!(self.t.next().is_some());
+                self.arguments()
+            } else {
+                Vec::new()
+            };
+            Node::new_new_expression(callee, arguments, position)
+        } else {
+            self.primary_expression()
+        };
 
-        let t = match self.t.peek() {
+        let depth = self.depth;
+        while let Some(member) = self.member_access(expr.clone()) {
+            expr = member;
+            if !self.deepen() {
+                break;
+            }
+        }
+        self.depth = depth;
+        expr
+    }
+
+    // 次のトークンが.か[の場合、objectのプロパティへのアクセスを読む
+    // テンプレートリテラルの場合、objectをタグにした呼び出しを読む
+    fn member_access(&mut self, object: Option<Rc<Node>>) -> Option<Option<Rc<Node>>> {
+        if self.is_next_punctuator('.') {
+            // '.'を消費する
+            assert!(self.t.next().is_some());
+            // o.defaultのように予約語もプロパティ名として使える
+            let property = match self.t.peek() {
+                Some(Token::Keyword(_)) => match self.t.next() {
+                    Some(Token::Keyword(name)) => Node::new_identifier(name),
+                    _ => None,
+                },
+                _ => self.identifier(),
+            };
+            return Some(Node::new_member_expression(object, property, false));
+        }
+
+        if self.is_next_punctuator('[') {
+            // '['を消費する
+            assert!(self.t.next().is_some());
+            let property = self.expression();
+            self.consume_punctuator(']');
+            return Some(Node::new_member_expression(object, property, true));
+        }
+
+        if matches!(self.t.peek(), Some(Token::Template { head: true, .. })) {
+            return Some(self.tagged_template(object));
+        }
+
+        None
+    }
+
+    fn is_next_arrow(&mut self) -> bool {
+        matches!(self.t.peek(), Some(Token::MultiCharPunctuator(p)) if p == "=>")
+    }
+
+    // (の後から)までを読む。後に=>が続く場合はアロー関数の仮引数として扱う
+    // asyncの後の場合、=>が続かなければasyncという名前の関数の呼び出しになる
+    fn parenthesized_expression(&mut self, async_position: Option<Position>) -> Option<Rc<Node>> {
+        let no_in = core::mem::replace(&mut self.no_in, false);
+        let mut exprs = Vec::new();
+        if self.is_next_punctuator(')') {
+            assert!(self.t.next().is_some());
+        } else {
+            loop {
+                exprs.push(self.assignment_expression());
+                if !self.is_next_punctuator(',') {
+                    break;
+                }
+                assert!(self.t.next().is_some());
+            }
+            self.consume_punctuator(')');
+        }
+        self.no_in = no_in;
+
+        if self.is_next_arrow() {
+            assert!(self.t.next().is_some());
+            return self.arrow_function(exprs, async_position.is_some());
+        }
+        if let Some(position) = async_position {
+            let callee = Node::new_identifier("async".to_string());
+            return Node::new_call_expression(callee, exprs, position);
+        }
+
+        match exprs.len() {
+            1 => exprs.remove(0),
+            // ()の後に=>がない
+            0 => self.unexpected_token(),
+            _ => Node::new_sequence_expression(exprs),
+        }
+    }
+
+    fn arrow_function(
+        &mut self,
+        params: Vec<Option<Rc<Node>>>,
+        is_async: bool,
+    ) -> Option<Rc<Node>> {
+        for param in &params {
+            if !matches!(param.as_deref(), Some(Node::Identifier(_))) {
+                let position = self.t.position();
+                return self.syntax_error("invalid arrow function parameter".to_string(), position);
+            }
+        }
+
+        // アロー関数の中ではyieldを使えない
+        let outer = (self.in_async, self.in_generator);
+        self.in_async = is_async;
+        self.in_generator = false;
+        let jumps = core::mem::take(&mut self.jumps);
+        let body = if self.is_next_punctuator('{') {
+            assert!(self.t.next().is_some());
+            let scope = DeclaredNames::new(true, parameter_names(&params));
+            self.scoped(scope, Self::statement_list)
+        } else {
+            // 本体が式の場合、その値を返すブロックとして扱う
+            let expr = self.assignment_expression();
+            Node::new_block_statement(Vec::from([Node::new_return_statement(expr)]))
+        };
+        (self.in_async, self.in_generator) = outer;
+        self.jumps = jumps;
+
+        Node::new_arrow_function_expression(params, body, is_async)
+    }
+
+    fn function_expression(&mut self, is_async: bool) -> Option<Rc<Node>> {
+        let is_generator = self.generator_star(is_async);
+        let id = match self.t.peek() {
+            Some(Token::Identifier(_)) => self.identifier(),
+            _ => None,
+        };
+        let params = self.parameter_list();
+        let body = self.function_body_of(&params, is_async, is_generator);
+        Node::new_function_expression(id, params, body, is_async, is_generator)
+    }
+
+    // asyncの後に続く関数を読む。続かない場合はasyncという名前の変数として扱う
+    fn async_expression(&mut self, position: Position) -> Option<Rc<Node>> {
+        match self.t.peek() {
+            Some(Token::Keyword(k)) if k == "function" => {
+                assert!(self.t.next().is_some());
+                self.function_expression(true)
+            }
+            // async a => a
+            Some(Token::Identifier(_)) => {
+                let param = self.identifier();
+                if !self.is_next_arrow() {
+                    return self.unexpected_token();
+                }
+                assert!(self.t.next().is_some());
+                self.arrow_function(Vec::from([param]), true)
+            }
+            Some(Token::Punctuator('(')) => {
+                assert!(self.t.next().is_some());
+                self.parenthesized_expression(Some(position))
+            }
+            _ => Node::new_identifier("async".to_string()),
+        }
+    }
+
+    // [の後から]までの要素を読む
+    fn array_literal(&mut self) -> Option<Rc<Node>> {
+        let mut elements = Vec::new();
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator(']')) => {
+                    assert!(self.t.next().is_some());
+                    return Node::new_array_expression(elements);
+                }
+                // [, 1]のように省略された要素はundefinedになる
+                Some(Token::Punctuator(',')) => {
+                    assert!(self.t.next().is_some());
+                    elements.push(None);
+                }
+                Some(_) => {
+                    elements.push(self.assignment_expression());
+                    // 要素の後の,は区切りなので、最後の,は長さに数えない
+                    if self.is_next_punctuator(',') {
+                        assert!(self.t.next().is_some());
+                    } else if !self.is_next_punctuator(']') {
+                        return self.unexpected_token();
+                    }
+                }
+                None => return self.unexpected_token(),
+            }
+        }
+    }
+
+    // {の後から}までのプロパティを読む
+    fn object_literal(&mut self) -> Option<Rc<Node>> {
+        let mut properties = Vec::new();
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator('}')) => {
+                    assert!(self.t.next().is_some());
+                    return Node::new_object_expression(properties);
+                }
+                Some(Token::Punctuator(',')) => {
+                    assert!(self.t.next().is_some());
+                }
+                Some(_) => properties.push(self.property_definition(false)),
+                None => return self.unexpected_token(),
+            }
+        }
+    }
+
+    // プロパティ名として使えるトークンを読む
+    fn property_name(&mut self) -> Option<String> {
+        let key = match self.t.peek() {
+            Some(Token::Identifier(name))
+            | Some(Token::Keyword(name))
+            | Some(Token::StringLiteral(name)) => name.clone(),
+            Some(Token::Number(value)) => format!("{}", value),
+            _ => {
+                self.unexpected_token();
+                return None;
+            }
+        };
+        assert!(self.t.next().is_some());
+        Some(key)
+    }
+
+    // プロパティ名の後に別のプロパティ名が続く場合、getやsetはゲッターやセッターの指定として扱う
+    fn is_next_property_name(&mut self) -> bool {
+        matches!(
+            self.t.peek(),
+            Some(Token::Identifier(_))
+                | Some(Token::Keyword(_))
+                | Some(Token::StringLiteral(_))
+                | Some(Token::Number(_))
+        )
+    }
+
+    // オブジェクトリテラルのkey: value、またはクラスやオブジェクトリテラルのメソッドを読む
+    fn property_definition(&mut self, is_class: bool) -> Option<Rc<Node>> {
+        let mut is_generator = self.generator_star(false);
+        let mut key = self.property_name()?;
+
+        // static() {}のように、staticという名前のメソッドも定義できる
+        let mut is_static = false;
+        if is_class
+            && !is_generator
+            && key == "static"
+            && (self.is_next_property_name() || self.is_next_punctuator('*'))
+        {
+            is_static = true;
+            is_generator = self.generator_star(false);
+            key = self.property_name()?;
+        }
+
+        // async method() {}
+        let mut is_async = false;
+        if !is_generator
+            && key == "async"
+            && (self.is_next_property_name() || self.is_next_punctuator('*'))
+        {
+            is_async = true;
+            self.generator_star(true);
+            key = self.property_name()?;
+        }
+
+        // async get() {}のように、asyncやジェネレータのメソッドはgetやsetという名前になる
+        let mut kind = MethodKind::Method;
+        if !is_async
+            && !is_generator
+            && (key == "get" || key == "set")
+            && self.is_next_property_name()
+        {
+            kind = if key == "get" {
+                MethodKind::Get
+            } else {
+                MethodKind::Set
+            };
+            key = self.property_name()?;
+        } else if is_class && !is_static && key == "constructor" {
+            kind = MethodKind::Constructor;
+        }
+
+        if !is_class
+            && kind == MethodKind::Method
+            && !is_async
+            && !is_generator
+            && !self.is_next_punctuator('(')
+        {
+            self.consume_punctuator(':');
+            return Node::new_property(key, self.assignment_expression());
+        }
+
+        let params = self.parameter_list();
+        let body = self.function_body_of(&params, is_async, is_generator);
+        let value = Node::new_function_expression(None, params, body, is_async, is_generator);
+        Node::new_method_definition(key, value, kind, is_static)
+    }
+
+    // extendsから}までのクラスの定義を読む
+    fn class_tail(&mut self) -> (Option<Rc<Node>>, Vec<Option<Rc<Node>>>) {
+        let mut super_class = None;
+        if matches!(self.t.peek(), Some(Token::Keyword(k)) if k == "extends") {
+            assert!(self.t.next().is_some());
+            super_class = self.left_hand_side_expression();
+        }
+
+        let mut body = Vec::new();
+        if !self.consume_punctuator('{') {
+            return (super_class, body);
+        }
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator('}')) => {
+                    assert!(self.t.next().is_some());
+                    return (super_class, body);
+                }
+                Some(Token::Punctuator(';')) => {
+                    assert!(self.t.next().is_some());
+                }
+                Some(_) => body.push(self.property_definition(true)),
+                None => {
+                    self.unexpected_token();
+                    return (super_class, body);
+                }
+            }
+        }
+    }
+
+    fn primary_expression(&mut self) -> Option<Rc<Node>> {
+        // 式の始まりの/は正規表現リテラル
+        self.t.read_again(Goal::RegExp);
+        let t = match self.t.next() {
             Some(token) => token,
-            None => return expr,
+            None => return self.unexpected_token(),
         };
 
         match t {
-            Token::Punctuator(c) => {
-                if c == &'.' {
-                    // '.'を消費する
+            Token::Identifier(value) => {
+                // a => a + 1のように仮引数が1つのアロー関数
+                if self.is_next_arrow() {
                     assert!(self.t.next().is_some());
-                    return Node::new_member_expression(expr, self.identifier());
+                    return self.arrow_function(Vec::from([Node::new_identifier(value)]), false);
+                }
+                if value == "async" {
+                    let position = self.t.position;
+                    return self.async_expression(position);
+                }
+                Node::new_identifier(value)
+            }
+            Token::StringLiteral(value) => Node::new_string_literal(value),
+            Token::Number(value) => Node::new_numeric_literal(value),
+            Token::Keyword(keyword) if keyword == "true" => Node::new_boolean_literal(true),
+            Token::Keyword(keyword) if keyword == "false" => Node::new_boolean_literal(false),
+            Token::Keyword(keyword) if keyword == "null" => Some(Rc::new(Node::NullLiteral)),
+            Token::Punctuator('[') => self.array_literal(),
+            Token::Punctuator('{') => self.object_literal(),
+            Token::Keyword(keyword) if keyword == "this" => Some(Rc::new(Node::ThisExpression)),
+            Token::Keyword(keyword) if keyword == "function" => self.function_expression(false),
+            Token::Keyword(keyword) if keyword == "super" => Some(Rc::new(Node::Super)),
+            Token::Keyword(keyword) if keyword == "class" => {
+                let id = match self.t.peek() {
+                    Some(Token::Identifier(_)) => self.identifier(),
+                    _ => None,
+                };
+                let (super_class, body) = self.class_tail();
+                Node::new_class_expression(id, super_class, body)
+            }
+            Token::Punctuator('(') => self.parenthesized_expression(None),
+            Token::Template { cooked, tail, .. } => {
+                let (quasis, _, expressions) = self.template_parts(cooked, String::new(), tail);
+                Node::new_template_literal(quasis, expressions)
+            }
+            Token::RegExp { pattern, flags } => {
+                // パターンの構文エラーは実行前に報告する
+                if let Err(e) = Regex::new(&pattern, &flags) {
+                    let position = self.t.position;
+                    let message =
+                        format!("Invalid regular expression: /{}/{}: {}", pattern, flags, e);
+                    return self.syntax_error(message, position);
                 }
-
-                expr
+                Node::new_regexp_literal(pattern, flags)
+            }
+            Token::Error(message) => {
+                let position = self.t.position;
+                self.syntax_error(message, position)
+            }
+            t => {
+                let position = self.t.position;
+                self.syntax_error(format!("unexpected token '{}'", t), position)
             }
-            _ => expr,
         }
     }
 
-    fn primary_expression(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
-        };
+    // テンプレートリテラルの最初の文字列の後から読み、文字列、ソースコードのままの文字列、式を返す
+    // ${}の後には文字列の続きのトークンが来る
+    fn template_parts(
+        &mut self,
+        head: String,
+        head_raw: String,
+        tail: bool,
+    ) -> (Vec<String>, Vec<String>, Vec<Option<Rc<Node>>>) {
+        let mut quasis = Vec::from([head]);
+        let mut raws = Vec::from([head_raw]);
+        let mut expressions = Vec::new();
+        let mut tail = tail;
+        while !tail {
+            expressions.push(self.expression());
+            match self.t.peek() {
+                Some(Token::Template { .. }) => {
+                    if let Some(Token::Template {
+                        cooked,
+                        raw,
+                        tail: t,
+                        ..
+                    }) = self.t.next()
+                    {
+                        quasis.push(cooked);
+                        raws.push(raw);
+                        tail = t;
+                    }
+                }
+                _ => {
+                    self.unexpected_token();
+                    break;
+                }
+            }
+        }
+        (quasis, raws, expressions)
+    }
 
-        match t {
-            Token::Identifier(value) => Node::new_identifier(value),
-            Token::StringLiteral(value) => Node::new_string_literal(value),
-            Token::Number(value) => Node::new_numeric_literal(value),
+    // tag`a${b}c`のタグ付きテンプレートを、文字列の配列と式の値を引数にしたtagの呼び出しとして読む
+    fn tagged_template(&mut self, tag: Option<Rc<Node>>) -> Option<Rc<Node>> {
+        let position = self.t.position();
+        match self.t.next() {
+            Some(Token::Template {
+                cooked, raw, tail, ..
+            }) => {
+                let (quasis, raws, expressions) = self.template_parts(cooked, raw, tail);
+                let mut arguments = Vec::from([Node::new_template_object(quasis, raws)]);
+                arguments.extend(expressions);
+                Node::new_call_expression(tag, arguments, position)
+            }
             _ => None,
         }
     }
 
-    fn variable_declaration(&mut self) -> Option<Rc<Node>> {
-        let ident = self.identifier();
-
-        let declarator = Node::new_variable_declarator(ident, self.initializer());
-
+    fn variable_declaration(&mut self, kind: DeclarationKind) -> Option<Rc<Node>> {
         let mut declarations = Vec::new();
-        declarations.push(declarator);
+        loop {
+            let position = self.t.position();
+            let ident = self.identifier();
+            if let Some(Node::Identifier(name)) = ident.as_deref() {
+                match kind {
+                    DeclarationKind::Var => self.declare_var(name, position),
+                    _ => self.declare_lexical(name, position),
+                }
+            }
+            declarations.push(Node::new_variable_declarator(ident, self.initializer()));
+
+            // var a = 1, b = 2;のように複数の変数を宣言できる
+            if !self.is_next_punctuator(',') {
+                break;
+            }
+            assert!(self.t.next().is_some());
+        }
 
-        Node::new_variable_declaration(declarations)
+        Node::new_variable_declaration(kind, declarations)
     }
 
     fn identifier(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
-        };
+        if !matches!(self.t.peek(), Some(Token::Identifier(_))) {
+            return self.unexpected_token();
+        }
 
-        match t {
-            Token::Identifier(name) => Node::new_identifier(name),
+        match self.t.next() {
+            Some(Token::Identifier(name)) => Node::new_identifier(name),
             _ => None,
         }
     }
 
     fn initializer(&mut self) -> Option<Rc<Node>> {
-        let t = match self.t.next() {
-            Some(token) => token,
-            None => return None,
-        };
-
-        match t {
-            Token::Punctuator(c) => match c {
-                '=' => self.assignment_expression(),
-                _ => None,
-            },
-            _ => None,
+        // var a;のように初期値がない場合は何も消費しない
+        if !self.is_next_punctuator('=') {
+            return None;
         }
+        assert!(self.t.next().is_some());
+        self.assignment_expression()
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 // ASTのルートノードとなる構造体
 pub struct Program {
     body: Vec<Rc<Node>>,
@@ -473,7 +2761,7 @@ mod tests {
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
         let expected = Program::new();
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -484,10 +2772,10 @@ mod tests {
         let mut expected = Program::new();
         let mut body = Vec::new();
         body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
-            Node::NumericLiteral(42),
+            Node::NumericLiteral(42.0),
         )))));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -500,12 +2788,12 @@ mod tests {
         body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
             Node::AdditiveExpression {
                 operator: '+',
-                left: Some(Rc::new(Node::NumericLiteral(1))),
-                right: Some(Rc::new(Node::NumericLiteral(2))),
+                left: Some(Rc::new(Node::NumericLiteral(1.0))),
+                right: Some(Rc::new(Node::NumericLiteral(2.0))),
             },
         )))));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -516,6 +2804,7 @@ mod tests {
         let mut expected = Program::new();
         let mut body = Vec::new();
         body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("foo".to_string()))),
                 init: Some(Rc::new(Node::StringLiteral("bar".to_string()))),
@@ -523,7 +2812,7 @@ mod tests {
             .to_vec(),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -534,25 +2823,27 @@ mod tests {
         let mut expected = Program::new();
         let mut body = Vec::new();
         body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("foo".to_string()))),
-                init: Some(Rc::new(Node::NumericLiteral(42))),
+                init: Some(Rc::new(Node::NumericLiteral(42.0))),
             }))]
             .to_vec(),
         }));
         body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("result".to_string()))),
                 init: Some(Rc::new(Node::AdditiveExpression {
                     operator: '+',
                     left: Some(Rc::new(Node::Identifier("foo".to_string()))),
-                    right: Some(Rc::new(Node::NumericLiteral(1))),
+                    right: Some(Rc::new(Node::NumericLiteral(1.0))),
                 })),
             }))]
             .to_vec(),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -567,13 +2858,15 @@ mod tests {
             params: [].to_vec(),
             body: Some(Rc::new(Node::BlockStatement {
                 body: [Some(Rc::new(Node::ReturnStatement {
-                    argument: Some(Rc::new(Node::NumericLiteral(42))),
+                    argument: Some(Rc::new(Node::NumericLiteral(42.0))),
                 }))]
                 .to_vec(),
             })),
+            is_async: false,
+            is_generator: false,
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -600,9 +2893,11 @@ mod tests {
                 }))]
                 .to_vec(),
             })),
+            is_async: false,
+            is_generator: false,
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
     }
 
     #[test]
@@ -617,12 +2912,15 @@ mod tests {
             params: [].to_vec(),
             body: Some(Rc::new(Node::BlockStatement {
                 body: [Some(Rc::new(Node::ReturnStatement {
-                    argument: Some(Rc::new(Node::NumericLiteral(42))),
+                    argument: Some(Rc::new(Node::NumericLiteral(42.0))),
                 }))]
                 .to_vec(),
             })),
+            is_async: false,
+            is_generator: false,
         }));
         body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
             declarations: [Some(Rc::new(Node::VariableDeclarator {
                 id: Some(Rc::new(Node::Identifier("result".to_string()))),
                 init: Some(Rc::new(Node::AdditiveExpression {
@@ -630,13 +2928,627 @@ mod tests {
                     left: Some(Rc::new(Node::CallExpression {
                         callee: Some(Rc::new(Node::Identifier("foo".to_string()))),
                         arguments: [].to_vec(),
+                        position: Position::new(1, 44),
+                    })),
+                    right: Some(Rc::new(Node::NumericLiteral(1.0))),
+                })),
+            }))]
+            .to_vec(),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let input = "a || b && c == 1 + 2 * 3".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::LogicalExpression {
+                operator: "||".to_string(),
+                left: Some(Rc::new(Node::Identifier("a".to_string()))),
+                right: Some(Rc::new(Node::LogicalExpression {
+                    operator: "&&".to_string(),
+                    left: Some(Rc::new(Node::Identifier("b".to_string()))),
+                    right: Some(Rc::new(Node::EqualityExpression {
+                        operator: "==".to_string(),
+                        left: Some(Rc::new(Node::Identifier("c".to_string()))),
+                        right: Some(Rc::new(Node::AdditiveExpression {
+                            operator: '+',
+                            left: Some(Rc::new(Node::NumericLiteral(1.0))),
+                            right: Some(Rc::new(Node::MultiplicativeExpression {
+                                operator: '*',
+                                left: Some(Rc::new(Node::NumericLiteral(2.0))),
+                                right: Some(Rc::new(Node::NumericLiteral(3.0))),
+                            })),
+                        })),
+                    })),
+                })),
+            },
+        )))));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_if_else() {
+        let input = "if (a < 1) b = 1; else { b = 2; }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::IfStatement {
+            test: Some(Rc::new(Node::RelationalExpression {
+                operator: "<".to_string(),
+                left: Some(Rc::new(Node::Identifier("a".to_string()))),
+                right: Some(Rc::new(Node::NumericLiteral(1.0))),
+            })),
+            consequent: Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                Node::AssignmentExpression {
+                    operator: "=".to_string(),
+                    left: Some(Rc::new(Node::Identifier("b".to_string()))),
+                    right: Some(Rc::new(Node::NumericLiteral(1.0))),
+                },
+            ))))),
+            alternate: Some(Rc::new(Node::BlockStatement {
+                body: [Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                    Node::AssignmentExpression {
+                        operator: "=".to_string(),
+                        left: Some(Rc::new(Node::Identifier("b".to_string()))),
+                        right: Some(Rc::new(Node::NumericLiteral(2.0))),
+                    },
+                )))))]
+                .to_vec(),
+            })),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_let_and_arrow_function() {
+        let input = "let f = (a, b) => a * b;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Let,
+            declarations: [Some(Rc::new(Node::VariableDeclarator {
+                id: Some(Rc::new(Node::Identifier("f".to_string()))),
+                init: Some(Rc::new(Node::ArrowFunctionExpression {
+                    params: [
+                        Some(Rc::new(Node::Identifier("a".to_string()))),
+                        Some(Rc::new(Node::Identifier("b".to_string()))),
+                    ]
+                    .to_vec(),
+                    // 式の本体はreturn文を含むブロックとして扱う
+                    body: Some(Rc::new(Node::BlockStatement {
+                        body: [Some(Rc::new(Node::ReturnStatement {
+                            argument: Some(Rc::new(Node::MultiplicativeExpression {
+                                operator: '*',
+                                left: Some(Rc::new(Node::Identifier("a".to_string()))),
+                                right: Some(Rc::new(Node::Identifier("b".to_string()))),
+                            })),
+                        }))]
+                        .to_vec(),
+                    })),
+                    is_async: false,
+                })),
+            }))]
+            .to_vec(),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let input = "var a = 1;\nif (a { a = 2; }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let error = parser.parse_ast().expect_err("should be a syntax error");
+        assert_eq!("unexpected token '{'", error.message());
+        assert_eq!(Position::new(2, 7), error.position());
+
+        // -2 ** 2は括弧がないと曖昧になる
+        let mut parser = JsParser::new(JsLexer::new("var a = -2 ** 2;".to_string()));
+        let error = parser.parse_ast().expect_err("should be a syntax error");
+        assert_eq!(
+            "unary operator before '**' needs parentheses",
+            error.message()
+        );
+
+        let mut parser = JsParser::new(JsLexer::new("var a = [1 2];".to_string()));
+        let error = parser.parse_ast().expect_err("should be a syntax error");
+        assert_eq!("unexpected token '2'", error.message());
+    }
+
+    #[test]
+    fn test_labeled_for_of_and_switch() {
+        let input =
+            "outer: for (const x of xs) switch (x) { case 1: continue outer; default: break; }"
+                .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::LabeledStatement {
+            label: "outer".to_string(),
+            body: Some(Rc::new(Node::ForOfStatement {
+                left: Some(Rc::new(Node::VariableDeclaration {
+                    kind: DeclarationKind::Const,
+                    declarations: [Some(Rc::new(Node::VariableDeclarator {
+                        id: Some(Rc::new(Node::Identifier("x".to_string()))),
+                        init: None,
+                    }))]
+                    .to_vec(),
+                })),
+                right: Some(Rc::new(Node::Identifier("xs".to_string()))),
+                body: Some(Rc::new(Node::SwitchStatement {
+                    discriminant: Some(Rc::new(Node::Identifier("x".to_string()))),
+                    cases: [
+                        Some(Rc::new(Node::SwitchCase {
+                            test: Some(Rc::new(Node::NumericLiteral(1.0))),
+                            consequent: [Some(Rc::new(Node::ContinueStatement {
+                                label: Some("outer".to_string()),
+                            }))]
+                            .to_vec(),
+                        })),
+                        Some(Rc::new(Node::SwitchCase {
+                            test: None,
+                            consequent: [Some(Rc::new(Node::BreakStatement { label: None }))]
+                                .to_vec(),
+                        })),
+                    ]
+                    .to_vec(),
+                })),
+            })),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_do_while_and_for_in() {
+        let input = "do i = i + 1; while (i < 3) for (k in o) {}".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::DoWhileStatement {
+            body: Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                Node::AssignmentExpression {
+                    operator: "=".to_string(),
+                    left: Some(Rc::new(Node::Identifier("i".to_string()))),
+                    right: Some(Rc::new(Node::AdditiveExpression {
+                        operator: '+',
+                        left: Some(Rc::new(Node::Identifier("i".to_string()))),
+                        right: Some(Rc::new(Node::NumericLiteral(1.0))),
+                    })),
+                },
+            ))))),
+            test: Some(Rc::new(Node::RelationalExpression {
+                operator: "<".to_string(),
+                left: Some(Rc::new(Node::Identifier("i".to_string()))),
+                right: Some(Rc::new(Node::NumericLiteral(3.0))),
+            })),
+        }));
+        body.push(Rc::new(Node::ForInStatement {
+            left: Some(Rc::new(Node::Identifier("k".to_string()))),
+            right: Some(Rc::new(Node::Identifier("o".to_string()))),
+            body: Some(Rc::new(Node::BlockStatement { body: Vec::new() })),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_jump_errors() {
+        for (input, message) in [
+            ("break;", "illegal break statement"),
+            (
+                "switch (a) { case 1: continue; }",
+                "illegal continue statement",
+            ),
+            ("while (a) { break b; }", "undefined label 'b'"),
+            (
+                "a: { while (b) { continue a; } }",
+                "label 'a' is not on an iteration statement",
+            ),
+            (
+                "while (a) { function f() { break; } }",
+                "illegal break statement",
+            ),
+        ] {
+            let lexer = JsLexer::new(input.to_string());
+            let mut parser = JsParser::new(lexer);
+            let error = parser.parse_ast().expect_err("should be a syntax error");
+            assert_eq!(message, error.message(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_early_errors() {
+        for (input, message) in [
+            (
+                "1++;",
+                "invalid left-hand side expression in postfix operation",
+            ),
+            (
+                "--f();",
+                "invalid left-hand side expression in prefix operation",
+            ),
+            ("a + 1 = 2;", "invalid left-hand side in assignment"),
+            ("(a, b) += 1;", "invalid left-hand side in assignment"),
+            (
+                "const a = 1; const a = 2;",
+                "identifier 'a' has already been declared",
+            ),
+            ("let a; var a;", "identifier 'a' has already been declared"),
+            (
+                "{ var a; } class a {}",
+                "identifier 'a' has already been declared",
+            ),
+            (
+                "function f(x) { let x; }",
+                "identifier 'x' has already been declared",
+            ),
+            (
+                "switch (a) { case 1: let b; default: let b; }",
+                "identifier 'b' has already been declared",
+            ),
+        ] {
+            let lexer = JsLexer::new(input.to_string());
+            let mut parser = JsParser::new(lexer);
+            let error = parser.parse_ast().expect_err("should be a syntax error");
+            assert_eq!(message, error.message(), "{}", input);
+        }
+
+        // 別のスコープのletやvar同士の宣言し直しは構文エラーにならない
+        for input in [
+            "let a; { let a; } for (let a;;) { let a; } function f(a) { var a; }",
+            "var a; var a; try {} catch (e) { var e; }",
+        ] {
+            let lexer = JsLexer::new(input.to_string());
+            let mut parser = JsParser::new(lexer);
+            assert!(parser.parse_ast().is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_try_statement() {
+        let input = "try { throw e; } finally {}".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::TryStatement {
+            block: Some(Rc::new(Node::BlockStatement {
+                body: [Some(Rc::new(Node::ThrowStatement {
+                    argument: Some(Rc::new(Node::Identifier("e".to_string()))),
+                    position: Position::new(1, 7),
+                }))]
+                .to_vec(),
+            })),
+            param: None,
+            handler: None,
+            finalizer: Some(Rc::new(Node::BlockStatement { body: Vec::new() })),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_new_expression() {
+        let input = "new a.B(1);".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::NewExpression {
+                callee: Some(Rc::new(Node::MemberExpression {
+                    object: Some(Rc::new(Node::Identifier("a".to_string()))),
+                    property: Some(Rc::new(Node::Identifier("B".to_string()))),
+                    computed: false,
+                })),
+                arguments: [Some(Rc::new(Node::NumericLiteral(1.0)))].to_vec(),
+                position: Position::new(1, 1),
+            },
+        )))));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_class_declaration() {
+        let input = "class A extends B { static get x() {} }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::ClassDeclaration {
+            id: Some(Rc::new(Node::Identifier("A".to_string()))),
+            super_class: Some(Rc::new(Node::Identifier("B".to_string()))),
+            body: [Some(Rc::new(Node::MethodDefinition {
+                key: "x".to_string(),
+                value: Some(Rc::new(Node::FunctionExpression {
+                    id: None,
+                    params: Vec::new(),
+                    body: Some(Rc::new(Node::BlockStatement { body: Vec::new() })),
+                    is_async: false,
+                    is_generator: false,
+                })),
+                kind: MethodKind::Get,
+                is_static: true,
+            }))]
+            .to_vec(),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_async_and_generator_functions() {
+        let input =
+            "async function f() { await g(); } function* h() { yield; yield 1; }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::FunctionDeclaration {
+            id: Some(Rc::new(Node::Identifier("f".to_string()))),
+            params: Vec::new(),
+            body: Some(Rc::new(Node::BlockStatement {
+                body: [Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                    Node::AwaitExpression {
+                        argument: Some(Rc::new(Node::CallExpression {
+                            callee: Some(Rc::new(Node::Identifier("g".to_string()))),
+                            arguments: Vec::new(),
+                            position: Position::new(1, 28),
+                        })),
+                    },
+                )))))]
+                .to_vec(),
+            })),
+            is_async: true,
+            is_generator: false,
+        }));
+        body.push(Rc::new(Node::FunctionDeclaration {
+            id: Some(Rc::new(Node::Identifier("h".to_string()))),
+            params: Vec::new(),
+            body: Some(Rc::new(Node::BlockStatement {
+                body: [
+                    Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                        Node::YieldExpression {
+                            argument: None,
+                            delegate: false,
+                        },
+                    ))))),
+                    Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                        Node::YieldExpression {
+                            argument: Some(Rc::new(Node::NumericLiteral(1.0))),
+                            delegate: false,
+                        },
+                    ))))),
+                ]
+                .to_vec(),
+            })),
+            is_async: false,
+            is_generator: true,
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_async_arrow_and_contextual_names() {
+        // 関数の外ではasync、await、yieldは普通の名前として使える
+        let input = "var f = async x => await x; var g = async(await, yield);".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
+            declarations: [Some(Rc::new(Node::VariableDeclarator {
+                id: Some(Rc::new(Node::Identifier("f".to_string()))),
+                init: Some(Rc::new(Node::ArrowFunctionExpression {
+                    params: [Some(Rc::new(Node::Identifier("x".to_string())))].to_vec(),
+                    body: Some(Rc::new(Node::BlockStatement {
+                        body: [Some(Rc::new(Node::ReturnStatement {
+                            argument: Some(Rc::new(Node::AwaitExpression {
+                                argument: Some(Rc::new(Node::Identifier("x".to_string()))),
+                            })),
+                        }))]
+                        .to_vec(),
                     })),
-                    right: Some(Rc::new(Node::NumericLiteral(1))),
+                    is_async: true,
+                })),
+            }))]
+            .to_vec(),
+        }));
+        body.push(Rc::new(Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
+            declarations: [Some(Rc::new(Node::VariableDeclarator {
+                id: Some(Rc::new(Node::Identifier("g".to_string()))),
+                init: Some(Rc::new(Node::CallExpression {
+                    callee: Some(Rc::new(Node::Identifier("async".to_string()))),
+                    arguments: [
+                        Some(Rc::new(Node::Identifier("await".to_string()))),
+                        Some(Rc::new(Node::Identifier("yield".to_string()))),
+                    ]
+                    .to_vec(),
+                    position: Position::new(1, 37),
                 })),
             }))]
             .to_vec(),
         }));
         expected.set_body(body);
-        assert_eq!(expected, parser.parse_ast());
+        assert_eq!(Ok(expected), parser.parse_ast());
+    }
+
+    #[test]
+    fn test_async_generator_is_syntax_error() {
+        let input = "async function* f() {}".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let error = parser.parse_ast().expect_err("should be a syntax error");
+        assert_eq!("async generators are not supported", error.message());
+    }
+
+    #[test]
+    fn test_import_and_export_declarations() {
+        let input = "import d, { a, default as b } from \"./m.js\"; import * as ns from \"./n.js\"; export { a as c }; export * from \"./o.js\"; export const x = 1; export default function () {}".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::ImportDeclaration {
+            specifiers: [
+                ("default".to_string(), "d".to_string()),
+                ("a".to_string(), "a".to_string()),
+                ("default".to_string(), "b".to_string()),
+            ]
+            .to_vec(),
+            source: "./m.js".to_string(),
+        }));
+        body.push(Rc::new(Node::ImportDeclaration {
+            specifiers: [("*".to_string(), "ns".to_string())].to_vec(),
+            source: "./n.js".to_string(),
+        }));
+        body.push(Rc::new(Node::ExportNamedDeclaration {
+            declaration: None,
+            specifiers: [("a".to_string(), "c".to_string())].to_vec(),
+            source: None,
+        }));
+        body.push(Rc::new(Node::ExportAllDeclaration {
+            source: "./o.js".to_string(),
+        }));
+        body.push(Rc::new(Node::ExportNamedDeclaration {
+            declaration: Some(Rc::new(Node::VariableDeclaration {
+                kind: DeclarationKind::Const,
+                declarations: [Some(Rc::new(Node::VariableDeclarator {
+                    id: Some(Rc::new(Node::Identifier("x".to_string()))),
+                    init: Some(Rc::new(Node::NumericLiteral(1.0))),
+                }))]
+                .to_vec(),
+            })),
+            specifiers: Vec::new(),
+            source: None,
+        }));
+        body.push(Rc::new(Node::ExportDefaultDeclaration {
+            declaration: Some(Rc::new(Node::FunctionDeclaration {
+                id: None,
+                params: Vec::new(),
+                body: Some(Rc::new(Node::BlockStatement { body: Vec::new() })),
+                is_async: false,
+                is_generator: false,
+            })),
+        }));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_module());
+    }
+
+    #[test]
+    fn test_import_outside_module_is_syntax_error() {
+        let input = "import { a } from \"./m.js\";".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let error = parser.parse_ast().expect_err("should be a syntax error");
+        assert_eq!("unexpected token 'import'", error.message());
+
+        // importとexportはモジュールのトップレベルでだけ使える
+        let input = "if (a) { export const b = 1; }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        assert!(parser.parse_module().is_err());
+    }
+
+    #[test]
+    fn test_automatic_semicolon_insertion() {
+        // 改行の後の++は次の文の前置演算子になる
+        let input = "a = b\n++c\nreturn_ = `x${a}y`".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+        body.push(Rc::new(Node::ExpressionStatement(
+            Node::new_assignment_expression(
+                "=".to_string(),
+                Node::new_identifier("a".to_string()),
+                Node::new_identifier("b".to_string()),
+            ),
+        )));
+        body.push(Rc::new(Node::ExpressionStatement(
+            Node::new_update_expression('+', true, Node::new_identifier("c".to_string())),
+        )));
+        body.push(Rc::new(Node::ExpressionStatement(
+            Node::new_assignment_expression(
+                "=".to_string(),
+                Node::new_identifier("return_".to_string()),
+                Node::new_template_literal(
+                    Vec::from(["x".to_string(), "y".to_string()]),
+                    Vec::from([Node::new_identifier("a".to_string())]),
+                ),
+            ),
+        )));
+        expected.set_body(body);
+        assert_eq!(Ok(expected), parser.parse_ast());
+
+        // 同じ行に続く文は;で区切る必要がある
+        let input = "var a = 1 var b = 2".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let error = parser.parse_ast().expect_err("should be a syntax error");
+        assert_eq!("unexpected token 'var'", error.message());
+        assert_eq!(Position::new(1, 11), error.position());
+    }
+
+    #[test]
+    fn test_lexical_and_regexp_errors() {
+        for (input, message) in [
+            ("var s = 'abc", "unterminated string literal"),
+            (
+                "var r = /a(/;",
+                "Invalid regular expression: /a(/: unterminated group",
+            ),
+            ("a += 1 +", "unexpected end of input"),
+        ] {
+            let lexer = JsLexer::new(input.to_string());
+            let mut parser = JsParser::new(lexer);
+            let error = parser.parse_ast().expect_err("should be a syntax error");
+            assert_eq!(message, error.message());
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting_is_syntax_error() {
+        for input in [
+            format!("var a = {}1{};", "(".repeat(50000), ")".repeat(50000)),
+            format!("var a = {}1;", "!".repeat(50000)),
+            format!("var a = b{};", ".c".repeat(50000)),
+            format!("{}{}", "{".repeat(50000), "}".repeat(50000)),
+        ] {
+            let lexer = JsLexer::new(input);
+            let mut parser = JsParser::new(lexer);
+            let error = parser.parse_ast().expect_err("should be a syntax error");
+            assert_eq!("too deeply nested", error.message());
+        }
+
+        // 上限より浅い入れ子と、左側に積み上げる長い二項演算子の式はパースできる
+        for input in [
+            format!("var a = {}1{};", "(".repeat(100), ")".repeat(100)),
+            format!("var a = {}1;", "1 + ".repeat(50000)),
+            format!("var a = {}b;", "a && b || ".repeat(50000)),
+        ] {
+            let lexer = JsLexer::new(input);
+            let mut parser = JsParser::new(lexer);
+            assert!(parser.parse_ast().is_ok());
+        }
     }
 }
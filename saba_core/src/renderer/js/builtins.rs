@@ -0,0 +1,1713 @@
+// JSの組み込みオブジェクトと関数
+// Math、JSON、Object、文字列と配列と数値のメソッドなどを、Rustで実装したネイティブ関数として定義する
+
+use crate::renderer::js::runtime::{
+    array_length, number_to_string, string_from_utf16, Function, JsArray, JsException, JsObject,
+    JsRuntime, NativeClosure, NativeFunction, RuntimeValue,
+};
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::RefCell;
+
+// 引数を取得する。省略された場合はundefined
+pub fn arg(args: &[RuntimeValue], index: usize) -> RuntimeValue {
+    args.get(index).cloned().unwrap_or(RuntimeValue::Undefined)
+}
+
+pub fn native(name: &str, function: NativeFunction) -> RuntimeValue {
+    RuntimeValue::Function(Rc::new(Function::new_native(name, function)))
+}
+
+// 値を捕捉するネイティブ関数を作る。捕捉した値が関数自身を参照する場合があるため、GCに登録する
+pub fn native_closure(
+    runtime: &JsRuntime,
+    name: &str,
+    closure: NativeClosure,
+    captures: Vec<RuntimeValue>,
+) -> RuntimeValue {
+    runtime.track(RuntimeValue::Function(Rc::new(
+        Function::new_native_closure(name, closure, captures),
+    )))
+}
+
+// ネイティブ関数をオブジェクトのプロパティとして定義する
+pub fn define_methods(object: &RuntimeValue, methods: &[(&str, NativeFunction)]) {
+    for (name, function) in methods {
+        object.set_property(name, native(name, *function));
+    }
+}
+
+// プロトタイプオブジェクトと静的メソッドを持つコンストラクタを作る
+pub fn constructor(
+    name: &str,
+    function: NativeFunction,
+    prototype: &RuntimeValue,
+    static_methods: &[(&str, NativeFunction)],
+) -> RuntimeValue {
+    let constructor = native(name, function);
+    constructor.set_property("prototype", prototype.clone());
+    prototype.set_property("constructor", constructor.clone());
+    define_methods(&constructor, static_methods);
+    constructor
+}
+
+pub fn new_array(runtime: &JsRuntime, elements: Vec<RuntimeValue>) -> RuntimeValue {
+    runtime.track(RuntimeValue::Array(Rc::new(RefCell::new(JsArray::new(
+        elements,
+    )))))
+}
+
+// グローバルスコープに組み込みの変数を定義し、組み込みのプロトタイプにメソッドを追加する
+pub fn define_globals(runtime: &mut JsRuntime) {
+    define_methods(&runtime.object_prototype(), &OBJECT_PROTOTYPE_METHODS);
+    define_methods(&runtime.function_prototype(), &FUNCTION_PROTOTYPE_METHODS);
+    define_methods(&runtime.string_prototype(), &STRING_METHODS);
+    define_methods(&runtime.array_prototype(), &ARRAY_METHODS);
+    define_methods(
+        &runtime.number_prototype(),
+        &[
+            ("toFixed", number_to_fixed),
+            ("toString", number_to_string_method),
+        ],
+    );
+
+    // TypeErrorなどのプロトタイプはError.prototypeを継承する
+    let error_prototype = runtime.new_object();
+    for (name, function) in ERROR_CONSTRUCTORS {
+        let prototype = if name == "Error" {
+            error_prototype.clone()
+        } else {
+            RuntimeValue::Object(Rc::new(RefCell::new(JsObject::with_prototype(Some(
+                error_prototype.clone(),
+            )))))
+        };
+        prototype.set_property("name", RuntimeValue::StringLiteral(name.to_string()));
+        prototype.set_property("message", RuntimeValue::StringLiteral(String::new()));
+        runtime.define_global(name, constructor(name, function, &prototype, &[]));
+    }
+    define_methods(&error_prototype, &[("toString", error_to_string)]);
+
+    for (name, function) in GLOBAL_FUNCTIONS {
+        runtime.define_global(name, native(name, function));
+    }
+
+    let constructors = [
+        (
+            "Object",
+            object_constructor as NativeFunction,
+            runtime.object_prototype(),
+            &OBJECT_METHODS[..],
+        ),
+        (
+            "Function",
+            function_constructor,
+            runtime.function_prototype(),
+            &[],
+        ),
+        (
+            "Array",
+            array_constructor,
+            runtime.array_prototype(),
+            &[("isArray", array_is_array as NativeFunction)],
+        ),
+        (
+            "String",
+            string_constructor,
+            runtime.string_prototype(),
+            &[
+                ("fromCharCode", string_from_char_code as NativeFunction),
+                ("raw", string_raw),
+            ],
+        ),
+        (
+            "Number",
+            number_constructor,
+            runtime.number_prototype(),
+            &[],
+        ),
+    ];
+    for (name, function, prototype, static_methods) in constructors {
+        runtime.define_global(
+            name,
+            constructor(name, function, &prototype, static_methods),
+        );
+    }
+    runtime.define_global(
+        "Boolean",
+        native("Boolean", |_, _, args| {
+            Ok(RuntimeValue::Boolean(arg(&args, 0).is_truthy()))
+        }),
+    );
+
+    let math = runtime.new_object();
+    define_methods(&math, &MATH_METHODS);
+    math.set_property("PI", RuntimeValue::Number(core::f64::consts::PI));
+    math.set_property("E", RuntimeValue::Number(core::f64::consts::E));
+    runtime.define_global("Math", math);
+
+    let json = runtime.new_object();
+    define_methods(
+        &json,
+        &[("parse", json_parse), ("stringify", json_stringify)],
+    );
+    runtime.define_global("JSON", json);
+}
+
+// 組み込みのエラーのコンストラクタ。Error("message")とnew Error("message")のどちらでも作れる
+static ERROR_CONSTRUCTORS: [(&str, NativeFunction); 5] = [
+    ("Error", |runtime, this, args| {
+        construct_error(runtime, this, "Error", args)
+    }),
+    ("TypeError", |runtime, this, args| {
+        construct_error(runtime, this, "TypeError", args)
+    }),
+    ("ReferenceError", |runtime, this, args| {
+        construct_error(runtime, this, "ReferenceError", args)
+    }),
+    ("SyntaxError", |runtime, this, args| {
+        construct_error(runtime, this, "SyntaxError", args)
+    }),
+    ("RangeError", |runtime, this, args| {
+        construct_error(runtime, this, "RangeError", args)
+    }),
+];
+
+// newやsuper()で呼ばれた場合はthisを初期化し、関数として呼ばれた場合は新しいエラーオブジェクトを作る
+fn construct_error(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    name: &str,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let message = error_message(runtime, args)?;
+    match this {
+        RuntimeValue::Object(_) => {
+            runtime.init_error(&this, message);
+            Ok(this)
+        }
+        _ => Ok(runtime.create_error(name, message)),
+    }
+}
+
+fn error_to_string(
+    _: &mut JsRuntime,
+    this: RuntimeValue,
+    _: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let name = this.get_property("name").to_string();
+    let message = this.get_property("message").to_string();
+    if message.is_empty() {
+        return Ok(RuntimeValue::StringLiteral(name));
+    }
+    Ok(RuntimeValue::StringLiteral(format!(
+        "{}: {}",
+        name, message
+    )))
+}
+
+// エラーのコンストラクタの第1引数を文字列に変換してメッセージにする。省略された場合は空文字列
+fn error_message(runtime: &mut JsRuntime, args: Vec<RuntimeValue>) -> Result<String, JsException> {
+    match args.into_iter().next() {
+        Some(RuntimeValue::Undefined) | None => Ok(String::new()),
+        Some(value) => runtime.to_js_string(value),
+    }
+}
+
+static GLOBAL_FUNCTIONS: [(&str, NativeFunction); 3] = [
+    ("parseInt", parse_int),
+    ("parseFloat", |_, _, args| {
+        Ok(RuntimeValue::Number(parse_float(
+            &arg(&args, 0).to_string(),
+        )))
+    }),
+    ("isNaN", |_, _, args| {
+        Ok(RuntimeValue::Boolean(arg(&args, 0).to_number().is_nan()))
+    }),
+];
+
+fn string_constructor(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    match args.into_iter().next() {
+        Some(value) => Ok(RuntimeValue::StringLiteral(runtime.to_js_string(value)?)),
+        None => Ok(RuntimeValue::StringLiteral(String::new())),
+    }
+}
+
+// String.fromCharCode(97, 98)。引数を16ビットのコードユニットとして文字列にする
+fn string_from_char_code(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let mut units = Vec::with_capacity(args.len());
+    for value in args {
+        let value = runtime.to_primitive(value, "number")?;
+        units.push(value.to_uint32() as u16);
+    }
+    Ok(string_from_utf16(&units))
+}
+
+// String.raw`a\n${b}`。テンプレートのraw配列の文字列の間に、残りの引数を文字列にして挟む
+fn string_raw(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let mut args = args.into_iter();
+    let template = args.next().unwrap_or(RuntimeValue::Undefined);
+    let raw = runtime.get(&template, "raw")?;
+    let length = runtime.get(&raw, "length")?;
+    let length = runtime.to_number(length)?;
+    let length = if length.is_nan() || length < 0.0 {
+        0
+    } else {
+        length as usize
+    };
+
+    let mut result = String::new();
+    for i in 0..length {
+        let quasi = runtime.get(&raw, &i.to_string())?;
+        result.push_str(&runtime.to_js_string(quasi)?);
+        if i + 1 < length {
+            if let Some(substitution) = args.next() {
+                result.push_str(&runtime.to_js_string(substitution)?);
+            }
+        }
+    }
+    Ok(RuntimeValue::StringLiteral(result))
+}
+
+fn number_constructor(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    match args.into_iter().next() {
+        Some(value) => Ok(RuntimeValue::Number(runtime.to_number(value)?)),
+        None => Ok(RuntimeValue::Number(0.0)),
+    }
+}
+
+// Object(value)。オブジェクトの場合はそのまま返し、それ以外の場合は空のオブジェクトを作る
+fn object_constructor(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    match arg(&args, 0) {
+        value if value.is_object() => Ok(value),
+        _ => Ok(runtime.new_object()),
+    }
+}
+
+// 文字列からの関数の作成はサポートしない
+fn function_constructor(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    _: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    Err(runtime.throw_error(
+        "TypeError",
+        "Function constructor is not supported".to_string(),
+    ))
+}
+
+// Array(3)は長さ3の配列、Array(1, 2)は要素を並べた配列を作る
+fn array_constructor(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    if let [length @ RuntimeValue::Number(_)] = args.as_slice() {
+        let length = match array_length(length) {
+            Some(length) => length,
+            None => {
+                return Err(runtime.throw_error("RangeError", "invalid array length".to_string()))
+            }
+        };
+        return Ok(new_array(
+            runtime,
+            Vec::from_iter((0..length).map(|_| RuntimeValue::Undefined)),
+        ));
+    }
+    Ok(new_array(runtime, args))
+}
+
+static OBJECT_PROTOTYPE_METHODS: [(&str, NativeFunction); 4] = [
+    ("hasOwnProperty", |_, this, args| {
+        let key = arg(&args, 0).to_string();
+        Ok(RuntimeValue::Boolean(this.own_property(&key).is_some()))
+    }),
+    ("isPrototypeOf", |runtime, this, args| {
+        let mut current = runtime.prototype_of(&arg(&args, 0));
+        while let Some(prototype) = current {
+            if prototype == this {
+                return Ok(RuntimeValue::Boolean(true));
+            }
+            current = runtime.prototype_of(&prototype);
+        }
+        Ok(RuntimeValue::Boolean(false))
+    }),
+    // DOMノードのラッパーは"[object HTMLElement]"のようにノードの種類を返す
+    ("toString", |_, this, _| {
+        let tag = match this {
+            RuntimeValue::Object(_) => return Ok(RuntimeValue::StringLiteral(this.to_string())),
+            RuntimeValue::Array(_) => "Array",
+            RuntimeValue::Function(_) => "Function",
+            RuntimeValue::Null => "Null",
+            RuntimeValue::Undefined => "Undefined",
+            RuntimeValue::StringLiteral(_) => "String",
+            RuntimeValue::Number(_) => "Number",
+            RuntimeValue::Boolean(_) => "Boolean",
+        };
+        Ok(RuntimeValue::StringLiteral(format!("[object {}]", tag)))
+    }),
+    ("valueOf", |_, this, _| Ok(this)),
+];
+
+static FUNCTION_PROTOTYPE_METHODS: [(&str, NativeFunction); 3] = [
+    // f.call(thisArg, a, b)
+    ("call", |runtime, this, args| {
+        let mut args = args.into_iter();
+        let this_arg = args.next().unwrap_or(RuntimeValue::Undefined);
+        runtime.call(&this, this_arg, args.collect())
+    }),
+    // f.apply(thisArg, [a, b])
+    ("apply", |runtime, this, args| {
+        let arguments = match arg(&args, 1) {
+            RuntimeValue::Array(elements) => elements.borrow().to_vec(),
+            _ => Vec::new(),
+        };
+        runtime.call(&this, arg(&args, 0), arguments)
+    }),
+    // ソースコードは保持していないため、本体を"[code]"にした文字列を返す
+    ("toString", |_, this, _| {
+        Ok(RuntimeValue::StringLiteral(this.to_string()))
+    }),
+];
+
+// 文字列の先頭から、radix進数の整数として読める部分を数値にする
+fn parse_int(
+    _: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let input = arg(&args, 0).to_string();
+    let mut s = input.trim();
+
+    let mut sign = 1.0;
+    if let Some(rest) = s.strip_prefix('-') {
+        sign = -1.0;
+        s = rest;
+    } else if let Some(rest) = s.strip_prefix('+') {
+        s = rest;
+    }
+
+    let mut radix = to_integer(arg(&args, 1).to_number()) as u32;
+    let has_hex_prefix = s.starts_with("0x") || s.starts_with("0X");
+    if (radix == 0 || radix == 16) && has_hex_prefix {
+        radix = 16;
+        s = &s[2..];
+    } else if radix == 0 {
+        radix = 10;
+    }
+    if !(2..=36).contains(&radix) {
+        return Ok(RuntimeValue::Number(f64::NAN));
+    }
+
+    let mut result: Option<f64> = None;
+    for c in s.chars() {
+        match c.to_digit(radix) {
+            Some(d) => result = Some(result.unwrap_or(0.0) * radix as f64 + d as f64),
+            None => break,
+        }
+    }
+
+    match result {
+        Some(value) => Ok(RuntimeValue::Number(sign * value)),
+        None => Ok(RuntimeValue::Number(f64::NAN)),
+    }
+}
+
+// 文字列の先頭から、10進数の数値として読める一番長い部分を数値にする
+fn parse_float(input: &str) -> f64 {
+    let s = input.trim_start();
+    let unsigned = s.trim_start_matches(['+', '-']);
+    if unsigned.starts_with("Infinity") {
+        return if s.starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+
+    let chars: Vec<char> = s.chars().collect();
+    let mut end = 0;
+    let mut longest = None;
+    let mut seen_dot = false;
+    let mut seen_exponent = false;
+    while end < chars.len() {
+        let c = chars[end];
+        let is_sign_position = end == 0 || matches!(chars[end - 1], 'e' | 'E');
+        let valid = c.is_ascii_digit()
+            || (matches!(c, '+' | '-') && is_sign_position)
+            || (c == '.' && !seen_dot && !seen_exponent)
+            || (matches!(c, 'e' | 'E') && !seen_exponent && longest.is_some());
+        if !valid {
+            break;
+        }
+        seen_dot |= c == '.';
+        seen_exponent |= matches!(c, 'e' | 'E');
+        end += 1;
+
+        let prefix: String = chars[..end].iter().collect();
+        if let Ok(value) = prefix.parse::<f64>() {
+            longest = Some(value);
+        }
+    }
+
+    longest.unwrap_or(f64::NAN)
+}
+
+// 小数部を切り捨てて整数にする。NaNは0になる
+fn to_integer(value: f64) -> f64 {
+    if value.is_nan() {
+        0.0
+    } else {
+        trunc(value)
+    }
+}
+
+// 符号のビットを消して絶対値にする
+fn abs(x: f64) -> f64 {
+    f64::from_bits(x.to_bits() & !(1 << 63))
+}
+
+// 2^52以上の数は小数部を持たない
+pub fn trunc(x: f64) -> f64 {
+    if !x.is_finite() || abs(x) >= 4_503_599_627_370_496.0 {
+        x
+    } else {
+        (x as i64) as f64
+    }
+}
+
+fn floor(x: f64) -> f64 {
+    let t = trunc(x);
+    if t > x {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+fn ceil(x: f64) -> f64 {
+    let t = trunc(x);
+    if t < x {
+        t + 1.0
+    } else {
+        t
+    }
+}
+
+// 2つの倍精度の和で表した、約106ビットの精度の数。libmのexpとpowは最後の桁がずれることがあるため、
+// この精度で計算し直してから倍精度に丸める
+#[derive(Debug, Clone, Copy)]
+struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+// ln(2)を上位と下位の桁に分けたもの
+const LN_2: DoubleDouble = DoubleDouble {
+    hi: core::f64::consts::LN_2,
+    lo: 2.319_046_813_846_299_6e-17,
+};
+
+// a + bを丸めた値と、その誤差
+fn two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+    let b_part = hi - a;
+    let lo = (a - (hi - b_part)) + (b - b_part);
+    DoubleDouble { hi, lo }
+}
+
+// aを26ビットずつの上位と下位に分ける
+fn split(a: f64) -> (f64, f64) {
+    let c = 134_217_729.0 * a;
+    let hi = c - (c - a);
+    (hi, a - hi)
+}
+
+// a * bを丸めた値と、その誤差
+fn two_product(a: f64, b: f64) -> DoubleDouble {
+    let hi = a * b;
+    let (a_hi, a_lo) = split(a);
+    let (b_hi, b_lo) = split(b);
+    let lo = ((a_hi * b_hi - hi) + a_hi * b_lo + a_lo * b_hi) + a_lo * b_lo;
+    DoubleDouble { hi, lo }
+}
+
+impl DoubleDouble {
+    fn new(value: f64) -> Self {
+        Self { hi: value, lo: 0.0 }
+    }
+
+    fn add(self, other: Self) -> Self {
+        let sum = two_sum(self.hi, other.hi);
+        two_sum(sum.hi, sum.lo + self.lo + other.lo)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let product = two_product(self.hi, other.hi);
+        two_sum(
+            product.hi,
+            product.lo + self.hi * other.lo + self.lo * other.hi,
+        )
+    }
+
+    fn div_f64(self, divisor: f64) -> Self {
+        let q = self.hi / divisor;
+        let product = two_product(q, divisor);
+        two_sum(q, ((self.hi - product.hi) - product.lo + self.lo) / divisor)
+    }
+}
+
+// 2^kを返す。正規化数で表せない場合はNone
+fn exp2_int(k: f64) -> Option<f64> {
+    if (-1022.0..=1023.0).contains(&k) {
+        Some(f64::from_bits(((k as i64 + 1023) as u64) << 52))
+    } else {
+        None
+    }
+}
+
+// e^x。x = k * ln(2) + rに分解し、e^rを級数で求める。結果が正規化数にならない場合はNone
+fn exp_double_double(x: DoubleDouble) -> Option<DoubleDouble> {
+    let k = floor(x.hi / core::f64::consts::LN_2 + 0.5);
+    // e^709のように2^1024を超える場合もあるため、2^kを2つに分けて掛ける
+    let half = floor(k / 2.0);
+    let (scale1, scale2) = (exp2_int(half)?, exp2_int(k - half)?);
+    let r = x
+        .add(two_product(-k, LN_2.hi))
+        .add(DoubleDouble::new(-k * LN_2.lo));
+
+    let mut term = DoubleDouble::new(1.0);
+    let mut sum = DoubleDouble::new(1.0);
+    for n in 1..30 {
+        term = term.mul(r).div_f64(n as f64);
+        sum = sum.add(term);
+        if abs(term.hi) < 1e-36 {
+            break;
+        }
+    }
+    // hiはhi + loを丸めた値のため、2^kを掛けても正規化数の範囲では誤差は増えない
+    let result = DoubleDouble {
+        hi: sum.hi * scale1 * scale2,
+        lo: sum.lo * scale1 * scale2,
+    };
+    result.hi.is_normal().then_some(result)
+}
+
+// ln(x)。x = m * 2^eに分解し、libmで求めたln(m)をニュートン法で1回補正する
+fn ln_double_double(x: f64) -> DoubleDouble {
+    // 非正規化数は2^54を掛けて正規化数にする
+    let (x, offset) = if x.is_normal() {
+        (x, 0)
+    } else {
+        (x * 18_014_398_509_481_984.0, 54)
+    };
+    let bits = x.to_bits();
+    let e = ((bits >> 52) & 0x7ff) as i64 - 1023 - offset;
+    let m = f64::from_bits((bits & !(0x7ff << 52)) | (1023 << 52));
+
+    // ln(m) ≒ l + m * e^(-l) - 1
+    let l = libm::log(m);
+    let correction = match exp_double_double(DoubleDouble::new(-l)) {
+        Some(inverse) => DoubleDouble::new(m).mul(inverse),
+        None => DoubleDouble::new(1.0),
+    };
+    let ln_m = DoubleDouble::new(l)
+        .add(correction)
+        .add(DoubleDouble::new(-1.0));
+    two_product(e as f64, LN_2.hi)
+        .add(DoubleDouble::new(e as f64 * LN_2.lo))
+        .add(ln_m)
+}
+
+// 0、非正規化数、無限大になる場合はlibmの結果をそのまま返す
+fn exp(x: f64) -> f64 {
+    let result = libm::exp(x);
+    if !result.is_normal() {
+        return result;
+    }
+    exp_double_double(DoubleDouble::new(x)).map_or(result, |e| e.hi)
+}
+
+// libmの結果にJSのべき乗の定義を合わせる。指数がNaNの場合はNaN、0の場合は底がNaNでも1になる
+// 底の絶対値が1で指数が無限大の場合は、1ではなくNaNになる
+pub fn pow(x: f64, y: f64) -> f64 {
+    if y.is_nan() || (abs(x) == 1.0 && y.is_infinite()) {
+        return f64::NAN;
+    }
+    if y == 0.0 {
+        return 1.0;
+    }
+    let result = libm::pow(x, y);
+    // 指数が大きすぎる場合は、倍精度2つに分けて掛けられない
+    if !result.is_normal() || abs(y) > 1e290 {
+        return result;
+    }
+    let product = ln_double_double(abs(x)).mul(DoubleDouble::new(y));
+    // 底が負の数の場合、libmは指数が整数のときだけ値を返す。符号はその結果に合わせる
+    match exp_double_double(product) {
+        Some(magnitude) if result < 0.0 => -magnitude.hi,
+        Some(magnitude) => magnitude.hi,
+        None => result,
+    }
+}
+
+// 0.49999999999999994 + 0.5のような丸めの誤差を避けるため、0.5を足さずに小数部で判断する
+// -0.5以上の負の数は-0になる
+fn round(x: f64) -> f64 {
+    if (-0.5..0.0).contains(&x) {
+        return -0.0;
+    }
+    let f = floor(x);
+    if x - f >= 0.5 {
+        f + 1.0
+    } else {
+        f
+    }
+}
+
+static MATH_METHODS: [(&str, NativeFunction); 13] = [
+    ("abs", |_, _, args| {
+        Ok(RuntimeValue::Number(abs(arg(&args, 0).to_number())))
+    }),
+    ("floor", |_, _, args| {
+        Ok(RuntimeValue::Number(floor(arg(&args, 0).to_number())))
+    }),
+    ("ceil", |_, _, args| {
+        Ok(RuntimeValue::Number(ceil(arg(&args, 0).to_number())))
+    }),
+    ("round", |_, _, args| {
+        Ok(RuntimeValue::Number(round(arg(&args, 0).to_number())))
+    }),
+    ("trunc", |_, _, args| {
+        Ok(RuntimeValue::Number(trunc(arg(&args, 0).to_number())))
+    }),
+    ("sign", |_, _, args| {
+        let x = arg(&args, 0).to_number();
+        let sign = if x > 0.0 {
+            1.0
+        } else if x < 0.0 {
+            -1.0
+        } else {
+            x
+        };
+        Ok(RuntimeValue::Number(sign))
+    }),
+    ("sqrt", |_, _, args| {
+        Ok(RuntimeValue::Number(libm::sqrt(arg(&args, 0).to_number())))
+    }),
+    ("pow", |_, _, args| {
+        let (x, y) = (arg(&args, 0).to_number(), arg(&args, 1).to_number());
+        Ok(RuntimeValue::Number(pow(x, y)))
+    }),
+    ("log", |_, _, args| {
+        Ok(RuntimeValue::Number(libm::log(arg(&args, 0).to_number())))
+    }),
+    ("exp", |_, _, args| {
+        Ok(RuntimeValue::Number(exp(arg(&args, 0).to_number())))
+    }),
+    // 引数のどれかがNaNの場合はNaN
+    ("max", |_, _, args| {
+        let mut result = f64::NEG_INFINITY;
+        for value in args.iter().map(|a| a.to_number()) {
+            if value.is_nan() || value > result {
+                result = value;
+            }
+            if result.is_nan() {
+                break;
+            }
+        }
+        Ok(RuntimeValue::Number(result))
+    }),
+    ("min", |_, _, args| {
+        let mut result = f64::INFINITY;
+        for value in args.iter().map(|a| a.to_number()) {
+            if value.is_nan() || value < result {
+                result = value;
+            }
+            if result.is_nan() {
+                break;
+            }
+        }
+        Ok(RuntimeValue::Number(result))
+    }),
+    ("random", |runtime, _, _| {
+        Ok(RuntimeValue::Number(runtime.next_random()))
+    }),
+];
+
+// slice()などの引数の位置を、長さlenの範囲に収める。負の数は末尾から数える
+fn relative_index(value: &RuntimeValue, len: usize, default: usize) -> usize {
+    if *value == RuntimeValue::Undefined {
+        return default;
+    }
+    let index = to_integer(value.to_number());
+    if index < 0.0 {
+        (len as f64 + index).max(0.0) as usize
+    } else {
+        index.min(len as f64) as usize
+    }
+}
+
+// haystackのfrom以降でneedleが最初に現れる位置。文字の列とUTF-16のコードユニットの列のどちらにも使う
+pub fn find_chars<T: PartialEq>(haystack: &[T], needle: &[T], from: usize) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    (from..=haystack.len() - needle.len()).find(|&i| haystack[i..i + needle.len()] == *needle)
+}
+
+pub fn this_chars(this: &RuntimeValue) -> Vec<char> {
+    this.to_string().chars().collect()
+}
+
+// 文字列のメソッドは、JSと同じくUTF-16のコードユニットで位置と長さを数える
+fn this_units(this: &RuntimeValue) -> Vec<u16> {
+    this.to_string().encode_utf16().collect()
+}
+
+static STRING_METHODS: [(&str, NativeFunction); 15] = [
+    ("charAt", |_, this, args| {
+        let units = this_units(&this);
+        let index = to_integer(arg(&args, 0).to_number());
+        match units.get(index as usize) {
+            Some(unit) if index >= 0.0 => Ok(string_from_utf16(&[*unit])),
+            _ => Ok(RuntimeValue::StringLiteral(String::new())),
+        }
+    }),
+    ("charCodeAt", |_, this, args| {
+        let units = this_units(&this);
+        let index = to_integer(arg(&args, 0).to_number());
+        match units.get(index as usize) {
+            Some(unit) if index >= 0.0 => Ok(RuntimeValue::Number(*unit as f64)),
+            _ => Ok(RuntimeValue::Number(f64::NAN)),
+        }
+    }),
+    ("indexOf", |_, this, args| {
+        let units = this_units(&this);
+        let search = this_units(&arg(&args, 0));
+        let from = relative_index(&arg(&args, 1), units.len(), 0);
+        match find_chars(&units, &search, from) {
+            Some(index) => Ok(RuntimeValue::Number(index as f64)),
+            None => Ok(RuntimeValue::Number(-1.0)),
+        }
+    }),
+    ("lastIndexOf", |_, this, args| {
+        let units = this_units(&this);
+        let search = this_units(&arg(&args, 0));
+        let mut result = -1.0;
+        let mut from = 0;
+        while let Some(index) = find_chars(&units, &search, from) {
+            result = index as f64;
+            from = index + 1;
+        }
+        Ok(RuntimeValue::Number(result))
+    }),
+    ("includes", |_, this, args| {
+        let units = this_units(&this);
+        let search = this_units(&arg(&args, 0));
+        Ok(RuntimeValue::Boolean(
+            find_chars(&units, &search, 0).is_some(),
+        ))
+    }),
+    ("startsWith", |_, this, args| {
+        let prefix = arg(&args, 0).to_string();
+        Ok(RuntimeValue::Boolean(this.to_string().starts_with(&prefix)))
+    }),
+    ("endsWith", |_, this, args| {
+        let suffix = arg(&args, 0).to_string();
+        Ok(RuntimeValue::Boolean(this.to_string().ends_with(&suffix)))
+    }),
+    ("slice", |_, this, args| {
+        let units = this_units(&this);
+        let start = relative_index(&arg(&args, 0), units.len(), 0);
+        let end = relative_index(&arg(&args, 1), units.len(), units.len());
+        if start >= end {
+            return Ok(RuntimeValue::StringLiteral(String::new()));
+        }
+        Ok(string_from_utf16(&units[start..end]))
+    }),
+    // 負の数は0として扱い、startがendより大きい場合は入れ替える
+    ("substring", |_, this, args| {
+        let units = this_units(&this);
+        let clamp = |value: RuntimeValue, default: usize| {
+            if value == RuntimeValue::Undefined {
+                return default;
+            }
+            to_integer(value.to_number()).clamp(0.0, units.len() as f64) as usize
+        };
+        let start = clamp(arg(&args, 0), 0);
+        let end = clamp(arg(&args, 1), units.len());
+        Ok(string_from_utf16(&units[start.min(end)..start.max(end)]))
+    }),
+    ("toUpperCase", |_, this, _| {
+        Ok(RuntimeValue::StringLiteral(this.to_string().to_uppercase()))
+    }),
+    ("toLowerCase", |_, this, _| {
+        Ok(RuntimeValue::StringLiteral(this.to_string().to_lowercase()))
+    }),
+    ("trim", |_, this, _| {
+        Ok(RuntimeValue::StringLiteral(
+            this.to_string().trim().to_string(),
+        ))
+    }),
+    ("repeat", |runtime, this, args| {
+        let count = to_integer(arg(&args, 0).to_number());
+        if count < 0.0 || count.is_infinite() {
+            return Err(runtime.throw_error("RangeError", "invalid count value".to_string()));
+        }
+        Ok(RuntimeValue::StringLiteral(
+            this.to_string().repeat(count as usize),
+        ))
+    }),
+    ("concat", |_, this, args| {
+        let mut s = this.to_string();
+        for a in args {
+            s.push_str(&a.to_string());
+        }
+        Ok(RuntimeValue::StringLiteral(s))
+    }),
+    ("toString", |_, this, _| {
+        Ok(RuntimeValue::StringLiteral(this.to_string()))
+    }),
+];
+
+// thisの配列の要素。配列以外の場合はTypeError
+fn this_elements(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+) -> Result<Rc<RefCell<JsArray>>, JsException> {
+    match this {
+        RuntimeValue::Array(elements) => Ok(elements.clone()),
+        _ => Err(runtime.throw_error("TypeError", format!("{} is not an array", this))),
+    }
+}
+
+// 配列の要素ごとにコールバックを呼び出す。コールバックの中で配列が変更される場合に備え、要素を複製しておく
+fn call_for_each(
+    runtime: &mut JsRuntime,
+    this: &RuntimeValue,
+    callback: &RuntimeValue,
+    mut f: impl FnMut(RuntimeValue, RuntimeValue) -> bool,
+) -> Result<(), JsException> {
+    let elements = this_elements(runtime, this)?.borrow().to_vec();
+    for (i, element) in elements.into_iter().enumerate() {
+        let args = Vec::from([
+            element.clone(),
+            RuntimeValue::Number(i as f64),
+            this.clone(),
+        ]);
+        let result = runtime.call(callback, RuntimeValue::Undefined, args)?;
+        // falseを返した場合は繰り返しを終える
+        if !f(element, result) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// NaN同士を等しいとみなす比較
+fn same_value_zero(a: &RuntimeValue, b: &RuntimeValue) -> bool {
+    match (a, b) {
+        (RuntimeValue::Number(x), RuntimeValue::Number(y)) if x.is_nan() && y.is_nan() => true,
+        _ => a == b,
+    }
+}
+
+static ARRAY_METHODS: [(&str, NativeFunction); 20] = [
+    ("push", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        elements.borrow_mut().extend(args);
+        let len = RefCell::borrow(&elements).len();
+        Ok(RuntimeValue::Number(len as f64))
+    }),
+    ("pop", |runtime, this, _| {
+        let elements = this_elements(runtime, &this)?;
+        let last = elements.borrow_mut().pop();
+        Ok(last.unwrap_or(RuntimeValue::Undefined))
+    }),
+    ("shift", |runtime, this, _| {
+        let elements = this_elements(runtime, &this)?;
+        let mut elements = elements.borrow_mut();
+        if elements.is_empty() {
+            return Ok(RuntimeValue::Undefined);
+        }
+        Ok(elements.remove(0))
+    }),
+    ("unshift", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        let mut elements = elements.borrow_mut();
+        for (i, a) in args.into_iter().enumerate() {
+            elements.insert(i, a);
+        }
+        Ok(RuntimeValue::Number(elements.len() as f64))
+    }),
+    ("slice", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        let elements = RefCell::borrow(&elements);
+        let start = relative_index(&arg(&args, 0), elements.len(), 0);
+        let end = relative_index(&arg(&args, 1), elements.len(), elements.len());
+        if start >= end {
+            return Ok(new_array(runtime, Vec::new()));
+        }
+        Ok(new_array(runtime, elements[start..end].to_vec()))
+    }),
+    // 配列の引数は要素を展開して連結する
+    ("concat", |runtime, this, args| {
+        let mut result = this_elements(runtime, &this)?.borrow().to_vec();
+        for a in args {
+            match a {
+                RuntimeValue::Array(elements) => {
+                    result.extend(RefCell::borrow(&elements).iter().cloned())
+                }
+                _ => result.push(a),
+            }
+        }
+        Ok(new_array(runtime, result))
+    }),
+    ("indexOf", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        let search = arg(&args, 0);
+        let index = RefCell::borrow(&elements).iter().position(|e| *e == search);
+        Ok(RuntimeValue::Number(match index {
+            Some(index) => index as f64,
+            None => -1.0,
+        }))
+    }),
+    ("includes", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        let search = arg(&args, 0);
+        let found = RefCell::borrow(&elements)
+            .iter()
+            .any(|e| same_value_zero(e, &search));
+        Ok(RuntimeValue::Boolean(found))
+    }),
+    ("join", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        let separator = match arg(&args, 0) {
+            RuntimeValue::Undefined => ",".to_string(),
+            s => runtime.to_js_string(s)?,
+        };
+        let joined = runtime.join(&elements, &separator)?;
+        Ok(RuntimeValue::StringLiteral(joined))
+    }),
+    ("toString", |runtime, this, _| {
+        let elements = this_elements(runtime, &this)?;
+        let joined = runtime.join(&elements, ",")?;
+        Ok(RuntimeValue::StringLiteral(joined))
+    }),
+    ("reverse", |runtime, this, _| {
+        this_elements(runtime, &this)?.borrow_mut().reverse();
+        Ok(this)
+    }),
+    ("forEach", |runtime, this, args| {
+        call_for_each(runtime, &this, &arg(&args, 0), |_, _| true)?;
+        Ok(RuntimeValue::Undefined)
+    }),
+    ("map", |runtime, this, args| {
+        let mut result = Vec::new();
+        call_for_each(runtime, &this, &arg(&args, 0), |_, value| {
+            result.push(value);
+            true
+        })?;
+        Ok(new_array(runtime, result))
+    }),
+    ("filter", |runtime, this, args| {
+        let mut result = Vec::new();
+        call_for_each(runtime, &this, &arg(&args, 0), |element, value| {
+            if value.is_truthy() {
+                result.push(element);
+            }
+            true
+        })?;
+        Ok(new_array(runtime, result))
+    }),
+    ("find", |runtime, this, args| {
+        let mut found = RuntimeValue::Undefined;
+        call_for_each(runtime, &this, &arg(&args, 0), |element, value| {
+            if value.is_truthy() {
+                found = element;
+                return false;
+            }
+            true
+        })?;
+        Ok(found)
+    }),
+    ("findIndex", |runtime, this, args| {
+        let mut index = -1.0;
+        let mut i = 0.0;
+        call_for_each(runtime, &this, &arg(&args, 0), |_, value| {
+            if value.is_truthy() {
+                index = i;
+                return false;
+            }
+            i += 1.0;
+            true
+        })?;
+        Ok(RuntimeValue::Number(index))
+    }),
+    ("some", |runtime, this, args| {
+        let mut result = false;
+        call_for_each(runtime, &this, &arg(&args, 0), |_, value| {
+            result = value.is_truthy();
+            !result
+        })?;
+        Ok(RuntimeValue::Boolean(result))
+    }),
+    ("every", |runtime, this, args| {
+        let mut result = true;
+        call_for_each(runtime, &this, &arg(&args, 0), |_, value| {
+            result = value.is_truthy();
+            result
+        })?;
+        Ok(RuntimeValue::Boolean(result))
+    }),
+    // 初期値が省略された場合は最初の要素を初期値にする
+    ("reduce", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?.borrow().to_vec();
+        let callback = arg(&args, 0);
+        let mut elements = elements.into_iter().enumerate();
+        let mut accumulator = match args.get(1) {
+            Some(initial) => initial.clone(),
+            None => match elements.next() {
+                Some((_, first)) => first,
+                None => {
+                    return Err(runtime.throw_error(
+                        "TypeError",
+                        "reduce of empty array with no initial value".to_string(),
+                    ))
+                }
+            },
+        };
+        for (i, element) in elements {
+            let args = Vec::from([
+                accumulator,
+                element,
+                RuntimeValue::Number(i as f64),
+                this.clone(),
+            ]);
+            accumulator = runtime.call(&callback, RuntimeValue::Undefined, args)?;
+        }
+        Ok(accumulator)
+    }),
+    // 比較関数が省略された場合は文字列として比較する。同じ順位の要素の順番は変えない
+    ("sort", |runtime, this, args| {
+        let elements = this_elements(runtime, &this)?;
+        let mut sorted = RefCell::borrow(&elements).to_vec();
+        let comparator = arg(&args, 0);
+        for i in 1..sorted.len() {
+            let mut j = i;
+            while j > 0 {
+                let (a, b) = (sorted[j - 1].clone(), sorted[j].clone());
+                let greater = match comparator {
+                    RuntimeValue::Undefined => a.to_string() > b.to_string(),
+                    _ => {
+                        let args = Vec::from([a, b]);
+                        runtime
+                            .call(&comparator, RuntimeValue::Undefined, args)?
+                            .to_number()
+                            > 0.0
+                    }
+                };
+                if !greater {
+                    break;
+                }
+                sorted.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+        **elements.borrow_mut() = sorted;
+        Ok(this)
+    }),
+];
+
+fn array_is_array(
+    _: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    Ok(RuntimeValue::Boolean(matches!(
+        arg(&args, 0),
+        RuntimeValue::Array(_)
+    )))
+}
+
+// オブジェクトのプロパティ名と値の組。配列の場合は添字の後に添字以外のプロパティが続く
+pub fn own_entries(value: &RuntimeValue) -> Vec<(String, RuntimeValue)> {
+    match value {
+        RuntimeValue::Object(object) => {
+            let object = RefCell::borrow(object);
+            object
+                .keys()
+                .into_iter()
+                .map(|key| {
+                    let value = object.get(&key);
+                    (key, value)
+                })
+                .collect()
+        }
+        RuntimeValue::Array(elements) => RefCell::borrow(elements)
+            .keys()
+            .into_iter()
+            .map(|key| {
+                let value = value.get_property(&key);
+                (key, value)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+static OBJECT_METHODS: [(&str, NativeFunction); 6] = [
+    ("keys", object_keys),
+    ("values", object_values),
+    ("entries", object_entries),
+    // protoを継承する空のオブジェクトを作る。nullの場合は何も継承しない
+    ("create", |runtime, _, args| match arg(&args, 0) {
+        RuntimeValue::Null => Ok(new_object(runtime, JsObject::new())),
+        prototype @ (RuntimeValue::Object(_) | RuntimeValue::Function(_)) => Ok(new_object(
+            runtime,
+            JsObject::with_prototype(Some(prototype)),
+        )),
+        prototype => Err(runtime.throw_error(
+            "TypeError",
+            format!(
+                "object prototype may only be an object or null: {}",
+                prototype
+            ),
+        )),
+    }),
+    ("getPrototypeOf", |runtime, _, args| {
+        Ok(runtime
+            .prototype_of(&arg(&args, 0))
+            .unwrap_or(RuntimeValue::Null))
+    }),
+    ("setPrototypeOf", |_, _, args| {
+        let object = arg(&args, 0);
+        if let RuntimeValue::Object(o) = &object {
+            match arg(&args, 1) {
+                prototype @ (RuntimeValue::Object(_) | RuntimeValue::Function(_)) => {
+                    o.borrow_mut().set_prototype(Some(prototype))
+                }
+                RuntimeValue::Null => o.borrow_mut().set_prototype(None),
+                _ => {}
+            }
+        }
+        Ok(object)
+    }),
+];
+
+fn new_object(runtime: &JsRuntime, object: JsObject) -> RuntimeValue {
+    runtime.track(RuntimeValue::Object(Rc::new(RefCell::new(object))))
+}
+
+fn object_keys(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let keys = own_entries(&arg(&args, 0))
+        .into_iter()
+        .map(|(key, _)| RuntimeValue::StringLiteral(key))
+        .collect();
+    Ok(new_array(runtime, keys))
+}
+
+fn object_values(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let values = own_entries(&arg(&args, 0))
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    Ok(new_array(runtime, values))
+}
+
+fn object_entries(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let entries = own_entries(&arg(&args, 0))
+        .into_iter()
+        .map(|(key, value)| {
+            new_array(
+                runtime,
+                Vec::from([RuntimeValue::StringLiteral(key), value]),
+            )
+        })
+        .collect();
+    Ok(new_array(runtime, entries))
+}
+
+// 小数点以下の桁数を指定して文字列にする
+fn number_to_fixed(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let digits = to_integer(arg(&args, 0).to_number());
+    if !(0.0..=100.0).contains(&digits) {
+        return Err(runtime.throw_error(
+            "RangeError",
+            "toFixed() digits argument must be between 0 and 100".to_string(),
+        ));
+    }
+    let value = this.to_number();
+    if !value.is_finite() {
+        return Ok(RuntimeValue::StringLiteral(number_to_string(value)));
+    }
+    Ok(RuntimeValue::StringLiteral(format!(
+        "{:.*}",
+        digits as usize, value
+    )))
+}
+
+// 基数を指定して文字列にする。10進数以外は整数部分だけを変換する
+fn number_to_string_method(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let value = this.to_number();
+    let radix = match arg(&args, 0) {
+        RuntimeValue::Undefined => 10,
+        r => to_integer(r.to_number()) as u32,
+    };
+    if !(2..=36).contains(&radix) {
+        return Err(runtime.throw_error(
+            "RangeError",
+            "toString() radix must be between 2 and 36".to_string(),
+        ));
+    }
+    if radix == 10 || !value.is_finite() {
+        return Ok(RuntimeValue::StringLiteral(number_to_string(value)));
+    }
+
+    let radix_f64 = radix as f64;
+    let to_digit = |d: f64| core::char::from_digit(d as u32, radix).unwrap_or('0');
+    let mut integer = floor(abs(value));
+    let mut fraction = abs(value) - integer;
+
+    // 小数部は元の数に戻せるところまで出す。deltaは隣の浮動小数点数との距離の半分
+    let mut fraction_digits = Vec::new();
+    let mut delta =
+        (0.5 * (f64::from_bits(abs(value).to_bits() + 1) - abs(value))).max(f64::from_bits(1));
+    if fraction >= delta {
+        loop {
+            fraction *= radix_f64;
+            delta *= radix_f64;
+            let digit = floor(fraction);
+            fraction_digits.push(digit as u32);
+            fraction -= digit;
+            // 残りが半分を超えて切り上げても元の数に戻せるなら、繰り上げて終わる
+            if (fraction > 0.5 || (fraction == 0.5 && digit as u32 % 2 == 1))
+                && fraction + delta > 1.0
+            {
+                loop {
+                    match fraction_digits.pop() {
+                        Some(d) if d + 1 < radix => {
+                            fraction_digits.push(d + 1);
+                            break;
+                        }
+                        Some(_) => {}
+                        None => {
+                            integer += 1.0;
+                            break;
+                        }
+                    }
+                }
+                break;
+            }
+            if fraction < delta {
+                break;
+            }
+        }
+    }
+
+    // 整数部は2^53以上だと下の桁が0になるので、割って0を並べてから1桁ずつ求める
+    let mut digits = Vec::new();
+    while integer / radix_f64 >= 9007199254740992.0 {
+        integer /= radix_f64;
+        digits.push('0');
+    }
+    loop {
+        let remainder = libm::fmod(integer, radix_f64);
+        digits.push(to_digit(remainder));
+        integer = (integer - remainder) / radix_f64;
+        if integer <= 0.0 {
+            break;
+        }
+    }
+    if value < 0.0 {
+        digits.push('-');
+    }
+    let mut result: String = digits.iter().rev().collect();
+    if !fraction_digits.is_empty() {
+        result.push('.');
+        result.extend(fraction_digits.iter().map(|d| to_digit(*d as f64)));
+    }
+    Ok(RuntimeValue::StringLiteral(result))
+}
+
+// JSON.parseとJSON.stringifyで入れ子にできる配列とオブジェクトの深さの上限
+pub const MAX_JSON_DEPTH: usize = 512;
+
+// JSON.parse(text)。fetchのResponse.json()でも使う
+pub fn json_parse(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let text = arg(&args, 0).to_string();
+    let mut parser = JsonParser {
+        input: text.chars().collect(),
+        pos: 0,
+        depth: 0,
+        runtime,
+    };
+    match parser.parse() {
+        Ok(value) => Ok(value),
+        Err(message) => Err(runtime.throw_error("SyntaxError", message)),
+    }
+}
+
+// JSONの文字列を読んでJSの値を作る
+struct JsonParser<'a> {
+    input: Vec<char>,
+    pos: usize,
+    // 読んでいる途中の配列とオブジェクトの数
+    depth: usize,
+    // 作成するオブジェクトを登録するランタイム
+    runtime: &'a JsRuntime,
+}
+
+impl JsonParser<'_> {
+    fn parse(&mut self) -> Result<RuntimeValue, String> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        if self.pos < self.input.len() {
+            return Err(self.unexpected());
+        }
+        Ok(value)
+    }
+
+    fn unexpected(&self) -> String {
+        match self.input.get(self.pos) {
+            Some(c) => format!("JSON.parse: unexpected character '{}' at {}", c, self.pos),
+            None => "JSON.parse: unexpected end of data".to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.input.get(self.pos), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn consume(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.input.get(self.pos) != Some(&c) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: RuntimeValue) -> Result<RuntimeValue, String> {
+        for c in word.chars() {
+            if self.input.get(self.pos) != Some(&c) {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<RuntimeValue, String> {
+        self.skip_whitespace();
+        match self.input.get(self.pos) {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(RuntimeValue::StringLiteral(self.string()?)),
+            Some('t') => self.literal("true", RuntimeValue::Boolean(true)),
+            Some('f') => self.literal("false", RuntimeValue::Boolean(false)),
+            Some('n') => self.literal("null", RuntimeValue::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // 深すぎる入れ子はスタックを使い切るため構文エラーにする
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<RuntimeValue, String>,
+    ) -> Result<RuntimeValue, String> {
+        if self.depth >= MAX_JSON_DEPTH {
+            return Err(format!("JSON.parse: too deeply nested at {}", self.pos));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<RuntimeValue, String> {
+        let start = self.pos;
+        while matches!(self.input.get(self.pos), Some(c) if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let number: String = self.input[start..self.pos].iter().collect();
+        match number.parse::<f64>() {
+            Ok(value) => Ok(RuntimeValue::Number(value)),
+            Err(_) => {
+                self.pos = start;
+                Err(self.unexpected())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.consume('"')?;
+        let mut result = String::new();
+        loop {
+            let c = match self.input.get(self.pos) {
+                Some(c) => *c,
+                None => return Err(self.unexpected()),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escaped = match self.input.get(self.pos) {
+                        Some(c) => *c,
+                        None => return Err(self.unexpected()),
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => result.push(escaped),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'u' => {
+                            let hex: String = self.input.iter().skip(self.pos).take(4).collect();
+                            let code = match u32::from_str_radix(&hex, 16) {
+                                Ok(code) if hex.len() == 4 => code,
+                                _ => return Err(self.unexpected()),
+                            };
+                            self.pos += 4;
+                            result.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.unexpected());
+                        }
+                    }
+                }
+                _ => result.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<RuntimeValue, String> {
+        self.consume('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.input.get(self.pos) == Some(&']') {
+            self.pos += 1;
+            return Ok(new_array(self.runtime, elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.input.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(new_array(self.runtime, elements));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<RuntimeValue, String> {
+        self.consume('{')?;
+        let mut object = JsObject::with_prototype(Some(self.runtime.object_prototype()));
+        self.skip_whitespace();
+        if self.input.get(self.pos) == Some(&'}') {
+            self.pos += 1;
+            return Ok(new_object(self.runtime, object));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.consume(':')?;
+            object.set(key, self.value()?);
+            self.skip_whitespace();
+            match self.input.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(new_object(self.runtime, object));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+}
+
+fn json_stringify(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    // 第3引数はインデントに使う空白の数か文字列
+    let gap = match arg(&args, 2) {
+        RuntimeValue::Number(n) => " ".repeat(to_integer(n).clamp(0.0, 10.0) as usize),
+        RuntimeValue::StringLiteral(s) => s.chars().take(10).collect(),
+        _ => String::new(),
+    };
+
+    let mut serializer = JsonSerializer {
+        gap,
+        stack: Vec::new(),
+    };
+    match serializer.serialize(&arg(&args, 0), "") {
+        Ok(Some(json)) => Ok(RuntimeValue::StringLiteral(json)),
+        Ok(None) => Ok(RuntimeValue::Undefined),
+        Err((name, message)) => Err(runtime.throw_error(name, message)),
+    }
+}
+
+// JSの値をJSONの文字列にする
+struct JsonSerializer {
+    gap: String,
+    // 循環参照を見つけるため、変換中のオブジェクトと配列のアドレスを保持する
+    stack: Vec<usize>,
+}
+
+impl JsonSerializer {
+    // undefinedと関数はJSONで表せないためNoneを返す。エラーは例外の名前とメッセージ
+    fn serialize(
+        &mut self,
+        value: &RuntimeValue,
+        indent: &str,
+    ) -> Result<Option<String>, (&'static str, String)> {
+        let json = match value {
+            RuntimeValue::Number(n) if n.is_finite() => number_to_string(*n),
+            RuntimeValue::Number(_) | RuntimeValue::Null => "null".to_string(),
+            RuntimeValue::Boolean(b) => b.to_string(),
+            RuntimeValue::StringLiteral(s) => quote(s),
+            RuntimeValue::Undefined | RuntimeValue::Function(_) => return Ok(None),
+            RuntimeValue::Array(elements) => {
+                let address = Rc::as_ptr(elements) as usize;
+                self.enter(address)?;
+                let inner = format!("{}{}", indent, self.gap);
+                let mut items = Vec::new();
+                for element in RefCell::borrow(elements).iter() {
+                    let item = self.serialize(element, &inner)?;
+                    items.push(item.unwrap_or_else(|| "null".to_string()));
+                }
+                self.stack.pop();
+                self.wrap('[', ']', items, indent)
+            }
+            RuntimeValue::Object(object) => {
+                let address = Rc::as_ptr(object) as usize;
+                self.enter(address)?;
+                let inner = format!("{}{}", indent, self.gap);
+                let separator = if self.gap.is_empty() { ":" } else { ": " };
+                let mut items = Vec::new();
+                for (key, v) in own_entries(value) {
+                    if let Some(item) = self.serialize(&v, &inner)? {
+                        items.push(format!("{}{}{}", quote(&key), separator, item));
+                    }
+                }
+                self.stack.pop();
+                self.wrap('{', '}', items, indent)
+            }
+        };
+        Ok(Some(json))
+    }
+
+    fn enter(&mut self, address: usize) -> Result<(), (&'static str, String)> {
+        if self.stack.contains(&address) {
+            return Err(("TypeError", "cyclic object value".to_string()));
+        }
+        if self.stack.len() >= MAX_JSON_DEPTH {
+            return Err((
+                "RangeError",
+                "JSON.stringify: too deeply nested".to_string(),
+            ));
+        }
+        self.stack.push(address);
+        Ok(())
+    }
+
+    // インデントが指定された場合は要素ごとに改行する
+    fn wrap(&self, open: char, close: char, items: Vec<String>, indent: &str) -> String {
+        if items.is_empty() {
+            return format!("{}{}", open, close);
+        }
+        if self.gap.is_empty() {
+            return format!("{}{}{}", open, items.join(","), close);
+        }
+        let inner = format!("{}{}", indent, self.gap);
+        let separator = format!(",\n{}", inner);
+        format!(
+            "{}\n{}{}\n{}{}",
+            open,
+            inner,
+            items.join(&separator),
+            indent,
+            close
+        )
+    }
+}
+
+// 文字列をJSONの文字列リテラルにする
+fn quote(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
@@ -0,0 +1,299 @@
+// コンパイラが出力し、VMが実行するバイトコード
+
+use crate::renderer::js::ast::{DeclarationKind, MethodKind};
+use crate::renderer::js::console::INLINE_SOURCE;
+use crate::renderer::js::runtime::RuntimeValue;
+use crate::renderer::js::token::Position;
+use alloc::{rc::Rc, string::String, vec::Vec};
+
+#[derive(Debug, Clone, PartialEq)]
+// スタックマシンの命令。コメントの[a, b]は命令の前後のスタックの上の部分を表す
+pub enum Instruction {
+    // 定数表の値を積む
+    Constant(usize),
+    Undefined,
+    Null,
+    Boolean(bool),
+    Pop,
+    // [a] -> [a, a]
+    Dup,
+    // [a, b] -> [a, b, a, b]
+    Dup2,
+    // 一番上の値をn個の値の下へ移す。Bury(2)は[a, b, c] -> [c, a, b]
+    Bury(usize),
+
+    // スコープの変数。depthは現在のスコープから外側へたどる数、indexはスコープの中の位置
+    GetLocal(usize, usize),
+    // [value] -> []
+    SetLocal(usize, usize),
+    // letやconstの宣言で変数を初期化する。[value] -> []
+    InitLocal(usize, usize),
+    // モジュールのスコープがimportした変数。リンク時に公開元のモジュールの変数に結び付けられる
+    GetImport(usize, usize),
+    // グローバル変数。名前はnamesの位置で指定する
+    GetGlobal(usize),
+    // [value] -> []
+    SetGlobal(usize),
+    // プログラムの実行前に変数を宣言する。letとconstは初期化されていない状態になる
+    DeclareGlobal(usize, DeclarationKind),
+    // [value] -> []
+    InitGlobal(usize, DeclarationKind),
+    // scopesの位置で指定したスコープを作り、現在のスコープにする
+    PushScope(usize),
+    PopScope,
+    // forの繰り返しごとに、現在のスコープを複製する
+    CopyScope,
+
+    // [left, right] -> [result]
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Exponent,
+    ShiftLeft,
+    ShiftRight,
+    UnsignedShiftRight,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    InstanceOf,
+    In,
+    Equal,
+    NotEqual,
+    StrictEqual,
+    StrictNotEqual,
+    // [value] -> [result]
+    Not,
+    Negate,
+    ToNumber,
+    ToString,
+    BitNot,
+    TypeOf,
+    // typeofの対象のグローバル変数。宣言されていない場合はReferenceErrorにせずundefinedを積む
+    TypeOfGlobal(usize),
+    // [object, key] -> [result]
+    DeleteProperty,
+
+    // instructionsの位置へ移動する
+    Jump(usize),
+    // [value] -> []
+    JumpIfFalse(usize),
+    // 偽の場合は値を残して移動し、真の場合は値を取り除く。&&で使う
+    JumpIfFalseOrPop(usize),
+    // 真の場合は値を残して移動し、偽の場合は値を取り除く。||で使う
+    JumpIfTrueOrPop(usize),
+    // [value] -> [iterator]。trueの場合はfor-inのキー、falseの場合はfor-ofの値を順に返す
+    Iterate(bool),
+    // [iterator] -> [value]。繰り返しが終わった場合は何も積まずにinstructionsの位置へ移動する
+    IteratorNext(usize),
+    // [iterator] -> []。breakやreturnでfor-ofを抜ける時に、イテレータのreturn()を呼び出す
+    IteratorClose,
+
+    // [object, key] -> [value]
+    GetProperty,
+    // [object] -> [value]
+    GetNamed(usize),
+    // [object, key, value] -> [value]
+    SetProperty,
+    // [object, value] -> [value]
+    SetNamed(usize),
+    // [] -> [object]
+    NewObject,
+    // 正規表現リテラル。constantsの位置のパターンとフラグからRegExpオブジェクトを作る
+    NewRegExp(usize, usize),
+    // [elements...] -> [array]
+    NewArray(usize),
+    // オブジェクトリテラルのプロパティを定義する。[object, value] -> [object]
+    DefineField(usize),
+    // functionsの位置の関数をメソッドとしてオブジェクトに定義する。[object] -> [object]
+    DefineMethod {
+        key: usize,
+        function: usize,
+        kind: MethodKind,
+    },
+    // functionsの位置の関数から、現在のスコープを捕捉した関数を作る
+    Closure(usize),
+    // [parent?] -> [class]。constructorが省略された場合はNone
+    Class {
+        name: Option<usize>,
+        constructor: Option<usize>,
+        has_parent: bool,
+    },
+
+    // 次の呼び出しやプロパティアクセスで発生したエラーの位置を設定する
+    Position(usize),
+    // [this, callee, args...] -> [result]
+    Call(usize, usize),
+    // super()。クラスのコンストラクタを呼び出せる
+    SuperCall(usize, usize),
+    // [callee, args...] -> [result]
+    New(usize, usize),
+    // [value] -> 呼び出し元へ
+    Return,
+    // ジェネレータを中断して値を返す。再開されるとnext()の引数を積む。[value] -> [sent]
+    // return()で再開された場合は、引数を積んでinstructionsの位置へ移動する
+    Yield(usize),
+    // yield*。[iterator] -> [iterator]の状態で委譲先の値を返して中断し、再開した方法で委譲先を進める
+    // 委譲先が終わると[result]にし、return()で終わった場合はinstructionsの位置へ移動する
+    Delegate(usize),
+    // async関数を中断し、Promiseが解決されると結果を積む。[value] -> [result]
+    Await,
+
+    // [value] -> 例外
+    Throw(Position),
+    // 例外が発生した場合の移動先を登録する。finallyの場合は例外を再送出するために保存する
+    PushHandler {
+        target: usize,
+        is_finally: bool,
+    },
+    PopHandler,
+    // finally節の終わりで、保存した例外を再送出する
+    Rethrow,
+    // コンパイル時に分かったエラーを実行時に投げる
+    ThrowError(&'static str, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionKind {
+    // thisを持つ
+    Normal,
+    // thisとsuperを外側のスコープから使う
+    Arrow,
+    // thisとsuperを持つ。クラスのコンストラクタを含む
+    Method,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// スコープの変数
+pub struct Binding {
+    pub name: String,
+    // letやconstのように、宣言が実行されるまで使えない変数
+    pub lexical: bool,
+    pub mutable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+// スコープが持つ変数の一覧。変数はコンパイル時に位置が決まる
+pub struct ScopeInfo {
+    pub bindings: Vec<Binding>,
+    // モジュールのスコープの場合、importした変数の名前
+    pub imports: Vec<String>,
+}
+
+impl ScopeInfo {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            imports: Vec::new(),
+        }
+    }
+
+    // 変数を追加して位置を返す。すでに存在する場合はその位置を返す
+    pub fn declare(&mut self, name: &str, lexical: bool, mutable: bool) -> usize {
+        if let Some(index) = self.index_of(name) {
+            return index;
+        }
+        self.bindings.push(Binding {
+            name: name.into(),
+            lexical,
+            mutable,
+        });
+        self.bindings.len() - 1
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.bindings.iter().position(|b| b.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// 関数呼び出しの情報。エラーメッセージで使う
+pub struct CallSite {
+    pub name: String,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// プログラムか関数1つ分のバイトコード
+pub struct Code {
+    pub name: Option<String>,
+    pub kind: FunctionKind,
+    // async関数とジェネレータは、呼び出すと中断できるフレームを作る
+    pub is_async: bool,
+    pub is_generator: bool,
+    pub instructions: Vec<Instruction>,
+    // 数値と文字列の定数表
+    pub constants: Vec<RuntimeValue>,
+    // グローバル変数とプロパティの名前
+    pub names: Vec<String>,
+    // 内側で定義された関数
+    pub functions: Vec<Rc<Code>>,
+    // ブロックのスコープ
+    pub scopes: Vec<Rc<ScopeInfo>>,
+    pub call_sites: Vec<CallSite>,
+    // 関数のスコープ。プログラムの場合はNone
+    pub scope: Option<Rc<ScopeInfo>>,
+    // 名前付きの関数式で、自身の名前だけを持つスコープ
+    pub self_scope: Option<Rc<ScopeInfo>>,
+    // 関数のスコープの中のthis、super、引数の位置
+    pub this_slot: Option<usize>,
+    pub super_slot: Option<usize>,
+    pub params: Vec<usize>,
+    // 関数が定義されたスクリプトのURL。インラインのスクリプトは"inline"
+    pub source: Rc<str>,
+}
+
+impl Code {
+    pub fn new(name: Option<String>, kind: FunctionKind) -> Self {
+        Self {
+            name,
+            kind,
+            is_async: false,
+            is_generator: false,
+            instructions: Vec::new(),
+            constants: Vec::new(),
+            names: Vec::new(),
+            functions: Vec::new(),
+            scopes: Vec::new(),
+            call_sites: Vec::new(),
+            scope: None,
+            self_scope: None,
+            this_slot: None,
+            super_slot: None,
+            params: Vec::new(),
+            source: Rc::from(INLINE_SOURCE),
+        }
+    }
+
+    // 命令を追加して位置を返す
+    pub fn emit(&mut self, instruction: Instruction) -> usize {
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+
+    // 次に追加される命令の位置
+    pub fn next(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn add_constant(&mut self, value: RuntimeValue) -> usize {
+        if let Some(index) = self.constants.iter().position(|c| *c == value) {
+            return index;
+        }
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    pub fn add_name(&mut self, name: &str) -> usize {
+        if let Some(index) = self.names.iter().position(|n| n == name) {
+            return index;
+        }
+        self.names.push(name.into());
+        self.names.len() - 1
+    }
+}
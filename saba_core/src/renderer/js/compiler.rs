@@ -0,0 +1,1885 @@
+// ASTをバイトコードに変換する
+// 変数はコンパイル時にスコープの位置を決め、どのスコープにもない変数はグローバル変数として名前で探す
+
+use crate::renderer::js::ast::{DeclarationKind, MethodKind, Node, Program};
+use crate::renderer::js::bytecode::{
+    Binding, CallSite, Code, FunctionKind, Instruction, ScopeInfo,
+};
+use crate::renderer::js::runtime::{callee_name, RuntimeValue};
+use crate::renderer::js::token::Position;
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::borrow::Borrow;
+
+// プログラムをバイトコードに変換する
+pub fn compile(program: &Program, source: Rc<str>) -> Code {
+    let mut code = Code::new(None, FunctionKind::Normal);
+    code.source = source.clone();
+    let mut compiler = Compiler {
+        functions: Vec::from([FunctionState::new(code)]),
+        source,
+    };
+    let body: Vec<Option<Rc<Node>>> = program.body().iter().map(|n| Some(n.clone())).collect();
+
+    // プログラムの変数はグローバル変数として、実行前に宣言する
+    let mut vars = Vec::new();
+    for statement in &body {
+        collect_vars(statement, &mut vars);
+    }
+    for name in vars {
+        compiler.emit_global(&name, Instruction::DeclareGlobal, DeclarationKind::Var);
+    }
+    for binding in block_bindings(&body) {
+        if binding.lexical {
+            let kind = if binding.mutable {
+                DeclarationKind::Let
+            } else {
+                DeclarationKind::Const
+            };
+            compiler.emit_global(&binding.name, Instruction::DeclareGlobal, kind);
+        }
+    }
+
+    compiler.hoist_functions(&body);
+    // 最後の文が式文の場合は、その値をプログラムの結果にする
+    let last = match body.last().and_then(|n| n.as_deref()) {
+        Some(Node::ExpressionStatement(expression)) => Some(expression.clone()),
+        _ => None,
+    };
+    let statements = if last.is_some() {
+        &body[..body.len() - 1]
+    } else {
+        &body[..]
+    };
+    for statement in statements {
+        compiler.statement(statement);
+    }
+    match &last {
+        Some(expression) => compiler.expression(expression),
+        None => {
+            compiler.emit(Instruction::Undefined);
+        }
+    }
+    compiler.emit(Instruction::Return);
+
+    compiler
+        .functions
+        .pop()
+        .expect("program should be compiled")
+        .code
+}
+
+// export defaultの式の値を保存する変数の名前。ソースコードからは参照できない
+pub const DEFAULT_EXPORT: &str = "*default*";
+
+// for-inとfor-ofの繰り返しの状態と、switch文の値を置く変数の名前
+const ITERATOR: &str = "*iterator*";
+const DISCRIMINANT: &str = "*discriminant*";
+
+// モジュールをバイトコードに変換する
+// モジュールの変数はグローバル変数にせず、codeのscopeで表すモジュールのスコープに置く
+pub fn compile_module(program: &Program, source: Rc<str>) -> Code {
+    let body = module_body(program);
+
+    let mut scope = ScopeInfo::new();
+    for node in program.body() {
+        if let Node::ImportDeclaration { specifiers, .. } = node.borrow() {
+            for (imported, local) in specifiers {
+                // 名前空間オブジェクトはリンク時にモジュールのスコープの変数に設定する
+                if imported == "*" {
+                    scope.declare(local, false, false);
+                } else if !scope.imports.contains(local) {
+                    scope.imports.push(local.clone());
+                }
+            }
+        }
+    }
+    let mut vars = Vec::new();
+    for statement in &body {
+        collect_vars(statement, &mut vars);
+    }
+    for name in vars {
+        scope.declare(&name, false, true);
+    }
+    for binding in block_bindings(&body) {
+        scope.declare(&binding.name, binding.lexical, binding.mutable);
+    }
+
+    let scope = Rc::new(scope);
+    let mut state = FunctionState::new(Code::new(None, FunctionKind::Normal));
+    state.code.scope = Some(scope.clone());
+    state.code.source = source.clone();
+    state.scopes.push(scope);
+    let mut compiler = Compiler {
+        functions: Vec::from([state]),
+        source,
+    };
+    compiler.hoist_functions(&body);
+    for statement in &body {
+        compiler.statement(statement);
+    }
+    compiler.emit(Instruction::Undefined);
+    compiler.emit(Instruction::Return);
+
+    compiler
+        .functions
+        .pop()
+        .expect("module should be compiled")
+        .code
+}
+
+// import文とexport文を取り除き、exportされた宣言とexport defaultの式を普通の文にする
+fn module_body(program: &Program) -> Vec<Option<Rc<Node>>> {
+    let mut body = Vec::new();
+    for node in program.body() {
+        match node.borrow() {
+            Node::ExportNamedDeclaration {
+                declaration: Some(declaration),
+                ..
+            } => body.push(Some(declaration.clone())),
+            Node::ExportDefaultDeclaration { declaration } => {
+                let statement = match declaration.as_deref() {
+                    Some(Node::FunctionDeclaration {
+                        id: None,
+                        params,
+                        body,
+                        is_async,
+                        is_generator,
+                    }) => Node::new_function_declaration(
+                        Node::new_identifier(DEFAULT_EXPORT.to_string()),
+                        params.clone(),
+                        body.clone(),
+                        *is_async,
+                        *is_generator,
+                    ),
+                    Some(Node::FunctionDeclaration { .. }) => declaration.clone(),
+                    Some(Node::ClassDeclaration { id: Some(_), .. }) => declaration.clone(),
+                    Some(Node::ClassDeclaration {
+                        id: None,
+                        super_class,
+                        body,
+                    }) => default_declaration(Node::new_class_expression(
+                        None,
+                        super_class.clone(),
+                        body.clone(),
+                    )),
+                    _ => default_declaration(declaration.clone()),
+                };
+                body.push(statement);
+            }
+            Node::ImportDeclaration { .. }
+            | Node::ExportNamedDeclaration { .. }
+            | Node::ExportAllDeclaration { .. } => {}
+            _ => body.push(Some(node.clone())),
+        }
+    }
+    body
+}
+
+// const *default* = expression;
+fn default_declaration(expression: Option<Rc<Node>>) -> Option<Rc<Node>> {
+    Node::new_variable_declaration(
+        DeclarationKind::Const,
+        Vec::from([Node::new_variable_declarator(
+            Node::new_identifier(DEFAULT_EXPORT.to_string()),
+            expression,
+        )]),
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+// 代入する値の求め方
+enum Assign<'a> {
+    // a = b
+    Value(&'a Option<Rc<Node>>),
+    // a += bのような複合代入。元の値と右辺を演算子で計算する
+    Compound(&'a str, &'a Option<Rc<Node>>),
+    // ++aとa++。後置の場合は更新前の値が式の結果になる
+    Update { increment: bool, postfix: bool },
+    // for-inとfor-ofの左辺。代入する値はすでにスタックにある
+    Stack,
+}
+
+impl Assign<'_> {
+    fn is_postfix(&self) -> bool {
+        matches!(self, Assign::Update { postfix: true, .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+// 変数の場所。depthは現在のスコープから外側へたどる数
+enum Variable {
+    Local {
+        depth: usize,
+        index: usize,
+        mutable: bool,
+    },
+    // モジュールのスコープがimportした変数。再代入できない
+    Import {
+        depth: usize,
+        index: usize,
+    },
+    Global,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// breakとcontinueで抜けられる文の種類
+enum LoopKind {
+    // 繰り返し文。continueの移動先になる
+    Iteration,
+    // ラベルのないbreakでも抜けられる
+    Switch,
+    // ラベルを指定したbreakでだけ抜けられる
+    Labeled,
+}
+
+#[derive(Debug, Clone)]
+// コンパイル中のループ。break、continueの移動先は、ループのコンパイルが終わった後に設定する
+// switch文とラベルが付いた文も、breakで抜ける移動先として同じように扱う
+struct Loop {
+    kind: LoopKind,
+    labels: Vec<String>,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+    scope_depth: usize,
+    handler_depth: usize,
+    finalizer_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+// コンパイル中のtry文のfinally節。return、break、continueで抜ける前に実行する
+struct Finalizer {
+    block: Option<Rc<Node>>,
+    // for-ofの場合、finally節の代わりに閉じるイテレータを持つ変数
+    iterator: Option<usize>,
+    scope_depth: usize,
+    handler_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+struct FunctionState {
+    code: Code,
+    // 外側から順に並んだスコープ。プログラムの場合、グローバルスコープは含まない
+    scopes: Vec<Rc<ScopeInfo>>,
+    loops: Vec<Loop>,
+    // 次にコンパイルする繰り返し文かswitch文に付いたラベル
+    labels: Vec<String>,
+    finalizers: Vec<Finalizer>,
+    // 実行時に登録されている例外ハンドラの数
+    handler_depth: usize,
+}
+
+impl FunctionState {
+    fn new(code: Code) -> Self {
+        Self {
+            code,
+            scopes: Vec::new(),
+            loops: Vec::new(),
+            labels: Vec::new(),
+            finalizers: Vec::new(),
+            handler_depth: 0,
+        }
+    }
+}
+
+struct Compiler {
+    // コンパイル中の関数。最後が一番内側の関数
+    functions: Vec<FunctionState>,
+    // スクリプトのURL。関数のバイトコードに記録する
+    source: Rc<str>,
+}
+
+impl Compiler {
+    fn state(&mut self) -> &mut FunctionState {
+        self.functions
+            .last_mut()
+            .expect("function state should exist")
+    }
+
+    fn code(&mut self) -> &mut Code {
+        &mut self.state().code
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code().emit(instruction)
+    }
+
+    // 移動命令の移動先を、次に追加される命令にする
+    fn patch(&mut self, at: usize) {
+        let next = self.code().next();
+        match &mut self.code().instructions[at] {
+            Instruction::Jump(target)
+            | Instruction::JumpIfFalse(target)
+            | Instruction::JumpIfFalseOrPop(target)
+            | Instruction::JumpIfTrueOrPop(target)
+            | Instruction::IteratorNext(target)
+            | Instruction::Yield(target)
+            | Instruction::Delegate(target)
+            | Instruction::PushHandler { target, .. } => *target = next,
+            _ => {}
+        }
+    }
+
+    fn emit_global(
+        &mut self,
+        name: &str,
+        instruction: fn(usize, DeclarationKind) -> Instruction,
+        kind: DeclarationKind,
+    ) {
+        let index = self.code().add_name(name);
+        self.emit(instruction(index, kind));
+    }
+
+    fn constant(&mut self, value: RuntimeValue) {
+        let index = self.code().add_constant(value);
+        self.emit(Instruction::Constant(index));
+    }
+
+    // 内側の関数から順にスコープを探す
+    fn resolve(&self, name: &str) -> Variable {
+        let mut depth = 0;
+        for state in self.functions.iter().rev() {
+            for scope in state.scopes.iter().rev() {
+                if let Some(index) = scope.index_of(name) {
+                    return Variable::Local {
+                        depth,
+                        index,
+                        mutable: scope.bindings[index].mutable,
+                    };
+                }
+                if let Some(index) = scope.imports.iter().position(|n| n == name) {
+                    return Variable::Import { depth, index };
+                }
+                depth += 1;
+            }
+        }
+        Variable::Global
+    }
+
+    fn push_scope(&mut self, scope: ScopeInfo) {
+        let scope = Rc::new(scope);
+        let state = self.state();
+        state.code.scopes.push(scope.clone());
+        let index = state.code.scopes.len() - 1;
+        state.scopes.push(scope);
+        self.emit(Instruction::PushScope(index));
+    }
+
+    fn pop_scope(&mut self) {
+        self.state().scopes.pop();
+        self.emit(Instruction::PopScope);
+    }
+
+    // 関数宣言はスコープの先頭で関数を作る
+    fn hoist_functions(&mut self, statements: &[Option<Rc<Node>>]) {
+        for statement in statements.iter().flatten() {
+            if let Node::FunctionDeclaration {
+                id,
+                params,
+                body,
+                is_async,
+                is_generator,
+            } = statement.borrow()
+            {
+                if let Some(Node::Identifier(name)) = id.as_deref() {
+                    // export default function () {}の関数名はdefault
+                    let function_name = if name == DEFAULT_EXPORT {
+                        "default"
+                    } else {
+                        name
+                    };
+                    let code = function_code(
+                        Some(function_name.to_string()),
+                        FunctionKind::Normal,
+                        *is_async,
+                        *is_generator,
+                    );
+                    let function = self.function(code, params, body, false);
+                    self.emit(Instruction::Closure(function));
+                    self.initialize(name, DeclarationKind::Var);
+                }
+            }
+        }
+    }
+
+    // 宣言された変数をスタックの上の値で初期化する
+    fn initialize(&mut self, name: &str, kind: DeclarationKind) {
+        match self.resolve(name) {
+            Variable::Local { depth, index, .. } => {
+                self.emit(Instruction::InitLocal(depth, index));
+            }
+            Variable::Import { .. } => {
+                self.emit(Instruction::Pop);
+            }
+            Variable::Global => self.emit_global(name, Instruction::InitGlobal, kind),
+        }
+    }
+
+    fn block(&mut self, statements: &[Option<Rc<Node>>]) {
+        let bindings = block_bindings(statements);
+        let has_scope = !bindings.is_empty();
+        if has_scope {
+            self.push_scope(ScopeInfo {
+                bindings,
+                ..Default::default()
+            });
+        }
+
+        self.hoist_functions(statements);
+        for statement in statements {
+            self.statement(statement);
+        }
+
+        if has_scope {
+            self.pop_scope();
+        }
+    }
+
+    fn statement(&mut self, node: &Option<Rc<Node>>) {
+        let node = match node {
+            Some(n) => n,
+            None => return,
+        };
+
+        match node.borrow() {
+            Node::ExpressionStatement(expression) => match expression.as_deref() {
+                Some(Node::AssignmentExpression {
+                    operator,
+                    left,
+                    right,
+                }) => self.assignment(left, assign_of(operator, right), false),
+                Some(Node::UpdateExpression {
+                    operator,
+                    prefix,
+                    argument,
+                }) => self.assignment(argument, update_of(*operator, *prefix), false),
+                _ => {
+                    self.expression(expression);
+                    self.emit(Instruction::Pop);
+                }
+            },
+            Node::VariableDeclaration { kind, declarations } => {
+                for declaration in declarations.iter().flatten() {
+                    if let Node::VariableDeclarator { id, init } = declaration.borrow() {
+                        let name = match id.as_deref() {
+                            Some(Node::Identifier(name)) => name,
+                            _ => continue,
+                        };
+                        // 初期値がないvarは、すでに宣言されている変数の値を変えない
+                        if *kind == DeclarationKind::Var && init.is_none() {
+                            continue;
+                        }
+                        self.expression(init);
+                        self.initialize(name, *kind);
+                    }
+                }
+            }
+            // 関数宣言はhoist_functionsで処理する
+            Node::FunctionDeclaration { .. } => {}
+            Node::ClassDeclaration {
+                id,
+                super_class,
+                body,
+            } => {
+                self.class(id, super_class, body);
+                match id.as_deref() {
+                    Some(Node::Identifier(name)) => self.initialize(name, DeclarationKind::Let),
+                    _ => {
+                        self.emit(Instruction::Pop);
+                    }
+                }
+            }
+            Node::BlockStatement { body } => self.block(body),
+            Node::ReturnStatement { argument } => {
+                self.expression(argument);
+                self.unwind(0, None);
+                self.emit(Instruction::Return);
+            }
+            Node::IfStatement {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.expression(test);
+                let to_alternate = self.emit(Instruction::JumpIfFalse(0));
+                self.statement(consequent);
+                if alternate.is_some() {
+                    let to_end = self.emit(Instruction::Jump(0));
+                    self.patch(to_alternate);
+                    self.statement(alternate);
+                    self.patch(to_end);
+                } else {
+                    self.patch(to_alternate);
+                }
+            }
+            Node::WhileStatement { test, body } => {
+                let start = self.code().next();
+                self.expression(test);
+                let to_end = self.emit(Instruction::JumpIfFalse(0));
+                self.push_loop(LoopKind::Iteration);
+                self.statement(body);
+                let l = self.state().loops.pop().expect("loop should exist");
+                for at in l.continues {
+                    self.patch_to(at, start);
+                }
+                self.emit(Instruction::Jump(start));
+                self.patch(to_end);
+                for at in l.breaks {
+                    self.patch(at);
+                }
+            }
+            Node::DoWhileStatement { body, test } => {
+                let start = self.code().next();
+                self.push_loop(LoopKind::Iteration);
+                self.statement(body);
+                let l = self.state().loops.pop().expect("loop should exist");
+                for at in l.continues {
+                    self.patch(at);
+                }
+                self.expression(test);
+                let to_end = self.emit(Instruction::JumpIfFalse(0));
+                self.emit(Instruction::Jump(start));
+                self.patch(to_end);
+                for at in l.breaks {
+                    self.patch(at);
+                }
+            }
+            Node::ForStatement {
+                init,
+                test,
+                update,
+                body,
+            } => self.for_statement(init, test, update, body),
+            Node::ForInStatement { left, right, body } => {
+                self.for_in_of_statement(left, right, body, true)
+            }
+            Node::ForOfStatement { left, right, body } => {
+                self.for_in_of_statement(left, right, body, false)
+            }
+            Node::SwitchStatement {
+                discriminant,
+                cases,
+            } => self.switch_statement(discriminant, cases),
+            Node::LabeledStatement { label, body } => {
+                self.state().labels.push(label.clone());
+                let takes_labels = matches!(
+                    body.as_deref(),
+                    Some(Node::WhileStatement { .. })
+                        | Some(Node::DoWhileStatement { .. })
+                        | Some(Node::ForStatement { .. })
+                        | Some(Node::ForInStatement { .. })
+                        | Some(Node::ForOfStatement { .. })
+                        | Some(Node::SwitchStatement { .. })
+                        | Some(Node::LabeledStatement { .. })
+                );
+                if takes_labels {
+                    self.statement(body);
+                } else {
+                    // ブロックなどに付いたラベルは、breakで抜ける移動先だけになる
+                    self.push_loop(LoopKind::Labeled);
+                    self.statement(body);
+                    let l = self.state().loops.pop().expect("loop should exist");
+                    for at in l.breaks {
+                        self.patch(at);
+                    }
+                }
+            }
+            Node::BreakStatement { label } => self.jump(label, false),
+            Node::ContinueStatement { label } => self.jump(label, true),
+            Node::ThrowStatement { argument, position } => {
+                self.expression(argument);
+                self.emit(Instruction::Throw(*position));
+            }
+            Node::TryStatement {
+                block,
+                param,
+                handler,
+                finalizer,
+            } => self.try_statement(block, param, handler, finalizer),
+            _ => {
+                self.expression(&Some(node.clone()));
+                self.emit(Instruction::Pop);
+            }
+        }
+    }
+
+    fn patch_to(&mut self, at: usize, target: usize) {
+        if let Instruction::Jump(t) = &mut self.code().instructions[at] {
+            *t = target;
+        }
+    }
+
+    fn push_loop(&mut self, kind: LoopKind) {
+        let state = self.state();
+        let l = Loop {
+            kind,
+            labels: core::mem::take(&mut state.labels),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            scope_depth: state.scopes.len(),
+            handler_depth: state.handler_depth,
+            finalizer_depth: state.finalizers.len(),
+        };
+        state.loops.push(l);
+    }
+
+    // breakとcontinue。移動先がない場合は構文解析でエラーになっている
+    fn jump(&mut self, label: &Option<String>, is_continue: bool) {
+        let found = self.state().loops.iter().rposition(|l| {
+            let is_target = match label {
+                Some(label) => l.labels.contains(label),
+                None => l.kind != LoopKind::Labeled,
+            };
+            is_target && (!is_continue || l.kind == LoopKind::Iteration)
+        });
+        let index = match found {
+            Some(index) => index,
+            None => return,
+        };
+        let l = self.state().loops[index].clone();
+        self.unwind(l.finalizer_depth, Some((l.scope_depth, l.handler_depth)));
+        let at = self.emit(Instruction::Jump(0));
+        let l = &mut self.state().loops[index];
+        if is_continue {
+            l.continues.push(at);
+        } else {
+            l.breaks.push(at);
+        }
+    }
+
+    // for-inはキーを、for-ofは値を順に左辺に代入する
+    // 繰り返しの状態は、breakで抜けてもスタックに残らないように隠れた変数に置く
+    fn for_in_of_statement(
+        &mut self,
+        left: &Option<Rc<Node>>,
+        right: &Option<Rc<Node>>,
+        body: &Option<Rc<Node>>,
+        is_for_in: bool,
+    ) {
+        let declaration = match left.as_deref() {
+            Some(Node::VariableDeclaration { kind, declarations }) => {
+                match declarations.first().and_then(|d| d.as_deref()) {
+                    Some(Node::VariableDeclarator { id, .. }) => match id.as_deref() {
+                        Some(Node::Identifier(name)) => Some((*kind, name.clone())),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let mut scope = ScopeInfo::new();
+        if let Some((kind, name)) = &declaration {
+            if *kind != DeclarationKind::Var {
+                scope.declare(name, true, *kind == DeclarationKind::Let);
+            }
+        }
+        let has_bindings = !scope.bindings.is_empty();
+        let iterator = scope.declare(ITERATOR, false, false);
+        self.push_scope(scope);
+
+        self.expression(right);
+        self.emit(Instruction::Iterate(is_for_in));
+        self.emit(Instruction::InitLocal(0, iterator));
+
+        // returnや外側のループへのbreakで抜ける場合、finally節と同じようにイテレータを閉じる
+        if !is_for_in {
+            let state = self.state();
+            let f = Finalizer {
+                block: None,
+                iterator: Some(iterator),
+                scope_depth: state.scopes.len(),
+                handler_depth: state.handler_depth,
+            };
+            state.finalizers.push(f);
+        }
+
+        let functions = self.code().functions.len();
+        let start = self.code().next();
+        self.emit(Instruction::GetLocal(0, iterator));
+        let to_end = self.emit(Instruction::IteratorNext(0));
+        match &declaration {
+            Some((kind, name)) => self.initialize(name, *kind),
+            None => self.assignment(left, Assign::Stack, false),
+        }
+
+        self.push_loop(LoopKind::Iteration);
+        self.statement(body);
+        let l = self.state().loops.pop().expect("loop should exist");
+        for at in l.continues {
+            self.patch(at);
+        }
+        // クロージャが作られた場合、繰り返しごとに別の変数を捕捉するようにスコープを複製する
+        if has_bindings && self.code().functions.len() > functions {
+            self.emit(Instruction::CopyScope);
+        }
+        self.emit(Instruction::Jump(start));
+
+        if is_for_in {
+            self.patch(to_end);
+            for at in l.breaks {
+                self.patch(at);
+            }
+        } else {
+            self.state().finalizers.pop();
+            // このループへのbreakで抜けた場合はイテレータを閉じる
+            for at in l.breaks {
+                self.patch(at);
+            }
+            self.emit(Instruction::GetLocal(0, iterator));
+            self.emit(Instruction::IteratorClose);
+            self.patch(to_end);
+        }
+        self.pop_scope();
+    }
+
+    // caseの式を順に===で比べ、一致した節から後の文をすべて実行する
+    fn switch_statement(&mut self, discriminant: &Option<Rc<Node>>, cases: &[Option<Rc<Node>>]) {
+        let mut statements = Vec::new();
+        for case in cases.iter().flatten() {
+            if let Node::SwitchCase { consequent, .. } = case.borrow() {
+                statements.extend(consequent.iter().cloned());
+            }
+        }
+        // caseの文はすべて1つのブロックにある
+        let mut scope = ScopeInfo {
+            bindings: block_bindings(&statements),
+            ..Default::default()
+        };
+        let value = scope.declare(DISCRIMINANT, false, false);
+        self.push_scope(scope);
+        self.expression(discriminant);
+        self.emit(Instruction::InitLocal(0, value));
+        self.hoist_functions(&statements);
+
+        let mut to_cases = Vec::new();
+        for case in cases.iter().flatten() {
+            if let Node::SwitchCase {
+                test: Some(test), ..
+            } = case.borrow()
+            {
+                self.emit(Instruction::GetLocal(0, value));
+                self.expression(&Some(test.clone()));
+                self.emit(Instruction::StrictNotEqual);
+                to_cases.push(Some(self.emit(Instruction::JumpIfFalse(0))));
+            } else {
+                to_cases.push(None);
+            }
+        }
+        // 一致するcaseがない場合はdefault節へ移動する
+        let to_default = self.emit(Instruction::Jump(0));
+        let mut has_default = false;
+
+        self.push_loop(LoopKind::Switch);
+        for (case, to_case) in cases.iter().flatten().zip(to_cases) {
+            match to_case {
+                Some(at) => self.patch(at),
+                None => {
+                    self.patch(to_default);
+                    has_default = true;
+                }
+            }
+            if let Node::SwitchCase { consequent, .. } = case.borrow() {
+                for statement in consequent {
+                    self.statement(statement);
+                }
+            }
+        }
+        let l = self.state().loops.pop().expect("loop should exist");
+        if !has_default {
+            self.patch(to_default);
+        }
+        for at in l.breaks {
+            self.patch(at);
+        }
+        self.pop_scope();
+    }
+
+    fn for_statement(
+        &mut self,
+        init: &Option<Rc<Node>>,
+        test: &Option<Rc<Node>>,
+        update: &Option<Rc<Node>>,
+        body: &Option<Rc<Node>>,
+    ) {
+        // 初期化式でletやconstを使った場合、ループの中だけで使える変数になる
+        let bindings = match init {
+            Some(node)
+                if !matches!(
+                    node.borrow(),
+                    Node::VariableDeclaration {
+                        kind: DeclarationKind::Var,
+                        ..
+                    }
+                ) =>
+            {
+                block_bindings(&[init.clone()])
+            }
+            _ => Vec::new(),
+        };
+        let has_scope = !bindings.is_empty();
+        if has_scope {
+            self.push_scope(ScopeInfo {
+                bindings,
+                ..Default::default()
+            });
+        }
+        self.statement(init);
+
+        let functions = self.code().functions.len();
+        let start = self.code().next();
+        let to_end = match test {
+            Some(_) => {
+                self.expression(test);
+                Some(self.emit(Instruction::JumpIfFalse(0)))
+            }
+            // 条件式が省略された場合は常にtrueとして扱う
+            None => None,
+        };
+        self.push_loop(LoopKind::Iteration);
+        self.statement(body);
+        let l = self.state().loops.pop().expect("loop should exist");
+        for at in l.continues {
+            self.patch(at);
+        }
+
+        // クロージャが作られた場合、繰り返しごとに別の変数を捕捉するようにスコープを複製する
+        if has_scope && self.code().functions.len() > functions {
+            self.emit(Instruction::CopyScope);
+        }
+        if update.is_some() {
+            self.expression(update);
+            self.emit(Instruction::Pop);
+        }
+        self.emit(Instruction::Jump(start));
+
+        if let Some(at) = to_end {
+            self.patch(at);
+        }
+        for at in l.breaks {
+            self.patch(at);
+        }
+        if has_scope {
+            self.pop_scope();
+        }
+    }
+
+    fn try_statement(
+        &mut self,
+        block: &Option<Rc<Node>>,
+        param: &Option<Rc<Node>>,
+        handler: &Option<Rc<Node>>,
+        finalizer: &Option<Rc<Node>>,
+    ) {
+        let handler_depth = self.state().handler_depth;
+        let mut to_ends = Vec::new();
+
+        // catch節がない場合、例外はfinally節を実行した後に再送出する
+        let to_handler = self.emit(Instruction::PushHandler {
+            target: 0,
+            is_finally: handler.is_none(),
+        });
+        self.state().handler_depth += 1;
+        self.push_finalizer(finalizer, handler_depth);
+        self.statement(block);
+        self.pop_finalizer(finalizer);
+        self.emit(Instruction::PopHandler);
+        self.state().handler_depth -= 1;
+        to_ends.push(self.emit(Instruction::Jump(0)));
+
+        let mut to_finalizer = to_handler;
+        if handler.is_some() {
+            // スタックの上に投げられた値がある
+            self.patch(to_handler);
+            if finalizer.is_some() {
+                to_finalizer = self.emit(Instruction::PushHandler {
+                    target: 0,
+                    is_finally: true,
+                });
+                self.state().handler_depth += 1;
+            }
+            self.push_finalizer(finalizer, handler_depth);
+
+            match param.as_deref() {
+                Some(Node::Identifier(name)) => {
+                    let mut scope = ScopeInfo::new();
+                    scope.declare(name, false, true);
+                    self.push_scope(scope);
+                    self.emit(Instruction::InitLocal(0, 0));
+                    self.statement(handler);
+                    self.pop_scope();
+                }
+                _ => {
+                    self.emit(Instruction::Pop);
+                    self.statement(handler);
+                }
+            }
+
+            self.pop_finalizer(finalizer);
+            if finalizer.is_some() {
+                self.emit(Instruction::PopHandler);
+                self.state().handler_depth -= 1;
+            }
+            to_ends.push(self.emit(Instruction::Jump(0)));
+        }
+
+        if finalizer.is_some() {
+            // 例外が発生した場合のfinally節
+            self.patch(to_finalizer);
+            self.statement(finalizer);
+            self.emit(Instruction::Rethrow);
+        }
+
+        for at in to_ends {
+            self.patch(at);
+        }
+        if finalizer.is_some() {
+            self.statement(finalizer);
+        }
+    }
+
+    fn push_finalizer(&mut self, finalizer: &Option<Rc<Node>>, handler_depth: usize) {
+        if finalizer.is_some() {
+            let state = self.state();
+            let f = Finalizer {
+                block: finalizer.clone(),
+                iterator: None,
+                scope_depth: state.scopes.len(),
+                handler_depth,
+            };
+            state.finalizers.push(f);
+        }
+    }
+
+    fn pop_finalizer(&mut self, finalizer: &Option<Rc<Node>>) {
+        if finalizer.is_some() {
+            self.state().finalizers.pop();
+        }
+    }
+
+    // return、break、continueで抜ける前に、内側から順にfinally節を実行する
+    // targetを指定した場合、そのスコープと例外ハンドラの数まで取り除く
+    fn unwind(&mut self, finalizer_depth: usize, target: Option<(usize, usize)>) {
+        let state = self.state();
+        let scopes = state.scopes.clone();
+        let handler_depth = state.handler_depth;
+        let finalizers = state.finalizers.clone();
+
+        while self.state().finalizers.len() > finalizer_depth {
+            let finalizer = self
+                .state()
+                .finalizers
+                .pop()
+                .expect("finalizer should exist");
+            self.pop_until(finalizer.scope_depth, finalizer.handler_depth);
+            match finalizer.iterator {
+                Some(iterator) => {
+                    self.emit(Instruction::GetLocal(0, iterator));
+                    self.emit(Instruction::IteratorClose);
+                }
+                None => self.statement(&finalizer.block),
+            }
+        }
+        if let Some((scope_depth, handler_depth)) = target {
+            self.pop_until(scope_depth, handler_depth);
+        }
+
+        let state = self.state();
+        state.scopes = scopes;
+        state.handler_depth = handler_depth;
+        state.finalizers = finalizers;
+    }
+
+    fn pop_until(&mut self, scope_depth: usize, handler_depth: usize) {
+        while self.state().handler_depth > handler_depth {
+            self.emit(Instruction::PopHandler);
+            self.state().handler_depth -= 1;
+        }
+        while self.state().scopes.len() > scope_depth {
+            self.pop_scope();
+        }
+    }
+
+    // 関数をコンパイルし、functionsの位置を返す。codeは名前と種類だけを設定したもの
+    fn function(
+        &mut self,
+        mut code: Code,
+        params: &[Option<Rc<Node>>],
+        body: &Option<Rc<Node>>,
+        has_self_scope: bool,
+    ) -> usize {
+        let name = code.name.clone();
+        let kind = code.kind;
+        code.source = self.source.clone();
+        let mut scope = ScopeInfo::new();
+        if kind != FunctionKind::Arrow {
+            code.this_slot = Some(scope.declare("this", false, false));
+        }
+        if kind == FunctionKind::Method {
+            code.super_slot = Some(scope.declare("super", false, false));
+        }
+        for param in params {
+            if let Some(Node::Identifier(name)) = param.as_deref() {
+                code.params.push(scope.declare(name, false, true));
+            }
+        }
+
+        let statements = match body.as_deref() {
+            Some(Node::BlockStatement { body }) => body.clone(),
+            _ => Vec::new(),
+        };
+        let mut vars = Vec::new();
+        for statement in &statements {
+            collect_vars(statement, &mut vars);
+        }
+        for name in vars {
+            scope.declare(&name, false, true);
+        }
+        for binding in block_bindings(&statements) {
+            scope.declare(&binding.name, binding.lexical, binding.mutable);
+        }
+
+        let mut state = FunctionState::new(code);
+        // 名前付きの関数式は、関数の中から自身の名前で参照できる
+        if let (Some(name), true) = (&name, has_self_scope) {
+            let mut self_scope = ScopeInfo::new();
+            self_scope.declare(name, false, true);
+            let self_scope = Rc::new(self_scope);
+            state.code.self_scope = Some(self_scope.clone());
+            state.scopes.push(self_scope);
+        }
+        let scope = Rc::new(scope);
+        state.code.scope = Some(scope.clone());
+        state.scopes.push(scope);
+
+        self.functions.push(state);
+        self.hoist_functions(&statements);
+        for statement in &statements {
+            self.statement(statement);
+        }
+        // return文が実行されなかった場合はundefined
+        self.emit(Instruction::Undefined);
+        self.emit(Instruction::Return);
+
+        let state = self.functions.pop().expect("function state should exist");
+        let code = self.code();
+        code.functions.push(Rc::new(state.code));
+        code.functions.len() - 1
+    }
+
+    // クラスを定義し、スタックにコンストラクタ関数を積む
+    fn class(
+        &mut self,
+        id: &Option<Rc<Node>>,
+        super_class: &Option<Rc<Node>>,
+        body: &[Option<Rc<Node>>],
+    ) {
+        let name = match id.as_deref() {
+            Some(Node::Identifier(name)) => Some(name.to_string()),
+            _ => None,
+        };
+        // クラスの中では、クラス自身の名前で参照できる
+        if let Some(name) = &name {
+            let mut scope = ScopeInfo::new();
+            scope.declare(name, true, false);
+            self.push_scope(scope);
+        }
+
+        if super_class.is_some() {
+            self.expression(super_class);
+        }
+        let mut constructor = None;
+        for member in body.iter().flatten() {
+            if let Node::MethodDefinition {
+                value,
+                kind: MethodKind::Constructor,
+                ..
+            } = member.borrow()
+            {
+                if let Some(Node::FunctionExpression { params, body, .. }) = value.as_deref() {
+                    let code = Code::new(name.clone(), FunctionKind::Method);
+                    constructor = Some(self.function(code, params, body, false));
+                }
+            }
+        }
+        let name_index = name.as_ref().map(|n| self.code().add_name(n));
+        self.emit(Instruction::Class {
+            name: name_index,
+            constructor,
+            has_parent: super_class.is_some(),
+        });
+
+        for member in body.iter().flatten() {
+            if let Node::MethodDefinition {
+                key,
+                value,
+                kind,
+                is_static,
+            } = member.borrow()
+            {
+                if *kind == MethodKind::Constructor {
+                    continue;
+                }
+                // 静的メソッドはクラス自身、それ以外はprototypeに定義する
+                if *is_static {
+                    self.method(key, value, *kind);
+                } else {
+                    self.emit(Instruction::Dup);
+                    let prototype = self.code().add_name("prototype");
+                    self.emit(Instruction::GetNamed(prototype));
+                    self.method(key, value, *kind);
+                    self.emit(Instruction::Pop);
+                }
+            }
+        }
+
+        if name.is_some() {
+            self.emit(Instruction::Dup);
+            self.emit(Instruction::InitLocal(0, 0));
+            self.pop_scope();
+        }
+    }
+
+    // スタックの上のオブジェクトにメソッドを定義する
+    fn method(&mut self, key: &str, value: &Option<Rc<Node>>, kind: MethodKind) {
+        if let Some(Node::FunctionExpression {
+            params,
+            body,
+            is_async,
+            is_generator,
+            ..
+        }) = value.as_deref()
+        {
+            let code = function_code(
+                Some(key.to_string()),
+                FunctionKind::Method,
+                *is_async,
+                *is_generator,
+            );
+            let function = self.function(code, params, body, false);
+            let key = self.code().add_name(key);
+            self.emit(Instruction::DefineMethod {
+                key,
+                function,
+                kind,
+            });
+        }
+    }
+
+    // thisはthisを持つ一番内側の関数のもの。どの関数の中でもない場合はundefined
+    fn this(&mut self) {
+        match self.resolve("this") {
+            Variable::Local { depth, index, .. } => self.emit(Instruction::GetLocal(depth, index)),
+            _ => self.emit(Instruction::Undefined),
+        };
+    }
+
+    fn super_base(&mut self) {
+        match self.resolve("super") {
+            Variable::Local { depth, index, .. } => self.emit(Instruction::GetLocal(depth, index)),
+            _ => self.emit(Instruction::ThrowError(
+                "SyntaxError",
+                "'super' keyword unexpected here".to_string(),
+            )),
+        };
+    }
+
+    fn call_site(&mut self, callee: &Option<Rc<Node>>, position: Position) -> usize {
+        let code = self.code();
+        code.call_sites.push(CallSite {
+            name: callee_name(callee),
+            position,
+        });
+        let site = code.call_sites.len() - 1;
+        self.emit(Instruction::Position(site));
+        site
+    }
+
+    fn arguments(&mut self, arguments: &[Option<Rc<Node>>]) -> usize {
+        for argument in arguments {
+            self.expression(argument);
+        }
+        arguments.len()
+    }
+
+    // object.propertyの場合は名前を指定した命令、object[property]の場合はキーを評価する命令
+    fn member(&mut self, property: &Option<Rc<Node>>, computed: bool) {
+        if let (Some(Node::Identifier(name)), false) = (property.as_deref(), computed) {
+            let name = self.code().add_name(name);
+            self.emit(Instruction::GetNamed(name));
+            return;
+        }
+        self.expression(property);
+        self.emit(Instruction::GetProperty);
+    }
+
+    fn unary(&mut self, operator: &str, argument: &Option<Rc<Node>>) {
+        match (operator, argument.as_deref()) {
+            // 宣言されていないグローバル変数のtypeofはReferenceErrorにならない
+            ("typeof", Some(Node::Identifier(name))) => {
+                if let Variable::Global = self.resolve(name) {
+                    let name = self.code().add_name(name);
+                    self.emit(Instruction::TypeOfGlobal(name));
+                    self.emit(Instruction::TypeOf);
+                    return;
+                }
+            }
+            (
+                "delete",
+                Some(Node::MemberExpression {
+                    object,
+                    property,
+                    computed,
+                }),
+            ) => {
+                self.expression(object);
+                match (property.as_deref(), computed) {
+                    (Some(Node::Identifier(name)), false) => {
+                        self.constant(RuntimeValue::StringLiteral(name.to_string()))
+                    }
+                    _ => self.expression(property),
+                }
+                self.emit(Instruction::DeleteProperty);
+                return;
+            }
+            // 変数は削除できない
+            ("delete", Some(Node::Identifier(_))) => {
+                self.emit(Instruction::Boolean(false));
+                return;
+            }
+            _ => {}
+        }
+
+        self.expression(argument);
+        let instruction = match operator {
+            "!" => Instruction::Not,
+            "-" => Instruction::Negate,
+            "+" => Instruction::ToNumber,
+            "~" => Instruction::BitNot,
+            "typeof" => Instruction::TypeOf,
+            "void" => {
+                self.emit(Instruction::Pop);
+                Instruction::Undefined
+            }
+            // 参照でない値のdeleteは常にtrue
+            _ => {
+                self.emit(Instruction::Pop);
+                Instruction::Boolean(true)
+            }
+        };
+        self.emit(instruction);
+    }
+
+    fn call(
+        &mut self,
+        callee: &Option<Rc<Node>>,
+        arguments: &[Option<Rc<Node>>],
+        position: Position,
+    ) {
+        let site = self.call_site(callee, position);
+        match callee.as_deref() {
+            // obj.method()のように呼ばれた場合、objがthisになる
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+            }) => {
+                // super.method()の場合は、現在のthisのままメソッドを呼び出す
+                if let Some(Node::Super) = object.as_deref() {
+                    self.this();
+                    self.super_base();
+                } else {
+                    self.expression(object);
+                    self.emit(Instruction::Dup);
+                }
+                self.member(property, *computed);
+            }
+            // super()は親クラスのコンストラクタを現在のthisで呼び出す
+            Some(Node::Super) => {
+                self.this();
+                self.super_base();
+                let constructor = self.code().add_name("constructor");
+                self.emit(Instruction::GetNamed(constructor));
+                let argc = self.arguments(arguments);
+                self.emit(Instruction::SuperCall(argc, site));
+                return;
+            }
+            _ => {
+                self.emit(Instruction::Undefined);
+                self.expression(callee);
+            }
+        }
+        let argc = self.arguments(arguments);
+        self.emit(Instruction::Call(argc, site));
+    }
+
+    // 代入する。文として使われた場合は、代入した値をスタックに残さない
+    fn assignment(&mut self, left: &Option<Rc<Node>>, assign: Assign, keep: bool) {
+        // 後置の++と--は、更新した値の代わりに更新前の値を残す
+        let pop_result = !keep || assign.is_postfix();
+        match left.as_deref() {
+            Some(Node::Identifier(name)) => {
+                match assign {
+                    Assign::Value(right) => self.expression(right),
+                    Assign::Stack => {}
+                    _ => {
+                        self.variable(name);
+                        self.updated_value(assign, keep, 0);
+                    }
+                }
+                if !pop_result {
+                    self.emit(Instruction::Dup);
+                }
+                match self.resolve(name) {
+                    Variable::Local {
+                        depth,
+                        index,
+                        mutable: true,
+                    } => {
+                        self.emit(Instruction::SetLocal(depth, index));
+                    }
+                    // 初期化前の場合はReferenceErrorを優先する
+                    Variable::Local { depth, index, .. } => {
+                        self.emit(Instruction::GetLocal(depth, index));
+                        self.emit(Instruction::ThrowError(
+                            "TypeError",
+                            format!("assignment to constant variable '{}'", name),
+                        ));
+                    }
+                    Variable::Import { .. } => {
+                        self.emit(Instruction::ThrowError(
+                            "TypeError",
+                            format!("assignment to constant variable '{}'", name),
+                        ));
+                    }
+                    Variable::Global => {
+                        let name = self.code().add_name(name);
+                        self.emit(Instruction::SetGlobal(name));
+                    }
+                }
+            }
+            // オブジェクトや配列のプロパティへの代入
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+            }) => {
+                self.expression(object);
+                if let (Some(Node::Identifier(name)), false) = (property.as_deref(), *computed) {
+                    let name = self.code().add_name(name);
+                    match assign {
+                        Assign::Value(right) => self.expression(right),
+                        // [value, object] -> [object, value]
+                        Assign::Stack => {
+                            self.emit(Instruction::Bury(1));
+                        }
+                        _ => {
+                            self.emit(Instruction::Dup);
+                            self.emit(Instruction::GetNamed(name));
+                            self.updated_value(assign, keep, 1);
+                        }
+                    }
+                    self.emit(Instruction::SetNamed(name));
+                } else {
+                    self.expression(property);
+                    match assign {
+                        Assign::Value(right) => self.expression(right),
+                        // [value, object, key] -> [object, key, value]
+                        Assign::Stack => {
+                            self.emit(Instruction::Bury(2));
+                            self.emit(Instruction::Bury(2));
+                        }
+                        _ => {
+                            self.emit(Instruction::Dup2);
+                            self.emit(Instruction::GetProperty);
+                            self.updated_value(assign, keep, 2);
+                        }
+                    }
+                    self.emit(Instruction::SetProperty);
+                }
+                if pop_result {
+                    self.emit(Instruction::Pop);
+                }
+            }
+            _ => {
+                if let Assign::Stack = assign {
+                    self.emit(Instruction::Pop);
+                }
+                if keep {
+                    self.emit(Instruction::Undefined);
+                }
+            }
+        }
+    }
+
+    // スタックの上の元の値から代入する値を計算する。後置の++と--で結果を残す場合は、
+    // 更新前の値をdepth個の値(オブジェクトとキー)の下に残す
+    fn updated_value(&mut self, assign: Assign, keep: bool, depth: usize) {
+        match assign {
+            Assign::Value(_) | Assign::Stack => {}
+            Assign::Compound(operator, right) => {
+                self.expression(right);
+                self.emit(binary_instruction(operator));
+            }
+            Assign::Update { increment, postfix } => {
+                self.emit(Instruction::ToNumber);
+                if postfix && keep {
+                    self.emit(Instruction::Dup);
+                    if depth > 0 {
+                        self.emit(Instruction::Bury(depth + 1));
+                    }
+                }
+                self.constant(RuntimeValue::Number(1.0));
+                self.emit(if increment {
+                    Instruction::Add
+                } else {
+                    Instruction::Sub
+                });
+            }
+        }
+    }
+
+    // 変数の値を積む
+    fn variable(&mut self, name: &str) {
+        match self.resolve(name) {
+            Variable::Local { depth, index, .. } => {
+                self.emit(Instruction::GetLocal(depth, index));
+            }
+            Variable::Import { depth, index } => {
+                self.emit(Instruction::GetImport(depth, index));
+            }
+            Variable::Global => {
+                let name = self.code().add_name(name);
+                self.emit(Instruction::GetGlobal(name));
+            }
+        }
+    }
+
+    // a + b + cのように左辺に続く二項演算子の式を、再帰せずに一番左の値から順にコンパイルする
+    fn binary_expression(&mut self, node: &Node) {
+        let chain = node.binary_chain();
+        if let Some(left) = chain[chain.len() - 1].binary_left() {
+            self.expression(left);
+        }
+        for node in chain.into_iter().rev() {
+            self.binary_operation(node);
+        }
+    }
+
+    // 左辺の値が積まれた状態で、右辺を評価して演算する
+    fn binary_operation(&mut self, node: &Node) {
+        match node {
+            Node::AdditiveExpression {
+                operator, right, ..
+            } => {
+                self.expression(right);
+                self.emit(match operator {
+                    '-' => Instruction::Sub,
+                    _ => Instruction::Add,
+                });
+            }
+            Node::MultiplicativeExpression {
+                operator, right, ..
+            } => {
+                self.expression(right);
+                self.emit(match operator {
+                    '/' => Instruction::Div,
+                    '%' => Instruction::Rem,
+                    _ => Instruction::Mul,
+                });
+            }
+            Node::RelationalExpression {
+                operator, right, ..
+            } => {
+                self.expression(right);
+                self.emit(match operator.as_str() {
+                    "<" => Instruction::Less,
+                    ">" => Instruction::Greater,
+                    "<=" => Instruction::LessEqual,
+                    ">=" => Instruction::GreaterEqual,
+                    "in" => Instruction::In,
+                    _ => Instruction::InstanceOf,
+                });
+            }
+            Node::EqualityExpression {
+                operator, right, ..
+            } => {
+                self.expression(right);
+                self.emit(match operator.as_str() {
+                    "==" => Instruction::Equal,
+                    "!=" => Instruction::NotEqual,
+                    "===" => Instruction::StrictEqual,
+                    _ => Instruction::StrictNotEqual,
+                });
+            }
+            // 左辺で結果が決まる場合は右辺を評価せずに左辺の値を返す
+            Node::LogicalExpression {
+                operator, right, ..
+            } => {
+                let to_end = match operator.as_str() {
+                    "&&" => self.emit(Instruction::JumpIfFalseOrPop(0)),
+                    _ => self.emit(Instruction::JumpIfTrueOrPop(0)),
+                };
+                self.expression(right);
+                self.patch(to_end);
+            }
+            Node::BinaryExpression {
+                operator, right, ..
+            } => {
+                self.expression(right);
+                self.emit(binary_instruction(operator));
+            }
+            _ => {}
+        }
+    }
+
+    // 式を評価し、結果をスタックに積む
+    fn expression(&mut self, node: &Option<Rc<Node>>) {
+        let node = match node {
+            Some(n) => n,
+            None => {
+                self.emit(Instruction::Undefined);
+                return;
+            }
+        };
+
+        match node.borrow() {
+            Node::AdditiveExpression { .. }
+            | Node::MultiplicativeExpression { .. }
+            | Node::RelationalExpression { .. }
+            | Node::EqualityExpression { .. }
+            | Node::LogicalExpression { .. }
+            | Node::BinaryExpression { .. } => self.binary_expression(node),
+            Node::UnaryExpression { operator, argument } => self.unary(operator, argument),
+            Node::SequenceExpression { expressions } => {
+                for (i, expression) in expressions.iter().enumerate() {
+                    if i > 0 {
+                        self.emit(Instruction::Pop);
+                    }
+                    self.expression(expression);
+                }
+            }
+            Node::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+            } => {
+                self.expression(test);
+                let to_alternate = self.emit(Instruction::JumpIfFalse(0));
+                self.expression(consequent);
+                let to_end = self.emit(Instruction::Jump(0));
+                self.patch(to_alternate);
+                self.expression(alternate);
+                self.patch(to_end);
+            }
+            Node::AssignmentExpression {
+                operator,
+                left,
+                right,
+            } => self.assignment(left, assign_of(operator, right), true),
+            Node::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            } => self.assignment(argument, update_of(*operator, *prefix), true),
+            Node::MemberExpression {
+                object,
+                property,
+                computed,
+            } => {
+                self.expression(object);
+                self.member(property, *computed);
+            }
+            Node::NumericLiteral(value) => self.constant(RuntimeValue::Number(*value)),
+            Node::StringLiteral(value) => {
+                self.constant(RuntimeValue::StringLiteral(value.to_string()))
+            }
+            // 文字列と、文字列に変換した式の値を順に連結する
+            Node::TemplateLiteral {
+                quasis,
+                expressions,
+            } => {
+                self.constant(RuntimeValue::StringLiteral(quasis[0].to_string()));
+                for (expression, quasi) in expressions.iter().zip(&quasis[1..]) {
+                    self.expression(expression);
+                    self.emit(Instruction::ToString);
+                    self.emit(Instruction::Add);
+                    if !quasi.is_empty() {
+                        self.constant(RuntimeValue::StringLiteral(quasi.to_string()));
+                        self.emit(Instruction::Add);
+                    }
+                }
+            }
+            // 文字列の配列を作り、raw配列をrawプロパティに定義する
+            Node::TemplateObject { cooked, raw } => {
+                for quasi in cooked {
+                    self.constant(RuntimeValue::StringLiteral(quasi.to_string()));
+                }
+                self.emit(Instruction::NewArray(cooked.len()));
+                for quasi in raw {
+                    self.constant(RuntimeValue::StringLiteral(quasi.to_string()));
+                }
+                self.emit(Instruction::NewArray(raw.len()));
+                let key = self.code().add_name("raw");
+                self.emit(Instruction::DefineField(key));
+            }
+            // 評価するたびに新しいRegExpオブジェクトを作る
+            Node::RegExpLiteral { pattern, flags } => {
+                let pattern = self
+                    .code()
+                    .add_constant(RuntimeValue::StringLiteral(pattern.to_string()));
+                let flags = self
+                    .code()
+                    .add_constant(RuntimeValue::StringLiteral(flags.to_string()));
+                self.emit(Instruction::NewRegExp(pattern, flags));
+            }
+            Node::BooleanLiteral(value) => {
+                self.emit(Instruction::Boolean(*value));
+            }
+            Node::NullLiteral => {
+                self.emit(Instruction::Null);
+            }
+            Node::ArrayExpression { elements } => {
+                for element in elements {
+                    self.expression(element);
+                }
+                self.emit(Instruction::NewArray(elements.len()));
+            }
+            Node::ObjectExpression { properties } => {
+                self.emit(Instruction::NewObject);
+                for property in properties.iter().flatten() {
+                    match property.borrow() {
+                        Node::Property { key, value } => {
+                            self.expression(value);
+                            let key = self.code().add_name(key);
+                            self.emit(Instruction::DefineField(key));
+                        }
+                        Node::MethodDefinition {
+                            key, value, kind, ..
+                        } => self.method(key, value, *kind),
+                        _ => {}
+                    }
+                }
+            }
+            Node::Identifier(name) => self.variable(name),
+            Node::FunctionExpression {
+                id,
+                params,
+                body,
+                is_async,
+                is_generator,
+            } => {
+                let name = match id.as_deref() {
+                    Some(Node::Identifier(name)) => Some(name.to_string()),
+                    _ => None,
+                };
+                let code = function_code(name, FunctionKind::Normal, *is_async, *is_generator);
+                let function = self.function(code, params, body, true);
+                self.emit(Instruction::Closure(function));
+            }
+            Node::ArrowFunctionExpression {
+                params,
+                body,
+                is_async,
+            } => {
+                let code = function_code(None, FunctionKind::Arrow, *is_async, false);
+                let function = self.function(code, params, body, false);
+                self.emit(Instruction::Closure(function));
+            }
+            Node::YieldExpression { argument, delegate } => {
+                self.expression(argument);
+                let at = if *delegate {
+                    self.emit(Instruction::Iterate(false));
+                    self.emit(Instruction::Delegate(0))
+                } else {
+                    self.emit(Instruction::Yield(0))
+                };
+                let to_end = self.emit(Instruction::Jump(0));
+                // return()で再開された場合、finally節を実行してから引数の値を返す
+                self.patch(at);
+                self.unwind(0, None);
+                self.emit(Instruction::Return);
+                self.patch(to_end);
+            }
+            Node::AwaitExpression { argument } => {
+                self.expression(argument);
+                self.emit(Instruction::Await);
+            }
+            Node::ThisExpression => self.this(),
+            // super.propertyのsuper。メソッドが定義されたオブジェクトのプロトタイプを表す
+            Node::Super => self.super_base(),
+            Node::ClassExpression {
+                id,
+                super_class,
+                body,
+            } => self.class(id, super_class, body),
+            Node::NewExpression {
+                callee,
+                arguments,
+                position,
+            } => {
+                let site = self.call_site(callee, *position);
+                self.expression(callee);
+                let argc = self.arguments(arguments);
+                self.emit(Instruction::New(argc, site));
+            }
+            Node::CallExpression {
+                callee,
+                arguments,
+                position,
+            } => self.call(callee, arguments, *position),
+            // 値を持たない式と文
+            _ => {
+                self.emit(Instruction::Undefined);
+            }
+        }
+    }
+}
+
+fn assign_of<'a>(operator: &'a str, right: &'a Option<Rc<Node>>) -> Assign<'a> {
+    match operator {
+        "=" => Assign::Value(right),
+        _ => Assign::Compound(operator, right),
+    }
+}
+
+// 二項演算子と複合代入の演算子に対応する命令
+fn binary_instruction(operator: &str) -> Instruction {
+    match operator {
+        "+" => Instruction::Add,
+        "-" => Instruction::Sub,
+        "*" => Instruction::Mul,
+        "/" => Instruction::Div,
+        "%" => Instruction::Rem,
+        "**" => Instruction::Exponent,
+        "<<" => Instruction::ShiftLeft,
+        ">>" => Instruction::ShiftRight,
+        ">>>" => Instruction::UnsignedShiftRight,
+        "&" => Instruction::BitAnd,
+        "|" => Instruction::BitOr,
+        _ => Instruction::BitXor,
+    }
+}
+
+fn update_of<'a>(operator: char, prefix: bool) -> Assign<'a> {
+    Assign::Update {
+        increment: operator == '+',
+        postfix: !prefix,
+    }
+}
+
+fn function_code(
+    name: Option<String>,
+    kind: FunctionKind,
+    is_async: bool,
+    is_generator: bool,
+) -> Code {
+    let mut code = Code::new(name, kind);
+    code.is_async = is_async;
+    code.is_generator = is_generator;
+    code
+}
+
+// ブロックの直下で宣言される変数。letとconstとクラスは宣言が実行されるまで使えない
+fn block_bindings(statements: &[Option<Rc<Node>>]) -> Vec<Binding> {
+    let mut scope = ScopeInfo::new();
+    for statement in statements.iter().flatten() {
+        match statement.borrow() {
+            Node::VariableDeclaration { kind, declarations } if *kind != DeclarationKind::Var => {
+                for declaration in declarations.iter().flatten() {
+                    if let Node::VariableDeclarator { id, .. } = declaration.borrow() {
+                        if let Some(Node::Identifier(name)) = id.as_deref() {
+                            scope.declare(name, true, *kind == DeclarationKind::Let);
+                        }
+                    }
+                }
+            }
+            Node::ClassDeclaration { id, .. } => {
+                if let Some(Node::Identifier(name)) = id.as_deref() {
+                    scope.declare(name, true, true);
+                }
+            }
+            Node::FunctionDeclaration { id, .. } => {
+                if let Some(Node::Identifier(name)) = id.as_deref() {
+                    scope.declare(name, false, true);
+                }
+            }
+            _ => {}
+        }
+    }
+    scope.bindings
+}
+
+// varで宣言された変数を集める。内側の関数の中は含まない
+fn collect_vars(node: &Option<Rc<Node>>, vars: &mut Vec<String>) {
+    let node = match node {
+        Some(n) => n,
+        None => return,
+    };
+
+    match node.borrow() {
+        Node::VariableDeclaration {
+            kind: DeclarationKind::Var,
+            declarations,
+        } => {
+            for declaration in declarations.iter().flatten() {
+                if let Node::VariableDeclarator { id, .. } = declaration.borrow() {
+                    if let Some(Node::Identifier(name)) = id.as_deref() {
+                        if !vars.contains(name) {
+                            vars.push(name.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        Node::BlockStatement { body } => {
+            for statement in body {
+                collect_vars(statement, vars);
+            }
+        }
+        Node::IfStatement {
+            consequent,
+            alternate,
+            ..
+        } => {
+            collect_vars(consequent, vars);
+            collect_vars(alternate, vars);
+        }
+        Node::WhileStatement { body, .. }
+        | Node::DoWhileStatement { body, .. }
+        | Node::LabeledStatement { body, .. } => collect_vars(body, vars),
+        Node::ForStatement { init, body, .. } => {
+            collect_vars(init, vars);
+            collect_vars(body, vars);
+        }
+        Node::ForInStatement { left, body, .. } | Node::ForOfStatement { left, body, .. } => {
+            collect_vars(left, vars);
+            collect_vars(body, vars);
+        }
+        Node::SwitchStatement { cases, .. } => {
+            for case in cases {
+                collect_vars(case, vars);
+            }
+        }
+        Node::SwitchCase { consequent, .. } => {
+            for statement in consequent {
+                collect_vars(statement, vars);
+            }
+        }
+        Node::TryStatement {
+            block,
+            handler,
+            finalizer,
+            ..
+        } => {
+            collect_vars(block, vars);
+            collect_vars(handler, vars);
+            collect_vars(finalizer, vars);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,667 @@
+use core::{
+    cell::RefCell,
+    fmt::{Debug, Display, Formatter},
+};
+
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    builtins::{arg, define_methods, own_entries, trunc},
+    runtime::{
+        number_to_string, InternalSlot, JsException, JsRuntime, NativeFunction, Property,
+        RuntimeValue,
+    },
+    token::Position,
+};
+
+// インラインのスクリプトから出力されたメッセージのソース
+pub const INLINE_SOURCE: &str = "inline";
+
+// オブジェクトと配列の中身を表示する深さ。これより深いものは[Object]のように省略する
+const MAX_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// consoleのメッセージの重要度
+pub enum ConsoleLevel {
+    Log,
+    Info,
+    Warn,
+    Error,
+    Debug,
+}
+
+impl Display for ConsoleLevel {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let s = match self {
+            ConsoleLevel::Log => "log",
+            ConsoleLevel::Info => "info",
+            ConsoleLevel::Warn => "warn",
+            ConsoleLevel::Error => "error",
+            ConsoleLevel::Debug => "debug",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// consoleに出力されたメッセージと、出力したスクリプトのURLと呼び出しの位置
+pub struct ConsoleMessage {
+    level: ConsoleLevel,
+    text: String,
+    // 外部スクリプトのURL。インラインのスクリプトは"inline"
+    source: String,
+    position: Option<Position>,
+}
+
+impl ConsoleMessage {
+    pub fn new(
+        level: ConsoleLevel,
+        text: String,
+        source: String,
+        position: Option<Position>,
+    ) -> Self {
+        Self {
+            level,
+            text,
+            source,
+            position,
+        }
+    }
+
+    pub fn level(&self) -> ConsoleLevel {
+        self.level
+    }
+
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    pub fn source(&self) -> String {
+        self.source.clone()
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+}
+
+impl Display for ConsoleMessage {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self.position {
+            Some(position) => write!(
+                f,
+                "[{}] {} ({}:{})",
+                self.level, self.text, self.source, position
+            ),
+            None => write!(f, "[{}] {} ({})", self.level, self.text, self.source),
+        }
+    }
+}
+
+// 埋め込み側がconsoleのメッセージを出力された順に受け取る
+pub trait ConsoleListener: Debug {
+    fn message(&self, message: &ConsoleMessage);
+}
+
+// 埋め込み側の関数をそのままConsoleListenerとして使える
+impl ConsoleListener for fn(&ConsoleMessage) {
+    fn message(&self, message: &ConsoleMessage) {
+        self(message)
+    }
+}
+
+pub fn define_globals(runtime: &mut JsRuntime) {
+    let console = runtime.new_object();
+    define_methods(&console, &CONSOLE_METHODS);
+    runtime.define_global("console", console);
+}
+
+static CONSOLE_METHODS: [(&str, NativeFunction); 6] = [
+    ("log", console_log),
+    ("info", console_info),
+    ("warn", console_warn),
+    ("error", console_error),
+    ("debug", console_debug),
+    ("table", console_table),
+];
+
+fn console_log(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    write(runtime, ConsoleLevel::Log, &args)
+}
+
+fn console_info(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    write(runtime, ConsoleLevel::Info, &args)
+}
+
+fn console_warn(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    write(runtime, ConsoleLevel::Warn, &args)
+}
+
+fn console_error(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    write(runtime, ConsoleLevel::Error, &args)
+}
+
+fn console_debug(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    write(runtime, ConsoleLevel::Debug, &args)
+}
+
+// 位置は最後に評価した関数呼び出し、つまりconsoleのメソッドを呼び出した位置になる
+// ソースはその呼び出しを含む関数が定義されたスクリプトになる
+fn write(
+    runtime: &mut JsRuntime,
+    level: ConsoleLevel,
+    args: &[RuntimeValue],
+) -> Result<RuntimeValue, JsException> {
+    let text = format_args(runtime, args);
+    let source = runtime.source().to_string();
+    let position = runtime.position();
+    runtime.add_console_message(ConsoleMessage::new(level, text, source, position));
+    Ok(RuntimeValue::Undefined)
+}
+
+// 最初の引数が文字列の場合は%s、%d、%i、%f、%o、%O、%cを残りの引数で置き換え、置き換えなかった引数は空白でつなげる
+pub fn format_args(runtime: &mut JsRuntime, args: &[RuntimeValue]) -> String {
+    let mut rest = args.iter();
+    let mut parts = Vec::new();
+    if let Some(RuntimeValue::StringLiteral(format)) = args.first() {
+        rest.next();
+        let mut text = String::new();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let specifier = match chars.peek() {
+                Some(&s) if "sdifoOc%".contains(s) => s,
+                _ => {
+                    text.push(c);
+                    continue;
+                }
+            };
+            chars.next();
+            if specifier == '%' {
+                text.push('%');
+                continue;
+            }
+            let value = match rest.next() {
+                Some(value) => value,
+                None => {
+                    // 引数が足りない場合は指定子をそのまま残す
+                    text.push('%');
+                    text.push(specifier);
+                    continue;
+                }
+            };
+            match specifier {
+                's' => text.push_str(&display(runtime, value)),
+                'd' | 'i' => {
+                    let n = value.to_number();
+                    text.push_str(&number_to_string(trunc(n)));
+                }
+                'f' => text.push_str(&number_to_string(value.to_number())),
+                'o' | 'O' => text.push_str(&inspect(runtime, value)),
+                // CSSによる装飾は表示できないため、引数だけ消費する
+                _ => {}
+            }
+        }
+        parts.push(text);
+    }
+    for value in rest {
+        parts.push(display(runtime, value));
+    }
+    parts.join(" ")
+}
+
+// 引数として直接渡された値の表示。文字列は引用符で囲まない
+fn display(runtime: &mut JsRuntime, value: &RuntimeValue) -> String {
+    match value {
+        RuntimeValue::StringLiteral(s) => s.clone(),
+        _ => inspect(runtime, value),
+    }
+}
+
+// 開発者ツールのように値の中身を表示する
+pub fn inspect(runtime: &mut JsRuntime, value: &RuntimeValue) -> String {
+    let mut inspector = Inspector {
+        runtime,
+        stack: Vec::new(),
+    };
+    inspector.inspect(value, 0)
+}
+
+struct Inspector<'a> {
+    runtime: &'a mut JsRuntime,
+    // 循環参照を見つけるため、表示中のオブジェクトと配列のアドレスを保持する
+    stack: Vec<usize>,
+}
+
+impl Inspector<'_> {
+    fn inspect(&mut self, value: &RuntimeValue, depth: usize) -> String {
+        match value {
+            RuntimeValue::Number(n) if *n == 0.0 && n.is_sign_negative() => "-0".to_string(),
+            RuntimeValue::StringLiteral(s) => quote(s),
+            RuntimeValue::Function(function) => {
+                let name = function.id().filter(|id| !id.is_empty());
+                match (name, function.is_class_constructor()) {
+                    (Some(name), true) => format!("[class {}]", name),
+                    (None, true) => "[class (anonymous)]".to_string(),
+                    (Some(name), false) => format!("[Function: {}]", name),
+                    (None, false) => "[Function (anonymous)]".to_string(),
+                }
+            }
+            RuntimeValue::Array(elements) => {
+                let address = Rc::as_ptr(elements) as usize;
+                if self.stack.contains(&address) {
+                    return "[Circular]".to_string();
+                }
+                if depth > MAX_DEPTH {
+                    return "[Array]".to_string();
+                }
+                self.stack.push(address);
+                let elements = RefCell::borrow(elements).to_vec();
+                let items: Vec<String> = elements
+                    .iter()
+                    .map(|e| self.inspect(e, depth + 1))
+                    .collect();
+                self.stack.pop();
+                format!("[{}]", items.join(", "))
+            }
+            RuntimeValue::Object(object) => {
+                if let Some(text) = self.special_object(value) {
+                    return text;
+                }
+                let address = Rc::as_ptr(object) as usize;
+                if self.stack.contains(&address) {
+                    return "[Circular]".to_string();
+                }
+                let prefix = match RefCell::borrow(object).slot() {
+                    Some(InternalSlot::Promise(_)) => "Promise ",
+                    _ => "",
+                };
+                if depth > MAX_DEPTH {
+                    return format!("[{}Object]", prefix);
+                }
+                self.stack.push(address);
+                let properties: Vec<(String, Option<Property>)> = {
+                    let object = RefCell::borrow(object);
+                    object
+                        .keys()
+                        .into_iter()
+                        .map(|key| {
+                            let property = object.get_own(&key);
+                            (key, property)
+                        })
+                        .collect()
+                };
+                let mut items = Vec::new();
+                for (key, property) in properties {
+                    let text = match property {
+                        Some(Property::Data(v)) => self.inspect(&v, depth + 1),
+                        Some(Property::Accessor { get, set }) => {
+                            match (get.is_some(), set.is_some()) {
+                                (true, true) => "[Getter/Setter]".to_string(),
+                                (true, false) => "[Getter]".to_string(),
+                                _ => "[Setter]".to_string(),
+                            }
+                        }
+                        None => continue,
+                    };
+                    items.push(format!("{}: {}", property_key(&key), text));
+                }
+                self.stack.pop();
+                if items.is_empty() {
+                    format!("{}{{}}", prefix)
+                } else {
+                    format!("{}{{{}}}", prefix, items.join(", "))
+                }
+            }
+            _ => value.to_string(),
+        }
+    }
+
+    // DOMノード、エラー、正規表現のように、プロパティの一覧ではなく独自の形式で表示するオブジェクト
+    fn special_object(&mut self, value: &RuntimeValue) -> Option<String> {
+        if let RuntimeValue::Object(object) = value {
+            let object = RefCell::borrow(object);
+            if object.node().is_some() {
+                return Some(value.to_string());
+            }
+            if let Some(InternalSlot::RegExp(_)) = object.slot() {
+                return Some(format!(
+                    "/{}/{}",
+                    value.get_property("source"),
+                    value.get_property("flags")
+                ));
+            }
+        }
+        let error = self.runtime.lookup_global("Error").ok()?;
+        if self.runtime.instance_of(value, &error).unwrap_or(false) {
+            let message = value.get_property("message");
+            return Some(match message {
+                RuntimeValue::Undefined => value.get_property("name").to_string(),
+                _ => format!("{}: {}", value.get_property("name"), message),
+            });
+        }
+        None
+    }
+}
+
+// 文字列は単一引用符で囲む
+fn quote(s: &str) -> String {
+    let mut result = String::from("'");
+    for c in s.chars() {
+        match c {
+            '\'' => result.push_str("\\'"),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c => result.push(c),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+// 識別子として書けないプロパティ名は引用符で囲む
+fn property_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        }
+        _ => false,
+    };
+    if is_identifier {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+// 配列かオブジェクトの要素を行にして、罫線で囲んだ表を出力する
+// 要素がオブジェクトの場合はそのプロパティを列にし、それ以外の値はValuesの列に表示する
+fn console_table(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let data = arg(&args, 0);
+    let rows = match data {
+        RuntimeValue::Object(_) | RuntimeValue::Array(_) => own_entries(&data),
+        _ => return write(runtime, ConsoleLevel::Log, &args),
+    };
+    // 第2引数で表示する列を指定できる
+    let filter: Option<Vec<String>> = match arg(&args, 1) {
+        RuntimeValue::Array(columns) => Some(
+            RefCell::borrow(&columns)
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    let mut columns: Vec<String> = Vec::new();
+    let mut has_values = false;
+    let mut cells: Vec<Vec<(String, String)>> = Vec::new();
+    for (_, row) in &rows {
+        let mut row_cells = Vec::new();
+        match row {
+            RuntimeValue::Object(_) | RuntimeValue::Array(_) => {
+                for (key, value) in own_entries(row) {
+                    if !columns.contains(&key) {
+                        columns.push(key.clone());
+                    }
+                    row_cells.push((key, inspect(runtime, &value)));
+                }
+            }
+            _ => {
+                has_values = true;
+                row_cells.push((String::new(), inspect(runtime, row)));
+            }
+        }
+        cells.push(row_cells);
+    }
+    if let Some(filter) = filter {
+        columns = filter;
+    }
+
+    let mut header = Vec::from(["(index)".to_string()]);
+    header.extend(columns.iter().cloned());
+    if has_values {
+        header.push("Values".to_string());
+    }
+    let mut table = Vec::new();
+    for ((index, _), row_cells) in rows.iter().zip(cells.iter()) {
+        let find = |key: &str| {
+            row_cells
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+                .unwrap_or_default()
+        };
+        let mut line = Vec::from([index.clone()]);
+        line.extend(columns.iter().map(|c| find(c)));
+        if has_values {
+            line.push(find(""));
+        }
+        table.push(line);
+    }
+
+    let text = draw_table(&header, &table);
+    let source = runtime.source().to_string();
+    let position = runtime.position();
+    runtime.add_console_message(ConsoleMessage::new(
+        ConsoleLevel::Log,
+        text,
+        source,
+        position,
+    ));
+    Ok(RuntimeValue::Undefined)
+}
+
+fn draw_table(header: &[String], rows: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([header[i].chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let border = |left: &str, middle: &str, right: &str| {
+        let lines: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{}{}{}", left, lines.join(middle), right)
+    };
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, w)| format!(" {}{} ", cell, " ".repeat(w - cell.chars().count())))
+            .collect();
+        format!("│{}│", cells.join("│"))
+    };
+
+    let mut lines = Vec::from([border("┌", "┬", "┐"), line(header), border("├", "┼", "┤")]);
+    for row in rows {
+        lines.push(line(row));
+    }
+    lines.push(border("└", "┴", "┘"));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::event_loop::run_tasks;
+    use crate::renderer::js::token::JsLexer;
+
+    fn run(source: &str) -> Vec<ConsoleMessage> {
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        let program = JsParser::new(JsLexer::new(source.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        let _ = runtime.execute(&program);
+        runtime.take_console_messages()
+    }
+
+    fn texts(source: &str) -> Vec<String> {
+        run(source).iter().map(|m| m.text()).collect()
+    }
+
+    #[test]
+    fn test_levels_and_positions() {
+        let messages = run("console.log(\"a\");\nconsole.warn(\"b\");\n  console.error(\"c\");");
+        assert_eq!(
+            Vec::from([
+                ConsoleMessage::new(
+                    ConsoleLevel::Log,
+                    "a".to_string(),
+                    "inline".to_string(),
+                    Some(Position::new(1, 1))
+                ),
+                ConsoleMessage::new(
+                    ConsoleLevel::Warn,
+                    "b".to_string(),
+                    "inline".to_string(),
+                    Some(Position::new(2, 1))
+                ),
+                ConsoleMessage::new(
+                    ConsoleLevel::Error,
+                    "c".to_string(),
+                    "inline".to_string(),
+                    Some(Position::new(3, 3))
+                ),
+            ]),
+            messages
+        );
+        assert_eq!("[warn] b (inline:2:1)", messages[1].to_string());
+    }
+
+    #[test]
+    fn test_value_formatting() {
+        assert_eq!(
+            Vec::from([
+                "1 a true null undefined -0".to_string(),
+                "[1, 'x', [2, [3, [Array]]]]".to_string(),
+                "{a: 1, 'b-c': 'd', e: {f: [Function: g]}}".to_string(),
+                "{self: [Circular]}".to_string(),
+                "TypeError: bad /a+/g [class A] [Function (anonymous)]".to_string(),
+                "[object HTMLDocument] {x: [Getter]}".to_string(),
+            ]),
+            texts(
+                "console.log(1, \"a\", true, null, undefined, -0);
+                console.log([1, \"x\", [2, [3, [4]]]]);
+                console.log({a: 1, \"b-c\": \"d\", e: {f: function g() {}}});
+                var o = {}; o.self = o; console.log(o);
+                class A {}
+                console.info(new TypeError(\"bad\"), /a+/g, A, function () {});
+                console.debug(document, {get x() { return 1; }});"
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_specifiers() {
+        assert_eq!(
+            Vec::from([
+                "a=x b=3 c=1.5 d=[1] 100% %s".to_string(),
+                "styled text ['q']".to_string(),
+            ]),
+            texts(
+                "console.log(\"a=%s b=%d c=%f d=%o 100%% %s\", \"x\", 3.7, 1.5, [1]);
+                console.log(\"%cstyled\", \"color: red\", \"text\", [\"q\"]);"
+            )
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            Vec::from([
+                "┌─────────┬───┬─────┐\n\
+                 │ (index) │ a │ b   │\n\
+                 ├─────────┼───┼─────┤\n\
+                 │ 0       │ 1 │ 'x' │\n\
+                 │ 1       │ 2 │     │\n\
+                 └─────────┴───┴─────┘"
+                    .to_string(),
+                "┌─────────┬────────┐\n\
+                 │ (index) │ Values │\n\
+                 ├─────────┼────────┤\n\
+                 │ p       │ 1      │\n\
+                 │ q       │ 'y'    │\n\
+                 └─────────┴────────┘"
+                    .to_string(),
+                "┌─────────┬───┐\n\
+                 │ (index) │ b │\n\
+                 ├─────────┼───┤\n\
+                 │ 0       │ 2 │\n\
+                 └─────────┴───┘"
+                    .to_string(),
+                "plain".to_string(),
+            ]),
+            texts(
+                "console.table([{a: 1, b: \"x\"}, {a: 2}]);
+                console.table({p: 1, q: \"y\"});
+                console.table([{a: 1, b: 2}], [\"b\"]);
+                console.table(\"plain\");"
+            )
+        );
+    }
+
+    #[test]
+    fn test_reported_exceptions_are_logged() {
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        let program = JsParser::new(JsLexer::new(
+            "setTimeout(function () { console.log(\"before\"); null.x; }, 0);".to_string(),
+        ))
+        .parse_ast()
+        .expect("failed to parse");
+        runtime.execute(&program).expect("failed to execute");
+        run_tasks(&mut runtime);
+
+        let messages = runtime.take_console_messages();
+        assert_eq!(
+            Vec::from([ConsoleLevel::Log, ConsoleLevel::Error]),
+            messages.iter().map(|m| m.level()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "Uncaught TypeError: cannot read properties of null (reading 'x')",
+            messages[1].text()
+        );
+        assert_eq!(1, runtime.take_reported_exceptions().len());
+    }
+}
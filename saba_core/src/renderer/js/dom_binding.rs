@@ -0,0 +1,792 @@
+// DOMのインターフェースをJSから使えるようにする
+// DOMノードはラッパーオブジェクトとして扱い、メソッドとプロパティはNode.prototypeなどのプロトタイプが持つ
+
+use crate::renderer::dom::{
+    api::{
+        append_child, child_nodes, contains, get_element_by_id, get_inner_html,
+        get_style_declarations, get_target_element_node, get_text_content, insert_before,
+        query_selector_all, remove_child, set_attribute, set_inner_html, set_style_property,
+        set_text_content,
+    },
+    node::{Element, ElementKind, Node as DomNode, NodeKind as DomNodeKind},
+    selector::SelectorList,
+};
+use crate::renderer::js::builtins::{arg, constructor, define_methods, native, new_array};
+use crate::renderer::js::event;
+use crate::renderer::js::runtime::{
+    JsException, JsObject, JsRuntime, NativeFunction, RuntimeValue,
+};
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::RefCell;
+
+// Node、Element、HTMLElement、Text、Documentのインターフェースとdocumentを定義する
+pub fn define_globals(runtime: &mut JsRuntime) {
+    let event_target = event::define_globals(runtime);
+    let node = interface(runtime, "Node", event_target);
+    define_accessor(
+        &node,
+        "textContent",
+        text_content,
+        Some(set_text_content_method),
+    );
+    define_accessor(&node, "nodeName", node_name, None);
+    define_accessor(
+        &node,
+        "nodeType",
+        |runtime, this, _| {
+            let node_type = match this_node(runtime, &this)?.borrow().kind() {
+                DomNodeKind::Element(_) => 1,
+                DomNodeKind::Text(_) => 3,
+                DomNodeKind::Document => 9,
+            };
+            Ok(RuntimeValue::Number(node_type as f64))
+        },
+        None,
+    );
+    define_accessor(
+        &node,
+        "parentNode",
+        |runtime, this, _| {
+            let parent = this_node(runtime, &this)?.borrow().parent().upgrade();
+            Ok(wrap(runtime, parent))
+        },
+        None,
+    );
+    define_accessor(
+        &node,
+        "firstChild",
+        |runtime, this, _| {
+            let child = this_node(runtime, &this)?.borrow().first_child();
+            Ok(wrap(runtime, child))
+        },
+        None,
+    );
+    define_accessor(
+        &node,
+        "lastChild",
+        |runtime, this, _| {
+            let child = this_node(runtime, &this)?.borrow().last_child().upgrade();
+            Ok(wrap(runtime, child))
+        },
+        None,
+    );
+    define_accessor(
+        &node,
+        "nextSibling",
+        |runtime, this, _| {
+            let sibling = this_node(runtime, &this)?.borrow().next_sibling();
+            Ok(wrap(runtime, sibling))
+        },
+        None,
+    );
+    define_accessor(
+        &node,
+        "previousSibling",
+        |runtime, this, _| {
+            let sibling = this_node(runtime, &this)?
+                .borrow()
+                .previous_sibling()
+                .upgrade();
+            Ok(wrap(runtime, sibling))
+        },
+        None,
+    );
+    define_accessor(
+        &node,
+        "childNodes",
+        |runtime, this, _| {
+            let children = child_nodes(&this_node(runtime, &this)?);
+            Ok(wrap_all(runtime, children))
+        },
+        None,
+    );
+    define_methods(&node, &NODE_METHODS);
+
+    let element = interface(runtime, "Element", node.clone());
+    define_accessor(&element, "tagName", node_name, None);
+    define_accessor(
+        &element,
+        "id",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(attribute_value(&node, "id").unwrap_or(RuntimeValue::StringLiteral(String::new())))
+        },
+        Some(|runtime, this, args| {
+            let node = this_node(runtime, &this)?;
+            set_attribute(&node, "id", &arg(&args, 0).to_string());
+            Ok(RuntimeValue::Undefined)
+        }),
+    );
+    define_accessor(
+        &element,
+        "className",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(attribute_value(&node, "class")
+                .unwrap_or(RuntimeValue::StringLiteral(String::new())))
+        },
+        Some(|runtime, this, args| {
+            let node = this_node(runtime, &this)?;
+            set_attribute(&node, "class", &arg(&args, 0).to_string());
+            Ok(RuntimeValue::Undefined)
+        }),
+    );
+    define_accessor(
+        &element,
+        "children",
+        |runtime, this, _| {
+            let children = child_nodes(&this_node(runtime, &this)?)
+                .into_iter()
+                .filter(|c| c.borrow().get_element().is_some())
+                .collect();
+            Ok(wrap_all(runtime, children))
+        },
+        None,
+    );
+    define_accessor(
+        &element,
+        "innerHTML",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(RuntimeValue::StringLiteral(get_inner_html(&node)))
+        },
+        Some(|runtime, this, args| {
+            let node = this_node(runtime, &this)?;
+            set_inner_html(&node, &arg(&args, 0).to_string());
+            Ok(RuntimeValue::Undefined)
+        }),
+    );
+    define_accessor(
+        &element,
+        "classList",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(runtime.new_host_object("DOMTokenList", &node))
+        },
+        None,
+    );
+    define_accessor(
+        &element,
+        "style",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(runtime.new_host_object("CSSStyleDeclaration", &node))
+        },
+        None,
+    );
+    define_methods(&element, &ELEMENT_METHODS);
+    define_methods(&element, &PARENT_NODE_METHODS);
+
+    interface(runtime, "HTMLElement", element);
+    interface(runtime, "Text", node.clone());
+
+    let document = interface(runtime, "Document", node);
+    define_methods(&document, &DOCUMENT_METHODS);
+    define_methods(&document, &PARENT_NODE_METHODS);
+    define_accessor(
+        &document,
+        "body",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            let body = get_target_element_node(Some(node), ElementKind::Body);
+            Ok(wrap(runtime, body))
+        },
+        None,
+    );
+
+    // class属性を空白で区切ったクラスの一覧
+    let token_list = interface(runtime, "DOMTokenList", runtime.object_prototype());
+    define_accessor(
+        &token_list,
+        "length",
+        |runtime, this, _| {
+            let classes = class_list(&this_node(runtime, &this)?);
+            Ok(RuntimeValue::Number(classes.len() as f64))
+        },
+        None,
+    );
+    define_accessor(
+        &token_list,
+        "value",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(attribute_value(&node, "class")
+                .unwrap_or(RuntimeValue::StringLiteral(String::new())))
+        },
+        None,
+    );
+    define_methods(&token_list, &TOKEN_LIST_METHODS);
+
+    // style属性の宣言。el.style.colorのように、プロパティ名はキャメルケースで指定する
+    let style = interface(runtime, "CSSStyleDeclaration", runtime.object_prototype());
+    define_accessor(
+        &style,
+        "cssText",
+        |runtime, this, _| {
+            let node = this_node(runtime, &this)?;
+            Ok(attribute_value(&node, "style")
+                .unwrap_or(RuntimeValue::StringLiteral(String::new())))
+        },
+        Some(|runtime, this, args| {
+            let node = this_node(runtime, &this)?;
+            set_attribute(&node, "style", &arg(&args, 0).to_string());
+            Ok(RuntimeValue::Undefined)
+        }),
+    );
+    for (name, get, set) in STYLE_PROPERTIES {
+        style.define_accessor(name, Some(native(name, get)), Some(native(name, set)));
+    }
+    define_methods(&style, &STYLE_METHODS);
+
+    let root = runtime.dom_root();
+    let document = runtime.wrap_node(&root);
+    runtime.define_global("document", document);
+}
+
+// インターフェースを定義し、そのプロトタイプを返す。プロトタイプはparentを継承する
+pub fn interface(runtime: &mut JsRuntime, name: &str, parent: RuntimeValue) -> RuntimeValue {
+    let prototype = RuntimeValue::Object(Rc::new(RefCell::new(JsObject::with_prototype(Some(
+        parent,
+    )))));
+    runtime.define_global(
+        name,
+        constructor(name, illegal_constructor, &prototype, &[]),
+    );
+    prototype
+}
+
+fn define_accessor(
+    prototype: &RuntimeValue,
+    name: &str,
+    get: NativeFunction,
+    set: Option<NativeFunction>,
+) {
+    prototype.define_accessor(
+        name,
+        Some(native(name, get)),
+        set.map(|set| native(name, set)),
+    );
+}
+
+// new Node()のように、DOMのインターフェースから直接オブジェクトを作ることはできない
+fn illegal_constructor(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    _: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    Err(runtime.throw_error("TypeError", "illegal constructor".to_string()))
+}
+
+// thisのラッパーオブジェクトが表すDOMノード。ラッパーオブジェクトでない場合はTypeError
+fn this_node(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+) -> Result<Rc<RefCell<DomNode>>, JsException> {
+    if let RuntimeValue::Object(object) = this {
+        if let Some(node) = object.borrow().node() {
+            return Ok(node);
+        }
+    }
+    Err(runtime.throw_error("TypeError", "illegal invocation".to_string()))
+}
+
+// 引数のラッパーオブジェクトが表すDOMノード。ノードでない場合はTypeError
+fn arg_node(
+    runtime: &JsRuntime,
+    args: &[RuntimeValue],
+    index: usize,
+) -> Result<Rc<RefCell<DomNode>>, JsException> {
+    if let RuntimeValue::Object(object) = arg(args, index) {
+        if let Some(node) = object.borrow().node() {
+            return Ok(node);
+        }
+    }
+    Err(runtime.throw_error(
+        "TypeError",
+        format!("parameter {} is not of type 'Node'", index + 1),
+    ))
+}
+
+// ノードが存在しない場合はnull
+fn wrap(runtime: &mut JsRuntime, node: Option<Rc<RefCell<DomNode>>>) -> RuntimeValue {
+    match node {
+        Some(node) => runtime.wrap_node(&node),
+        None => RuntimeValue::Null,
+    }
+}
+
+fn wrap_all(runtime: &mut JsRuntime, nodes: Vec<Rc<RefCell<DomNode>>>) -> RuntimeValue {
+    let wrappers = nodes.iter().map(|n| runtime.wrap_node(n)).collect();
+    new_array(runtime, wrappers)
+}
+
+fn attribute_value(node: &Rc<RefCell<DomNode>>, name: &str) -> Option<RuntimeValue> {
+    node.borrow()
+        .get_element()?
+        .get_attribute(name)
+        .map(RuntimeValue::StringLiteral)
+}
+
+fn text_content(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    _: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let node = this_node(runtime, &this)?;
+    // documentのtextContentはnull
+    if node.borrow().kind() == DomNodeKind::Document {
+        return Ok(RuntimeValue::Null);
+    }
+    Ok(RuntimeValue::StringLiteral(get_text_content(&node)))
+}
+
+// target.textContent = "foobar";のようにノードのテキストを変更する
+fn set_text_content_method(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let node = this_node(runtime, &this)?;
+    set_text_content(&node, &arg(&args, 0).to_string());
+    Ok(RuntimeValue::Undefined)
+}
+
+// 要素の場合は大文字のタグ名
+fn node_name(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    _: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let name = match this_node(runtime, &this)?.borrow().kind() {
+        DomNodeKind::Document => "#document".to_string(),
+        DomNodeKind::Element(e) => e.kind().to_string().to_uppercase(),
+        DomNodeKind::Text(_) => "#text".to_string(),
+    };
+    Ok(RuntimeValue::StringLiteral(name))
+}
+
+// childをparentの子ノードにできるか。テキストノードは子ノードを持てず、祖先を子孫にすることはできない
+fn check_hierarchy(
+    runtime: &JsRuntime,
+    parent: &Rc<RefCell<DomNode>>,
+    child: &Rc<RefCell<DomNode>>,
+) -> Result<(), JsException> {
+    let is_text = matches!(parent.borrow().kind(), DomNodeKind::Text(_));
+    if is_text || contains(child, parent) {
+        return Err(runtime.throw_error(
+            "Error",
+            "HierarchyRequestError: the new child cannot be inserted here".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_child(
+    runtime: &JsRuntime,
+    parent: &Rc<RefCell<DomNode>>,
+    child: &Rc<RefCell<DomNode>>,
+) -> Result<(), JsException> {
+    match child.borrow().parent().upgrade() {
+        Some(p) if Rc::ptr_eq(&p, parent) => Ok(()),
+        _ => Err(runtime.throw_error(
+            "Error",
+            "NotFoundError: the node is not a child of this node".to_string(),
+        )),
+    }
+}
+
+static NODE_METHODS: [(&str, NativeFunction); 3] = [
+    // 追加したノードを返す。すでにツリーの中にあるノードは移動する
+    ("appendChild", |runtime, this, args| {
+        let parent = this_node(runtime, &this)?;
+        let child = arg_node(runtime, &args, 0)?;
+        check_hierarchy(runtime, &parent, &child)?;
+        append_child(&parent, &child);
+        Ok(arg(&args, 0))
+    }),
+    ("removeChild", |runtime, this, args| {
+        let parent = this_node(runtime, &this)?;
+        let child = arg_node(runtime, &args, 0)?;
+        check_child(runtime, &parent, &child)?;
+        remove_child(&parent, &child);
+        Ok(arg(&args, 0))
+    }),
+    // 第2引数がnullの場合は最後に追加する
+    ("insertBefore", |runtime, this, args| {
+        let parent = this_node(runtime, &this)?;
+        let child = arg_node(runtime, &args, 0)?;
+        let reference = match arg(&args, 1) {
+            RuntimeValue::Null | RuntimeValue::Undefined => None,
+            _ => Some(arg_node(runtime, &args, 1)?),
+        };
+        check_hierarchy(runtime, &parent, &child)?;
+        if let Some(reference) = &reference {
+            check_child(runtime, &parent, reference)?;
+        }
+        insert_before(&parent, &child, reference.as_ref());
+        Ok(arg(&args, 0))
+    }),
+];
+
+static ELEMENT_METHODS: [(&str, NativeFunction); 2] = [
+    // 属性が存在しない場合はnull
+    ("getAttribute", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        Ok(attribute_value(&node, &arg(&args, 0).to_string()).unwrap_or(RuntimeValue::Null))
+    }),
+    ("setAttribute", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        set_attribute(
+            &node,
+            &arg(&args, 0).to_string(),
+            &arg(&args, 1).to_string(),
+        );
+        Ok(RuntimeValue::Undefined)
+    }),
+];
+
+// 子孫の要素をセレクタで探す。セレクタが正しくない場合はSyntaxError
+fn select(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+    args: &[RuntimeValue],
+) -> Result<Vec<Rc<RefCell<DomNode>>>, JsException> {
+    let node = this_node(runtime, this)?;
+    let selectors = SelectorList::parse(&arg(args, 0).to_string())
+        .map_err(|message| runtime.throw_error("SyntaxError", message))?;
+    Ok(query_selector_all(&node, &selectors))
+}
+
+// ElementとDocumentが持つメソッド
+static PARENT_NODE_METHODS: [(&str, NativeFunction); 2] = [
+    // 一致する要素がない場合はnull
+    ("querySelector", |runtime, this, args| {
+        let first = select(runtime, &this, &args)?.into_iter().next();
+        Ok(wrap(runtime, first))
+    }),
+    ("querySelectorAll", |runtime, this, args| {
+        let nodes = select(runtime, &this, &args)?;
+        Ok(wrap_all(runtime, nodes))
+    }),
+];
+
+static DOCUMENT_METHODS: [(&str, NativeFunction); 3] = [
+    ("getElementById", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let target = get_element_by_id(Some(node), &arg(&args, 0).to_string());
+        Ok(wrap(runtime, target))
+    }),
+    // 個別に扱わない要素は、タグ名を保持した要素として作る。タグ名として使えない文字列の場合はエラー
+    ("createElement", |runtime, _, args| {
+        let tag = arg(&args, 0).to_string().to_lowercase();
+        if tag.is_empty()
+            || tag
+                .chars()
+                .any(|c| c.is_ascii_whitespace() || matches!(c, '<' | '>' | '/' | '='))
+        {
+            return Err(runtime.throw_error(
+                "Error",
+                format!("InvalidCharacterError: '{}' is not a valid tag name", tag),
+            ));
+        }
+        let element = DomNode::new(DomNodeKind::Element(Element::new(&tag, Vec::new())));
+        Ok(runtime.wrap_node(&Rc::new(RefCell::new(element))))
+    }),
+    ("createTextNode", |runtime, _, args| {
+        let text = DomNode::new(DomNodeKind::Text(arg(&args, 0).to_string()));
+        Ok(runtime.wrap_node(&Rc::new(RefCell::new(text))))
+    }),
+];
+
+fn class_list(node: &Rc<RefCell<DomNode>>) -> Vec<String> {
+    node.borrow()
+        .get_element()
+        .and_then(|e| e.get_attribute("class"))
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|c| c.to_string())
+        .collect()
+}
+
+fn set_class_list(node: &Rc<RefCell<DomNode>>, classes: &[String]) {
+    set_attribute(node, "class", &classes.join(" "));
+}
+
+static TOKEN_LIST_METHODS: [(&str, NativeFunction); 5] = [
+    ("add", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let mut classes = class_list(&node);
+        for token in args.iter().map(|a| a.to_string()) {
+            if !classes.contains(&token) {
+                classes.push(token);
+            }
+        }
+        set_class_list(&node, &classes);
+        Ok(RuntimeValue::Undefined)
+    }),
+    ("remove", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let tokens: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let mut classes = class_list(&node);
+        classes.retain(|c| !tokens.contains(c));
+        set_class_list(&node, &classes);
+        Ok(RuntimeValue::Undefined)
+    }),
+    ("contains", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let token = arg(&args, 0).to_string();
+        Ok(RuntimeValue::Boolean(class_list(&node).contains(&token)))
+    }),
+    // 第2引数が指定された場合、trueなら追加、falseなら削除する。クラスを持つようになったかを返す
+    ("toggle", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let token = arg(&args, 0).to_string();
+        let mut classes = class_list(&node);
+        let has_token = classes.contains(&token);
+        let force = match arg(&args, 1) {
+            RuntimeValue::Undefined => !has_token,
+            force => force.is_truthy(),
+        };
+        if force && !has_token {
+            classes.push(token);
+        } else if !force {
+            classes.retain(|c| *c != token);
+        }
+        set_class_list(&node, &classes);
+        Ok(RuntimeValue::Boolean(force))
+    }),
+    ("toString", |runtime, this, _| {
+        let node = this_node(runtime, &this)?;
+        Ok(RuntimeValue::StringLiteral(class_list(&node).join(" ")))
+    }),
+];
+
+// style属性のプロパティの値。指定されていない場合は空文字列
+fn style_value(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+    name: &str,
+) -> Result<RuntimeValue, JsException> {
+    let node = this_node(runtime, this)?;
+    let value = get_style_declarations(&node)
+        .into_iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value)
+        .unwrap_or_default();
+    Ok(RuntimeValue::StringLiteral(value))
+}
+
+fn set_style_value(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+    name: &str,
+    value: RuntimeValue,
+) -> Result<RuntimeValue, JsException> {
+    let node = this_node(runtime, this)?;
+    // nullを代入した場合は削除する
+    let value = match value {
+        RuntimeValue::Null => String::new(),
+        value => value.to_string(),
+    };
+    set_style_property(&node, name, &value);
+    Ok(RuntimeValue::Undefined)
+}
+
+static STYLE_METHODS: [(&str, NativeFunction); 3] = [
+    ("getPropertyValue", |runtime, this, args| {
+        style_value(runtime, &this, &arg(&args, 0).to_string())
+    }),
+    ("setProperty", |runtime, this, args| {
+        set_style_value(runtime, &this, &arg(&args, 0).to_string(), arg(&args, 1))
+    }),
+    // 削除する前の値を返す
+    ("removeProperty", |runtime, this, args| {
+        let name = arg(&args, 0).to_string();
+        let value = style_value(runtime, &this, &name)?;
+        set_style_value(runtime, &this, &name, RuntimeValue::Null)?;
+        Ok(value)
+    }),
+];
+
+// el.style.backgroundColorのように参照できるプロパティ。レイアウトが対応しているものを定義する
+static STYLE_PROPERTIES: [(&str, NativeFunction, NativeFunction); 14] = [
+    (
+        "backgroundColor",
+        |runtime, this, _| style_value(runtime, &this, "background-color"),
+        |runtime, this, args| set_style_value(runtime, &this, "background-color", arg(&args, 0)),
+    ),
+    (
+        "color",
+        |runtime, this, _| style_value(runtime, &this, "color"),
+        |runtime, this, args| set_style_value(runtime, &this, "color", arg(&args, 0)),
+    ),
+    (
+        "display",
+        |runtime, this, _| style_value(runtime, &this, "display"),
+        |runtime, this, args| set_style_value(runtime, &this, "display", arg(&args, 0)),
+    ),
+    (
+        "position",
+        |runtime, this, _| style_value(runtime, &this, "position"),
+        |runtime, this, args| set_style_value(runtime, &this, "position", arg(&args, 0)),
+    ),
+    (
+        "top",
+        |runtime, this, _| style_value(runtime, &this, "top"),
+        |runtime, this, args| set_style_value(runtime, &this, "top", arg(&args, 0)),
+    ),
+    (
+        "right",
+        |runtime, this, _| style_value(runtime, &this, "right"),
+        |runtime, this, args| set_style_value(runtime, &this, "right", arg(&args, 0)),
+    ),
+    (
+        "bottom",
+        |runtime, this, _| style_value(runtime, &this, "bottom"),
+        |runtime, this, args| set_style_value(runtime, &this, "bottom", arg(&args, 0)),
+    ),
+    (
+        "left",
+        |runtime, this, _| style_value(runtime, &this, "left"),
+        |runtime, this, args| set_style_value(runtime, &this, "left", arg(&args, 0)),
+    ),
+    (
+        "width",
+        |runtime, this, _| style_value(runtime, &this, "width"),
+        |runtime, this, args| set_style_value(runtime, &this, "width", arg(&args, 0)),
+    ),
+    (
+        "height",
+        |runtime, this, _| style_value(runtime, &this, "height"),
+        |runtime, this, args| set_style_value(runtime, &this, "height", arg(&args, 0)),
+    ),
+    // floatは予約語だったため、cssFloatという名前になっている
+    (
+        "cssFloat",
+        |runtime, this, _| style_value(runtime, &this, "float"),
+        |runtime, this, args| set_style_value(runtime, &this, "float", arg(&args, 0)),
+    ),
+    (
+        "clear",
+        |runtime, this, _| style_value(runtime, &this, "clear"),
+        |runtime, this, args| set_style_value(runtime, &this, "clear", arg(&args, 0)),
+    ),
+    (
+        "verticalAlign",
+        |runtime, this, _| style_value(runtime, &this, "vertical-align"),
+        |runtime, this, args| set_style_value(runtime, &this, "vertical-align", arg(&args, 0)),
+    ),
+    (
+        "zIndex",
+        |runtime, this, _| style_value(runtime, &this, "z-index"),
+        |runtime, this, args| set_style_value(runtime, &this, "z-index", arg(&args, 0)),
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::html::{parser::HtmlParser, token::HtmlTokenizer};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::token::JsLexer;
+
+    // HTMLを解釈した文書でスクリプトを実行し、グローバル変数resultの値を返す
+    fn run(html: &str, script: &str) -> (Rc<RefCell<DomNode>>, RuntimeValue) {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let dom = window.borrow().document();
+        let mut parser = JsParser::new(JsLexer::new(script.to_string()));
+        let program = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom.clone());
+        runtime.execute(&program).expect("failed to execute");
+        let result = runtime
+            .lookup_global("result")
+            .expect("result should exist");
+        (dom, result)
+    }
+
+    const HTML: &str = r#"<html><head></head><body><div id="list" class="box"><p class="item">a</p><p class="item selected">b</p></div></body></html>"#;
+
+    #[test]
+    fn test_query_selector() {
+        let (_, result) = run(
+            HTML,
+            "var items = document.querySelectorAll(\"#list > .item\"); var selected = document.querySelector(\"div p.selected\"); var result = items.length + selected.textContent + document.querySelector(\"a\") + document.body.querySelectorAll(\"p\").length;",
+        );
+        assert_eq!(RuntimeValue::StringLiteral("2bnull2".to_string()), result);
+    }
+
+    #[test]
+    fn test_create_and_insert() {
+        let (dom, result) = run(
+            HTML,
+            "var list = document.getElementById(\"list\"); var p = document.createElement(\"P\"); p.appendChild(document.createTextNode(\"c\")); list.appendChild(p); var first = list.firstChild; list.insertBefore(document.createTextNode(\"0\"), first); list.removeChild(first); var error; try { p.appendChild(list); } catch (e) { error = e.message; } var result = list.textContent + list.children.length + list.childNodes.length + (p.parentNode === list) + (list.lastChild === p) + error;",
+        );
+        assert_eq!(
+            RuntimeValue::StringLiteral(
+                "0bc23truetrueHierarchyRequestError: the new child cannot be inserted here"
+                    .to_string()
+            ),
+            result
+        );
+        assert!(dom.borrow().is_child_dirty());
+    }
+
+    #[test]
+    fn test_attributes_and_class_list() {
+        let (_, result) = run(
+            HTML,
+            "var p = document.querySelector(\".selected\"); p.classList.remove(\"selected\"); p.classList.add(\"done\", \"item\"); var toggled = p.classList.toggle(\"hidden\"); p.setAttribute(\"title\", \"x\"); var result = p.className + \"|\" + p.classList.contains(\"done\") + toggled + p.classList.length + \"|\" + p.getAttribute(\"title\") + p.getAttribute(\"lang\");",
+        );
+        assert_eq!(
+            RuntimeValue::StringLiteral("item done hidden|truetrue3|xnull".to_string()),
+            result
+        );
+    }
+
+    #[test]
+    fn test_inner_html() {
+        let (_, result) = run(
+            HTML,
+            "var list = document.getElementById(\"list\"); var before = list.innerHTML; list.innerHTML = \"<p id=\\\"new\\\">x</p>text\"; document.getElementById(\"new\").textContent = \"a<b\"; var result = before + \"|\" + list.innerHTML + \"|\" + document.getElementById(\"new\").parentNode.id + list.childNodes.length;",
+        );
+        assert_eq!(
+            RuntimeValue::StringLiteral(
+                "<p class=\"item\">a</p><p class=\"item selected\">b</p>|<p id=\"new\">a&lt;b</p>text|list2"
+                    .to_string()
+            ),
+            result
+        );
+    }
+
+    #[test]
+    fn test_other_elements() {
+        let (_, result) = run(
+            HTML,
+            "var list = document.getElementById(\"list\"); list.innerHTML = \"<ul><li>a<b>b</b></li><li>c<br>d</li></ul>\"; var li = document.createElement(\"LI\"); li.textContent = \"e\"; list.firstChild.appendChild(li); var error; try { document.createElement(\"a b\"); } catch (e) { error = e.message; } var result = list.innerHTML + \"|\" + li.tagName + list.querySelectorAll(\"li\").length + \"|\" + error;",
+        );
+        assert_eq!(
+            RuntimeValue::StringLiteral(
+                "<ul><li>a<b>b</b></li><li>c<br>d</li><li>e</li></ul>|LI3|InvalidCharacterError: 'a b' is not a valid tag name"
+                    .to_string()
+            ),
+            result
+        );
+    }
+
+    #[test]
+    fn test_style() {
+        let (_, result) = run(
+            HTML,
+            "var div = document.getElementById(\"list\"); div.style.backgroundColor = \"red\"; div.style.display = \"none\"; div.style.setProperty(\"width\", \"10px\"); div.style.display = null; var result = div.getAttribute(\"style\") + \"|\" + div.style.backgroundColor + div.style.getPropertyValue(\"width\") + div.style.color;",
+        );
+        assert_eq!(
+            RuntimeValue::StringLiteral("background-color: red; width: 10px;|red10px".to_string()),
+            result
+        );
+    }
+}
@@ -0,0 +1,606 @@
+// DOMのイベント
+// リスナーはノードごとに登録し、イベントはキャプチャ、ターゲット、バブリングの順に伝わる
+
+use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+use crate::renderer::js::ast::JsParser;
+use crate::renderer::js::builtins::{arg, constructor, define_methods};
+use crate::renderer::js::dom_binding::interface;
+use crate::renderer::js::gc::Marker;
+use crate::renderer::js::runtime::{
+    JsException, JsObject, JsRuntime, NativeFunction, RuntimeValue,
+};
+use crate::renderer::js::token::JsLexer;
+use alloc::{
+    format,
+    rc::{Rc, Weak},
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::RefCell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// イベントが伝わっている段階。Event.eventPhaseの値になる
+pub enum EventPhase {
+    None = 0,
+    Capturing = 1,
+    AtTarget = 2,
+    Bubbling = 3,
+}
+
+impl EventPhase {
+    fn value(self) -> RuntimeValue {
+        RuntimeValue::Number(self as u8 as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EventListener {
+    // 伝播中に削除されたリスナーを見分けるための番号
+    id: usize,
+    node: Weak<RefCell<DomNode>>,
+    event_type: String,
+    callback: RuntimeValue,
+    capture: bool,
+    // 一度呼び出したら削除する
+    once: bool,
+}
+
+#[derive(Debug, Clone)]
+// 伝播中のイベントと、stopPropagation()が呼ばれたかどうか
+struct Dispatch {
+    event: RuntimeValue,
+    stopped: bool,
+    stopped_immediately: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+// ノードに登録されたイベントリスナーの一覧
+pub struct EventListeners {
+    listeners: Vec<EventListener>,
+    next_id: usize,
+    dispatches: Vec<Dispatch>,
+}
+
+impl EventListeners {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 同じ種類、コールバック、captureのリスナーは重複して登録しない
+    pub fn add(
+        &mut self,
+        node: &Rc<RefCell<DomNode>>,
+        event_type: &str,
+        callback: RuntimeValue,
+        capture: bool,
+        once: bool,
+    ) {
+        // 解放されたノードのリスナーを取り除く
+        self.listeners.retain(|l| l.node.strong_count() > 0);
+        if self.find(node, event_type, &callback, capture).is_some() {
+            return;
+        }
+        self.listeners.push(EventListener {
+            id: self.next_id,
+            node: Rc::downgrade(node),
+            event_type: event_type.to_string(),
+            callback,
+            capture,
+            once,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn remove(
+        &mut self,
+        node: &Rc<RefCell<DomNode>>,
+        event_type: &str,
+        callback: &RuntimeValue,
+        capture: bool,
+    ) {
+        if let Some(id) = self.find(node, event_type, callback, capture) {
+            self.remove_by_id(id);
+        }
+    }
+
+    fn find(
+        &self,
+        node: &Rc<RefCell<DomNode>>,
+        event_type: &str,
+        callback: &RuntimeValue,
+        capture: bool,
+    ) -> Option<usize> {
+        self.listeners
+            .iter()
+            .find(|l| {
+                l.node.as_ptr() == Rc::as_ptr(node)
+                    && l.event_type == event_type
+                    && l.callback == *callback
+                    && l.capture == capture
+            })
+            .map(|l| l.id)
+    }
+
+    fn remove_by_id(&mut self, id: usize) {
+        self.listeners.retain(|l| l.id != id);
+    }
+
+    fn contains(&self, id: usize) -> bool {
+        self.listeners.iter().any(|l| l.id == id)
+    }
+
+    // ノードのリスナーのうち、phaseで呼び出すもの。ターゲットではキャプチャのリスナーを先に呼び出す
+    fn matching(
+        &self,
+        node: &Rc<RefCell<DomNode>>,
+        event_type: &str,
+        phase: EventPhase,
+    ) -> Vec<EventListener> {
+        let mut listeners: Vec<EventListener> = self
+            .listeners
+            .iter()
+            .filter(|l| l.node.as_ptr() == Rc::as_ptr(node) && l.event_type == event_type)
+            .filter(|l| match phase {
+                EventPhase::Capturing => l.capture,
+                EventPhase::Bubbling => !l.capture,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        listeners.sort_by_key(|l| !l.capture);
+        listeners
+    }
+
+    fn dispatch(&mut self, event: &RuntimeValue) -> Option<&mut Dispatch> {
+        self.dispatches.iter_mut().rev().find(|d| d.event == *event)
+    }
+
+    // リスナーのコールバックと伝播中のイベントに印を付ける
+    pub fn mark(&self, marker: &mut Marker) {
+        for listener in &self.listeners {
+            if listener.node.strong_count() > 0 {
+                marker.mark_value(&listener.callback);
+            }
+        }
+        for dispatch in &self.dispatches {
+            marker.mark_value(&dispatch.event);
+        }
+    }
+}
+
+// EventTargetとEventのインターフェースを定義し、EventTarget.prototypeを返す
+pub fn define_globals(runtime: &mut JsRuntime) -> RuntimeValue {
+    let event_target = interface(runtime, "EventTarget", runtime.object_prototype());
+    define_methods(&event_target, &EVENT_TARGET_METHODS);
+
+    let event = runtime.new_object();
+    define_methods(&event, &EVENT_METHODS);
+    let event_constructor = constructor("Event", event_constructor, &event, &[]);
+    for (name, phase) in [
+        ("NONE", EventPhase::None),
+        ("CAPTURING_PHASE", EventPhase::Capturing),
+        ("AT_TARGET", EventPhase::AtTarget),
+        ("BUBBLING_PHASE", EventPhase::Bubbling),
+    ] {
+        event_constructor.set_property(name, phase.value());
+    }
+    runtime.define_global("Event", event_constructor);
+
+    event_target
+}
+
+// new Event(type, { bubbles, cancelable })
+fn event_constructor(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    if !this.is_object() {
+        return Err(
+            runtime.throw_error("TypeError", "Event constructor requires 'new'".to_string())
+        );
+    }
+    if args.is_empty() {
+        return Err(runtime.throw_error(
+            "TypeError",
+            "Event constructor requires 1 argument".to_string(),
+        ));
+    }
+    let init = arg(&args, 1);
+    init_event(
+        &this,
+        &arg(&args, 0).to_string(),
+        init.get_property("bubbles").is_truthy(),
+        init.get_property("cancelable").is_truthy(),
+    );
+    Ok(this)
+}
+
+fn init_event(event: &RuntimeValue, event_type: &str, bubbles: bool, cancelable: bool) {
+    event.set_property("type", RuntimeValue::StringLiteral(event_type.to_string()));
+    event.set_property("bubbles", RuntimeValue::Boolean(bubbles));
+    event.set_property("cancelable", RuntimeValue::Boolean(cancelable));
+    event.set_property("defaultPrevented", RuntimeValue::Boolean(false));
+    event.set_property("eventPhase", EventPhase::None.value());
+    event.set_property("target", RuntimeValue::Null);
+    event.set_property("currentTarget", RuntimeValue::Null);
+}
+
+// ブラウザが発生させるイベントを作る。clickのような追加のプロパティは呼び出し元で設定する
+pub fn new_event(
+    runtime: &mut JsRuntime,
+    event_type: &str,
+    bubbles: bool,
+    cancelable: bool,
+) -> RuntimeValue {
+    let prototype = runtime
+        .lookup_global("Event")
+        .map(|c| c.get_property("prototype"))
+        .ok();
+    let event = runtime.track(RuntimeValue::Object(Rc::new(RefCell::new(
+        JsObject::with_prototype(prototype),
+    ))));
+    init_event(&event, event_type, bubbles, cancelable);
+    event
+}
+
+// キャンセルできるイベントの場合は、デフォルトの動作を行わないようにする
+fn prevent_default(event: &RuntimeValue) {
+    if event.get_property("cancelable").is_truthy() {
+        event.set_property("defaultPrevented", RuntimeValue::Boolean(true));
+    }
+}
+
+static EVENT_METHODS: [(&str, NativeFunction); 3] = [
+    ("preventDefault", |_, this, _| {
+        prevent_default(&this);
+        Ok(RuntimeValue::Undefined)
+    }),
+    // 残りのノードへの伝播を止める。現在のノードの残りのリスナーは呼び出す
+    ("stopPropagation", |runtime, this, _| {
+        if let Some(dispatch) = runtime.event_listeners().dispatch(&this) {
+            dispatch.stopped = true;
+        }
+        Ok(RuntimeValue::Undefined)
+    }),
+    ("stopImmediatePropagation", |runtime, this, _| {
+        if let Some(dispatch) = runtime.event_listeners().dispatch(&this) {
+            dispatch.stopped = true;
+            dispatch.stopped_immediately = true;
+        }
+        Ok(RuntimeValue::Undefined)
+    }),
+];
+
+// thisのラッパーオブジェクトが表すDOMノード
+fn this_node(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+) -> Result<Rc<RefCell<DomNode>>, JsException> {
+    if let RuntimeValue::Object(object) = this {
+        if let Some(node) = object.borrow().node() {
+            return Ok(node);
+        }
+    }
+    Err(runtime.throw_error("TypeError", "illegal invocation".to_string()))
+}
+
+// addEventListenerの第3引数。真偽値の場合はcaptureを表す
+fn listener_options(options: &RuntimeValue) -> (bool, bool) {
+    match options {
+        RuntimeValue::Object(_) => (
+            options.get_property("capture").is_truthy(),
+            options.get_property("once").is_truthy(),
+        ),
+        _ => (options.is_truthy(), false),
+    }
+}
+
+static EVENT_TARGET_METHODS: [(&str, NativeFunction); 3] = [
+    // コールバックがnullの場合は何もしない
+    ("addEventListener", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let callback = arg(&args, 1);
+        if !callback.is_object() {
+            return Ok(RuntimeValue::Undefined);
+        }
+        let (capture, once) = listener_options(&arg(&args, 2));
+        runtime
+            .event_listeners()
+            .add(&node, &arg(&args, 0).to_string(), callback, capture, once);
+        Ok(RuntimeValue::Undefined)
+    }),
+    ("removeEventListener", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let (capture, _) = listener_options(&arg(&args, 2));
+        runtime.event_listeners().remove(
+            &node,
+            &arg(&args, 0).to_string(),
+            &arg(&args, 1),
+            capture,
+        );
+        Ok(RuntimeValue::Undefined)
+    }),
+    // preventDefault()が呼ばれた場合はfalseを返す
+    ("dispatchEvent", |runtime, this, args| {
+        let node = this_node(runtime, &this)?;
+        let event = arg(&args, 0);
+        let is_event = match runtime.lookup_global("Event") {
+            Ok(constructor) => runtime.instance_of(&event, &constructor)?,
+            Err(_) => false,
+        };
+        if !is_event {
+            return Err(runtime.throw_error(
+                "TypeError",
+                "parameter 1 is not of type 'Event'".to_string(),
+            ));
+        }
+        if runtime.event_listeners().dispatch(&event).is_some() {
+            return Err(runtime.throw_error(
+                "Error",
+                "InvalidStateError: the event is already being dispatched".to_string(),
+            ));
+        }
+        Ok(RuntimeValue::Boolean(dispatch_event(
+            runtime, &node, &event,
+        )))
+    }),
+];
+
+// targetからドキュメントまでの祖先にイベントを伝え、preventDefault()が呼ばれなかったかを返す
+// リスナーで発生した例外は伝播を止めず、ランタイムに報告する
+pub fn dispatch_event(
+    runtime: &mut JsRuntime,
+    target: &Rc<RefCell<DomNode>>,
+    event: &RuntimeValue,
+) -> bool {
+    let mut path = Vec::from([target.clone()]);
+    loop {
+        let parent = path[path.len() - 1].borrow().parent().upgrade();
+        match parent {
+            Some(parent) => path.push(parent),
+            None => break,
+        }
+    }
+
+    let target_wrapper = runtime.wrap_node(target);
+    event.set_property("target", target_wrapper);
+    runtime.event_listeners().dispatches.push(Dispatch {
+        event: event.clone(),
+        stopped: false,
+        stopped_immediately: false,
+    });
+
+    let bubbles = event.get_property("bubbles").is_truthy();
+    let phases = path
+        .iter()
+        .skip(1)
+        .rev()
+        .map(|node| (node, EventPhase::Capturing))
+        .chain([(target, EventPhase::AtTarget)])
+        .chain(
+            path.iter()
+                .skip(1)
+                .filter(|_| bubbles)
+                .map(|node| (node, EventPhase::Bubbling)),
+        );
+    for (node, phase) in phases {
+        invoke_listeners(runtime, node, event, phase);
+        if is_stopped(runtime, event) {
+            break;
+        }
+    }
+
+    runtime.event_listeners().dispatches.pop();
+    event.set_property("eventPhase", EventPhase::None.value());
+    event.set_property("currentTarget", RuntimeValue::Null);
+    !event.get_property("defaultPrevented").is_truthy()
+}
+
+fn is_stopped(runtime: &mut JsRuntime, event: &RuntimeValue) -> bool {
+    runtime
+        .event_listeners()
+        .dispatch(event)
+        .map(|d| d.stopped)
+        .unwrap_or(false)
+}
+
+// nodeに登録されたリスナーと、onclickのようなイベントハンドラを呼び出す
+fn invoke_listeners(
+    runtime: &mut JsRuntime,
+    node: &Rc<RefCell<DomNode>>,
+    event: &RuntimeValue,
+    phase: EventPhase,
+) {
+    let event_type = event.get_property("type").to_string();
+    let current_target = runtime.wrap_node(node);
+    event.set_property("currentTarget", current_target.clone());
+    event.set_property("eventPhase", phase.value());
+
+    let listeners = runtime.event_listeners().matching(node, &event_type, phase);
+    for listener in listeners {
+        // 伝播中に削除されたリスナーは呼び出さない
+        if !runtime.event_listeners().contains(listener.id) {
+            continue;
+        }
+        let result = call_listener(runtime, &listener.callback, &current_target, event);
+        if listener.once {
+            runtime.event_listeners().remove_by_id(listener.id);
+        }
+        if let Err(e) = result {
+            runtime.report_exception(e);
+        }
+        let stopped_immediately = runtime
+            .event_listeners()
+            .dispatch(event)
+            .map(|d| d.stopped_immediately)
+            .unwrap_or(false);
+        if stopped_immediately {
+            return;
+        }
+    }
+
+    if phase == EventPhase::Capturing {
+        return;
+    }
+    match event_handler(runtime, node, &current_target, &event_type) {
+        Ok(Some(handler)) => {
+            match runtime.call(&handler, current_target, Vec::from([event.clone()])) {
+                // ハンドラがfalseを返した場合はpreventDefault()と同じ
+                Ok(RuntimeValue::Boolean(false)) => prevent_default(event),
+                Ok(_) => {}
+                Err(e) => runtime.report_exception(e),
+            }
+        }
+        Ok(None) => {}
+        Err(e) => runtime.report_exception(e),
+    }
+}
+
+// 関数の場合はそのまま呼び出し、オブジェクトの場合はhandleEventメソッドを呼び出す
+fn call_listener(
+    runtime: &mut JsRuntime,
+    callback: &RuntimeValue,
+    current_target: &RuntimeValue,
+    event: &RuntimeValue,
+) -> Result<RuntimeValue, JsException> {
+    let args = Vec::from([event.clone()]);
+    if callback.is_function() {
+        return runtime.call(callback, current_target.clone(), args);
+    }
+    let handle_event = runtime.get(callback, "handleEvent")?;
+    runtime.call(&handle_event, callback.clone(), args)
+}
+
+// el.onclick = function () {}で設定された関数か、onclick属性のコードから作った関数
+fn event_handler(
+    runtime: &mut JsRuntime,
+    node: &Rc<RefCell<DomNode>>,
+    wrapper: &RuntimeValue,
+    event_type: &str,
+) -> Result<Option<RuntimeValue>, JsException> {
+    let name = format!("on{}", event_type);
+    let property = wrapper.get_property(&name);
+    if property.is_function() {
+        return Ok(Some(property));
+    }
+
+    let code = match node.borrow().kind() {
+        DomNodeKind::Element(e) => e.get_attribute(&name),
+        _ => None,
+    };
+    let code = match code {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+    // 属性のコードは、eventを引数に取る関数の本体として扱う
+    let lexer = JsLexer::new(format!("(function (event) {{\n{}\n}})", code));
+    let program = match JsParser::new(lexer).parse_ast() {
+        Ok(program) => program,
+        Err(e) => return Err(runtime.throw_error("SyntaxError", e.message())),
+    };
+    runtime.evaluate(&program).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::get_element_by_id;
+    use crate::renderer::html::{parser::HtmlParser, token::HtmlTokenizer};
+
+    fn setup(html: &str, script: &str) -> (Rc<RefCell<DomNode>>, JsRuntime) {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let dom = window.borrow().document();
+        let mut runtime = JsRuntime::new(dom.clone());
+        let program = JsParser::new(JsLexer::new(script.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        runtime.execute(&program).expect("failed to execute");
+        (dom, runtime)
+    }
+
+    fn click(runtime: &mut JsRuntime, dom: &Rc<RefCell<DomNode>>, id: &str) -> bool {
+        let target = get_element_by_id(Some(dom.clone()), &id.to_string()).expect("no element");
+        let event = new_event(runtime, "click", true, true);
+        dispatch_event(runtime, &target, &event)
+    }
+
+    const HTML: &str =
+        r#"<html><head></head><body><div id="outer"><p id="inner">text</p></div></body></html>"#;
+
+    #[test]
+    fn test_phases() {
+        let (dom, mut runtime) = setup(
+            HTML,
+            "var log = []; var outer = document.getElementById(\"outer\"); var inner = document.getElementById(\"inner\"); outer.addEventListener(\"click\", function (e) { log.push(\"capture:\" + e.eventPhase + \":\" + this.id); }, true); outer.addEventListener(\"click\", function (e) { log.push(\"bubble:\" + e.eventPhase + \":\" + e.target.id); }); inner.addEventListener(\"click\", function (e) { log.push(\"target:\" + e.eventPhase + \":\" + (e.currentTarget === inner)); }); document.addEventListener(\"click\", { handleEvent: function (e) { log.push(\"document\"); } });",
+        );
+        assert!(click(&mut runtime, &dom, "inner"));
+        assert_eq!(
+            Ok(RuntimeValue::StringLiteral(
+                "capture:1:outer,target:2:true,bubble:3:inner,document".to_string()
+            )),
+            runtime.evaluate(
+                &JsParser::new(JsLexer::new("log.join(\",\")".to_string()))
+                    .parse_ast()
+                    .expect("failed to parse")
+            )
+        );
+    }
+
+    #[test]
+    fn test_cancel_and_stop() {
+        let (dom, mut runtime) = setup(
+            HTML,
+            "var count = 0; var inner = document.getElementById(\"inner\"); function count_up() { count = count + 1; } inner.addEventListener(\"click\", function (e) { e.preventDefault(); e.stopPropagation(); }); inner.addEventListener(\"click\", count_up, { once: true }); document.getElementById(\"outer\").addEventListener(\"click\", count_up);",
+        );
+        assert!(!click(&mut runtime, &dom, "inner"));
+        assert!(!click(&mut runtime, &dom, "inner"));
+        // onceのリスナーは1回だけ呼ばれ、外側のノードには伝わらない
+        assert_eq!(
+            Ok(RuntimeValue::Number(1.0)),
+            runtime.lookup_global("count")
+        );
+    }
+
+    #[test]
+    fn test_event_handler_attribute() {
+        let html = r#"<html><head></head><body><a id="link" href="/next" onclick="window_clicked = event.type; return false;">link</a><p id="text" onclick="missing()">text</p></body></html>"#;
+        let (dom, mut runtime) = setup(
+            html,
+            "var window_clicked = null; var text = document.getElementById(\"text\"); var handled = false; text.addEventListener(\"click\", function () { handled = true; });",
+        );
+        // ハンドラがfalseを返したため、デフォルトの動作はキャンセルされる
+        assert!(!click(&mut runtime, &dom, "link"));
+        assert_eq!(
+            Ok(RuntimeValue::StringLiteral("click".to_string())),
+            runtime.lookup_global("window_clicked")
+        );
+
+        // ハンドラの例外は報告され、他のリスナーは呼び出される
+        assert!(click(&mut runtime, &dom, "text"));
+        assert_eq!(
+            Ok(RuntimeValue::Boolean(true)),
+            runtime.lookup_global("handled")
+        );
+        let errors = runtime.take_reported_exceptions();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "ReferenceError: missing is not defined",
+            errors[0].message()
+        );
+    }
+
+    #[test]
+    fn test_dispatch_event_from_script() {
+        let (_, runtime) = setup(
+            HTML,
+            "var inner = document.getElementById(\"inner\"); var seen = \"\"; document.body.addEventListener(\"ping\", function (e) { seen = seen + \"body\"; }); inner.addEventListener(\"ping\", function (e) { seen = seen + e.type + e.bubbles; }); inner.dispatchEvent(new Event(\"ping\")); var result = inner.dispatchEvent(new Event(\"ping\", { bubbles: true, cancelable: true })); var error; try { inner.dispatchEvent({}); } catch (e) { error = e.name; } result = seen + \"|\" + result + error;",
+        );
+        assert_eq!(
+            Ok(RuntimeValue::StringLiteral(
+                "pingfalsepingtruebody|trueTypeError".to_string()
+            )),
+            runtime.lookup_global("result")
+        );
+    }
+}
@@ -0,0 +1,538 @@
+// イベントループ
+// タスク、マイクロタスク、タイマー、requestAnimationFrameのコールバックを管理する
+// 時刻はClockから取得するため、テストでは時間を自由に進められる
+
+use crate::renderer::js::builtins::{arg, define_methods, native};
+use crate::renderer::js::gc::Marker;
+use crate::renderer::js::promise;
+use crate::renderer::js::runtime::{JsException, JsRuntime, NativeFunction, RuntimeValue};
+use alloc::{collections::VecDeque, rc::Rc, string::ToString, vec::Vec};
+use core::{cell::Cell, fmt::Debug};
+
+// requestAnimationFrameのコールバックを呼び出す間隔（ミリ秒）
+pub const FRAME_INTERVAL: u64 = 16;
+// タイマーの中でタイマーを作る入れ子がこの深さを超えると、遅延を最小値に切り上げる
+const MAX_TIMER_NESTING: u32 = 5;
+const MIN_NESTED_TIMER_DELAY: u64 = 4;
+// タイマーの遅延の上限。ブラウザと同じく符号付き32ビット整数の最大値に切り詰める
+const MAX_TIMER_DELAY: u64 = i32::MAX as u64;
+
+// 現在の時刻をミリ秒で返す時計
+pub trait Clock: Debug {
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Default)]
+// 手動で進める時計。複製した時計は同じ時刻を共有する
+pub struct ManualClock {
+    now: Rc<Cell<u64>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + ms);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+#[derive(Debug, Clone)]
+// キューに入れられた関数呼び出し
+pub struct Task {
+    callback: RuntimeValue,
+    args: Vec<RuntimeValue>,
+}
+
+impl Task {
+    pub fn new(callback: RuntimeValue, args: Vec<RuntimeValue>) -> Self {
+        Self { callback, args }
+    }
+
+    fn mark(&self, marker: &mut Marker) {
+        marker.mark_value(&self.callback);
+        for arg in &self.args {
+            marker.mark_value(arg);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Timer {
+    id: u32,
+    // コールバックを呼び出す時刻
+    due: u64,
+    // setIntervalの場合は繰り返す間隔
+    interval: Option<u64>,
+    nesting: u32,
+    task: Task,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventLoop {
+    clock: Rc<dyn Clock>,
+    tasks: VecDeque<Task>,
+    microtasks: VecDeque<Task>,
+    timers: Vec<Timer>,
+    animation_frames: Vec<(u32, RuntimeValue)>,
+    // 次にrequestAnimationFrameのコールバックを呼び出せる時刻
+    next_frame: u64,
+    next_id: u32,
+    // 実行中のタイマーの入れ子の深さ
+    timer_nesting: u32,
+    // 実行中のタスク。キューから取り出した後もGCのルートにする
+    running: Vec<Task>,
+    // 拒否されたときにコールバックが登録されていなかったPromise
+    rejected_promises: Vec<RuntimeValue>,
+}
+
+impl EventLoop {
+    pub fn new(clock: Rc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            tasks: VecDeque::new(),
+            microtasks: VecDeque::new(),
+            timers: Vec::new(),
+            animation_frames: Vec::new(),
+            next_frame: 0,
+            next_id: 1,
+            timer_nesting: 0,
+            running: Vec::new(),
+            rejected_promises: Vec::new(),
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn queue_task(&mut self, task: Task) {
+        self.tasks.push_back(task);
+    }
+
+    pub fn queue_microtask(&mut self, task: Task) {
+        self.microtasks.push_back(task);
+    }
+
+    // マイクロタスクの実行後に、まだ処理されていなければ報告する
+    pub fn add_rejected_promise(&mut self, promise: RuntimeValue) {
+        self.rejected_promises.push(promise);
+    }
+
+    pub fn take_rejected_promises(&mut self) -> Vec<RuntimeValue> {
+        core::mem::take(&mut self.rejected_promises)
+    }
+
+    // delayミリ秒後にタスクを実行するタイマーを登録し、タイマーのIDを返す
+    pub fn set_timer(&mut self, task: Task, delay: u64, repeat: bool) -> u32 {
+        let nesting = self.timer_nesting + 1;
+        let delay = delay.min(MAX_TIMER_DELAY);
+        let delay = if nesting > MAX_TIMER_NESTING {
+            delay.max(MIN_NESTED_TIMER_DELAY)
+        } else {
+            delay
+        };
+        let id = self.next_id();
+        self.timers.push(Timer {
+            id,
+            due: self.now().saturating_add(delay),
+            interval: if repeat { Some(delay.max(1)) } else { None },
+            nesting,
+            task,
+        });
+        id
+    }
+
+    pub fn clear_timer(&mut self, id: u32) {
+        self.timers.retain(|t| t.id != id);
+    }
+
+    pub fn request_animation_frame(&mut self, callback: RuntimeValue) -> u32 {
+        let id = self.next_id();
+        self.animation_frames.push((id, callback));
+        id
+    }
+
+    pub fn cancel_animation_frame(&mut self, id: u32) {
+        self.animation_frames.retain(|(i, _)| *i != id);
+    }
+
+    // 次に実行できるタスクとマイクロタスクがあるか、登録されたタイマーやコールバックがあるか
+    pub fn has_pending_work(&self) -> bool {
+        !self.tasks.is_empty()
+            || !self.microtasks.is_empty()
+            || !self.timers.is_empty()
+            || !self.animation_frames.is_empty()
+    }
+
+    // 最も早く呼び出されるタイマーの時刻
+    pub fn next_timer_due(&self) -> Option<u64> {
+        self.timers.iter().map(|t| t.due).min()
+    }
+
+    // 時刻が来たタイマーのうち最も早いもの。同じ時刻の場合は先に登録されたもの
+    // setIntervalのタイマーは次の時刻に登録し直す。遅れた場合は呼び出せなかった分をまとめて実行せず、
+    // 現在の時刻から数え直す
+    fn take_due_timer(&mut self) -> Option<(Task, u32)> {
+        let now = self.now();
+        let index = self
+            .timers
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.due <= now)
+            .min_by_key(|(_, t)| (t.due, t.id))
+            .map(|(i, _)| i)?;
+        let timer = &mut self.timers[index];
+        let found = (timer.task.clone(), timer.nesting);
+        match timer.interval {
+            Some(interval) => {
+                let next = timer.due.saturating_add(interval);
+                timer.due = if next > now {
+                    next
+                } else {
+                    now.saturating_add(interval)
+                };
+            }
+            None => {
+                self.timers.remove(index);
+            }
+        }
+        Some(found)
+    }
+
+    // 待っている値に印を付ける
+    pub fn mark(&self, marker: &mut Marker) {
+        for task in self
+            .tasks
+            .iter()
+            .chain(self.microtasks.iter())
+            .chain(self.timers.iter().map(|t| &t.task))
+            .chain(self.running.iter())
+        {
+            task.mark(marker);
+        }
+        for (_, callback) in &self.animation_frames {
+            marker.mark_value(callback);
+        }
+        for promise in &self.rejected_promises {
+            marker.mark_value(promise);
+        }
+    }
+}
+
+// タスクを実行する。例外は呼び出し元に返さず、ランタイムに報告する
+fn run_task(runtime: &mut JsRuntime, task: Task) {
+    runtime.event_loop().running.push(task.clone());
+    let result = runtime.call(&task.callback, RuntimeValue::Undefined, task.args);
+    runtime.event_loop().running.pop();
+    if let Err(e) = result {
+        runtime.report_exception(e);
+    }
+}
+
+// マイクロタスクのキューが空になるまで実行する。実行中に追加されたマイクロタスクも実行する
+pub fn perform_microtask_checkpoint(runtime: &mut JsRuntime) {
+    while let Some(task) = runtime.event_loop().microtasks.pop_front() {
+        run_task(runtime, task);
+    }
+    promise::report_unhandled_rejections(runtime);
+}
+
+// キューにあるタスクと、現在の時刻までに呼び出すタイマーを実行する。何か実行した場合はtrue
+// 各タスクの後にはマイクロタスクを実行する
+pub fn run_tasks(runtime: &mut JsRuntime) -> bool {
+    let mut ran = false;
+    // 実行中に追加されたタスクは次の呼び出しで実行する
+    let queued = runtime.event_loop().tasks.len();
+    for _ in 0..queued {
+        let task = match runtime.event_loop().tasks.pop_front() {
+            Some(task) => task,
+            None => break,
+        };
+        run_task(runtime, task);
+        perform_microtask_checkpoint(runtime);
+        ran = true;
+    }
+
+    while let Some((task, nesting)) = runtime.event_loop().take_due_timer() {
+        runtime.event_loop().timer_nesting = nesting;
+        run_task(runtime, task);
+        runtime.event_loop().timer_nesting = 0;
+        perform_microtask_checkpoint(runtime);
+        ran = true;
+    }
+    ran
+}
+
+// 描画の前に、requestAnimationFrameで登録されたコールバックを実行する。何か実行した場合はtrue
+// 実行中に登録されたコールバックは次のフレームで実行する
+pub fn run_animation_frames(runtime: &mut JsRuntime) -> bool {
+    let now = runtime.event_loop().now();
+    if runtime.event_loop().animation_frames.is_empty() || now < runtime.event_loop().next_frame {
+        return false;
+    }
+    runtime.event_loop().next_frame = now + FRAME_INTERVAL;
+
+    let callbacks = core::mem::take(&mut runtime.event_loop().animation_frames);
+    for (_, callback) in callbacks {
+        let task = Task::new(callback, Vec::from([RuntimeValue::Number(now as f64)]));
+        run_task(runtime, task);
+        perform_microtask_checkpoint(runtime);
+    }
+    true
+}
+
+// タイマーなどのグローバル関数を定義する
+pub fn define_globals(runtime: &mut JsRuntime) {
+    for (name, function) in GLOBAL_FUNCTIONS {
+        runtime.define_global(name, native(name, function));
+    }
+
+    let performance = runtime.new_object();
+    define_methods(
+        &performance,
+        &[("now", |runtime, _, _| {
+            Ok(RuntimeValue::Number(runtime.event_loop().now() as f64))
+        })],
+    );
+    runtime.define_global("performance", performance);
+}
+
+// コールバックの引数が関数でない場合はTypeError
+fn callback_arg(runtime: &JsRuntime, args: &[RuntimeValue]) -> Result<RuntimeValue, JsException> {
+    let callback = arg(args, 0);
+    if !callback.is_function() {
+        return Err(runtime.throw_error(
+            "TypeError",
+            "parameter 1 is not of type 'Function'".to_string(),
+        ));
+    }
+    Ok(callback)
+}
+
+// setTimeout(callback, delay, ...args)。負の値やNaNの遅延は0になり、Infinityのような大きな値は上限に切り詰める
+fn set_timer(
+    runtime: &mut JsRuntime,
+    args: Vec<RuntimeValue>,
+    repeat: bool,
+) -> Result<RuntimeValue, JsException> {
+    let callback = callback_arg(runtime, &args)?;
+    let delay = arg(&args, 1).to_number();
+    let delay = if delay.is_nan() || delay < 0.0 {
+        0
+    } else {
+        delay.min(MAX_TIMER_DELAY as f64) as u64
+    };
+    let task = Task::new(callback, args.into_iter().skip(2).collect());
+    let id = runtime.event_loop().set_timer(task, delay, repeat);
+    Ok(RuntimeValue::Number(id as f64))
+}
+
+fn clear_timer(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let id = arg(&args, 0).to_number();
+    if id.is_finite() {
+        runtime.event_loop().clear_timer(id as u32);
+    }
+    Ok(RuntimeValue::Undefined)
+}
+
+static GLOBAL_FUNCTIONS: [(&str, NativeFunction); 7] = [
+    ("setTimeout", |runtime, _, args| {
+        set_timer(runtime, args, false)
+    }),
+    ("setInterval", |runtime, _, args| {
+        set_timer(runtime, args, true)
+    }),
+    // setTimeoutとsetIntervalのIDは共通のため、どちらでも解除できる
+    ("clearTimeout", clear_timer),
+    ("clearInterval", clear_timer),
+    ("queueMicrotask", |runtime, _, args| {
+        let callback = callback_arg(runtime, &args)?;
+        runtime
+            .event_loop()
+            .queue_microtask(Task::new(callback, Vec::new()));
+        Ok(RuntimeValue::Undefined)
+    }),
+    // コールバックは次の描画の前に、その時刻を引数にして呼び出される
+    ("requestAnimationFrame", |runtime, _, args| {
+        let callback = callback_arg(runtime, &args)?;
+        let id = runtime.event_loop().request_animation_frame(callback);
+        Ok(RuntimeValue::Number(id as f64))
+    }),
+    ("cancelAnimationFrame", |runtime, _, args| {
+        let id = arg(&args, 0).to_number();
+        if id.is_finite() {
+            runtime.event_loop().cancel_animation_frame(id as u32);
+        }
+        Ok(RuntimeValue::Undefined)
+    }),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::token::JsLexer;
+    use alloc::string::String;
+    use core::cell::RefCell;
+
+    fn setup(script: &str) -> (ManualClock, JsRuntime) {
+        setup_with_clock(ManualClock::new(), script)
+    }
+
+    fn setup_with_clock(clock: ManualClock, script: &str) -> (ManualClock, JsRuntime) {
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        runtime.event_loop().set_clock(Rc::new(clock.clone()));
+        let program = JsParser::new(JsLexer::new(script.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        runtime.execute(&program).expect("failed to execute");
+        perform_microtask_checkpoint(&mut runtime);
+        (clock, runtime)
+    }
+
+    fn log(runtime: &JsRuntime) -> String {
+        match runtime.lookup_global("log") {
+            Ok(RuntimeValue::Array(elements)) => elements
+                .borrow()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            _ => panic!("log should be an array"),
+        }
+    }
+
+    #[test]
+    fn test_task_order() {
+        let (clock, mut runtime) = setup(
+            "var log = []; setTimeout(function () { log.push(\"timeout\"); queueMicrotask(function () { log.push(\"micro2\"); }); }, 0); setTimeout(function (a, b) { log.push(a + b); }, 10, \"late\", \"r\"); queueMicrotask(function () { log.push(\"micro1\"); }); log.push(\"script\");",
+        );
+        assert_eq!("script,micro1", log(&runtime));
+
+        assert!(run_tasks(&mut runtime));
+        assert_eq!("script,micro1,timeout,micro2", log(&runtime));
+
+        clock.advance(9);
+        assert!(!run_tasks(&mut runtime));
+        clock.advance(1);
+        assert!(run_tasks(&mut runtime));
+        assert_eq!("script,micro1,timeout,micro2,later", log(&runtime));
+        assert!(!runtime.event_loop().has_pending_work());
+    }
+
+    #[test]
+    fn test_interval_and_clear() {
+        let (clock, mut runtime) = setup(
+            "var log = []; var n = 0; var id = setInterval(function () { n = n + 1; log.push(performance.now()); if (n == 3) { clearInterval(id); } }, 10); var cancelled = setTimeout(function () { log.push(\"cancelled\"); }, 5); clearTimeout(cancelled);",
+        );
+        for _ in 0..3 {
+            clock.advance(10);
+            run_tasks(&mut runtime);
+        }
+        assert_eq!("10,20,30", log(&runtime));
+        assert_eq!(None, runtime.event_loop().next_timer_due());
+    }
+
+    #[test]
+    fn test_interval_skips_missed_ticks() {
+        let (clock, mut runtime) = setup(
+            "var log = []; setInterval(function () { log.push(performance.now()); }, 0); setInterval(function () { log.push(\"i\" + performance.now()); }, 30);",
+        );
+        // 時間をまとめて進めた場合も、呼び出しは1回ずつにして次の時刻を現在の時刻から数え直す
+        for _ in 0..5 {
+            clock.advance(100);
+            run_tasks(&mut runtime);
+        }
+        assert_eq!(
+            "100,i100,200,i200,300,i300,400,i400,500,i500",
+            log(&runtime)
+        );
+        assert_eq!(Some(501), runtime.event_loop().next_timer_due());
+    }
+
+    #[test]
+    fn test_large_delays_are_clamped() {
+        let clock = ManualClock::new();
+        clock.advance(1000);
+        let (_, mut runtime) = setup_with_clock(
+            clock.clone(),
+            "var log = []; setTimeout(function () { log.push(\"infinity\"); }, Infinity); setTimeout(function () { log.push(\"huge\"); }, 1e300); setTimeout(function () { log.push(\"nan\"); }, NaN);",
+        );
+        run_tasks(&mut runtime);
+        assert_eq!("nan", log(&runtime));
+        assert_eq!(
+            Some(1000 + i32::MAX as u64),
+            runtime.event_loop().next_timer_due()
+        );
+        clock.advance(i32::MAX as u64 - 1);
+        assert!(!run_tasks(&mut runtime));
+        clock.advance(1);
+        assert!(run_tasks(&mut runtime));
+        assert_eq!("nan,infinity,huge", log(&runtime));
+    }
+
+    #[test]
+    fn test_nested_timers_are_clamped() {
+        let (clock, mut runtime) = setup(
+            "var log = []; function tick() { log.push(performance.now()); if (log.length < 8) { setTimeout(tick, 0); } } setTimeout(tick, 0);",
+        );
+        run_tasks(&mut runtime);
+        assert_eq!("0,0,0,0,0", log(&runtime));
+        assert_eq!(Some(4), runtime.event_loop().next_timer_due());
+        clock.advance(8);
+        run_tasks(&mut runtime);
+        assert_eq!("0,0,0,0,0,8", log(&runtime));
+        assert_eq!(Some(12), runtime.event_loop().next_timer_due());
+    }
+
+    #[test]
+    fn test_animation_frames() {
+        let (clock, mut runtime) = setup(
+            "var log = []; function frame(time) { log.push(time); if (log.length < 2) { requestAnimationFrame(frame); } } requestAnimationFrame(frame); var id = requestAnimationFrame(function () { log.push(\"cancelled\"); }); cancelAnimationFrame(id);",
+        );
+        assert!(run_animation_frames(&mut runtime));
+        // 次のフレームの時刻までは呼び出さない
+        clock.advance(FRAME_INTERVAL - 1);
+        assert!(!run_animation_frames(&mut runtime));
+        clock.advance(1);
+        assert!(run_animation_frames(&mut runtime));
+        assert_eq!("0,16", log(&runtime));
+        assert!(!runtime.event_loop().has_pending_work());
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let (_, mut runtime) = setup(
+            "var log = []; setTimeout(function () { throw new Error(\"first\"); }, 0); setTimeout(function () { log.push(\"second\"); }, 0);",
+        );
+        run_tasks(&mut runtime);
+        assert_eq!("second", log(&runtime));
+        let errors = runtime.take_reported_exceptions();
+        assert_eq!(1, errors.len());
+        assert_eq!("Error: first", errors[0].message());
+    }
+}
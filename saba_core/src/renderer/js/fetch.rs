@@ -0,0 +1,688 @@
+// fetchとXMLHttpRequest
+// リクエストは埋め込み側が設定したTransportに送り、レスポンスはタスクとしてスクリプトに届ける
+
+use crate::error::Error;
+use crate::http::{Header, HttpRequest, HttpResponse};
+use crate::renderer::js::builtins::{
+    arg, constructor, define_methods, json_parse, native, own_entries,
+};
+use crate::renderer::js::dom_binding::interface;
+use crate::renderer::js::event::new_event;
+use crate::renderer::js::event_loop::Task;
+use crate::renderer::js::promise::{new_promise, reject_promise, resolve_promise};
+use crate::renderer::js::runtime::{
+    InternalSlot, JsException, JsObject, JsRuntime, NativeFunction, RuntimeValue,
+};
+use crate::url;
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::RefCell;
+
+#[derive(Debug, Clone)]
+// Responseの本文。本文は一度だけ読める
+pub struct ResponseState {
+    body: String,
+    body_used: bool,
+}
+
+#[derive(Debug, Clone)]
+// 送るリクエストと、受け取ったレスポンスのヘッダ。XMLHttpRequestはopen()でリクエストを作る
+pub struct RequestState {
+    request: HttpRequest,
+    sent: bool,
+    // open()の3番目の引数。falseの場合、send()はレスポンスを受け取るまで戻らない
+    is_async: bool,
+    response_headers: Vec<Header>,
+}
+
+// XMLHttpRequest.readyStateの値
+const UNSENT: f64 = 0.0;
+const OPENED: f64 = 1.0;
+const DONE: f64 = 4.0;
+
+// メソッドを検査する。よく使われるメソッドは大文字にし、CONNECTなどの禁止されたメソッドはTypeError
+fn normalize_method(runtime: &JsRuntime, method: &str) -> Result<String, JsException> {
+    let upper = method.to_ascii_uppercase();
+    if ["CONNECT", "TRACE", "TRACK"].contains(&upper.as_str()) {
+        return Err(runtime.throw_error("TypeError", format!("method {} is forbidden", method)));
+    }
+    if ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"].contains(&upper.as_str()) {
+        return Ok(upper);
+    }
+    Ok(method.to_string())
+}
+
+// メソッドとURLからリクエストを作る
+fn new_request(runtime: &JsRuntime, method: &str, url: &str) -> Result<HttpRequest, JsException> {
+    let method = normalize_method(runtime, method)?;
+    let url = resolve_url(runtime, url)?;
+    HttpRequest::new(&method, url).map_err(|e| runtime.throw_error("TypeError", error_message(e)))
+}
+
+fn error_message(error: Error) -> String {
+    match error {
+        Error::Network(m) | Error::UnexpectedInput(m) | Error::InvalidUI(m) | Error::Other(m) => m,
+    }
+}
+
+// 文字列の本文を設定する。GETとHEADは本文を持てない
+fn set_text_body(
+    runtime: &JsRuntime,
+    request: &mut HttpRequest,
+    body: &RuntimeValue,
+) -> Result<(), JsException> {
+    if matches!(body, RuntimeValue::Undefined | RuntimeValue::Null) {
+        return Ok(());
+    }
+    if ["GET", "HEAD"].contains(&request.method().as_str()) {
+        return Err(runtime.throw_error(
+            "TypeError",
+            "Request with GET/HEAD method cannot have body".to_string(),
+        ));
+    }
+    request.set_body(body.to_string().into_bytes(), "text/plain;charset=UTF-8");
+    Ok(())
+}
+
+// init.headersのヘッダを追加する。Headers、[名前, 値]の配列、オブジェクトを受け付ける
+fn append_headers(
+    runtime: &JsRuntime,
+    request: &mut HttpRequest,
+    headers: &RuntimeValue,
+) -> Result<(), JsException> {
+    let entries: Vec<(String, String)> = match headers {
+        RuntimeValue::Undefined | RuntimeValue::Null => Vec::new(),
+        RuntimeValue::Object(_) if this_headers(runtime, headers).is_ok() => {
+            this_headers(runtime, headers)?
+                .iter()
+                .map(|h| (h.name(), h.value()))
+                .collect()
+        }
+        RuntimeValue::Array(_) => own_entries(headers)
+            .into_iter()
+            .map(|(_, pair)| {
+                (
+                    pair.get_property("0").to_string(),
+                    pair.get_property("1").to_string(),
+                )
+            })
+            .collect(),
+        _ => own_entries(headers)
+            .into_iter()
+            .map(|(name, value)| (name, value.to_string()))
+            .collect(),
+    };
+    for (name, value) in entries {
+        request
+            .append_header(&name, &value)
+            .map_err(|e| runtime.throw_error("TypeError", error_message(e)))?;
+    }
+    Ok(())
+}
+
+// ドキュメントのURLを基準にしてURLを解決する
+fn resolve_url(runtime: &JsRuntime, input: &str) -> Result<String, JsException> {
+    url::resolve(&runtime.document_url(), input).map_err(|_| {
+        runtime.throw_error("TypeError", format!("Failed to parse URL from {}", input))
+    })
+}
+
+// Transportにリクエストを送る
+fn send(runtime: &JsRuntime, request: &HttpRequest) -> Option<HttpResponse> {
+    runtime.transport()?.send(request).ok()
+}
+
+pub fn define_globals(runtime: &mut JsRuntime) {
+    runtime.define_global("fetch", native("fetch", fetch));
+
+    let response = interface(runtime, "Response", runtime.object_prototype());
+    define_methods(&response, &RESPONSE_METHODS);
+    let headers = interface(runtime, "Headers", runtime.object_prototype());
+    define_methods(&headers, &HEADERS_METHODS);
+
+    let request = runtime.new_object();
+    define_methods(&request, &REQUEST_METHODS);
+    let request_constructor = constructor("XMLHttpRequest", request_constructor, &request, &[]);
+    for (name, state) in [
+        ("UNSENT", UNSENT),
+        ("OPENED", OPENED),
+        ("HEADERS_RECEIVED", 2.0),
+        ("LOADING", 3.0),
+        ("DONE", DONE),
+    ] {
+        request_constructor.set_property(name, RuntimeValue::Number(state));
+    }
+    runtime.define_global("XMLHttpRequest", request_constructor);
+}
+
+// インターフェースのプロトタイプを継承し、内部スロットを持つオブジェクトを作る
+fn new_instance(runtime: &JsRuntime, interface: &str, slot: InternalSlot) -> RuntimeValue {
+    let prototype = runtime
+        .lookup_global(interface)
+        .map(|c| c.get_property("prototype"))
+        .ok();
+    let mut object = JsObject::with_prototype(prototype);
+    object.set_slot(slot);
+    runtime.track(RuntimeValue::Object(Rc::new(RefCell::new(object))))
+}
+
+// fetch(input, init)。レスポンスを受け取るとResponseで解決されるPromiseを返す
+fn fetch(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let promise = new_promise(runtime);
+    let input = arg(&args, 0).to_string();
+    let init = arg(&args, 1);
+    let method = match init.get_property("method") {
+        RuntimeValue::Undefined => "GET".to_string(),
+        method => method.to_string(),
+    };
+    let request = new_request(runtime, &method, &input).and_then(|mut request| {
+        append_headers(runtime, &mut request, &init.get_property("headers"))?;
+        set_text_body(runtime, &mut request, &init.get_property("body"))?;
+        Ok(request)
+    });
+    match request {
+        Ok(request) => {
+            // タスクの引数として渡すため、リクエストを内部スロットに持つオブジェクトにする
+            let carrier = runtime.new_object();
+            if let RuntimeValue::Object(object) = &carrier {
+                object
+                    .borrow_mut()
+                    .set_slot(InternalSlot::Request(RequestState {
+                        request,
+                        sent: true,
+                        is_async: true,
+                        response_headers: Vec::new(),
+                    }));
+            }
+            let args = Vec::from([promise.clone(), carrier]);
+            let task = Task::new(native("", fetch_task), args);
+            runtime.event_loop().queue_task(task);
+        }
+        Err(exception) => reject_promise(runtime, &promise, exception.value()),
+    }
+    Ok(promise)
+}
+
+// リクエストを送り、結果でfetchのPromiseを解決する
+fn fetch_task(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let promise = arg(&args, 0);
+    let request = with_request(runtime, &arg(&args, 1), |state| state.request.clone())?;
+    match send(runtime, &request) {
+        Some(response) => {
+            let response = new_response(runtime, &request.url(), response);
+            resolve_promise(runtime, &promise, response);
+        }
+        None => {
+            let error = runtime.create_error("TypeError", "Failed to fetch".to_string());
+            reject_promise(runtime, &promise, error);
+        }
+    }
+    Ok(RuntimeValue::Undefined)
+}
+
+fn new_response(runtime: &JsRuntime, url: &str, response: HttpResponse) -> RuntimeValue {
+    let object = new_instance(
+        runtime,
+        "Response",
+        InternalSlot::Response(ResponseState {
+            body: response.body(),
+            body_used: false,
+        }),
+    );
+    let status = response.status_code();
+    object.set_property("status", RuntimeValue::Number(status as f64));
+    object.set_property("ok", RuntimeValue::Boolean((200..300).contains(&status)));
+    object.set_property("statusText", RuntimeValue::StringLiteral(response.reason()));
+    object.set_property("url", RuntimeValue::StringLiteral(url.to_string()));
+    object.set_property("bodyUsed", RuntimeValue::Boolean(false));
+    let headers = new_instance(
+        runtime,
+        "Headers",
+        InternalSlot::Headers(response.headers()),
+    );
+    object.set_property("headers", headers);
+    object
+}
+
+// Responseの本文を読む。二度目以降はTypeError
+fn take_body(runtime: &JsRuntime, this: &RuntimeValue) -> Result<String, JsException> {
+    let body = match this {
+        RuntimeValue::Object(object) => match object.borrow_mut().slot_mut() {
+            Some(InternalSlot::Response(state)) if state.body_used => None,
+            Some(InternalSlot::Response(state)) => {
+                state.body_used = true;
+                Some(core::mem::take(&mut state.body))
+            }
+            _ => return Err(runtime.throw_error("TypeError", "illegal invocation".to_string())),
+        },
+        _ => return Err(runtime.throw_error("TypeError", "illegal invocation".to_string())),
+    };
+    match body {
+        Some(body) => {
+            this.set_property("bodyUsed", RuntimeValue::Boolean(true));
+            Ok(body)
+        }
+        None => Err(runtime.throw_error("TypeError", "body stream already read".to_string())),
+    }
+}
+
+// 本文を読んで値に変換し、その値で解決されるPromiseを返す
+fn read_body(
+    runtime: &mut JsRuntime,
+    this: &RuntimeValue,
+    convert: NativeFunction,
+) -> Result<RuntimeValue, JsException> {
+    let promise = new_promise(runtime);
+    let result = take_body(runtime, this).and_then(|body| {
+        convert(
+            runtime,
+            RuntimeValue::Undefined,
+            Vec::from([RuntimeValue::StringLiteral(body)]),
+        )
+    });
+    match result {
+        Ok(value) => resolve_promise(runtime, &promise, value),
+        Err(exception) => reject_promise(runtime, &promise, exception.value()),
+    }
+    Ok(promise)
+}
+
+static RESPONSE_METHODS: [(&str, NativeFunction); 2] = [
+    ("text", |runtime, this, _| {
+        read_body(runtime, &this, |_, _, args| Ok(arg(&args, 0)))
+    }),
+    ("json", |runtime, this, _| {
+        read_body(runtime, &this, json_parse)
+    }),
+];
+
+// 名前が一致するヘッダの値。大文字と小文字は区別せず、複数ある場合は", "でつなげる
+fn header_value(headers: &[Header], name: &str) -> Option<String> {
+    let values: Vec<String> = headers
+        .iter()
+        .filter(|h| h.name().eq_ignore_ascii_case(name))
+        .map(|h| h.value())
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join(", "))
+    }
+}
+
+fn this_headers(runtime: &JsRuntime, this: &RuntimeValue) -> Result<Vec<Header>, JsException> {
+    if let RuntimeValue::Object(object) = this {
+        if let Some(InternalSlot::Headers(headers)) = RefCell::borrow(object).slot() {
+            return Ok(headers.clone());
+        }
+    }
+    Err(runtime.throw_error("TypeError", "illegal invocation".to_string()))
+}
+
+static HEADERS_METHODS: [(&str, NativeFunction); 2] = [
+    ("get", |runtime, this, args| {
+        let headers = this_headers(runtime, &this)?;
+        Ok(match header_value(&headers, &arg(&args, 0).to_string()) {
+            Some(value) => RuntimeValue::StringLiteral(value),
+            None => RuntimeValue::Null,
+        })
+    }),
+    ("has", |runtime, this, args| {
+        let headers = this_headers(runtime, &this)?;
+        Ok(RuntimeValue::Boolean(
+            header_value(&headers, &arg(&args, 0).to_string()).is_some(),
+        ))
+    }),
+];
+
+// new XMLHttpRequest()
+fn request_constructor(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    _: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let object = match &this {
+        RuntimeValue::Object(object) if RefCell::borrow(object).slot().is_none() => object,
+        _ => {
+            return Err(runtime.throw_error(
+                "TypeError",
+                "XMLHttpRequest constructor requires 'new'".to_string(),
+            ))
+        }
+    };
+    object
+        .borrow_mut()
+        .set_slot(InternalSlot::Request(RequestState {
+            request: HttpRequest::get(String::new()),
+            sent: false,
+            is_async: true,
+            response_headers: Vec::new(),
+        }));
+    set_ready_state(&this, UNSENT);
+    reset_response(&this);
+    for handler in ["onreadystatechange", "onload", "onerror", "onloadend"] {
+        this.set_property(handler, RuntimeValue::Null);
+    }
+    Ok(this)
+}
+
+// XMLHttpRequestの状態を操作する。XMLHttpRequestでない場合はTypeError
+fn with_request<T>(
+    runtime: &JsRuntime,
+    this: &RuntimeValue,
+    f: impl FnOnce(&mut RequestState) -> T,
+) -> Result<T, JsException> {
+    if let RuntimeValue::Object(object) = this {
+        if let Some(InternalSlot::Request(state)) = object.borrow_mut().slot_mut() {
+            return Ok(f(state));
+        }
+    }
+    Err(runtime.throw_error("TypeError", "illegal invocation".to_string()))
+}
+
+fn set_ready_state(request: &RuntimeValue, state: f64) {
+    request.set_property("readyState", RuntimeValue::Number(state));
+}
+
+fn reset_response(request: &RuntimeValue) {
+    request.set_property("status", RuntimeValue::Number(0.0));
+    request.set_property("statusText", RuntimeValue::StringLiteral(String::new()));
+    request.set_property("responseText", RuntimeValue::StringLiteral(String::new()));
+    request.set_property("responseURL", RuntimeValue::StringLiteral(String::new()));
+}
+
+// onloadのようなイベントハンドラを呼び出す。例外は報告して残りのハンドラを呼び出す
+fn fire(runtime: &mut JsRuntime, request: &RuntimeValue, event_type: &str) {
+    let handler = request.get_property(&format!("on{}", event_type));
+    if !handler.is_function() {
+        return;
+    }
+    let event = new_event(runtime, event_type, false, false);
+    event.set_property("target", request.clone());
+    event.set_property("currentTarget", request.clone());
+    if let Err(e) = runtime.call(&handler, request.clone(), Vec::from([event])) {
+        runtime.report_exception(e);
+    }
+}
+
+// open()の後でsend()の前でなければInvalidStateError
+fn check_opened(runtime: &JsRuntime, request: &RuntimeValue) -> Result<(), JsException> {
+    let is_opened = request.get_property("readyState").to_number() == OPENED;
+    if !is_opened || with_request(runtime, request, |state| state.sent)? {
+        return Err(runtime.throw_error(
+            "Error",
+            "InvalidStateError: the object's state must be OPENED".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+static REQUEST_METHODS: [(&str, NativeFunction); 5] = [
+    // open(method, url, async)。asyncが省略された場合は非同期
+    ("open", |runtime, this, args| {
+        let request = new_request(
+            runtime,
+            &arg(&args, 0).to_string(),
+            &arg(&args, 1).to_string(),
+        )?;
+        let is_async = args.len() < 3 || arg(&args, 2).is_truthy();
+        with_request(runtime, &this, |state| {
+            state.request = request;
+            state.sent = false;
+            state.is_async = is_async;
+            state.response_headers = Vec::new();
+        })?;
+        reset_response(&this);
+        set_ready_state(&this, OPENED);
+        fire(runtime, &this, "readystatechange");
+        Ok(RuntimeValue::Undefined)
+    }),
+    // setRequestHeader(name, value)。同じ名前のヘッダは", "でつなげて送る
+    ("setRequestHeader", |runtime, this, args| {
+        check_opened(runtime, &this)?;
+        let name = arg(&args, 0).to_string();
+        let value = arg(&args, 1).to_string();
+        with_request(runtime, &this, |state| {
+            state.request.append_header(&name, &value)
+        })?
+        .map_err(|e| runtime.throw_error("SyntaxError", error_message(e)))?;
+        Ok(RuntimeValue::Undefined)
+    }),
+    // send(body)。レスポンスはタスクとして処理する。GETとHEADでは本文を無視する
+    // 同期的なリクエストの場合は、レスポンスを処理してから戻る
+    ("send", |runtime, this, args| {
+        check_opened(runtime, &this)?;
+        let mut request = with_request(runtime, &this, |state| state.request.clone())?;
+        if !["GET", "HEAD"].contains(&request.method().as_str()) {
+            set_text_body(runtime, &mut request, &arg(&args, 0))?;
+        }
+        let is_async = with_request(runtime, &this, |state| {
+            state.request = request;
+            state.sent = true;
+            state.is_async
+        })?;
+        if !is_async {
+            return request_task(runtime, RuntimeValue::Undefined, Vec::from([this]));
+        }
+        let task = Task::new(native("", request_task), Vec::from([this]));
+        runtime.event_loop().queue_task(task);
+        Ok(RuntimeValue::Undefined)
+    }),
+    ("getResponseHeader", |runtime, this, args| {
+        let headers = with_request(runtime, &this, |state| state.response_headers.clone())?;
+        Ok(match header_value(&headers, &arg(&args, 0).to_string()) {
+            Some(value) => RuntimeValue::StringLiteral(value),
+            None => RuntimeValue::Null,
+        })
+    }),
+    ("getAllResponseHeaders", |runtime, this, _| {
+        let headers = with_request(runtime, &this, |state| state.response_headers.clone())?;
+        let mut all = String::new();
+        for h in headers {
+            all.push_str(&h.name().to_ascii_lowercase());
+            all.push_str(": ");
+            all.push_str(&h.value());
+            all.push_str("\r\n");
+        }
+        Ok(RuntimeValue::StringLiteral(all))
+    }),
+];
+
+// XMLHttpRequestのリクエストを送り、結果をプロパティに設定してハンドラを呼び出す
+fn request_task(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let request = arg(&args, 0);
+    let http_request = with_request(runtime, &request, |state| state.request.clone())?;
+    let url = http_request.url();
+    let response = send(runtime, &http_request);
+    set_ready_state(&request, DONE);
+    match response {
+        Some(response) => {
+            with_request(runtime, &request, |state| {
+                state.response_headers = response.headers();
+            })?;
+            let status = RuntimeValue::Number(response.status_code() as f64);
+            request.set_property("status", status);
+            request.set_property("statusText", RuntimeValue::StringLiteral(response.reason()));
+            request.set_property("responseText", RuntimeValue::StringLiteral(response.body()));
+            request.set_property("responseURL", RuntimeValue::StringLiteral(url));
+            fire(runtime, &request, "readystatechange");
+            fire(runtime, &request, "load");
+        }
+        None => {
+            fire(runtime, &request, "readystatechange");
+            fire(runtime, &request, "error");
+        }
+    }
+    fire(runtime, &request, "loadend");
+    Ok(RuntimeValue::Undefined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Transport;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::event_loop::{perform_microtask_checkpoint, run_tasks};
+    use crate::renderer::js::token::JsLexer;
+
+    #[derive(Debug)]
+    // 決まったURLにだけレスポンスを返すTransport。受け取ったURLを記録する
+    // GET以外のリクエストは記録して、本文をそのまま返す
+    struct MockTransport {
+        requests: RefCell<Vec<String>>,
+        sent: RefCell<Vec<HttpRequest>>,
+    }
+
+    impl Transport for MockTransport {
+        fn fetch(&self, url: String) -> Result<HttpResponse, Error> {
+            self.requests.borrow_mut().push(url.clone());
+            let raw = match url.as_str() {
+                "http://example.com/api/data.json" => {
+                    "HTTP/1.1 200 OK\nContent-Type: application/json\nX-Count: 1\nx-count: 2\n\n{\"items\": [1, 2, 3]}"
+                }
+                "http://example.com/missing" => "HTTP/1.1 404 NotFound\nContent-Type: text/plain\n\nnot found",
+                _ => return Err(Error::Network(format!("failed to connect to {}", url))),
+            };
+            HttpResponse::new(raw.to_string())
+        }
+
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+            if request.method() == "GET" {
+                return self.fetch(request.url());
+            }
+            self.sent.borrow_mut().push(request.clone());
+            let mut raw = b"HTTP/1.1 201 Created\r\n\r\n".to_vec();
+            raw.extend_from_slice(&request.body());
+            HttpResponse::from_bytes(&raw)
+        }
+    }
+
+    fn setup(script: &str) -> (Rc<MockTransport>, JsRuntime) {
+        let transport = Rc::new(MockTransport {
+            requests: RefCell::new(Vec::new()),
+            sent: RefCell::new(Vec::new()),
+        });
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        runtime.set_transport(transport.clone());
+        runtime.set_document_url("http://example.com/pages/index.html".to_string());
+        let program = JsParser::new(JsLexer::new(script.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        runtime.execute(&program).expect("failed to execute");
+        perform_microtask_checkpoint(&mut runtime);
+        (transport, runtime)
+    }
+
+    fn log(runtime: &JsRuntime) -> String {
+        match runtime.lookup_global("log") {
+            Ok(RuntimeValue::Array(elements)) => elements
+                .borrow()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            _ => panic!("log should be an array"),
+        }
+    }
+
+    #[test]
+    fn test_fetch() {
+        let (transport, mut runtime) = setup(
+            "var log = []; fetch(\"../api/data.json\").then(function (r) { log.push(r.status + \" \" + r.ok + \" \" + r.url); log.push(r.headers.get(\"x-count\") + \" \" + r.headers.has(\"Content-Type\") + \" \" + r.headers.get(\"missing\")); return r.json(); }).then(function (data) { log.push(data.items.length); }); fetch(\"/missing\").then(function (r) { log.push(r.status + \" \" + r.ok); return r.text().then(function (t) { log.push(t + \" \" + r.bodyUsed); return r.text(); }); }).catch(function (e) { log.push(e.name); }); fetch(\"http://offline.test/\").catch(function (e) { log.push(e.message); }); fetch(\"/x\", { method: \"TRACE\" }).catch(function (e) { log.push(e.name); }); log.push(\"sync\");",
+        );
+        assert_eq!("sync,TypeError", log(&runtime));
+
+        assert!(run_tasks(&mut runtime));
+        assert_eq!(
+            "sync,TypeError,200 true http://example.com/api/data.json,1, 2 true null,3,404 false,not found true,TypeError,Failed to fetch",
+            log(&runtime)
+        );
+        assert_eq!(
+            Vec::from([
+                "http://example.com/api/data.json".to_string(),
+                "http://example.com/missing".to_string(),
+                "http://offline.test/".to_string(),
+            ]),
+            *transport.requests.borrow()
+        );
+    }
+
+    #[test]
+    fn test_xml_http_request() {
+        let (_, mut runtime) = setup(
+            "var log = []; var xhr = new XMLHttpRequest(); xhr.onreadystatechange = function () { log.push(\"state \" + xhr.readyState); }; xhr.onload = function (e) { log.push(this.status + \" \" + this.statusText + \" \" + this.responseText + \" \" + (e.target === xhr)); log.push(xhr.getResponseHeader(\"X-COUNT\")); }; xhr.onloadend = function () { log.push(\"loadend\"); }; xhr.open(\"get\", \"/missing\"); xhr.send(); try { xhr.send(); } catch (e) { log.push(e.message); } var failed = new XMLHttpRequest(); failed.onerror = function () { log.push(\"error \" + failed.status); }; failed.open(\"GET\", \"http://offline.test/\"); failed.send();",
+        );
+        assert_eq!(
+            "state 1,InvalidStateError: the object's state must be OPENED",
+            log(&runtime)
+        );
+
+        assert!(run_tasks(&mut runtime));
+        assert_eq!(
+            "state 1,InvalidStateError: the object's state must be OPENED,state 4,404 NotFound not found true,null,loadend,error 0",
+            log(&runtime)
+        );
+    }
+
+    #[test]
+    fn test_synchronous_xml_http_request() {
+        let (transport, mut runtime) = setup(
+            "var log = []; var xhr = new XMLHttpRequest(); xhr.onload = function () { log.push(\"load\"); }; xhr.open(\"GET\", \"/api/data.json\", false); xhr.send(); log.push(xhr.readyState + \" \" + xhr.status + \" \" + xhr.responseText);",
+        );
+        // send()から戻る前にレスポンスを処理し、タスクは残らない
+        assert_eq!("load,4 200 {\"items\": [1, 2, 3]}", log(&runtime));
+        assert!(!run_tasks(&mut runtime));
+        assert_eq!(1, transport.requests.borrow().len());
+    }
+
+    #[test]
+    fn test_fetch_with_body() {
+        let (transport, mut runtime) = setup(
+            "var log = []; fetch(\"/api/items\", { method: \"post\", headers: { \"Content-Type\": \"application/json\", \"X-Token\": \"a\" }, body: \"{}\" }).then(function (r) { log.push(r.status); return r.text(); }).then(function (t) { log.push(t); }); fetch(\"/x\", { body: \"a\" }).catch(function (e) { log.push(e.message); }); fetch(\"/x\", { method: \"PATCH\", headers: [[\"X-Bad\", \"a\\nb\"]] }).catch(function (e) { log.push(e.name); }); var xhr = new XMLHttpRequest(); try { xhr.setRequestHeader(\"X-A\", \"1\"); } catch (e) { log.push(e.message); } xhr.open(\"PUT\", \"/api/items/1\"); xhr.setRequestHeader(\"X-A\", \"1\"); xhr.setRequestHeader(\"X-A\", \"2\"); xhr.onload = function () { log.push(xhr.status + \" \" + xhr.responseText); }; xhr.send(\"name=saba\");",
+        );
+        assert_eq!(
+            "InvalidStateError: the object's state must be OPENED,Request with GET/HEAD method cannot have body,TypeError",
+            log(&runtime)
+        );
+
+        assert!(run_tasks(&mut runtime));
+        assert_eq!(
+            "InvalidStateError: the object's state must be OPENED,Request with GET/HEAD method cannot have body,TypeError,201,{},201 name=saba",
+            log(&runtime)
+        );
+        let sent = transport.sent.borrow();
+        assert_eq!(2, sent.len());
+        assert_eq!("POST", sent[0].method());
+        assert_eq!("http://example.com/api/items", sent[0].url());
+        assert_eq!(
+            Some("application/json".to_string()),
+            sent[0].header_value("content-type")
+        );
+        assert_eq!(Some("a".to_string()), sent[0].header_value("X-Token"));
+        assert_eq!("PUT", sent[1].method());
+        assert_eq!(Some("1, 2".to_string()), sent[1].header_value("X-A"));
+        assert_eq!(
+            Some("text/plain;charset=UTF-8".to_string()),
+            sent[1].header_value("Content-Type")
+        );
+        assert_eq!(b"name=saba".to_vec(), sent[1].body());
+    }
+}
@@ -0,0 +1,468 @@
+// JSのヒープを管理するマーク&スイープ方式のガベージコレクタ
+// 値はRcで共有されるため、クロージャが自身のスコープを参照するような循環参照は解放されない
+// GCはルートから到達できる値に印を付け、印のない値のプロパティや変数を空にして循環を切る
+
+use crate::renderer::js::runtime::{Environment, Function, JsArray, JsObject, RuntimeValue};
+use crate::renderer::js::vm::Scope;
+use alloc::{
+    collections::BTreeSet,
+    rc::{Rc, Weak},
+    vec::Vec,
+};
+use core::cell::RefCell;
+
+// 最初のGCを実行するまでに登録できる値の数
+pub const INITIAL_THRESHOLD: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+// ヒープの統計情報
+pub struct HeapStats {
+    // 登録されている値のうち、解放されていないものの数
+    pub objects: usize,
+    pub arrays: usize,
+    pub functions: usize,
+    pub scopes: usize,
+    pub environments: usize,
+    // GCを実行した回数
+    pub collections: usize,
+    // 最後のGCで循環を切った値の数
+    pub freed: usize,
+}
+
+// 参照している値をたどれる型。ルートから到達できない場合はclearで参照を手放す
+pub trait Trace {
+    // 参照している値をmarkerに登録する
+    fn trace(&self, marker: &mut Marker);
+    // 参照している値をreleaserへ移して循環参照を切る
+    fn clear(&self, releaser: &mut Releaser);
+}
+
+#[derive(Debug, Clone)]
+// ヒープに登録された値。登録しても値の寿命は変わらない
+enum Allocation {
+    Object(Weak<RefCell<JsObject>>),
+    Array(Weak<RefCell<JsArray>>),
+    Function(Weak<Function>),
+    Scope(Weak<Scope>),
+    Environment(Weak<RefCell<Environment>>),
+}
+
+// 生存している値。印を付ける時と循環を切る時に使う
+enum Live {
+    Object(Rc<RefCell<JsObject>>),
+    Array(Rc<RefCell<JsArray>>),
+    Function(Rc<Function>),
+    Scope(Rc<Scope>),
+    Environment(Rc<RefCell<Environment>>),
+}
+
+impl Allocation {
+    fn is_alive(&self) -> bool {
+        match self {
+            Allocation::Object(weak) => weak.strong_count() > 0,
+            Allocation::Array(weak) => weak.strong_count() > 0,
+            Allocation::Function(weak) => weak.strong_count() > 0,
+            Allocation::Scope(weak) => weak.strong_count() > 0,
+            Allocation::Environment(weak) => weak.strong_count() > 0,
+        }
+    }
+
+    fn upgrade(&self) -> Option<Live> {
+        match self {
+            Allocation::Object(weak) => weak.upgrade().map(Live::Object),
+            Allocation::Array(weak) => weak.upgrade().map(Live::Array),
+            Allocation::Function(weak) => weak.upgrade().map(Live::Function),
+            Allocation::Scope(weak) => weak.upgrade().map(Live::Scope),
+            Allocation::Environment(weak) => weak.upgrade().map(Live::Environment),
+        }
+    }
+}
+
+impl Live {
+    fn address(&self) -> usize {
+        match self {
+            Live::Object(rc) => address(rc),
+            Live::Array(rc) => address(rc),
+            Live::Function(rc) => address(rc),
+            Live::Scope(rc) => address(rc),
+            Live::Environment(rc) => address(rc),
+        }
+    }
+
+    fn trace(&self, marker: &mut Marker) {
+        match self {
+            Live::Object(rc) => rc.trace(marker),
+            Live::Array(rc) => rc.trace(marker),
+            Live::Function(rc) => rc.trace(marker),
+            Live::Scope(rc) => rc.trace(marker),
+            Live::Environment(rc) => rc.trace(marker),
+        }
+    }
+
+    fn clear(&self, releaser: &mut Releaser) {
+        match self {
+            Live::Object(rc) => rc.clear(releaser),
+            Live::Array(rc) => rc.clear(releaser),
+            Live::Function(rc) => rc.clear(releaser),
+            Live::Scope(rc) => rc.clear(releaser),
+            Live::Environment(rc) => rc.clear(releaser),
+        }
+    }
+}
+
+fn address<T>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const () as usize
+}
+
+#[derive(Default)]
+// ルートから到達できる値に印を付ける。深いオブジェクトでもスタックが溢れないように、作業リストを使う
+pub struct Marker {
+    marked: BTreeSet<usize>,
+    pending: Vec<Live>,
+}
+
+impl Marker {
+    pub fn mark_value(&mut self, value: &RuntimeValue) {
+        match value {
+            RuntimeValue::Object(object) => self.push(Live::Object(object.clone())),
+            RuntimeValue::Array(elements) => self.push(Live::Array(elements.clone())),
+            RuntimeValue::Function(function) => self.push(Live::Function(function.clone())),
+            _ => {}
+        }
+    }
+
+    pub fn mark_scope(&mut self, scope: &Rc<Scope>) {
+        self.push(Live::Scope(scope.clone()));
+    }
+
+    pub fn mark_environment(&mut self, env: &Rc<RefCell<Environment>>) {
+        self.push(Live::Environment(env.clone()));
+    }
+
+    pub fn is_marked<T>(&self, rc: &Rc<T>) -> bool {
+        self.marked.contains(&address(rc))
+    }
+
+    fn push(&mut self, live: Live) {
+        if self.marked.insert(live.address()) {
+            self.pending.push(live);
+        }
+    }
+
+    // 印を付けた値が参照している値を、新しい値がなくなるまでたどる
+    pub fn trace_all(&mut self) {
+        while let Some(live) = self.pending.pop() {
+            live.trace(self);
+        }
+    }
+}
+
+#[derive(Default)]
+// 参照を手放した値を解放する。長いリストや深い木を入れ子のDropで解放するとスタックが溢れるため、
+// 参照している値を作業リストに移し、最後の参照だったものから順に中身を取り出して解放する
+pub struct Releaser {
+    pending: Vec<Live>,
+}
+
+impl Releaser {
+    pub fn push_value(&mut self, value: RuntimeValue) {
+        match value {
+            RuntimeValue::Object(object) => self.pending.push(Live::Object(object)),
+            RuntimeValue::Array(elements) => self.pending.push(Live::Array(elements)),
+            RuntimeValue::Function(function) => self.pending.push(Live::Function(function)),
+            _ => {}
+        }
+    }
+
+    pub fn push_scope(&mut self, scope: Rc<Scope>) {
+        self.pending.push(Live::Scope(scope));
+    }
+
+    pub fn push_environment(&mut self, env: Rc<RefCell<Environment>>) {
+        self.pending.push(Live::Environment(env));
+    }
+
+    // 作業リストが空になるまで解放する。他からも参照されている値は参照を1つ減らすだけになる
+    pub fn release_all(&mut self) {
+        while let Some(live) = self.pending.pop() {
+            // 中身を取り出した値は、参照している値を持たないためDropが浅く終わる
+            match live {
+                Live::Object(rc) => {
+                    if let Ok(object) = Rc::try_unwrap(rc) {
+                        object.into_inner().release(self);
+                    }
+                }
+                Live::Array(rc) => {
+                    if let Ok(array) = Rc::try_unwrap(rc) {
+                        array.into_inner().release(self);
+                    }
+                }
+                Live::Function(rc) => {
+                    if let Ok(mut function) = Rc::try_unwrap(rc) {
+                        function.release(self);
+                    }
+                }
+                Live::Scope(rc) => {
+                    if let Ok(mut scope) = Rc::try_unwrap(rc) {
+                        scope.release(self);
+                    }
+                }
+                Live::Environment(rc) => {
+                    if let Ok(env) = Rc::try_unwrap(rc) {
+                        env.into_inner().release(self);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+// GCの対象になる値の一覧と、GCを実行する時期の管理
+pub struct Heap {
+    allocations: Vec<Allocation>,
+    // 前回のGCの後に登録された値の数
+    allocated: usize,
+    // allocatedがこの数を超えるとGCを実行する
+    threshold: usize,
+    collections: usize,
+    freed: usize,
+}
+
+impl Heap {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            allocations: Vec::new(),
+            allocated: 0,
+            threshold,
+            collections: 0,
+            freed: 0,
+        }
+    }
+
+    fn register(&mut self, allocation: Allocation) {
+        self.allocations.push(allocation);
+        self.allocated += 1;
+    }
+
+    // 新しく作った値を登録する。数値や文字列のようにRcを持たない値は無視する
+    pub fn track_value(&mut self, value: &RuntimeValue) {
+        match value {
+            RuntimeValue::Object(object) => {
+                self.register(Allocation::Object(Rc::downgrade(object)))
+            }
+            RuntimeValue::Array(elements) => {
+                self.register(Allocation::Array(Rc::downgrade(elements)))
+            }
+            RuntimeValue::Function(function) => {
+                self.register(Allocation::Function(Rc::downgrade(function)))
+            }
+            _ => {}
+        }
+    }
+
+    pub fn track_scope(&mut self, scope: &Rc<Scope>) {
+        self.register(Allocation::Scope(Rc::downgrade(scope)));
+    }
+
+    pub fn track_environment(&mut self, env: &Rc<RefCell<Environment>>) {
+        self.register(Allocation::Environment(Rc::downgrade(env)));
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.allocated >= self.threshold
+    }
+
+    // 印のない値の参照を手放し、解放された値を一覧から取り除く。循環を切った値の数を返す
+    // 到達できない長いリストや木もスタックを使わずに解放できるように、手放した値は作業リストで解放する
+    pub fn sweep(&mut self, marker: &Marker) -> usize {
+        let mut garbage = Vec::new();
+        self.allocations
+            .retain(|allocation| match allocation.upgrade() {
+                Some(live) if marker.marked.contains(&live.address()) => true,
+                Some(live) => {
+                    garbage.push(live);
+                    false
+                }
+                None => false,
+            });
+        let mut releaser = Releaser::default();
+        for live in &garbage {
+            live.clear(&mut releaser);
+        }
+        let freed = garbage.len();
+        releaser.pending.extend(garbage);
+        releaser.release_all();
+
+        // 生存している値が増えた場合は、GCの間隔を広げる
+        self.threshold = INITIAL_THRESHOLD.max(self.allocations.len());
+        self.allocated = 0;
+        self.collections += 1;
+        self.freed = freed;
+        self.freed
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let mut stats = HeapStats {
+            collections: self.collections,
+            freed: self.freed,
+            ..HeapStats::default()
+        };
+        for allocation in &self.allocations {
+            if !allocation.is_alive() {
+                continue;
+            }
+            match allocation {
+                Allocation::Object(_) => stats.objects += 1,
+                Allocation::Array(_) => stats.arrays += 1,
+                Allocation::Function(_) => stats.functions += 1,
+                Allocation::Scope(_) => stats.scopes += 1,
+                Allocation::Environment(_) => stats.environments += 1,
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::html::{parser::HtmlParser, token::HtmlTokenizer};
+    use crate::renderer::js::ast::{JsParser, Program};
+    use crate::renderer::js::runtime::JsRuntime;
+    use crate::renderer::js::token::JsLexer;
+    use alloc::string::ToString;
+
+    fn parse(input: &str) -> Program {
+        let lexer = JsLexer::new(input.to_string());
+        let mut parser = JsParser::new(lexer);
+        parser.parse_ast().expect("failed to parse")
+    }
+
+    fn execute(runtime: &mut JsRuntime, input: &str) {
+        runtime.execute(&parse(input)).expect("failed to execute");
+    }
+
+    fn new_runtime() -> JsRuntime {
+        JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))))
+    }
+
+    #[test]
+    fn test_collect_cycles() {
+        let mut runtime = new_runtime();
+        execute(
+            &mut runtime,
+            "var keep = { n: 1 }; keep.self = keep; var leak = (function () { let o = {}; o.f = function () { return o; }; return o; })();",
+        );
+        let leak = match runtime.lookup_global("leak") {
+            Ok(RuntimeValue::Object(object)) => Rc::downgrade(&object),
+            _ => panic!("leak should be an object"),
+        };
+        execute(&mut runtime, "leak = null;");
+        // 循環参照があるため、参照がなくなっても解放されない
+        assert!(leak.upgrade().is_some());
+
+        let before = runtime.heap_stats();
+        let after = runtime.collect_garbage();
+        assert!(leak.upgrade().is_none());
+        assert_eq!(1, after.collections);
+        assert!(after.freed > 0);
+        assert!(after.objects < before.objects);
+        assert!(after.functions < before.functions);
+
+        // ルートから到達できる値は残る
+        execute(&mut runtime, "var result = keep.self.n;");
+        assert_eq!(
+            Ok(RuntimeValue::Number(1.0)),
+            runtime.lookup_global("result")
+        );
+    }
+
+    #[test]
+    fn test_collect_during_execution() {
+        let mut runtime = new_runtime();
+        execute(
+            &mut runtime,
+            "var fs = []; var s = 0; for (let i = 0; i < 10000; i = i + 1) { let o = { i: i }; o.self = o; o.get = () => o.i; if (i % 1000 == 0) { fs.push(o.get); } s = s + o.get(); } var result = s; for (let j = 0; j < fs.length; j = j + 1) { result = result + fs[j](); }",
+        );
+        assert_eq!(
+            Ok(RuntimeValue::Number(49995000.0 + 45000.0)),
+            runtime.lookup_global("result")
+        );
+
+        let stats = runtime.heap_stats();
+        assert!(stats.collections > 0);
+        assert!(stats.objects < 10000);
+    }
+
+    #[test]
+    fn test_release_deep_chain() {
+        // 長いリスト、深く入れ子になった配列、前のクロージャを捕捉するクロージャの列
+        let mut runtime = new_runtime();
+        execute(
+            &mut runtime,
+            "var head = null; for (var i = 0; i < 100000; i++) head = { value: i, next: head }; var a = []; for (var i = 0; i < 100000; i++) a = [a]; var f = null; for (let i = 0; i < 100000; i++) { let g = f; f = () => g; }",
+        );
+        let head = match runtime.lookup_global("head") {
+            Ok(RuntimeValue::Object(object)) => Rc::downgrade(&object),
+            _ => panic!("head should be an object"),
+        };
+        execute(&mut runtime, "head = null; a = null; f = null;");
+        assert!(head.upgrade().is_none());
+
+        // ランタイムを破棄する時も、変数から参照されている値を作業リストで解放する
+        let mut runtime = new_runtime();
+        execute(
+            &mut runtime,
+            "var head = null; for (var i = 0; i < 100000; i++) head = { next: head };",
+        );
+        drop(runtime);
+    }
+
+    #[test]
+    fn test_collect_deep_cycle() {
+        // 循環参照を含む、到達できない長いリストと深い木
+        let mut runtime = new_runtime();
+        execute(
+            &mut runtime,
+            "var first = {}; var head = first; for (var i = 0; i < 100000; i++) head = { next: head }; first.last = head; var root = { children: [] }; var node = root; for (var i = 0; i < 100000; i++) { var child = { parent: node, children: [] }; node.children.push(child); node = child; }",
+        );
+        let first = match runtime.lookup_global("first") {
+            Ok(RuntimeValue::Object(object)) => Rc::downgrade(&object),
+            _ => panic!("first should be an object"),
+        };
+        execute(
+            &mut runtime,
+            "first = null; head = null; root = null; node = null; child = null;",
+        );
+        assert!(first.upgrade().is_some());
+
+        let stats = runtime.collect_garbage();
+        assert!(first.upgrade().is_none());
+        assert!(stats.freed >= 200000);
+    }
+
+    #[test]
+    fn test_collect_dom_wrappers() {
+        let html = "<html><head></head><body><p id=\"target\">hello</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let dom = RefCell::borrow(&window).document();
+        let mut runtime = JsRuntime::new(dom);
+        execute(
+            &mut runtime,
+            "(function () { let t = document.getElementById(\"target\"); t.handler = function () { return t; }; })();",
+        );
+        runtime.collect_garbage();
+
+        // 文書に含まれるノードのラッパーと、そのプロパティは残る
+        execute(
+            &mut runtime,
+            "var p = document.getElementById(\"target\"); var result = p.handler() === p && p.textContent == \"hello\";",
+        );
+        assert_eq!(
+            Ok(RuntimeValue::Boolean(true)),
+            runtime.lookup_global("result")
+        );
+    }
+}
@@ -1,3 +1,16 @@
 pub mod ast;
+pub mod builtins;
+pub mod bytecode;
+pub mod compiler;
+pub mod console;
+pub mod dom_binding;
+pub mod event;
+pub mod event_loop;
+pub mod fetch;
+pub mod gc;
+pub mod module;
+pub mod promise;
+pub mod regexp;
 pub mod runtime;
 pub mod token;
+pub mod vm;
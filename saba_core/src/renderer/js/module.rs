@@ -0,0 +1,644 @@
+// ESモジュールの読み込み、リンク、評価
+// 指定子はimportしたモジュールのURLを基準に解決し、Transportから取得する。同じURLのモジュールは1度だけ評価する
+// モジュールの変数はモジュールごとのスコープに置き、グローバル変数とは分ける
+
+use crate::renderer::js::ast::{JsParser, Node, Program};
+use crate::renderer::js::bytecode::Code;
+use crate::renderer::js::compiler::{compile_module, DEFAULT_EXPORT};
+use crate::renderer::js::gc::Marker;
+use crate::renderer::js::runtime::{JsException, JsObject, JsRuntime, RuntimeValue};
+use crate::renderer::js::token::JsLexer;
+use crate::renderer::js::vm::Scope;
+use crate::url;
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::borrow::Borrow;
+use core::cell::RefCell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModuleStatus {
+    Unlinked,
+    // importした変数が公開元の変数に結び付けられた
+    Linked,
+    // 循環して読み込まれた場合、評価中のモジュールは評価済みとして扱う
+    Evaluating,
+    Evaluated,
+}
+
+#[derive(Debug, Clone)]
+// import文で読み込む変数。importedが*の場合は名前空間オブジェクト
+struct ImportEntry {
+    request: String,
+    imported: String,
+    local: String,
+}
+
+#[derive(Debug, Clone)]
+// export {a as b} from "m"のように、別のモジュールの変数を公開する
+struct IndirectExport {
+    exported: String,
+    request: String,
+    imported: String,
+}
+
+#[derive(Debug, Clone)]
+// パースしたモジュールと、リンクと評価の状態
+struct ModuleRecord {
+    url: String,
+    // script要素に書かれたモジュールは、URLで参照されない
+    inline: bool,
+    code: Rc<Code>,
+    scope: Rc<Scope>,
+    // import文とexport fromの指定子
+    requests: Vec<String>,
+    // requestsと同じ順に並んだ、読み込んだモジュールの位置
+    dependencies: Vec<usize>,
+    imports: Vec<ImportEntry>,
+    // 公開する名前と、モジュールのスコープの変数の名前
+    local_exports: Vec<(String, String)>,
+    indirect_exports: Vec<IndirectExport>,
+    // export * fromの指定子
+    star_exports: Vec<String>,
+    // import * asで参照される名前空間オブジェクト。評価が終わった時点の値を持つ
+    namespace: Option<RuntimeValue>,
+    status: ModuleStatus,
+    // リンクか評価で発生した例外。同じモジュールを再び読み込んだ場合にも返す
+    error: Option<JsException>,
+}
+
+impl ModuleRecord {
+    // 指定子で読み込んだモジュールの位置
+    fn dependency(&self, request: &str) -> usize {
+        let index = self
+            .requests
+            .iter()
+            .position(|r| r == request)
+            .expect("module request should exist");
+        self.dependencies[index]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+// 読み込んだモジュール。モジュールは位置で参照する
+pub struct ModuleMap {
+    records: Vec<ModuleRecord>,
+}
+
+impl ModuleMap {
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+        }
+    }
+
+    fn find(&self, url: &str) -> Option<usize> {
+        self.records
+            .iter()
+            .position(|record| !record.inline && record.url == url)
+    }
+
+    // 読み込んだモジュールのスコープと名前空間オブジェクトはGCのルートになる
+    pub fn mark(&self, marker: &mut Marker) {
+        for record in &self.records {
+            marker.mark_scope(&record.scope);
+            if let Some(namespace) = &record.namespace {
+                marker.mark_value(namespace);
+            }
+            if let Some(error) = &record.error {
+                marker.mark_value(&error.value());
+            }
+        }
+    }
+}
+
+impl JsRuntime {
+    // script要素のモジュールを、依存するモジュールと共に読み込んで評価する。urlは指定子の基準になる
+    pub fn run_module(&mut self, url: &str, source: String) -> Result<(), JsException> {
+        let entry = self.parse_module(url, source, true)?;
+        self.fetch_dependencies(entry)?;
+        self.link(entry)?;
+        self.evaluate_module(entry)
+    }
+
+    fn parse_module(
+        &mut self,
+        url: &str,
+        source: String,
+        inline: bool,
+    ) -> Result<usize, JsException> {
+        let program = JsParser::new(JsLexer::new(source))
+            .parse_module()
+            .map_err(|e| {
+                self.throw_error(
+                    "SyntaxError",
+                    format!("{} ({}:{})", e.message(), url, e.position()),
+                )
+            })?;
+        // script要素のモジュールは実行中のスクリプトのURLを、importしたモジュールはそのURLをソースにする
+        let source = if inline { self.source() } else { Rc::from(url) };
+        let code = compile_module(&program, source);
+        let info = code.scope.clone().expect("module scope should exist");
+        let scope = self.track_scope(Scope::new(info, None));
+
+        let mut record = ModuleRecord {
+            url: url.to_string(),
+            inline,
+            code: Rc::new(code),
+            scope,
+            requests: Vec::new(),
+            dependencies: Vec::new(),
+            imports: Vec::new(),
+            local_exports: Vec::new(),
+            indirect_exports: Vec::new(),
+            star_exports: Vec::new(),
+            namespace: None,
+            status: ModuleStatus::Unlinked,
+            error: None,
+        };
+        collect_entries(&program, &mut record);
+
+        let records = &mut self.modules().records;
+        records.push(record);
+        Ok(records.len() - 1)
+    }
+
+    // 依存するモジュールをすべて読み込む
+    fn fetch_dependencies(&mut self, entry: usize) -> Result<(), JsException> {
+        let mut pending = Vec::from([entry]);
+        while let Some(index) = pending.pop() {
+            let record = self.modules().records[index].clone();
+            // 前回の読み込みが途中で失敗したモジュールは、もう一度読み込む
+            if record.dependencies.len() == record.requests.len() {
+                continue;
+            }
+
+            let mut dependencies = Vec::new();
+            for request in &record.requests {
+                let url = self.resolve_specifier(&record.url, request)?;
+                let dependency = match self.modules().find(&url) {
+                    Some(dependency) => dependency,
+                    None => {
+                        let source = self.fetch_module(&url)?;
+                        self.parse_module(&url, source, false)?
+                    }
+                };
+                pending.push(dependency);
+                dependencies.push(dependency);
+            }
+            self.modules().records[index].dependencies = dependencies;
+        }
+        Ok(())
+    }
+
+    // ./や../で始まる相対URLか、絶対URLだけを指定子として使える
+    fn resolve_specifier(&self, base: &str, specifier: &str) -> Result<String, JsException> {
+        let is_relative = ["/", "./", "../"]
+            .iter()
+            .any(|prefix| specifier.starts_with(prefix));
+        let resolved = if is_relative || specifier.contains("://") {
+            url::resolve(base, specifier).ok()
+        } else {
+            None
+        };
+        resolved.ok_or_else(|| {
+            self.throw_error(
+                "TypeError",
+                format!(
+                    "Failed to resolve module specifier \"{}\". Relative references must start with either \"/\", \"./\", or \"../\".",
+                    specifier
+                ),
+            )
+        })
+    }
+
+    // 2xx以外のステータスは失敗とする
+    fn fetch_module(&self, url: &str) -> Result<String, JsException> {
+        let response = self
+            .transport()
+            .and_then(|transport| transport.fetch(url.to_string()).ok());
+        match response {
+            Some(response) if (200..300).contains(&response.status_code()) => Ok(response.body()),
+            _ => Err(self.throw_error("TypeError", format!("Failed to fetch module {}", url))),
+        }
+    }
+
+    // 依存するモジュールから順に、importした変数を公開元の変数に結び付ける
+    fn link(&mut self, index: usize) -> Result<(), JsException> {
+        if self.modules().records[index].status != ModuleStatus::Unlinked {
+            return Ok(());
+        }
+        // 循環して読み込まれた場合に、もう一度リンクしない
+        self.modules().records[index].status = ModuleStatus::Linked;
+
+        let result = self.link_imports(index);
+        if let Err(e) = &result {
+            // リンクに失敗したモジュールは評価しない
+            let record = &mut self.modules().records[index];
+            record.status = ModuleStatus::Evaluated;
+            record.error = Some(e.clone());
+        }
+        result
+    }
+
+    fn link_imports(&mut self, index: usize) -> Result<(), JsException> {
+        let record = self.modules().records[index].clone();
+        for dependency in &record.dependencies {
+            self.link(*dependency)?;
+        }
+
+        let info = record.scope.info();
+        let mut imports = Vec::new();
+        for local in &info.imports {
+            let entry = record
+                .imports
+                .iter()
+                .find(|entry| &entry.local == local)
+                .expect("import entry should exist");
+            let dependency = record.dependency(&entry.request);
+            match self.resolve_export(dependency, &entry.imported, &mut Vec::new()) {
+                Some((module, name)) => {
+                    let scope = self.modules().records[module].scope.clone();
+                    let slot = scope
+                        .info()
+                        .index_of(&name)
+                        .expect("exported binding should exist");
+                    imports.push((scope, slot));
+                }
+                None => {
+                    return Err(self.throw_error(
+                        "SyntaxError",
+                        format!(
+                            "The requested module '{}' does not provide an export named '{}'",
+                            entry.request, entry.imported
+                        ),
+                    ))
+                }
+            }
+        }
+        record.scope.set_imports(imports);
+
+        for entry in record.imports.iter().filter(|entry| entry.imported == "*") {
+            let namespace = self.namespace(record.dependency(&entry.request));
+            let slot = info
+                .index_of(&entry.local)
+                .expect("namespace binding should exist");
+            record.scope.set(slot, namespace);
+        }
+        Ok(())
+    }
+
+    // 公開された名前から、公開元のモジュールの位置と変数の名前を探す
+    // visitedは循環したexport fromを見つけるために使う
+    fn resolve_export(
+        &mut self,
+        index: usize,
+        name: &str,
+        visited: &mut Vec<(usize, String)>,
+    ) -> Option<(usize, String)> {
+        if visited.iter().any(|(i, n)| *i == index && n == name) {
+            return None;
+        }
+        visited.push((index, name.to_string()));
+
+        let record = self.modules().records[index].clone();
+        if let Some((_, local)) = record.local_exports.iter().find(|(e, _)| e == name) {
+            return Some((index, local.clone()));
+        }
+        if let Some(export) = record.indirect_exports.iter().find(|e| e.exported == name) {
+            let dependency = record.dependency(&export.request);
+            return self.resolve_export(dependency, &export.imported, visited);
+        }
+        // export * fromはdefaultを公開しない
+        if name == "default" {
+            return None;
+        }
+        for request in &record.star_exports {
+            let dependency = record.dependency(request);
+            if let Some(resolved) = self.resolve_export(dependency, name, visited) {
+                return Some(resolved);
+            }
+        }
+        None
+    }
+
+    // モジュールが公開する名前の一覧
+    fn exported_names(&mut self, index: usize, visited: &mut Vec<usize>) -> Vec<String> {
+        if visited.contains(&index) {
+            return Vec::new();
+        }
+        visited.push(index);
+
+        let record = self.modules().records[index].clone();
+        let mut names: Vec<String> = record
+            .local_exports
+            .iter()
+            .map(|(exported, _)| exported.clone())
+            .chain(record.indirect_exports.iter().map(|e| e.exported.clone()))
+            .collect();
+        for request in &record.star_exports {
+            for name in self.exported_names(record.dependency(request), visited) {
+                if name != "default" && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    // import * asで参照される名前空間オブジェクト。プロトタイプを持たない
+    fn namespace(&mut self, index: usize) -> RuntimeValue {
+        if let Some(namespace) = &self.modules().records[index].namespace {
+            return namespace.clone();
+        }
+        let namespace = self.track(RuntimeValue::Object(Rc::new(RefCell::new(JsObject::new()))));
+        self.modules().records[index].namespace = Some(namespace.clone());
+        self.update_namespace(index);
+        namespace
+    }
+
+    // 名前空間オブジェクトのプロパティを、公開された変数の現在の値にする
+    fn update_namespace(&mut self, index: usize) {
+        let namespace = match &self.modules().records[index].namespace {
+            Some(namespace) => namespace.clone(),
+            None => return,
+        };
+        for name in self.exported_names(index, &mut Vec::new()) {
+            if let Some((module, local)) = self.resolve_export(index, &name, &mut Vec::new()) {
+                let scope = self.modules().records[module].scope.clone();
+                let value = scope.info().index_of(&local).and_then(|i| scope.get(i));
+                if let Some(value) = value {
+                    namespace.set_property(&name, value);
+                }
+            }
+        }
+    }
+
+    // 依存するモジュールから順に評価する。循環している場合は評価中のモジュールを飛ばす
+    fn evaluate_module(&mut self, index: usize) -> Result<(), JsException> {
+        let record = self.modules().records[index].clone();
+        match record.status {
+            ModuleStatus::Evaluated => {
+                return match record.error {
+                    Some(e) => Err(e),
+                    None => Ok(()),
+                }
+            }
+            ModuleStatus::Evaluating => return Ok(()),
+            _ => {}
+        }
+        self.modules().records[index].status = ModuleStatus::Evaluating;
+
+        let mut result = Ok(());
+        for dependency in &record.dependencies {
+            result = self.evaluate_module(*dependency);
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self
+                .run_module_code(record.code.clone(), record.scope.clone())
+                .map(|_| ());
+        }
+
+        let record = &mut self.modules().records[index];
+        record.status = ModuleStatus::Evaluated;
+        record.error = result.clone().err();
+        // export *で公開し直しているモジュールの名前空間も変わる
+        for i in 0..self.modules().records.len() {
+            self.update_namespace(i);
+        }
+        result
+    }
+}
+
+// import文とexport文から、モジュールが読み込む変数と公開する変数を集める
+fn collect_entries(program: &Program, record: &mut ModuleRecord) {
+    // export {a}でimportした変数を公開し直す場合のために、import文を先に集める
+    for node in program.body() {
+        if let Node::ImportDeclaration { specifiers, source } = node.borrow() {
+            add_request(record, source);
+            for (imported, local) in specifiers {
+                record.imports.push(ImportEntry {
+                    request: source.clone(),
+                    imported: imported.clone(),
+                    local: local.clone(),
+                });
+            }
+        }
+    }
+
+    for node in program.body() {
+        match node.borrow() {
+            Node::ExportNamedDeclaration {
+                declaration: Some(declaration),
+                ..
+            } => {
+                for name in declared_names(declaration) {
+                    record.local_exports.push((name.clone(), name));
+                }
+            }
+            Node::ExportNamedDeclaration {
+                specifiers,
+                source: Some(source),
+                ..
+            } => {
+                add_request(record, source);
+                for (imported, exported) in specifiers {
+                    record.indirect_exports.push(IndirectExport {
+                        exported: exported.clone(),
+                        request: source.clone(),
+                        imported: imported.clone(),
+                    });
+                }
+            }
+            Node::ExportNamedDeclaration { specifiers, .. } => {
+                for (local, exported) in specifiers {
+                    let import = record
+                        .imports
+                        .iter()
+                        .find(|entry| &entry.local == local && entry.imported != "*")
+                        .cloned();
+                    match import {
+                        Some(import) => record.indirect_exports.push(IndirectExport {
+                            exported: exported.clone(),
+                            request: import.request,
+                            imported: import.imported,
+                        }),
+                        None => record.local_exports.push((exported.clone(), local.clone())),
+                    }
+                }
+            }
+            Node::ExportDefaultDeclaration { declaration } => {
+                let local = match declaration.as_deref() {
+                    Some(Node::FunctionDeclaration { id, .. })
+                    | Some(Node::ClassDeclaration { id, .. }) => match id.as_deref() {
+                        Some(Node::Identifier(name)) => name.clone(),
+                        _ => DEFAULT_EXPORT.to_string(),
+                    },
+                    _ => DEFAULT_EXPORT.to_string(),
+                };
+                record.local_exports.push(("default".to_string(), local));
+            }
+            Node::ExportAllDeclaration { source } => {
+                add_request(record, source);
+                record.star_exports.push(source.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_request(record: &mut ModuleRecord, source: &str) {
+    if !record.requests.iter().any(|r| r == source) {
+        record.requests.push(source.to_string());
+    }
+}
+
+// export var a = 1, b;やexport function f() {}で宣言される変数の名前
+fn declared_names(declaration: &Rc<Node>) -> Vec<String> {
+    match declaration.borrow() {
+        Node::VariableDeclaration { declarations, .. } => declarations
+            .iter()
+            .flatten()
+            .filter_map(|declarator| match declarator.borrow() {
+                Node::VariableDeclarator { id, .. } => match id.as_deref() {
+                    Some(Node::Identifier(name)) => Some(name.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        Node::FunctionDeclaration { id, .. } | Node::ClassDeclaration { id, .. } => {
+            match id.as_deref() {
+                Some(Node::Identifier(name)) => Vec::from([name.clone()]),
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::http::HttpResponse;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+
+    fn serve(url: String) -> Result<HttpResponse, Error> {
+        let body = match url.as_str() {
+            "http://example.com/app/lib/math.js" => {
+                "loads = loads + 1; export let count = 0; export function increment() { count = count + 1; } export default function (a, b) { return a + b; } export const PI = 3;"
+            }
+            "http://example.com/app/lib/index.js" => {
+                "export * from \"./math.js\"; export { default as add } from \"./math.js\"; import { PI } from \"./math.js\"; export { PI as pi };"
+            }
+            "http://example.com/app/a.js" => {
+                "import { b } from \"./b.js\"; export function a() { return \"a\" + b(); }"
+            }
+            "http://example.com/app/b.js" => {
+                "import { a } from \"./a.js\"; export function b() { return \"b\"; } export function callA() { return a(); }"
+            }
+            "http://example.com/app/broken.js" => "export let = 1;",
+            _ => return Err(Error::Network(format!("failed to connect to {}", url))),
+        };
+        HttpResponse::new(format!(
+            "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\n{}",
+            body
+        ))
+    }
+
+    fn new_runtime() -> JsRuntime {
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        let transport: fn(String) -> Result<HttpResponse, Error> = serve;
+        runtime.set_transport(Rc::new(transport));
+        runtime.define_global("loads", RuntimeValue::Number(0.0));
+        runtime
+    }
+
+    fn run(runtime: &mut JsRuntime, source: &str) -> Result<(), String> {
+        runtime
+            .run_module("http://example.com/app/index.html", source.to_string())
+            .map_err(|e| e.message())
+    }
+
+    #[test]
+    fn test_import_and_export() {
+        let mut runtime = new_runtime();
+        assert_eq!(
+            Ok(()),
+            run(
+                &mut runtime,
+                "import add, { count, increment as inc } from \"./lib/math.js\"; import * as lib from \"/app/lib/index.js\"; import { pi } from \"./lib/index.js\"; import { callA } from \"./b.js\"; var hidden = 1; inc(); inc(); result = add(1, 2) + \" \" + count + \" \" + pi + \" \" + lib.add(2, 3) + \" \" + lib.PI + \" \" + lib.default + \" \" + callA();"
+            )
+        );
+        // importした変数は公開元の変数の現在の値を参照する
+        assert_eq!(
+            Ok(RuntimeValue::StringLiteral(
+                "3 2 3 5 3 undefined ab".to_string()
+            )),
+            runtime.lookup_global("result")
+        );
+        // モジュールの変数はグローバル変数にならない
+        assert!(runtime.lookup_global("hidden").is_err());
+
+        // 同じURLのモジュールは1度だけ評価される
+        assert_eq!(
+            Ok(()),
+            run(
+                &mut runtime,
+                "import { count } from \"./lib/math.js\"; result = count;"
+            )
+        );
+        assert_eq!(
+            Ok(RuntimeValue::Number(2.0)),
+            runtime.lookup_global("result")
+        );
+        assert_eq!(
+            Ok(RuntimeValue::Number(1.0)),
+            runtime.lookup_global("loads")
+        );
+    }
+
+    #[test]
+    fn test_module_errors() {
+        let mut runtime = new_runtime();
+        assert_eq!(
+            Err("SyntaxError: The requested module './lib/math.js' does not provide an export named 'missing'".to_string()),
+            run(&mut runtime, "import { missing } from \"./lib/math.js\";")
+        );
+        assert_eq!(
+            Err("TypeError: Failed to resolve module specifier \"lib\". Relative references must start with either \"/\", \"./\", or \"../\".".to_string()),
+            run(&mut runtime, "import \"lib\";")
+        );
+        assert_eq!(
+            Err("TypeError: Failed to fetch module http://example.com/missing.js".to_string()),
+            run(&mut runtime, "import \"../missing.js\";")
+        );
+        assert_eq!(
+            Err(
+                "SyntaxError: unexpected token '=' (http://example.com/app/broken.js:1:12)"
+                    .to_string()
+            ),
+            run(&mut runtime, "import \"./broken.js\";")
+        );
+        assert_eq!(
+            Err("TypeError: assignment to constant variable 'PI'".to_string()),
+            run(
+                &mut runtime,
+                "import { PI } from \"./lib/math.js\"; PI = 4;"
+            )
+        );
+        // 失敗したimportで評価されなかったモジュールは、後で読み込んだ時に評価される
+        assert_eq!(
+            Ok(RuntimeValue::Number(1.0)),
+            runtime.lookup_global("loads")
+        );
+    }
+}
@@ -0,0 +1,730 @@
+// Promiseの組み込みオブジェクト
+// Promiseが解決されると、thenで登録されたコールバックをマイクロタスクとして呼び出す
+
+use crate::renderer::js::builtins::{
+    arg, constructor, define_methods, native, native_closure, new_array,
+};
+use crate::renderer::js::event_loop::Task;
+use crate::renderer::js::gc::Marker;
+use crate::renderer::js::runtime::{
+    InternalSlot, JsException, JsObject, JsRuntime, NativeFunction, RuntimeValue,
+};
+use alloc::{format, rc::Rc, string::ToString, vec::Vec};
+use core::cell::RefCell;
+
+#[derive(Debug, Clone)]
+// thenで登録されたコールバックと、コールバックの結果で解決するPromise
+struct Reaction {
+    // awaitのように結果を使わない場合はundefined
+    promise: RuntimeValue,
+    on_fulfilled: RuntimeValue,
+    on_rejected: RuntimeValue,
+}
+
+#[derive(Debug, Clone)]
+enum Status {
+    Pending(Vec<Reaction>),
+    Fulfilled(RuntimeValue),
+    Rejected(RuntimeValue),
+}
+
+#[derive(Debug, Clone)]
+// Promiseの内部スロット
+pub struct PromiseState {
+    status: Status,
+    // thenでコールバックが登録されたか。拒否されたまま処理されないPromiseを報告するために使う
+    handled: bool,
+}
+
+impl PromiseState {
+    fn new() -> Self {
+        Self {
+            status: Status::Pending(Vec::new()),
+            handled: false,
+        }
+    }
+
+    pub fn mark(&self, marker: &mut Marker) {
+        match &self.status {
+            Status::Pending(reactions) => {
+                for reaction in reactions {
+                    marker.mark_value(&reaction.promise);
+                    marker.mark_value(&reaction.on_fulfilled);
+                    marker.mark_value(&reaction.on_rejected);
+                }
+            }
+            Status::Fulfilled(value) | Status::Rejected(value) => marker.mark_value(value),
+        }
+    }
+}
+
+// Promiseの内部スロットを操作する。Promiseでない場合はNone
+fn with_state<T>(promise: &RuntimeValue, f: impl FnOnce(&mut PromiseState) -> T) -> Option<T> {
+    match promise {
+        RuntimeValue::Object(object) => match object.borrow_mut().slot_mut() {
+            Some(InternalSlot::Promise(state)) => Some(f(state)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn is_promise(value: &RuntimeValue) -> bool {
+    with_state(value, |_| ()).is_some()
+}
+
+// 待機中のPromiseを作る
+pub fn new_promise(runtime: &JsRuntime) -> RuntimeValue {
+    let mut object = JsObject::with_prototype(Some(runtime.promise_prototype()));
+    object.set_slot(InternalSlot::Promise(PromiseState::new()));
+    runtime.track(RuntimeValue::Object(Rc::new(RefCell::new(object))))
+}
+
+// Promiseを値で解決する。値がthenメソッドを持つ場合は、その結果を待つ
+pub fn resolve_promise(runtime: &mut JsRuntime, promise: &RuntimeValue, resolution: RuntimeValue) {
+    if resolution == *promise {
+        let error = runtime.create_error(
+            "TypeError",
+            "chaining cycle detected for promise".to_string(),
+        );
+        reject_promise(runtime, promise, error);
+        return;
+    }
+    if !resolution.is_object() {
+        settle(runtime, promise, true, resolution);
+        return;
+    }
+
+    match runtime.get(&resolution, "then") {
+        Ok(then) if then.is_function() => {
+            let args = Vec::from([promise.clone(), resolution, then]);
+            let task = Task::new(native("", resolve_thenable_job), args);
+            runtime.event_loop().queue_microtask(task);
+        }
+        Ok(_) => settle(runtime, promise, true, resolution),
+        Err(exception) => reject_promise(runtime, promise, exception.value()),
+    }
+}
+
+pub fn reject_promise(runtime: &mut JsRuntime, promise: &RuntimeValue, reason: RuntimeValue) {
+    settle(runtime, promise, false, reason);
+}
+
+// Promiseを確定し、登録されたコールバックを呼び出すマイクロタスクを追加する
+fn settle(runtime: &mut JsRuntime, promise: &RuntimeValue, fulfilled: bool, value: RuntimeValue) {
+    let settled = with_state(promise, |state| {
+        let reactions = match &mut state.status {
+            Status::Pending(reactions) => core::mem::take(reactions),
+            _ => return None,
+        };
+        state.status = if fulfilled {
+            Status::Fulfilled(value.clone())
+        } else {
+            Status::Rejected(value.clone())
+        };
+        Some((reactions, state.handled))
+    })
+    .flatten();
+    let (reactions, handled) = match settled {
+        Some(settled) => settled,
+        None => return,
+    };
+
+    if !fulfilled && !handled {
+        runtime.event_loop().add_rejected_promise(promise.clone());
+    }
+    for reaction in reactions {
+        queue_reaction_job(runtime, reaction, fulfilled, value.clone());
+    }
+}
+
+fn queue_reaction_job(
+    runtime: &mut JsRuntime,
+    reaction: Reaction,
+    fulfilled: bool,
+    argument: RuntimeValue,
+) {
+    let handler = if fulfilled {
+        reaction.on_fulfilled
+    } else {
+        reaction.on_rejected
+    };
+    let args = Vec::from([
+        reaction.promise,
+        handler,
+        RuntimeValue::Boolean(fulfilled),
+        argument,
+    ]);
+    let task = Task::new(native("", reaction_job), args);
+    runtime.event_loop().queue_microtask(task);
+}
+
+// コールバックを呼び出し、その結果でthenが返したPromiseを解決する
+// コールバックが関数でない場合は、確定した値をそのまま引き継ぐ
+fn reaction_job(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let promise = arg(&args, 0);
+    let handler = arg(&args, 1);
+    let argument = arg(&args, 3);
+    let result = if handler.is_function() {
+        runtime.call(&handler, RuntimeValue::Undefined, Vec::from([argument]))
+    } else if arg(&args, 2).is_truthy() {
+        Ok(argument)
+    } else {
+        Err(JsException::new(argument, None))
+    };
+
+    match (result, &promise) {
+        (result, RuntimeValue::Undefined) => {
+            result?;
+        }
+        (Ok(value), _) => resolve_promise(runtime, &promise, value),
+        (Err(exception), _) => reject_promise(runtime, &promise, exception.value()),
+    }
+    Ok(RuntimeValue::Undefined)
+}
+
+// thenメソッドを持つ値で解決された場合、その値のthenにPromiseを解決する関数を渡す
+fn resolve_thenable_job(
+    runtime: &mut JsRuntime,
+    _: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let (resolve, reject) = resolving_functions(runtime, &arg(&args, 0));
+    let thenable = arg(&args, 1);
+    let then = arg(&args, 2);
+    if let Err(exception) = runtime.call(&then, thenable, Vec::from([resolve, reject.clone()])) {
+        runtime.call(
+            &reject,
+            RuntimeValue::Undefined,
+            Vec::from([exception.value()]),
+        )?;
+    }
+    Ok(RuntimeValue::Undefined)
+}
+
+// 一度だけPromiseを解決できるresolveとrejectの組を作る
+fn resolving_functions(
+    runtime: &JsRuntime,
+    promise: &RuntimeValue,
+) -> (RuntimeValue, RuntimeValue) {
+    let already_resolved = new_array(runtime, Vec::from([RuntimeValue::Boolean(false)]));
+    let captures = Vec::from([promise.clone(), already_resolved]);
+    let resolve = native_closure(
+        runtime,
+        "resolve",
+        |runtime, captures, _, args| {
+            if take_first_call(&captures[1]) {
+                resolve_promise(runtime, &captures[0], arg(&args, 0));
+            }
+            Ok(RuntimeValue::Undefined)
+        },
+        captures.clone(),
+    );
+    let reject = native_closure(
+        runtime,
+        "reject",
+        |runtime, captures, _, args| {
+            if take_first_call(&captures[1]) {
+                reject_promise(runtime, &captures[0], arg(&args, 0));
+            }
+            Ok(RuntimeValue::Undefined)
+        },
+        captures,
+    );
+    (resolve, reject)
+}
+
+// [false]の配列を印として使い、最初の呼び出しの場合だけtrueを返す
+fn take_first_call(flag: &RuntimeValue) -> bool {
+    match flag {
+        RuntimeValue::Array(flag) => {
+            let mut flag = flag.borrow_mut();
+            let first = !flag[0].is_truthy();
+            flag[0] = RuntimeValue::Boolean(true);
+            first
+        }
+        _ => false,
+    }
+}
+
+// 確定したPromiseか値をPromiseに変換する。Promiseの場合はそのまま返す
+pub fn promise_resolve(runtime: &mut JsRuntime, value: RuntimeValue) -> RuntimeValue {
+    if is_promise(&value) {
+        return value;
+    }
+    let promise = new_promise(runtime);
+    resolve_promise(runtime, &promise, value);
+    promise
+}
+
+// コールバックを登録する。確定済みの場合はすぐにマイクロタスクを追加する
+fn perform_then(
+    runtime: &mut JsRuntime,
+    promise: &RuntimeValue,
+    on_fulfilled: RuntimeValue,
+    on_rejected: RuntimeValue,
+    derived: RuntimeValue,
+) {
+    let reaction = Reaction {
+        promise: derived,
+        on_fulfilled,
+        on_rejected,
+    };
+    let settled = with_state(promise, |state| {
+        state.handled = true;
+        match &mut state.status {
+            Status::Pending(reactions) => {
+                reactions.push(reaction.clone());
+                None
+            }
+            Status::Fulfilled(value) => Some((true, value.clone())),
+            Status::Rejected(reason) => Some((false, reason.clone())),
+        }
+    })
+    .flatten();
+    if let Some((fulfilled, value)) = settled {
+        queue_reaction_job(runtime, reaction, fulfilled, value);
+    }
+}
+
+// awaitした値が解決されたら、on_fulfilledかon_rejectedを呼び出す
+pub fn await_value(
+    runtime: &mut JsRuntime,
+    value: RuntimeValue,
+    on_fulfilled: RuntimeValue,
+    on_rejected: RuntimeValue,
+) {
+    let promise = promise_resolve(runtime, value);
+    perform_then(
+        runtime,
+        &promise,
+        on_fulfilled,
+        on_rejected,
+        RuntimeValue::Undefined,
+    );
+}
+
+// コールバックを登録し、その結果で解決する新しいPromiseを返す
+fn then_promise(
+    runtime: &mut JsRuntime,
+    promise: &RuntimeValue,
+    on_fulfilled: RuntimeValue,
+    on_rejected: RuntimeValue,
+) -> RuntimeValue {
+    let derived = new_promise(runtime);
+    perform_then(runtime, promise, on_fulfilled, on_rejected, derived.clone());
+    derived
+}
+
+// マイクロタスクの実行後、拒否されたままコールバックが登録されなかったPromiseを報告する
+pub fn report_unhandled_rejections(runtime: &mut JsRuntime) {
+    for promise in runtime.event_loop().take_rejected_promises() {
+        let reason = with_state(&promise, |state| match &state.status {
+            Status::Rejected(reason) if !state.handled => Some(reason.clone()),
+            _ => None,
+        })
+        .flatten();
+        if let Some(reason) = reason {
+            runtime.report_exception(JsException::new(reason, None));
+        }
+    }
+}
+
+// Promiseコンストラクタとメソッドを定義する
+pub fn define_globals(runtime: &mut JsRuntime) {
+    let prototype = runtime.promise_prototype();
+    define_methods(&prototype, &PROMISE_METHODS);
+    runtime.define_global(
+        "Promise",
+        constructor("Promise", promise_constructor, &prototype, &STATIC_METHODS),
+    );
+}
+
+// new Promise(executor)。executorにresolveとrejectを渡して呼び出す
+fn promise_constructor(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let object = match &this {
+        RuntimeValue::Object(object) if RefCell::borrow(object).slot().is_none() => object,
+        _ => {
+            return Err(runtime.throw_error(
+                "TypeError",
+                "Promise constructor cannot be invoked without 'new'".to_string(),
+            ))
+        }
+    };
+    let executor = arg(&args, 0);
+    if !executor.is_function() {
+        return Err(runtime.throw_error(
+            "TypeError",
+            format!("Promise resolver {} is not a function", executor),
+        ));
+    }
+    object
+        .borrow_mut()
+        .set_slot(InternalSlot::Promise(PromiseState::new()));
+
+    let (resolve, reject) = resolving_functions(runtime, &this);
+    let args = Vec::from([resolve, reject.clone()]);
+    if let Err(exception) = runtime.call(&executor, RuntimeValue::Undefined, args) {
+        runtime.call(
+            &reject,
+            RuntimeValue::Undefined,
+            Vec::from([exception.value()]),
+        )?;
+    }
+    Ok(this)
+}
+
+fn this_promise(runtime: &JsRuntime, this: &RuntimeValue) -> Result<(), JsException> {
+    if is_promise(this) {
+        Ok(())
+    } else {
+        Err(runtime.throw_error("TypeError", "receiver is not a Promise".to_string()))
+    }
+}
+
+// this.then(onFulfilled, onRejected)を呼び出す
+fn invoke_then(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    on_fulfilled: RuntimeValue,
+    on_rejected: RuntimeValue,
+) -> Result<RuntimeValue, JsException> {
+    let then = runtime.get(&this, "then")?;
+    runtime.call(&then, this, Vec::from([on_fulfilled, on_rejected]))
+}
+
+static PROMISE_METHODS: [(&str, NativeFunction); 3] = [
+    ("then", |runtime, this, args| {
+        this_promise(runtime, &this)?;
+        Ok(then_promise(runtime, &this, arg(&args, 0), arg(&args, 1)))
+    }),
+    ("catch", |runtime, this, args| {
+        invoke_then(runtime, this, RuntimeValue::Undefined, arg(&args, 0))
+    }),
+    ("finally", promise_finally),
+];
+
+// finally(onFinally)。onFinallyの後に、元の値か理由をそのまま引き継ぐ
+fn promise_finally(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let on_finally = arg(&args, 0);
+    if !on_finally.is_function() {
+        return invoke_then(runtime, this, on_finally.clone(), on_finally);
+    }
+
+    let captures = Vec::from([on_finally]);
+    let then_finally = native_closure(
+        runtime,
+        "",
+        |runtime, captures, _, args| {
+            let value = arg(&args, 0);
+            let result = runtime.call(&captures[0], RuntimeValue::Undefined, Vec::new())?;
+            let promise = promise_resolve(runtime, result);
+            let value_thunk = native_closure(
+                runtime,
+                "",
+                |_, captures, _, _| Ok(captures[0].clone()),
+                Vec::from([value]),
+            );
+            Ok(then_promise(
+                runtime,
+                &promise,
+                value_thunk,
+                RuntimeValue::Undefined,
+            ))
+        },
+        captures.clone(),
+    );
+    let catch_finally = native_closure(
+        runtime,
+        "",
+        |runtime, captures, _, args| {
+            let reason = arg(&args, 0);
+            let result = runtime.call(&captures[0], RuntimeValue::Undefined, Vec::new())?;
+            let promise = promise_resolve(runtime, result);
+            let thrower = native_closure(
+                runtime,
+                "",
+                |_, captures, _, _| Err(JsException::new(captures[0].clone(), None)),
+                Vec::from([reason]),
+            );
+            Ok(then_promise(
+                runtime,
+                &promise,
+                thrower,
+                RuntimeValue::Undefined,
+            ))
+        },
+        captures,
+    );
+    invoke_then(runtime, this, then_finally, catch_finally)
+}
+
+static STATIC_METHODS: [(&str, NativeFunction); 5] = [
+    ("resolve", |runtime, _, args| {
+        Ok(promise_resolve(runtime, arg(&args, 0)))
+    }),
+    ("reject", |runtime, _, args| {
+        let promise = new_promise(runtime);
+        reject_promise(runtime, &promise, arg(&args, 0));
+        Ok(promise)
+    }),
+    ("all", |runtime, _, args| {
+        combine(runtime, &args, Combinator::All)
+    }),
+    ("allSettled", |runtime, _, args| {
+        combine(runtime, &args, Combinator::AllSettled)
+    }),
+    ("race", |runtime, _, args| {
+        combine(runtime, &args, Combinator::Race)
+    }),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// 複数のPromiseをまとめる方法
+enum Combinator {
+    // すべて履行されたら値の配列で履行し、どれかが拒否されたら拒否する
+    All,
+    // すべて確定したら結果のオブジェクトの配列で履行する
+    AllSettled,
+    // 最初に確定したものと同じ結果になる
+    Race,
+}
+
+// Promise.all、Promise.allSettled、Promise.race。反復できる値として配列を受け取る
+fn combine(
+    runtime: &mut JsRuntime,
+    args: &[RuntimeValue],
+    combinator: Combinator,
+) -> Result<RuntimeValue, JsException> {
+    let elements = match arg(args, 0) {
+        RuntimeValue::Array(elements) => RefCell::borrow(&elements).to_vec(),
+        value => {
+            return Err(runtime.throw_error("TypeError", format!("{} is not iterable", value)));
+        }
+    };
+    let result = new_promise(runtime);
+    let (resolve, reject) = resolving_functions(runtime, &result);
+
+    let values = new_array(
+        runtime,
+        Vec::from_iter(elements.iter().map(|_| RuntimeValue::Undefined)),
+    );
+    let remaining = new_array(
+        runtime,
+        Vec::from([RuntimeValue::Number(elements.len() as f64)]),
+    );
+    if elements.is_empty() && combinator != Combinator::Race {
+        runtime.call(&resolve, RuntimeValue::Undefined, Vec::from([values]))?;
+        return Ok(result);
+    }
+
+    for (index, element) in elements.into_iter().enumerate() {
+        let promise = promise_resolve(runtime, element);
+        if combinator == Combinator::Race {
+            perform_then(
+                runtime,
+                &promise,
+                resolve.clone(),
+                reject.clone(),
+                RuntimeValue::Undefined,
+            );
+            continue;
+        }
+
+        // 同じ要素のコールバックは一度だけ結果を記録する
+        let called = new_array(runtime, Vec::from([RuntimeValue::Boolean(false)]));
+        let captures = Vec::from([
+            called,
+            RuntimeValue::Number(index as f64),
+            values.clone(),
+            remaining.clone(),
+            resolve.clone(),
+        ]);
+        let (on_fulfilled, on_rejected) = match combinator {
+            Combinator::All => (
+                native_closure(
+                    runtime,
+                    "",
+                    |runtime, captures, _, args| record_element(runtime, captures, arg(&args, 0)),
+                    captures,
+                ),
+                reject.clone(),
+            ),
+            _ => (
+                native_closure(
+                    runtime,
+                    "",
+                    |runtime, captures, _, args| {
+                        let entry = settled_entry(runtime, "fulfilled", "value", arg(&args, 0));
+                        record_element(runtime, captures, entry)
+                    },
+                    captures.clone(),
+                ),
+                native_closure(
+                    runtime,
+                    "",
+                    |runtime, captures, _, args| {
+                        let entry = settled_entry(runtime, "rejected", "reason", arg(&args, 0));
+                        record_element(runtime, captures, entry)
+                    },
+                    captures,
+                ),
+            ),
+        };
+        perform_then(
+            runtime,
+            &promise,
+            on_fulfilled,
+            on_rejected,
+            RuntimeValue::Undefined,
+        );
+    }
+    Ok(result)
+}
+
+// {status: "fulfilled", value: v}のようなallSettledの結果
+fn settled_entry(
+    runtime: &JsRuntime,
+    status: &str,
+    key: &str,
+    value: RuntimeValue,
+) -> RuntimeValue {
+    let entry = runtime.new_object();
+    entry.set_property("status", RuntimeValue::StringLiteral(status.to_string()));
+    entry.set_property(key, value);
+    entry
+}
+
+// capturesは[called, index, values, remaining, resolve]。すべての要素がそろったら配列で解決する
+fn record_element(
+    runtime: &mut JsRuntime,
+    captures: &[RuntimeValue],
+    value: RuntimeValue,
+) -> Result<RuntimeValue, JsException> {
+    if !take_first_call(&captures[0]) {
+        return Ok(RuntimeValue::Undefined);
+    }
+    let index = captures[1].to_number() as usize;
+    let is_complete = match (&captures[2], &captures[3]) {
+        (RuntimeValue::Array(values), RuntimeValue::Array(remaining)) => {
+            values.borrow_mut()[index] = value;
+            let mut remaining = remaining.borrow_mut();
+            let count = remaining[0].to_number() - 1.0;
+            remaining[0] = RuntimeValue::Number(count);
+            count == 0.0
+        }
+        _ => false,
+    };
+    if is_complete {
+        runtime.call(
+            &captures[4],
+            RuntimeValue::Undefined,
+            Vec::from([captures[2].clone()]),
+        )?;
+    }
+    Ok(RuntimeValue::Undefined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::event_loop::perform_microtask_checkpoint;
+    use crate::renderer::js::token::JsLexer;
+    use alloc::string::String;
+
+    fn setup(script: &str) -> JsRuntime {
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        let program = JsParser::new(JsLexer::new(script.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        runtime.execute(&program).expect("failed to execute");
+        perform_microtask_checkpoint(&mut runtime);
+        runtime
+    }
+
+    fn log(runtime: &JsRuntime) -> String {
+        match runtime.lookup_global("log") {
+            Ok(RuntimeValue::Array(elements)) => elements
+                .borrow()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            _ => panic!("log should be an array"),
+        }
+    }
+
+    #[test]
+    fn test_then_runs_as_microtask() {
+        let runtime = setup(
+            "var log = []; var p = new Promise(function (resolve) { log.push(\"executor\"); resolve(1); }); p.then(function (v) { log.push(\"then\" + v); return v + 1; }).then(function (v) { log.push(\"then\" + v); }); queueMicrotask(function () { log.push(\"micro\"); }); log.push(\"script\");",
+        );
+        assert_eq!("executor,script,then1,micro,then2", log(&runtime));
+    }
+
+    #[test]
+    fn test_rejection_and_finally() {
+        let runtime = setup(
+            "var log = []; Promise.reject(new Error(\"x\")).then(function () { log.push(\"skipped\"); }).catch(function (e) { log.push(\"caught \" + e.message); return \"ok\"; }).finally(function () { log.push(\"finally\"); return \"ignored\"; }).then(function (v) { log.push(v); }); new Promise(function () { throw \"thrown\"; }).catch(function (e) { log.push(e); });",
+        );
+        assert_eq!("thrown,caught x,finally,ok", log(&runtime));
+    }
+
+    #[test]
+    fn test_thenable_adoption() {
+        let runtime = setup(
+            "var log = []; var thenable = { then: function (resolve) { log.push(\"then called\"); resolve(\"adopted\"); } }; Promise.resolve(thenable).then(function (v) { log.push(v); }); var p = Promise.resolve(1); log.push(Promise.resolve(p) === p); var q = Promise.resolve().then(function () { return q; }); q.catch(function (e) { log.push(e.name); });",
+        );
+        assert_eq!("true,then called,adopted,TypeError", log(&runtime));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let runtime = setup(
+            "var log = []; var late = new Promise(function (resolve) { queueMicrotask(function () { resolve(\"late\"); }); }); Promise.all([late, 1, Promise.resolve(2)]).then(function (v) { log.push(\"all \" + v); }); Promise.all([1, Promise.reject(\"no\")]).catch(function (e) { log.push(\"all rejected \" + e); }); Promise.all([]).then(function (v) { log.push(\"empty \" + v.length); }); Promise.race([late, Promise.resolve(\"fast\")]).then(function (v) { log.push(\"race \" + v); }); Promise.allSettled([Promise.reject(\"r\"), 3]).then(function (v) { log.push(v[0].status + \":\" + v[0].reason + \" \" + v[1].status + \":\" + v[1].value); });",
+        );
+        assert_eq!(
+            "empty 0,all rejected no,race fast,rejected:r fulfilled:3,all late,1,2",
+            log(&runtime)
+        );
+    }
+
+    #[test]
+    fn test_unhandled_rejection_is_reported() {
+        let mut runtime = setup(
+            "var log = []; Promise.reject(new Error(\"lost\")); Promise.reject(\"handled\").catch(function () { log.push(\"handled\"); });",
+        );
+        assert_eq!("handled", log(&runtime));
+        let errors = runtime.take_reported_exceptions();
+        assert_eq!(1, errors.len());
+        assert_eq!("Error: lost", errors[0].message());
+    }
+
+    #[test]
+    fn test_constructor_errors() {
+        let mut runtime = setup("var log = [];");
+        let program = JsParser::new(JsLexer::new("Promise(function () {});".to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        let error = runtime.execute(&program).expect_err("should throw");
+        assert_eq!(
+            "TypeError: Promise constructor cannot be invoked without 'new'",
+            error.message()
+        );
+    }
+}
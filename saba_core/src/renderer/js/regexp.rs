@@ -0,0 +1,1665 @@
+// 正規表現のエンジンと、RegExpオブジェクトと正規表現を使う文字列のメソッド
+// パターンを構文木に変換し、バックトラックで入力と照合する
+
+use crate::renderer::js::builtins::{
+    arg, constructor, define_methods, find_chars, new_array, this_chars,
+};
+use crate::renderer::js::runtime::{
+    InternalSlot, JsException, JsObject, JsRuntime, NativeFunction, RuntimeValue,
+};
+use alloc::{
+    boxed::Box,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::cell::{Cell, RefCell};
+use core::fmt::{Display, Formatter};
+
+// キャプチャした範囲。0番目は一致した全体で、一致しなかったグループはNone
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+// 1つの開始位置で照合を試すノードの数の上限。/^(a+)+$/のように指数的にバックトラックするパターンで止まらなくなるのを防ぐ
+// 上限を超えた場合は、一致しなかったとせずにエラーにする
+pub const MAX_BACKTRACK_STEPS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags {
+    // g: 一致するものをすべて探す
+    pub global: bool,
+    // i: 大文字と小文字を区別しない
+    pub ignore_case: bool,
+    // m: ^と$が行の先頭と末尾にも一致する
+    pub multiline: bool,
+    // s: .が改行にも一致する
+    pub dot_all: bool,
+    // u: このエンジンでは文字単位で照合するため、常にUnicodeのコードポイントとして扱う
+    pub unicode: bool,
+    // y: lastIndexの位置でだけ一致する
+    pub sticky: bool,
+}
+
+impl Flags {
+    pub fn parse(flags: &str) -> Result<Self, String> {
+        let mut result = Flags::default();
+        for c in flags.chars() {
+            let flag = match c {
+                'g' => &mut result.global,
+                'i' => &mut result.ignore_case,
+                'm' => &mut result.multiline,
+                's' => &mut result.dot_all,
+                'u' => &mut result.unicode,
+                'y' => &mut result.sticky,
+                _ => return Err(format!("invalid flags '{}'", flags)),
+            };
+            // 同じフラグは2回指定できない
+            if *flag {
+                return Err(format!("invalid flags '{}'", flags));
+            }
+            *flag = true;
+        }
+        Ok(result)
+    }
+}
+
+// flagsプロパティの値。フラグはアルファベット順に並べる
+impl Display for Flags {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        for (enabled, c) in [
+            (self.global, 'g'),
+            (self.ignore_case, 'i'),
+            (self.multiline, 'm'),
+            (self.dot_all, 's'),
+            (self.unicode, 'u'),
+            (self.sticky, 'y'),
+        ] {
+            if enabled {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// 文字クラスの要素。boolは\Dのように否定された場合にtrue
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RegexNode {
+    Char(char),
+    // .
+    Any,
+    // [a-z]や\d。negatedは[^a-z]
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    // ^と$
+    LineStart,
+    LineEnd,
+    // \bはtrue、\Bはfalse
+    WordBoundary(bool),
+    // (...)。indexはキャプチャグループの番号で、(?:...)の場合はNone
+    Group {
+        node: Box<RegexNode>,
+        index: Option<usize>,
+    },
+    // \1や\k<name>
+    BackReference(usize),
+    // (?=...)、(?!...)、(?<=...)、(?<!...)
+    LookAround {
+        node: Box<RegexNode>,
+        ahead: bool,
+        negated: bool,
+    },
+    // *、+、?、{n,m}。greedyがfalseの場合は短い一致から試す
+    Repeat {
+        node: Box<RegexNode>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+    Sequence(Vec<RegexNode>),
+    // a|b
+    Alternation(Vec<RegexNode>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// コンパイルした正規表現
+pub struct Regex {
+    pub source: String,
+    pub flags: Flags,
+    node: RegexNode,
+    // キャプチャグループの数。0番目の全体の一致は含まない
+    group_count: usize,
+    // 一致する文字列の先頭に必ず現れる文字列。検索では、これが現れる位置だけを試す
+    prefix: Vec<char>,
+    // 名前付きグループの名前と番号
+    group_names: Vec<(String, usize)>,
+}
+
+impl Regex {
+    // パターンとフラグをコンパイルする。構文エラーの場合はその理由を返す
+    pub fn new(pattern: &str, flags: &str) -> Result<Self, String> {
+        let flags = Flags::parse(flags)?;
+        let chars: Vec<char> = pattern.chars().collect();
+        let group_names = scan_group_names(&chars)?;
+        let mut parser = PatternParser {
+            chars,
+            pos: 0,
+            group_count: 0,
+            group_names,
+        };
+        let node = parser.disjunction()?;
+        if parser.pos < parser.chars.len() {
+            return Err("unmatched ')'".to_string());
+        }
+        // iフラグの場合は大文字と小文字の違いがあるため、先頭の文字列で絞り込まない
+        let prefix = if flags.ignore_case {
+            Vec::new()
+        } else {
+            literal_prefix(&node)
+        };
+        Ok(Self {
+            source: pattern.to_string(),
+            flags,
+            node,
+            group_count: parser.group_count,
+            prefix,
+            group_names: parser
+                .group_names
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, name)| name.map(|name| (name, i + 1)))
+                .collect(),
+        })
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.group_count
+    }
+
+    pub fn group_names(&self) -> &[(String, usize)] {
+        &self.group_names
+    }
+
+    // input[pos..]の先頭で一致するか試す。バックトラックが上限を超えた場合はエラー
+    pub fn match_at(&self, input: &[char], pos: usize) -> Result<Option<Captures>, String> {
+        if pos > input.len() {
+            return Ok(None);
+        }
+        let steps = Cell::new(0);
+        let matcher = Matcher {
+            input,
+            flags: self.flags,
+            steps: &steps,
+        };
+        let mut captures = vec![None; self.group_count + 1];
+        let matched = matcher.match_node(&self.node, pos, &mut captures, &mut |end, captures| {
+            captures[0] = Some((pos, end));
+            true
+        });
+        if matched {
+            Ok(Some(captures))
+        } else if steps.get() > MAX_BACKTRACK_STEPS {
+            Err("regular expression is too complex".to_string())
+        } else {
+            Ok(None)
+        }
+    }
+
+    // start以降で最初に一致する位置を探す。yフラグの場合はstartの位置だけを試す
+    pub fn exec_at(&self, input: &[char], start: usize) -> Result<Option<Captures>, String> {
+        if self.flags.sticky {
+            return self.match_at(input, start);
+        }
+        let mut pos = start;
+        while pos <= input.len() {
+            if !self.prefix.is_empty() {
+                pos = match find_chars(input, &self.prefix, pos) {
+                    Some(pos) => pos,
+                    None => return Ok(None),
+                };
+            }
+            if let Some(captures) = self.match_at(input, pos)? {
+                return Ok(Some(captures));
+            }
+            pos += 1;
+        }
+        Ok(None)
+    }
+}
+
+// パターンの先頭に並んだ文字。/abc\d/の場合は"abc"
+fn literal_prefix(node: &RegexNode) -> Vec<char> {
+    match node {
+        RegexNode::Char(c) => vec![*c],
+        RegexNode::Sequence(nodes) => nodes
+            .iter()
+            .map_while(|node| match node {
+                RegexNode::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// キャプチャグループの名前を、番号の順に集める。名前のないグループはNone
+// \k<name>はグループより前に書けるため、パースの前に調べておく
+fn scan_group_names(chars: &[char]) -> Result<Vec<Option<String>>, String> {
+    let mut names: Vec<Option<String>> = Vec::new();
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '(' if !in_class => {
+                if chars.get(i + 1) != Some(&'?') {
+                    names.push(None);
+                } else if chars.get(i + 2) == Some(&'<')
+                    && !matches!(chars.get(i + 3), Some('=') | Some('!'))
+                {
+                    let name: String = chars[i + 3..].iter().take_while(|c| **c != '>').collect();
+                    if names.contains(&Some(name.clone())) {
+                        return Err("duplicate capture group name".to_string());
+                    }
+                    names.push(Some(name));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Ok(names)
+}
+
+struct PatternParser {
+    chars: Vec<char>,
+    pos: usize,
+    // これまでに読んだキャプチャグループの数
+    group_count: usize,
+    group_names: Vec<Option<String>>,
+}
+
+// 文字クラスの中の要素。a-zのように範囲の端になれるのは1文字の場合だけ
+enum ClassAtom {
+    Char(char),
+    Set(ClassItem),
+}
+
+impl PatternParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let matched = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matched {
+            self.pos += s.chars().count();
+        }
+        matched
+    }
+
+    fn disjunction(&mut self) -> Result<RegexNode, String> {
+        let mut alternatives = Vec::from([self.alternative()?]);
+        while self.eat('|') {
+            alternatives.push(self.alternative()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.remove(0));
+        }
+        Ok(RegexNode::Alternation(alternatives))
+    }
+
+    fn alternative(&mut self) -> Result<RegexNode, String> {
+        let mut terms = Vec::new();
+        while !matches!(self.peek(), None | Some('|') | Some(')')) {
+            terms.push(self.term()?);
+        }
+        Ok(RegexNode::Sequence(terms))
+    }
+
+    fn term(&mut self) -> Result<RegexNode, String> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err("unexpected end of pattern".to_string()),
+        };
+        let atom = match c {
+            '^' => return Ok(RegexNode::LineStart),
+            '$' => return Ok(RegexNode::LineEnd),
+            '(' => self.group()?,
+            '.' => RegexNode::Any,
+            '[' => self.class()?,
+            '\\' => match self.peek() {
+                Some('b') | Some('B') => {
+                    let c = self.next();
+                    return Ok(RegexNode::WordBoundary(c == Some('b')));
+                }
+                _ => self.atom_escape()?,
+            },
+            '*' | '+' | '?' => return Err("nothing to repeat".to_string()),
+            // 数量詞として読めない{は文字として扱う
+            '{' => {
+                self.pos -= 1;
+                if self.brace_quantifier().is_some() {
+                    return Err("nothing to repeat".to_string());
+                }
+                self.pos += 1;
+                RegexNode::Char('{')
+            }
+            c => RegexNode::Char(c),
+        };
+        self.quantifier(atom)
+    }
+
+    fn quantifier(&mut self, atom: RegexNode) -> Result<RegexNode, String> {
+        let (min, max) = match self.peek() {
+            Some('{') => match self.brace_quantifier() {
+                Some(range) => range,
+                None => return Ok(atom),
+            },
+            Some(c @ ('*' | '+' | '?')) => {
+                self.pos += 1;
+                match c {
+                    '*' => (0, None),
+                    '+' => (1, None),
+                    _ => (0, Some(1)),
+                }
+            }
+            _ => return Ok(atom),
+        };
+        if matches!(max, Some(max) if max < min) {
+            return Err("numbers out of order in {} quantifier".to_string());
+        }
+        let greedy = !self.eat('?');
+        Ok(RegexNode::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    // {n}、{n,}、{n,m}を読む。数量詞でない場合は位置を戻してNoneを返す
+    fn brace_quantifier(&mut self) -> Option<(usize, Option<usize>)> {
+        let start = self.pos;
+        let result = self.brace_quantifier_inner();
+        if result.is_none() {
+            self.pos = start;
+        }
+        result
+    }
+
+    fn brace_quantifier_inner(&mut self) -> Option<(usize, Option<usize>)> {
+        if !self.eat('{') {
+            return None;
+        }
+        let min = self.decimal()?;
+        let max = if self.eat(',') {
+            if self.peek() == Some('}') {
+                None
+            } else {
+                Some(self.decimal()?)
+            }
+        } else {
+            Some(min)
+        };
+        if !self.eat('}') {
+            return None;
+        }
+        Some((min, max))
+    }
+
+    fn decimal(&mut self) -> Option<usize> {
+        let start = self.pos;
+        let mut value: usize = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            value = value.saturating_mul(10).saturating_add(digit as usize);
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return None;
+        }
+        Some(value)
+    }
+
+    // (の後から読む
+    fn group(&mut self) -> Result<RegexNode, String> {
+        let node = if self.eat_str("?:") {
+            RegexNode::Group {
+                node: Box::new(self.disjunction()?),
+                index: None,
+            }
+        } else if self.peek() == Some('?') {
+            let (ahead, negated) = if self.eat_str("?=") {
+                (true, false)
+            } else if self.eat_str("?!") {
+                (true, true)
+            } else if self.eat_str("?<=") {
+                (false, false)
+            } else if self.eat_str("?<!") {
+                (false, true)
+            } else if self.eat_str("?<") {
+                // 名前はパースの前に集めてあるので、>まで読み飛ばす
+                let name: String = self.chars[self.pos..]
+                    .iter()
+                    .take_while(|c| **c != '>')
+                    .collect();
+                if name.is_empty() || self.pos + name.chars().count() >= self.chars.len() {
+                    return Err("invalid capture group name".to_string());
+                }
+                self.pos += name.chars().count() + 1;
+                return self.capturing_group();
+            } else {
+                return Err("invalid group".to_string());
+            };
+            RegexNode::LookAround {
+                node: Box::new(self.disjunction()?),
+                ahead,
+                negated,
+            }
+        } else {
+            return self.capturing_group();
+        };
+        if !self.eat(')') {
+            return Err("unterminated group".to_string());
+        }
+        Ok(node)
+    }
+
+    fn capturing_group(&mut self) -> Result<RegexNode, String> {
+        // 番号は(が現れた順に付ける
+        self.group_count += 1;
+        let index = self.group_count;
+        let node = self.disjunction()?;
+        if !self.eat(')') {
+            return Err("unterminated group".to_string());
+        }
+        Ok(RegexNode::Group {
+            node: Box::new(node),
+            index: Some(index),
+        })
+    }
+
+    // [の後から読む
+    fn class(&mut self) -> Result<RegexNode, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        loop {
+            let c = match self.next() {
+                Some(c) => c,
+                None => return Err("unterminated character class".to_string()),
+            };
+            if c == ']' {
+                break;
+            }
+            let start = self.class_atom(c)?;
+
+            let is_range = self.peek() == Some('-')
+                && !matches!(self.chars.get(self.pos + 1), None | Some(']'));
+            match (start, is_range) {
+                (ClassAtom::Char(from), true) => {
+                    self.pos += 1;
+                    let c = self.next().unwrap_or(']');
+                    match self.class_atom(c)? {
+                        ClassAtom::Char(to) => {
+                            if to < from {
+                                return Err("range out of order in character class".to_string());
+                            }
+                            items.push(ClassItem::Range(from, to));
+                        }
+                        // [a-\d]は範囲ではなく、a、-、\dのそれぞれとして扱う
+                        ClassAtom::Set(item) => {
+                            items.push(ClassItem::Range(from, from));
+                            items.push(ClassItem::Range('-', '-'));
+                            items.push(item);
+                        }
+                    }
+                }
+                (ClassAtom::Char(c), false) => items.push(ClassItem::Range(c, c)),
+                (ClassAtom::Set(item), _) => items.push(item),
+            }
+        }
+        Ok(RegexNode::Class { items, negated })
+    }
+
+    fn class_atom(&mut self, c: char) -> Result<ClassAtom, String> {
+        if c != '\\' {
+            return Ok(ClassAtom::Char(c));
+        }
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err("\\ at end of pattern".to_string()),
+        };
+        Ok(match class_escape(c) {
+            Some(item) => ClassAtom::Set(item),
+            // 文字クラスの中の\bはバックスペース
+            None if c == 'b' => ClassAtom::Char('\u{8}'),
+            None => ClassAtom::Char(self.character_escape(c)),
+        })
+    }
+
+    // \の後から読む
+    fn atom_escape(&mut self) -> Result<RegexNode, String> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err("\\ at end of pattern".to_string()),
+        };
+        if let Some(item) = class_escape(c) {
+            return Ok(RegexNode::Class {
+                items: Vec::from([item]),
+                negated: false,
+            });
+        }
+        match c {
+            '1'..='9' => {
+                self.pos -= 1;
+                let start = self.pos;
+                let index = self.decimal().unwrap_or(0);
+                if index <= self.group_names.len() {
+                    return Ok(RegexNode::BackReference(index));
+                }
+                // 存在しないグループの番号は8進数のエスケープとして扱う
+                self.pos = start + 1;
+                let mut code = c.to_digit(10).unwrap_or(0);
+                if code < 8 {
+                    while let Some(digit) = self.peek().and_then(|c| c.to_digit(8)) {
+                        if code * 8 + digit > 0o377 {
+                            break;
+                        }
+                        code = code * 8 + digit;
+                        self.pos += 1;
+                    }
+                }
+                Ok(RegexNode::Char(char::from_u32(code).unwrap_or(c)))
+            }
+            // 名前付きグループがないパターンでは\kは文字のk
+            'k' if self.group_names.iter().any(|name| name.is_some()) => {
+                if !self.eat('<') {
+                    return Err("invalid named reference".to_string());
+                }
+                let name: String = self.chars[self.pos..]
+                    .iter()
+                    .take_while(|c| **c != '>')
+                    .collect();
+                self.pos += name.chars().count() + 1;
+                match self
+                    .group_names
+                    .iter()
+                    .position(|n| n.as_deref() == Some(name.as_str()))
+                {
+                    Some(i) => Ok(RegexNode::BackReference(i + 1)),
+                    None => Err("invalid named capture referenced".to_string()),
+                }
+            }
+            c => Ok(RegexNode::Char(self.character_escape(c))),
+        }
+    }
+
+    // \n、\x41、Aのような文字のエスケープ。それ以外の文字はその文字自身を表す
+    fn character_escape(&mut self, c: char) -> char {
+        match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' if !matches!(self.peek(), Some(c) if c.is_ascii_digit()) => '\0',
+            // \cJは制御文字
+            'c' => match self.peek() {
+                Some(letter) if letter.is_ascii_alphabetic() => {
+                    self.pos += 1;
+                    char::from_u32(letter as u32 % 32).unwrap_or(c)
+                }
+                _ => '\\',
+            },
+            'x' => self.hex_escape(2).unwrap_or(c),
+            'u' => {
+                if self.eat('{') {
+                    let start = self.pos;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+                        self.pos += 1;
+                    }
+                    let digits: String = self.chars[start..self.pos].iter().collect();
+                    match u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                    {
+                        Some(c) if self.eat('}') => c,
+                        _ => {
+                            self.pos = start - 1;
+                            'u'
+                        }
+                    }
+                } else {
+                    self.hex_escape(4).unwrap_or(c)
+                }
+            }
+            c => c,
+        }
+    }
+
+    fn hex_escape(&mut self, n: usize) -> Option<char> {
+        let mut code = 0;
+        for i in 0..n {
+            code = code * 16 + self.chars.get(self.pos + i)?.to_digit(16)?;
+        }
+        self.pos += n;
+        char::from_u32(code)
+    }
+}
+
+// \d、\w、\sとその否定
+fn class_escape(c: char) -> Option<ClassItem> {
+    Some(match c {
+        'd' => ClassItem::Digit(false),
+        'D' => ClassItem::Digit(true),
+        'w' => ClassItem::Word(false),
+        'W' => ClassItem::Word(true),
+        's' => ClassItem::Space(false),
+        'S' => ClassItem::Space(true),
+        _ => return None,
+    })
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// 大文字と小文字を区別しない比較のため、小文字にそろえる
+fn fold_case(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+// 照合の続きを表す関数。一致の終わりの位置とキャプチャを受け取り、全体が一致したかを返す
+type Continuation<'a> = dyn FnMut(usize, &mut Captures) -> bool + 'a;
+
+struct Matcher<'a> {
+    input: &'a [char],
+    flags: Flags,
+    steps: &'a Cell<usize>,
+}
+
+impl Matcher<'_> {
+    // posからnodeに一致させ、続きのkが成功するまで別の一致の仕方を試す
+    fn match_node(
+        &self,
+        node: &RegexNode,
+        pos: usize,
+        captures: &mut Captures,
+        k: &mut Continuation,
+    ) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        if self.steps.get() > MAX_BACKTRACK_STEPS {
+            return false;
+        }
+        match node {
+            RegexNode::Char(_) | RegexNode::Any | RegexNode::Class { .. } => {
+                self.match_char(node, pos) && k(pos + 1, captures)
+            }
+            RegexNode::LineStart => {
+                let at_start =
+                    pos == 0 || (self.flags.multiline && is_line_terminator(self.input[pos - 1]));
+                at_start && k(pos, captures)
+            }
+            RegexNode::LineEnd => {
+                let at_end = pos == self.input.len()
+                    || (self.flags.multiline && is_line_terminator(self.input[pos]));
+                at_end && k(pos, captures)
+            }
+            RegexNode::WordBoundary(expected) => {
+                let before = pos > 0 && is_word_char(self.input[pos - 1]);
+                let after = pos < self.input.len() && is_word_char(self.input[pos]);
+                (before != after) == *expected && k(pos, captures)
+            }
+            RegexNode::Group { node, index: None } => self.match_node(node, pos, captures, k),
+            RegexNode::Group {
+                node,
+                index: Some(index),
+            } => self.match_node(node, pos, captures, &mut |end, captures| {
+                // 続きが失敗した場合は、前のキャプチャに戻す
+                let previous = captures[*index];
+                captures[*index] = Some((pos, end));
+                if k(end, captures) {
+                    return true;
+                }
+                captures[*index] = previous;
+                false
+            }),
+            RegexNode::BackReference(index) => {
+                let (start, end) = match captures.get(*index).copied().flatten() {
+                    Some(range) => range,
+                    // まだキャプチャしていないグループは空文字列に一致する
+                    None => return k(pos, captures),
+                };
+                let len = end - start;
+                if pos + len > self.input.len() {
+                    return false;
+                }
+                let matched =
+                    (0..len).all(|i| self.char_equals(self.input[start + i], self.input[pos + i]));
+                matched && k(pos + len, captures)
+            }
+            RegexNode::LookAround {
+                node,
+                ahead,
+                negated,
+            } => {
+                let mut inner = captures.clone();
+                let matched = if *ahead {
+                    self.match_node(node, pos, &mut inner, &mut |_, _| true)
+                } else {
+                    // 後読みは、posで終わる一致を近い開始位置から探す
+                    (0..=pos).rev().any(|start| {
+                        self.match_node(node, start, &mut inner, &mut |end, _| end == pos)
+                    })
+                };
+                if matched == *negated {
+                    return false;
+                }
+                if *negated {
+                    return k(pos, captures);
+                }
+                // 先読みの中のキャプチャは、続きが失敗した場合に元に戻す
+                let saved = core::mem::replace(captures, inner);
+                if k(pos, captures) {
+                    return true;
+                }
+                *captures = saved;
+                false
+            }
+            RegexNode::Repeat { .. } => self.match_repeat(node, 0, pos, captures, k),
+            RegexNode::Sequence(nodes) => self.match_sequence(nodes, pos, captures, k),
+            RegexNode::Alternation(alternatives) => {
+                for alternative in alternatives {
+                    if self.match_node(alternative, pos, captures, k) {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    fn match_sequence(
+        &self,
+        nodes: &[RegexNode],
+        pos: usize,
+        captures: &mut Captures,
+        k: &mut Continuation,
+    ) -> bool {
+        match nodes.split_first() {
+            None => k(pos, captures),
+            Some((first, rest)) => self.match_node(first, pos, captures, &mut |end, captures| {
+                self.match_sequence(rest, end, captures, k)
+            }),
+        }
+    }
+
+    // repeatのnodeにcount回一致した後の続きを照合する
+    fn match_repeat(
+        &self,
+        repeat: &RegexNode,
+        count: usize,
+        pos: usize,
+        captures: &mut Captures,
+        k: &mut Continuation,
+    ) -> bool {
+        let (node, min, max, greedy) = match repeat {
+            RegexNode::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => (node.as_ref(), *min, *max, *greedy),
+            _ => return false,
+        };
+        if max == Some(count) {
+            return k(pos, captures);
+        }
+
+        // 1文字に一致するノードの繰り返しは、再帰せずに一致する長さを数える
+        if count == 0
+            && matches!(
+                node,
+                RegexNode::Char(_) | RegexNode::Any | RegexNode::Class { .. }
+            )
+        {
+            let limit = max.unwrap_or(usize::MAX);
+            let mut n = 0;
+            while n < limit && self.match_char(node, pos + n) {
+                n += 1;
+            }
+            if n < min {
+                return false;
+            }
+            if greedy {
+                return (min..=n).rev().any(|n| k(pos + n, captures));
+            }
+            return (min..=n).any(|n| k(pos + n, captures));
+        }
+
+        if count < min {
+            return self.match_node(node, pos, captures, &mut |end, captures| {
+                self.match_repeat(repeat, count + 1, end, captures, k)
+            });
+        }
+        // 空文字列に一致した場合は、無限に繰り返さないように次を試さない
+        if greedy {
+            let matched = self.match_node(node, pos, captures, &mut |end, captures| {
+                end != pos && self.match_repeat(repeat, count + 1, end, captures, k)
+            });
+            matched || k(pos, captures)
+        } else {
+            k(pos, captures)
+                || self.match_node(node, pos, captures, &mut |end, captures| {
+                    end != pos && self.match_repeat(repeat, count + 1, end, captures, k)
+                })
+        }
+    }
+
+    // posの1文字がnodeに一致するか
+    fn match_char(&self, node: &RegexNode, pos: usize) -> bool {
+        let c = match self.input.get(pos) {
+            Some(c) => *c,
+            None => return false,
+        };
+        match node {
+            RegexNode::Char(expected) => self.char_equals(*expected, c),
+            RegexNode::Any => self.flags.dot_all || !is_line_terminator(c),
+            RegexNode::Class { items, negated } => {
+                let candidates = if self.flags.ignore_case {
+                    [c, fold_case(c), c.to_uppercase().next().unwrap_or(c)]
+                } else {
+                    [c, c, c]
+                };
+                let matched = candidates
+                    .iter()
+                    .any(|c| items.iter().any(|item| class_item_matches(item, *c)));
+                matched != *negated
+            }
+            _ => false,
+        }
+    }
+
+    fn char_equals(&self, a: char, b: char) -> bool {
+        a == b || (self.flags.ignore_case && fold_case(a) == fold_case(b))
+    }
+}
+
+fn class_item_matches(item: &ClassItem, c: char) -> bool {
+    match item {
+        ClassItem::Range(from, to) => *from <= c && c <= *to,
+        ClassItem::Digit(negated) => c.is_ascii_digit() != *negated,
+        ClassItem::Word(negated) => is_word_char(c) != *negated,
+        ClassItem::Space(negated) => (c.is_whitespace() || c == '\u{feff}') != *negated,
+    }
+}
+
+pub fn define_globals(runtime: &mut JsRuntime) {
+    let prototype = runtime.regexp_prototype();
+    define_methods(&prototype, &REGEXP_METHODS);
+    runtime.define_global(
+        "RegExp",
+        constructor("RegExp", regexp_constructor, &prototype, &[]),
+    );
+    define_methods(&runtime.string_prototype(), &STRING_METHODS);
+}
+
+impl JsRuntime {
+    // パターンとフラグからRegExpオブジェクトを作る。構文エラーの場合はSyntaxError
+    pub fn new_regexp(&mut self, pattern: &str, flags: &str) -> Result<RuntimeValue, JsException> {
+        let regex = match Regex::new(pattern, flags) {
+            Ok(regex) => regex,
+            Err(e) => {
+                return Err(self.throw_error(
+                    "SyntaxError",
+                    format!("Invalid regular expression: /{}/{}: {}", pattern, flags, e),
+                ))
+            }
+        };
+
+        let object = self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+            JsObject::with_prototype(Some(self.regexp_prototype())),
+        ))));
+        let source = if regex.source.is_empty() {
+            "(?:)".to_string()
+        } else {
+            regex.source.clone()
+        };
+        object.set_property("source", RuntimeValue::StringLiteral(source));
+        object.set_property(
+            "flags",
+            RuntimeValue::StringLiteral(regex.flags.to_string()),
+        );
+        for (name, value) in [
+            ("global", regex.flags.global),
+            ("ignoreCase", regex.flags.ignore_case),
+            ("multiline", regex.flags.multiline),
+            ("dotAll", regex.flags.dot_all),
+            ("unicode", regex.flags.unicode),
+            ("sticky", regex.flags.sticky),
+        ] {
+            object.set_property(name, RuntimeValue::Boolean(value));
+        }
+        object.set_property("lastIndex", RuntimeValue::Number(0.0));
+        if let RuntimeValue::Object(o) = &object {
+            o.borrow_mut()
+                .set_slot(InternalSlot::RegExp(Rc::new(regex)));
+        }
+        Ok(object)
+    }
+}
+
+// valueがRegExpオブジェクトの場合、そのパターン
+fn regex_of(value: &RuntimeValue) -> Option<Rc<Regex>> {
+    match value {
+        RuntimeValue::Object(object) => match RefCell::borrow(object).slot() {
+            Some(InternalSlot::RegExp(regex)) => Some(regex.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn this_regex(
+    runtime: &mut JsRuntime,
+    this: &RuntimeValue,
+    method: &str,
+) -> Result<Rc<Regex>, JsException> {
+    regex_of(this).ok_or_else(|| {
+        runtime.throw_error(
+            "TypeError",
+            format!(
+                "RegExp.prototype.{} called on incompatible receiver",
+                method
+            ),
+        )
+    })
+}
+
+// new RegExp(pattern, flags)。patternがRegExpの場合は同じパターンで新しいオブジェクトを作る
+fn regexp_constructor(
+    runtime: &mut JsRuntime,
+    _this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let (pattern, default_flags) = match (regex_of(&arg(&args, 0)), arg(&args, 0)) {
+        (Some(regex), _) => (regex.source.clone(), regex.flags.to_string()),
+        (None, RuntimeValue::Undefined) => (String::new(), String::new()),
+        (None, pattern) => (pattern.to_string(), String::new()),
+    };
+    let flags = match arg(&args, 1) {
+        RuntimeValue::Undefined => default_flags,
+        flags => flags.to_string(),
+    };
+    runtime.new_regexp(&pattern, &flags)
+}
+
+// 照合が複雑すぎる場合のエラーをRangeErrorにする
+fn too_complex(runtime: &JsRuntime, message: String) -> JsException {
+    runtime.throw_error("RangeError", message)
+}
+
+// lastIndexから検索し、lastIndexを更新する。gとyのフラグがない場合は常に先頭から検索する
+fn regexp_exec(
+    runtime: &JsRuntime,
+    regexp: &RuntimeValue,
+    regex: &Regex,
+    input: &[char],
+) -> Result<Option<Captures>, JsException> {
+    let uses_last_index = regex.flags.global || regex.flags.sticky;
+    let start = if uses_last_index {
+        let index = regexp.get_property("lastIndex").to_number();
+        if index.is_nan() {
+            0
+        } else {
+            index.max(0.0) as usize
+        }
+    } else {
+        0
+    };
+
+    let result = regex
+        .exec_at(input, start)
+        .map_err(|e| too_complex(runtime, e))?;
+    if uses_last_index {
+        let last_index = match &result {
+            Some(captures) => captures[0].map(|(_, end)| end).unwrap_or(0),
+            None => 0,
+        };
+        regexp.set_property("lastIndex", RuntimeValue::Number(last_index as f64));
+    }
+    Ok(result)
+}
+
+fn captured_value(input: &[char], range: Option<(usize, usize)>) -> RuntimeValue {
+    match range {
+        Some((start, end)) => RuntimeValue::StringLiteral(input[start..end].iter().collect()),
+        None => RuntimeValue::Undefined,
+    }
+}
+
+// 一致した文字列とキャプチャの配列。一致した位置のindex、元の文字列のinput、名前付きグループのgroupsを持つ
+// 名前付きグループがない場合、groupsはundefined
+fn match_array(
+    runtime: &JsRuntime,
+    regex: &Regex,
+    input: &[char],
+    captures: &Captures,
+) -> RuntimeValue {
+    let elements = captures
+        .iter()
+        .map(|range| captured_value(input, *range))
+        .collect();
+    let array = new_array(runtime, elements);
+    let index = captures[0].map(|(start, _)| start).unwrap_or(0);
+    array.set_property("index", RuntimeValue::Number(index as f64));
+    array.set_property("input", RuntimeValue::StringLiteral(input.iter().collect()));
+    let groups = if regex.group_names().is_empty() {
+        RuntimeValue::Undefined
+    } else {
+        let groups = runtime.new_object();
+        for (name, index) in regex.group_names() {
+            groups.set_property(name, captured_value(input, captures[*index]));
+        }
+        groups
+    };
+    array.set_property("groups", groups);
+    array
+}
+
+static REGEXP_METHODS: [(&str, NativeFunction); 3] = [
+    ("exec", |runtime, this, args| {
+        let regex = this_regex(runtime, &this, "exec")?;
+        let input = this_chars(&arg(&args, 0));
+        match regexp_exec(runtime, &this, &regex, &input)? {
+            Some(captures) => Ok(match_array(runtime, &regex, &input, &captures)),
+            None => Ok(RuntimeValue::Null),
+        }
+    }),
+    ("test", |runtime, this, args| {
+        let regex = this_regex(runtime, &this, "test")?;
+        let input = this_chars(&arg(&args, 0));
+        Ok(RuntimeValue::Boolean(
+            regexp_exec(runtime, &this, &regex, &input)?.is_some(),
+        ))
+    }),
+    ("toString", |_, this, _| {
+        Ok(RuntimeValue::StringLiteral(format!(
+            "/{}/{}",
+            this.get_property("source"),
+            this.get_property("flags")
+        )))
+    }),
+];
+
+// 文字列の引数をRegExpに変換する。match()のように省略された場合は空のパターン
+fn to_regexp(runtime: &mut JsRuntime, value: RuntimeValue) -> Result<RuntimeValue, JsException> {
+    if regex_of(&value).is_some() {
+        return Ok(value);
+    }
+    let pattern = match value {
+        RuntimeValue::Undefined => String::new(),
+        value => value.to_string(),
+    };
+    runtime.new_regexp(&pattern, "")
+}
+
+// gフラグの正規表現に一致する部分をすべて探す。空文字列に一致した場合は1文字進める
+fn find_all(
+    runtime: &JsRuntime,
+    regex: &Regex,
+    input: &[char],
+) -> Result<Vec<Captures>, JsException> {
+    let mut matches = Vec::new();
+    let mut pos = 0;
+    while pos <= input.len() {
+        let captures = match regex
+            .exec_at(input, pos)
+            .map_err(|e| too_complex(runtime, e))?
+        {
+            Some(captures) => captures,
+            None => break,
+        };
+        let (start, end) = captures[0].unwrap_or((pos, pos));
+        pos = if end == start { end + 1 } else { end };
+        matches.push(captures);
+    }
+    Ok(matches)
+}
+
+// $&、$1、$<name>などの置換パターンを展開する
+fn expand_replacement(
+    replacement: &[char],
+    input: &[char],
+    captures: &Captures,
+    names: &[(String, usize)],
+) -> String {
+    let (start, end) = captures[0].unwrap_or((0, 0));
+    let mut result = String::new();
+    let mut i = 0;
+    while i < replacement.len() {
+        let c = replacement[i];
+        let next = match replacement.get(i + 1) {
+            Some(next) if c == '$' => *next,
+            _ => {
+                result.push(c);
+                i += 1;
+                continue;
+            }
+        };
+        match next {
+            '$' => result.push('$'),
+            '&' => result.extend(&input[start..end]),
+            '`' => result.extend(&input[..start]),
+            '\'' => result.extend(&input[end..]),
+            '0'..='9' => {
+                // $12は12番目のグループが存在する場合だけ2桁の番号として読む
+                let one = next.to_digit(10).unwrap_or(0) as usize;
+                let two = replacement
+                    .get(i + 2)
+                    .and_then(|c| c.to_digit(10))
+                    .map(|d| one * 10 + d as usize);
+                let (index, width) = match two {
+                    Some(n) if n >= 1 && n < captures.len() => (n, 3),
+                    _ => (one, 2),
+                };
+                if index == 0 || index >= captures.len() {
+                    result.push('$');
+                    i += 1;
+                    continue;
+                }
+                if let Some((s, e)) = captures[index] {
+                    result.extend(&input[s..e]);
+                }
+                i += width;
+                continue;
+            }
+            '<' if !names.is_empty() => {
+                let close = match replacement[i + 2..].iter().position(|c| *c == '>') {
+                    Some(close) => i + 2 + close,
+                    None => {
+                        result.push('$');
+                        i += 1;
+                        continue;
+                    }
+                };
+                let name: String = replacement[i + 2..close].iter().collect();
+                let range = names
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .and_then(|(_, index)| captures[*index]);
+                if let Some((s, e)) = range {
+                    result.extend(&input[s..e]);
+                }
+                i = close + 1;
+                continue;
+            }
+            _ => {
+                result.push('$');
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    result
+}
+
+// replaceとreplaceAll。patternが文字列の場合、replaceは最初に現れた部分だけを置き換える
+fn string_replace(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+    all: bool,
+) -> Result<RuntimeValue, JsException> {
+    let input = this_chars(&this);
+    let pattern = arg(&args, 0);
+
+    let (matches, names) = match regex_of(&pattern) {
+        Some(regex) => {
+            if all && !regex.flags.global {
+                return Err(runtime.throw_error(
+                    "TypeError",
+                    "replaceAll must be called with a global RegExp".to_string(),
+                ));
+            }
+            let matches = if regex.flags.global {
+                pattern.set_property("lastIndex", RuntimeValue::Number(0.0));
+                find_all(runtime, &regex, &input)?
+            } else {
+                regexp_exec(runtime, &pattern, &regex, &input)?
+                    .into_iter()
+                    .collect()
+            };
+            (matches, regex.group_names().to_vec())
+        }
+        None => {
+            let search = this_chars(&pattern);
+            let mut matches = Vec::new();
+            let mut from = 0;
+            while let Some(index) = find_chars(&input, &search, from) {
+                matches.push(Vec::from([Some((index, index + search.len()))]));
+                if !all {
+                    break;
+                }
+                from = index + search.len().max(1);
+                if from > input.len() {
+                    break;
+                }
+            }
+            (matches, Vec::new())
+        }
+    };
+
+    let replacement = arg(&args, 1);
+    let template = this_chars(&replacement);
+    let mut result = String::new();
+    let mut last = 0;
+    for captures in matches {
+        let (start, end) = captures[0].unwrap_or((last, last));
+        result.extend(&input[last..start]);
+        if replacement.is_function() {
+            // 関数には一致した文字列、キャプチャ、位置、元の文字列を渡す
+            let mut args: Vec<RuntimeValue> = captures
+                .iter()
+                .map(|range| captured_value(&input, *range))
+                .collect();
+            args.push(RuntimeValue::Number(start as f64));
+            args.push(RuntimeValue::StringLiteral(input.iter().collect()));
+            let value = runtime.call(&replacement, RuntimeValue::Undefined, args)?;
+            result.push_str(&value.to_string());
+        } else {
+            result.push_str(&expand_replacement(&template, &input, &captures, &names));
+        }
+        last = end;
+    }
+    result.extend(&input[last..]);
+    Ok(RuntimeValue::StringLiteral(result))
+}
+
+// 正規表現の区切りで分ける。キャプチャした部分も結果に含める
+fn split_by_regex(
+    runtime: &JsRuntime,
+    regex: &Regex,
+    input: &[char],
+) -> Result<Vec<RuntimeValue>, JsException> {
+    let match_at = |pos| {
+        regex
+            .match_at(input, pos)
+            .map_err(|e| too_complex(runtime, e))
+    };
+    if input.is_empty() {
+        return Ok(match match_at(0)? {
+            Some(_) => Vec::new(),
+            None => Vec::from([RuntimeValue::StringLiteral(String::new())]),
+        });
+    }
+
+    let mut parts = Vec::new();
+    let mut last = 0;
+    let mut pos = 0;
+    while pos < input.len() {
+        let captures = match match_at(pos)? {
+            Some(captures) => captures,
+            None => {
+                pos += 1;
+                continue;
+            }
+        };
+        let end = captures[0].map(|(_, end)| end).unwrap_or(pos);
+        // 前の区切りの直後で空文字列に一致した場合は区切らない
+        if end == last {
+            pos += 1;
+            continue;
+        }
+        parts.push(RuntimeValue::StringLiteral(
+            input[last..pos].iter().collect(),
+        ));
+        for range in &captures[1..] {
+            parts.push(captured_value(input, *range));
+        }
+        last = end;
+        pos = end;
+    }
+    parts.push(RuntimeValue::StringLiteral(input[last..].iter().collect()));
+    Ok(parts)
+}
+
+// 正規表現を引数に取る文字列のメソッド
+static STRING_METHODS: [(&str, NativeFunction); 5] = [
+    // gフラグがない場合はexec()と同じ結果、ある場合は一致したすべての文字列の配列を返す
+    ("match", |runtime, this, args| {
+        let regexp = to_regexp(runtime, arg(&args, 0))?;
+        let regex = this_regex(runtime, &regexp, "match")?;
+        let input = this_chars(&this);
+        if !regex.flags.global {
+            return Ok(match regexp_exec(runtime, &regexp, &regex, &input)? {
+                Some(captures) => match_array(runtime, &regex, &input, &captures),
+                None => RuntimeValue::Null,
+            });
+        }
+        regexp.set_property("lastIndex", RuntimeValue::Number(0.0));
+        let matches = find_all(runtime, &regex, &input)?;
+        if matches.is_empty() {
+            return Ok(RuntimeValue::Null);
+        }
+        let elements = matches
+            .iter()
+            .map(|captures| captured_value(&input, captures[0]))
+            .collect();
+        Ok(new_array(runtime, elements))
+    }),
+    ("replace", |runtime, this, args| {
+        string_replace(runtime, this, args, false)
+    }),
+    ("replaceAll", |runtime, this, args| {
+        string_replace(runtime, this, args, true)
+    }),
+    // 最初に一致した位置。一致しない場合は-1
+    ("search", |runtime, this, args| {
+        let regexp = to_regexp(runtime, arg(&args, 0))?;
+        let regex = this_regex(runtime, &regexp, "search")?;
+        let input = this_chars(&this);
+        Ok(RuntimeValue::Number(
+            match regex
+                .exec_at(&input, 0)
+                .map_err(|e| too_complex(runtime, e))?
+                .and_then(|captures| captures[0])
+            {
+                Some((start, _)) => start as f64,
+                None => -1.0,
+            },
+        ))
+    }),
+    // 区切りが省略された場合は文字列全体、空文字列の場合は1文字ずつに分ける。limitで要素の数を制限できる
+    ("split", |runtime, this, args| {
+        let input = this_chars(&this);
+        let mut parts: Vec<RuntimeValue> = match arg(&args, 0) {
+            RuntimeValue::Undefined => {
+                Vec::from([RuntimeValue::StringLiteral(input.iter().collect())])
+            }
+            separator => match regex_of(&separator) {
+                Some(regex) => split_by_regex(runtime, &regex, &input)?,
+                None => {
+                    let separator = separator.to_string();
+                    let s: String = input.iter().collect();
+                    if separator.is_empty() {
+                        s.chars()
+                            .map(|c| RuntimeValue::StringLiteral(c.to_string()))
+                            .collect()
+                    } else {
+                        s.split(separator.as_str())
+                            .map(|p| RuntimeValue::StringLiteral(p.to_string()))
+                            .collect()
+                    }
+                }
+            },
+        };
+        if let limit @ RuntimeValue::Number(_) = arg(&args, 1) {
+            parts.truncate(limit.to_number().max(0.0) as usize);
+        }
+        Ok(new_array(runtime, parts))
+    }),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::token::JsLexer;
+
+    // 一致した範囲の文字列。一致しなかったグループは"-"
+    fn exec(pattern: &str, flags: &str, input: &str) -> Option<Vec<String>> {
+        let regex = Regex::new(pattern, flags).expect("failed to compile");
+        let input: Vec<char> = input.chars().collect();
+        regex
+            .exec_at(&input, 0)
+            .expect("too complex")
+            .map(|captures| {
+                captures
+                    .iter()
+                    .map(|range| match range {
+                        Some((start, end)) => input[*start..*end].iter().collect(),
+                        None => "-".to_string(),
+                    })
+                    .collect()
+            })
+    }
+
+    fn run(script: &str) -> String {
+        let mut runtime =
+            JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))));
+        let program = JsParser::new(JsLexer::new(script.to_string()))
+            .parse_ast()
+            .expect("failed to parse");
+        runtime.execute(&program).expect("failed to execute");
+        runtime
+            .lookup_global("result")
+            .expect("result should be defined")
+            .to_string()
+    }
+
+    #[test]
+    fn test_match() {
+        let cases = [
+            ("a.c", "", "xxabc", Some(vec!["abc"])),
+            ("^\\d+$", "", "123", Some(vec!["123"])),
+            ("^\\d+$", "", "12a", None),
+            (
+                "(\\w+)@(\\w+)\\.com",
+                "",
+                "mail me@host.com",
+                Some(vec!["me@host.com", "me", "host"]),
+            ),
+            ("a(b)?c", "", "ac", Some(vec!["ac", "-"])),
+            ("[^a-c]+", "", "abcdef", Some(vec!["def"])),
+            ("<.*>", "", "<a><b>", Some(vec!["<a><b>"])),
+            ("<.*?>", "", "<a><b>", Some(vec!["<a>"])),
+            ("x{2,3}", "", "xxxxx", Some(vec!["xxx"])),
+            ("a{,2}", "", "a{,2}", Some(vec!["a{,2}"])),
+            ("cat|dog", "", "hotdog", Some(vec!["dog"])),
+            (
+                "(a|ab)(c|bcd)(d*)",
+                "",
+                "abcd",
+                Some(vec!["abcd", "a", "bcd", ""]),
+            ),
+            ("(\\w)\\1", "", "abccd", Some(vec!["cc", "c"])),
+            (
+                "(?<year>\\d{4})-\\k<year>",
+                "",
+                "2024-2024",
+                Some(vec!["2024-2024", "2024"]),
+            ),
+            ("\\bfoo\\b", "", "afoo foo", Some(vec!["foo"])),
+            ("foo(?=bar)", "", "foobaz foobar", Some(vec!["foo"])),
+            ("foo(?!bar)", "", "foobar foobaz", Some(vec!["foo"])),
+            ("(?<=\\$)\\d+", "", "cost $42", Some(vec!["42"])),
+            ("(?<!\\$)\\b\\d+", "", "$42 17", Some(vec!["17"])),
+            ("HELLO", "i", "say hello", Some(vec!["hello"])),
+            ("[A-Z]+", "i", "abc", Some(vec!["abc"])),
+            ("^b", "m", "a\nb", Some(vec!["b"])),
+            ("^b", "", "a\nb", None),
+            ("a.b", "s", "a\nb", Some(vec!["a\nb"])),
+            ("\\u0041\\x42\\u{43}", "", "ABC", Some(vec!["ABC"])),
+            ("(a*)*b", "", "aaab", Some(vec!["aaab", "aaa"])),
+        ];
+        for (pattern, flags, input, expected) in cases {
+            let expected =
+                expected.map(|e| e.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+            assert_eq!(
+                expected,
+                exec(pattern, flags, input),
+                "/{}/{}",
+                pattern,
+                flags
+            );
+        }
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for (pattern, flags, message) in [
+            ("a(b", "", "unterminated group"),
+            ("a)b", "", "unmatched ')'"),
+            ("*a", "", "nothing to repeat"),
+            ("[z-a]", "", "range out of order in character class"),
+            ("[ab", "", "unterminated character class"),
+            ("a{3,1}", "", "numbers out of order in {} quantifier"),
+            ("a", "gg", "invalid flags 'gg'"),
+            ("a", "x", "invalid flags 'x'"),
+        ] {
+            assert_eq!(Err(message.to_string()), Regex::new(pattern, flags));
+        }
+    }
+
+    #[test]
+    fn test_sticky() {
+        let regex = Regex::new("b", "y").expect("failed to compile");
+        let input: Vec<char> = "abb".chars().collect();
+        assert_eq!(Ok(None), regex.exec_at(&input, 0));
+        assert_eq!(Ok(Some(vec![Some((1, 2))])), regex.exec_at(&input, 1));
+    }
+
+    #[test]
+    fn test_backtrack_limit() {
+        let regex = Regex::new("^(a+)+$", "").expect("failed to compile");
+        let input: Vec<char> = format!("{}!", "a".repeat(28)).chars().collect();
+        assert!(regex.exec_at(&input, 0).is_err());
+        assert_eq!(
+            Some(vec!["aaaa".to_string(), "aaaa".to_string()]),
+            exec("^(a+)+$", "", "aaaa")
+        );
+        assert_eq!(
+            "RangeError: regular expression is too complex",
+            run("var result; try { /^(a+)+$/.test(\"a\".repeat(28) + \"!\"); } catch (e) { result = e.toString(); }")
+        );
+    }
+
+    #[test]
+    fn test_long_input() {
+        // 開始位置ごとに上限を数えるため、長い文字列の末尾にある一致も見つかる
+        assert_eq!(
+            "true,600000,600000,true",
+            run("var s = \"a\".repeat(600000) + \"b\"; var result = [/b/.test(s), s.search(/b/), s.indexOf(\"b\"), /a*b$/.test(s.slice(599000))].join(\",\");")
+        );
+        assert_eq!(
+            Some(vec!["xyz1".to_string()]),
+            exec("xyz\\d", "", "xyzxyz1")
+        );
+        assert_eq!(
+            vec!['x', 'y', 'z'],
+            Regex::new("xyz\\d", "").unwrap().prefix
+        );
+        assert!(Regex::new("xyz", "i").unwrap().prefix.is_empty());
+    }
+
+    #[test]
+    fn test_regexp_object() {
+        assert_eq!(
+            "true,false,/a+b/gi,a+b,gi,aab,AAB,4,0",
+            run("var r = /a+b/gi; var m1 = r.test(\"xaab\"); var last = r.lastIndex; var m2 = r.test(\"xaab\"); var m = new RegExp(r).exec(\"aab AAB\"); var s = new RegExp(\"a+b\", \"gi\"); s.exec(\"ab AAB\"); var result = [m1, m2, r.toString(), r.source, r.flags, m[0], s.exec(\"ab AAB\")[0], last, r.lastIndex].join(\",\");")
+        );
+        assert_eq!(
+            "2024,05,-",
+            run("var m = /(\\d+)-(\\d+)(x)?/.exec(\"on 2024-05\"); var result = m[1] + \",\" + m[2] + \",\" + (m[3] === undefined ? \"-\" : m[3]);")
+        );
+        assert_eq!(
+            "3,on 2024-05,2024,05,true",
+            run("var m = /(?<year>\\d+)-(?<month>\\d+)/.exec(\"on 2024-05\"); var result = [m.index, m.input, m.groups.year, m.groups.month, /a/.exec(\"a\").groups === undefined].join(\",\");")
+        );
+    }
+
+    #[test]
+    fn test_string_methods() {
+        let cases = [
+            ("\"a1b22c333\".match(/\\d+/g).join(\"|\")", "1|22|333"),
+            ("\"a1b22\".match(/(\\d)(\\d)/).join(\"|\")", "22|2|2"),
+            ("\"abc\".match(/x/g)", "null"),
+            (
+                "\"John Smith\".replace(/(\\w+)\\s(\\w+)/, \"$2, $1\")",
+                "Smith, John",
+            ),
+            ("\"aaa\".replace(/a/g, \"[$&]\")", "[a][a][a]"),
+            (
+                "\"x-y\".replace(/(?<l>\\w)-(?<r>\\w)/, \"$<r>+$<l>$$\")",
+                "y+x$",
+            ),
+            ("\"a.b.c\".replace(\".\", \"-\")", "a-b.c"),
+            ("\"a.b.c\".replaceAll(\".\", \"-\")", "a-b-c"),
+            ("\"abc\".replace(/b/, \"$`|$'\")", "aa|cc"),
+            (
+                "\"3 4\".replace(/\\d/g, function (d, offset) { return d * 2 + \"@\" + offset; })",
+                "6@0 8@2",
+            ),
+            ("\"xyz\".replace(/$/, \"!\")", "xyz!"),
+            ("\"abc\".replace(/(?:)/g, \"-\")", "-a-b-c-"),
+            ("\"hello world\".search(/o\\s/)", "4"),
+            ("\"hello\".search(\"z\")", "-1"),
+            ("\"a1b2c\".split(/\\d/).join(\"|\")", "a|b|c"),
+            ("\"a1b2c\".split(/(\\d)/).join(\"|\")", "a|1|b|2|c"),
+            ("\"abc\".split(\"\").join(\"|\")", "a|b|c"),
+            ("\"a,b,c\".split(\",\", 2).join(\"|\")", "a|b"),
+            ("\"abc\".split(/(?:)/).join(\"|\")", "a|b|c"),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                expected,
+                run(&format!("var result = {};", expression)),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "SyntaxError: Invalid regular expression: /(/: unterminated group",
+            run("var result; try { new RegExp(\"(\"); } catch (e) { result = e.toString(); }")
+        );
+        assert_eq!(
+            "TypeError: replaceAll must be called with a global RegExp",
+            run("var result; try { \"a\".replaceAll(/a/, \"b\"); } catch (e) { result = e.toString(); }")
+        );
+    }
+}
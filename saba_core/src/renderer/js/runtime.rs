@@ -1,6 +1,18 @@
-use crate::renderer::dom::node::NodeKind as DomNodeKind;
-use crate::renderer::dom::{api::get_element_by_id, node::Node as DomNode};
-use crate::renderer::js::ast::{Node, Program};
+use crate::http::{Header, Transport};
+use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+use crate::renderer::js::ast::{DeclarationKind, MethodKind, Node, Program};
+use crate::renderer::js::bytecode::{Code, FunctionKind};
+use crate::renderer::js::console::{ConsoleLevel, ConsoleMessage, INLINE_SOURCE};
+use crate::renderer::js::event::EventListeners;
+use crate::renderer::js::event_loop::{EventLoop, ManualClock};
+use crate::renderer::js::fetch::{RequestState, ResponseState};
+use crate::renderer::js::gc::{Heap, HeapStats, Marker, Releaser, Trace, INITIAL_THRESHOLD};
+use crate::renderer::js::module::ModuleMap;
+use crate::renderer::js::promise::PromiseState;
+use crate::renderer::js::regexp::Regex;
+use crate::renderer::js::token::Position;
+use crate::renderer::js::vm::{self, GeneratorState, Scope, SuspendedFrame};
+use crate::renderer::js::{builtins, console, dom_binding, event_loop, fetch, promise, regexp};
 use alloc::{
     format,
     rc::Rc,
@@ -9,516 +21,4983 @@ use alloc::{
 };
 use core::{
     cell::RefCell,
-    fmt::{Display, Formatter},
-    ops::{Add, Sub},
+    cmp::Ordering,
+    fmt::{Debug, Display, Formatter},
+    mem,
+    ops::{Add, BitAnd, BitOr, BitXor, Deref, DerefMut, Div, Mul, Rem, Shl, Shr, Sub},
 };
 
 use core::borrow::Borrow;
 
+// 配列の長さの上限。長さを伸ばすと間の要素もすべて確保するため、これを超える長さはRangeErrorにする
+pub const MAX_ARRAY_LENGTH: usize = 1 << 22;
+
+// 関数呼び出しの深さの上限。これを超える再帰はRangeErrorにする
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+// 実行するスレッドのスタックの大きさ（バイト）の既定値。Rustが新しいスレッドに割り当てる大きさと同じ
+pub const DEFAULT_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// 文の実行が終わった理由
+// return、break、continueが実行された場合、残りの文を実行せずに外側へ伝える
+// breakとcontinueはラベルが指定された場合、そのラベルを持つ文まで伝える
+enum Completion {
+    Normal,
+    Return,
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// throwされた値と、例外が発生したソースコード上の位置
+pub struct JsException {
+    value: RuntimeValue,
+    position: Option<Position>,
+}
+
+impl JsException {
+    pub fn new(value: RuntimeValue, position: Option<Position>) -> Self {
+        Self { value, position }
+    }
+
+    pub fn value(&self) -> RuntimeValue {
+        self.value.clone()
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    // エラーオブジェクトの場合は"TypeError: message"の形式、それ以外は値を文字列にしたもの
+    pub fn message(&self) -> String {
+        if let RuntimeValue::Object(_) = self.value {
+            let name = self.value.get_property("name");
+            let message = self.value.get_property("message");
+            if name != RuntimeValue::Undefined {
+                return format!("{}: {}", name, message);
+            }
+        }
+        self.value.to_string()
+    }
+}
+
+impl Display for JsException {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "Uncaught {} ({})", self.message(), position),
+            None => write!(f, "Uncaught {}", self.message()),
+        }
+    }
+}
+
+// ネイティブ関数。thisと引数を受け取る
+pub type NativeFunction =
+    fn(&mut JsRuntime, RuntimeValue, Vec<RuntimeValue>) -> Result<RuntimeValue, JsException>;
+
+// 作成時に値を捕捉するネイティブ関数。捕捉した値、this、引数を受け取る
+pub type NativeClosure = fn(
+    &mut JsRuntime,
+    &[RuntimeValue],
+    RuntimeValue,
+    Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException>;
+
 #[derive(Debug, Clone)]
 pub struct JsRuntime {
     env: Rc<RefCell<Environment>>,
-    functions: Vec<Function>,
     dom_root: Rc<RefCell<DomNode>>,
+    completion: Completion,
+    // 次に実行するループやswitch文に付けられたラベル
+    labels: Vec<String>,
+    // 最後に評価した関数呼び出しかthrow文の位置。実行時エラーの位置として使う
+    position: Option<Position>,
+    // 組み込みのプロトタイプオブジェクト
+    object_prototype: RuntimeValue,
+    function_prototype: RuntimeValue,
+    string_prototype: RuntimeValue,
+    array_prototype: RuntimeValue,
+    number_prototype: RuntimeValue,
+    promise_prototype: RuntimeValue,
+    regexp_prototype: RuntimeValue,
+    // ジェネレータ関数のprototypeが継承するオブジェクト
+    generator_prototype: RuntimeValue,
+    // DOMノードのラッパーオブジェクト。同じノードには同じオブジェクトを返す
+    dom_wrappers: Vec<RuntimeValue>,
+    // Math.random()で使う疑似乱数の状態
+    random_state: u64,
+    // 実行中に作られたオブジェクト、関数、スコープ
+    heap: RefCell<Heap>,
+    // 関数を呼び出している途中のVMのフレーム。GCのルートになる
+    suspended_frames: Vec<SuspendedFrame>,
+    // 再利用するVMのスタックとスコープ
+    stack_pool: Vec<Vec<RuntimeValue>>,
+    scope_pool: Vec<Rc<Scope>>,
+    // GCから見えない値を持つ可能性がある、実行中のフレーム、ネイティブ関数、ASTの評価の数
+    active_frames: usize,
+    // 実行中の関数呼び出しの深さ
+    call_depth: usize,
+    // 最初の呼び出しでのスタックのアドレス。スタックの使用量を測る基準にする
+    stack_base: Option<usize>,
+    // 評価に使ってよいスタックの大きさ（バイト）。呼び出しの深さが上限より小さくても、これを超えたらRangeErrorにする
+    stack_limit: usize,
+    // Array.prototype.joinで要素を連結している途中の配列のアドレス
+    joining: Vec<usize>,
+    // DOMノードに登録されたイベントリスナー
+    event_listeners: EventListeners,
+    // タスク、マイクロタスク、タイマー
+    event_loop: EventLoop,
+    // イベントリスナーのように、呼び出し元に返せない場所で発生した例外
+    reported_exceptions: Vec<JsException>,
+    // consoleに出力されたメッセージ。報告された例外もエラーとして含む
+    console_messages: Vec<ConsoleMessage>,
+    // fetchとXMLHttpRequestがリクエストを送る先。埋め込み側が設定する
+    transport: Option<Rc<dyn Transport>>,
+    // 相対URLの基準になるドキュメントのURL
+    document_url: String,
+    // 実行中のスクリプトのURL。インラインのスクリプトは"inline"
+    source: Rc<str>,
+    // 読み込んだESモジュール
+    modules: ModuleMap,
 }
 
 impl JsRuntime {
     pub fn new(dom_root: Rc<RefCell<DomNode>>) -> Self {
-        Self {
-            env: Rc::new(RefCell::new(Environment::new(None))),
-            functions: Vec::new(),
+        // グローバルスコープにはNaN、Infinity、undefinedが定義されている
+        let mut global = Environment::new_function_scope(None);
+        global.add_variable("NaN".to_string(), Some(RuntimeValue::Number(f64::NAN)));
+        global.add_variable(
+            "Infinity".to_string(),
+            Some(RuntimeValue::Number(f64::INFINITY)),
+        );
+        global.add_variable("undefined".to_string(), Some(RuntimeValue::Undefined));
+
+        // 組み込みのプロトタイプはObject.prototypeを継承する
+        let object_prototype = RuntimeValue::Object(Rc::new(RefCell::new(JsObject::new())));
+        let new_prototype = || {
+            RuntimeValue::Object(Rc::new(RefCell::new(JsObject::with_prototype(Some(
+                object_prototype.clone(),
+            )))))
+        };
+
+        let mut runtime = Self {
+            env: Rc::new(RefCell::new(global)),
             dom_root,
+            completion: Completion::Normal,
+            labels: Vec::new(),
+            position: None,
+            function_prototype: new_prototype(),
+            string_prototype: new_prototype(),
+            array_prototype: new_prototype(),
+            number_prototype: new_prototype(),
+            promise_prototype: new_prototype(),
+            regexp_prototype: new_prototype(),
+            generator_prototype: new_prototype(),
+            object_prototype,
+            dom_wrappers: Vec::new(),
+            random_state: 0x2545_f491_4f6c_dd1d,
+            heap: RefCell::new(Heap::new(INITIAL_THRESHOLD)),
+            suspended_frames: Vec::new(),
+            stack_pool: Vec::new(),
+            scope_pool: Vec::new(),
+            active_frames: 0,
+            call_depth: 0,
+            stack_base: None,
+            stack_limit: stack_limit(DEFAULT_STACK_SIZE),
+            joining: Vec::new(),
+            event_listeners: EventListeners::new(),
+            // 埋め込み側が時計を設定するまで、時刻は0のまま進まない
+            event_loop: EventLoop::new(Rc::new(ManualClock::new())),
+            reported_exceptions: Vec::new(),
+            console_messages: Vec::new(),
+            transport: None,
+            document_url: "about:blank".to_string(),
+            source: Rc::from(INLINE_SOURCE),
+            modules: ModuleMap::new(),
+        };
+        builtins::define_globals(&mut runtime);
+        promise::define_globals(&mut runtime);
+        regexp::define_globals(&mut runtime);
+        vm::define_generator_methods(&runtime.generator_prototype());
+        event_loop::define_globals(&mut runtime);
+        dom_binding::define_globals(&mut runtime);
+        fetch::define_globals(&mut runtime);
+        console::define_globals(&mut runtime);
+        runtime
+    }
+
+    // グローバル変数を定義する
+    pub fn define_global(&mut self, name: &str, value: RuntimeValue) {
+        self.env.borrow_mut().set_variable(name.to_string(), value);
+    }
+
+    pub fn object_prototype(&self) -> RuntimeValue {
+        self.object_prototype.clone()
+    }
+
+    pub fn function_prototype(&self) -> RuntimeValue {
+        self.function_prototype.clone()
+    }
+
+    pub fn string_prototype(&self) -> RuntimeValue {
+        self.string_prototype.clone()
+    }
+
+    pub fn array_prototype(&self) -> RuntimeValue {
+        self.array_prototype.clone()
+    }
+
+    pub fn number_prototype(&self) -> RuntimeValue {
+        self.number_prototype.clone()
+    }
+
+    pub fn promise_prototype(&self) -> RuntimeValue {
+        self.promise_prototype.clone()
+    }
+
+    pub fn regexp_prototype(&self) -> RuntimeValue {
+        self.regexp_prototype.clone()
+    }
+
+    pub fn generator_prototype(&self) -> RuntimeValue {
+        self.generator_prototype.clone()
+    }
+
+    pub fn dom_root(&self) -> Rc<RefCell<DomNode>> {
+        self.dom_root.clone()
+    }
+
+    // Object.prototypeを継承する空のオブジェクトを作る
+    pub fn new_object(&self) -> RuntimeValue {
+        self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+            JsObject::with_prototype(Some(self.object_prototype.clone())),
+        ))))
+    }
+
+    // 作った値をGCの対象に登録する
+    pub fn track(&self, value: RuntimeValue) -> RuntimeValue {
+        self.heap.borrow_mut().track_value(&value);
+        value
+    }
+
+    pub fn track_scope(&self, scope: Scope) -> Rc<Scope> {
+        let scope = Rc::new(scope);
+        self.heap.borrow_mut().track_scope(&scope);
+        scope
+    }
+
+    fn new_environment(&self, env: Environment) -> Rc<RefCell<Environment>> {
+        let env = Rc::new(RefCell::new(env));
+        self.heap.borrow_mut().track_environment(&env);
+        env
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        RefCell::borrow(&self.heap).stats()
+    }
+
+    // 実行中のJSがない時にGCを実行する。実行中の場合は何もしない
+    pub fn collect_garbage(&mut self) -> HeapStats {
+        if self.active_frames == 0 {
+            self.collect(|_| {});
         }
+        self.heap_stats()
+    }
+
+    // VMのフレームが1つだけ実行中の場合に、必要ならGCを実行する。rootsはそのフレームの値に印を付ける
+    pub fn collect_if_needed(&mut self, roots: impl FnOnce(&mut Marker)) {
+        if self.active_frames == 1 && RefCell::borrow(&self.heap).should_collect() {
+            self.collect(roots);
+        }
+    }
+
+    fn collect(&mut self, roots: impl FnOnce(&mut Marker)) {
+        let mut marker = Marker::default();
+        marker.mark_environment(&self.env);
+        for prototype in [
+            &self.object_prototype,
+            &self.function_prototype,
+            &self.string_prototype,
+            &self.array_prototype,
+            &self.number_prototype,
+            &self.promise_prototype,
+            &self.regexp_prototype,
+            &self.generator_prototype,
+        ] {
+            marker.mark_value(prototype);
+        }
+        for frame in &self.suspended_frames {
+            frame.mark(&mut marker);
+        }
+        self.event_listeners.mark(&mut marker);
+        self.event_loop.mark(&mut marker);
+        self.modules.mark(&mut marker);
+        for exception in &self.reported_exceptions {
+            marker.mark_value(&exception.value());
+        }
+        // 文書に含まれるノードのラッパーは、document経由で再び参照される可能性がある
+        for wrapper in &self.dom_wrappers {
+            if let RuntimeValue::Object(object) = wrapper {
+                if matches!(RefCell::borrow(object).node(), Some(node) if self.is_connected(&node))
+                {
+                    marker.mark_value(wrapper);
+                }
+            }
+        }
+        roots(&mut marker);
+        marker.trace_all();
+
+        self.dom_wrappers.retain(|wrapper| match wrapper {
+            RuntimeValue::Object(object) => marker.is_marked(object),
+            _ => false,
+        });
+        self.heap.borrow_mut().sweep(&marker);
+    }
+
+    // ノードが文書に含まれているか
+    fn is_connected(&self, node: &Rc<RefCell<DomNode>>) -> bool {
+        let mut current = node.clone();
+        loop {
+            if Rc::ptr_eq(&current, &self.dom_root) {
+                return true;
+            }
+            let parent = RefCell::borrow(&current).parent().upgrade();
+            match parent {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    // VMのフレームの実行を始める
+    pub fn enter_frame(&mut self) {
+        self.active_frames += 1;
+    }
+
+    pub fn leave_frame(&mut self) {
+        self.active_frames -= 1;
+    }
+
+    // 関数を呼び出す間、呼び出し元のフレームの値をGCのルートとして預ける
+    pub fn suspend_frame(&mut self, frame: SuspendedFrame) {
+        self.suspended_frames.push(frame);
+        self.active_frames -= 1;
+    }
+
+    pub fn resume_frame(&mut self) -> SuspendedFrame {
+        self.active_frames += 1;
+        self.suspended_frames
+            .pop()
+            .expect("suspended frame should exist")
+    }
+
+    // 関数呼び出しのたびに確保しないように、使い終わったVMのスタックを再利用する
+    pub fn take_stack(&mut self) -> Vec<RuntimeValue> {
+        self.stack_pool.pop().unwrap_or_default()
+    }
+
+    pub fn recycle_stack(&mut self, mut stack: Vec<RuntimeValue>) {
+        stack.clear();
+        self.stack_pool.push(stack);
+    }
+
+    // 再利用できるスコープ。取り出した側が初期化し直す
+    pub fn take_scope(&mut self) -> Option<Rc<Scope>> {
+        self.scope_pool.pop()
+    }
+
+    pub fn put_scope(&mut self, scope: Rc<Scope>) {
+        self.scope_pool.push(scope);
+    }
+
+    // グローバルに定義されたコンストラクタのprototypeプロパティ
+    fn global_prototype(&self, name: &str) -> Option<RuntimeValue> {
+        let constructor = RefCell::borrow(&self.env).get_variable(name.to_string())?;
+        match constructor.get_property("prototype") {
+            prototype @ RuntimeValue::Object(_) => Some(prototype),
+            _ => None,
+        }
+    }
+
+    // DOMノードのラッパーオブジェクトを返す。プロトタイプはノードの種類で決まる
+    pub fn wrap_node(&mut self, node: &Rc<RefCell<DomNode>>) -> RuntimeValue {
+        for wrapper in &self.dom_wrappers {
+            if let RuntimeValue::Object(object) = wrapper {
+                if matches!(RefCell::borrow(object).node(), Some(n) if Rc::ptr_eq(&n, node)) {
+                    return wrapper.clone();
+                }
+            }
+        }
+
+        let interface = match RefCell::borrow(node).kind() {
+            DomNodeKind::Document => "Document",
+            DomNodeKind::Element(_) => "HTMLElement",
+            DomNodeKind::Text(_) => "Text",
+        };
+        let wrapper = self.new_host_object(interface, node);
+        self.dom_wrappers.push(wrapper.clone());
+        wrapper
+    }
+
+    // interfaceのプロトタイプを継承し、DOMノードを操作するオブジェクトを作る。classListやstyleで使う
+    pub fn new_host_object(&self, interface: &str, node: &Rc<RefCell<DomNode>>) -> RuntimeValue {
+        let mut object = JsObject::with_prototype(self.global_prototype(interface));
+        object.set_node(node.clone());
+        self.track(RuntimeValue::Object(Rc::new(RefCell::new(object))))
+    }
+
+    pub fn event_listeners(&mut self) -> &mut EventListeners {
+        &mut self.event_listeners
+    }
+
+    pub fn event_loop(&mut self) -> &mut EventLoop {
+        &mut self.event_loop
+    }
+
+    pub fn modules(&mut self) -> &mut ModuleMap {
+        &mut self.modules
+    }
+
+    // 捕捉されなかった例外を記録する。埋め込み側はtake_reported_exceptionsで受け取る
+    pub fn report_exception(&mut self, exception: JsException) {
+        self.console_messages.push(ConsoleMessage::new(
+            ConsoleLevel::Error,
+            format!("Uncaught {}", exception.message()),
+            self.source.to_string(),
+            exception.position(),
+        ));
+        self.reported_exceptions.push(exception);
+    }
+
+    pub fn take_reported_exceptions(&mut self) -> Vec<JsException> {
+        core::mem::take(&mut self.reported_exceptions)
+    }
+
+    pub fn add_console_message(&mut self, message: ConsoleMessage) {
+        self.console_messages.push(message);
+    }
+
+    // consoleに出力されたメッセージを出力された順に取り出す
+    pub fn take_console_messages(&mut self) -> Vec<ConsoleMessage> {
+        core::mem::take(&mut self.console_messages)
+    }
+
+    pub fn set_transport(&mut self, transport: Rc<dyn Transport>) {
+        self.transport = Some(transport);
+    }
+
+    pub fn transport(&self) -> Option<Rc<dyn Transport>> {
+        self.transport.clone()
     }
 
-    pub fn execute(&mut self, program: &Program) {
+    // 実行するスレッドのスタックの大きさを設定する。既定値と異なるスタックで実行する場合に呼び出す
+    pub fn set_stack_size(&mut self, size: usize) {
+        self.stack_limit = stack_limit(size);
+    }
+
+    pub fn set_document_url(&mut self, url: String) {
+        self.document_url = url;
+    }
+
+    pub fn document_url(&self) -> String {
+        self.document_url.clone()
+    }
+
+    pub fn set_source(&mut self, source: Rc<str>) {
+        self.source = source;
+    }
+
+    pub fn source(&self) -> Rc<str> {
+        self.source.clone()
+    }
+
+    // 0以上1未満の疑似乱数を返す（xorshift）
+    pub fn next_random(&mut self) -> f64 {
+        let mut x = self.random_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.random_state = x;
+        (x >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    // ASTを直接評価してプログラムを実行する。VMの結果を確かめるためのテストで使う
+    // 捕捉されなかった例外が発生した場合、残りの文は実行せずに例外を返す
+    pub fn interpret(&mut self, program: &Program) -> Result<(), JsException> {
+        // 評価中の値はGCから見えないため、評価が終わるまでGCは実行しない
+        self.active_frames += 1;
+        let result = self.execute_body(program);
+        self.active_frames -= 1;
+        // 関数やループの外で実行されたreturn、break、continueは無視する
+        self.completion = Completion::Normal;
+        self.reset_stack_base();
+        result
+    }
+
+    fn execute_body(&mut self, program: &Program) -> Result<(), JsException> {
+        self.hoist_declarations(program.body().iter(), self.env.clone())?;
         for node in program.body() {
-            self.eval(&Some(node.clone()), self.env.clone());
+            self.eval(&Some(node.clone()), self.env.clone())?;
+            self.completion = Completion::Normal;
+        }
+        Ok(())
+    }
+
+    // 次に発生するエラーの位置を設定する
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Option<Position>) {
+        self.position = position;
+    }
+
+    // グローバル変数の値を返す
+    pub fn lookup_global(&self, name: &str) -> Result<RuntimeValue, JsException> {
+        let found = self.find_global(name);
+        self.variable_value(name, found)
+    }
+
+    // グローバル変数を探す。初期化前の場合はSome(None)
+    pub fn find_global(&self, name: &str) -> Option<Option<RuntimeValue>> {
+        RefCell::borrow(&self.env).lookup(name)
+    }
+
+    // グローバル変数に代入する。存在しない場合は追加する
+    pub fn assign_global(&mut self, name: &str, value: RuntimeValue) -> Result<(), JsException> {
+        let result = self
+            .env
+            .borrow_mut()
+            .update_variable(name.to_string(), Some(value));
+        result.map_err(|(name, message)| self.throw_error(name, message))
+    }
+
+    // グローバル変数を宣言する。letとconstは初期化されていない状態になる
+    pub fn declare_global(&mut self, name: &str, kind: DeclarationKind) {
+        match kind {
+            DeclarationKind::Var => Environment::declare_var(&self.env, name.to_string(), None),
+            DeclarationKind::Let | DeclarationKind::Const => {
+                self.env.borrow_mut().declare_lexical(name.to_string())
+            }
+        }
+    }
+
+    // 宣言が実行された時にグローバル変数を初期化する
+    pub fn initialize_global(&mut self, name: &str, value: RuntimeValue, kind: DeclarationKind) {
+        match kind {
+            DeclarationKind::Var => {
+                Environment::declare_var(&self.env, name.to_string(), Some(value))
+            }
+            DeclarationKind::Let | DeclarationKind::Const => self
+                .env
+                .borrow_mut()
+                .initialize_variable(name.to_string(), value, kind == DeclarationKind::Let),
         }
     }
 
+    // 変数の検索結果を値にする。見つからない場合と初期化前の場合はReferenceError
+    fn variable_value(
+        &self,
+        name: &str,
+        found: Option<Option<RuntimeValue>>,
+    ) -> Result<RuntimeValue, JsException> {
+        match found {
+            Some(Some(v)) => Ok(v),
+            // letとconstで宣言された変数は、宣言が実行されるまで使えない（TDZ）
+            Some(None) => Err(self.throw_error(
+                "ReferenceError",
+                format!("cannot access '{}' before initialization", name),
+            )),
+            None => Err(self.throw_error("ReferenceError", format!("{} is not defined", name))),
+        }
+    }
+
+    // messageを持つエラーオブジェクトを作る。nameはTypeError.prototypeのようなプロトタイプが持つ
+    pub fn create_error(&self, name: &str, message: String) -> RuntimeValue {
+        let prototype = self
+            .global_prototype(name)
+            .unwrap_or_else(|| self.object_prototype.clone());
+        let error = self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+            JsObject::with_prototype(Some(prototype)),
+        ))));
+        self.init_error(&error, message);
+        error
+    }
+
+    // エラーオブジェクトにmessageを設定する。位置が分かる場合は行と列も設定する
+    pub fn init_error(&self, error: &RuntimeValue, message: String) {
+        error.set_property("message", RuntimeValue::StringLiteral(message));
+        if let Some(position) = self.position {
+            error.set_property("lineNumber", RuntimeValue::Number(position.line as f64));
+            error.set_property("columnNumber", RuntimeValue::Number(position.column as f64));
+        }
+    }
+
+    // ランタイムが発生させる例外。TypeErrorやReferenceErrorのエラーオブジェクトを投げる
+    pub fn throw_error(&self, name: &str, message: String) -> JsException {
+        JsException::new(self.create_error(name, message), self.position)
+    }
+
     fn eval(
         &mut self,
         node: &Option<Rc<Node>>,
         env: Rc<RefCell<Environment>>,
-    ) -> Option<RuntimeValue> {
+    ) -> Result<Option<RuntimeValue>, JsException> {
         let node = match node {
             Some(n) => n,
-            None => return None,
+            None => return Ok(None),
         };
+        // 入れ子になった式の評価もスタックを使うため、関数呼び出しと同じ上限を確かめる
+        self.check_stack()?;
 
+        // 各分岐は値を返すだけにする。分岐の中で一時的な値を作ると、再帰のたびに積まれるフレームが大きくなる
         match node.borrow() {
-            Node::ExpressionStatement(expr) => return self.eval(&expr, env.clone()),
-            Node::AdditiveExpression {
-                operator,
-                left,
-                right,
-            } => {
-                let left_value = match self.eval(&left, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
-                let right_value = match self.eval(&right, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
-
-                if operator == &'+' {
-                    Some(left_value + right_value)
-                } else if operator == &'-' {
-                    Some(left_value - right_value)
-                } else {
-                    None
-                }
+            Node::ExpressionStatement(expr) => self.eval(expr, env),
+            Node::AdditiveExpression { .. }
+            | Node::MultiplicativeExpression { .. }
+            | Node::RelationalExpression { .. }
+            | Node::EqualityExpression { .. }
+            | Node::LogicalExpression { .. }
+            | Node::BinaryExpression { .. } => self.binary_chain(node, env),
+            Node::UnaryExpression { operator, argument } => {
+                self.unary_expression(operator, argument, env)
+            }
+            Node::SequenceExpression { expressions } => self.sequence_expression(expressions, env),
+            Node::ConditionalExpression {
+                test,
+                consequent,
+                alternate,
             }
+            | Node::IfStatement {
+                test,
+                consequent,
+                alternate,
+            } => self.conditional(test, consequent, alternate, env),
             Node::AssignmentExpression {
                 operator,
                 left,
                 right,
-            } => {
-                if operator != &'=' {
-                    return None;
+            } => self.assignment_expression(operator, left, right, env),
+            Node::MemberExpression {
+                object,
+                property,
+                computed,
+            } => self.member_expression(object, property, *computed, env),
+            Node::NumericLiteral(value) => Ok(Some(RuntimeValue::Number(*value))),
+            Node::BooleanLiteral(value) => Ok(Some(RuntimeValue::Boolean(*value))),
+            Node::NullLiteral => Ok(Some(RuntimeValue::Null)),
+            Node::ArrayExpression { elements } => self.array_expression(elements, env),
+            Node::ObjectExpression { properties } => self.object_expression(properties, env),
+            // プロパティとメソッドはObjectExpressionやクラスの中で評価する
+            Node::Property { .. } | Node::MethodDefinition { .. } => Ok(None),
+            Node::VariableDeclaration { kind, declarations } => {
+                self.variable_declaration(*kind, declarations, env)
+            }
+            // 変数の宣言はVariableDeclarationの中で処理する
+            Node::VariableDeclarator { .. } => Ok(None),
+            Node::Identifier(name) => self.identifier(name, env),
+            Node::StringLiteral(value) => Ok(Some(RuntimeValue::StringLiteral(value.to_string()))),
+            Node::TemplateLiteral {
+                quasis,
+                expressions,
+            } => self.template_literal(quasis, expressions, env),
+            Node::TemplateObject { cooked, raw } => Ok(Some(self.template_object(cooked, raw))),
+            Node::RegExpLiteral { pattern, flags } => self.new_regexp(pattern, flags).map(Some),
+            Node::UpdateExpression {
+                operator,
+                prefix,
+                argument,
+            } => self.update_expression(*operator, *prefix, argument, env),
+            Node::BlockStatement { body } => self.block_statement(body, env),
+            Node::ReturnStatement { argument } => self.return_statement(argument, env),
+            Node::WhileStatement { test, body } => self.while_statement(test, body, env),
+            Node::DoWhileStatement { body, test } => self.do_while_statement(body, test, env),
+            Node::ForInStatement { left, right, body } => {
+                self.for_in_of_statement(left, right, body, true, env)
+            }
+            Node::ForOfStatement { left, right, body } => {
+                self.for_in_of_statement(left, right, body, false, env)
+            }
+            Node::SwitchStatement {
+                discriminant,
+                cases,
+            } => self.switch_statement(discriminant, cases, env),
+            // 各caseはSwitchStatementの中で評価する
+            Node::SwitchCase { .. } => Ok(None),
+            Node::LabeledStatement { label, body } => self.labeled_statement(label, body, env),
+            Node::ForStatement {
+                init,
+                test,
+                update,
+                body,
+            } => self.for_statement(init, test, update, body, env),
+            Node::BreakStatement { label } => {
+                self.completion = Completion::Break(label.clone());
+                Ok(None)
+            }
+            Node::ContinueStatement { label } => {
+                self.completion = Completion::Continue(label.clone());
+                Ok(None)
+            }
+            Node::FunctionDeclaration {
+                id,
+                params,
+                body,
+                is_async,
+                is_generator,
+            } => self.function_declaration(id, params, body, *is_async || *is_generator, env),
+            Node::FunctionExpression {
+                id,
+                params,
+                body,
+                is_async,
+                is_generator,
+            } => self.function_expression(id, params, body, *is_async || *is_generator, env),
+            Node::ArrowFunctionExpression {
+                params,
+                body,
+                is_async,
+            } => self.arrow_function(params, body, *is_async, env),
+            // yieldとawaitはasync関数とジェネレータの中にだけ現れる
+            Node::YieldExpression { .. } | Node::AwaitExpression { .. } => {
+                Err(self.unsupported_function())
+            }
+            Node::ThisExpression => match RefCell::borrow(&env).lookup("this") {
+                Some(Some(this)) => Ok(Some(this)),
+                _ => Ok(Some(RuntimeValue::Undefined)),
+            },
+            // super.method()のsuper。メソッドが定義されたオブジェクトのプロトタイプを表す
+            Node::Super => {
+                match RefCell::borrow(&env).lookup("super") {
+                    Some(Some(base)) => Ok(Some(base)),
+                    _ => Err(self
+                        .throw_error("SyntaxError", "'super' keyword unexpected here".to_string())),
                 }
+            }
+            Node::ClassDeclaration {
+                id,
+                super_class,
+                body,
+            } => self.class_declaration(id, super_class, body, env),
+            Node::ClassExpression {
+                id,
+                super_class,
+                body,
+            } => self.define_class(id, super_class, body, env).map(Some),
+            Node::NewExpression {
+                callee,
+                arguments,
+                position,
+            } => self.new_expression(callee, arguments, *position, env),
+            Node::CallExpression {
+                callee,
+                arguments,
+                position,
+            } => self.call_expression(callee, arguments, *position, env),
+            Node::ThrowStatement { argument, position } => {
+                self.throw_statement(argument, *position, env)
+            }
+            Node::TryStatement {
+                block,
+                param,
+                handler,
+                finalizer,
+            } => self.try_statement(block, param, handler, finalizer, env),
+            // モジュールはVMだけで実行できる
+            Node::ImportDeclaration { .. }
+            | Node::ExportNamedDeclaration { .. }
+            | Node::ExportDefaultDeclaration { .. }
+            | Node::ExportAllDeclaration { .. } => Err(self.throw_error(
+                "SyntaxError",
+                "modules are not supported by the interpreter".to_string(),
+            )),
+        }
+    }
 
-                // 変数の再割当て
-                if let Some(node) = left {
-                    if let Node::Identifier(id) = node.borrow() {
-                        let new_value = self.eval(right, env.clone());
-                        env.borrow_mut().update_variable(id.to_string(), new_value);
-                        return None;
-                    }
-                }
+    fn sequence_expression(
+        &mut self,
+        expressions: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let mut result = None;
+        for expression in expressions {
+            result = self.eval(expression, env.clone())?;
+        }
+        Ok(result)
+    }
 
-                if let Some(RuntimeValue::HtmlElement { object, property }) =
-                    self.eval(left, env.clone())
-                {
-                    let right_value = match self.eval(right, env.clone()) {
-                        Some(value) => value,
-                        None => return None,
-                    };
-
-                    if let Some(p) = property {
-                        // target.textContent = "foobar";のようにノードのテキストを変更する
-                        if p == "textContent" {
-                            object
-                                .borrow_mut()
-                                .set_first_child(Some(Rc::new(RefCell::new(DomNode::new(
-                                    DomNodeKind::Text(right_value.to_string()),
-                                )))));
-                        }
-                    }
-                }
+    // 条件演算子とif文。条件に応じてどちらか一方だけを評価する
+    fn conditional(
+        &mut self,
+        test: &Option<Rc<Node>>,
+        consequent: &Option<Rc<Node>>,
+        alternate: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        if self.eval_condition(test, env.clone())? {
+            self.eval(consequent, env)
+        } else {
+            self.eval(alternate, env)
+        }
+    }
 
-                None
-            }
-            Node::MemberExpression { object, property } => {
-                let object_value = match self.eval(object, env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
-                let property_value = match self.eval(property, env.clone()) {
-                    Some(value) => value,
-                    // プロパティが存在しないため 'object_value'をここで返す
-                    None => return Some(object_value),
-                };
+    fn member_expression(
+        &mut self,
+        object: &Option<Rc<Node>>,
+        property: &Option<Rc<Node>>,
+        computed: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let object_value = match self.eval(object, env.clone())? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let key = match self.property_key(property, computed, env)? {
+            Some(key) => key,
+            // プロパティが存在しないため 'object_value'をここで返す
+            None => return Ok(Some(object_value)),
+        };
 
-                // もしオブジェクトがDOMノードの場合、HtmlElementのpropertyを更新する
-                if let RuntimeValue::HtmlElement { object, property } = object_value {
-                    assert!(property.is_none());
-                    return Some(RuntimeValue::HtmlElement {
-                        object,
-                        property: Some(property_value.to_string()),
-                    });
-                }
-
-                // document.getElementByIdは"document.getElementById"という一つの文字列として扱う
-                // このメソッドへの呼び出しは、"document.getElementById"という名前の関数への呼び出しとなる
-                return Some(
-                    object_value + RuntimeValue::StringLiteral(".".to_string()) + property_value,
-                );
-            }
-            Node::NumericLiteral(value) => Some(RuntimeValue::Number(*value)),
-            Node::VariableDeclaration { declarations } => {
-                for declaration in declarations {
-                    self.eval(&declaration, env.clone());
-                }
-                None
-            }
-            Node::VariableDeclarator { id, init } => {
-                if let Some(node) = id {
-                    if let Node::Identifier(id) = node.borrow() {
-                        let init = self.eval(&init, env.clone());
-                        env.borrow_mut().add_variable(id.to_string(), init);
-                    }
-                }
-                None
-            }
-            Node::Identifier(name) => {
-                match env.borrow_mut().get_variable(name.to_string()) {
-                    Some(v) => Some(v),
-                    // 変数名が初めて使用される場合、まだ値は保存されていないので文字列として扱う
-                    // 例えばvar a = 42;の時aはStringLiteralとして扱われる
-                    None => Some(RuntimeValue::StringLiteral(name.to_string())),
-                }
+        Ok(Some(self.get(&object_value, &key)?))
+    }
+
+    fn array_expression(
+        &mut self,
+        elements: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let mut values = Vec::new();
+        for element in elements {
+            values.push(
+                self.eval(element, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined),
+            );
+        }
+        Ok(Some(self.track(RuntimeValue::Array(Rc::new(
+            RefCell::new(JsArray::new(values)),
+        )))))
+    }
+
+    fn identifier(
+        &mut self,
+        name: &str,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let found = RefCell::borrow(&env).lookup(name);
+        Ok(Some(self.variable_value(name, found)?))
+    }
+
+    // 前置の場合は更新後の値、後置の場合は数値に変換した更新前の値を返す
+    fn update_expression(
+        &mut self,
+        operator: char,
+        prefix: bool,
+        argument: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        self.update(argument, env, |runtime, old| {
+            let old = RuntimeValue::Number(runtime.to_number(old)?);
+            let new_value = match operator {
+                '+' => old.clone() + RuntimeValue::Number(1.0),
+                _ => old.clone() - RuntimeValue::Number(1.0),
+            };
+            let result = if prefix { new_value.clone() } else { old };
+            Ok((new_value, result))
+        })
+    }
+
+    fn return_statement(
+        &mut self,
+        argument: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let result = self.eval(argument, env)?;
+        self.completion = Completion::Return;
+        Ok(result)
+    }
+
+    fn while_statement(
+        &mut self,
+        test: &Option<Rc<Node>>,
+        body: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let labels = mem::take(&mut self.labels);
+        while self.eval_condition(test, env.clone())? {
+            let result = self.eval(body, env.clone())?;
+            if self.finish_iteration(&labels) {
+                return Ok(result);
             }
-            Node::StringLiteral(value) => Some(RuntimeValue::StringLiteral(value.to_string())),
-            Node::BlockStatement { body } => {
-                let mut result: Option<RuntimeValue> = None;
-                for stmt in body {
-                    result = self.eval(&stmt, env.clone());
-                }
+        }
+        Ok(None)
+    }
 
-                result
+    fn do_while_statement(
+        &mut self,
+        body: &Option<Rc<Node>>,
+        test: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let labels = mem::take(&mut self.labels);
+        loop {
+            let result = self.eval(body, env.clone())?;
+            if self.finish_iteration(&labels) {
+                return Ok(result);
             }
-            Node::ReturnStatement { argument } => return self.eval(&argument, env.clone()),
-            Node::FunctionDeclaration { id, params, body } => {
-                if let Some(RuntimeValue::StringLiteral(id)) = self.eval(&id, env.clone()) {
-                    let cloned_body = match body {
-                        Some(b) => Some(b.clone()),
-                        None => None,
-                    };
-                    self.functions
-                        .push(Function::new(id, params.to_vec(), cloned_body));
-                };
-                None
+            if !self.eval_condition(test, env.clone())? {
+                break;
             }
-            Node::CallExpression { callee, arguments } => {
-                let new_env = Rc::new(RefCell::new(Environment::new(Some(env))));
+        }
+        Ok(None)
+    }
+
+    fn arrow_function(
+        &mut self,
+        params: &[Option<Rc<Node>>],
+        body: &Option<Rc<Node>>,
+        is_async: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        if is_async {
+            return Err(self.unsupported_function());
+        }
+        Ok(Some(self.track(RuntimeValue::Function(Rc::new(
+            Function::new(None, params.to_vec(), body.clone(), Some(env), true),
+        )))))
+    }
+
+    fn class_declaration(
+        &mut self,
+        id: &Option<Rc<Node>>,
+        super_class: &Option<Rc<Node>>,
+        body: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let class = self.define_class(id, super_class, body, env.clone())?;
+        if let Some(Node::Identifier(name)) = id.as_deref() {
+            env.borrow_mut()
+                .initialize_variable(name.to_string(), class, true);
+        }
+        Ok(None)
+    }
+
+    fn throw_statement(
+        &mut self,
+        argument: &Option<Rc<Node>>,
+        position: Position,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let value = self.eval(argument, env)?.unwrap_or(RuntimeValue::Undefined);
+        self.position = Some(position);
+        Err(JsException::new(value, Some(position)))
+    }
+
+    // 単項演算子を評価する
+    fn unary_expression(
+        &mut self,
+        operator: &str,
+        argument: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        match (operator, argument.as_deref()) {
+            ("typeof", Some(Node::Identifier(name))) => {
+                let found = RefCell::borrow(&env).lookup(name);
+                let value = self.typeof_variable(name, found)?;
+                return Ok(Some(RuntimeValue::StringLiteral(
+                    value.type_of().to_string(),
+                )));
+            }
+            (
+                "delete",
+                Some(Node::MemberExpression {
+                    object,
+                    property,
+                    computed,
+                }),
+            ) => {
+                let target = self
+                    .eval(object, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined);
+                let key = self
+                    .property_key(property, *computed, env.clone())?
+                    .unwrap_or_default();
+                return Ok(Some(RuntimeValue::Boolean(self.delete(&target, &key)?)));
+            }
+            // 変数は削除できない
+            ("delete", Some(Node::Identifier(_))) => {
+                return Ok(Some(RuntimeValue::Boolean(false)));
+            }
+            _ => {}
+        }
+
+        let value = self.eval(argument, env)?.unwrap_or(RuntimeValue::Undefined);
+        let result = match operator {
+            "!" => RuntimeValue::Boolean(!value.is_truthy()),
+            "-" => RuntimeValue::Number(-self.to_number(value)?),
+            "+" => RuntimeValue::Number(self.to_number(value)?),
+            "~" => RuntimeValue::Number(!self.to_primitive(value, "number")?.to_int32() as f64),
+            "typeof" => RuntimeValue::StringLiteral(value.type_of().to_string()),
+            "void" => RuntimeValue::Undefined,
+            // 参照でない値のdeleteは常にtrue
+            _ => RuntimeValue::Boolean(true),
+        };
+        Ok(Some(result))
+    }
+
+    // a + b + cのように左辺に続く二項演算子の式を、再帰せずに一番左の値から順に評価する
+    fn binary_chain(
+        &mut self,
+        node: &Node,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let chain = node.binary_chain();
+        let mut value = match chain[chain.len() - 1].binary_left() {
+            Some(left) => self.eval(left, env.clone())?,
+            None => None,
+        };
+        for node in chain.into_iter().rev() {
+            value = self.binary_operation(node, value, env.clone())?;
+        }
+        Ok(value)
+    }
+
+    // 評価した左辺の値と、右辺を評価した値で演算する
+    fn binary_operation(
+        &mut self,
+        node: &Node,
+        left_value: Option<RuntimeValue>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        match node {
+            Node::AdditiveExpression {
+                operator, right, ..
+            }
+            | Node::MultiplicativeExpression {
+                operator, right, ..
+            } => self.arithmetic_expression(*operator, left_value, right, env),
+            Node::RelationalExpression {
+                operator, right, ..
+            } => self.relational_expression(operator, left_value, right, env),
+            Node::EqualityExpression {
+                operator, right, ..
+            } => self.equality_expression(operator, left_value, right, env),
+            Node::LogicalExpression {
+                operator, right, ..
+            } => {
+                let is_truthy = match &left_value {
+                    Some(value) => value.is_truthy(),
+                    None => false,
+                };
+
+                // 左辺で結果が決まる場合は右辺を評価せずに左辺の値を返す
+                match operator.as_str() {
+                    "&&" if !is_truthy => Ok(left_value),
+                    "||" if is_truthy => Ok(left_value),
+                    "&&" | "||" => self.eval(right, env),
+                    _ => Ok(None),
+                }
+            }
+            Node::BinaryExpression {
+                operator, right, ..
+            } => self.binary_expression(operator, left_value, right, env),
+            _ => Ok(None),
+        }
+    }
+
+    // + - * / %を評価する。どちらかの値がない場合は値を返さない
+    fn arithmetic_expression(
+        &mut self,
+        operator: char,
+        left_value: Option<RuntimeValue>,
+        right: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let left_value = match left_value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let right_value = match self.eval(right, env)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        match operator {
+            '+' | '-' | '*' | '/' | '%' => Ok(Some(self.operate(
+                &operator.to_string(),
+                left_value,
+                right_value,
+            )?)),
+            _ => Ok(None),
+        }
+    }
+
+    // **、ビット演算子、シフト演算子を評価する
+    fn binary_expression(
+        &mut self,
+        operator: &str,
+        left_value: Option<RuntimeValue>,
+        right: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let right_value = self.eval(right, env)?;
+        Ok(Some(self.operate(
+            operator,
+            left_value.unwrap_or(RuntimeValue::Undefined),
+            right_value.unwrap_or(RuntimeValue::Undefined),
+        )?))
+    }
+
+    // テンプレートリテラルを評価する。式の値は文字列に変換して連結する
+    fn template_literal(
+        &mut self,
+        quasis: &[String],
+        expressions: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let mut result = quasis[0].to_string();
+        for (expression, quasi) in expressions.iter().zip(&quasis[1..]) {
+            let value = self
+                .eval(expression, env.clone())?
+                .unwrap_or(RuntimeValue::Undefined);
+            result.push_str(&self.to_js_string(value)?);
+            result.push_str(quasi);
+        }
+        Ok(Some(RuntimeValue::StringLiteral(result)))
+    }
+
+    // タグ付きテンプレートの文字列の配列を作る。raw配列をrawプロパティに持つ
+    fn template_object(&mut self, cooked: &[String], raw: &[String]) -> RuntimeValue {
+        let strings = |strings: &[String]| {
+            strings
+                .iter()
+                .map(|s| RuntimeValue::StringLiteral(s.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let raw = self.track(RuntimeValue::Array(Rc::new(RefCell::new(JsArray::new(
+            strings(raw),
+        )))));
+        let object = self.track(RuntimeValue::Array(Rc::new(RefCell::new(JsArray::new(
+            strings(cooked),
+        )))));
+        object.set_property("raw", raw);
+        object
+    }
+
+    // <、>、<=、>=、instanceof、inを評価する
+    fn relational_expression(
+        &mut self,
+        operator: &str,
+        left_value: Option<RuntimeValue>,
+        right: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let left_value = match left_value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let right_value = match self.eval(right, env.clone())? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        if operator == "instanceof" {
+            let result = self.instance_of(&left_value, &right_value)?;
+            return Ok(Some(RuntimeValue::Boolean(result)));
+        }
+        if operator == "in" {
+            let result = self.has_property(&right_value, &left_value.to_string())?;
+            return Ok(Some(RuntimeValue::Boolean(result)));
+        }
+
+        let ordering = self.compare(left_value, right_value)?;
+        let result = match (operator, ordering) {
+            // 比較できない場合は常にfalse
+            (_, None) => false,
+            ("<", Some(o)) => o == Ordering::Less,
+            (">", Some(o)) => o == Ordering::Greater,
+            ("<=", Some(o)) => o != Ordering::Greater,
+            (">=", Some(o)) => o != Ordering::Less,
+            _ => return Ok(None),
+        };
+        Ok(Some(RuntimeValue::Boolean(result)))
+    }
+
+    // ==、!=、===、!==を評価する
+    fn equality_expression(
+        &mut self,
+        operator: &str,
+        left_value: Option<RuntimeValue>,
+        right: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let right_value = self.eval(right, env.clone())?;
+        let (left_value, right_value) = match (left_value, right_value) {
+            (Some(l), Some(r)) => (l, r),
+            (l, r) => {
+                // 値がない場合は、両方とも値がない場合だけ等しい
+                let equal = l.is_none() && r.is_none();
+                let negate = operator.starts_with('!');
+                return Ok(Some(RuntimeValue::Boolean(equal != negate)));
+            }
+        };
+
+        let result = match operator {
+            "==" => self.loosely_equals(left_value, right_value)?,
+            "!=" => !self.loosely_equals(left_value, right_value)?,
+            "===" => left_value == right_value,
+            "!==" => left_value != right_value,
+            _ => return Ok(None),
+        };
+        Ok(Some(RuntimeValue::Boolean(result)))
+    }
+
+    // =と+=のような複合代入を評価する
+    fn assignment_expression(
+        &mut self,
+        operator: &str,
+        left: &Option<Rc<Node>>,
+        right: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        // 複合代入は現在の値と右辺を演算して代入する。代入式の値は'='と同じくNone
+        if operator != "=" {
+            self.update(left, env.clone(), |runtime, old| {
+                let right = runtime
+                    .eval(right, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined);
+                let new_value = runtime.operate(operator, old, right)?;
+                Ok((new_value.clone(), new_value))
+            })?;
+            return Ok(None);
+        }
+
+        // 変数の再割当て
+        if let Some(node) = left {
+            if let Node::Identifier(id) = node.borrow() {
+                let new_value = self.eval(right, env.clone())?;
+                let result = env.borrow_mut().update_variable(id.to_string(), new_value);
+                if let Err((name, message)) = result {
+                    return Err(self.throw_error(name, message));
+                }
+                return Ok(None);
+            }
+
+            // オブジェクトや配列のプロパティへの代入
+            if let Node::MemberExpression {
+                object,
+                property,
+                computed,
+            } = node.borrow()
+            {
+                let target = self
+                    .eval(object, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined);
+                let key = match self.property_key(property, *computed, env.clone())? {
+                    Some(key) => key,
+                    None => return Ok(None),
+                };
+                let new_value = self
+                    .eval(right, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined);
+                self.put(&target, &key, new_value)?;
+                return Ok(None);
+            }
+        }
+
+        Ok(None)
+    }
+
+    // オブジェクトリテラルを評価する
+    fn object_expression(
+        &mut self,
+        properties: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let object = self.new_object();
+        for property in properties.iter().flatten() {
+            match property.borrow() {
+                Node::Property { key, value } => {
+                    let value = self
+                        .eval(value, env.clone())?
+                        .unwrap_or(RuntimeValue::Undefined);
+                    object.set_property(key, value);
+                }
+                Node::MethodDefinition {
+                    key, value, kind, ..
+                } => match Self::method_function(key, value, env.clone()) {
+                    Some(function) => self.define_method(&object, key, function, *kind),
+                    None => return Err(self.unsupported_function()),
+                },
+                _ => {}
+            }
+        }
+        Ok(Some(object))
+    }
+
+    // var、let、constの宣言を実行する
+    fn variable_declaration(
+        &mut self,
+        kind: DeclarationKind,
+        declarations: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        for declaration in declarations.iter().flatten() {
+            if let Node::VariableDeclarator { id, init } = declaration.borrow() {
+                let name = match id.as_deref() {
+                    Some(Node::Identifier(name)) => name.to_string(),
+                    _ => continue,
+                };
+
+                match kind {
+                    DeclarationKind::Var => {
+                        // 初期値がない場合、すでに宣言されている変数の値は変えない
+                        let value = match init {
+                            Some(_) => Some(
+                                self.eval(init, env.clone())?
+                                    .unwrap_or(RuntimeValue::Undefined),
+                            ),
+                            None => None,
+                        };
+                        Environment::declare_var(&env, name, value);
+                    }
+                    DeclarationKind::Let | DeclarationKind::Const => {
+                        let value = self
+                            .eval(init, env.clone())?
+                            .unwrap_or(RuntimeValue::Undefined);
+                        env.borrow_mut().initialize_variable(
+                            name,
+                            value,
+                            kind == DeclarationKind::Let,
+                        );
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // ブロックの中の文を、新しいスコープで順に実行する
+    fn block_statement(
+        &mut self,
+        body: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        // letとconstで宣言された変数はブロックの中だけで使える
+        let env = self.new_environment(Environment::new(Some(env)));
+        self.hoist_declarations(body.iter().flatten(), env.clone())?;
+
+        let mut result: Option<RuntimeValue> = None;
+        for stmt in body {
+            result = self.eval(stmt, env.clone())?;
+            // return、break、continueが実行された場合、残りの文は実行しない
+            if self.completion != Completion::Normal {
+                break;
+            }
+        }
+
+        Ok(result)
+    }
+
+    // ラベルの付いた文を実行する
+    fn labeled_statement(
+        &mut self,
+        label: &str,
+        body: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        match body.as_deref() {
+            // ラベルはループとswitch文が受け取る
+            Some(
+                Node::WhileStatement { .. }
+                | Node::DoWhileStatement { .. }
+                | Node::ForStatement { .. }
+                | Node::ForInStatement { .. }
+                | Node::ForOfStatement { .. }
+                | Node::SwitchStatement { .. }
+                | Node::LabeledStatement { .. },
+            ) => self.labels.push(label.to_string()),
+            _ => self.labels.clear(),
+        }
+        let result = self.eval(body, env.clone())?;
+        // ループ以外の文に付けられたラベルは、breakでその文から抜けるためだけに使う
+        if self.completion == Completion::Break(Some(label.to_string())) {
+            self.completion = Completion::Normal;
+        }
+        Ok(result)
+    }
+
+    // for文を実行する
+    fn for_statement(
+        &mut self,
+        init: &Option<Rc<Node>>,
+        test: &Option<Rc<Node>>,
+        update: &Option<Rc<Node>>,
+        body: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let labels = mem::take(&mut self.labels);
+        // 初期化式でletやconstを使った場合、ループの中だけで使える変数になる
+        let mut env = self.new_environment(Environment::new(Some(env)));
+        if let Some(init) = init {
+            self.hoist_declarations([init].into_iter(), env.clone())?;
+        }
+        self.eval(init, env.clone())?;
+        // 条件式が省略された場合は常にtrueとして扱う
+        while test.is_none() || self.eval_condition(test, env.clone())? {
+            let result = self.eval(body, env.clone())?;
+            if self.finish_iteration(&labels) {
+                return Ok(result);
+            }
+            // クロージャが繰り返しごとに別の変数を捕捉するように、環境を複製する
+            let next_env = RefCell::borrow(&env).clone();
+            env = self.new_environment(next_env);
+            self.eval(update, env.clone())?;
+        }
+        Ok(None)
+    }
+
+    // 関数宣言を評価する
+    fn function_declaration(
+        &mut self,
+        id: &Option<Rc<Node>>,
+        params: &[Option<Rc<Node>>],
+        body: &Option<Rc<Node>>,
+        is_suspendable: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        if is_suspendable {
+            return Err(self.unsupported_function());
+        }
+        // 関数は宣言されたスコープの変数として定義する
+        if let Some(Node::Identifier(name)) = id.as_deref() {
+            let function = Function::new(
+                Some(name.to_string()),
+                params.to_vec(),
+                body.clone(),
+                Some(env.clone()),
+                false,
+            );
+            let function = self.new_function(function);
+            env.borrow_mut().set_variable(name.to_string(), function);
+        }
+        Ok(None)
+    }
+
+    // 関数式を評価する
+    fn function_expression(
+        &mut self,
+        id: &Option<Rc<Node>>,
+        params: &[Option<Rc<Node>>],
+        body: &Option<Rc<Node>>,
+        is_suspendable: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        if is_suspendable {
+            return Err(self.unsupported_function());
+        }
+        let name = match id.as_deref() {
+            Some(Node::Identifier(name)) => Some(name.to_string()),
+            _ => None,
+        };
+
+        // 名前付きの関数式は、関数の中から自身の名前で参照できる
+        let function_env = match &name {
+            Some(_) => self.new_environment(Environment::new(Some(env))),
+            None => env,
+        };
+        let function = self.new_function(Function::new(
+            name.clone(),
+            params.to_vec(),
+            body.clone(),
+            Some(function_env.clone()),
+            false,
+        ));
+        if let Some(name) = name {
+            function_env
+                .borrow_mut()
+                .set_variable(name, function.clone());
+        }
+        Ok(Some(function))
+    }
+
+    // newで関数をコンストラクタとして呼び出す
+    fn new_expression(
+        &mut self,
+        callee: &Option<Rc<Node>>,
+        arguments: &[Option<Rc<Node>>],
+        position: Position,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        self.position = Some(position);
+        let constructor = self
+            .eval(callee, env.clone())?
+            .unwrap_or(RuntimeValue::Undefined);
+        let mut args = Vec::new();
+        for argument in arguments {
+            args.push(
+                self.eval(argument, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined),
+            );
+        }
+
+        self.position = Some(position);
+        if !is_constructor(&constructor) {
+            return Err(self.throw_error(
+                "TypeError",
+                format!("{} is not a constructor", callee_name(callee)),
+            ));
+        }
+        Ok(Some(self.construct(&constructor, args)?))
+    }
+
+    // 関数呼び出しを評価する
+    fn call_expression(
+        &mut self,
+        callee: &Option<Rc<Node>>,
+        arguments: &[Option<Rc<Node>>],
+        position: Position,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        self.position = Some(position);
+        let this_value = || match RefCell::borrow(&env).lookup("this") {
+            Some(Some(this)) => this,
+            _ => RuntimeValue::Undefined,
+        };
+        // obj.method()のように呼ばれた場合、objがthisになる
+        let (callee_value, this) = match callee.as_deref() {
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+            }) => {
+                let object_value = match self.eval(object, env.clone())? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+                let key = match self.property_key(property, *computed, env.clone())? {
+                    Some(key) => key,
+                    None => return Ok(None),
+                };
+                let method = self.get(&object_value, &key)?;
+                // super.method()の場合は、現在のthisのままメソッドを呼び出す
+                match object.as_deref() {
+                    Some(Node::Super) => (method, this_value()),
+                    _ => (method, object_value),
+                }
+            }
+            // super()は親クラスのコンストラクタを現在のthisで呼び出す
+            // 親クラスは、メソッドが定義されたオブジェクトのプロトタイプのconstructorとする
+            Some(Node::Super) => {
+                let base = self.eval(callee, env.clone())?;
+                let constructor = base
+                    .map(|b| b.get_property("constructor"))
+                    .unwrap_or(RuntimeValue::Undefined);
+                (constructor, this_value())
+            }
+            _ => match self.eval(callee, env.clone())? {
+                Some(value) => (value, RuntimeValue::Undefined),
+                None => return Ok(None),
+            },
+        };
+
+        // 引数は呼び出し元のスコープで評価する
+        let mut args = Vec::new();
+        for argument in arguments {
+            args.push(
+                self.eval(argument, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined),
+            );
+        }
+
+        // 引数の中で別の関数が呼ばれた場合があるため、位置を戻す
+        self.position = Some(position);
+        let function = match callee_value {
+            RuntimeValue::Function(f) => f,
+            _ => {
+                return Err(self.throw_error(
+                    "TypeError",
+                    format!("{} is not a function", callee_name(callee)),
+                ))
+            }
+        };
+        let is_super_call = matches!(callee.as_deref(), Some(Node::Super));
+        if function.is_class_constructor && !is_super_call {
+            return Err(self.throw_error(
+                "TypeError",
+                format!(
+                    "class constructor {} cannot be invoked without 'new'",
+                    callee_name(callee)
+                ),
+            ));
+        }
+
+        Ok(Some(self.call_function(&function, this, args)?))
+    }
+
+    // try文を実行する
+    fn try_statement(
+        &mut self,
+        block: &Option<Rc<Node>>,
+        param: &Option<Rc<Node>>,
+        handler: &Option<Rc<Node>>,
+        finalizer: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let mut result = self.eval(block, env.clone());
+
+        if let (Err(exception), Some(_)) = (&result, handler) {
+            // catch節の仮引数に投げられた値を割り当てる
+            let catch_env = self.new_environment(Environment::new(Some(env.clone())));
+            if let Some(Node::Identifier(name)) = param.as_deref() {
+                catch_env
+                    .borrow_mut()
+                    .set_variable(name.to_string(), exception.value());
+            }
+            result = self.eval(handler, catch_env);
+        }
+
+        if finalizer.is_some() {
+            // finally節はreturnやbreakが実行された場合でも実行する
+            let completion = mem::replace(&mut self.completion, Completion::Normal);
+            self.eval(finalizer, env.clone())?;
+            // finally節でreturnやbreakが実行された場合はそちらを優先する
+            if self.completion != Completion::Normal {
+                return Ok(None);
+            }
+            self.completion = completion;
+        }
+
+        result
+    }
+
+    // ASTの評価は関数を中断できないため、async関数とジェネレータはVMだけで実行できる
+    fn unsupported_function(&self) -> JsException {
+        self.throw_error(
+            "SyntaxError",
+            "async functions and generators are not supported by the interpreter".to_string(),
+        )
+    }
+
+    // 関数の値を呼び出す。関数でない場合はTypeError
+    pub fn call(
+        &mut self,
+        function: &RuntimeValue,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> Result<RuntimeValue, JsException> {
+        match function {
+            RuntimeValue::Function(f) => self.call_function(f, this, args),
+            _ => Err(self.throw_error("TypeError", format!("{} is not a function", function))),
+        }
+    }
+
+    // 関数を呼び出す。呼び出しが深すぎる場合はRangeErrorにする
+    fn call_function(
+        &mut self,
+        function: &Function,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> Result<RuntimeValue, JsException> {
+        self.check_stack()?;
+        self.call_depth += 1;
+        let result = self.call_function_body(function, this, args);
+        self.call_depth -= 1;
+        if self.call_depth == 0 {
+            self.reset_stack_base();
+        }
+        result
+    }
+
+    // 呼び出しの深さとスタックの使用量が上限を超えていないか確かめる
+    fn check_stack(&mut self) -> Result<(), JsException> {
+        let marker = 0_u8;
+        let address = core::hint::black_box(&marker) as *const u8 as usize;
+        let base = *self.stack_base.get_or_insert(address);
+        if self.call_depth >= MAX_CALL_DEPTH || base.saturating_sub(address) > self.stack_limit {
+            return Err(
+                self.throw_error("RangeError", "Maximum call stack size exceeded".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    // 実行が終わったら、次の実行で改めてスタックの基準を測る
+    pub fn reset_stack_base(&mut self) {
+        if self.call_depth == 0 {
+            self.stack_base = None;
+        }
+    }
+
+    // 関数が定義されたスコープの内側に新しいスコープを作り、引数を割り当てる
+    fn call_function_body(
+        &mut self,
+        function: &Function,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> Result<RuntimeValue, JsException> {
+        if let Some(native) = function.native {
+            // ネイティブ関数が持つ値はGCから見えないため、実行中はGCを実行しない
+            self.active_frames += 1;
+            let result = native(self, this, args);
+            self.active_frames -= 1;
+            return result;
+        }
+        if let Some(closure) = function.closure {
+            let captures = RefCell::borrow(&function.captures).clone();
+            self.active_frames += 1;
+            let result = closure(self, &captures, this, args);
+            self.active_frames -= 1;
+            return result;
+        }
+        if let Some(code) = function.code.clone() {
+            return self.call_compiled(function, code, this, args);
+        }
+
+        // 派生クラスのconstructorが省略された場合、引数をそのまま親クラスのコンストラクタに渡す
+        if function.is_class_constructor && function.body.is_none() {
+            if let Some(parent) = RefCell::borrow(&function.properties).prototype() {
+                self.call(&parent, this, args)?;
+            }
+            return Ok(RuntimeValue::Undefined);
+        }
+
+        let mut function_env = Environment::new_function_scope(function.env.clone());
+        // アロー関数は定義されたスコープのthisとsuperを使う
+        if !function.is_arrow {
+            function_env.set_variable("this".to_string(), this);
+            if let Some(home) = &function.home_object {
+                let base = self.prototype_of(home).unwrap_or(RuntimeValue::Null);
+                function_env.set_variable("super".to_string(), base);
+            }
+        }
+
+        // 足りない引数はundefined、余った引数は無視する
+        let mut args = args.into_iter();
+        for param in &function.params {
+            if let Some(Node::Identifier(name)) = param.as_deref() {
+                let value = args.next().unwrap_or(RuntimeValue::Undefined);
+                function_env.set_variable(name.to_string(), value);
+            }
+        }
+
+        let function_env = self.new_environment(function_env);
+        let result = self.eval(&function.body, function_env);
+        let is_returned = self.completion == Completion::Return;
+        self.completion = Completion::Normal;
+
+        // return文が実行されなかった場合はundefined
+        match result? {
+            Some(value) if is_returned => Ok(value),
+            _ => Ok(RuntimeValue::Undefined),
+        }
+    }
+
+    // ブロックの実行前に、関数宣言とletとconstで宣言された変数をスコープに追加する
+    // letとconstで宣言された変数は、宣言が実行されるまで初期化されない
+    fn hoist_declarations<'a>(
+        &mut self,
+        body: impl Iterator<Item = &'a Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<(), JsException> {
+        for node in body {
+            match node.borrow() {
+                Node::FunctionDeclaration { .. } => {
+                    self.eval(&Some(node.clone()), env.clone())?;
+                }
+                // クラスもletと同じく、宣言が実行されるまで使えない
+                Node::ClassDeclaration { id, .. } => {
+                    if let Some(Node::Identifier(name)) = id.as_deref() {
+                        env.borrow_mut().declare_lexical(name.to_string());
+                    }
+                }
+                Node::VariableDeclaration { kind, declarations } => {
+                    if *kind == DeclarationKind::Var {
+                        continue;
+                    }
+                    for declaration in declarations.iter().flatten() {
+                        if let Node::VariableDeclarator { id, .. } = declaration.borrow() {
+                            if let Some(Node::Identifier(name)) = id.as_deref() {
+                                env.borrow_mut().declare_lexical(name.to_string());
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // functionで定義された関数はコンストラクタとして使えるため、prototypeプロパティを持つ
+    // ジェネレータ関数のprototypeは、呼び出して作られるジェネレータが継承する
+    pub fn new_function(&self, function: Function) -> RuntimeValue {
+        let is_arrow = function.is_arrow;
+        let (is_async, is_generator) = match &function.code {
+            Some(code) => (code.is_async, code.is_generator),
+            None => (false, false),
+        };
+        let value = self.track(RuntimeValue::Function(Rc::new(function)));
+        if is_generator {
+            let prototype = self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+                JsObject::with_prototype(Some(self.generator_prototype.clone())),
+            ))));
+            value.set_property("prototype", prototype);
+        } else if !is_arrow && !is_async {
+            let prototype = self.new_object();
+            prototype.set_property("constructor", value.clone());
+            value.set_property("prototype", prototype);
+        }
+        value
+    }
+
+    // クラスを定義し、コンストラクタ関数を返す
+    fn define_class(
+        &mut self,
+        id: &Option<Rc<Node>>,
+        super_class: &Option<Rc<Node>>,
+        body: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<RuntimeValue, JsException> {
+        let name = match id.as_deref() {
+            Some(Node::Identifier(name)) => Some(name.to_string()),
+            _ => None,
+        };
+        let parent = match super_class {
+            Some(_) => Some(
+                self.eval(super_class, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined),
+            ),
+            None => None,
+        };
+
+        // クラスの中では、クラス自身の名前で参照できる
+        let class_env = self.new_environment(Environment::new(Some(env)));
+        let constructor = body
+            .iter()
+            .flatten()
+            .find_map(|member| match member.borrow() {
+                Node::MethodDefinition {
+                    key,
+                    value,
+                    kind: MethodKind::Constructor,
+                    ..
+                } => Self::method_function(key, value, class_env.clone()),
+                _ => None,
+            });
+        let class = self.create_class(name.clone(), constructor, parent)?;
+        let prototype = class.get_property("prototype");
+
+        for member in body.iter().flatten() {
+            if let Node::MethodDefinition {
+                key,
+                value,
+                kind,
+                is_static,
+            } = member.borrow()
+            {
+                if *kind == MethodKind::Constructor {
+                    continue;
+                }
+                let home = if *is_static {
+                    class.clone()
+                } else {
+                    prototype.clone()
+                };
+                match Self::method_function(key, value, class_env.clone()) {
+                    Some(function) => self.define_method(&home, key, function, *kind),
+                    None => return Err(self.unsupported_function()),
+                }
+            }
+        }
+
+        if let Some(name) = name {
+            class_env
+                .borrow_mut()
+                .initialize_variable(name, class.clone(), false);
+        }
+        Ok(class)
+    }
+
+    // クラスのコンストラクタ関数を作る。constructorが省略された場合は、親クラスを呼び出すだけになる
+    pub fn create_class(
+        &mut self,
+        name: Option<String>,
+        constructor: Option<Function>,
+        super_class: Option<RuntimeValue>,
+    ) -> Result<RuntimeValue, JsException> {
+        // インスタンスのプロトタイプが継承するオブジェクト。extendsが省略された場合はObject.prototype
+        let mut parent = None;
+        let mut prototype_parent = Some(self.object_prototype.clone());
+        if let Some(value) = super_class {
+            match value.get_property("prototype") {
+                _ if value == RuntimeValue::Null => prototype_parent = None,
+                prototype @ RuntimeValue::Object(_) if is_constructor(&value) => {
+                    parent = Some(value);
+                    prototype_parent = Some(prototype);
+                }
+                RuntimeValue::Null if is_constructor(&value) => {
+                    parent = Some(value);
+                    prototype_parent = None;
+                }
+                _ => {
+                    return Err(self.throw_error(
+                        "TypeError",
+                        format!("class extends value {} is not a constructor or null", value),
+                    ))
+                }
+            }
+        }
+        let prototype = self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+            JsObject::with_prototype(prototype_parent),
+        ))));
+
+        let mut function =
+            constructor.unwrap_or_else(|| Function::new(name, Vec::new(), None, None, false));
+        function.is_class_constructor = true;
+        function.home_object = Some(prototype.clone());
+        // 静的メソッドは親クラスから継承する
+        function.properties.borrow_mut().set_prototype(parent);
+        let class = self.track(RuntimeValue::Function(Rc::new(function)));
+        class.set_property("prototype", prototype.clone());
+        prototype.set_property("constructor", class.clone());
+        Ok(class)
+    }
+
+    // メソッドの関数式から関数を作る。asyncとジェネレータのメソッドの場合はNone
+    fn method_function(
+        key: &str,
+        value: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<Function> {
+        match value.as_deref() {
+            Some(Node::FunctionExpression {
+                params,
+                body,
+                is_async: false,
+                is_generator: false,
+                ..
+            }) => Some(Function::new(
+                Some(key.to_string()),
+                params.to_vec(),
+                body.clone(),
+                Some(env),
+                false,
+            )),
+            _ => None,
+        }
+    }
+
+    // メソッド、ゲッター、セッターをオブジェクトに定義する
+    pub fn define_method(
+        &mut self,
+        home: &RuntimeValue,
+        key: &str,
+        mut function: Function,
+        kind: MethodKind,
+    ) {
+        function.home_object = Some(home.clone());
+        let function = self.track(RuntimeValue::Function(Rc::new(function)));
+
+        match kind {
+            MethodKind::Get => home.define_accessor(key, Some(function), None),
+            MethodKind::Set => home.define_accessor(key, None, Some(function)),
+            MethodKind::Method | MethodKind::Constructor => home.set_property(key, function),
+        }
+    }
+
+    // new F(args)を実行する。thisはF.prototypeを継承する新しいオブジェクトになる
+    pub fn construct(
+        &mut self,
+        constructor: &RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> Result<RuntimeValue, JsException> {
+        let function = match constructor {
+            RuntimeValue::Function(f) if is_constructor(constructor) => f.clone(),
+            _ => {
+                return Err(
+                    self.throw_error("TypeError", format!("{} is not a constructor", constructor))
+                )
+            }
+        };
+
+        let prototype = match constructor.get_property("prototype") {
+            prototype @ RuntimeValue::Object(_) => prototype,
+            _ => self.object_prototype.clone(),
+        };
+        let this = self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+            JsObject::with_prototype(Some(prototype)),
+        ))));
+
+        let result = self.call_function(&function, this.clone(), args)?;
+        // コンストラクタがオブジェクトを返した場合は、そのオブジェクトがnewの結果になる
+        if result.is_object() {
+            Ok(result)
+        } else {
+            Ok(this)
+        }
+    }
+
+    // value instanceof constructor。valueのプロトタイプチェーンにconstructor.prototypeがあるか
+    pub fn instance_of(
+        &mut self,
+        value: &RuntimeValue,
+        constructor: &RuntimeValue,
+    ) -> Result<bool, JsException> {
+        if !constructor.is_function() {
+            return Err(self.throw_error(
+                "TypeError",
+                "right-hand side of 'instanceof' is not callable".to_string(),
+            ));
+        }
+        if !value.is_object() {
+            return Ok(false);
+        }
+
+        let prototype = self.get(constructor, "prototype")?;
+        let mut current = self.prototype_of(value);
+        while let Some(p) = current {
+            if p == prototype {
+                return Ok(true);
+            }
+            current = self.prototype_of(&p);
+        }
+        Ok(false)
+    }
+
+    // 値のプロトタイプ。プリミティブな値の場合は、その型の組み込みのプロトタイプ
+    pub fn prototype_of(&self, value: &RuntimeValue) -> Option<RuntimeValue> {
+        match value {
+            RuntimeValue::Object(object) => RefCell::borrow(object).prototype(),
+            // ネイティブ関数などプロトタイプが設定されていない関数はFunction.prototypeを継承する
+            RuntimeValue::Function(function) => Some(
+                RefCell::borrow(&function.properties)
+                    .prototype()
+                    .unwrap_or_else(|| self.function_prototype.clone()),
+            ),
+            RuntimeValue::Array(_) => Some(self.array_prototype.clone()),
+            RuntimeValue::StringLiteral(_) => Some(self.string_prototype.clone()),
+            RuntimeValue::Number(_) => Some(self.number_prototype.clone()),
+            RuntimeValue::Boolean(_) => Some(self.object_prototype.clone()),
+            RuntimeValue::Null | RuntimeValue::Undefined => None,
+        }
+    }
+
+    // プロトタイプチェーンをたどってプロパティを探す
+    fn find_property(&self, value: &RuntimeValue, key: &str) -> Option<Property> {
+        match value.own_property(key) {
+            Some(property) => Some(property),
+            None => {
+                let prototype = self.prototype_of(value)?;
+                self.find_property(&prototype, key)
+            }
+        }
+    }
+
+    // プロパティの値を返す。ゲッターの場合は呼び出した結果を返す
+    pub fn get(&mut self, value: &RuntimeValue, key: &str) -> Result<RuntimeValue, JsException> {
+        if matches!(value, RuntimeValue::Null | RuntimeValue::Undefined) {
+            return Err(self.throw_error(
+                "TypeError",
+                format!("cannot read properties of {} (reading '{}')", value, key),
+            ));
+        }
+
+        match self.find_property(value, key) {
+            Some(Property::Data(v)) => Ok(v),
+            Some(Property::Accessor {
+                get: Some(getter), ..
+            }) => self.call(&getter, value.clone(), Vec::new()),
+            _ => Ok(RuntimeValue::Undefined),
+        }
+    }
+
+    // プロパティに値を設定する。セッターの場合は値を引数にして呼び出す
+    pub fn put(
+        &mut self,
+        target: &RuntimeValue,
+        key: &str,
+        value: RuntimeValue,
+    ) -> Result<(), JsException> {
+        if matches!(target, RuntimeValue::Null | RuntimeValue::Undefined) {
+            return Err(self.throw_error(
+                "TypeError",
+                format!("cannot set properties of {} (setting '{}')", target, key),
+            ));
+        }
+
+        if let Some(Property::Accessor { set, .. }) = self.find_property(target, key) {
+            // セッターがない場合は何もしない
+            if let Some(setter) = set {
+                self.call(&setter, target.clone(), Vec::from([value]))?;
+            }
+            return Ok(());
+        }
+        if let RuntimeValue::Array(_) = target {
+            self.check_array_length(key, &value)?;
+        }
+        target.set_property(key, value);
+        Ok(())
+    }
+
+    // inで、プロトタイプチェーンも含めてプロパティを持つかを調べる
+    pub fn has_property(&self, target: &RuntimeValue, key: &str) -> Result<bool, JsException> {
+        if !target.is_object() {
+            return Err(self.throw_error(
+                "TypeError",
+                format!(
+                    "cannot use 'in' operator to search for '{}' in {}",
+                    key, target
+                ),
+            ));
+        }
+        Ok(self.find_property(target, key).is_some())
+    }
+
+    // deleteでプロパティを削除する
+    pub fn delete(&mut self, target: &RuntimeValue, key: &str) -> Result<bool, JsException> {
+        if matches!(target, RuntimeValue::Null | RuntimeValue::Undefined) {
+            return Err(self.throw_error(
+                "TypeError",
+                format!(
+                    "cannot delete properties of {} (deleting '{}')",
+                    target, key
+                ),
+            ));
+        }
+        Ok(target.delete_property(key))
+    }
+
+    // typeofの対象の変数。宣言されていない場合はReferenceErrorにせずundefinedとして扱う
+    pub fn typeof_variable(
+        &self,
+        name: &str,
+        found: Option<Option<RuntimeValue>>,
+    ) -> Result<RuntimeValue, JsException> {
+        match found {
+            None => Ok(RuntimeValue::Undefined),
+            found => self.variable_value(name, found),
+        }
+    }
+
+    // 配列の添字やlengthへの代入で、長さが不正な値や上限を超える値にならないかを確かめる
+    fn check_array_length(&self, key: &str, value: &RuntimeValue) -> Result<(), JsException> {
+        let is_valid = if key == "length" {
+            array_length(value).is_some()
+        } else {
+            array_index(key).map_or(true, |index| index < MAX_ARRAY_LENGTH)
+        };
+        if is_valid {
+            Ok(())
+        } else {
+            Err(self.throw_error("RangeError", "invalid array length".to_string()))
+        }
+    }
+
+    // ToPrimitive。オブジェクトの場合はvalueOfとtoStringを順に呼び出し、最初に返されたプリミティブな値にする
+    // hintが"string"の場合はtoStringを先に呼ぶ。どちらもプリミティブな値を返さない場合はTypeError
+    pub fn to_primitive(
+        &mut self,
+        value: RuntimeValue,
+        hint: &str,
+    ) -> Result<RuntimeValue, JsException> {
+        if !value.is_object() {
+            return Ok(value);
+        }
+
+        let methods = if hint == "string" {
+            ["toString", "valueOf"]
+        } else {
+            ["valueOf", "toString"]
+        };
+        for name in methods {
+            let method = self.get(&value, name)?;
+            if !method.is_function() {
+                continue;
+            }
+            let result = self.call(&method, value.clone(), Vec::new())?;
+            if !result.is_object() {
+                return Ok(result);
+            }
+        }
+        Err(self.throw_error(
+            "TypeError",
+            "cannot convert object to primitive value".to_string(),
+        ))
+    }
+
+    // ToNumber。オブジェクトはvalueOfを先に呼び出して変換する
+    pub fn to_number(&mut self, value: RuntimeValue) -> Result<f64, JsException> {
+        Ok(self.to_primitive(value, "number")?.to_number())
+    }
+
+    // ToString。オブジェクトはtoStringを先に呼び出して変換する
+    pub fn to_js_string(&mut self, value: RuntimeValue) -> Result<String, JsException> {
+        match self.to_primitive(value, "string")? {
+            RuntimeValue::StringLiteral(value) => Ok(value),
+            value => Ok(value.to_string()),
+        }
+    }
+
+    // ==による比較。オブジェクトとプリミティブな値を比べる場合は、オブジェクトをプリミティブな値に変換する
+    pub fn loosely_equals(
+        &mut self,
+        left: RuntimeValue,
+        right: RuntimeValue,
+    ) -> Result<bool, JsException> {
+        let is_nullish =
+            |v: &RuntimeValue| matches!(v, RuntimeValue::Null | RuntimeValue::Undefined);
+        let (left, right) = match (left.is_object(), right.is_object()) {
+            (true, false) if !is_nullish(&right) => (self.to_primitive(left, "default")?, right),
+            (false, true) if !is_nullish(&left) => {
+                let right = self.to_primitive(right, "default")?;
+                (left, right)
+            }
+            _ => (left, right),
+        };
+        Ok(left.loosely_equals(&right))
+    }
+
+    // < > <= >=による比較。両辺をプリミティブな値に変換してから比べる
+    pub fn compare(
+        &mut self,
+        left: RuntimeValue,
+        right: RuntimeValue,
+    ) -> Result<Option<Ordering>, JsException> {
+        let left = self.to_primitive(left, "number")?;
+        let right = self.to_primitive(right, "number")?;
+        Ok(left.compare(&right))
+    }
+
+    // 二項演算子で計算する。両辺をプリミティブな値に変換してから演算する
+    // +はどちらかが文字列になった場合に連結するため、hintを指定せずに変換する
+    pub fn operate(
+        &mut self,
+        operator: &str,
+        left: RuntimeValue,
+        right: RuntimeValue,
+    ) -> Result<RuntimeValue, JsException> {
+        let hint = if operator == "+" { "default" } else { "number" };
+        let left = self.to_primitive(left, hint)?;
+        let right = self.to_primitive(right, hint)?;
+        Ok(left.operate(operator, right))
+    }
+
+    // 配列の要素をToStringで文字列にしてseparatorでつなげる。nullとundefinedは空文字列になる
+    // 変換中の配列が要素に現れた場合は、循環参照として空文字列にする
+    pub fn join(
+        &mut self,
+        elements: &Rc<RefCell<JsArray>>,
+        separator: &str,
+    ) -> Result<String, JsException> {
+        let address = Rc::as_ptr(elements) as usize;
+        if self.joining.contains(&address) {
+            return Ok(String::new());
+        }
+        self.joining.push(address);
+        let result = self.join_elements(elements, separator);
+        self.joining.pop();
+        result
+    }
+
+    fn join_elements(
+        &mut self,
+        elements: &Rc<RefCell<JsArray>>,
+        separator: &str,
+    ) -> Result<String, JsException> {
+        let values = RefCell::borrow(elements).to_vec();
+        let mut parts = Vec::with_capacity(values.len());
+        for value in values {
+            parts.push(match value {
+                RuntimeValue::Null | RuntimeValue::Undefined => String::new(),
+                value => self.to_js_string(value)?,
+            });
+        }
+        Ok(parts.join(separator))
+    }
+
+    // for-inとfor-ofで繰り返す値。プロパティは列挙できるかを区別しないため、for-inは自身のキーだけを返す
+    pub fn create_iterator(
+        &mut self,
+        value: RuntimeValue,
+        is_for_in: bool,
+    ) -> Result<RuntimeValue, JsException> {
+        let state = if is_for_in {
+            let keys = match &value {
+                RuntimeValue::Object(object) => RefCell::borrow(object).keys(),
+                RuntimeValue::Function(function) => RefCell::borrow(&function.properties).keys(),
+                RuntimeValue::Array(elements) => RefCell::borrow(elements).keys(),
+                RuntimeValue::StringLiteral(s) => (0..s.encode_utf16().count())
+                    .map(|i| i.to_string())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            IteratorState::Keys(keys, 0)
+        } else {
+            match &value {
+                RuntimeValue::Array(_) | RuntimeValue::StringLiteral(_) => {
+                    IteratorState::Elements(value, 0)
+                }
+                RuntimeValue::Object(_) if self.get(&value, "next")?.is_function() => {
+                    IteratorState::Protocol(value)
+                }
+                _ => {
+                    return Err(self.throw_error("TypeError", format!("{} is not iterable", value)))
+                }
+            }
+        };
+        let mut object = JsObject::new();
+        object.set_slot(InternalSlot::Iterator(state));
+        Ok(self.track(RuntimeValue::Object(Rc::new(RefCell::new(object)))))
+    }
+
+    // 繰り返しの次の値。終わった場合はNone
+    pub fn iterator_next(
+        &mut self,
+        iterator: &RuntimeValue,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let state = match iterator {
+            RuntimeValue::Object(object) => match RefCell::borrow(object).slot() {
+                Some(InternalSlot::Iterator(state)) => state.clone(),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let (next, state) = match state {
+            IteratorState::Keys(keys, index) => (
+                keys.get(index).cloned().map(RuntimeValue::StringLiteral),
+                IteratorState::Keys(keys, index + 1),
+            ),
+            IteratorState::Elements(value, index) => (
+                match value.own_property(&index.to_string()) {
+                    Some(Property::Data(element)) => Some(element),
+                    _ => None,
+                },
+                IteratorState::Elements(value, index + 1),
+            ),
+            IteratorState::Protocol(object) => {
+                let next = self.get(&object, "next")?;
+                let result = self.call(&next, object.clone(), Vec::new())?;
+                if !matches!(result, RuntimeValue::Object(_)) {
+                    return Err(self.throw_error(
+                        "TypeError",
+                        format!("iterator result {} is not an object", result),
+                    ));
+                }
+                let value = if self.get(&result, "done")?.is_truthy() {
+                    None
+                } else {
+                    Some(self.get(&result, "value")?)
+                };
+                (value, IteratorState::Protocol(object))
+            }
+        };
+        if let RuntimeValue::Object(object) = iterator {
+            object.borrow_mut().set_slot(InternalSlot::Iterator(state));
+        }
+        Ok(next)
+    }
+
+    // breakやreturnで繰り返しを途中で抜ける場合、next()を持つオブジェクトのreturn()を呼び出す
+    pub fn iterator_close(&mut self, iterator: &RuntimeValue) -> Result<(), JsException> {
+        let object = match iterator {
+            RuntimeValue::Object(object) => match RefCell::borrow(object).slot() {
+                Some(InternalSlot::Iterator(IteratorState::Protocol(object))) => object.clone(),
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let close = self.get(&object, "return")?;
+        if !close.is_function() {
+            return Ok(());
+        }
+        let result = self.call(&close, object, Vec::new())?;
+        if !matches!(result, RuntimeValue::Object(_)) {
+            return Err(self.throw_error(
+                "TypeError",
+                format!("iterator result {} is not an object", result),
+            ));
+        }
+        Ok(())
+    }
+
+    // object.propertyの場合はプロパティ名、object[property]の場合は評価した値を文字列にしたもの
+    fn property_key(
+        &mut self,
+        property: &Option<Rc<Node>>,
+        computed: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<String>, JsException> {
+        if !computed {
+            if let Some(node) = property {
+                if let Node::Identifier(name) = node.borrow() {
+                    return Ok(Some(name.to_string()));
+                }
+            }
+        }
+
+        Ok(self.eval(property, env)?.map(|key| key.to_string()))
+    }
+
+    // 条件式を評価し、真偽値として扱った結果を返す
+    fn eval_condition(
+        &mut self,
+        test: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<bool, JsException> {
+        match self.eval(test, env)? {
+            Some(value) => Ok(value.is_truthy()),
+            None => Ok(false),
+        }
+    }
+
+    // ループの本体を1回実行した後に呼ばれ、ループを終了する場合はtrueを返す
+    // labelsはこのループに付けられたラベル
+    fn finish_iteration(&mut self, labels: &[String]) -> bool {
+        let is_target = |label: &Option<String>| match label {
+            Some(label) => labels.contains(label),
+            None => true,
+        };
+        match &self.completion {
+            Completion::Normal => false,
+            Completion::Continue(label) if is_target(label) => {
+                self.completion = Completion::Normal;
+                false
+            }
+            Completion::Break(label) if is_target(label) => {
+                self.completion = Completion::Normal;
+                true
+            }
+            // 外側のループを対象とするbreakとcontinue、returnはそのまま外側へ伝える
+            _ => true,
+        }
+    }
+
+    // for-in文とfor-of文。繰り返しごとに新しい環境で変数を宣言する
+    fn for_in_of_statement(
+        &mut self,
+        left: &Option<Rc<Node>>,
+        right: &Option<Rc<Node>>,
+        body: &Option<Rc<Node>>,
+        is_for_in: bool,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let labels = mem::take(&mut self.labels);
+        let value = self
+            .eval(right, env.clone())?
+            .unwrap_or(RuntimeValue::Undefined);
+        let iterator = self.create_iterator(value, is_for_in)?;
+        while let Some(value) = self.iterator_next(&iterator)? {
+            let env = self.new_environment(Environment::new(Some(env.clone())));
+            self.assign_target(left, value, env.clone())?;
+            let result = self.eval(body, env)?;
+            if self.finish_iteration(&labels) {
+                // return()の呼び出しでは、外側へ伝えるbreakやreturnを使わない
+                let completion = mem::replace(&mut self.completion, Completion::Normal);
+                self.iterator_close(&iterator)?;
+                self.completion = completion;
+                return Ok(result);
+            }
+        }
+        Ok(None)
+    }
+
+    // 複合代入と++、--。fは現在の値から、代入する値と式の値を返す
+    fn update(
+        &mut self,
+        target: &Option<Rc<Node>>,
+        env: Rc<RefCell<Environment>>,
+        f: impl FnOnce(&mut Self, RuntimeValue) -> Result<(RuntimeValue, RuntimeValue), JsException>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        match target.as_deref() {
+            Some(Node::Identifier(name)) => {
+                let found = RefCell::borrow(&env).lookup(name);
+                let old = self.variable_value(name, found)?;
+                let (new_value, result) = f(self, old)?;
+                let updated = env
+                    .borrow_mut()
+                    .update_variable(name.to_string(), Some(new_value));
+                if let Err((name, message)) = updated {
+                    return Err(self.throw_error(name, message));
+                }
+                Ok(Some(result))
+            }
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+            }) => {
+                let target = self
+                    .eval(object, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined);
+                let key = match self.property_key(property, *computed, env)? {
+                    Some(key) => key,
+                    None => return Ok(None),
+                };
+                let old = self.get(&target, &key)?;
+                let (new_value, result) = f(self, old)?;
+                self.put(&target, &key, new_value)?;
+                Ok(Some(result))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // for-in文とfor-of文で、取り出した値を左辺の変数やプロパティに代入する
+    fn assign_target(
+        &mut self,
+        target: &Option<Rc<Node>>,
+        value: RuntimeValue,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<(), JsException> {
+        match target.as_deref() {
+            Some(Node::VariableDeclaration { kind, declarations }) => {
+                let name = match declarations.first().and_then(|d| d.as_deref()) {
+                    Some(Node::VariableDeclarator { id, .. }) => match id.as_deref() {
+                        Some(Node::Identifier(name)) => name.to_string(),
+                        _ => return Ok(()),
+                    },
+                    _ => return Ok(()),
+                };
+                match kind {
+                    DeclarationKind::Var => Environment::declare_var(&env, name, Some(value)),
+                    DeclarationKind::Let | DeclarationKind::Const => env
+                        .borrow_mut()
+                        .initialize_variable(name, value, *kind == DeclarationKind::Let),
+                }
+            }
+            Some(Node::Identifier(name)) => {
+                let result = env
+                    .borrow_mut()
+                    .update_variable(name.to_string(), Some(value));
+                if let Err((name, message)) = result {
+                    return Err(self.throw_error(name, message));
+                }
+            }
+            Some(Node::MemberExpression {
+                object,
+                property,
+                computed,
+            }) => {
+                let target = self
+                    .eval(object, env.clone())?
+                    .unwrap_or(RuntimeValue::Undefined);
+                if let Some(key) = self.property_key(property, *computed, env)? {
+                    self.put(&target, &key, value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // switch文。一致したcaseから、breakされるまで後続のcaseも続けて実行する
+    fn switch_statement(
+        &mut self,
+        discriminant: &Option<Rc<Node>>,
+        cases: &[Option<Rc<Node>>],
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<RuntimeValue>, JsException> {
+        let labels = mem::take(&mut self.labels);
+        let value = self.eval(discriminant, env.clone())?;
+        // letとconstで宣言された変数はswitch文のブロック全体で使える
+        let env = self.new_environment(Environment::new(Some(env)));
+        let consequents = cases
+            .iter()
+            .flatten()
+            .filter_map(|case| match case.borrow() {
+                Node::SwitchCase { consequent, .. } => Some(consequent.iter().flatten()),
+                _ => None,
+            });
+        self.hoist_declarations(consequents.flatten(), env.clone())?;
+
+        let mut start = None;
+        let mut default = None;
+        for (i, case) in cases.iter().enumerate() {
+            match case.as_deref() {
+                Some(Node::SwitchCase { test: None, .. }) => default = Some(i),
+                Some(Node::SwitchCase { test, .. }) => {
+                    if self.eval(test, env.clone())? == value {
+                        start = Some(i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut result = None;
+        let start = match start.or(default) {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        for case in &cases[start..] {
+            if let Some(Node::SwitchCase { consequent, .. }) = case.as_deref() {
+                for stmt in consequent {
+                    result = self.eval(stmt, env.clone())?;
+                    if self.completion != Completion::Normal {
+                        // このswitch文を対象とするbreakだけを処理し、continueは外側のループへ伝える
+                        if let Completion::Break(label) = &self.completion {
+                            if label.as_ref().map_or(true, |l| labels.contains(l)) {
+                                self.completion = Completion::Normal;
+                            }
+                        }
+                        return Ok(result);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+// スタックの大きさから評価に使ってよい大きさを求める
+// 評価を始める前に呼び出し元が使った分と、最後の確認の後に組み込み関数が使う分を4分の1だけ残しておく
+fn stack_limit(stack_size: usize) -> usize {
+    stack_size - stack_size / 4
+}
+
+// エラーメッセージで使う、呼び出された関数の名前。a.b.cや"abc".padStartのようにソースコードの形で表す
+pub fn callee_name(callee: &Option<Rc<Node>>) -> String {
+    match callee.as_deref() {
+        Some(Node::MemberExpression {
+            object, property, ..
+        }) if expression_text(object).is_none() => {
+            // (a + b).cのように表せない式の場合はプロパティの名前だけにする
+            expression_text(property).unwrap_or_else(|| "expression".to_string())
+        }
+        _ => expression_text(callee).unwrap_or_else(|| "expression".to_string()),
+    }
+}
+
+// 識別子、リテラル、プロパティアクセス、呼び出しからなる式をソースコードの形にする
+fn expression_text(node: &Option<Rc<Node>>) -> Option<String> {
+    match node.as_deref()? {
+        Node::Identifier(name) => Some(name.to_string()),
+        Node::ThisExpression => Some("this".to_string()),
+        Node::Super => Some("super".to_string()),
+        Node::StringLiteral(value) => Some(format!("\"{}\"", value)),
+        Node::NumericLiteral(value) => Some(number_to_string(*value)),
+        Node::MemberExpression {
+            object,
+            property,
+            computed,
+        } => {
+            let object = expression_text(object)?;
+            let property = expression_text(property)?;
+            if *computed {
+                Some(format!("{}[{}]", object, property))
+            } else {
+                Some(format!("{}.{}", object, property))
+            }
+        }
+        Node::CallExpression { callee, .. } => Some(format!("{}(...)", expression_text(callee)?)),
+        _ => None,
+    }
+}
+
+// newで呼び出せる関数か。アロー関数、メソッド、async関数、ジェネレータはコンストラクタにならない
+pub fn is_constructor(value: &RuntimeValue) -> bool {
+    match value {
+        RuntimeValue::Function(f) => {
+            let is_suspendable = f
+                .code
+                .as_ref()
+                .is_some_and(|code| code.is_async || code.is_generator);
+            f.is_class_constructor || (!f.is_arrow && f.home_object.is_none() && !is_suspendable)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+// オブジェクトのプロパティ。ゲッターかセッターで定義されたものはアクセサプロパティになる
+pub enum Property {
+    Data(RuntimeValue),
+    Accessor {
+        get: Option<RuntimeValue>,
+        set: Option<RuntimeValue>,
+    },
+}
+
+#[derive(Debug, Clone)]
+// 組み込みのオブジェクトが持つ、プロパティとしては見えない状態
+pub enum InternalSlot {
+    Promise(PromiseState),
+    // ジェネレータとasync関数の中断したフレーム
+    Generator(Rc<RefCell<GeneratorState>>),
+    // fetchのResponseとHeaders、XMLHttpRequestの状態
+    Response(ResponseState),
+    Headers(Vec<Header>),
+    Request(RequestState),
+    // RegExpのコンパイルしたパターン
+    RegExp(Rc<Regex>),
+    // for-inとfor-ofの繰り返しの状態
+    Iterator(IteratorState),
+}
+
+#[derive(Debug, Clone)]
+// for-inとfor-ofで次に返す値
+pub enum IteratorState {
+    // for-inのキー。繰り返しを始める時に集める
+    Keys(Vec<String>, usize),
+    // 配列と文字列の要素。長さは繰り返しごとに確かめる
+    Elements(RuntimeValue, usize),
+    // next()を持つオブジェクト。ジェネレータなど
+    Protocol(RuntimeValue),
+}
+
+#[derive(Debug, Clone, Default)]
+// {a: 1}のように作成されたオブジェクト
+pub struct JsObject {
+    properties: Vec<(String, Property)>,
+    // プロパティが見つからない場合に次に探すオブジェクト。ObjectかFunction
+    prototype: Option<RuntimeValue>,
+    // DOMノードのラッパーオブジェクトの場合、対応するノード
+    node: Option<Rc<RefCell<DomNode>>>,
+    slot: Option<InternalSlot>,
+}
+
+impl JsObject {
+    pub fn new() -> Self {
+        Self {
+            properties: Vec::new(),
+            prototype: None,
+            node: None,
+            slot: None,
+        }
+    }
+
+    pub fn with_prototype(prototype: Option<RuntimeValue>) -> Self {
+        Self {
+            properties: Vec::new(),
+            prototype,
+            node: None,
+            slot: None,
+        }
+    }
+
+    pub fn slot(&self) -> Option<&InternalSlot> {
+        self.slot.as_ref()
+    }
+
+    pub fn slot_mut(&mut self) -> Option<&mut InternalSlot> {
+        self.slot.as_mut()
+    }
+
+    pub fn set_slot(&mut self, slot: InternalSlot) {
+        self.slot = Some(slot);
+    }
+
+    pub fn prototype(&self) -> Option<RuntimeValue> {
+        self.prototype.clone()
+    }
+
+    pub fn set_prototype(&mut self, prototype: Option<RuntimeValue>) {
+        self.prototype = prototype;
+    }
+
+    pub fn node(&self) -> Option<Rc<RefCell<DomNode>>> {
+        self.node.clone()
+    }
+
+    pub fn set_node(&mut self, node: Rc<RefCell<DomNode>>) {
+        self.node = Some(node);
+    }
+
+    // 自身のプロパティの値を返す。存在しない場合とアクセサプロパティの場合はundefined
+    pub fn get(&self, key: &str) -> RuntimeValue {
+        match self.get_own(key) {
+            Some(Property::Data(value)) => value,
+            _ => RuntimeValue::Undefined,
+        }
+    }
+
+    pub fn get_own(&self, key: &str) -> Option<Property> {
+        self.properties
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, p)| p.clone())
+    }
+
+    pub fn set(&mut self, key: String, value: RuntimeValue) {
+        self.define(key, Property::Data(value));
+    }
+
+    // ゲッターとセッターを定義する。片方だけ指定された場合、もう片方は既存のものを残す
+    pub fn define_accessor(
+        &mut self,
+        key: String,
+        get: Option<RuntimeValue>,
+        set: Option<RuntimeValue>,
+    ) {
+        let (get, set) = match self.get_own(&key) {
+            Some(Property::Accessor {
+                get: old_get,
+                set: old_set,
+            }) => (get.or(old_get), set.or(old_set)),
+            _ => (get, set),
+        };
+        self.define(key, Property::Accessor { get, set });
+    }
+
+    fn define(&mut self, key: String, property: Property) {
+        match self.properties.iter_mut().find(|(k, _)| *k == key) {
+            Some(p) => p.1 = property,
+            None => self.properties.push((key, property)),
+        }
+    }
+
+    pub fn has_own(&self, key: &str) -> bool {
+        self.properties.iter().any(|(k, _)| k == key)
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.properties.retain(|(k, _)| k != key);
+    }
+
+    // 整数のプロパティ名を小さい順に返し、その後に残りを追加された順に返す
+    pub fn keys(&self) -> Vec<String> {
+        let mut indices: Vec<(usize, &String)> = self
+            .properties
+            .iter()
+            .filter_map(|(k, _)| array_index(k).map(|i| (i, k)))
+            .collect();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .map(|(_, k)| k.clone())
+            .chain(
+                self.properties
+                    .iter()
+                    .filter(|(k, _)| array_index(k).is_none())
+                    .map(|(k, _)| k.clone()),
+            )
+            .collect()
+    }
+}
+
+impl Trace for RefCell<JsObject> {
+    fn trace(&self, marker: &mut Marker) {
+        RefCell::borrow(self).mark(marker);
+    }
+
+    fn clear(&self, releaser: &mut Releaser) {
+        let mut object = self.borrow_mut();
+        object.release(releaser);
+        object.node = None;
+        object.slot = None;
+    }
+}
+
+impl JsObject {
+    // プロパティ、プロトタイプ、内部スロットが参照している値に印を付ける
+    fn mark(&self, marker: &mut Marker) {
+        let object = self;
+        for (_, property) in &object.properties {
+            match property {
+                Property::Data(value) => marker.mark_value(value),
+                Property::Accessor { get, set } => {
+                    for function in get.iter().chain(set.iter()) {
+                        marker.mark_value(function);
+                    }
+                }
+            }
+        }
+        if let Some(prototype) = &object.prototype {
+            marker.mark_value(prototype);
+        }
+        match &object.slot {
+            Some(InternalSlot::Promise(state)) => state.mark(marker),
+            Some(InternalSlot::Generator(state)) => RefCell::borrow(state).mark(marker),
+            Some(InternalSlot::Iterator(IteratorState::Elements(value, _)))
+            | Some(InternalSlot::Iterator(IteratorState::Protocol(value))) => {
+                marker.mark_value(value)
+            }
+            Some(InternalSlot::Response(_))
+            | Some(InternalSlot::Headers(_))
+            | Some(InternalSlot::Request(_))
+            | Some(InternalSlot::RegExp(_))
+            | Some(InternalSlot::Iterator(IteratorState::Keys(..)))
+            | None => {}
+        }
+    }
+
+    // プロパティ、プロトタイプ、内部スロットが参照している値をreleaserへ移す
+    pub fn release(&mut self, releaser: &mut Releaser) {
+        for (_, property) in self.properties.drain(..) {
+            match property {
+                Property::Data(value) => releaser.push_value(value),
+                Property::Accessor { get, set } => {
+                    for function in get.into_iter().chain(set) {
+                        releaser.push_value(function);
+                    }
+                }
+            }
+        }
+        if let Some(prototype) = self.prototype.take() {
+            releaser.push_value(prototype);
+        }
+        match self.slot.take() {
+            Some(InternalSlot::Iterator(IteratorState::Elements(value, _)))
+            | Some(InternalSlot::Iterator(IteratorState::Protocol(value))) => {
+                releaser.push_value(value)
+            }
+            _ => {}
+        }
+    }
+}
+
+// {next: {next: ...}}のような長いリストを解放してもスタックが溢れないように、参照している値は作業リストで解放する
+impl Drop for JsObject {
+    fn drop(&mut self) {
+        let mut releaser = Releaser::default();
+        self.release(&mut releaser);
+        releaser.release_all();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+// [1, 2]のように作成された配列。添字以外のキーで設定されたプロパティはpropertiesに持つ
+// 要素はVec<RuntimeValue>としてそのまま扱える
+pub struct JsArray {
+    elements: Vec<RuntimeValue>,
+    properties: JsObject,
+}
+
+impl JsArray {
+    pub fn new(elements: Vec<RuntimeValue>) -> Self {
+        Self {
+            elements,
+            properties: JsObject::new(),
+        }
+    }
+
+    // 添字の後に、追加された順でプロパティ名を返す
+    pub fn keys(&self) -> Vec<String> {
+        (0..self.elements.len())
+            .map(|i| i.to_string())
+            .chain(self.properties.keys())
+            .collect()
+    }
+}
+
+impl Deref for JsArray {
+    type Target = Vec<RuntimeValue>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl DerefMut for JsArray {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.elements
+    }
+}
+
+impl Trace for RefCell<JsArray> {
+    fn trace(&self, marker: &mut Marker) {
+        let array = RefCell::borrow(self);
+        for element in array.iter() {
+            marker.mark_value(element);
+        }
+        array.properties.mark(marker);
+    }
+
+    fn clear(&self, releaser: &mut Releaser) {
+        self.borrow_mut().release(releaser);
+    }
+}
+
+impl JsArray {
+    // 要素とプロパティが参照している値をreleaserへ移す
+    pub fn release(&mut self, releaser: &mut Releaser) {
+        for element in self.elements.drain(..) {
+            releaser.push_value(element);
+        }
+        self.properties.release(releaser);
+    }
+}
+
+impl Drop for JsArray {
+    fn drop(&mut self) {
+        let mut releaser = Releaser::default();
+        self.release(&mut releaser);
+        releaser.release_all();
+    }
+}
+
+// 配列のlengthに設定できる値の場合、その長さを返す。0以上の整数で上限以下のもの
+pub fn array_length(value: &RuntimeValue) -> Option<usize> {
+    let length = value.to_number();
+    if length >= 0.0 && length <= MAX_ARRAY_LENGTH as f64 && length as usize as f64 == length {
+        Some(length as usize)
+    } else {
+        None
+    }
+}
+
+// "0"や"42"のように配列の添字として使えるキーの場合、その値を返す
+// "01"や"-1"、2^32 - 1以上の値は通常のプロパティ名として扱う
+pub fn array_index(key: &str) -> Option<usize> {
+    let index = key.parse::<u32>().ok()?;
+    if index == u32::MAX || index.to_string() != key {
+        return None;
+    }
+    Some(index as usize)
+}
+
+// JSランタイムで扱う値
+#[derive(Debug, Clone)]
+pub enum RuntimeValue {
+    // IEEE 754の倍精度浮動小数点数
+    Number(f64),
+    Boolean(bool),
+    Null,
+    Undefined,
+    StringLiteral(String),
+    Object(Rc<RefCell<JsObject>>),
+    Array(Rc<RefCell<JsArray>>),
+    Function(Rc<Function>),
+}
+
+// ===による比較。オブジェクトは同じものを指している場合だけ等しい
+impl PartialEq for RuntimeValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RuntimeValue::Number(l), RuntimeValue::Number(r)) => l == r,
+            (RuntimeValue::Boolean(l), RuntimeValue::Boolean(r)) => l == r,
+            (RuntimeValue::Null, RuntimeValue::Null) => true,
+            (RuntimeValue::Undefined, RuntimeValue::Undefined) => true,
+            (RuntimeValue::StringLiteral(l), RuntimeValue::StringLiteral(r)) => l == r,
+            (RuntimeValue::Object(l), RuntimeValue::Object(r)) => Rc::ptr_eq(l, r),
+            (RuntimeValue::Array(l), RuntimeValue::Array(r)) => Rc::ptr_eq(l, r),
+            (RuntimeValue::Function(l), RuntimeValue::Function(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+// 数値を文字列に変換する
+pub fn number_to_string(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 {
+            "Infinity".to_string()
+        } else {
+            "-Infinity".to_string()
+        }
+    } else if value == 0.0 {
+        // -0も"0"になる
+        "0".to_string()
+    } else if value < 0.0 {
+        format!("-{}", number_to_string(-value))
+    } else {
+        // 元の数に戻せる最短の桁と指数を求め、1e21以上と1e-7未満は指数表記にする
+        let scientific = format!("{:e}", value);
+        let (mantissa, exponent) = scientific
+            .split_once('e')
+            .expect("scientific notation should have an exponent");
+        let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+        let k = digits.len() as i32;
+        // 小数点の位置。digitsの先頭からn桁目の後に小数点が来る
+        let n = exponent
+            .parse::<i32>()
+            .expect("exponent should be an integer")
+            + 1;
+
+        if k <= n && n <= 21 {
+            format!("{}{}", digits, "0".repeat((n - k) as usize))
+        } else if 0 < n && n <= 21 {
+            format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+        } else if -6 < n && n <= 0 {
+            format!("0.{}{}", "0".repeat(-n as usize), digits)
+        } else {
+            let sign = if n - 1 < 0 { '-' } else { '+' };
+            let exponent = (n - 1).unsigned_abs();
+            match digits.split_at(1) {
+                (first, "") => format!("{}e{}{}", first, sign, exponent),
+                (first, rest) => format!("{}.{}e{}{}", first, rest, sign, exponent),
+            }
+        }
+    }
+}
+
+// UTF-16のコードユニットの列から文字列を作る
+// 文字列はUTF-8で保持するため、対になっていないサロゲートはU+FFFDになる
+pub fn string_from_utf16(units: &[u16]) -> RuntimeValue {
+    RuntimeValue::StringLiteral(String::from_utf16_lossy(units))
+}
+
+// 文字列を数値に変換する。数値として読めない場合はNaN
+fn string_to_number(value: &str) -> f64 {
+    let value = value.trim();
+    if value.is_empty() {
+        return 0.0;
+    }
+
+    let (sign, unsigned) = match value.strip_prefix('-') {
+        Some(v) => (-1.0, v),
+        None => (1.0, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    if unsigned == "Infinity" {
+        return sign * f64::INFINITY;
+    }
+
+    // 0b、0o、0xから始まる2進数、8進数、16進数。符号は付けられない
+    let radix = match value.get(..2) {
+        Some("0b" | "0B") => Some(2),
+        Some("0o" | "0O") => Some(8),
+        Some("0x" | "0X") => Some(16),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        return non_decimal_to_number(&value[2..], radix);
+    }
+
+    // "inf"や"nan"のようにRustでは数値として読めるがJSでは読めない文字列と、+-1のように符号が続く文字列を除く
+    if !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        || !unsigned
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+    {
+        return f64::NAN;
+    }
+
+    match unsigned.parse::<f64>() {
+        Ok(num) => sign * num,
+        Err(_) => f64::NAN,
+    }
+}
+
+// radix進数の数字だけからなる文字列を数値にする。u64に収まらない場合は上の桁から順に計算する
+fn non_decimal_to_number(digits: &str, radix: u32) -> f64 {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return f64::NAN;
+    }
+    match u64::from_str_radix(digits, radix) {
+        Ok(num) => num as f64,
+        Err(_) => digits.chars().fold(0.0, |result, c| {
+            result * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64
+        }),
+    }
+}
+
+impl RuntimeValue {
+    // 条件式で使われた時に真として扱われるか
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            RuntimeValue::Number(value) => *value != 0.0 && !value.is_nan(),
+            RuntimeValue::Boolean(value) => *value,
+            RuntimeValue::Null | RuntimeValue::Undefined => false,
+            RuntimeValue::StringLiteral(value) => !value.is_empty(),
+            RuntimeValue::Object(_) | RuntimeValue::Array(_) | RuntimeValue::Function(_) => true,
+        }
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(self, RuntimeValue::Function(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(
+            self,
+            RuntimeValue::Object(_) | RuntimeValue::Array(_) | RuntimeValue::Function(_)
+        )
+    }
+
+    // valueOfとtoStringを呼び出さずに、オブジェクトを文字列表現に変換する
+    // JSの演算ではJsRuntime::to_primitiveで変換した値を渡すため、ここでオブジェクトを受け取ることはない
+    fn into_primitive(self) -> RuntimeValue {
+        if self.is_object() {
+            RuntimeValue::StringLiteral(self.to_string())
+        } else {
+            self
+        }
+    }
+
+    // 数値に変換する。変換できない場合はNaN
+    // オブジェクトは文字列表現から変換する。valueOfを呼び出す場合はJsRuntime::to_numberを使う
+    pub fn to_number(&self) -> f64 {
+        match self {
+            RuntimeValue::Number(value) => *value,
+            RuntimeValue::Boolean(value) => {
+                if *value {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            RuntimeValue::Null => 0.0,
+            RuntimeValue::Undefined => f64::NAN,
+            RuntimeValue::StringLiteral(value) => string_to_number(value),
+            _ => string_to_number(&self.to_string()),
+        }
+    }
+
+    // ==による比較。型が異なる場合は型を変換して比較する
+    // オブジェクトとプリミティブな値の比較は、JsRuntime::loosely_equalsでオブジェクトを変換してから行う
+    pub fn loosely_equals(&self, other: &RuntimeValue) -> bool {
+        let is_nullish =
+            |v: &RuntimeValue| matches!(v, RuntimeValue::Null | RuntimeValue::Undefined);
+        if is_nullish(self) || is_nullish(other) {
+            return is_nullish(self) && is_nullish(other);
+        }
+
+        match (self, other) {
+            (RuntimeValue::StringLiteral(l), RuntimeValue::StringLiteral(r)) => l == r,
+            _ if self.is_object() || other.is_object() => self == other,
+            _ => self.to_number() == other.to_number(),
+        }
+    }
+
+    // < > <= >=による比較。両方とも文字列の場合は辞書順、それ以外は数値に変換して比較する
+    // NaNが含まれて比較できない場合はNone
+    pub fn compare(&self, other: &RuntimeValue) -> Option<Ordering> {
+        if let (RuntimeValue::StringLiteral(l), RuntimeValue::StringLiteral(r)) = (self, other) {
+            return Some(l.cmp(r));
+        }
+
+        self.to_number().partial_cmp(&other.to_number())
+    }
+
+    // typeofの結果になる型の名前
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            RuntimeValue::Number(_) => "number",
+            RuntimeValue::Boolean(_) => "boolean",
+            RuntimeValue::Undefined => "undefined",
+            RuntimeValue::StringLiteral(_) => "string",
+            RuntimeValue::Function(_) => "function",
+            RuntimeValue::Null | RuntimeValue::Object(_) | RuntimeValue::Array(_) => "object",
+        }
+    }
+
+    // ビット演算のために32ビットの符号なし整数に変換する。NaNと無限大は0になる
+    pub fn to_uint32(&self) -> u32 {
+        let value = builtins::trunc(self.to_number());
+        if !value.is_finite() {
+            return 0;
+        }
+        let value = value % 4_294_967_296.0;
+        if value < 0.0 {
+            (value + 4_294_967_296.0) as u32
+        } else {
+            value as u32
+        }
+    }
+
+    pub fn to_int32(&self) -> i32 {
+        self.to_uint32() as i32
+    }
+
+    // >>>。左辺を符号なしとして右へずらす
+    pub fn unsigned_shift_right(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_uint32().wrapping_shr(rhs.to_uint32()) as f64)
+    }
+
+    // **によるべき乗
+    pub fn exponentiate(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(builtins::pow(self.to_number(), rhs.to_number()))
+    }
+
+    // 二項演算子で計算する。複合代入とBinaryExpressionで使う
+    pub fn operate(self, operator: &str, rhs: RuntimeValue) -> RuntimeValue {
+        match operator {
+            "+" => self + rhs,
+            "-" => self - rhs,
+            "*" => self * rhs,
+            "/" => self / rhs,
+            "%" => self % rhs,
+            "**" => self.exponentiate(rhs),
+            "<<" => self << rhs,
+            ">>" => self >> rhs,
+            ">>>" => self.unsigned_shift_right(rhs),
+            "&" => self & rhs,
+            "|" => self | rhs,
+            _ => self ^ rhs,
+        }
+    }
+
+    // deleteでプロパティを削除する。削除できないプロパティの場合はfalse
+    pub fn delete_property(&self, key: &str) -> bool {
+        match self {
+            RuntimeValue::Object(object) => {
+                object.borrow_mut().remove(key);
+                true
+            }
+            RuntimeValue::Function(function) => {
+                function.properties.borrow_mut().remove(key);
+                true
+            }
+            RuntimeValue::Array(elements) => {
+                let mut elements = elements.borrow_mut();
+                if key == "length" {
+                    return false;
+                }
+                // 配列は穴を持てないため、削除した要素はundefinedになる
+                match array_index(key) {
+                    Some(index) => {
+                        if let Some(element) = elements.get_mut(index) {
+                            *element = RuntimeValue::Undefined;
+                        }
+                        true
+                    }
+                    None => {
+                        elements.properties.remove(key);
+                        true
+                    }
+                }
+            }
+            RuntimeValue::StringLiteral(value) => {
+                key != "length"
+                    && !matches!(array_index(key), Some(i) if i < value.encode_utf16().count())
+            }
+            _ => true,
+        }
+    }
+
+    // 自身のプロパティ。配列と文字列の場合は添字とlength
+    // 文字列の長さと添字はUTF-16のコードユニットで数える
+    pub fn own_property(&self, key: &str) -> Option<Property> {
+        match self {
+            RuntimeValue::Object(object) => RefCell::borrow(object).get_own(key),
+            RuntimeValue::Function(function) => RefCell::borrow(&function.properties).get_own(key),
+            RuntimeValue::Array(elements) => {
+                let elements = RefCell::borrow(elements);
+                if key == "length" {
+                    return Some(Property::Data(RuntimeValue::Number(elements.len() as f64)));
+                }
+                match array_index(key) {
+                    Some(index) => elements.get(index).cloned().map(Property::Data),
+                    None => elements.properties.get_own(key),
+                }
+            }
+            RuntimeValue::StringLiteral(value) => {
+                if key == "length" {
+                    return Some(Property::Data(RuntimeValue::Number(
+                        value.encode_utf16().count() as f64,
+                    )));
+                }
+                let index = array_index(key)?;
+                value
+                    .encode_utf16()
+                    .nth(index)
+                    .map(|unit| Property::Data(string_from_utf16(&[unit])))
+            }
+            _ => None,
+        }
+    }
+
+    // プロパティの値を取得する。オブジェクトと関数はプロトタイプチェーンもたどる
+    // ゲッターは呼び出さない。存在しない場合はundefined
+    pub fn get_property(&self, key: &str) -> RuntimeValue {
+        match self.own_property(key) {
+            Some(Property::Data(value)) => value,
+            Some(Property::Accessor { .. }) => RuntimeValue::Undefined,
+            None => {
+                let prototype = match self {
+                    RuntimeValue::Object(object) => RefCell::borrow(object).prototype(),
+                    RuntimeValue::Function(function) => {
+                        RefCell::borrow(&function.properties).prototype()
+                    }
+                    _ => None,
+                };
+                match prototype {
+                    Some(p) => p.get_property(key),
+                    None => RuntimeValue::Undefined,
+                }
+            }
+        }
+    }
+
+    // プロパティに値を設定する。オブジェクト、配列、関数以外の場合は何もしない
+    pub fn set_property(&self, key: &str, value: RuntimeValue) {
+        match self {
+            RuntimeValue::Object(object) => object.borrow_mut().set(key.to_string(), value),
+            RuntimeValue::Function(function) => {
+                function.properties.borrow_mut().set(key.to_string(), value)
+            }
+            RuntimeValue::Array(elements) => {
+                let mut elements = elements.borrow_mut();
+                // 長さの上限を超える場合は何もしない。JsRuntime::putはRangeErrorを投げる
+                if key == "length" {
+                    if let Some(length) = array_length(&value) {
+                        elements.resize(length, RuntimeValue::Undefined);
+                    }
+                    return;
+                }
+                match array_index(key) {
+                    // 配列の長さより大きい位置に設定した場合、間をundefinedで埋める
+                    Some(index) if index < MAX_ARRAY_LENGTH => {
+                        if index >= elements.len() {
+                            elements.resize(index + 1, RuntimeValue::Undefined);
+                        }
+                        elements[index] = value;
+                    }
+                    Some(_) => {}
+                    None => elements.properties.set(key.to_string(), value),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // ゲッターとセッターを定義する。オブジェクトと関数以外の場合は何もしない
+    pub fn define_accessor(&self, key: &str, get: Option<RuntimeValue>, set: Option<RuntimeValue>) {
+        match self {
+            RuntimeValue::Object(object) => {
+                object
+                    .borrow_mut()
+                    .define_accessor(key.to_string(), get, set)
+            }
+            RuntimeValue::Function(function) => {
+                function
+                    .properties
+                    .borrow_mut()
+                    .define_accessor(key.to_string(), get, set)
+            }
+            RuntimeValue::Array(elements) if key != "length" && array_index(key).is_none() => {
+                elements
+                    .borrow_mut()
+                    .properties
+                    .define_accessor(key.to_string(), get, set)
+            }
+            _ => {}
+        }
+    }
+}
+
+// どちらかが文字列の場合は文字列の連結、それ以外は数値の足し算
+impl Add<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn add(self, rhs: RuntimeValue) -> RuntimeValue {
+        let (left, right) = (self.into_primitive(), rhs.into_primitive());
+        match (left, right) {
+            // 左辺の文字列に右辺を追加して、左辺を複製しないようにする
+            (RuntimeValue::StringLiteral(mut l), r) => {
+                match r {
+                    RuntimeValue::StringLiteral(r) => l.push_str(&r),
+                    r => l.push_str(&r.to_string()),
+                }
+                RuntimeValue::StringLiteral(l)
+            }
+            (l, RuntimeValue::StringLiteral(r)) => RuntimeValue::StringLiteral(l.to_string() + &r),
+            (l, r) => RuntimeValue::Number(l.to_number() + r.to_number()),
+        }
+    }
+}
+
+impl Sub<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn sub(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_number() - rhs.to_number())
+    }
+}
+
+impl Mul<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn mul(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_number() * rhs.to_number())
+    }
+}
+
+impl Div<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn div(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_number() / rhs.to_number())
+    }
+}
+
+impl Rem<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn rem(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_number() % rhs.to_number())
+    }
+}
+
+// ビット演算は両辺を32ビットの符号付き整数に変換して計算する
+impl BitAnd<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn bitand(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number((self.to_int32() & rhs.to_int32()) as f64)
+    }
+}
+
+impl BitOr<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn bitor(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number((self.to_int32() | rhs.to_int32()) as f64)
+    }
+}
+
+impl BitXor<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn bitxor(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number((self.to_int32() ^ rhs.to_int32()) as f64)
+    }
+}
+
+// ずらす数は右辺の下位5ビットだけを使う
+impl Shl<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn shl(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_int32().wrapping_shl(rhs.to_uint32()) as f64)
+    }
+}
+
+impl Shr<RuntimeValue> for RuntimeValue {
+    type Output = RuntimeValue;
+
+    fn shr(self, rhs: RuntimeValue) -> RuntimeValue {
+        RuntimeValue::Number(self.to_int32().wrapping_shr(rhs.to_uint32()) as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+// 変数。letとconstで宣言された変数は、宣言が実行されるまでvalueがNoneになる（TDZ）
+struct Variable {
+    name: String,
+    value: Option<RuntimeValue>,
+    // constで宣言された変数は再代入できない
+    mutable: bool,
+}
+
+#[derive(Debug, Clone)]
+// JSの変数のスコープ管理を行う
+// スコープ：関数、変数が使用可能な範囲のこと。内側のスコープは外側のスコープの値を参考できるが逆はできない
+pub struct Environment {
+    variables: Vec<Variable>,
+    // 外部スコープを表す
+    outer: Option<Rc<RefCell<Environment>>>,
+    // varで宣言された変数が追加されるスコープかどうか。グローバルスコープと関数のスコープがtrue
+    is_function_scope: bool,
+}
+
+impl Environment {
+    fn new(outer: Option<Rc<RefCell<Environment>>>) -> Self {
+        Self {
+            variables: Vec::new(),
+            outer,
+            is_function_scope: false,
+        }
+    }
+
+    fn new_function_scope(outer: Option<Rc<RefCell<Environment>>>) -> Self {
+        Self {
+            variables: Vec::new(),
+            outer,
+            is_function_scope: true,
+        }
+    }
+
+    pub fn get_variable(&self, name: String) -> Option<RuntimeValue> {
+        self.lookup(&name).flatten()
+    }
+
+    // 変数を探す。見つからない場合はNone、初期化前の変数の場合はSome(None)
+    fn lookup(&self, name: &str) -> Option<Option<RuntimeValue>> {
+        for variable in &self.variables {
+            if variable.name == name {
+                return Some(variable.value.clone());
+            }
+        }
+        match &self.outer {
+            Some(env) => RefCell::borrow(env).lookup(name),
+            None => None,
+        }
+    }
+
+    fn add_variable(&mut self, name: String, value: Option<RuntimeValue>) {
+        self.variables.push(Variable {
+            name,
+            value,
+            mutable: true,
+        });
+    }
+
+    // このスコープの変数に値を設定する。存在しない場合は追加する
+    fn set_variable(&mut self, name: String, value: RuntimeValue) {
+        match self.variables.iter_mut().find(|v| v.name == name) {
+            Some(variable) => variable.value = Some(value),
+            None => self.add_variable(name, Some(value)),
+        }
+    }
+
+    // varで宣言された変数を、一番近い関数のスコープに追加する
+    fn declare_var(env: &Rc<RefCell<Environment>>, name: String, value: Option<RuntimeValue>) {
+        if !RefCell::borrow(env).is_function_scope {
+            if let Some(outer) = RefCell::borrow(env).outer.clone() {
+                return Self::declare_var(&outer, name, value);
+            }
+        }
+
+        let mut env = env.borrow_mut();
+        match (env.variables.iter_mut().find(|v| v.name == name), value) {
+            (Some(variable), Some(value)) => variable.value = Some(value),
+            (Some(_), None) => {}
+            (None, value) => env.add_variable(name, Some(value.unwrap_or(RuntimeValue::Undefined))),
+        }
+    }
+
+    // letとconstで宣言された変数を、初期化されていない状態でこのスコープに追加する
+    fn declare_lexical(&mut self, name: String) {
+        match self.variables.iter_mut().find(|v| v.name == name) {
+            Some(variable) => variable.value = None,
+            None => self.add_variable(name, None),
+        }
+    }
+
+    // letとconstの宣言が実行された時に変数を初期化する
+    fn initialize_variable(&mut self, name: String, value: RuntimeValue, mutable: bool) {
+        match self.variables.iter_mut().find(|v| v.name == name) {
+            Some(variable) => {
+                variable.value = Some(value);
+                variable.mutable = mutable;
+            }
+            None => self.variables.push(Variable {
+                name,
+                value: Some(value),
+                mutable,
+            }),
+        }
+    }
+
+    // 変数に代入する。どのスコープにも存在しない場合はグローバル変数になる
+    // 代入できない場合は、エラーの種類とメッセージを返す
+    fn update_variable(
+        &mut self,
+        name: String,
+        value: Option<RuntimeValue>,
+    ) -> Result<(), (&'static str, String)> {
+        if let Some(variable) = self.variables.iter_mut().find(|v| v.name == name) {
+            if variable.value.is_none() {
+                return Err((
+                    "ReferenceError",
+                    format!("cannot access '{}' before initialization", name),
+                ));
+            }
+            if !variable.mutable {
+                return Err((
+                    "TypeError",
+                    format!("assignment to constant variable '{}'", name),
+                ));
+            }
+            variable.value = Some(value.unwrap_or(RuntimeValue::Undefined));
+            return Ok(());
+        }
+
+        match &self.outer {
+            Some(env) => env.borrow_mut().update_variable(name, value),
+            None => {
+                self.add_variable(name, Some(value.unwrap_or(RuntimeValue::Undefined)));
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Trace for RefCell<Environment> {
+    fn trace(&self, marker: &mut Marker) {
+        let env = RefCell::borrow(self);
+        for value in env.variables.iter().filter_map(|v| v.value.as_ref()) {
+            marker.mark_value(value);
+        }
+        if let Some(outer) = &env.outer {
+            marker.mark_environment(outer);
+        }
+    }
+
+    fn clear(&self, releaser: &mut Releaser) {
+        self.borrow_mut().release(releaser);
+    }
+}
+
+impl Environment {
+    // 変数の値と外側のスコープをreleaserへ移す
+    pub fn release(&mut self, releaser: &mut Releaser) {
+        for value in self.variables.drain(..).filter_map(|v| v.value) {
+            releaser.push_value(value);
+        }
+        if let Some(outer) = self.outer.take() {
+            releaser.push_environment(outer);
+        }
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        let mut releaser = Releaser::default();
+        self.release(&mut releaser);
+        releaser.release_all();
+    }
+}
+
+// 配列の要素をseparatorでつなげる。nullとundefinedは空文字列になる
+// 変換中の配列が要素に現れた場合は、循環参照として空文字列にする
+pub fn join_array(elements: &Rc<RefCell<JsArray>>, separator: &str) -> String {
+    join_array_with(elements, separator, &mut Vec::new())
+}
+
+// visitingは変換中の配列のアドレス
+fn join_array_with(
+    elements: &Rc<RefCell<JsArray>>,
+    separator: &str,
+    visiting: &mut Vec<usize>,
+) -> String {
+    let address = Rc::as_ptr(elements) as usize;
+    if visiting.contains(&address) {
+        return String::new();
+    }
+    visiting.push(address);
+    let joined = RefCell::borrow(elements)
+        .iter()
+        .map(|e| match e {
+            RuntimeValue::Null | RuntimeValue::Undefined => String::new(),
+            RuntimeValue::Array(inner) => join_array_with(inner, ",", visiting),
+            _ => e.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(separator);
+    visiting.pop();
+    joined
+}
+
+impl Display for RuntimeValue {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let s = match self {
+            RuntimeValue::Number(value) => number_to_string(*value),
+            RuntimeValue::Boolean(value) => format!("{}", value),
+            RuntimeValue::Null => "null".to_string(),
+            RuntimeValue::Undefined => "undefined".to_string(),
+            RuntimeValue::StringLiteral(value) => value.to_string(),
+            RuntimeValue::Object(object) => match RefCell::borrow(object).node() {
+                Some(node) => match RefCell::borrow(&node).kind() {
+                    DomNodeKind::Document => "[object HTMLDocument]".to_string(),
+                    DomNodeKind::Element(_) => "[object HTMLElement]".to_string(),
+                    DomNodeKind::Text(_) => "[object Text]".to_string(),
+                },
+                None => "[object Object]".to_string(),
+            },
+            RuntimeValue::Array(elements) => join_array(elements, ","),
+            RuntimeValue::Function(function) => match (&function.id, function.is_class_constructor)
+            {
+                (Some(id), true) => format!("class {} {{ [code] }}", id),
+                (None, true) => "class { [code] }".to_string(),
+                (Some(id), false) => format!("function {}() {{ [code] }}", id),
+                (None, false) => "function () { [code] }".to_string(),
+            },
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone)]
+// 関数オブジェクト。関数が定義されたスコープを保持する（クロージャ）
+pub struct Function {
+    id: Option<String>,
+    params: Vec<Option<Rc<Node>>>,
+    body: Option<Rc<Node>>,
+    env: Option<Rc<RefCell<Environment>>>,
+    // バイトコードにコンパイルされた関数の場合、bodyの代わりに実行するコードと、関数が定義されたスコープ
+    code: Option<Rc<Code>>,
+    scope: Option<Rc<Scope>>,
+    // アロー関数はthisを持たない
+    is_arrow: bool,
+    // classで定義されたコンストラクタ。newを使わずに呼び出すことはできない
+    is_class_constructor: bool,
+    // メソッドが定義されたオブジェクト。superはこのオブジェクトのプロトタイプを表す
+    home_object: Option<RuntimeValue>,
+    // ネイティブ関数の場合、bodyの代わりに実行されるRustの関数
+    native: Option<NativeFunction>,
+    // 値を捕捉するネイティブ関数の場合、実行されるRustの関数と捕捉した値
+    closure: Option<NativeClosure>,
+    captures: RefCell<Vec<RuntimeValue>>,
+    // prototypeや静的メソッドのような関数自身のプロパティ
+    properties: RefCell<JsObject>,
+}
+
+impl Function {
+    fn new(
+        id: Option<String>,
+        params: Vec<Option<Rc<Node>>>,
+        body: Option<Rc<Node>>,
+        env: Option<Rc<RefCell<Environment>>>,
+        is_arrow: bool,
+    ) -> Self {
+        Self {
+            id,
+            params,
+            body,
+            env,
+            code: None,
+            scope: None,
+            is_arrow,
+            is_class_constructor: false,
+            home_object: None,
+            native: None,
+            closure: None,
+            captures: RefCell::new(Vec::new()),
+            properties: RefCell::new(JsObject::new()),
+        }
+    }
+
+    pub fn new_native(name: &str, native: NativeFunction) -> Self {
+        Self {
+            id: Some(name.to_string()),
+            params: Vec::new(),
+            body: None,
+            env: None,
+            code: None,
+            scope: None,
+            is_arrow: false,
+            is_class_constructor: false,
+            home_object: None,
+            native: Some(native),
+            closure: None,
+            captures: RefCell::new(Vec::new()),
+            properties: RefCell::new(JsObject::new()),
+        }
+    }
+
+    pub fn new_native_closure(
+        name: &str,
+        closure: NativeClosure,
+        captures: Vec<RuntimeValue>,
+    ) -> Self {
+        Self {
+            id: Some(name.to_string()),
+            params: Vec::new(),
+            body: None,
+            env: None,
+            code: None,
+            scope: None,
+            is_arrow: false,
+            is_class_constructor: false,
+            home_object: None,
+            native: None,
+            closure: Some(closure),
+            captures: RefCell::new(captures),
+            properties: RefCell::new(JsObject::new()),
+        }
+    }
+
+    // コンパイルされた関数。scopeは関数が定義されたスコープ
+    pub fn new_compiled(code: Rc<Code>, scope: Option<Rc<Scope>>) -> Self {
+        Self {
+            id: code.name.clone(),
+            params: Vec::new(),
+            body: None,
+            env: None,
+            is_arrow: code.kind == FunctionKind::Arrow,
+            code: Some(code),
+            scope,
+            is_class_constructor: false,
+            home_object: None,
+            native: None,
+            closure: None,
+            captures: RefCell::new(Vec::new()),
+            properties: RefCell::new(JsObject::new()),
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    pub fn scope(&self) -> Option<Rc<Scope>> {
+        self.scope.clone()
+    }
+
+    pub fn home_object(&self) -> Option<RuntimeValue> {
+        self.home_object.clone()
+    }
+
+    pub fn is_class_constructor(&self) -> bool {
+        self.is_class_constructor
+    }
+
+    // prototypeのような関数自身のプロパティの値
+    pub fn property(&self, key: &str) -> RuntimeValue {
+        RefCell::borrow(&self.properties).get(key)
+    }
+}
+
+// スコープとhome_objectは、それぞれがGCに登録されているため、循環はそちらで切る
+impl Trace for Function {
+    fn trace(&self, marker: &mut Marker) {
+        self.properties.trace(marker);
+        if let Some(env) = &self.env {
+            marker.mark_environment(env);
+        }
+        if let Some(scope) = &self.scope {
+            marker.mark_scope(scope);
+        }
+        if let Some(home) = &self.home_object {
+            marker.mark_value(home);
+        }
+        for value in RefCell::borrow(&self.captures).iter() {
+            marker.mark_value(value);
+        }
+    }
+
+    fn clear(&self, releaser: &mut Releaser) {
+        self.properties.clear(releaser);
+        for value in self.captures.borrow_mut().drain(..) {
+            releaser.push_value(value);
+        }
+    }
+}
+
+impl Function {
+    // プロパティ、捕捉した値、スコープをreleaserへ移す
+    pub fn release(&mut self, releaser: &mut Releaser) {
+        self.properties.get_mut().release(releaser);
+        for value in self.captures.get_mut().drain(..) {
+            releaser.push_value(value);
+        }
+        if let Some(env) = self.env.take() {
+            releaser.push_environment(env);
+        }
+        if let Some(scope) = self.scope.take() {
+            releaser.push_scope(scope);
+        }
+        if let Some(home) = self.home_object.take() {
+            releaser.push_value(home);
+        }
+    }
+}
+
+impl Drop for Function {
+    fn drop(&mut self) {
+        let mut releaser = Releaser::default();
+        self.release(&mut releaser);
+        releaser.release_all();
+    }
+}
+
+// 関数は自身を含むスコープを保持するため、スコープは表示しない
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Function")
+            .field("id", &self.id)
+            .field("params", &self.params)
+            .field("is_arrow", &self.is_arrow)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use crate::renderer::js::{ast::JsParser, token::JsLexer};
+
+    use super::*;
+
+    use crate::renderer::dom::node::NodeKind as DomNodeKind;
+    use crate::renderer::html::{parser::HtmlParser, token::HtmlTokenizer};
+
+    #[test]
+    fn test_num() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "42".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [Some(RuntimeValue::Number(42.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_add_nums() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "1 + 2".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [Some(RuntimeValue::Number(3.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_sub_nums() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "3 - 1".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [Some(RuntimeValue::Number(2.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_assign_variable() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var foo=42;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_add_variable_and_num() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var foo=42; foo+1".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, Some(RuntimeValue::Number(43.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_reassign_variable() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var foo=42; foo=1; foo".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, None, Some(RuntimeValue::Number(1.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_add_function_and_num() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "function foo() { return 42; } foo()+1".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, Some(RuntimeValue::Number(43.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_local_variable() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var a=42; function foo() { var a=1; return a; } foo()+a".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, None, Some(RuntimeValue::Number(43.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "1 + 2 * 3; (1 + 2) * 3; 7 % 4 - 10 / 5; 10 - 2 - 3".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            Some(RuntimeValue::Number(7.0)),
+            Some(RuntimeValue::Number(9.0)),
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::Number(5.0)),
+        ];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_comparison_and_logical() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input =
+            "1 < 2 && 2 <= 2; 1 == \"1\"; 1 === \"1\"; !0 || false; 3 > 4 ? \"yes\" : \"no\""
+                .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(false)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::StringLiteral("no".to_string())),
+        ];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_if_else() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input =
+            "var a=0; if (a == 1) { a = 10; } else if (a == 0) { a = 20; } else { a = 30; } a"
+                .to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, None, Some(RuntimeValue::Number(20.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_while_break_continue() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var i=0; var sum=0; while (true) { i = i + 1; if (i > 5) { break; } if (i % 2 == 0) { continue; } sum = sum + i; } sum".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, None, None, Some(RuntimeValue::Number(9.0))];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
 
-                let callee_value = match self.eval(callee, new_env.clone()) {
-                    Some(value) => value,
-                    None => return None,
-                };
+    #[test]
+    fn test_for_and_return() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "function find() { for (var i=0; i < 10; i = i + 1) { if (i * i > 20) { return i; } } return 0; } find()".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [None, Some(RuntimeValue::Number(5.0))];
+        let mut i = 0;
 
-                // ブラウザAPIの呼び出しを試す
-                let api_result = self.call_browser_api(&callee_value, arguments, new_env.clone());
-                if api_result.0 {
-                    // もしブラウザAPIを呼び出していたらユーザーが定義した関数は実行しない
-                    return api_result.1;
-                }
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
 
-                // すでに定義されている関数を探す
-                let function = {
-                    let mut f: Option<Function> = None;
-                    for func in &self.functions {
-                        if callee_value == RuntimeValue::StringLiteral(func.id.to_string()) {
-                            f = Some(func.clone());
-                        }
-                    }
+    #[test]
+    fn test_negative_and_float_numbers() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "1 - 2; -1.5 * 2; 1 / 0; 0x10 + 1e1; 7 % 2.5".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            Some(RuntimeValue::Number(-1.0)),
+            Some(RuntimeValue::Number(-3.0)),
+            Some(RuntimeValue::Number(f64::INFINITY)),
+            Some(RuntimeValue::Number(26.0)),
+            Some(RuntimeValue::Number(2.0)),
+        ];
+        let mut i = 0;
 
-                    match f {
-                        Some(f) => f,
-                        None => panic!("function {:?} doesn't exist", callee),
-                    }
-                };
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
+    }
 
-                // 関数呼び出し時に渡される引数を新しく作成したスコープのローカル変数として割り当てる
-                assert!(arguments.len() == function.params.len());
-                for (i, item) in arguments.iter().enumerate() {
-                    if let Some(RuntimeValue::StringLiteral(name)) =
-                        self.eval(&function.params[i], new_env.clone())
-                    {
-                        new_env
-                            .borrow_mut()
-                            .add_variable(name, self.eval(item, new_env.clone()));
-                    }
-                }
+    #[test]
+    fn test_type_coercion() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "\"1\" + 2; \"3\" * \"4\"; true + 1; null + 1; [1, 2] + \"\"; null == undefined; null == 0; NaN == NaN".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            Some(RuntimeValue::StringLiteral("12".to_string())),
+            Some(RuntimeValue::Number(12.0)),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::StringLiteral("1,2".to_string())),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(false)),
+            Some(RuntimeValue::Boolean(false)),
+        ];
+        let mut i = 0;
 
-                // 関数を新しいスコープと共に呼ぶ
-                self.eval(&function.body.clone(), new_env.clone())
-            }
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
         }
     }
 
-    fn call_browser_api(
-        &mut self,
-        func: &RuntimeValue,
-        arguments: &[Option<Rc<Node>>],
-        env: Rc<RefCell<Environment>>,
-    ) -> (bool, Option<RuntimeValue>) {
-        if func == &RuntimeValue::StringLiteral("document.getElementById".to_string()) {
-            let arg = match self.eval(&arguments[0], env.clone()) {
-                Some(a) => a,
-                None => return (true, None),
-            };
-            let target = match get_element_by_id(Some(self.dom_root.clone()), &arg.to_string()) {
-                Some(n) => n,
-                None => return (true, None),
-            };
-            return (
-                true,
-                Some(RuntimeValue::HtmlElement {
-                    object: target,
-                    property: None,
-                }),
-            );
+    #[test]
+    fn test_object_and_array() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var o = {a: 1, \"b\": [10, 20]}; o.c = o.a + o.b[1]; o.b[3] = 5; o.c; o.b.length; o.b[2]; o.x".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            None,
+            None,
+            None,
+            Some(RuntimeValue::Number(21.0)),
+            Some(RuntimeValue::Number(4.0)),
+            Some(RuntimeValue::Undefined),
+            Some(RuntimeValue::Undefined),
+        ];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
         }
+    }
 
-        (false, None)
+    #[test]
+    fn test_number_to_string() {
+        assert_eq!("NaN", number_to_string(f64::NAN));
+        assert_eq!("-Infinity", number_to_string(f64::NEG_INFINITY));
+        assert_eq!("0", number_to_string(-0.0));
+        assert_eq!("42", number_to_string(42.0));
+        assert_eq!("0.5", number_to_string(0.5));
+        assert!(string_to_number("nan").is_nan());
+        assert_eq!(-12.5, string_to_number(" -12.5 "));
+        assert_eq!(255.0, string_to_number("0xff"));
     }
-}
 
-// JSランタイムで扱う値
-#[derive(Debug, Clone, PartialEq)]
-pub enum RuntimeValue {
-    Number(u64),
-    StringLiteral(String),
-    HtmlElement {
-        object: Rc<RefCell<DomNode>>,
-        property: Option<String>,
-    },
-}
+    #[test]
+    fn test_number_to_string_exponent() {
+        for (value, expected) in [
+            (1e21, "1e+21"),
+            (123456789012345680000.0, "123456789012345680000"),
+            (1.5e300, "1.5e+300"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (-1.23e-20, "-1.23e-20"),
+            (5e-324, "5e-324"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (123.456, "123.456"),
+            (100.0, "100"),
+        ] {
+            assert_eq!(expected, number_to_string(value));
+        }
+    }
 
-impl Add<RuntimeValue> for RuntimeValue {
-    type Output = RuntimeValue;
+    #[test]
+    fn test_string_to_number_prefixes() {
+        for (input, expected) in [
+            ("0b101", 5.0),
+            ("0B11", 3.0),
+            ("0o17", 15.0),
+            (" 0O7 ", 7.0),
+            ("0x1F", 31.0),
+            ("0x1fffffffffffffffff", 590295810358705700000.0),
+            ("1e3", 1000.0),
+            (".5", 0.5),
+        ] {
+            assert_eq!(expected, string_to_number(input));
+        }
+        for input in [
+            "0b", "0b102", "0o8", "-0x10", "+0b1", "0x", "+-1", "1_000", "0xg",
+        ] {
+            assert!(string_to_number(input).is_nan(), "{}", input);
+        }
+    }
 
-    fn add(self, rhs: RuntimeValue) -> RuntimeValue {
-        if let (RuntimeValue::Number(left_num), RuntimeValue::Number(right_num)) = (&self, &rhs) {
-            return RuntimeValue::Number(left_num + right_num);
+    #[test]
+    fn test_closure() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "function counter() { var count = 0; return function() { count = count + 1; return count; }; } var c = counter(); c(); c(); var d = counter(); d();".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            None,
+            None,
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::Number(2.0)),
+            None,
+            Some(RuntimeValue::Number(1.0)),
+        ];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
         }
+    }
+
+    #[test]
+    fn test_arrow_function_and_this() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var o = {n: 2, get: function() { return this.n; }, later: function() { return () => this.n * 10; }}; o.get(); o.later()(); var add = (a, b) => a + b; add(1, 2); add(1);".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            None,
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::Number(20.0)),
+            None,
+            Some(RuntimeValue::Number(3.0)),
+            Some(RuntimeValue::Number(f64::NAN)),
+        ];
+        let mut i = 0;
 
-        RuntimeValue::StringLiteral(self.to_string() + &rhs.to_string())
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            // NaNは自身と等しくないので別に確認する
+            match (&expected[i], &result) {
+                (Some(RuntimeValue::Number(e)), Some(RuntimeValue::Number(r))) if e.is_nan() => {
+                    assert!(r.is_nan())
+                }
+                _ => assert_eq!(expected[i], result),
+            }
+            i += 1;
+        }
     }
-}
 
-impl Sub<RuntimeValue> for RuntimeValue {
-    type Output = RuntimeValue;
+    #[test]
+    fn test_let_block_scope() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "let x = 1; { let x = 2; var y = x; } x; y; var fs = []; for (let i = 0; i < 3; i = i + 1) { fs[i] = () => i; } fs[0]() + fs[2]();".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            None,
+            None,
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::Number(2.0)),
+            None,
+            None,
+            Some(RuntimeValue::Number(2.0)),
+        ];
+        let mut i = 0;
 
-    fn sub(self, rhs: RuntimeValue) -> RuntimeValue {
-        if let (RuntimeValue::Number(left_num), RuntimeValue::Number(right_num)) = (&self, &rhs) {
-            return RuntimeValue::Number(left_num - right_num);
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
         }
+    }
 
-        // NaN: Not a number
-        RuntimeValue::Number(u64::MIN)
+    #[test]
+    fn test_hoisting() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input =
+            "var r = f(); function f() { return g(); function g() { return 5; } }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        runtime.execute(&ast).expect("failed to execute");
+        assert_eq!(
+            Some(RuntimeValue::Number(5.0)),
+            RefCell::borrow(&runtime.env).get_variable("r".to_string())
+        );
+    }
+
+    #[test]
+    fn test_let_temporal_dead_zone() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "x; let x = 1;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let exception = runtime.execute(&ast).expect_err("should throw");
+        assert_eq!(
+            "ReferenceError: cannot access 'x' before initialization",
+            exception.message()
+        );
     }
-}
 
-type VariableMap = Vec<(String, Option<RuntimeValue>)>;
+    #[test]
+    fn test_const_assignment() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "const x = 1; x = 2;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let exception = runtime.execute(&ast).expect_err("should throw");
+        assert_eq!(
+            "TypeError: assignment to constant variable 'x'",
+            exception.message()
+        );
+    }
 
-#[derive(Debug, Clone)]
-// JSの変数のスコープ管理を行う
-// スコープ：関数、変数が使用可能な範囲のこと。内側のスコープは外側のスコープの値を参考できるが逆はできない
-pub struct Environment {
-    variables: VariableMap,
-    // 外部スコープを表す
-    outer: Option<Rc<RefCell<Environment>>>,
-}
+    #[test]
+    fn test_try_catch_finally() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var log = \"\"; function f() { try { throw \"a\"; } catch (e) { log = log + e; return 1; } finally { log = log + \"f\"; } } f(); try { undefinedFunction(); } catch (e) { e.name; } try { null(); } catch { 2; } log;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            None,
+            None,
+            Some(RuntimeValue::Number(1.0)),
+            Some(RuntimeValue::StringLiteral("ReferenceError".to_string())),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::StringLiteral("af".to_string())),
+        ];
+        let mut i = 0;
 
-impl Environment {
-    fn new(outer: Option<Rc<RefCell<Environment>>>) -> Self {
-        Self {
-            variables: VariableMap::new(),
-            outer,
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
         }
     }
 
-    pub fn get_variable(&self, name: String) -> Option<RuntimeValue> {
-        for variable in &self.variables {
-            if variable.0 == name {
-                return variable.1.clone();
-            }
-        }
-        if let Some(env) = &self.outer {
-            env.borrow_mut().get_variable(name)
-        } else {
-            None
-        }
+    #[test]
+    fn test_uncaught_error() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var a = 1;\nvar o = {};\n  o.f();\na = 2;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let exception = runtime.execute(&ast).expect_err("should throw");
+        assert_eq!(
+            "Uncaught TypeError: o.f is not a function (3:3)",
+            exception.to_string()
+        );
+        // 例外が発生した後の文は実行しない
+        assert_eq!(
+            Some(RuntimeValue::Number(1.0)),
+            RefCell::borrow(&runtime.env).get_variable("a".to_string())
+        );
+
+        let error = exception.value();
+        assert_eq!(RuntimeValue::Number(3.0), error.get_property("lineNumber"));
+        assert_eq!(
+            RuntimeValue::Number(3.0),
+            error.get_property("columnNumber")
+        );
     }
 
-    fn add_variable(&mut self, name: String, value: Option<RuntimeValue>) {
-        self.variables.push((name, value));
+    #[test]
+    fn test_throw_error_object() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "throw TypeError(\"bad\");".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let exception = runtime.execute(&ast).expect_err("should throw");
+        assert_eq!("Uncaught TypeError: bad (1:1)", exception.to_string());
     }
 
-    fn update_variable(&mut self, name: String, value: Option<RuntimeValue>) {
-        for i in 0..self.variables.len() {
-            if self.variables[i].0 == name {
-                self.variables.remove(i);
-                self.variables.push((name, value));
-                return;
-            }
+    #[test]
+    fn test_math() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "Math.floor(-1.5); Math.ceil(1.2); Math.round(2.5); Math.max(1, 3, 2); Math.min(); Math.sqrt(16); Math.pow(2, 10); Math.pow(4, 0.5); Math.abs(-3); var r = Math.random(); r >= 0 && r < 1;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            Some(RuntimeValue::Number(-2.0)),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::Number(3.0)),
+            Some(RuntimeValue::Number(3.0)),
+            Some(RuntimeValue::Number(f64::INFINITY)),
+            Some(RuntimeValue::Number(4.0)),
+            Some(RuntimeValue::Number(1024.0)),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::Number(3.0)),
+            None,
+            Some(RuntimeValue::Boolean(true)),
+        ];
+        let mut i = 0;
+
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
         }
     }
-}
 
-impl Display for RuntimeValue {
-    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
-        let s = match self {
-            RuntimeValue::Number(value) => format!("{}", value),
-            RuntimeValue::StringLiteral(value) => value.to_string(),
-            RuntimeValue::HtmlElement {
-                object,
-                property: _,
-            } => {
-                format!("HtmlElement: {:#?}", object)
+    #[test]
+    fn test_math_precision() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "Math.sqrt(2); Math.sqrt(5e-324); Math.exp(1); Math.log(Math.E); Math.pow(10, -5); Math.pow(10, 308); Math.pow(2, 0.5); Math.pow(1, Infinity); Math.pow(NaN, 0); Math.round(0.49999999999999994); Math.round(-2.5); 1 / Math.round(-0.4); Math.round(4503599627370497);".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            1.4142135623730951,
+            2.2227587494850775e-162,
+            core::f64::consts::E,
+            1.0,
+            0.00001,
+            1e308,
+            core::f64::consts::SQRT_2,
+            f64::NAN,
+            1.0,
+            0.0,
+            -2.0,
+            f64::NEG_INFINITY,
+            4503599627370497.0,
+        ];
+
+        for (node, expected) in ast.body().iter().zip(expected) {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            match result {
+                Some(RuntimeValue::Number(n)) if expected.is_nan() => assert!(n.is_nan()),
+                _ => assert_eq!(Some(RuntimeValue::Number(expected)), result),
             }
-        };
-        write!(f, "{}", s)
+        }
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Function {
-    id: String,
-    params: Vec<Option<Rc<Node>>>,
-    body: Option<Rc<Node>>,
-}
+    #[test]
+    fn test_string_methods() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "var s = \" Hello, World \".trim(); s.length; s.slice(-5); s.substring(5, 0); s.indexOf(\"o\"); s.lastIndexOf(\"o\"); s.toUpperCase(); s.split(\", \").length; s.charAt(1); s.charCodeAt(0); s.includes(\"World\"); s.replace(\"World\", \"JS\"); \"ab\".repeat(3); (1.005).toFixed(1); (255).toString(16);".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            None,
+            Some(RuntimeValue::Number(12.0)),
+            Some(RuntimeValue::StringLiteral("World".to_string())),
+            Some(RuntimeValue::StringLiteral("Hello".to_string())),
+            Some(RuntimeValue::Number(4.0)),
+            Some(RuntimeValue::Number(8.0)),
+            Some(RuntimeValue::StringLiteral("HELLO, WORLD".to_string())),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::StringLiteral("e".to_string())),
+            Some(RuntimeValue::Number(72.0)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::StringLiteral("Hello, JS".to_string())),
+            Some(RuntimeValue::StringLiteral("ababab".to_string())),
+            Some(RuntimeValue::StringLiteral("1.0".to_string())),
+            Some(RuntimeValue::StringLiteral("ff".to_string())),
+        ];
+        let mut i = 0;
 
-impl Function {
-    fn new(id: String, params: Vec<Option<Rc<Node>>>, body: Option<Rc<Node>>) -> Self {
-        Self { id, params, body }
+        for node in ast.body() {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(expected[i], result);
+            i += 1;
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use alloc::string::ToString;
-
-    use crate::renderer::js::{ast::JsParser, token::JsLexer};
 
-    use super::*;
+    #[test]
+    fn test_number_to_string_radix() {
+        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
+        let input = "(0.5).toString(16); (255.5).toString(16); (-0.5).toString(2); (0.1).toString(2); (1e21).toString(16); (-255).toString(36); (0.1).toString(3); (1e21).toString(); (1e-7).toString();".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast().expect("failed to parse");
+        let mut runtime = JsRuntime::new(dom);
+        let expected = [
+            "0.8",
+            "ff.8",
+            "-0.1",
+            "0.0001100110011001100110011001100110011001100110011001101",
+            "3635c9adc5dea00000",
+            "-73",
+            "0.0022002200220022002200220022002201",
+            "1e+21",
+            "1e-7",
+        ];
 
-    use crate::renderer::dom::node::NodeKind as DomNodeKind;
+        for (node, expected) in ast.body().iter().zip(expected) {
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
+            assert_eq!(
+                Some(RuntimeValue::StringLiteral(expected.to_string())),
+                result
+            );
+        }
+    }
 
     #[test]
-    fn test_num() {
+    fn test_array_methods() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "42".to_string();
+        let input = "var a = [3, 1, 2]; a.push(4); a.map((x) => x * 2).join(\"-\"); a.filter(function(x) { return x % 2 === 1; }).length; var sum = 0; a.forEach((x) => { sum = sum + x; }); sum; a.reduce((acc, x) => acc + x, 10); a.sort().join(); a.indexOf(3); a.slice(1, 3).toString(); a.pop(); a.concat([5], 6).length; Array.isArray(a); a.find((x) => x > 1); a.some((x) => x > 2); a.every((x) => x > 2);".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [Some(RuntimeValue::Number(42))];
+        let expected = [
+            None,
+            Some(RuntimeValue::Number(4.0)),
+            Some(RuntimeValue::StringLiteral("6-2-4-8".to_string())),
+            Some(RuntimeValue::Number(2.0)),
+            None,
+            Some(RuntimeValue::Undefined),
+            Some(RuntimeValue::Number(10.0)),
+            Some(RuntimeValue::Number(20.0)),
+            Some(RuntimeValue::StringLiteral("1,2,3,4".to_string())),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::StringLiteral("2,3".to_string())),
+            Some(RuntimeValue::Number(4.0)),
+            Some(RuntimeValue::Number(5.0)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Number(2.0)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(false)),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_add_nums() {
+    fn test_global_functions() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "1 + 2".to_string();
+        let input = "parseInt(\"42px\"); parseInt(\"0x1f\"); isNaN(parseInt(\"z\")); parseFloat(\"3.5e2abc\"); isNaN(parseFloat(\".\")); Object.keys({a: 1, b: 2}).join(); Object.values([7, 8]).join(); String(12) + Number(\"3\");".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [Some(RuntimeValue::Number(3))];
+        let expected = [
+            Some(RuntimeValue::Number(42.0)),
+            Some(RuntimeValue::Number(31.0)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Number(350.0)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::StringLiteral("a,b".to_string())),
+            Some(RuntimeValue::StringLiteral("7,8".to_string())),
+            Some(RuntimeValue::StringLiteral("123".to_string())),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_sub_nums() {
+    fn test_json() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "3 - 1".to_string();
+        let input = "var o = JSON.parse(\"{\\\"a\\\": [1, 2.5, \\\"x\\\\n\\\"], \\\"b\\\": {\\\"c\\\": null}, \\\"d\\\": true}\"); o.a[1]; o.b.c; JSON.stringify(o); JSON.stringify({u: undefined, f: function() {}, n: [undefined]}); JSON.stringify([1, {a: 2}], null, 2);".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [Some(RuntimeValue::Number(2))];
+        let expected = [
+            None,
+            Some(RuntimeValue::Number(2.5)),
+            Some(RuntimeValue::Null),
+            Some(RuntimeValue::StringLiteral(
+                "{\"a\":[1,2.5,\"x\\n\"],\"b\":{\"c\":null},\"d\":true}".to_string(),
+            )),
+            Some(RuntimeValue::StringLiteral("{\"n\":[null]}".to_string())),
+            Some(RuntimeValue::StringLiteral(
+                "[\n  1,\n  {\n    \"a\": 2\n  }\n]".to_string(),
+            )),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_assign_variable() {
+    fn test_builtin_errors() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "var foo=42;".to_string();
+        let input = "var o = {}; o.self = o; try { JSON.stringify(o); } catch (e) { e.name; } try { JSON.parse(\"{\"); } catch (e) { e.name; } try { [].reduce((a, b) => a); } catch (e) { e.message; } try { undefined.x; } catch (e) { e.message; }".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None];
+        let expected = [
+            None,
+            None,
+            Some(RuntimeValue::StringLiteral("TypeError".to_string())),
+            Some(RuntimeValue::StringLiteral("SyntaxError".to_string())),
+            Some(RuntimeValue::StringLiteral(
+                "reduce of empty array with no initial value".to_string(),
+            )),
+            Some(RuntimeValue::StringLiteral(
+                "cannot read properties of undefined (reading 'x')".to_string(),
+            )),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_add_variable_and_num() {
+    fn test_constructor_and_prototype() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "var foo=42; foo+1".to_string();
+        let input = "function Point(x, y) { this.x = x; this.y = y; } Point.prototype.sum = function() { return this.x + this.y; }; var p = new Point(1, 2); p.sum(); p instanceof Point; p instanceof Object; [] instanceof Array; Object.getPrototypeOf(p) === Point.prototype; p.hasOwnProperty(\"sum\");".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, Some(RuntimeValue::Number(43))];
+        let expected = [
+            None,
+            None,
+            None,
+            Some(RuntimeValue::Number(3.0)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(false)),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_reassign_variable() {
+    fn test_class_inheritance() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "var foo=42; foo=1; foo".to_string();
+        let input = "class Animal { constructor(name) { this.name = name; } speak() { return this.name + \" makes a sound\"; } static create(name) { return new this(name); } } class Dog extends Animal { speak() { return super.speak() + \" (woof)\"; } } var d = Dog.create(\"Rex\"); d.speak(); d instanceof Animal; d.constructor === Dog; try { Dog(); } catch (e) { e.name; }".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, None, Some(RuntimeValue::Number(1))];
+        let expected = [
+            None,
+            None,
+            None,
+            Some(RuntimeValue::StringLiteral(
+                "Rex makes a sound (woof)".to_string(),
+            )),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::StringLiteral("TypeError".to_string())),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_add_function_and_num() {
+    fn test_getter_setter_and_error_subclass() {
         let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "function foo() { return 42; } foo()+1".to_string();
+        let input = "var o = { v: 1, get double() { return this.v * 2; }, set double(x) { this.v = x / 2; } }; o.double = 10; o.v; o.double; class MyError extends Error { constructor(m) { super(m); this.name = \"MyError\"; } } var e = new MyError(\"oops\"); e instanceof Error; e.message; e.toString();".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, Some(RuntimeValue::Number(43))];
+        let expected = [
+            None,
+            None,
+            Some(RuntimeValue::Number(5.0)),
+            Some(RuntimeValue::Number(10.0)),
+            None,
+            None,
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::StringLiteral("oops".to_string())),
+            Some(RuntimeValue::StringLiteral("MyError: oops".to_string())),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
     }
 
     #[test]
-    fn test_local_variable() {
-        let dom = Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document)));
-        let input = "var a=42; function foo() { var a=1; return a; } foo()+a".to_string();
+    fn test_dom_wrapper() {
+        let html = "<html><head></head><body><p id=\"target\">hello</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let dom = RefCell::borrow(&window).document();
+        let input = "var t = document.getElementById(\"target\"); t.textContent; t instanceof HTMLElement; t instanceof Node; t === document.getElementById(\"target\"); t.tagName; t.textContent = \"bye\"; document.body.textContent;".to_string();
         let lexer = JsLexer::new(input);
         let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+        let ast = parser.parse_ast().expect("failed to parse");
         let mut runtime = JsRuntime::new(dom);
-        let expected = [None, None, Some(RuntimeValue::Number(43))];
+        let expected = [
+            None,
+            Some(RuntimeValue::StringLiteral("hello".to_string())),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::Boolean(true)),
+            Some(RuntimeValue::StringLiteral("P".to_string())),
+            None,
+            Some(RuntimeValue::StringLiteral("bye".to_string())),
+        ];
         let mut i = 0;
 
         for node in ast.body() {
-            let result = runtime.eval(&Some(node.clone()), runtime.env.clone());
+            let result = runtime
+                .eval(&Some(node.clone()), runtime.env.clone())
+                .expect("failed to evaluate");
             assert_eq!(expected[i], result);
             i += 1;
         }
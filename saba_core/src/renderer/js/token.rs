@@ -2,16 +2,172 @@ use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
+use core::fmt::{Display, Formatter};
 
-static RESERVED_WORDS: [&str; 3] = ["var", "function", "return"];
+static RESERVED_WORDS: [&str; 33] = [
+    "var",
+    "function",
+    "return",
+    "if",
+    "else",
+    "while",
+    "for",
+    "break",
+    "continue",
+    "true",
+    "false",
+    "null",
+    "let",
+    "const",
+    "this",
+    "try",
+    "catch",
+    "finally",
+    "throw",
+    "new",
+    "class",
+    "extends",
+    "super",
+    "instanceof",
+    "import",
+    "export",
+    "do",
+    "switch",
+    "case",
+    "in",
+    "typeof",
+    "void",
+    "delete",
+];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// 2文字以上の記号。長いものから順に確認する
+static MULTI_CHAR_PUNCTUATORS: [&str; 33] = [
+    ">>>=", "===", "!==", "**=", "<<=", ">>=", ">>>", "...", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "**", "<<", ">>",
+];
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Punctuator(char),
-    Number(u64),
+    // ===や&&のように2文字以上で構成される記号
+    MultiCharPunctuator(String),
+    Number(f64),
     Identifier(String),
     Keyword(String),
     StringLiteral(String),
+    // テンプレートリテラルの${}で区切られた文字列の部分。tailは`で終わる最後の部分
+    // rawはエスケープを解釈しない、ソースコードのままの文字列。タグ付きテンプレートで使う
+    // headは`で始まる最初の部分
+    Template {
+        cooked: String,
+        raw: String,
+        head: bool,
+        tail: bool,
+    },
+    // /pattern/flagsの正規表現リテラル
+    RegExp {
+        pattern: String,
+        flags: String,
+    },
+    // 字句として解釈できない文字
+    Invalid(char),
+    // 閉じていない文字列のような字句のエラー
+    Error(String),
+}
+
+// 構文エラーのメッセージで使う、ソースコード上での表記
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            Token::Punctuator(c) | Token::Invalid(c) => write!(f, "{}", c),
+            Token::MultiCharPunctuator(s)
+            | Token::Identifier(s)
+            | Token::Keyword(s)
+            | Token::Error(s) => write!(f, "{}", s),
+            Token::Number(value) => write!(f, "{}", value),
+            Token::StringLiteral(s) => write!(f, "\"{}\"", s),
+            Token::Template {
+                cooked, tail: true, ..
+            } => write!(f, "`{}`", cooked),
+            Token::Template {
+                cooked,
+                tail: false,
+                ..
+            } => write!(f, "`{}${{", cooked),
+            Token::RegExp { pattern, flags } => write!(f, "/{}/{}", pattern, flags),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// ソースコード上の位置。行と列は1から数える
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// トークンのソースコード上の範囲。endはトークンの直後の位置
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// /から始まる字句の読み方。式の始まりでは正規表現リテラル、値の後では除算として読む
+pub enum Goal {
+    RegExp,
+    Div,
+}
+
+#[derive(Debug, Clone)]
+// /から始まるトークンを別の読み方で読み直すために、トークンを読む直前の状態を保存したもの
+pub struct Rewind {
+    pos: usize,
+    scanned: usize,
+    line: usize,
+    line_start: usize,
+    braces: Vec<bool>,
+    newline_before: bool,
+    goal: Goal,
+}
+
+impl Rewind {
+    // 保存したトークンを読んだときの読み方
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '$' | '_' | '\u{200c}' | '\u{200d}')
 }
 
 // レキサー：トークナイザー＋α
@@ -19,6 +175,20 @@ pub enum Token {
 pub struct JsLexer {
     pos: usize,
     input: Vec<char>,
+    // 行と列の計算が済んだ位置と、その時点の行番号と行の先頭の位置
+    scanned: usize,
+    line: usize,
+    line_start: usize,
+    // 最後に返したトークン。パーサーが読み方を指定しない間、/が除算か正規表現リテラルかを推測するために使う
+    last: Option<Token>,
+    // 最後に返したトークンが/から始まる場合、読み直すための状態
+    rewind: Option<Rewind>,
+    // 閉じていない{の種類。trueはテンプレートリテラルの${で、対応する}の後に文字列が続く
+    braces: Vec<bool>,
+    // 最後に返したトークンの前に改行があったか。自動セミコロン挿入で使う
+    newline_before: bool,
+    // <!--と-->をコメントとして扱うか。モジュールでは使えない
+    html_comments: bool,
 }
 
 impl JsLexer {
@@ -26,129 +196,612 @@ impl JsLexer {
         Self {
             pos: 0,
             input: js.chars().collect(),
+            scanned: 0,
+            line: 1,
+            line_start: 0,
+            last: None,
+            rewind: None,
+            braces: Vec::new(),
+            newline_before: false,
+            html_comments: true,
         }
     }
 
-    fn consume_number(&mut self) -> u64 {
-        let mut num = 0;
+    // モジュールとして読む。HTMLのようなコメントを使えなくする
+    pub fn set_module(&mut self) {
+        self.html_comments = false;
+    }
 
-        loop {
-            if self.pos >= self.input.len() {
-                return num;
+    // posの位置の行と列を返す。posは前回より後ろの位置でなければならない
+    fn position_at(&mut self, pos: usize) -> Position {
+        while self.scanned < pos {
+            let c = self.input[self.scanned];
+            // \r\nは1つの改行として数える
+            if is_line_terminator(c)
+                && !(c == '\r' && self.input.get(self.scanned + 1) == Some(&'\n'))
+            {
+                self.line += 1;
+                self.line_start = self.scanned + 1;
             }
+            self.scanned += 1;
+        }
+        Position::new(self.line, pos - self.line_start + 1)
+    }
 
-            let c = self.input[self.pos];
+    fn peek_char(&self, offset: usize) -> Option<char> {
+        self.input.get(self.pos + offset).copied()
+    }
 
-            match c {
-                '0'..='9' => {
-                    num = num * 10 + (c.to_digit(10).unwrap() as u64);
-                    self.pos += 1;
+    fn is_digit_at(&self, pos: usize) -> bool {
+        matches!(self.input.get(pos), Some(c) if c.is_ascii_digit())
+    }
+
+    // 空白、改行、コメントを読み飛ばす。閉じていないコメントの場合はその開始位置を返す
+    fn skip_whitespace(&mut self) -> Result<(), usize> {
+        // スクリプトの先頭の#!から始まる行はコメントとして扱う
+        if self.pos == 0 && self.peek_char(0) == Some('#') && self.peek_char(1) == Some('!') {
+            self.skip_line();
+        }
+
+        while let Some(c) = self.peek_char(0) {
+            if is_line_terminator(c) {
+                self.newline_before = true;
+                self.pos += 1;
+            } else if c.is_whitespace() || c == '\u{feff}' {
+                self.pos += 1;
+            } else if self.contains("//") || self.is_html_comment() {
+                self.skip_line();
+            } else if c == '/' && self.peek_char(1) == Some('*') {
+                let start = self.pos;
+                self.pos += 2;
+                loop {
+                    match self.peek_char(0) {
+                        None => return Err(start),
+                        Some('*') if self.peek_char(1) == Some('/') => {
+                            self.pos += 2;
+                            break;
+                        }
+                        Some(c) => {
+                            // 改行を含む複数行コメントは改行として扱う
+                            if is_line_terminator(c) {
+                                self.newline_before = true;
+                            }
+                            self.pos += 1;
+                        }
+                    }
                 }
-                _ => break,
+            } else {
+                break;
             }
         }
+        Ok(())
+    }
 
-        return num;
+    // スクリプトでは<!--と、行の先頭の-->を//と同じ1行のコメントとして扱う
+    fn is_html_comment(&self) -> bool {
+        self.html_comments
+            && (self.contains("<!--")
+                || ((self.newline_before || self.last.is_none()) && self.contains("-->")))
     }
 
-    fn contains(&self, keyword: &str) -> bool {
-        for i in 0..keyword.len() {
-            if keyword
-                .chars()
-                .nth(i)
-                .expect("failed to access to i-th char")
-                != self.input[self.pos + i]
-            {
-                return false;
-            }
+    // 改行の手前まで読み飛ばす
+    fn skip_line(&mut self) {
+        while matches!(self.peek_char(0), Some(c) if !is_line_terminator(c)) {
+            self.pos += 1;
         }
-        true
     }
 
-    fn check_reserved_word(&self) -> Option<String> {
-        for word in RESERVED_WORDS {
-            if self.contains(word) {
-                return Some(word.to_string());
+    // 数字を読む。1_000のように数字の間の_は区切りとして無視する
+    fn consume_digits(&mut self, radix: u32) -> Result<String, String> {
+        let mut digits = String::new();
+        loop {
+            match self.peek_char(0) {
+                Some(c) if c.is_digit(radix) => {
+                    digits.push(c);
+                    self.pos += 1;
+                }
+                Some('_')
+                    if !digits.is_empty()
+                        && matches!(self.peek_char(1), Some(c) if c.is_digit(radix)) =>
+                {
+                    self.pos += 1;
+                }
+                Some('_') => {
+                    return Err("numeric separators are only allowed between digits".to_string())
+                }
+                _ => return Ok(digits),
             }
         }
-        None
     }
 
-    fn consume_identifier(&mut self) -> String {
-        let mut result = String::new();
+    // 10進数の整数、小数、指数表記と、0x、0o、0bから始まる16進数、8進数、2進数の整数を読む
+    fn consume_number(&mut self) -> Result<f64, String> {
+        let radix = match (self.peek_char(0), self.peek_char(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
 
-        loop {
-            if self.pos >= self.input.len() {
-                return result;
+        let value = if radix == 10 {
+            let mut number = self.consume_digits(10)?;
+            if self.peek_char(0) == Some('.') {
+                self.pos += 1;
+                number.push('.');
+                number.push_str(&self.consume_digits(10)?);
+            }
+
+            // 指数部：1e3、1.5e-3など
+            if matches!(self.peek_char(0), Some('e' | 'E')) {
+                let sign = matches!(self.peek_char(1), Some('+' | '-'));
+                let digit_pos = if sign { self.pos + 2 } else { self.pos + 1 };
+                if self.is_digit_at(digit_pos) {
+                    number.push('e');
+                    if sign {
+                        number.push(self.input[self.pos + 1]);
+                    }
+                    self.pos = digit_pos;
+                    number.push_str(&self.consume_digits(10)?);
+                }
             }
+            number.parse::<f64>().unwrap_or(f64::NAN)
+        } else {
+            self.pos += 2;
+            let digits = self.consume_digits(radix)?;
+            if digits.is_empty() {
+                return Err("missing digits after the radix prefix".to_string());
+            }
+            // 64ビットを超える大きな整数も表せるように浮動小数点数で計算する
+            digits.chars().fold(0.0, |value, c| {
+                value * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64
+            })
+        };
+
+        match self.peek_char(0) {
+            Some('n') => Err("BigInt literals are not supported".to_string()),
+            // 3inのように数値の直後に識別子が続くことはできない
+            Some(c) if is_identifier_start(c) || c.is_ascii_digit() || c == '\\' => {
+                Err("identifier starts immediately after numeric literal".to_string())
+            }
+            _ => Ok(value),
+        }
+    }
+
+    fn contains(&self, keyword: &str) -> bool {
+        keyword
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_char(i) == Some(c))
+    }
 
-            if self.input[self.pos].is_ascii_alphanumeric() || self.input[self.pos] == '$' {
-                result.push(self.input[self.pos]);
+    fn check_multi_char_punctuator(&self) -> Option<&'static str> {
+        let punctuator = MULTI_CHAR_PUNCTUATORS
+            .iter()
+            .find(|punctuator| self.contains(punctuator))?;
+        // a?.5:0は条件演算子と小数
+        if *punctuator == "?." && self.is_digit_at(self.pos + 2) {
+            return None;
+        }
+        Some(punctuator)
+    }
+
+    // 識別子を読む。\u0061のようなエスケープシーケンスも使える
+    fn consume_identifier(&mut self) -> Result<String, String> {
+        let mut result = String::new();
+
+        while let Some(c) = self.peek_char(0) {
+            if c == '\\' {
+                if self.peek_char(1) != Some('u') {
+                    return Err("invalid Unicode escape sequence".to_string());
+                }
+                self.pos += 2;
+                let c = self.consume_unicode_escape()?;
+                let valid = if result.is_empty() {
+                    is_identifier_start(c)
+                } else {
+                    is_identifier_part(c)
+                };
+                if !valid {
+                    return Err("invalid Unicode escape sequence".to_string());
+                }
+                result.push(c);
+            } else if is_identifier_part(c) {
+                result.push(c);
                 self.pos += 1;
             } else {
-                return result;
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    // n桁の16進数を読む
+    fn consume_hex_digits(&mut self, n: usize) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..n {
+            value = value * 16 + self.peek_char(i)?.to_digit(16)?;
+        }
+        self.pos += n;
+        Some(value)
+    }
+
+    // \uの後のXXXXか{X...}を読む
+    fn consume_code_point(&mut self) -> Option<u32> {
+        if self.peek_char(0) != Some('{') {
+            return self.consume_hex_digits(4);
+        }
+        self.pos += 1;
+        let start = self.pos;
+        while matches!(self.peek_char(0), Some(c) if c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        if self.pos == start || self.peek_char(0) != Some('}') {
+            return None;
+        }
+        let digits: String = self.input[start..self.pos].iter().collect();
+        self.pos += 1;
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|code| *code <= 0x10ffff)
+    }
+
+    // \uの後から読む。\uD83D\uDE00のようなサロゲートペアは1文字にまとめる
+    fn consume_unicode_escape(&mut self) -> Result<char, String> {
+        let code = self
+            .consume_code_point()
+            .ok_or_else(|| "invalid Unicode escape sequence".to_string())?;
+
+        if (0xd800..0xdc00).contains(&code)
+            && self.peek_char(0) == Some('\\')
+            && self.peek_char(1) == Some('u')
+        {
+            let saved = self.pos;
+            self.pos += 2;
+            match self.consume_code_point() {
+                Some(low) if (0xdc00..0xe000).contains(&low) => {
+                    let c = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                    return Ok(char::from_u32(c).unwrap_or('\u{fffd}'));
+                }
+                _ => self.pos = saved,
+            }
+        }
+        // 対になっていないサロゲートは文字として表せないので置換文字にする
+        Ok(char::from_u32(code).unwrap_or('\u{fffd}'))
+    }
+
+    // \の後のエスケープシーケンスを読み、resultに追加する
+    fn consume_escape(&mut self, result: &mut String, in_template: bool) -> Result<(), String> {
+        // 入力の終わりは呼び出し元で閉じていないエラーにする
+        let c = match self.peek_char(0) {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        self.pos += 1;
+
+        match c {
+            'n' => result.push('\n'),
+            't' => result.push('\t'),
+            'r' => result.push('\r'),
+            'b' => result.push('\u{8}'),
+            'f' => result.push('\u{c}'),
+            'v' => result.push('\u{b}'),
+            '0' if !self.is_digit_at(self.pos) => result.push('\0'),
+            'x' => {
+                let code = self
+                    .consume_hex_digits(2)
+                    .ok_or_else(|| "invalid hexadecimal escape sequence".to_string())?;
+                result.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            'u' => result.push(self.consume_unicode_escape()?),
+            '0'..='7' if in_template => {
+                return Err("octal escape sequences are not allowed in template literals".into())
+            }
+            // \101のような古い8進数のエスケープシーケンス。値は255まで
+            '0'..='7' => {
+                let mut code = c.to_digit(8).unwrap_or(0);
+                while let Some(digit) = self.peek_char(0).and_then(|c| c.to_digit(8)) {
+                    if code * 8 + digit > 0o377 {
+                        break;
+                    }
+                    code = code * 8 + digit;
+                    self.pos += 1;
+                }
+                result.push(char::from_u32(code).unwrap_or('\u{fffd}'));
             }
+            // 行末の\は行の継続で、文字列には何も追加しない
+            '\r' => {
+                if self.peek_char(0) == Some('\n') {
+                    self.pos += 1;
+                }
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            c => result.push(c),
         }
+        Ok(())
     }
 
-    fn consume_string(&mut self) -> String {
+    // "か'で囲まれた文字列を読む
+    fn consume_string(&mut self, quote: char) -> Result<String, String> {
         let mut result = String::new();
         self.pos += 1;
 
         loop {
-            if self.pos >= self.input.len() {
-                return result;
+            match self.peek_char(0) {
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    self.consume_escape(&mut result, false)?;
+                }
+                // U+2028とU+2029は文字列の中に書ける
+                None | Some('\n' | '\r') => {
+                    return Err("unterminated string literal".to_string());
+                }
+                Some(c) => {
+                    result.push(c);
+                    self.pos += 1;
+                }
             }
+        }
+    }
 
-            if self.input[self.pos] == '"' {
-                self.pos += 1;
-                return result;
-            }
+    // `か${}の}の後から、次の${か`までのテンプレートリテラルの文字列を読む
+    fn consume_template(&mut self, head: bool) -> Token {
+        let mut cooked = String::new();
+        let start = self.pos;
 
-            result.push(self.input[self.pos]);
-            self.pos += 1;
+        loop {
+            match self.peek_char(0) {
+                None => return Token::Error("unterminated template literal".to_string()),
+                Some('`') => {
+                    let raw = self.template_raw(start);
+                    self.pos += 1;
+                    return Token::Template {
+                        cooked,
+                        raw,
+                        head,
+                        tail: true,
+                    };
+                }
+                Some('$') if self.peek_char(1) == Some('{') => {
+                    let raw = self.template_raw(start);
+                    self.pos += 2;
+                    self.braces.push(true);
+                    return Token::Template {
+                        cooked,
+                        raw,
+                        head,
+                        tail: false,
+                    };
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    if let Err(message) = self.consume_escape(&mut cooked, true) {
+                        return Token::Error(message);
+                    }
+                }
+                // 改行は\nにそろえる
+                Some('\r') => {
+                    self.pos += 1;
+                    if self.peek_char(0) == Some('\n') {
+                        self.pos += 1;
+                    }
+                    cooked.push('\n');
+                }
+                Some(c) => {
+                    cooked.push(c);
+                    self.pos += 1;
+                }
+            }
         }
     }
-}
 
-impl Iterator for JsLexer {
-    type Item = Token;
+    // startから現在の位置までのソースコード。改行は\nにそろえる
+    fn template_raw(&self, start: usize) -> String {
+        let mut raw = String::new();
+        let mut chars = self.input[start..self.pos].iter().peekable();
+        while let Some(&c) = chars.next() {
+            if c == '\r' {
+                chars.next_if_eq(&&'\n');
+                raw.push('\n');
+            } else {
+                raw.push(c);
+            }
+        }
+        raw
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
-            return None;
+    // /が正規表現リテラルの始まりかを直前のトークンで推測する。値の後の/は除算になる
+    // if (x) /a/のように推測を誤る場合は、パーサーがread_again()で読み直す
+    fn is_regexp_allowed(&self) -> bool {
+        match &self.last {
+            None | Some(Token::Invalid(_)) | Some(Token::Error(_)) => true,
+            Some(Token::Punctuator(c)) => !matches!(c, ')' | ']' | '}'),
+            Some(Token::MultiCharPunctuator(p)) => p != "++" && p != "--",
+            Some(Token::Keyword(k)) => {
+                !matches!(k.as_str(), "this" | "super" | "true" | "false" | "null")
+            }
+            Some(Token::Template { tail, .. }) => !tail,
+            Some(Token::Number(_))
+            | Some(Token::Identifier(_))
+            | Some(Token::StringLiteral(_))
+            | Some(Token::RegExp { .. }) => false,
         }
+    }
 
-        while self.input[self.pos] == ' ' || self.input[self.pos] == '\n' {
-            self.pos += 1;
+    // /pattern/flagsを読む。パターンの構文は正規表現のエンジンが確認する
+    fn consume_regexp(&mut self) -> Token {
+        let mut pattern = String::new();
+        // [/]のように文字クラスの中の/はパターンの一部
+        let mut in_class = false;
+        self.pos += 1;
 
-            if self.pos >= self.input.len() {
-                return None;
+        loop {
+            let c = match self.peek_char(0) {
+                Some(c) if !is_line_terminator(c) => c,
+                _ => return Token::Error("unterminated regular expression literal".to_string()),
+            };
+            self.pos += 1;
+            match c {
+                '/' if !in_class => break,
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '\\' => match self.peek_char(0) {
+                    Some(next) if !is_line_terminator(next) => {
+                        pattern.push(c);
+                        pattern.push(next);
+                        self.pos += 1;
+                        continue;
+                    }
+                    _ => {
+                        return Token::Error("unterminated regular expression literal".to_string())
+                    }
+                },
+                _ => {}
             }
+            pattern.push(c);
         }
 
-        // 予約語が現れたらKeywordトークンを返す
-        if let Some(keyword) = self.check_reserved_word() {
-            self.pos += keyword.len();
-            let token = Some(Token::Keyword(keyword));
-            return token;
+        let mut flags = String::new();
+        while let Some(c) = self.peek_char(0).filter(|c| is_identifier_part(*c)) {
+            flags.push(c);
+            self.pos += 1;
         }
+        Token::RegExp { pattern, flags }
+    }
 
+    fn consume_token(&mut self, goal: Goal) -> Token {
         let c = self.input[self.pos];
 
-        let token = match c {
-            '+' | '-' | '=' | ';' | '{' | '}' | '(' | ')' | ',' | '.' => {
-                let t = Token::Punctuator(c);
+        match c {
+            '`' => {
+                self.pos += 1;
+                self.consume_template(true)
+            }
+            // ${}を閉じる}の後はテンプレートリテラルの続き
+            '}' if self.braces.last() == Some(&true) => {
+                self.braces.pop();
                 self.pos += 1;
-                t
+                self.consume_template(false)
             }
-            '0'..='9' => Token::Number(self.consume_number()),
-            'a'..='z' | 'A'..='Z' | '_' | '$' => Token::Identifier(self.consume_identifier()),
-            '"' => Token::StringLiteral(self.consume_string()),
-            _ => unimplemented!("char {:?} is not supported yet.", c),
+            '/' if goal == Goal::RegExp => self.consume_regexp(),
+            // .5のように小数点から始まる数値
+            '.' if self.is_digit_at(self.pos + 1) => match self.consume_number() {
+                Ok(value) => Token::Number(value),
+                Err(message) => Token::Error(message),
+            },
+            '0'..='9' => match self.consume_number() {
+                Ok(value) => Token::Number(value),
+                Err(message) => Token::Error(message),
+            },
+            '"' | '\'' => match self.consume_string(c) {
+                Ok(s) => Token::StringLiteral(s),
+                Err(message) => Token::Error(message),
+            },
+            c if is_identifier_start(c) || c == '\\' => match self.consume_identifier() {
+                // 予約語が現れたらKeywordトークンを返す
+                Ok(identifier) if RESERVED_WORDS.contains(&identifier.as_str()) => {
+                    Token::Keyword(identifier)
+                }
+                Ok(identifier) => Token::Identifier(identifier),
+                Err(message) => Token::Error(message),
+            },
+            _ => {
+                if let Some(punctuator) = self.check_multi_char_punctuator() {
+                    self.pos += punctuator.len();
+                    return Token::MultiCharPunctuator(punctuator.to_string());
+                }
+                self.pos += 1;
+                match c {
+                    '{' => self.braces.push(false),
+                    '}' => {
+                        self.braces.pop();
+                    }
+                    _ => {}
+                }
+                match c {
+                    '+' | '-' | '=' | ';' | '{' | '}' | '(' | ')' | ',' | '.' | '*' | '/' | '%'
+                    | '<' | '>' | '!' | '?' | ':' | '[' | ']' | '&' | '|' | '^' | '~' => {
+                        Token::Punctuator(c)
+                    }
+                    _ => Token::Invalid(c),
+                }
+            }
+        }
+    }
+}
+
+impl JsLexer {
+    // 次のトークンと、そのトークンのソースコード上の範囲を返す。/の読み方は直前のトークンから推測する
+    pub fn next_token(&mut self) -> Option<(Token, Span)> {
+        self.newline_before = false;
+        self.rewind = None;
+
+        if let Err(start) = self.skip_whitespace() {
+            let start = self.position_at(start);
+            let token = Token::Error("unterminated comment".to_string());
+            self.last = Some(token.clone());
+            return Some((token, Span::new(start, self.position_at(self.pos))));
+        }
+        if self.pos >= self.input.len() {
+            return None;
+        }
+
+        let goal = if self.is_regexp_allowed() {
+            Goal::RegExp
+        } else {
+            Goal::Div
         };
+        Some(self.read_token(goal))
+    }
+
+    // 直前に返した/から始まるトークンを、goalの読み方で読み直す
+    pub fn read_again(&mut self, rewind: Rewind, goal: Goal) -> (Token, Span) {
+        self.pos = rewind.pos;
+        self.scanned = rewind.scanned;
+        self.line = rewind.line;
+        self.line_start = rewind.line_start;
+        self.braces = rewind.braces;
+        self.newline_before = rewind.newline_before;
+        self.read_token(goal)
+    }
+
+    // 空白を読み飛ばした後の位置から、トークンを1つ読む
+    fn read_token(&mut self, goal: Goal) -> (Token, Span) {
+        let start = self.position_at(self.pos);
+        self.rewind = (self.input[self.pos] == '/').then(|| Rewind {
+            pos: self.pos,
+            scanned: self.scanned,
+            line: self.line,
+            line_start: self.line_start,
+            braces: self.braces.clone(),
+            newline_before: self.newline_before,
+            goal,
+        });
+        let token = self.consume_token(goal);
+        let end = self.position_at(self.pos);
+        self.last = Some(token.clone());
+        (token, Span::new(start, end))
+    }
+
+    // 最後に返したトークンが/から始まる場合、別の読み方で読み直すための状態を返す
+    pub fn take_rewind(&mut self) -> Option<Rewind> {
+        self.rewind.take()
+    }
+
+    // 最後に返したトークンの前に改行があったか
+    pub fn newline_before(&self) -> bool {
+        self.newline_before
+    }
+}
 
-        Some(token)
+impl Iterator for JsLexer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().map(|(token, _)| token)
     }
 }
 
@@ -170,7 +823,7 @@ mod test {
     fn test_num() {
         let input = "42".to_string();
         let mut lexer = JsLexer::new(input).peekable();
-        let expected = [Token::Number(42)].to_vec();
+        let expected = [Token::Number(42.0)].to_vec();
         let mut i = 0;
         while lexer.peek().is_some() {
             assert_eq!(Some(expected[i].clone()), lexer.next());
@@ -183,7 +836,12 @@ mod test {
     fn test_add_nums() {
         let input = "1 + 2".to_string();
         let mut lexer = JsLexer::new(input).peekable();
-        let expected = [Token::Number(1), Token::Punctuator('+'), Token::Number(2)].to_vec();
+        let expected = [
+            Token::Number(1.0),
+            Token::Punctuator('+'),
+            Token::Number(2.0),
+        ]
+        .to_vec();
         let mut i = 0;
         while lexer.peek().is_some() {
             assert_eq!(Some(expected[i].clone()), lexer.next());
@@ -220,14 +878,14 @@ mod test {
             Token::Keyword("var".to_string()),
             Token::Identifier("foo".to_string()),
             Token::Punctuator('='),
-            Token::Number(42),
+            Token::Number(42.0),
             Token::Punctuator(';'),
             Token::Keyword("var".to_string()),
             Token::Identifier("result".to_string()),
             Token::Punctuator('='),
             Token::Identifier("foo".to_string()),
             Token::Punctuator('+'),
-            Token::Number(1),
+            Token::Number(1.0),
             Token::Punctuator(';'),
         ]
         .to_vec();
@@ -252,7 +910,7 @@ mod test {
             Token::Keyword("var".to_string()),
             Token::Identifier("a".to_string()),
             Token::Punctuator('='),
-            Token::Number(42),
+            Token::Number(42.0),
             Token::Punctuator(';'),
             Token::Keyword("return".to_string()),
             Token::Identifier("a".to_string()),
@@ -265,7 +923,7 @@ mod test {
             Token::Punctuator('('),
             Token::Punctuator(')'),
             Token::Punctuator('+'),
-            Token::Number(1),
+            Token::Number(1.0),
             Token::Punctuator(';'),
         ]
         .to_vec();
@@ -276,4 +934,288 @@ mod test {
         }
         assert!(lexer.peek().is_none());
     }
+
+    #[test]
+    fn test_keyword_prefix() {
+        let input = "var format = iffy;".to_string();
+        let mut lexer = JsLexer::new(input).peekable();
+        let expected = [
+            Token::Keyword("var".to_string()),
+            Token::Identifier("format".to_string()),
+            Token::Punctuator('='),
+            Token::Identifier("iffy".to_string()),
+            Token::Punctuator(';'),
+        ]
+        .to_vec();
+        let mut i = 0;
+        while lexer.peek().is_some() {
+            assert_eq!(Some(expected[i].clone()), lexer.next());
+            i += 1;
+        }
+        assert!(lexer.peek().is_none());
+    }
+
+    #[test]
+    fn test_operators() {
+        let input = "if (a <= 1 && b !== 2) {} else {}".to_string();
+        let mut lexer = JsLexer::new(input).peekable();
+        let expected = [
+            Token::Keyword("if".to_string()),
+            Token::Punctuator('('),
+            Token::Identifier("a".to_string()),
+            Token::MultiCharPunctuator("<=".to_string()),
+            Token::Number(1.0),
+            Token::MultiCharPunctuator("&&".to_string()),
+            Token::Identifier("b".to_string()),
+            Token::MultiCharPunctuator("!==".to_string()),
+            Token::Number(2.0),
+            Token::Punctuator(')'),
+            Token::Punctuator('{'),
+            Token::Punctuator('}'),
+            Token::Keyword("else".to_string()),
+            Token::Punctuator('{'),
+            Token::Punctuator('}'),
+        ]
+        .to_vec();
+        let mut i = 0;
+        while lexer.peek().is_some() {
+            assert_eq!(Some(expected[i].clone()), lexer.next());
+            i += 1;
+        }
+        assert!(lexer.peek().is_none());
+    }
+
+    #[test]
+    fn test_numbers() {
+        let input = "1.5 .25 1e3 2.5E-1 0x1F 7.".to_string();
+        let mut lexer = JsLexer::new(input).peekable();
+        let expected = [
+            Token::Number(1.5),
+            Token::Number(0.25),
+            Token::Number(1000.0),
+            Token::Number(0.25),
+            Token::Number(31.0),
+            Token::Number(7.0),
+        ]
+        .to_vec();
+        let mut i = 0;
+        while lexer.peek().is_some() {
+            assert_eq!(Some(expected[i].clone()), lexer.next());
+            i += 1;
+        }
+        assert!(lexer.peek().is_none());
+    }
+
+    #[test]
+    fn test_position() {
+        let input = "var a;\n  a = @;".to_string();
+        let mut lexer = JsLexer::new(input);
+        let expected = [
+            (Token::Keyword("var".to_string()), Position::new(1, 1)),
+            (Token::Identifier("a".to_string()), Position::new(1, 5)),
+            (Token::Punctuator(';'), Position::new(1, 6)),
+            (Token::Identifier("a".to_string()), Position::new(2, 3)),
+            (Token::Punctuator('='), Position::new(2, 5)),
+            (Token::Invalid('@'), Position::new(2, 7)),
+            (Token::Punctuator(';'), Position::new(2, 8)),
+        ];
+        for e in expected {
+            assert_eq!(Some(e), lexer.next_token().map(|(t, span)| (t, span.start)));
+        }
+        assert!(lexer.next_token().is_none());
+    }
+
+    #[test]
+    fn test_span_and_newline() {
+        let input = "a /* \n */ +=\r\n  'bc' // c\n\u{2028}x".to_string();
+        let mut lexer = JsLexer::new(input);
+        let expected = [
+            (Token::Identifier("a".to_string()), (1, 1), (1, 2), false),
+            (
+                Token::MultiCharPunctuator("+=".to_string()),
+                (2, 5),
+                (2, 7),
+                true,
+            ),
+            (Token::StringLiteral("bc".to_string()), (3, 3), (3, 7), true),
+            (Token::Identifier("x".to_string()), (5, 1), (5, 2), true),
+        ];
+        for (token, start, end, newline) in expected {
+            let span = Span::new(Position::new(start.0, start.1), Position::new(end.0, end.1));
+            assert_eq!(Some((token, span)), lexer.next_token());
+            assert_eq!(newline, lexer.newline_before());
+        }
+        assert!(lexer.next_token().is_none());
+    }
+
+    #[test]
+    fn test_punctuators() {
+        let input = "a >>>= b?.c ?? d++ ... ~e ** f?.5:0".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Identifier("a".to_string()),
+            Token::MultiCharPunctuator(">>>=".to_string()),
+            Token::Identifier("b".to_string()),
+            Token::MultiCharPunctuator("?.".to_string()),
+            Token::Identifier("c".to_string()),
+            Token::MultiCharPunctuator("??".to_string()),
+            Token::Identifier("d".to_string()),
+            Token::MultiCharPunctuator("++".to_string()),
+            Token::MultiCharPunctuator("...".to_string()),
+            Token::Punctuator('~'),
+            Token::Identifier("e".to_string()),
+            Token::MultiCharPunctuator("**".to_string()),
+            Token::Identifier("f".to_string()),
+            Token::Punctuator('?'),
+            Token::Number(0.5),
+            Token::Punctuator(':'),
+            Token::Number(0.0),
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let input = r#"'it\'s' "\x41\u0042\u{43}\uD83D\uDE00\0\101\
+z" '\q'"#
+            .to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::StringLiteral("it's".to_string()),
+            Token::StringLiteral("ABC\u{1f600}\0Az".to_string()),
+            Token::StringLiteral("q".to_string()),
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let input = "0b101 0o17 1_000_000 0xFF_FF 1e+2".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Number(5.0),
+            Token::Number(15.0),
+            Token::Number(1000000.0),
+            Token::Number(65535.0),
+            Token::Number(100.0),
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_template_literal() {
+        let input = "`a${ {b: `c${d}`} }e\\t\r\n`".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Template {
+                cooked: "a".to_string(),
+                raw: "a".to_string(),
+                head: true,
+                tail: false,
+            },
+            Token::Punctuator('{'),
+            Token::Identifier("b".to_string()),
+            Token::Punctuator(':'),
+            Token::Template {
+                cooked: "c".to_string(),
+                raw: "c".to_string(),
+                head: true,
+                tail: false,
+            },
+            Token::Identifier("d".to_string()),
+            Token::Template {
+                cooked: "".to_string(),
+                raw: "".to_string(),
+                head: false,
+                tail: true,
+            },
+            Token::Punctuator('}'),
+            Token::Template {
+                cooked: "e\t\n".to_string(),
+                raw: "e\\t\n".to_string(),
+                head: false,
+                tail: true,
+            },
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_html_like_comment() {
+        let input = "a <!-- b\n--> c\nd --> e".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Identifier("a".to_string()),
+            Token::Identifier("d".to_string()),
+            Token::MultiCharPunctuator("--".to_string()),
+            Token::Punctuator('>'),
+            Token::Identifier("e".to_string()),
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+
+        let mut lexer = JsLexer::new("a <!-- b".to_string());
+        lexer.set_module();
+        let expected = [
+            Token::Identifier("a".to_string()),
+            Token::Punctuator('<'),
+            Token::Punctuator('!'),
+            Token::MultiCharPunctuator("--".to_string()),
+            Token::Identifier("b".to_string()),
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_regexp_or_division() {
+        let input = "a / b / c; x = /[/]+\\//gi.test(s); (1) /2".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Identifier("a".to_string()),
+            Token::Punctuator('/'),
+            Token::Identifier("b".to_string()),
+            Token::Punctuator('/'),
+            Token::Identifier("c".to_string()),
+            Token::Punctuator(';'),
+            Token::Identifier("x".to_string()),
+            Token::Punctuator('='),
+            Token::RegExp {
+                pattern: "[/]+\\/".to_string(),
+                flags: "gi".to_string(),
+            },
+            Token::Punctuator('.'),
+            Token::Identifier("test".to_string()),
+            Token::Punctuator('('),
+            Token::Identifier("s".to_string()),
+            Token::Punctuator(')'),
+            Token::Punctuator(';'),
+            Token::Punctuator('('),
+            Token::Number(1.0),
+            Token::Punctuator(')'),
+            Token::Punctuator('/'),
+            Token::Number(2.0),
+        ];
+        assert_eq!(expected.to_vec(), lexer.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_lexical_errors() {
+        for (input, message) in [
+            ("'abc", "unterminated string literal"),
+            ("\"a\nb\"", "unterminated string literal"),
+            ("/* a", "unterminated comment"),
+            ("`a${b}", "unterminated template literal"),
+            ("/ab\n/", "unterminated regular expression literal"),
+            ("3in", "identifier starts immediately after numeric literal"),
+            ("1__0", "numeric separators are only allowed between digits"),
+            ("'\\x4'", "invalid hexadecimal escape sequence"),
+        ] {
+            let tokens = JsLexer::new(input.to_string()).collect::<Vec<_>>();
+            assert!(
+                tokens.contains(&Token::Error(message.to_string())),
+                "{}: {:?}",
+                input,
+                tokens
+            );
+        }
+    }
 }
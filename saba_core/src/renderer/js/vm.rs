@@ -0,0 +1,1721 @@
+// バイトコードを実行するスタックマシン
+// 関数呼び出しごとにフレームを作り、例外はフレームに登録されたハンドラで捕捉する
+// ジェネレータとasync関数は、yieldとawaitで中断したフレームを保存し、後で再開する
+
+use crate::renderer::js::ast::Program;
+use crate::renderer::js::builtins::{arg, define_methods, native_closure};
+use crate::renderer::js::bytecode::{Code, Instruction, ScopeInfo};
+use crate::renderer::js::compiler::compile;
+use crate::renderer::js::gc::{Marker, Releaser, Trace};
+use crate::renderer::js::promise;
+use crate::renderer::js::runtime::{
+    is_constructor, Function, InternalSlot, IteratorState, JsArray, JsException, JsObject,
+    JsRuntime, RuntimeValue,
+};
+use alloc::{format, rc::Rc, string::ToString, vec::Vec};
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Formatter};
+
+// 実行時のスコープ。変数はコンパイル時に決まった位置に保存する
+pub struct Scope {
+    // letとconstで宣言された変数は、宣言が実行されるまでNoneになる（TDZ）
+    slots: RefCell<Vec<Option<RuntimeValue>>>,
+    info: Rc<ScopeInfo>,
+    parent: Option<Rc<Scope>>,
+    // モジュールのスコープの場合、importした変数が参照する公開元のスコープと位置
+    imports: RefCell<Vec<(Rc<Scope>, usize)>>,
+}
+
+impl Scope {
+    pub fn new(info: Rc<ScopeInfo>, parent: Option<Rc<Scope>>) -> Self {
+        let mut scope = Self {
+            slots: RefCell::new(Vec::new()),
+            info: info.clone(),
+            parent: None,
+            imports: RefCell::new(Vec::new()),
+        };
+        scope.reset(info, parent);
+        scope
+    }
+
+    // 確保した領域を残したまま、新しいスコープとして初期化する
+    fn reset(&mut self, info: Rc<ScopeInfo>, parent: Option<Rc<Scope>>) {
+        let slots = self.slots.get_mut();
+        slots.clear();
+        slots.extend(info.bindings.iter().map(|b| {
+            if b.lexical {
+                None
+            } else {
+                Some(RuntimeValue::Undefined)
+            }
+        }));
+        self.imports.get_mut().clear();
+        self.info = info;
+        self.parent = parent;
+    }
+
+    pub fn info(&self) -> Rc<ScopeInfo> {
+        self.info.clone()
+    }
+
+    // 変数の値。初期化されていない場合はNone
+    pub fn get(&self, index: usize) -> Option<RuntimeValue> {
+        self.slots.borrow()[index].clone()
+    }
+
+    // リンク時に、importした変数を公開元の変数に結び付ける
+    pub fn set_imports(&self, imports: Vec<(Rc<Scope>, usize)>) {
+        *self.imports.borrow_mut() = imports;
+    }
+
+    // depthの数だけ外側のスコープ
+    fn ancestor(&self, depth: usize) -> &Scope {
+        let mut scope = self;
+        for _ in 0..depth {
+            scope = scope.parent.as_deref().expect("outer scope should exist");
+        }
+        scope
+    }
+
+    pub fn set(&self, index: usize, value: RuntimeValue) {
+        self.slots.borrow_mut()[index] = Some(value);
+    }
+
+    // 変数と外側のスコープを共有する新しいスコープ
+    fn copy(&self) -> Self {
+        Self {
+            slots: RefCell::new(self.slots.borrow().clone()),
+            info: self.info.clone(),
+            parent: self.parent.clone(),
+            imports: RefCell::new(self.imports.borrow().clone()),
+        }
+    }
+}
+
+impl Trace for Scope {
+    fn trace(&self, marker: &mut Marker) {
+        for value in self.slots.borrow().iter().flatten() {
+            marker.mark_value(value);
+        }
+        if let Some(parent) = &self.parent {
+            marker.mark_scope(parent);
+        }
+        for (scope, _) in self.imports.borrow().iter() {
+            marker.mark_scope(scope);
+        }
+    }
+
+    // 外側のスコープは共有されているため残す
+    fn clear(&self, releaser: &mut Releaser) {
+        for value in self.slots.borrow_mut().iter_mut().filter_map(Option::take) {
+            releaser.push_value(value);
+        }
+        for (scope, _) in self.imports.borrow_mut().drain(..) {
+            releaser.push_scope(scope);
+        }
+    }
+}
+
+impl Scope {
+    // 変数の値、外側のスコープ、importした変数のスコープをreleaserへ移す
+    pub fn release(&mut self, releaser: &mut Releaser) {
+        for value in self.slots.get_mut().drain(..).flatten() {
+            releaser.push_value(value);
+        }
+        if let Some(parent) = self.parent.take() {
+            releaser.push_scope(parent);
+        }
+        for (scope, _) in self.imports.get_mut().drain(..) {
+            releaser.push_scope(scope);
+        }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let mut releaser = Releaser::default();
+        self.release(&mut releaser);
+        releaser.release_all();
+    }
+}
+
+// 変数の値は自身を含む関数を保持する場合があるため、変数の名前だけを表示する
+impl Debug for Scope {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Scope")
+            .field("bindings", &self.info.bindings)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+// 例外が発生した場合の移動先と、戻すスタックとスコープの状態
+struct Handler {
+    target: usize,
+    is_finally: bool,
+    stack_len: usize,
+    scope: Option<Rc<Scope>>,
+}
+
+// 実行中の関数かプログラム
+struct Frame {
+    code: Rc<Code>,
+    ip: usize,
+    stack: Vec<RuntimeValue>,
+    scope: Option<Rc<Scope>>,
+    handlers: Vec<Handler>,
+    // finally節の実行後に再送出する例外
+    exceptions: Vec<JsException>,
+    // yieldかawaitで中断した場合はtrue
+    yielded: bool,
+    // yield*で中断したジェネレータを再開した方法。委譲先のイテレータに渡す
+    delegated: Option<Resume>,
+    // ジェネレータかasync関数のフレームの場合、そのフレームを保存するオブジェクト
+    generator: Option<RuntimeValue>,
+}
+
+impl Frame {
+    fn new(code: Rc<Code>, scope: Option<Rc<Scope>>) -> Self {
+        Self {
+            code,
+            ip: 0,
+            stack: Vec::new(),
+            scope,
+            handlers: Vec::new(),
+            exceptions: Vec::new(),
+            yielded: false,
+            delegated: None,
+            generator: None,
+        }
+    }
+
+    fn pop(&mut self) -> RuntimeValue {
+        self.stack.pop().expect("stack should not be empty")
+    }
+
+    fn peek(&self) -> RuntimeValue {
+        self.stack
+            .last()
+            .cloned()
+            .expect("stack should not be empty")
+    }
+
+    fn pop_args(&mut self, argc: usize) -> Vec<RuntimeValue> {
+        self.stack.split_off(self.stack.len() - argc)
+    }
+
+    fn scope(&self, depth: usize) -> &Scope {
+        self.scope
+            .as_deref()
+            .expect("scope should exist")
+            .ancestor(depth)
+    }
+
+    fn mark(&self, marker: &mut Marker) {
+        for value in self.stack.iter().chain(self.generator.iter()) {
+            marker.mark_value(value);
+        }
+        mark_scopes(marker, &self.scope, &self.handlers, &self.exceptions);
+    }
+
+    // 関数を呼び出す間、GCのルートになる値をランタイムに預ける
+    // 値は複製せずに移し、呼び出しから戻った時に戻す
+    fn suspend(&mut self) -> SuspendedFrame {
+        SuspendedFrame {
+            stack: core::mem::take(&mut self.stack),
+            scope: self.scope.take(),
+            handlers: core::mem::take(&mut self.handlers),
+            exceptions: core::mem::take(&mut self.exceptions),
+            generator: self.generator.take(),
+        }
+    }
+
+    fn resume(&mut self, suspended: SuspendedFrame) {
+        self.stack = suspended.stack;
+        self.scope = suspended.scope;
+        self.handlers = suspended.handlers;
+        self.exceptions = suspended.exceptions;
+        self.generator = suspended.generator;
+    }
+}
+
+#[derive(Debug, Clone)]
+// 関数を呼び出している途中のフレームが持つ値
+pub struct SuspendedFrame {
+    stack: Vec<RuntimeValue>,
+    scope: Option<Rc<Scope>>,
+    handlers: Vec<Handler>,
+    exceptions: Vec<JsException>,
+    generator: Option<RuntimeValue>,
+}
+
+impl SuspendedFrame {
+    pub fn mark(&self, marker: &mut Marker) {
+        for value in self.stack.iter().chain(self.generator.iter()) {
+            marker.mark_value(value);
+        }
+        mark_scopes(marker, &self.scope, &self.handlers, &self.exceptions);
+    }
+}
+
+fn mark_scopes(
+    marker: &mut Marker,
+    scope: &Option<Rc<Scope>>,
+    handlers: &[Handler],
+    exceptions: &[JsException],
+) {
+    for scope in scope
+        .iter()
+        .chain(handlers.iter().filter_map(|h| h.scope.as_ref()))
+    {
+        marker.mark_scope(scope);
+    }
+    for exception in exceptions {
+        marker.mark_value(&exception.value());
+    }
+}
+
+impl JsRuntime {
+    // プログラムをバイトコードにコンパイルして実行する
+    // 捕捉されなかった例外が発生した場合、残りの文は実行せずに例外を返す
+    pub fn execute(&mut self, program: &Program) -> Result<(), JsException> {
+        self.evaluate(program)?;
+        Ok(())
+    }
+
+    // プログラムを実行し、最後の式文の値を返す。最後の文が式文でない場合はundefined
+    pub fn evaluate(&mut self, program: &Program) -> Result<RuntimeValue, JsException> {
+        let mut frame = Frame::new(Rc::new(compile(program, self.source())), None);
+        self.run(&mut frame)
+    }
+
+    // コンパイルされたモジュールを、リンク済みのモジュールのスコープで実行する
+    pub fn run_module_code(
+        &mut self,
+        code: Rc<Code>,
+        scope: Rc<Scope>,
+    ) -> Result<RuntimeValue, JsException> {
+        let mut frame = Frame::new(code, Some(scope));
+        self.run(&mut frame)
+    }
+
+    // コンパイルされた関数を呼び出す。関数のスコープに、this、super、引数を割り当てる
+    pub fn call_compiled(
+        &mut self,
+        function: &Function,
+        code: Rc<Code>,
+        this: RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> Result<RuntimeValue, JsException> {
+        let info = code.scope.clone().expect("function scope should exist");
+        let scope = self.new_scope(&code, info, function.scope());
+        if let Some(slot) = code.this_slot {
+            scope.set(slot, this);
+        }
+        if let Some(slot) = code.super_slot {
+            let base = function
+                .home_object()
+                .and_then(|home| self.prototype_of(&home))
+                .unwrap_or(RuntimeValue::Null);
+            scope.set(slot, base);
+        }
+        // 足りない引数はundefined、余った引数は無視する
+        let mut args = args.into_iter();
+        for slot in &code.params {
+            scope.set(*slot, args.next().unwrap_or(RuntimeValue::Undefined));
+        }
+
+        let mut frame = Frame::new(code.clone(), Some(scope.clone()));
+        frame.stack = self.take_stack();
+        // ジェネレータは本体を実行せずにジェネレータオブジェクトを返す
+        if code.is_generator {
+            let prototype = match function.property("prototype") {
+                prototype @ RuntimeValue::Object(_) => prototype,
+                _ => self.generator_prototype(),
+            };
+            return Ok(self.new_generator(frame, Some(prototype), None));
+        }
+        // async関数は最初のawaitまで実行し、結果のPromiseを返す
+        if code.is_async {
+            let promise = promise::new_promise(self);
+            let generator = self.new_generator(frame, None, Some(promise.clone()));
+            self.step_async(&generator, Resume::Next(RuntimeValue::Undefined));
+            return Ok(promise);
+        }
+        let result = self.run(&mut frame);
+        self.recycle_stack(core::mem::take(&mut frame.stack));
+        drop(frame);
+        self.recycle_scope(scope);
+        result
+    }
+
+    // 例外が発生した場合、登録されたハンドラがあればそこから実行を続ける
+    fn run(&mut self, frame: &mut Frame) -> Result<RuntimeValue, JsException> {
+        self.enter_frame();
+        let result = self.run_frame(frame, None);
+        self.leave_frame();
+        self.reset_stack_base();
+        result
+    }
+
+    // thrownを指定した場合、最初にその例外が発生したものとして扱う
+    fn run_frame(
+        &mut self,
+        frame: &mut Frame,
+        mut thrown: Option<JsException>,
+    ) -> Result<RuntimeValue, JsException> {
+        loop {
+            let exception = match thrown.take() {
+                Some(exception) => exception,
+                None => match self.dispatch(frame) {
+                    Ok(value) => return Ok(value),
+                    Err(exception) => exception,
+                },
+            };
+            let handler = match frame.handlers.pop() {
+                Some(handler) => handler,
+                None => return Err(exception),
+            };
+
+            frame.stack.truncate(handler.stack_len);
+            frame.scope = handler.scope;
+            frame.ip = handler.target;
+            if handler.is_finally {
+                frame.exceptions.push(exception);
+            } else {
+                frame.stack.push(exception.value());
+            }
+        }
+    }
+
+    // Return命令まで実行する
+    fn dispatch(&mut self, frame: &mut Frame) -> Result<RuntimeValue, JsException> {
+        let code = frame.code.clone();
+        loop {
+            let instruction = &code.instructions[frame.ip];
+            frame.ip += 1;
+
+            match instruction {
+                Instruction::Constant(index) => frame.stack.push(code.constants[*index].clone()),
+                Instruction::Undefined => frame.stack.push(RuntimeValue::Undefined),
+                Instruction::Null => frame.stack.push(RuntimeValue::Null),
+                Instruction::Boolean(value) => frame.stack.push(RuntimeValue::Boolean(*value)),
+                Instruction::Pop => {
+                    frame.pop();
+                }
+                Instruction::Dup => {
+                    let value = frame.peek();
+                    frame.stack.push(value);
+                }
+                Instruction::Dup2 => {
+                    let len = frame.stack.len();
+                    frame.stack.extend_from_within(len - 2..);
+                }
+                Instruction::Bury(n) => {
+                    let value = frame.pop();
+                    let index = frame.stack.len() - n;
+                    frame.stack.insert(index, value);
+                }
+
+                Instruction::GetLocal(depth, index) => {
+                    let scope = frame.scope(*depth);
+                    let value = scope.slots.borrow()[*index].clone();
+                    match value {
+                        Some(value) => frame.stack.push(value),
+                        None => return Err(self.uninitialized(scope, *index)),
+                    }
+                }
+                Instruction::SetLocal(depth, index) => {
+                    let value = frame.pop();
+                    let scope = frame.scope(*depth);
+                    if scope.slots.borrow()[*index].is_none() {
+                        return Err(self.uninitialized(scope, *index));
+                    }
+                    scope.set(*index, value);
+                }
+                Instruction::InitLocal(depth, index) => {
+                    let value = frame.pop();
+                    frame.scope(*depth).set(*index, value);
+                }
+                Instruction::GetImport(depth, index) => {
+                    let (scope, index) = frame.scope(*depth).imports.borrow()[*index].clone();
+                    match scope.get(index) {
+                        Some(value) => frame.stack.push(value),
+                        None => return Err(self.uninitialized(&scope, index)),
+                    }
+                }
+                Instruction::GetGlobal(name) => {
+                    let value = self.lookup_global(&code.names[*name])?;
+                    frame.stack.push(value);
+                }
+                Instruction::SetGlobal(name) => {
+                    let value = frame.pop();
+                    self.assign_global(&code.names[*name], value)?;
+                }
+                Instruction::DeclareGlobal(name, kind) => {
+                    self.declare_global(&code.names[*name], *kind);
+                }
+                Instruction::InitGlobal(name, kind) => {
+                    let value = frame.pop();
+                    self.initialize_global(&code.names[*name], value, *kind);
+                }
+                Instruction::PushScope(index) => {
+                    let info = code.scopes[*index].clone();
+                    frame.scope = Some(self.new_scope(&code, info, frame.scope.take()));
+                }
+                Instruction::PopScope => {
+                    let scope = frame.scope.take().expect("scope should exist");
+                    frame.scope = scope.parent.clone();
+                    self.recycle_scope(scope);
+                }
+                Instruction::CopyScope => {
+                    frame.scope = frame.scope.take().map(|s| self.track_scope(s.copy()));
+                }
+
+                Instruction::Add => self.binary(frame, "+")?,
+                Instruction::Sub => self.binary(frame, "-")?,
+                Instruction::Mul => self.binary(frame, "*")?,
+                Instruction::Div => self.binary(frame, "/")?,
+                Instruction::Rem => self.binary(frame, "%")?,
+                Instruction::Exponent => self.binary(frame, "**")?,
+                Instruction::ShiftLeft => self.binary(frame, "<<")?,
+                Instruction::ShiftRight => self.binary(frame, ">>")?,
+                Instruction::UnsignedShiftRight => self.binary(frame, ">>>")?,
+                Instruction::BitAnd => self.binary(frame, "&")?,
+                Instruction::BitOr => self.binary(frame, "|")?,
+                Instruction::BitXor => self.binary(frame, "^")?,
+                // 比較できない場合は常にfalse
+                Instruction::Less => self.compare_on_stack(frame, |o| o == Ordering::Less)?,
+                Instruction::Greater => self.compare_on_stack(frame, |o| o == Ordering::Greater)?,
+                Instruction::LessEqual => {
+                    self.compare_on_stack(frame, |o| o != Ordering::Greater)?
+                }
+                Instruction::GreaterEqual => {
+                    self.compare_on_stack(frame, |o| o != Ordering::Less)?
+                }
+                Instruction::InstanceOf => {
+                    let right = frame.pop();
+                    let left = frame.pop();
+                    let result = self.instance_of(&left, &right)?;
+                    frame.stack.push(RuntimeValue::Boolean(result));
+                }
+                Instruction::In => {
+                    let right = frame.pop();
+                    let left = frame.pop();
+                    let result = self.has_property(&right, &left.to_string())?;
+                    frame.stack.push(RuntimeValue::Boolean(result));
+                }
+                Instruction::Equal => {
+                    let right = frame.pop();
+                    let left = frame.pop();
+                    let result = self.loosely_equals(left, right)?;
+                    frame.stack.push(RuntimeValue::Boolean(result));
+                }
+                Instruction::NotEqual => {
+                    let right = frame.pop();
+                    let left = frame.pop();
+                    let result = self.loosely_equals(left, right)?;
+                    frame.stack.push(RuntimeValue::Boolean(!result));
+                }
+                Instruction::StrictEqual => strict_equal(frame, false),
+                Instruction::StrictNotEqual => strict_equal(frame, true),
+                Instruction::Not => {
+                    let value = frame.pop();
+                    frame.stack.push(RuntimeValue::Boolean(!value.is_truthy()));
+                }
+                Instruction::Negate => {
+                    let value = frame.pop();
+                    let value = self.to_number(value)?;
+                    frame.stack.push(RuntimeValue::Number(-value));
+                }
+                Instruction::ToNumber => {
+                    let value = frame.pop();
+                    let value = self.to_number(value)?;
+                    frame.stack.push(RuntimeValue::Number(value));
+                }
+                Instruction::ToString => {
+                    let value = frame.pop();
+                    let value = self.to_js_string(value)?;
+                    frame.stack.push(RuntimeValue::StringLiteral(value));
+                }
+                Instruction::BitNot => {
+                    let value = frame.pop();
+                    let value = self.to_primitive(value, "number")?;
+                    frame
+                        .stack
+                        .push(RuntimeValue::Number(!value.to_int32() as f64));
+                }
+                Instruction::TypeOf => {
+                    let value = frame.pop();
+                    let name = value.type_of().to_string();
+                    frame.stack.push(RuntimeValue::StringLiteral(name));
+                }
+                Instruction::TypeOfGlobal(name) => {
+                    let name = &code.names[*name];
+                    let found = self.find_global(name);
+                    let value = self.typeof_variable(name, found)?;
+                    frame.stack.push(value);
+                }
+                Instruction::DeleteProperty => {
+                    let key = frame.pop().to_string();
+                    let object = frame.pop();
+                    let result = self.delete(&object, &key)?;
+                    frame.stack.push(RuntimeValue::Boolean(result));
+                }
+
+                Instruction::Jump(target) => {
+                    // ループの繰り返しごとに、必要ならGCを実行する
+                    if *target < frame.ip {
+                        self.collect_if_needed(|marker| frame.mark(marker));
+                    }
+                    frame.ip = *target;
+                }
+                Instruction::JumpIfFalse(target) => {
+                    if !frame.pop().is_truthy() {
+                        frame.ip = *target;
+                    }
+                }
+                Instruction::JumpIfFalseOrPop(target) => {
+                    if frame.peek().is_truthy() {
+                        frame.pop();
+                    } else {
+                        frame.ip = *target;
+                    }
+                }
+                Instruction::JumpIfTrueOrPop(target) => {
+                    if frame.peek().is_truthy() {
+                        frame.ip = *target;
+                    } else {
+                        frame.pop();
+                    }
+                }
+                Instruction::Iterate(is_for_in) => {
+                    let value = frame.pop();
+                    let iterator = self.create_iterator(value, *is_for_in)?;
+                    frame.stack.push(iterator);
+                }
+                Instruction::IteratorNext(target) => {
+                    let iterator = frame.pop();
+                    match self.iterator_next(&iterator)? {
+                        Some(value) => frame.stack.push(value),
+                        None => frame.ip = *target,
+                    }
+                }
+                Instruction::IteratorClose => {
+                    let iterator = frame.pop();
+                    self.iterator_close(&iterator)?;
+                }
+
+                Instruction::GetProperty => {
+                    let key = frame.pop().to_string();
+                    let object = frame.pop();
+                    let value = self.get(&object, &key)?;
+                    frame.stack.push(value);
+                }
+                Instruction::GetNamed(name) => {
+                    let object = frame.pop();
+                    let value = self.get(&object, &code.names[*name])?;
+                    frame.stack.push(value);
+                }
+                Instruction::SetProperty => {
+                    let value = frame.pop();
+                    let key = frame.pop().to_string();
+                    let object = frame.pop();
+                    self.put(&object, &key, value.clone())?;
+                    frame.stack.push(value);
+                }
+                Instruction::SetNamed(name) => {
+                    let value = frame.pop();
+                    let object = frame.pop();
+                    self.put(&object, &code.names[*name], value.clone())?;
+                    frame.stack.push(value);
+                }
+                Instruction::NewObject => frame.stack.push(self.new_object()),
+                Instruction::NewRegExp(pattern, flags) => {
+                    let pattern = code.constants[*pattern].to_string();
+                    let flags = code.constants[*flags].to_string();
+                    let regexp = self.new_regexp(&pattern, &flags)?;
+                    frame.stack.push(regexp);
+                }
+                Instruction::NewArray(length) => {
+                    let elements = frame.pop_args(*length);
+                    let array = self.track(RuntimeValue::Array(Rc::new(RefCell::new(
+                        JsArray::new(elements),
+                    ))));
+                    frame.stack.push(array);
+                }
+                Instruction::DefineField(name) => {
+                    let value = frame.pop();
+                    frame.peek().set_property(&code.names[*name], value);
+                }
+                Instruction::DefineMethod {
+                    key,
+                    function,
+                    kind,
+                } => {
+                    let home = frame.peek();
+                    let function = Function::new_compiled(
+                        code.functions[*function].clone(),
+                        frame.scope.clone(),
+                    );
+                    self.define_method(&home, &code.names[*key], function, *kind);
+                }
+                Instruction::Closure(function) => {
+                    let value =
+                        self.closure(code.functions[*function].clone(), frame.scope.clone());
+                    frame.stack.push(value);
+                }
+                Instruction::Class {
+                    name,
+                    constructor,
+                    has_parent,
+                } => {
+                    let parent = if *has_parent { Some(frame.pop()) } else { None };
+                    let constructor = constructor.map(|index| {
+                        Function::new_compiled(code.functions[index].clone(), frame.scope.clone())
+                    });
+                    let name = name.map(|index| code.names[index].clone());
+                    let class = self.create_class(name, constructor, parent)?;
+                    frame.stack.push(class);
+                }
+
+                Instruction::Position(site) => {
+                    self.set_position(Some(code.call_sites[*site].position));
+                    self.set_source(code.source.clone());
+                }
+                Instruction::Call(argc, site) | Instruction::SuperCall(argc, site) => {
+                    self.collect_if_needed(|marker| frame.mark(marker));
+                    let args = frame.pop_args(*argc);
+                    let callee = frame.pop();
+                    let this = frame.pop();
+                    // 引数の中で別のスクリプトの関数が呼ばれた場合があるため、位置とスクリプトを戻す
+                    let site = &code.call_sites[*site];
+                    self.set_position(Some(site.position));
+                    self.set_source(code.source.clone());
+                    let function = match &callee {
+                        RuntimeValue::Function(f) => f,
+                        _ => {
+                            return Err(self.throw_error(
+                                "TypeError",
+                                format!("{} is not a function", site.name),
+                            ))
+                        }
+                    };
+                    let is_super_call = matches!(instruction, Instruction::SuperCall(..));
+                    if function.is_class_constructor() && !is_super_call {
+                        return Err(self.throw_error(
+                            "TypeError",
+                            format!(
+                                "class constructor {} cannot be invoked without 'new'",
+                                site.name
+                            ),
+                        ));
+                    }
+                    let result =
+                        self.suspended(frame, |runtime| runtime.call(&callee, this, args))?;
+                    frame.stack.push(result);
+                }
+                Instruction::New(argc, site) => {
+                    self.collect_if_needed(|marker| frame.mark(marker));
+                    let args = frame.pop_args(*argc);
+                    let constructor = frame.pop();
+                    let site = &code.call_sites[*site];
+                    self.set_position(Some(site.position));
+                    self.set_source(code.source.clone());
+                    if !is_constructor(&constructor) {
+                        return Err(self.throw_error(
+                            "TypeError",
+                            format!("{} is not a constructor", site.name),
+                        ));
+                    }
+                    let result =
+                        self.suspended(frame, |runtime| runtime.construct(&constructor, args))?;
+                    frame.stack.push(result);
+                }
+                Instruction::Return => return Ok(frame.pop()),
+                Instruction::Yield(_) | Instruction::Await => {
+                    frame.yielded = true;
+                    return Ok(frame.pop());
+                }
+                Instruction::Delegate(target) => {
+                    let resume = frame
+                        .delegated
+                        .take()
+                        .unwrap_or(Resume::Next(RuntimeValue::Undefined));
+                    let iterator = frame.peek();
+                    match self.suspended(frame, |runtime| runtime.delegate(&iterator, resume))? {
+                        Delegated::Yield(value) => {
+                            frame.yielded = true;
+                            return Ok(value);
+                        }
+                        Delegated::Done(value) => {
+                            frame.pop();
+                            frame.stack.push(value);
+                        }
+                        Delegated::Return(value) => {
+                            frame.pop();
+                            frame.stack.push(value);
+                            frame.ip = *target;
+                        }
+                    }
+                }
+
+                Instruction::Throw(position) => {
+                    let value = frame.pop();
+                    self.set_position(Some(*position));
+                    self.set_source(code.source.clone());
+                    return Err(JsException::new(value, Some(*position)));
+                }
+                Instruction::PushHandler { target, is_finally } => {
+                    frame.handlers.push(Handler {
+                        target: *target,
+                        is_finally: *is_finally,
+                        stack_len: frame.stack.len(),
+                        scope: frame.scope.clone(),
+                    });
+                }
+                Instruction::PopHandler => {
+                    frame.handlers.pop();
+                }
+                Instruction::Rethrow => {
+                    if let Some(exception) = frame.exceptions.pop() {
+                        return Err(exception);
+                    }
+                }
+                Instruction::ThrowError(name, message) => {
+                    return Err(self.throw_error(name, message.clone()));
+                }
+            }
+        }
+    }
+
+    // フレームの値をGCのルートとして預けて関数を呼び出す
+    fn suspended<T>(
+        &mut self,
+        frame: &mut Frame,
+        call: impl FnOnce(&mut Self) -> Result<T, JsException>,
+    ) -> Result<T, JsException> {
+        self.suspend_frame(frame.suspend());
+        let result = call(self);
+        frame.resume(self.resume_frame());
+        result
+    }
+
+    // スタックの上の2つの値を二項演算子で計算する。オブジェクトはvalueOfやtoStringで変換する
+    fn binary(&mut self, frame: &mut Frame, operator: &str) -> Result<(), JsException> {
+        let right = frame.pop();
+        let left = frame.pop();
+        let result = self.operate(operator, left, right)?;
+        frame.stack.push(result);
+        Ok(())
+    }
+
+    fn compare_on_stack(
+        &mut self,
+        frame: &mut Frame,
+        predicate: fn(Ordering) -> bool,
+    ) -> Result<(), JsException> {
+        let right = frame.pop();
+        let left = frame.pop();
+        let result = self.compare(left, right)?.map(predicate).unwrap_or(false);
+        frame.stack.push(RuntimeValue::Boolean(result));
+        Ok(())
+    }
+
+    // 関数を作らず中断もしないコードのスコープは、循環参照の一部にならないためGCに登録せず、
+    // 使い終わったスコープを再利用する
+    fn new_scope(
+        &mut self,
+        code: &Code,
+        info: Rc<ScopeInfo>,
+        parent: Option<Rc<Scope>>,
+    ) -> Rc<Scope> {
+        if !code.functions.is_empty() || code.is_generator || code.is_async {
+            return self.track_scope(Scope::new(info, parent));
+        }
+        match self.take_scope() {
+            Some(mut scope) => {
+                Rc::get_mut(&mut scope)
+                    .expect("recycled scope should not be shared")
+                    .reset(info, parent);
+                scope
+            }
+            None => Rc::new(Scope::new(info, parent)),
+        }
+    }
+
+    // どこからも参照されていないスコープを、値を手放してから再利用できるようにする
+    fn recycle_scope(&mut self, mut scope: Rc<Scope>) {
+        if let Some(s) = Rc::get_mut(&mut scope) {
+            s.slots.get_mut().clear();
+            s.imports.get_mut().clear();
+            s.parent = None;
+            self.put_scope(scope);
+        }
+    }
+
+    // 関数を作る。名前付きの関数式の場合、自身の名前だけを持つスコープを間に挟む
+    fn closure(&mut self, code: Rc<Code>, scope: Option<Rc<Scope>>) -> RuntimeValue {
+        let self_scope = code
+            .self_scope
+            .clone()
+            .map(|info| self.track_scope(Scope::new(info, scope.clone())));
+        let function = Function::new_compiled(code, self_scope.clone().or(scope));
+        let value = self.new_function(function);
+        if let Some(self_scope) = self_scope {
+            self_scope.set(0, value.clone());
+        }
+        value
+    }
+
+    fn uninitialized(&self, scope: &Scope, index: usize) -> JsException {
+        self.throw_error(
+            "ReferenceError",
+            format!(
+                "cannot access '{}' before initialization",
+                scope.info.bindings[index].name
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeneratorStatus {
+    // まだ本体を実行していない
+    SuspendedStart,
+    SuspendedYield,
+    Running,
+    Completed,
+}
+
+// ジェネレータとasync関数の状態。中断している間はフレームを保存する
+pub struct GeneratorState {
+    frame: Option<Frame>,
+    status: GeneratorStatus,
+    // async関数の場合、関数の結果で解決するPromise
+    promise: Option<RuntimeValue>,
+}
+
+impl GeneratorState {
+    pub fn mark(&self, marker: &mut Marker) {
+        if let Some(frame) = &self.frame {
+            frame.mark(marker);
+        }
+        if let Some(promise) = &self.promise {
+            marker.mark_value(promise);
+        }
+    }
+}
+
+// フレームは自身のジェネレータを参照するため、状態だけを表示する
+impl Debug for GeneratorState {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("GeneratorState")
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+// ジェネレータを再開する方法。next()、throw()、return()に対応する
+pub enum Resume {
+    Next(RuntimeValue),
+    Throw(RuntimeValue),
+    Return(RuntimeValue),
+}
+
+// yield*で委譲先のイテレータを進めた結果
+enum Delegated {
+    // 委譲先が中断した。値をそのままyieldする
+    Yield(RuntimeValue),
+    // 委譲先が終わった。値がyield*の式の値になる
+    Done(RuntimeValue),
+    // return()で委譲先が終わった。値をジェネレータの戻り値にする
+    Return(RuntimeValue),
+}
+
+fn generator_state(value: &RuntimeValue) -> Option<Rc<RefCell<GeneratorState>>> {
+    match value {
+        RuntimeValue::Object(object) => match RefCell::borrow(object).slot() {
+            Some(InternalSlot::Generator(state)) => Some(state.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl JsRuntime {
+    // 中断したフレームを保存するオブジェクトを作る
+    fn new_generator(
+        &mut self,
+        mut frame: Frame,
+        prototype: Option<RuntimeValue>,
+        promise: Option<RuntimeValue>,
+    ) -> RuntimeValue {
+        let generator = self.track(RuntimeValue::Object(Rc::new(RefCell::new(
+            JsObject::with_prototype(prototype),
+        ))));
+        frame.generator = Some(generator.clone());
+        let state = GeneratorState {
+            frame: Some(frame),
+            status: GeneratorStatus::SuspendedStart,
+            promise,
+        };
+        if let RuntimeValue::Object(object) = &generator {
+            object
+                .borrow_mut()
+                .set_slot(InternalSlot::Generator(Rc::new(RefCell::new(state))));
+        }
+        generator
+    }
+
+    // ジェネレータを次のyieldか終わりまで実行し、値と終わったかどうかを返す
+    // yieldで中断している間のreturn()は、finally節を実行してからジェネレータを終える
+    pub fn resume_generator(
+        &mut self,
+        generator: &RuntimeValue,
+        resume: Resume,
+    ) -> Result<(RuntimeValue, bool), JsException> {
+        let state = match generator_state(generator) {
+            Some(state) => state,
+            None => {
+                return Err(self.throw_error("TypeError", "not a generator".to_string()));
+            }
+        };
+
+        let (mut frame, thrown) = {
+            let mut state = state.borrow_mut();
+            let status = state.status;
+            if status == GeneratorStatus::Running {
+                return Err(
+                    self.throw_error("TypeError", "generator is already running".to_string())
+                );
+            }
+            match resume {
+                Resume::Next(_) if status == GeneratorStatus::Completed => {
+                    return Ok((RuntimeValue::Undefined, true));
+                }
+                Resume::Return(value) if status != GeneratorStatus::SuspendedYield => {
+                    state.status = GeneratorStatus::Completed;
+                    state.frame = None;
+                    return Ok((value, true));
+                }
+                Resume::Throw(value) if status != GeneratorStatus::SuspendedYield => {
+                    state.status = GeneratorStatus::Completed;
+                    state.frame = None;
+                    return Err(JsException::new(value, None));
+                }
+                _ => {}
+            }
+
+            state.status = GeneratorStatus::Running;
+            let mut frame = state.frame.take().expect("suspended frame should exist");
+            let delegating = status == GeneratorStatus::SuspendedYield
+                && matches!(
+                    frame.code.instructions[frame.ip - 1],
+                    Instruction::Delegate(_)
+                );
+            let thrown = match resume {
+                // yield*で中断している場合は、Delegate命令をやり直して委譲先に渡す
+                resume if delegating => {
+                    frame.ip -= 1;
+                    frame.delegated = Some(resume);
+                    None
+                }
+                // 最初のnext()の引数は使われない
+                Resume::Next(value) => {
+                    if status == GeneratorStatus::SuspendedYield {
+                        frame.stack.push(value);
+                    }
+                    None
+                }
+                Resume::Throw(value) => Some(JsException::new(value, None)),
+                // 中断したyieldに対応する、finally節を実行してreturnする位置へ移動する
+                Resume::Return(value) => {
+                    if let Instruction::Yield(target) = frame.code.instructions[frame.ip - 1] {
+                        frame.ip = target;
+                    }
+                    frame.stack.push(value);
+                    None
+                }
+            };
+            (frame, thrown)
+        };
+
+        self.enter_frame();
+        let result = self.run_frame(&mut frame, thrown);
+        self.leave_frame();
+
+        let mut state = state.borrow_mut();
+        match result {
+            Ok(value) if frame.yielded => {
+                frame.yielded = false;
+                state.frame = Some(frame);
+                state.status = GeneratorStatus::SuspendedYield;
+                Ok((value, false))
+            }
+            result => {
+                state.status = GeneratorStatus::Completed;
+                result.map(|value| (value, true))
+            }
+        }
+    }
+
+    // yield*の委譲先を、ジェネレータを再開した方法で進める
+    // throw()とreturn()を持たないイテレータの場合、throw()はTypeErrorになり、return()はそのまま終わる
+    fn delegate(
+        &mut self,
+        iterator: &RuntimeValue,
+        resume: Resume,
+    ) -> Result<Delegated, JsException> {
+        let object = match iterator {
+            RuntimeValue::Object(object) => match RefCell::borrow(object).slot() {
+                Some(InternalSlot::Iterator(IteratorState::Protocol(object))) => {
+                    Some(object.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        let returning = matches!(resume, Resume::Return(_));
+        let (name, value) = match &resume {
+            Resume::Next(value) => ("next", value.clone()),
+            Resume::Throw(value) => ("throw", value.clone()),
+            Resume::Return(value) => ("return", value.clone()),
+        };
+        let method = match &object {
+            Some(object) => self.get(object, name)?,
+            None => RuntimeValue::Undefined,
+        };
+
+        let result = match (object, resume) {
+            (Some(object), _) if method.is_function() => {
+                self.call(&method, object, Vec::from([value]))?
+            }
+            // 配列と文字列の要素はnext()の引数を使わない
+            (None, Resume::Next(_)) => {
+                return Ok(match self.iterator_next(iterator)? {
+                    Some(value) => Delegated::Yield(value),
+                    None => Delegated::Done(RuntimeValue::Undefined),
+                });
+            }
+            (_, Resume::Return(value)) => return Ok(Delegated::Return(value)),
+            (_, Resume::Throw(_)) => {
+                self.iterator_close(iterator)?;
+                return Err(self.throw_error(
+                    "TypeError",
+                    "the delegated iterator does not have a throw method".to_string(),
+                ));
+            }
+            (Some(_), Resume::Next(_)) => {
+                return Err(self.throw_error("TypeError", "next is not a function".to_string()));
+            }
+        };
+        if !matches!(result, RuntimeValue::Object(_)) {
+            return Err(self.throw_error(
+                "TypeError",
+                format!("iterator result {} is not an object", result),
+            ));
+        }
+        let done = self.get(&result, "done")?.is_truthy();
+        let value = self.get(&result, "value")?;
+        Ok(match (done, returning) {
+            (false, _) => Delegated::Yield(value),
+            (true, true) => Delegated::Return(value),
+            (true, false) => Delegated::Done(value),
+        })
+    }
+
+    // async関数を次のawaitか終わりまで実行する。awaitした値が解決されると続きを実行する
+    fn step_async(&mut self, generator: &RuntimeValue, resume: Resume) {
+        let promise = generator_state(generator)
+            .and_then(|state| RefCell::borrow(&state).promise.clone())
+            .expect("async function should have a promise");
+        match self.resume_generator(generator, resume) {
+            Ok((value, true)) => promise::resolve_promise(self, &promise, value),
+            Ok((value, false)) => {
+                let captures = Vec::from([generator.clone()]);
+                let on_fulfilled = native_closure(
+                    self,
+                    "",
+                    |runtime, captures, _, args| {
+                        runtime.step_async(&captures[0], Resume::Next(arg(&args, 0)));
+                        Ok(RuntimeValue::Undefined)
+                    },
+                    captures.clone(),
+                );
+                let on_rejected = native_closure(
+                    self,
+                    "",
+                    |runtime, captures, _, args| {
+                        runtime.step_async(&captures[0], Resume::Throw(arg(&args, 0)));
+                        Ok(RuntimeValue::Undefined)
+                    },
+                    captures,
+                );
+                promise::await_value(self, value, on_fulfilled, on_rejected);
+            }
+            Err(exception) => promise::reject_promise(self, &promise, exception.value()),
+        }
+    }
+}
+
+// ジェネレータのnext、return、throwメソッドを定義する
+pub fn define_generator_methods(prototype: &RuntimeValue) {
+    define_methods(
+        prototype,
+        &[
+            ("next", |runtime, this, args| {
+                generator_method(runtime, this, Resume::Next(arg(&args, 0)))
+            }),
+            ("return", |runtime, this, args| {
+                generator_method(runtime, this, Resume::Return(arg(&args, 0)))
+            }),
+            ("throw", |runtime, this, args| {
+                generator_method(runtime, this, Resume::Throw(arg(&args, 0)))
+            }),
+        ],
+    );
+}
+
+// ジェネレータを再開し、{value, done}の形の結果を返す
+fn generator_method(
+    runtime: &mut JsRuntime,
+    this: RuntimeValue,
+    resume: Resume,
+) -> Result<RuntimeValue, JsException> {
+    let (value, done) = runtime.resume_generator(&this, resume)?;
+    let result = runtime.new_object();
+    result.set_property("value", value);
+    result.set_property("done", RuntimeValue::Boolean(done));
+    Ok(result)
+}
+
+fn strict_equal(frame: &mut Frame, negate: bool) {
+    let right = frame.pop();
+    let left = frame.pop();
+    frame
+        .stack
+        .push(RuntimeValue::Boolean((left == right) != negate));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::node::{Node as DomNode, NodeKind as DomNodeKind};
+    use crate::renderer::js::ast::JsParser;
+    use crate::renderer::js::event_loop::perform_microtask_checkpoint;
+    use crate::renderer::js::token::JsLexer;
+    use alloc::string::String;
+
+    fn parse(input: &str) -> Program {
+        let lexer = JsLexer::new(input.to_string());
+        let mut parser = JsParser::new(lexer);
+        parser.parse_ast().expect("failed to parse")
+    }
+
+    fn new_runtime() -> JsRuntime {
+        JsRuntime::new(Rc::new(RefCell::new(DomNode::new(DomNodeKind::Document))))
+    }
+
+    // ASTの評価とVMで同じプログラムを実行し、グローバル変数resultの値を比べる
+    fn assert_same_result(input: &str, expected: RuntimeValue) {
+        let program = parse(input);
+
+        let mut interpreter = new_runtime();
+        interpreter
+            .interpret(&program)
+            .expect("failed to interpret");
+        let mut vm = new_runtime();
+        vm.execute(&program).expect("failed to execute");
+
+        assert_eq!(Ok(expected.clone()), interpreter.lookup_global("result"));
+        assert_eq!(Ok(expected), vm.lookup_global("result"));
+    }
+
+    // VMだけでプログラムを実行し、マイクロタスクの後のグローバル変数resultの値を確かめる
+    // async関数とジェネレータはASTの評価では実行できない
+    fn assert_vm_result(input: &str, expected: RuntimeValue) {
+        let mut vm = new_runtime();
+        vm.execute(&parse(input)).expect("failed to execute");
+        perform_microtask_checkpoint(&mut vm);
+        assert_eq!(Ok(expected), vm.lookup_global("result"));
+    }
+
+    // ASTの評価とVMで同じプログラムを実行し、捕捉されなかった例外を比べる
+    fn assert_same_error(input: &str, expected: &str) {
+        let program = parse(input);
+
+        let mut interpreter = new_runtime();
+        let interpreter_error = interpreter.interpret(&program).expect_err("should throw");
+        let mut vm = new_runtime();
+        let vm_error = vm.execute(&program).expect_err("should throw");
+
+        assert_eq!(expected, interpreter_error.to_string());
+        assert_eq!(expected, vm_error.to_string());
+    }
+
+    #[test]
+    fn test_recursion() {
+        assert_same_result(
+            "function fib(n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); } var result = fib(15);",
+            RuntimeValue::Number(610.0),
+        );
+    }
+
+    #[test]
+    fn test_infinite_recursion() {
+        assert_same_result(
+            "var result; try { (function f() { f(); })(); } catch (e) { result = e.name + \": \" + e.message; }",
+            RuntimeValue::StringLiteral(String::from(
+                "RangeError: Maximum call stack size exceeded",
+            )),
+        );
+        // 上限に達した後も、同じランタイムで関数を呼び出せる
+        assert_same_result(
+            "function f(n) { return f(n + 1); } function g(n) { return n == 0 ? 0 : 1 + g(n - 1); } var result; try { f(0); } catch (e) { result = g(10); }",
+            RuntimeValue::Number(10.0),
+        );
+    }
+
+    #[test]
+    fn test_long_binary_chains() {
+        // 同じ優先順位の演算子が続く長い式は、入れ子が深すぎるとはみなさない
+        assert_same_result(
+            &format!("var result = {}1;", "1 + ".repeat(299)),
+            RuntimeValue::Number(300.0),
+        );
+        assert_same_result(
+            &format!("var result = \"\"{};", " + \"ab\"".repeat(400)),
+            RuntimeValue::StringLiteral("ab".repeat(400)),
+        );
+        assert_same_result(
+            &format!("var result = {}true;", "false || 0 && x || ".repeat(300)),
+            RuntimeValue::Boolean(true),
+        );
+    }
+
+    #[test]
+    fn test_stack_size() {
+        let program = parse(
+            "function g(n) { return n == 0 ? 0 : 1 + g(n - 1); } var result; try { result = g(40); } catch (e) { result = e.name; }",
+        );
+
+        // 既定のスタックでは40回の再帰を実行できる
+        let mut vm = new_runtime();
+        vm.execute(&program).expect("failed to execute");
+        assert_eq!(Ok(RuntimeValue::Number(40.0)), vm.lookup_global("result"));
+
+        // スタックが小さいスレッドでは、呼び出しの深さの上限より手前でRangeErrorにする
+        let expected = Ok(RuntimeValue::StringLiteral(String::from("RangeError")));
+        let mut interpreter = new_runtime();
+        interpreter.set_stack_size(64 * 1024);
+        interpreter
+            .interpret(&program)
+            .expect("failed to interpret");
+        assert_eq!(expected, interpreter.lookup_global("result"));
+        let mut vm = new_runtime();
+        vm.set_stack_size(64 * 1024);
+        vm.execute(&program).expect("failed to execute");
+        assert_eq!(expected, vm.lookup_global("result"));
+    }
+
+    #[test]
+    fn test_regexp_or_division_by_context() {
+        // )や}の後でも、文や式の始まりの/は正規表現リテラル
+        assert_same_result(
+            "var x = true; var r = []; if (x) /a/.test(\"a\") ? r.push(1) : r.push(0); while (false) /a/g; { r.push(2); } /b/.test(\"b\") && r.push(3); if (x) /=/.test(\"=\") && r.push(4); var result = r.join(\",\");",
+            RuntimeValue::StringLiteral(String::from("1,2,3,4")),
+        );
+        // 値の後の/は、改行を挟んでも除算
+        assert_same_result(
+            "var a = 8, b = 2, g = 2; var c = a\n/b/g; var d = (a) /b; d /= 2; var result = c + \",\" + d;",
+            RuntimeValue::StringLiteral(String::from("2,2")),
+        );
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_same_result(
+            "var s = \"\"; var i = 0; while (i < 10) { i = i + 1; if (i % 2 == 0) { continue; } if (i > 8) { break; } s = s + i; } for (let j = 0; j < 3; j = j + 1) { s = s + \"-\" + j; } var result = s;",
+            RuntimeValue::StringLiteral(String::from("1357-0-1-2")),
+        );
+    }
+
+    #[test]
+    fn test_do_while_and_switch() {
+        assert_same_result(
+            "var s = \"\"; var i = 0; do { i = i + 1; s = s + i; } while (i < 3) do s = s + \"!\"; while (false); for (let n = 0; n < 4; n = n + 1) { switch (n) { case 0: s = s + \"a\"; case 1: s = s + \"b\"; break; default: s = s + \"d\"; continue; case 3: s = s + \"c\"; } s = s + \";\"; } var result = s;",
+            RuntimeValue::StringLiteral(String::from("123!ab;b;dc;")),
+        );
+        // 一致するcaseがない場合、defaultがなければ何も実行しない
+        assert_same_result(
+            "var result = 0; switch (\"1\") { case 1: result = 1; } switch (2) { case 1: result = 1; default: result = 2; }",
+            RuntimeValue::Number(2.0),
+        );
+    }
+
+    #[test]
+    fn test_labeled_statements() {
+        assert_same_result(
+            "var s = \"\"; outer: for (let i = 0; i < 3; i = i + 1) { inner: for (let j = 0; j < 3; j = j + 1) { if (j == 1) { continue outer; } if (i == 2) { break outer; } s = s + i + j; } } block: { s = s + \"-\"; break block; s = s + \"x\"; } a: b: while (true) { while (true) { break a; } } var result = s;",
+            RuntimeValue::StringLiteral(String::from("0010-")),
+        );
+    }
+
+    #[test]
+    fn test_for_in_and_for_of() {
+        assert_same_result(
+            "var s = \"\"; var o = { a: 1, b: 2 }; for (var k in o) { s = s + k + o[k]; } for (const x of [3, 4]) { s = s + x; } for (let c of \"hi\") { s = s + c; } for (k in [5, 6]) { s = s + k; } for (k in null) { s = s + \"x\"; } var fs = []; for (let x of [7, 8]) { fs.push(() => x); } for (const f of fs) { s = s + f(); } var result = s + k;",
+            RuntimeValue::StringLiteral(String::from("a1b234hi01781")),
+        );
+        assert_same_result(
+            "var o = {}; for (o.last of [1, 2, 3]) { if (o.last == 2) { break; } } var result = o.last;",
+            RuntimeValue::Number(2.0),
+        );
+        assert_same_error(
+            "function f() { for (const x of 1) {} }\nf();",
+            "Uncaught TypeError: 1 is not iterable (2:1)",
+        );
+        // ジェネレータはASTの評価では実行できない
+        assert_vm_result(
+            "function* g() { yield 1; yield 2; } var result = 0; for (const x of g()) { result = result + x; }",
+            RuntimeValue::Number(3.0),
+        );
+    }
+
+    #[test]
+    fn test_cyclic_array_to_string() {
+        assert_same_result(
+            "var a = [1]; a.push(a); var result = a.join() + \"|\" + String(a) + \"|\" + [a, 2].toString();",
+            RuntimeValue::StringLiteral(String::from("1,|1,|1,,2")),
+        );
+    }
+
+    #[test]
+    fn test_to_primitive() {
+        // 算術演算子と関係演算子はvalueOfを先に呼ぶ
+        assert_same_result(
+            "var n = { valueOf() { return 3; } }; var result = [n > 2, n < 2, n >= 3, +{ valueOf() { return 7; } }, -n, n - 1, n * 2, n / 3, n % 2, n ** 2, n << 1, n | 4, ~n].join();",
+            RuntimeValue::StringLiteral(String::from("true,false,true,7,-3,2,6,1,1,9,6,7,-4")),
+        );
+        // +と==はhintなし、テンプレートリテラルとString()はtoStringを先に呼ぶ
+        assert_same_result(
+            "var both = { valueOf() { return 1; }, toString() { return \"s\"; } }; var result = [both + 1, both == 1, both != \"s\", `${both}`, String(both), [both].join()].join();",
+            RuntimeValue::StringLiteral(String::from("2,true,true,s,s,s")),
+        );
+        assert_same_result(
+            "class V { toString() { return \"v\"; } } var v = new V(); var n = { valueOf() { return 2; } }; var i = n; i++; n += 1; var result = `${v}` + \":\" + (v + 1) + \":\" + (v == \"v\") + \":\" + i + \":\" + n + \":\" + [1, [2, { toString() { return \"t\"; } }]].join(\"-\");",
+            RuntimeValue::StringLiteral(String::from("v:v1:true:3:3:1-2,t")),
+        );
+        // 組み込みのtoString
+        assert_same_result(
+            "var result = [String({}), \"\" + [1, 2], String(function f() {}), Object.prototype.toString.call([])].join(\"|\");",
+            RuntimeValue::StringLiteral(String::from(
+                "[object Object]|1,2|function f() { [code] }|[object Array]",
+            )),
+        );
+        // どちらもプリミティブな値を返さない場合はTypeError
+        assert_same_result(
+            "var o = { valueOf() { return {}; }, toString() { return {}; } }; var result; try { o + 1; } catch (e) { result = e.name; }",
+            RuntimeValue::StringLiteral(String::from("TypeError")),
+        );
+    }
+
+    #[test]
+    fn test_error_to_string() {
+        // Error.prototype.toStringはToPrimitiveから呼ばれる
+        assert_same_result(
+            "var e = new Error(\"boom\"); var r = [String(e), \"\" + e, `${e}`]; try { null.x; } catch (err) { r.push(\"failed: \" + err); } r.push(String(new RangeError()), String(new Error({ toString() { return \"m\"; } }))); var result = r.join(\"|\");",
+            RuntimeValue::StringLiteral(String::from(
+                "Error: boom|Error: boom|Error: boom|failed: TypeError: cannot read properties of null (reading 'x')|RangeError|Error: m",
+            )),
+        );
+    }
+
+    #[test]
+    fn test_string_utf16() {
+        // 長さと位置はUTF-16のコードユニットで数える
+        assert_same_result(
+            "var s = \"a😀b\"; var result = [\"😀\".length, s.length, s.charCodeAt(1), s.charCodeAt(2), s.indexOf(\"b\"), s.lastIndexOf(\"😀\"), s.slice(1, 3), s.substring(3), s.slice(-1), s.charAt(3), s[3], s.includes(\"😀b\")].join();",
+            RuntimeValue::StringLiteral(String::from("2,4,55357,56832,3,1,😀,b,b,b,b,true")),
+        );
+        assert_same_result(
+            "var result = String.fromCharCode(97, 98) + String.fromCharCode(0xD83D, 0xDE00) + String.fromCharCode(65601) + String.fromCharCode({ valueOf() { return 99; } }) + String.fromCharCode().length;",
+            RuntimeValue::StringLiteral(String::from("ab😀Ac0")),
+        );
+    }
+
+    #[test]
+    fn test_tagged_template() {
+        // タグの関数は文字列の配列と式の値を受け取る。配列のrawはエスケープを解釈しない文字列
+        assert_same_result(
+            "function tag(s, a, b) { return s.length + \":\" + s.join(\"|\") + \":\" + s.raw.join(\"|\") + \":\" + a + \":\" + b; } var o = { p: \"P\", m(s, x) { return this.p + s[0] + x; } }; var result = [String.raw`a\\nb${1}c`, tag`x\\t${1 + 1}y${\"z\"}`, o.m`q${3}`, tag``].join(\";\");",
+            RuntimeValue::StringLiteral(String::from(
+                "a\\nb1c;3:x\t|y|:x\\t|y|:2:z;Pq3;1:::undefined:undefined",
+            )),
+        );
+    }
+
+    #[test]
+    fn test_html_like_comment() {
+        // スクリプトでは<!--と行頭の-->から行末までがコメントになる
+        assert_same_result(
+            "var result = 1; <!-- result = 10;\n--> result = 20;\nresult = result + 1; var x = 3; result += x --> 2;",
+            RuntimeValue::Number(3.0),
+        );
+    }
+
+    #[test]
+    fn test_json_depth_limit() {
+        assert_same_result(
+            "var names = []; try { JSON.parse(\"[\".repeat(100000)); } catch (e) { names.push(e.name); } var a = []; for (var i = 0; i < 1000; i++) { a = [a]; } try { JSON.stringify(a); } catch (e) { names.push(e.name); } var result = names.join() + \":\" + JSON.stringify(JSON.parse(\"[[[1]]]\")) + \":\" + JSON.stringify(JSON.parse(\"[\".repeat(500) + \"]\".repeat(500))).length;",
+            RuntimeValue::StringLiteral(String::from("SyntaxError,RangeError:[[[1]]]:1000")),
+        );
+    }
+
+    #[test]
+    fn test_array_properties_and_length() {
+        assert_same_result(
+            "var a = [1, 2]; a.x = 5; a[\"01\"] = 3; a[2] = 4; a[1e10] = 6; var keys = Object.keys(a).join(\",\"); a.length = 1; var result = a.x + \":\" + a.length + \":\" + keys + \":\" + a[\"01\"] + a[1] + a[\"10000000000\"];",
+            RuntimeValue::StringLiteral(String::from("5:1:0,1,2,x,01,10000000000:3undefined6")),
+        );
+        // 間の要素をすべて確保するため、長さの上限を超える添字やlengthはRangeErrorにする
+        assert_same_error(
+            "function f() { var a = []; a[1e8] = 1; }\nf();",
+            "Uncaught RangeError: invalid array length (2:1)",
+        );
+        assert_same_error(
+            "function f() { var a = []; a.length = -1; }\nf();",
+            "Uncaught RangeError: invalid array length (2:1)",
+        );
+        assert_same_error(
+            "var a = new Array(1e9);",
+            "Uncaught RangeError: invalid array length (1:9)",
+        );
+    }
+
+    #[test]
+    fn test_array_elision_and_key_order() {
+        assert_same_result(
+            "var result = [[, 1].length, [1, , 2, ].length, [, ].length, typeof [, 1][0]].join();",
+            RuntimeValue::StringLiteral(String::from("2,3,1,undefined")),
+        );
+        // 整数のキーは小さい順、それ以外のキーは追加された順になる
+        assert_same_result(
+            "var o = { b: 1, a: 2, 1: 3 }; o[0] = 4; o.c = 5; var keys = ''; for (var k in o) { keys += k; } var result = Object.keys(o).join() + ':' + keys + ':' + JSON.stringify({ b: 1, 2: 2, a: 3, 1: 4 });",
+            RuntimeValue::StringLiteral(String::from(
+                "0,1,b,a,c:01bac:{\"1\":4,\"2\":2,\"b\":1,\"a\":3}",
+            )),
+        );
+    }
+
+    #[test]
+    fn test_closures() {
+        assert_same_result(
+            "function counter() { let c = 0; return function () { c = c + 1; return c; }; } var next = counter(); next(); next(); var fs = []; for (let i = 0; i < 3; i = i + 1) { fs.push(() => i); } var result = next() + fs[0]() + fs[1]() + fs[2]();",
+            RuntimeValue::Number(6.0),
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert_same_result(
+            "let x = \"outer\"; var log = \"\"; { let x = \"inner\"; log = log + x; } var f = function g(n) { return n == 0 ? x : g(n - 1); }; var result = log + f(3);",
+            RuntimeValue::StringLiteral(String::from("innerouter")),
+        );
+        // for文のletと本体のletは別のスコープなので、同じ名前で宣言できる
+        assert_same_result(
+            "var log = ''; for (let i = 0; i < 2; i++) { let i = 'x'; log += i; } let i = 3; function f(i) { var i = i + 1; return i; } var result = log + i + f(1);",
+            RuntimeValue::StringLiteral(String::from("xx32")),
+        );
+    }
+
+    #[test]
+    fn test_this_and_arrow_functions() {
+        assert_same_result(
+            "var o = { n: 3, f() { return [1, 2].map(x => x * this.n); } }; var result = o.f().join(\",\");",
+            RuntimeValue::StringLiteral(String::from("3,6")),
+        );
+    }
+
+    #[test]
+    fn test_exceptions() {
+        assert_same_result(
+            "var log = \"\"; function f() { try { return \"a\"; } finally { log = log + \"f\"; } } var r = f(); for (let i = 0; i < 5; i = i + 1) { try { if (i == 2) { break; } log = log + i; } finally { log = log + \".\"; } } function thrower() { throw new TypeError(\"x\"); } try { try { thrower(); } finally { log = log + \"!\"; } } catch (e) { log = log + e.message + (e instanceof TypeError); } var result = r + log;",
+            RuntimeValue::StringLiteral(String::from("af0.1..!xtrue")),
+        );
+    }
+
+    #[test]
+    fn test_classes() {
+        assert_same_result(
+            "class A { constructor(x) { this.x = x; } describe() { return \"A\" + this.x; } static create() { return new B(); } } class B extends A { constructor() { super(5); } describe() { return \"B\" + super.describe(); } } var result = A.create().describe() + (A.create() instanceof A);",
+            RuntimeValue::StringLiteral(String::from("BA5true")),
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_same_error(
+            "var o = {};\n  o.f();",
+            "Uncaught TypeError: o.f is not a function (2:3)",
+        );
+        // 呼び出された式をソースコードの形で表し、表せない場合はプロパティの名前にする
+        assert_same_error(
+            "var a = { b: {} };\na.b.padStart(1);",
+            "Uncaught TypeError: a.b.padStart is not a function (2:1)",
+        );
+        assert_same_error(
+            "var s = \"abc\";\n\"abc\".padStart(1);",
+            "Uncaught TypeError: \"abc\".padStart is not a function (2:1)",
+        );
+        assert_same_error(
+            "var a = [{}];\na[0].f().g();",
+            "Uncaught TypeError: a[0].f is not a function (2:1)",
+        );
+        assert_same_error(
+            "var o = { f() { return {}; } };\no.f().g();",
+            "Uncaught TypeError: o.f(...).g is not a function (2:1)",
+        );
+        assert_same_error(
+            "var a = 1;\n(a + 1).f();",
+            "Uncaught TypeError: f is not a function (2:1)",
+        );
+        assert_same_error(
+            "function f() { return y; }\nf();",
+            "Uncaught ReferenceError: y is not defined (2:1)",
+        );
+        assert_same_error("throw \"oops\";", "Uncaught oops (1:1)");
+    }
+
+    #[test]
+    fn test_generators() {
+        assert_vm_result(
+            "function* count(n) { var sent = yield n; while (n < 3) { n = n + 1; sent = yield n + \":\" + sent; } return \"end\"; } var g = count(1); var log = \"\"; var r = g.next(\"ignored\"); while (!r.done) { log = log + r.value + \" \"; r = g.next(\"s\"); } var result = log + r.value + g.next().done + (g instanceof count);",
+            RuntimeValue::StringLiteral(String::from("1 2:s 3:s endtruetrue")),
+        );
+        assert_vm_result(
+            "var log = \"\"; function* g() { try { yield 1; yield 2; } catch (e) { log = log + \"caught \" + e; yield 3; } finally { log = log + \" finally\"; } } var it = g(); it.next(); var thrown = it.throw(\"x\").value; var done = it.next().done; function* h() { yield 1; yield 2; } var r = h(); r.next(); var returned = r.return(\"early\"); var result = thrown + log + done + returned.value + returned.done + r.next().done;",
+            RuntimeValue::StringLiteral(String::from("3caught x finallytrueearlytruetrue")),
+        );
+        // yieldで中断している間のreturn()は、外側のfinally節を実行する
+        assert_vm_result(
+            "var log = []; function* g() { try { try { yield 1; log.push(\"unreachable\"); } finally { log.push(\"inner\"); } } finally { log.push(\"outer\"); } } var it = g(); it.next(); var r = it.return(5); var before = g(); var b = before.return(6); var result = log.join() + \":\" + r.value + r.done + it.next().done + b.value + b.done;",
+            RuntimeValue::StringLiteral(String::from("inner,outer:5truetrue6true")),
+        );
+        // finally節でyieldした場合はそこで中断し、finally節のreturnは値を置き換える
+        assert_vm_result(
+            "function* g() { try { yield 1; } finally { yield 2; } } var it = g(); it.next(); var a = it.return(3); var b = it.next(); function* h() { try { yield 1; } finally { return 4; } } var x = h(); x.next(); var c = x.return(5); var result = [a.value, a.done, b.value, b.done, c.value, c.done].join();",
+            RuntimeValue::StringLiteral(String::from("2,false,3,true,4,true")),
+        );
+    }
+
+    #[test]
+    fn test_generator_delegation() {
+        // yield*は委譲先の値を順にyieldし、next()の引数を渡す。委譲先の戻り値が式の値になる
+        assert_vm_result(
+            "function* inner() { var x = yield 1; yield x; return \"r\"; } function* outer() { var v = yield* inner(); yield v; yield* [4, 5]; yield* \"ab\"; return yield* { next: function (s) { return { value: s, done: s == \"s\" }; } }; } var log = []; var it = outer(); var r = it.next(); while (!r.done) { log.push(r.value); r = it.next(\"s\"); } var result = log.join() + \":\" + r.value;",
+            RuntimeValue::StringLiteral(String::from("1,s,r,4,5,a,b,:s")),
+        );
+        // throw()とreturn()も委譲先に渡す。throw()を持たない委譲先へのthrow()はTypeErrorになる
+        assert_vm_result(
+            "var log = []; function* inner() { try { yield 1; } catch (e) { log.push(\"inner \" + e); yield 2; } finally { log.push(\"inner finally\"); } } function* outer() { try { yield* inner(); } finally { log.push(\"outer finally\"); } } var a = outer(); a.next(); var t = a.throw(\"x\").value; var r = a.return(7); var b = outer(); b.next(); var s = b.return(8); function* arr() { try { yield* [1, 2]; } catch (e) { log.push(e instanceof TypeError); } } var c = arr(); c.next(); var d = c.throw(\"y\"); var e = arr(); e.next(); var f = e.return(9); var result = [t, r.value, r.done, s.value, s.done, d.done, f.value, f.done, log.join()].join();",
+            RuntimeValue::StringLiteral(String::from(
+                "2,7,true,8,true,true,9,true,inner x,inner finally,outer finally,inner finally,outer finally,true",
+            )),
+        );
+    }
+
+    #[test]
+    fn test_for_of_closes_iterator() {
+        // breakやreturnで途中で抜けた場合だけreturn()を呼び出す
+        assert_same_result(
+            "var log = []; function iter(n) { var i = 0; return { next: function () { i++; return { value: i, done: i > n }; }, return: function () { log.push(\"return \" + i); return {}; } }; } for (var x of iter(5)) { if (x == 2) { break; } } for (var y of iter(2)) { continue; } function f() { for (var z of iter(5)) { if (z == 3) { return z; } } } f(); outer: for (var a of iter(5)) { for (var b of iter(5)) { if (b == 1) { continue outer; } } } var result = log.join();",
+            RuntimeValue::StringLiteral(String::from(
+                "return 2,return 3,return 1,return 1,return 1,return 1,return 1",
+            )),
+        );
+        assert_vm_result(
+            "var log = []; function* g() { try { yield 1; yield 2; } finally { log.push(\"closed\"); } } for (var v of g()) { log.push(v); break; } var result = log.join();",
+            RuntimeValue::StringLiteral(String::from("1,closed")),
+        );
+    }
+
+    #[test]
+    fn test_async_functions() {
+        assert_vm_result(
+            "var log = []; async function f(x) { log.push(\"start\"); var a = await x; log.push(\"after \" + a); var b = await Promise.resolve(a + 1); return b * 2; } f(1).then(function (v) { log.push(\"result \" + v); }); log.push(\"sync\"); var result; Promise.resolve().then(function () {}).then(function () {}).then(function () {}).then(function () { result = log.join(); });",
+            RuntimeValue::StringLiteral(String::from("start,sync,after 1,result 4")),
+        );
+        assert_vm_result(
+            "var result = \"\"; var fail = async () => { await null; throw new Error(\"bad\"); }; var o = { async m() { try { await fail(); } catch (e) { return \"caught \" + e.message; } } }; o.m().then(function (v) { result = v; });",
+            RuntimeValue::StringLiteral(String::from("caught bad")),
+        );
+    }
+
+    #[test]
+    fn test_update_and_compound_assignment() {
+        assert_same_result(
+            "var i = 1; var a = i++; var b = ++i; var c = i--; let s = \"x\"; s += i; const o = { n: 1 }; var d = o.n++; o.n *= 10; var arr = [5]; var e = arr[0]--; arr[0] %= 3; var t = 0; for (var k = 0; k < 4; k++) { t += k; } var result = [a, b, c, i, s, d, o.n, e, arr[0], t].join();",
+            RuntimeValue::StringLiteral(String::from("1,3,3,2,x2,1,20,5,1,6")),
+        );
+        assert_same_result(
+            "var result; const c = 1; try { c += 1; } catch (e) { result = e.message; }",
+            RuntimeValue::StringLiteral(String::from("assignment to constant variable 'c'")),
+        );
+        // ループの更新式に++を使っても、ASTの評価で終了する
+        assert_same_result(
+            "var result = 0; var i = 0; while (i < 5) { i++; result += i; } for (let j = 3; j > 0; --j) { result -= j; }",
+            RuntimeValue::Number(9.0),
+        );
+    }
+
+    #[test]
+    fn test_typeof_and_operators() {
+        assert_same_result(
+            "function f() {} let n = null; var result = [typeof 1, typeof 'a', typeof undeclared, typeof n, typeof {}, typeof [], typeof f, typeof true, typeof typeof 1, void f()].join();",
+            RuntimeValue::StringLiteral(String::from(
+                "number,string,undefined,object,object,object,function,boolean,string,",
+            )),
+        );
+        assert_same_result(
+            "var result = [2 ** 10, 2 ** 3 ** 2, (-2) ** 2, 2 ** -1, 5 & 3, 5 | 3, 5 ^ 3, 1 << 33, -16 >> 2, -1 >>> 28, ~5, 2 ** 32 + 5 | 0, 1 + 2 << 1, 1 | 2 == 2].join();",
+            RuntimeValue::StringLiteral(String::from(
+                "1024,512,4,0.5,1,7,6,2,-4,15,-6,5,6,1",
+            )),
+        );
+        assert_same_result(
+            "var o = { a: 1 }; var arr = [1, 2]; var r = ['a' in o, delete o.a, 'a' in o, 'toString' in o, 1 in arr, 2 in arr, delete arr[0], delete arr.length, delete 1]; var x = 2; x **= 3; x <<= 2; x |= 1; x ^= 3; x >>= 1; var result = r.join() + ':' + arr + ':' + x;",
+            RuntimeValue::StringLiteral(String::from(
+                "true,true,false,true,true,false,true,false,true:,2:17",
+            )),
+        );
+        // カンマ演算子は最後の値になり、forの初期化式のinはfor-inの区切りになる
+        assert_same_result(
+            "var n = 0; for (var i = 0, j = 5; i < j; i++, j--) { n = (n, n + 1); } var keys = ''; for (var k in { a: 1, b: 2 }) { keys += k; } var result = n + keys;",
+            RuntimeValue::StringLiteral(String::from("3ab")),
+        );
+        assert_same_error(
+            "var result = 'a' in 1;",
+            "Uncaught TypeError: cannot use 'in' operator to search for 'a' in 1",
+        );
+        assert_same_error(
+            "let t = typeof y; let y = 1;",
+            "Uncaught ReferenceError: cannot access 'y' before initialization",
+        );
+    }
+
+    #[test]
+    fn test_template_literals_and_asi() {
+        assert_same_result(
+            "var name = 'world'\nvar n = 2\nvar result = `hello ${name}, ${n + 1} ${`nested ${n}`}!`",
+            RuntimeValue::StringLiteral(String::from("hello world, 3 nested 2!")),
+        );
+        assert_same_result(
+            "function f() {\n  return\n  1\n}\nvar result = f() // コメント\n/* 複数行の\nコメント */",
+            RuntimeValue::Undefined,
+        );
+    }
+}
@@ -1,14 +1,19 @@
-use core::cell::RefCell;
+use core::{cell::RefCell, str::FromStr};
 
 use alloc::{
     format,
     rc::Rc,
     string::{String, ToString},
+    vec::Vec,
 };
 
 use crate::{
     error::Error,
-    renderer::dom::node::{ElementKind, Node, NodeKind},
+    renderer::{
+        css::cssom::ComponentValue,
+        dom::node::{ElementKind, Node, NodeKind},
+        layout::grid::{GridLine, GridTrackSize},
+    },
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,8 +23,29 @@ pub struct ComputedStyle {
     display: Option<DisplayType>,
     font_size: Option<FontSize>,
     text_decoration: Option<TextDecoration>,
-    height: Option<f64>,
-    width: Option<f64>,
+    height: Option<Length>,
+    width: Option<Length>,
+    position: Option<Position>,
+    top: Option<Length>,
+    right: Option<Length>,
+    bottom: Option<Length>,
+    left: Option<Length>,
+    z_index: Option<ZIndex>,
+    float: Option<Float>,
+    clear: Option<Clear>,
+    grid_template_columns: Option<Vec<GridTrackSize>>,
+    grid_template_rows: Option<Vec<GridTrackSize>>,
+    grid_template_areas: Option<Vec<Vec<String>>>,
+    row_gap: Option<f64>,
+    column_gap: Option<f64>,
+    grid_row_start: Option<GridLine>,
+    grid_row_end: Option<GridLine>,
+    grid_column_start: Option<GridLine>,
+    grid_column_end: Option<GridLine>,
+    table_layout: Option<TableLayoutType>,
+    border_collapse: Option<BorderCollapse>,
+    border_spacing: Option<f64>,
+    vertical_align: Option<VerticalAlign>,
 }
 
 impl ComputedStyle {
@@ -32,6 +58,27 @@ impl ComputedStyle {
             text_decoration: None,
             height: None,
             width: None,
+            position: None,
+            top: None,
+            right: None,
+            bottom: None,
+            left: None,
+            z_index: None,
+            float: None,
+            clear: None,
+            grid_template_columns: None,
+            grid_template_rows: None,
+            grid_template_areas: None,
+            row_gap: None,
+            column_gap: None,
+            grid_row_start: None,
+            grid_row_end: None,
+            grid_column_start: None,
+            grid_column_end: None,
+            table_layout: None,
+            border_collapse: None,
+            border_spacing: None,
+            vertical_align: None,
         }
     }
 
@@ -80,26 +127,234 @@ impl ComputedStyle {
             .expect("failed to access CSS property: text_decoration")
     }
 
-    pub fn set_height(&mut self, height: f64) {
+    pub fn set_height(&mut self, height: Length) {
         self.height = Some(height)
     }
 
-    pub fn height(&self) -> f64 {
+    pub fn height(&self) -> Length {
         self.height
             // .clone()
             .expect("failed to access CSS property: height")
     }
 
-    pub fn set_width(&mut self, width: f64) {
+    pub fn set_width(&mut self, width: Length) {
         self.width = Some(width)
     }
 
-    pub fn width(&self) -> f64 {
+    pub fn width(&self) -> Length {
         self.width
             // .clone()
             .expect("failed to access CSS property: width")
     }
 
+    pub fn set_position(&mut self, position: Position) {
+        self.position = Some(position)
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+            .expect("failed to access CSS property: position")
+    }
+
+    pub fn set_top(&mut self, top: Length) {
+        self.top = Some(top)
+    }
+
+    pub fn top(&self) -> Length {
+        self.top.expect("failed to access CSS property: top")
+    }
+
+    pub fn set_right(&mut self, right: Length) {
+        self.right = Some(right)
+    }
+
+    pub fn right(&self) -> Length {
+        self.right.expect("failed to access CSS property: right")
+    }
+
+    pub fn set_bottom(&mut self, bottom: Length) {
+        self.bottom = Some(bottom)
+    }
+
+    pub fn bottom(&self) -> Length {
+        self.bottom.expect("failed to access CSS property: bottom")
+    }
+
+    pub fn set_left(&mut self, left: Length) {
+        self.left = Some(left)
+    }
+
+    pub fn left(&self) -> Length {
+        self.left.expect("failed to access CSS property: left")
+    }
+
+    pub fn set_z_index(&mut self, z_index: ZIndex) {
+        self.z_index = Some(z_index)
+    }
+
+    pub fn z_index(&self) -> ZIndex {
+        self.z_index
+            .expect("failed to access CSS property: z_index")
+    }
+
+    pub fn set_float(&mut self, float: Float) {
+        self.float = Some(float)
+    }
+
+    pub fn float(&self) -> Float {
+        self.float.expect("failed to access CSS property: float")
+    }
+
+    pub fn set_clear(&mut self, clear: Clear) {
+        self.clear = Some(clear)
+    }
+
+    pub fn clear(&self) -> Clear {
+        self.clear.expect("failed to access CSS property: clear")
+    }
+
+    pub fn is_floating(&self) -> bool {
+        self.float() != Float::None
+    }
+
+    // 通常のフローから外れて配置される要素か
+    pub fn is_out_of_flow(&self) -> bool {
+        matches!(self.position(), Position::Absolute | Position::Fixed)
+    }
+
+    // 新しいスタッキングコンテキストを作る要素か
+    pub fn creates_stacking_context(&self) -> bool {
+        match self.position() {
+            Position::Static => false,
+            Position::Fixed | Position::Sticky => true,
+            Position::Relative | Position::Absolute => self.z_index() != ZIndex::Auto,
+        }
+    }
+
+    pub fn set_grid_template_columns(&mut self, tracks: Vec<GridTrackSize>) {
+        self.grid_template_columns = Some(tracks)
+    }
+
+    pub fn grid_template_columns(&self) -> Vec<GridTrackSize> {
+        self.grid_template_columns
+            .clone()
+            .expect("failed to access CSS property: grid_template_columns")
+    }
+
+    pub fn set_grid_template_rows(&mut self, tracks: Vec<GridTrackSize>) {
+        self.grid_template_rows = Some(tracks)
+    }
+
+    pub fn grid_template_rows(&self) -> Vec<GridTrackSize> {
+        self.grid_template_rows
+            .clone()
+            .expect("failed to access CSS property: grid_template_rows")
+    }
+
+    pub fn set_grid_template_areas(&mut self, areas: Vec<Vec<String>>) {
+        self.grid_template_areas = Some(areas)
+    }
+
+    pub fn grid_template_areas(&self) -> Vec<Vec<String>> {
+        self.grid_template_areas
+            .clone()
+            .expect("failed to access CSS property: grid_template_areas")
+    }
+
+    pub fn set_row_gap(&mut self, gap: f64) {
+        self.row_gap = Some(gap)
+    }
+
+    pub fn row_gap(&self) -> f64 {
+        self.row_gap
+            .expect("failed to access CSS property: row_gap")
+    }
+
+    pub fn set_column_gap(&mut self, gap: f64) {
+        self.column_gap = Some(gap)
+    }
+
+    pub fn column_gap(&self) -> f64 {
+        self.column_gap
+            .expect("failed to access CSS property: column_gap")
+    }
+
+    pub fn set_grid_row_start(&mut self, line: GridLine) {
+        self.grid_row_start = Some(line)
+    }
+
+    pub fn grid_row_start(&self) -> GridLine {
+        self.grid_row_start
+            .clone()
+            .expect("failed to access CSS property: grid_row_start")
+    }
+
+    pub fn set_grid_row_end(&mut self, line: GridLine) {
+        self.grid_row_end = Some(line)
+    }
+
+    pub fn grid_row_end(&self) -> GridLine {
+        self.grid_row_end
+            .clone()
+            .expect("failed to access CSS property: grid_row_end")
+    }
+
+    pub fn set_grid_column_start(&mut self, line: GridLine) {
+        self.grid_column_start = Some(line)
+    }
+
+    pub fn grid_column_start(&self) -> GridLine {
+        self.grid_column_start
+            .clone()
+            .expect("failed to access CSS property: grid_column_start")
+    }
+
+    pub fn set_grid_column_end(&mut self, line: GridLine) {
+        self.grid_column_end = Some(line)
+    }
+
+    pub fn grid_column_end(&self) -> GridLine {
+        self.grid_column_end
+            .clone()
+            .expect("failed to access CSS property: grid_column_end")
+    }
+
+    pub fn set_table_layout(&mut self, table_layout: TableLayoutType) {
+        self.table_layout = Some(table_layout)
+    }
+
+    pub fn table_layout(&self) -> TableLayoutType {
+        self.table_layout
+            .expect("failed to access CSS property: table_layout")
+    }
+
+    pub fn set_border_collapse(&mut self, border_collapse: BorderCollapse) {
+        self.border_collapse = Some(border_collapse)
+    }
+
+    pub fn border_collapse(&self) -> BorderCollapse {
+        self.border_collapse
+            .expect("failed to access CSS property: border_collapse")
+    }
+
+    pub fn set_border_spacing(&mut self, border_spacing: f64) {
+        self.border_spacing = Some(border_spacing)
+    }
+
+    pub fn border_spacing(&self) -> f64 {
+        self.border_spacing
+            .expect("failed to access CSS property: border_spacing")
+    }
+
+    pub fn set_vertical_align(&mut self, vertical_align: VerticalAlign) {
+        self.vertical_align = Some(vertical_align)
+    }
+
+    pub fn vertical_align(&self) -> VerticalAlign {
+        self.vertical_align
+            .expect("failed to access CSS property: vertical_align")
+    }
+
     pub fn defaulting(&mut self, node: &Rc<RefCell<Node>>, parent_style: Option<ComputedStyle>) {
         // 親ノードが存在し、そのCSSの値が初期値と異なる場合に値を継承する
         if let Some(parent_style) = parent_style {
@@ -118,6 +373,14 @@ impl ComputedStyle {
             {
                 self.text_decoration = Some(parent_style.text_decoration());
             }
+            if self.border_collapse.is_none()
+                && parent_style.border_collapse() != BorderCollapse::Separate
+            {
+                self.border_collapse = Some(parent_style.border_collapse());
+            }
+            if self.border_spacing.is_none() && parent_style.border_spacing() != 0.0 {
+                self.border_spacing = Some(parent_style.border_spacing());
+            }
         }
 
         // 各プロパティに対して初期値を設定
@@ -137,10 +400,76 @@ impl ComputedStyle {
             self.text_decoration = Some(TextDecoration::default(node));
         }
         if self.height.is_none() {
-            self.height = Some(0.0)
+            self.height = Some(Length::Auto)
         }
         if self.width.is_none() {
-            self.width = Some(0.0)
+            self.width = Some(Length::Auto)
+        }
+        // 位置指定のプロパティは継承されない
+        if self.position.is_none() {
+            self.position = Some(Position::Static);
+        }
+        if self.top.is_none() {
+            self.top = Some(Length::Auto);
+        }
+        if self.right.is_none() {
+            self.right = Some(Length::Auto);
+        }
+        if self.bottom.is_none() {
+            self.bottom = Some(Length::Auto);
+        }
+        if self.left.is_none() {
+            self.left = Some(Length::Auto);
+        }
+        if self.z_index.is_none() {
+            self.z_index = Some(ZIndex::Auto);
+        }
+        // floatとclearは継承されない
+        if self.float.is_none() {
+            self.float = Some(Float::None);
+        }
+        if self.clear.is_none() {
+            self.clear = Some(Clear::None);
+        }
+        // グリッドのプロパティは継承されない
+        if self.grid_template_columns.is_none() {
+            self.grid_template_columns = Some(Vec::new());
+        }
+        if self.grid_template_rows.is_none() {
+            self.grid_template_rows = Some(Vec::new());
+        }
+        if self.grid_template_areas.is_none() {
+            self.grid_template_areas = Some(Vec::new());
+        }
+        if self.row_gap.is_none() {
+            self.row_gap = Some(0.0);
+        }
+        if self.column_gap.is_none() {
+            self.column_gap = Some(0.0);
+        }
+        if self.grid_row_start.is_none() {
+            self.grid_row_start = Some(GridLine::Auto);
+        }
+        if self.grid_row_end.is_none() {
+            self.grid_row_end = Some(GridLine::Auto);
+        }
+        if self.grid_column_start.is_none() {
+            self.grid_column_start = Some(GridLine::Auto);
+        }
+        if self.grid_column_end.is_none() {
+            self.grid_column_end = Some(GridLine::Auto);
+        }
+        if self.table_layout.is_none() {
+            self.table_layout = Some(TableLayoutType::Auto);
+        }
+        if self.border_collapse.is_none() {
+            self.border_collapse = Some(BorderCollapse::Separate);
+        }
+        if self.border_spacing.is_none() {
+            self.border_spacing = Some(0.0);
+        }
+        if self.vertical_align.is_none() {
+            self.vertical_align = Some(VerticalAlign::Baseline);
         }
     }
 }
@@ -275,6 +604,15 @@ impl FontSize {
 pub enum DisplayType {
     Block,
     Inline,
+    // 子要素をグリッドに並べるブロック要素
+    Grid,
+    // 内部にフロートを含む新しいブロック整形コンテキストを作るブロック要素
+    FlowRoot,
+    // 表と、その行のグループ、行、セル
+    Table,
+    TableRowGroup,
+    TableRow,
+    TableCell,
     // 要素を非表示にする
     DisplayNone,
 }
@@ -283,21 +621,41 @@ impl DisplayType {
     fn default(node: &Rc<RefCell<Node>>) -> Self {
         match &node.borrow().kind() {
             NodeKind::Document => DisplayType::Block,
-            NodeKind::Element(e) => {
-                if e.is_block_element() {
-                    DisplayType::Block
-                } else {
-                    DisplayType::Inline
+            NodeKind::Element(e) => match e.kind() {
+                ElementKind::Table => DisplayType::Table,
+                ElementKind::Thead | ElementKind::Tbody | ElementKind::Tfoot => {
+                    DisplayType::TableRowGroup
                 }
-            }
+                ElementKind::Tr => DisplayType::TableRow,
+                ElementKind::Td | ElementKind::Th => DisplayType::TableCell,
+                // body要素の中のscript要素やstyle要素は表示しない
+                ElementKind::Script | ElementKind::Style => DisplayType::DisplayNone,
+                _ => {
+                    if e.is_block_element() {
+                        DisplayType::Block
+                    } else {
+                        DisplayType::Inline
+                    }
+                }
+            },
             NodeKind::Text(_) => DisplayType::Inline,
         }
     }
+}
+
+impl FromStr for DisplayType {
+    type Err = Error;
 
-    pub fn from_str(s: &str) -> Result<Self, Error> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "block" => Ok(Self::Block),
             "inline" => Ok(Self::Inline),
+            "grid" => Ok(Self::Grid),
+            "flow-root" => Ok(Self::FlowRoot),
+            "table" => Ok(Self::Table),
+            "table-row-group" => Ok(Self::TableRowGroup),
+            "table-row" => Ok(Self::TableRow),
+            "table-cell" => Ok(Self::TableCell),
             "none" => Ok(Self::DisplayNone),
             _ => Err(Error::UnexpectedInput(format!(
                 "display {:?} is not supported yet",
@@ -307,6 +665,226 @@ impl DisplayType {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+// width, topなどに指定される長さを表す
+pub enum Length {
+    Auto,
+    Px(f64),
+    Percentage(f64),
+}
+
+impl Length {
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(s) if s == "auto" => Ok(Self::Auto),
+            ComponentValue::Dimension(v, unit) if unit == "px" => Ok(Self::Px(*v)),
+            ComponentValue::Percentage(v) => Ok(Self::Percentage(*v)),
+            ComponentValue::Number(v) if *v == 0.0 => Ok(Self::Px(0.0)),
+            _ => Err(Error::UnexpectedInput(format!(
+                "length {:?} is not supported yet",
+                value
+            ))),
+        }
+    }
+
+    // 基準となる長さを使ってピクセルに変換する。autoの場合はNone
+    pub fn resolve(&self, base: i64) -> Option<i64> {
+        match self {
+            Length::Auto => None,
+            Length::Px(v) => Some(*v as i64),
+            Length::Percentage(p) => Some((base as f64 * p / 100.0) as i64),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// positionに対応する値を表す
+pub enum Position {
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "static" => Ok(Self::Static),
+            "relative" => Ok(Self::Relative),
+            "absolute" => Ok(Self::Absolute),
+            "fixed" => Ok(Self::Fixed),
+            "sticky" => Ok(Self::Sticky),
+            _ => Err(Error::UnexpectedInput(format!(
+                "position {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// floatに対応する値を表す
+pub enum Float {
+    None,
+    Left,
+    Right,
+}
+
+impl FromStr for Float {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => Err(Error::UnexpectedInput(format!(
+                "float {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// clearに対応する値を表す
+pub enum Clear {
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+impl FromStr for Clear {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "both" => Ok(Self::Both),
+            _ => Err(Error::UnexpectedInput(format!(
+                "clear {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+impl Clear {
+    // 指定されたfloatの要素を回り込まないか
+    pub fn clears(&self, float: Float) -> bool {
+        matches!(
+            (self, float),
+            (Clear::Left, Float::Left) | (Clear::Right, Float::Right) | (Clear::Both, _)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// table-layoutに対応する値を表す
+pub enum TableLayoutType {
+    // セルの内容から列の幅を決める
+    Auto,
+    // 最初の行と表の幅だけから列の幅を決める
+    Fixed,
+}
+
+impl FromStr for TableLayoutType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "fixed" => Ok(Self::Fixed),
+            _ => Err(Error::UnexpectedInput(format!(
+                "table-layout {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// border-collapseに対応する値を表す
+pub enum BorderCollapse {
+    Separate,
+    Collapse,
+}
+
+impl FromStr for BorderCollapse {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "separate" => Ok(Self::Separate),
+            "collapse" => Ok(Self::Collapse),
+            _ => Err(Error::UnexpectedInput(format!(
+                "border-collapse {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// vertical-alignに対応する値を表す
+pub enum VerticalAlign {
+    Baseline,
+    Top,
+    Middle,
+    Bottom,
+}
+
+impl FromStr for VerticalAlign {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "baseline" => Ok(Self::Baseline),
+            "top" => Ok(Self::Top),
+            "middle" => Ok(Self::Middle),
+            "bottom" => Ok(Self::Bottom),
+            _ => Err(Error::UnexpectedInput(format!(
+                "vertical-align {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// z-indexに対応する値を表す
+pub enum ZIndex {
+    Auto,
+    Value(i64),
+}
+
+impl ZIndex {
+    pub fn from_component_value(value: &ComponentValue) -> Result<Self, Error> {
+        match value {
+            ComponentValue::Ident(s) if s == "auto" => Ok(Self::Auto),
+            ComponentValue::Number(v) if *v == (*v as i64) as f64 => Ok(Self::Value(*v as i64)),
+            _ => Err(Error::UnexpectedInput(format!(
+                "z-index {:?} is not supported yet",
+                value
+            ))),
+        }
+    }
+
+    // 重ねる順番を比較するための値。autoは0として扱う
+    pub fn order(&self) -> i64 {
+        match self {
+            ZIndex::Auto => 0,
+            ZIndex::Value(v) => *v,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 // text-decorationに対応する値を表す
 pub enum TextDecoration {
@@ -0,0 +1,192 @@
+use alloc::vec::Vec;
+
+use crate::renderer::layout::{
+    computed_style::{Clear, Float},
+    layout_object::{LayoutPoint, LayoutSize},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+// 配置済みのフロートの領域
+struct FloatBox {
+    float: Float,
+    point: LayoutPoint,
+    size: LayoutSize,
+}
+
+impl FloatBox {
+    fn top(&self) -> i64 {
+        self.point.y()
+    }
+
+    fn bottom(&self) -> i64 {
+        self.point.y() + self.size.height()
+    }
+
+    // y座標がtopからtop+heightの範囲と重なるか
+    fn overlaps(&self, top: i64, height: i64) -> bool {
+        self.top() < top + height.max(1) && top < self.bottom()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+// ブロック整形コンテキストの中に配置されたフロートを管理する
+// 座標はすべてレイアウトツリー全体での絶対座標
+pub struct FloatContext {
+    floats: Vec<FloatBox>,
+}
+
+impl FloatContext {
+    pub fn new() -> Self {
+        Self { floats: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.floats.is_empty()
+    }
+
+    // topからtop+heightの範囲で、フロートを避けて使える左端と右端を返す
+    pub fn available_range(&self, top: i64, height: i64, left: i64, right: i64) -> (i64, i64) {
+        let mut l = left;
+        let mut r = right;
+        for f in self.floats.iter().filter(|f| f.overlaps(top, height)) {
+            match f.float {
+                Float::Left => l = l.max(f.point.x() + f.size.width()),
+                Float::Right => r = r.min(f.point.x()),
+                Float::None => {}
+            }
+        }
+        (l, r.max(l))
+    }
+
+    // topからtop+heightの範囲にフロートが入り込んでいるか
+    pub fn intrudes(&self, top: i64, height: i64, left: i64, right: i64) -> bool {
+        self.available_range(top, height, left, right) != (left, right)
+    }
+
+    // topより下で、topと重なるフロートが最初に終わるy座標
+    pub fn next_bottom(&self, top: i64) -> Option<i64> {
+        self.floats
+            .iter()
+            .filter(|f| f.overlaps(top, 1))
+            .map(|f| f.bottom())
+            .min()
+    }
+
+    // clearを指定された要素が置かれるy座標
+    pub fn clearance(&self, clear: Clear, top: i64) -> i64 {
+        self.floats
+            .iter()
+            .filter(|f| clear.clears(f.float))
+            .map(|f| f.bottom())
+            .fold(top, i64::max)
+    }
+
+    // 全てのフロートの下端
+    pub fn bottom(&self) -> Option<i64> {
+        self.floats.iter().map(|f| f.bottom()).max()
+    }
+
+    // フロートを置く位置を決める
+    // 先に置かれたフロートより上には置かず、幅が収まる位置まで下に移動する
+    pub fn place(
+        &self,
+        float: Float,
+        size: LayoutSize,
+        top: i64,
+        left: i64,
+        right: i64,
+    ) -> LayoutPoint {
+        let mut y = self.floats.iter().map(|f| f.top()).fold(top, i64::max);
+        loop {
+            let (l, r) = self.available_range(y, size.height(), left, right);
+            if r - l >= size.width() || (l, r) == (left, right) {
+                let x = match float {
+                    Float::Right => r - size.width(),
+                    _ => l,
+                };
+                return LayoutPoint::new(x, y);
+            }
+
+            match self
+                .floats
+                .iter()
+                .filter(|f| f.overlaps(y, size.height()))
+                .map(|f| f.bottom())
+                .min()
+            {
+                Some(bottom) => y = bottom,
+                None => return LayoutPoint::new(l, y),
+            }
+        }
+    }
+
+    pub fn add(&mut self, float: Float, point: LayoutPoint, size: LayoutSize) {
+        self.floats.push(FloatBox { float, point, size });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_left_and_right() {
+        let mut floats = FloatContext::new();
+        let point = floats.place(Float::Left, LayoutSize::new(100, 50), 0, 0, 500);
+        assert_eq!(LayoutPoint::new(0, 0), point);
+        floats.add(Float::Left, point, LayoutSize::new(100, 50));
+
+        let point = floats.place(Float::Left, LayoutSize::new(100, 30), 0, 0, 500);
+        assert_eq!(LayoutPoint::new(100, 0), point);
+        floats.add(Float::Left, point, LayoutSize::new(100, 30));
+
+        let point = floats.place(Float::Right, LayoutSize::new(100, 30), 0, 0, 500);
+        assert_eq!(LayoutPoint::new(400, 0), point);
+        floats.add(Float::Right, point, LayoutSize::new(100, 30));
+
+        assert_eq!((200, 400), floats.available_range(10, 10, 0, 500));
+        assert_eq!((100, 500), floats.available_range(40, 10, 0, 500));
+        assert_eq!((0, 500), floats.available_range(50, 10, 0, 500));
+        assert_eq!(Some(50), floats.bottom());
+    }
+
+    #[test]
+    fn test_place_below_when_no_room() {
+        let mut floats = FloatContext::new();
+        floats.add(
+            Float::Left,
+            LayoutPoint::new(0, 0),
+            LayoutSize::new(300, 50),
+        );
+        floats.add(
+            Float::Right,
+            LayoutPoint::new(400, 0),
+            LayoutSize::new(100, 20),
+        );
+
+        // 幅が足りない場合は右のフロートが終わる位置まで下がる
+        let point = floats.place(Float::Left, LayoutSize::new(150, 10), 0, 0, 500);
+        assert_eq!(LayoutPoint::new(300, 20), point);
+    }
+
+    #[test]
+    fn test_clearance() {
+        let mut floats = FloatContext::new();
+        floats.add(
+            Float::Left,
+            LayoutPoint::new(0, 0),
+            LayoutSize::new(100, 50),
+        );
+        floats.add(
+            Float::Right,
+            LayoutPoint::new(400, 0),
+            LayoutSize::new(100, 80),
+        );
+
+        assert_eq!(50, floats.clearance(Clear::Left, 10));
+        assert_eq!(80, floats.clearance(Clear::Right, 10));
+        assert_eq!(80, floats.clearance(Clear::Both, 10));
+        assert_eq!(100, floats.clearance(Clear::Both, 100));
+        assert_eq!(10, floats.clearance(Clear::None, 10));
+    }
+}
@@ -0,0 +1,921 @@
+use core::cmp::Ordering;
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    error::Error,
+    renderer::{
+        css::cssom::ComponentValue,
+        layout::{
+            computed_style::ComputedStyle,
+            layout_object::{LayoutPoint, LayoutSize},
+        },
+    },
+};
+
+// repeat()の回数、トラックの数、線の番号、spanの上限。表のcolspanと同じく1から1000の範囲に丸める
+pub const MAX_GRID_TRACKS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+// grid-template-columns/rowsの1つのトラックの大きさを表す
+pub enum GridTrackSize {
+    Px(f64),
+    Percentage(f64),
+    Fr(f64),
+    Auto,
+    // minmax(min, max)
+    MinMax(Box<GridTrackSize>, Box<GridTrackSize>),
+}
+
+impl GridTrackSize {
+    // トラックの大きさの下限
+    fn base_size(&self, available: Option<i64>, content: i64) -> i64 {
+        match self {
+            GridTrackSize::Px(v) => *v as i64,
+            GridTrackSize::Percentage(p) => match available {
+                Some(a) => (a as f64 * p / 100.0) as i64,
+                None => content,
+            },
+            GridTrackSize::Auto => content,
+            // 1frはminmax(0, 1fr)として扱う
+            GridTrackSize::Fr(_) => 0,
+            GridTrackSize::MinMax(min, _) => match **min {
+                GridTrackSize::Fr(_) => 0,
+                ref m => m.base_size(available, content),
+            },
+        }
+    }
+
+    // トラックが伸びることができる上限。frの場合はNone
+    fn growth_limit(&self, available: Option<i64>, content: i64) -> Option<i64> {
+        match self {
+            GridTrackSize::Fr(_) => None,
+            GridTrackSize::MinMax(min, max) => match **max {
+                GridTrackSize::Fr(_) => None,
+                ref m => Some(
+                    m.growth_limit(available, content)
+                        .unwrap_or(content)
+                        .max(min.base_size(available, content)),
+                ),
+            },
+            _ => Some(self.base_size(available, content)),
+        }
+    }
+
+    fn flex_factor(&self) -> Option<f64> {
+        match self {
+            GridTrackSize::Fr(f) => Some(*f),
+            GridTrackSize::MinMax(_, max) => max.flex_factor(),
+            _ => None,
+        }
+    }
+
+    // 余ったスペースで引き伸ばされるトラックか
+    fn is_auto(&self) -> bool {
+        match self {
+            GridTrackSize::Auto => true,
+            GridTrackSize::MinMax(_, max) => **max == GridTrackSize::Auto,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// grid-row-startなどで指定されるグリッド線を表す
+pub enum GridLine {
+    Auto,
+    // 1から始まる線の番号。負の数は末尾から数える
+    Line(i64),
+    Span(usize),
+    // grid-template-areasで名前をつけた領域
+    Area(String),
+}
+
+// 100px 1fr repeat(2, minmax(50px, 1fr))のような値をトラックのリストに変換する
+pub fn parse_track_list(values: &[ComponentValue]) -> Result<Vec<GridTrackSize>, Error> {
+    let mut pos = 0;
+    let tracks = parse_tracks_until(values, &mut pos, None)?;
+    if tracks.is_empty() {
+        return Err(Error::UnexpectedInput("empty grid track list".to_string()));
+    }
+    Ok(tracks)
+}
+
+fn parse_tracks_until(
+    values: &[ComponentValue],
+    pos: &mut usize,
+    terminator: Option<ComponentValue>,
+) -> Result<Vec<GridTrackSize>, Error> {
+    let mut tracks = Vec::new();
+
+    while *pos < values.len() {
+        if Some(values[*pos].clone()) == terminator {
+            return Ok(tracks);
+        }
+
+        match &values[*pos] {
+            ComponentValue::Ident(name) if name == "repeat" => {
+                *pos += 1;
+                expect(values, pos, ComponentValue::OpenParenthesis)?;
+                let count = match values.get(*pos) {
+                    Some(ComponentValue::Number(n)) if *n >= 1.0 => {
+                        (*n as usize).min(MAX_GRID_TRACKS)
+                    }
+                    v => {
+                        return Err(Error::UnexpectedInput(format!(
+                            "invalid repeat count {:?}",
+                            v
+                        )))
+                    }
+                };
+                *pos += 1;
+                expect(values, pos, ComponentValue::Delim(','))?;
+                let repeated =
+                    parse_tracks_until(values, pos, Some(ComponentValue::CloseParenthesis))?;
+                expect(values, pos, ComponentValue::CloseParenthesis)?;
+                for _ in 0..count {
+                    if tracks.len() >= MAX_GRID_TRACKS {
+                        break;
+                    }
+                    tracks.extend(repeated.iter().cloned());
+                }
+            }
+            _ => tracks.push(parse_track_size(values, pos)?),
+        }
+        tracks.truncate(MAX_GRID_TRACKS);
+    }
+
+    match terminator {
+        Some(t) => Err(Error::UnexpectedInput(format!("missing {:?}", t))),
+        None => Ok(tracks),
+    }
+}
+
+fn parse_track_size(values: &[ComponentValue], pos: &mut usize) -> Result<GridTrackSize, Error> {
+    let value = values[*pos].clone();
+    *pos += 1;
+
+    match value {
+        ComponentValue::Dimension(v, unit) if unit == "px" => Ok(GridTrackSize::Px(v)),
+        ComponentValue::Dimension(v, unit) if unit == "fr" => Ok(GridTrackSize::Fr(v)),
+        ComponentValue::Percentage(v) => Ok(GridTrackSize::Percentage(v)),
+        ComponentValue::Number(v) if v == 0.0 => Ok(GridTrackSize::Px(0.0)),
+        ComponentValue::Ident(name) if name == "auto" => Ok(GridTrackSize::Auto),
+        ComponentValue::Ident(name) if name == "minmax" => {
+            expect(values, pos, ComponentValue::OpenParenthesis)?;
+            let min = parse_track_size(values, pos)?;
+            expect(values, pos, ComponentValue::Delim(','))?;
+            let max = parse_track_size(values, pos)?;
+            expect(values, pos, ComponentValue::CloseParenthesis)?;
+            Ok(GridTrackSize::MinMax(Box::new(min), Box::new(max)))
+        }
+        _ => Err(Error::UnexpectedInput(format!(
+            "grid track size {:?} is not supported yet",
+            value
+        ))),
+    }
+}
+
+fn expect(
+    values: &[ComponentValue],
+    pos: &mut usize,
+    expected: ComponentValue,
+) -> Result<(), Error> {
+    if values.get(*pos) == Some(&expected) {
+        *pos += 1;
+        return Ok(());
+    }
+    Err(Error::UnexpectedInput(format!(
+        "expected {:?} but got {:?}",
+        expected,
+        values.get(*pos)
+    )))
+}
+
+// auto、3、-1、span 2、areaのような1つのグリッド線を解釈する
+pub fn parse_grid_line(values: &[ComponentValue]) -> Result<GridLine, Error> {
+    match values {
+        [ComponentValue::Ident(name)] if name == "auto" => Ok(GridLine::Auto),
+        [ComponentValue::Number(n)] if *n != 0.0 => {
+            let max = MAX_GRID_TRACKS as i64;
+            Ok(GridLine::Line((*n as i64).clamp(-max, max)))
+        }
+        [ComponentValue::Ident(name), ComponentValue::Number(n)] if name == "span" && *n >= 1.0 => {
+            Ok(GridLine::Span((*n as usize).min(MAX_GRID_TRACKS)))
+        }
+        [ComponentValue::Ident(name)] if name != "span" => Ok(GridLine::Area(name.to_string())),
+        _ => Err(Error::UnexpectedInput(format!(
+            "grid line {:?} is not supported yet",
+            values
+        ))),
+    }
+}
+
+// "1 / 3"のように'/'で区切られた値をそれぞれのグリッド線に変換する
+pub fn parse_grid_lines(values: &[ComponentValue]) -> Result<Vec<GridLine>, Error> {
+    values
+        .split(|v| *v == ComponentValue::Delim('/'))
+        .map(parse_grid_line)
+        .collect()
+}
+
+// grid-row, grid-columnの値を開始線と終了線に変換する
+pub fn parse_grid_line_pair(values: &[ComponentValue]) -> Result<(GridLine, GridLine), Error> {
+    let lines = parse_grid_lines(values)?;
+    match lines.len() {
+        1 => {
+            // 名前だけが指定された場合は終了線も同じ名前になる
+            let end = match &lines[0] {
+                GridLine::Area(name) => GridLine::Area(name.clone()),
+                _ => GridLine::Auto,
+            };
+            Ok((lines[0].clone(), end))
+        }
+        2 => Ok((lines[0].clone(), lines[1].clone())),
+        _ => Err(Error::UnexpectedInput(format!(
+            "invalid grid line pair {:?}",
+            values
+        ))),
+    }
+}
+
+// grid-areaの値をrow-start / column-start / row-end / column-endの順に変換する
+pub fn parse_grid_area(values: &[ComponentValue]) -> Result<[GridLine; 4], Error> {
+    let lines = parse_grid_lines(values)?;
+    if lines.is_empty() || lines.len() > 4 {
+        return Err(Error::UnexpectedInput(format!(
+            "invalid grid area {:?}",
+            values
+        )));
+    }
+
+    // 省略された線は、対応する線が名前ならその名前、そうでなければautoになる
+    let fallback = |line: &GridLine| match line {
+        GridLine::Area(name) => GridLine::Area(name.clone()),
+        _ => GridLine::Auto,
+    };
+    let row_start = lines[0].clone();
+    let column_start = lines.get(1).cloned().unwrap_or(fallback(&row_start));
+    let row_end = lines.get(2).cloned().unwrap_or(fallback(&row_start));
+    let column_end = lines.get(3).cloned().unwrap_or(fallback(&column_start));
+
+    Ok([row_start, column_start, row_end, column_end])
+}
+
+// grid-template-areasの文字列を行ごとのセル名のリストに変換する
+pub fn parse_template_areas(values: &[ComponentValue]) -> Result<Vec<Vec<String>>, Error> {
+    let mut rows = Vec::new();
+    for value in values {
+        match value {
+            ComponentValue::StringToken(s) => rows.push(
+                s.split_whitespace()
+                    .map(|cell| cell.to_string())
+                    .collect::<Vec<String>>(),
+            ),
+            _ => {
+                return Err(Error::UnexpectedInput(format!(
+                    "grid-template-areas should be strings but got {:?}",
+                    value
+                )))
+            }
+        }
+    }
+
+    // すべての行は同じ数のセルを持たなければならない
+    let columns = rows.first().map(|r| r.len()).unwrap_or(0);
+    if columns == 0 || rows.iter().any(|r| r.len() != columns) {
+        return Err(Error::UnexpectedInput(format!(
+            "invalid grid-template-areas {:?}",
+            values
+        )));
+    }
+
+    Ok(rows)
+}
+
+// row-gap, column-gapに指定される長さ
+pub fn parse_gap(value: &ComponentValue) -> Result<f64, Error> {
+    match value {
+        ComponentValue::Dimension(v, unit) if unit == "px" && *v >= 0.0 => Ok(*v),
+        ComponentValue::Number(v) if *v == 0.0 => Ok(0.0),
+        _ => Err(Error::UnexpectedInput(format!(
+            "gap {:?} is not supported yet",
+            value
+        ))),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+// グリッドアイテムが配置される領域。0から始まるトラックの番号で、endは含まない
+pub struct GridArea {
+    row_start: usize,
+    row_end: usize,
+    column_start: usize,
+    column_end: usize,
+}
+
+impl GridArea {
+    pub fn new(row_start: usize, row_end: usize, column_start: usize, column_end: usize) -> Self {
+        Self {
+            row_start,
+            row_end,
+            column_start,
+            column_end,
+        }
+    }
+
+    pub fn row_start(&self) -> usize {
+        self.row_start
+    }
+
+    pub fn row_end(&self) -> usize {
+        self.row_end
+    }
+
+    pub fn column_start(&self) -> usize {
+        self.column_start
+    }
+
+    pub fn column_end(&self) -> usize {
+        self.column_end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// 1つの軸について解決されたアイテムの位置
+enum AxisPlacement {
+    Definite(usize, usize),
+    Auto(usize),
+}
+
+impl AxisPlacement {
+    fn span(&self) -> usize {
+        match self {
+            AxisPlacement::Definite(start, end) => end - start,
+            AxisPlacement::Auto(span) => *span,
+        }
+    }
+}
+
+// 名前付き領域のある軸での開始、終了位置を探す
+fn find_named_area(areas: &[Vec<String>], name: &str, is_row: bool) -> Option<(usize, usize)> {
+    let mut range: Option<(usize, usize)> = None;
+    for (r, row) in areas.iter().enumerate() {
+        for (c, cell) in row.iter().enumerate() {
+            if cell != name {
+                continue;
+            }
+            let i = if is_row { r } else { c };
+            range = match range {
+                Some((start, end)) => Some((start.min(i), end.max(i + 1))),
+                None => Some((i, i + 1)),
+            };
+        }
+    }
+    range
+}
+
+fn resolve_axis(
+    start: &GridLine,
+    end: &GridLine,
+    explicit_tracks: usize,
+    areas: &[Vec<String>],
+    is_row: bool,
+) -> AxisPlacement {
+    // グリッド線をトラックの番号に変換する
+    let resolve_line = |line: &GridLine, is_start: bool| -> Option<usize> {
+        match line {
+            GridLine::Line(n) if *n > 0 => Some((*n as usize).min(MAX_GRID_TRACKS) - 1),
+            GridLine::Line(n) => Some((explicit_tracks as i64 + 1 + n).max(0) as usize),
+            GridLine::Area(name) => {
+                find_named_area(areas, name, is_row).map(|(s, e)| if is_start { s } else { e })
+            }
+            _ => None,
+        }
+    };
+    let span_of = |line: &GridLine| match line {
+        GridLine::Span(s) => Some((*s).clamp(1, MAX_GRID_TRACKS)),
+        _ => None,
+    };
+
+    match (resolve_line(start, true), resolve_line(end, false)) {
+        (Some(s), Some(e)) => match e.cmp(&s) {
+            Ordering::Greater => AxisPlacement::Definite(s, e),
+            Ordering::Less => AxisPlacement::Definite(e, s),
+            Ordering::Equal => AxisPlacement::Definite(s, s + 1),
+        },
+        (Some(s), None) => AxisPlacement::Definite(s, s + span_of(end).unwrap_or(1)),
+        (None, Some(e)) => {
+            let span = span_of(start).unwrap_or(1);
+            let s = e.saturating_sub(span);
+            AxisPlacement::Definite(s, s.max(e).max(s + 1))
+        }
+        (None, None) => AxisPlacement::Auto(span_of(start).or(span_of(end)).unwrap_or(1)),
+    }
+}
+
+// どのセルがアイテムで埋まっているかを管理する
+struct OccupancyGrid {
+    columns: usize,
+    cells: Vec<Vec<bool>>,
+}
+
+impl OccupancyGrid {
+    fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            cells: Vec::new(),
+        }
+    }
+
+    fn fits(&self, row: usize, column: usize, row_span: usize, column_span: usize) -> bool {
+        if column + column_span > self.columns {
+            return false;
+        }
+        for r in row..row + row_span {
+            if let Some(cells) = self.cells.get(r) {
+                if cells[column..column + column_span].iter().any(|c| *c) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn occupy(&mut self, area: &GridArea) {
+        while self.cells.len() < area.row_end {
+            self.cells.push(vec![false; self.columns]);
+        }
+        for r in area.row_start..area.row_end {
+            for c in area.column_start..area.column_end {
+                self.cells[r][c] = true;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// グリッドコンテナのレイアウト結果
+pub struct GridLayout {
+    columns: Vec<i64>,
+    rows: Vec<i64>,
+    column_gap: i64,
+    row_gap: i64,
+    // 子ノードの順に並んだ各アイテムの領域
+    areas: Vec<GridArea>,
+}
+
+impl GridLayout {
+    // グリッドアイテムの配置を決める（自動配置アルゴリズム）
+    pub fn place_items(container: &ComputedStyle, items: &[ComputedStyle]) -> Self {
+        let template_areas = container.grid_template_areas();
+        let explicit_columns = container
+            .grid_template_columns()
+            .len()
+            .max(template_areas.first().map(|r| r.len()).unwrap_or(0));
+        let explicit_rows = container
+            .grid_template_rows()
+            .len()
+            .max(template_areas.len());
+
+        let placements: Vec<(AxisPlacement, AxisPlacement)> = items
+            .iter()
+            .map(|item| {
+                (
+                    resolve_axis(
+                        &item.grid_row_start(),
+                        &item.grid_row_end(),
+                        explicit_rows,
+                        &template_areas,
+                        true,
+                    ),
+                    resolve_axis(
+                        &item.grid_column_start(),
+                        &item.grid_column_end(),
+                        explicit_columns,
+                        &template_areas,
+                        false,
+                    ),
+                )
+            })
+            .collect();
+
+        // 列の数は明示的な列とアイテムが要求する列のうち大きい方
+        let mut column_count = explicit_columns.max(1);
+        for (_, column) in &placements {
+            column_count = column_count.max(match column {
+                AxisPlacement::Definite(_, end) => *end,
+                AxisPlacement::Auto(span) => *span,
+            });
+        }
+
+        let mut occupancy = OccupancyGrid::new(column_count);
+        let mut areas: Vec<Option<GridArea>> = vec![None; items.len()];
+
+        // 1. 行と列の両方が決まっているアイテムを配置する
+        for (i, placement) in placements.iter().enumerate() {
+            if let (AxisPlacement::Definite(rs, re), AxisPlacement::Definite(cs, ce)) = placement {
+                let area = GridArea::new(*rs, *re, *cs, *ce);
+                occupancy.occupy(&area);
+                areas[i] = Some(area);
+            }
+        }
+
+        // 2. 行だけが決まっているアイテムを、その行で空いている最初の列に配置する
+        for (i, placement) in placements.iter().enumerate() {
+            if let (AxisPlacement::Definite(rs, re), AxisPlacement::Auto(span)) = placement {
+                let column = (0..=column_count - span)
+                    .find(|c| occupancy.fits(*rs, *c, re - rs, *span))
+                    .unwrap_or(0);
+                let area = GridArea::new(*rs, *re, column, column + span);
+                occupancy.occupy(&area);
+                areas[i] = Some(area);
+            }
+        }
+
+        // 3. 残りのアイテムをカーソルを進めながら配置する
+        let mut cursor = (0, 0);
+        for (i, (row, column)) in placements.iter().enumerate() {
+            if areas[i].is_some() {
+                continue;
+            }
+            let row_span = row.span();
+
+            let area = match column {
+                AxisPlacement::Definite(cs, ce) => {
+                    if *cs < cursor.1 {
+                        cursor.0 += 1;
+                    }
+                    cursor.1 = *cs;
+                    while !occupancy.fits(cursor.0, *cs, row_span, ce - cs) {
+                        cursor.0 += 1;
+                    }
+                    GridArea::new(cursor.0, cursor.0 + row_span, *cs, *ce)
+                }
+                AxisPlacement::Auto(column_span) => {
+                    loop {
+                        if occupancy.fits(cursor.0, cursor.1, row_span, *column_span) {
+                            break;
+                        }
+                        cursor.1 += 1;
+                        if cursor.1 + column_span > column_count {
+                            cursor.0 += 1;
+                            cursor.1 = 0;
+                        }
+                    }
+                    let area = GridArea::new(
+                        cursor.0,
+                        cursor.0 + row_span,
+                        cursor.1,
+                        cursor.1 + column_span,
+                    );
+                    cursor.1 += column_span;
+                    area
+                }
+            };
+            occupancy.occupy(&area);
+            areas[i] = Some(area);
+        }
+
+        let areas: Vec<GridArea> = areas.into_iter().flatten().collect();
+        let row_count = areas
+            .iter()
+            .map(|a| a.row_end)
+            .max()
+            .unwrap_or(0)
+            .max(explicit_rows);
+
+        Self {
+            columns: vec![0; column_count],
+            rows: vec![0; row_count],
+            column_gap: container.column_gap() as i64,
+            row_gap: container.row_gap() as i64,
+            areas,
+        }
+    }
+
+    // 列の幅を決める。contentsは各アイテムの内容の幅
+    pub fn resolve_columns(
+        &mut self,
+        definitions: &[GridTrackSize],
+        available_width: i64,
+        contents: &[i64],
+    ) {
+        let content = self.track_contents(contents, false);
+        let available = available_width - self.column_gap * (self.columns.len() as i64 - 1);
+        self.columns = resolve_tracks(definitions, Some(available), &content);
+    }
+
+    // 行の高さを決める。contentsは各アイテムの高さ
+    pub fn resolve_rows(&mut self, definitions: &[GridTrackSize], contents: &[i64]) {
+        let content = self.track_contents(contents, true);
+        self.rows = resolve_tracks(definitions, None, &content);
+
+        // 複数の行にまたがるアイテムが収まらない場合、最後の行を広げる
+        for (area, height) in self.areas.iter().zip(contents) {
+            if area.row_end - area.row_start <= 1 {
+                continue;
+            }
+            let current = self.span_size(&self.rows, self.row_gap, area.row_start, area.row_end);
+            if *height > current {
+                self.rows[area.row_end - 1] += height - current;
+            }
+        }
+    }
+
+    // 1つのトラックだけを占めるアイテムの中で最大の内容の大きさをトラックごとに求める
+    fn track_contents(&self, contents: &[i64], is_row: bool) -> Vec<i64> {
+        let count = if is_row {
+            self.rows.len()
+        } else {
+            self.columns.len()
+        };
+        let mut result = vec![0; count];
+        for (area, size) in self.areas.iter().zip(contents) {
+            let (start, end) = if is_row {
+                (area.row_start, area.row_end)
+            } else {
+                (area.column_start, area.column_end)
+            };
+            if end - start == 1 {
+                result[start] = result[start].max(*size);
+            }
+        }
+        result
+    }
+
+    fn span_size(&self, tracks: &[i64], gap: i64, start: usize, end: usize) -> i64 {
+        tracks[start..end].iter().sum::<i64>() + gap * (end - start - 1) as i64
+    }
+
+    fn offset(&self, tracks: &[i64], gap: i64, index: usize) -> i64 {
+        tracks[..index].iter().sum::<i64>() + gap * index as i64
+    }
+
+    pub fn areas(&self) -> &Vec<GridArea> {
+        &self.areas
+    }
+
+    // i番目のアイテムの幅
+    pub fn area_width(&self, i: usize) -> i64 {
+        let area = &self.areas[i];
+        self.span_size(
+            &self.columns,
+            self.column_gap,
+            area.column_start,
+            area.column_end,
+        )
+    }
+
+    // i番目のアイテムのコンテナ左上からの位置
+    pub fn area_offset(&self, i: usize) -> LayoutPoint {
+        let area = &self.areas[i];
+        LayoutPoint::new(
+            self.offset(&self.columns, self.column_gap, area.column_start),
+            self.offset(&self.rows, self.row_gap, area.row_start),
+        )
+    }
+
+    // i番目のアイテムの領域の大きさ
+    pub fn area_size(&self, i: usize) -> LayoutSize {
+        let area = &self.areas[i];
+        LayoutSize::new(
+            self.area_width(i),
+            self.span_size(&self.rows, self.row_gap, area.row_start, area.row_end),
+        )
+    }
+
+    pub fn columns(&self) -> &Vec<i64> {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &Vec<i64> {
+        &self.rows
+    }
+
+    // グリッド全体の高さ
+    pub fn height(&self) -> i64 {
+        if self.rows.is_empty() {
+            return 0;
+        }
+        self.span_size(&self.rows, self.row_gap, 0, self.rows.len())
+    }
+}
+
+// トラックの定義と内容の大きさからそれぞれのトラックの大きさを決める
+// availableがNoneの場合（行方向）はfrやパーセントを内容の大きさとして扱う
+fn resolve_tracks(
+    definitions: &[GridTrackSize],
+    available: Option<i64>,
+    contents: &[i64],
+) -> Vec<i64> {
+    // 明示的に定義されていないトラックはautoになる
+    let defs: Vec<GridTrackSize> = (0..contents.len())
+        .map(|i| definitions.get(i).cloned().unwrap_or(GridTrackSize::Auto))
+        .collect();
+
+    let available = match available {
+        Some(a) => a,
+        None => {
+            return defs
+                .iter()
+                .zip(contents)
+                .map(|(def, content)| {
+                    let base = def.base_size(None, *content).max(*content);
+                    match def {
+                        GridTrackSize::Px(v) => *v as i64,
+                        GridTrackSize::MinMax(_, _) => match def.growth_limit(None, *content) {
+                            Some(limit) => base.min(limit).max(def.base_size(None, *content)),
+                            None => base,
+                        },
+                        _ => base,
+                    }
+                })
+                .collect();
+        }
+    };
+
+    let mut sizes: Vec<i64> = defs
+        .iter()
+        .zip(contents)
+        .map(|(def, content)| def.base_size(Some(available), *content))
+        .collect();
+    let limits: Vec<Option<i64>> = defs
+        .iter()
+        .zip(contents)
+        .map(|(def, content)| def.growth_limit(Some(available), *content))
+        .collect();
+
+    // 上限まで伸ばせるトラックに余りのスペースを分配する
+    let mut free = available - sizes.iter().sum::<i64>();
+    for _ in 0..sizes.len() {
+        let growable: Vec<usize> = (0..sizes.len())
+            .filter(|i| matches!(limits[*i], Some(limit) if limit > sizes[*i]))
+            .collect();
+        if free <= 0 || growable.is_empty() {
+            break;
+        }
+        let share = (free / growable.len() as i64).max(1);
+        for i in growable {
+            let delta = share.min(limits[i].unwrap_or(0) - sizes[i]).min(free);
+            sizes[i] += delta;
+            free -= delta;
+        }
+    }
+
+    // 残りのスペースをfrの比率で分配する
+    let flex_total: f64 = defs.iter().filter_map(|d| d.flex_factor()).sum();
+    if flex_total > 0.0 {
+        let flex_base: i64 = defs
+            .iter()
+            .zip(&sizes)
+            .filter(|(d, _)| d.flex_factor().is_some())
+            .map(|(_, s)| *s)
+            .sum();
+        let leftover = (free + flex_base).max(0) as f64;
+        // frの合計が1未満の場合はスペースの一部だけを使う
+        let fr_size = leftover / flex_total.max(1.0);
+        for (i, def) in defs.iter().enumerate() {
+            if let Some(factor) = def.flex_factor() {
+                sizes[i] = sizes[i].max((factor * fr_size) as i64);
+            }
+        }
+    } else if free > 0 {
+        // frのトラックがない場合、autoのトラックを均等に引き伸ばす
+        let autos: Vec<usize> = (0..defs.len()).filter(|i| defs[*i].is_auto()).collect();
+        if !autos.is_empty() {
+            let share = free / autos.len() as i64;
+            for i in autos {
+                sizes[i] += share;
+            }
+        }
+    }
+
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::token::CssTokenizer;
+
+    fn values(s: &str) -> Vec<ComponentValue> {
+        CssTokenizer::new(s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_track_list() {
+        assert_eq!(
+            Ok(vec![
+                GridTrackSize::Px(100.0),
+                GridTrackSize::Fr(1.0),
+                GridTrackSize::Percentage(25.0),
+                GridTrackSize::Auto,
+            ]),
+            parse_track_list(&values("100px 1fr 25% auto"))
+        );
+    }
+
+    #[test]
+    fn test_parse_repeat_and_minmax() {
+        let minmax = GridTrackSize::MinMax(
+            Box::new(GridTrackSize::Px(50.0)),
+            Box::new(GridTrackSize::Fr(1.0)),
+        );
+        assert_eq!(
+            Ok(vec![
+                GridTrackSize::Px(10.0),
+                minmax.clone(),
+                GridTrackSize::Auto,
+                minmax,
+                GridTrackSize::Auto,
+            ]),
+            parse_track_list(&values("10px repeat(2, minmax(50px, 1fr) auto)"))
+        );
+        assert!(parse_track_list(&values("repeat(2 1fr)")).is_err());
+    }
+
+    #[test]
+    fn test_large_repeat_is_clamped() {
+        let tracks = parse_track_list(&values("repeat(100000000, 1px)")).unwrap();
+        assert_eq!(MAX_GRID_TRACKS, tracks.len());
+        let tracks = parse_track_list(&values("repeat(1000, repeat(1000, 1px) 2px)")).unwrap();
+        assert_eq!(MAX_GRID_TRACKS, tracks.len());
+    }
+
+    #[test]
+    fn test_large_lines_and_spans_are_clamped() {
+        assert_eq!(
+            Ok((GridLine::Line(1000), GridLine::Auto)),
+            parse_grid_line_pair(&values("500000000"))
+        );
+        assert_eq!(
+            Ok((GridLine::Line(-1000), GridLine::Span(1000))),
+            parse_grid_line_pair(&values("-500000000 / span 500000000"))
+        );
+
+        let placement = resolve_axis(&GridLine::Line(500_000_000), &GridLine::Auto, 0, &[], false);
+        assert_eq!(1, placement.span());
+        let placement = resolve_axis(
+            &GridLine::Line(1),
+            &GridLine::Span(500_000_000),
+            0,
+            &[],
+            false,
+        );
+        assert_eq!(MAX_GRID_TRACKS, placement.span());
+        let placement = resolve_axis(&GridLine::Span(500_000_000), &GridLine::Auto, 0, &[], true);
+        assert_eq!(MAX_GRID_TRACKS, placement.span());
+    }
+
+    #[test]
+    fn test_parse_grid_lines() {
+        assert_eq!(
+            Ok((GridLine::Line(1), GridLine::Span(2))),
+            parse_grid_line_pair(&values("1 / span 2"))
+        );
+        assert_eq!(
+            Ok((GridLine::Line(-1), GridLine::Auto)),
+            parse_grid_line_pair(&values("-1"))
+        );
+        assert_eq!(
+            Ok([
+                GridLine::Area("main".to_string()),
+                GridLine::Area("main".to_string()),
+                GridLine::Area("main".to_string()),
+                GridLine::Area("main".to_string()),
+            ]),
+            parse_grid_area(&values("main"))
+        );
+    }
+
+    #[test]
+    fn test_parse_template_areas() {
+        assert_eq!(
+            Ok(vec![
+                vec!["a".to_string(), "a".to_string()],
+                vec!["b".to_string(), "c".to_string()],
+            ]),
+            parse_template_areas(&values("\"a a\" \"b c\""))
+        );
+        assert!(parse_template_areas(&values("\"a a\" \"b\"")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_columns() {
+        let tracks = parse_track_list(&values("100px 1fr 2fr")).unwrap();
+        assert_eq!(
+            vec![100, 100, 200],
+            resolve_tracks(&tracks, Some(400), &[0, 0, 0])
+        );
+
+        let tracks = parse_track_list(&values("minmax(50px, 80px) auto")).unwrap();
+        assert_eq!(vec![80, 220], resolve_tracks(&tracks, Some(300), &[0, 40]));
+    }
+}
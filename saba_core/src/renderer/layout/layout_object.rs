@@ -1,4 +1,4 @@
-use core::cell::RefCell;
+use core::{cell::RefCell, str::FromStr};
 
 use alloc::{
     rc::{Rc, Weak},
@@ -14,9 +14,23 @@ use crate::{
     },
     display_item::DisplayItem,
     renderer::{
-        css::cssom::{ComponentValue, Declaration, Selector, StyleSheet},
+        css::{
+            cssom::{ComponentValue, CssParser, Declaration, Selector, StyleSheet},
+            token::CssTokenizer,
+        },
         dom::node::{Node, NodeKind},
-        layout::computed_style::{Color, ComputedStyle, DisplayType, FontSize},
+        layout::{
+            computed_style::{
+                BorderCollapse, Clear, Color, ComputedStyle, DisplayType, Float, FontSize, Length,
+                Position, TableLayoutType, VerticalAlign, ZIndex,
+            },
+            float::FloatContext,
+            grid::{
+                parse_gap, parse_grid_area, parse_grid_line, parse_grid_line_pair,
+                parse_template_areas, parse_track_list, GridLayout,
+            },
+            table::TableLayout,
+        },
     },
 };
 
@@ -62,6 +76,16 @@ pub fn create_layout_object(
             }
         }
 
+        // style属性の宣言は、スタイルシートのルールより優先する
+        if let Some(style) = n
+            .borrow()
+            .get_element()
+            .and_then(|e| e.get_attribute("style"))
+        {
+            let declarations = CssParser::new(CssTokenizer::new(style)).parse_declaration_list();
+            layout_object.borrow_mut().cascading_style(declarations);
+        }
+
         // CSSでスタイルが指定されていない場合、デフォルトの値または親のノードから継承した値を使用する
         let parent_style = if let Some(parent) = parent_obj {
             Some(parent.borrow().style())
@@ -76,6 +100,28 @@ pub fn create_layout_object(
             return None;
         }
 
+        // グリッドアイテムや絶対位置指定された要素にはfloatは効かない
+        let is_grid_item = match parent_obj {
+            Some(parent) => parent.borrow().style().display() == DisplayType::Grid,
+            None => false,
+        };
+        if is_grid_item || layout_object.borrow().style().is_out_of_flow() {
+            layout_object.borrow_mut().style.set_float(Float::None);
+        }
+
+        // グリッドアイテムとなるインライン要素や、絶対位置指定またはフロートのインライン要素はブロック要素として扱う
+        if (is_grid_item
+            || layout_object.borrow().style().is_out_of_flow()
+            || layout_object.borrow().style().is_floating())
+            && layout_object.borrow().style().display() == DisplayType::Inline
+            && matches!(n.borrow().kind(), NodeKind::Element(_))
+        {
+            layout_object
+                .borrow_mut()
+                .style
+                .set_display(DisplayType::Block);
+        }
+
         // displayプロパティの最終的な値を使用してノードの種類を決定する
         layout_object.borrow_mut().update_kind();
         return Some(layout_object);
@@ -83,6 +129,82 @@ pub fn create_layout_object(
     None
 }
 
+// 可能な限り改行した場合の内容の幅を求める
+pub fn min_content_width(node: &Rc<RefCell<LayoutObject>>) -> i64 {
+    let kind = node.borrow().kind();
+    match kind {
+        LayoutObjectKind::Text => {
+            if let NodeKind::Text(t) = node.borrow().node_kind() {
+                let ratio = match node.borrow().style().font_size() {
+                    FontSize::Medium => 1,
+                    FontSize::XLarge => 2,
+                    FontSize::XXLarge => 3,
+                };
+                // 最も長い単語の幅
+                let len = t.split_whitespace().map(|w| w.len()).max().unwrap_or(0) as i64;
+                return (CHAR_WIDTH * ratio * len).min(CONTENT_AREA_WIDTH);
+            }
+            0
+        }
+        LayoutObjectKind::Inline | LayoutObjectKind::Block => {
+            let mut min_width = 0;
+            let mut child = node.borrow().first_child();
+            while let Some(c) = child {
+                child = c.borrow().next_sibling();
+                if c.borrow().is_out_of_flow() {
+                    continue;
+                }
+                min_width = min_width.max(min_content_width(&c));
+            }
+            min_width
+        }
+    }
+}
+
+// 改行しなかった場合の内容の幅を求める
+pub fn max_content_width(node: &Rc<RefCell<LayoutObject>>) -> i64 {
+    let kind = node.borrow().kind();
+    match kind {
+        LayoutObjectKind::Text => {
+            if let NodeKind::Text(t) = node.borrow().node_kind() {
+                let ratio = match node.borrow().style().font_size() {
+                    FontSize::Medium => 1,
+                    FontSize::XLarge => 2,
+                    FontSize::XXLarge => 3,
+                };
+                let len = t.split_whitespace().collect::<Vec<_>>().join(" ").len() as i64;
+                return (CHAR_WIDTH * ratio * len).min(CONTENT_AREA_WIDTH);
+            }
+            0
+        }
+        LayoutObjectKind::Inline | LayoutObjectKind::Block => {
+            // インライン要素が続く間は同じ行に並ぶので幅を足し、ブロック要素は最大値をとる
+            let mut max_width = 0;
+            let mut line_width = 0;
+            let mut child = node.borrow().first_child();
+            while let Some(c) = child {
+                child = c.borrow().next_sibling();
+                // 通常のフローから外れた要素は幅に影響しない
+                if c.borrow().is_out_of_flow() {
+                    continue;
+                }
+
+                // 表のセルは同じ行に並ぶ
+                let width = max_content_width(&c);
+                if c.borrow().kind() == LayoutObjectKind::Block
+                    && c.borrow().style().display() != DisplayType::TableCell
+                {
+                    max_width = max_width.max(line_width).max(width);
+                    line_width = 0;
+                } else {
+                    line_width += width;
+                }
+            }
+            max_width.max(line_width)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 // 描画に必要な情報をすべて持った構造体
 pub struct LayoutObject {
@@ -94,6 +216,20 @@ pub struct LayoutObject {
     style: ComputedStyle,
     point: LayoutPoint,
     size: LayoutSize,
+    // display:gridの場合のグリッドのレイアウト結果
+    grid_layout: Option<GridLayout>,
+    // display:tableの場合の表のレイアウト結果
+    table_layout: Option<TableLayout>,
+    // フロートを避けて折り返したテキストの各行。空の場合は描画時に折り返す
+    line_boxes: Vec<LineBox>,
+    // 自身のスタイルやサイズを再計算する必要があるか
+    needs_layout: bool,
+    // 子孫のいずれかを再計算する必要があるか
+    child_needs_layout: bool,
+    // サイズの計算が終わった時点のサイズ。位置の計算で変わる前の値を再利用のために保存する
+    flow_size: LayoutSize,
+    // サイズの計算に使った親ノードの幅
+    last_parent_width: Option<i64>,
 }
 
 impl LayoutObject {
@@ -112,13 +248,62 @@ impl LayoutObject {
             style: ComputedStyle::new(),
             point: LayoutPoint::new(0, 0),
             size: LayoutSize::new(0, 0),
+            grid_layout: None,
+            table_layout: None,
+            line_boxes: Vec::new(),
+            needs_layout: true,
+            child_needs_layout: false,
+            flow_size: LayoutSize::new(0, 0),
+            last_parent_width: None,
         }
     }
 
+    pub fn needs_layout(&self) -> bool {
+        self.needs_layout
+    }
+
+    pub fn child_needs_layout(&self) -> bool {
+        self.child_needs_layout
+    }
+
+    pub fn set_child_needs_layout(&mut self, child_needs_layout: bool) {
+        self.child_needs_layout = child_needs_layout;
+    }
+
+    pub fn last_parent_width(&self) -> Option<i64> {
+        self.last_parent_width
+    }
+
+    pub fn set_last_parent_width(&mut self, width: i64) {
+        self.last_parent_width = Some(width);
+    }
+
+    // 前回のレイアウトで計算したグリッドと表の結果を捨てる
+    pub fn clear_formatting_layout(&mut self) {
+        self.grid_layout = None;
+        self.table_layout = None;
+    }
+
+    // サイズの計算結果を保存し、再計算が必要なことを表すフラグを消す
+    pub fn commit_layout(&mut self) {
+        self.flow_size = self.size;
+        self.needs_layout = false;
+        self.child_needs_layout = false;
+    }
+
+    // 保存したサイズに戻す
+    pub fn restore_flow_size(&mut self) {
+        self.size = self.flow_size;
+    }
+
     pub fn kind(&self) -> LayoutObjectKind {
         self.kind
     }
 
+    pub fn node(&self) -> Rc<RefCell<Node>> {
+        self.node.clone()
+    }
+
     pub fn node_kind(&self) -> NodeKind {
         self.node.borrow().kind().clone()
     }
@@ -155,6 +340,91 @@ impl LayoutObject {
         self.size
     }
 
+    pub fn set_point(&mut self, point: LayoutPoint) {
+        self.point = point;
+    }
+
+    pub fn set_size(&mut self, size: LayoutSize) {
+        self.size = size;
+    }
+
+    pub fn is_out_of_flow(&self) -> bool {
+        self.style.is_out_of_flow()
+    }
+
+    pub fn is_floating(&self) -> bool {
+        self.style.is_floating()
+    }
+
+    // 兄弟ノードの位置や親ノードの高さに影響する要素か
+    pub fn is_in_flow(&self) -> bool {
+        !self.is_out_of_flow() && !self.is_floating()
+    }
+
+    // 子孫のフロートを閉じ込める新しいブロック整形コンテキストを作るか
+    pub fn establishes_formatting_context(&self) -> bool {
+        if self.kind != LayoutObjectKind::Block {
+            return false;
+        }
+
+        let is_grid_item = match self.parent.upgrade() {
+            Some(parent) => parent.borrow().is_grid_container(),
+            // ルート要素
+            None => return true,
+        };
+        is_grid_item
+            || self.is_out_of_flow()
+            || self.is_floating()
+            || matches!(
+                self.style.display(),
+                DisplayType::Grid
+                    | DisplayType::FlowRoot
+                    | DisplayType::Table
+                    | DisplayType::TableCell
+            )
+    }
+
+    pub fn line_boxes(&self) -> Vec<LineBox> {
+        self.line_boxes.clone()
+    }
+
+    pub fn is_grid_container(&self) -> bool {
+        self.kind == LayoutObjectKind::Block && self.style.display() == DisplayType::Grid
+    }
+
+    pub fn set_grid_layout(&mut self, grid_layout: GridLayout) {
+        self.grid_layout = Some(grid_layout);
+    }
+
+    pub fn grid_layout(&self) -> Option<GridLayout> {
+        self.grid_layout.clone()
+    }
+
+    pub fn is_table(&self) -> bool {
+        self.kind == LayoutObjectKind::Block && self.style.display() == DisplayType::Table
+    }
+
+    pub fn set_table_layout(&mut self, table_layout: TableLayout) {
+        self.table_layout = Some(table_layout);
+    }
+
+    pub fn table_layout(&self) -> Option<TableLayout> {
+        self.table_layout.clone()
+    }
+
+    // colspanやrowspanなどの、セルがまたがる数を表す属性の値
+    pub fn span_attribute(&self, name: &str) -> usize {
+        match self.node_kind() {
+            NodeKind::Element(e) => e
+                .get_attribute(name)
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                // 0や大きすぎる値は1から1000の範囲に丸める
+                .map(|v| v.clamp(1, 1000))
+                .unwrap_or(1),
+            _ => 1,
+        }
+    }
+
     pub fn is_node_selected(&self, selector: &Selector) -> bool {
         match &self.node_kind() {
             NodeKind::Element(e) => match selector {
@@ -238,6 +508,151 @@ impl LayoutObject {
                         self.style.set_display(display_type)
                     }
                 }
+                "position" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(position) = Position::from_str(value) {
+                            self.style.set_position(position);
+                        }
+                    }
+                }
+                "top" | "right" | "bottom" | "left" | "width" | "height" => {
+                    let length = match Length::from_component_value(&declaration.value) {
+                        Ok(length) => length,
+                        Err(_) => continue,
+                    };
+                    match declaration.property.as_str() {
+                        "top" => self.style.set_top(length),
+                        "right" => self.style.set_right(length),
+                        "bottom" => self.style.set_bottom(length),
+                        "left" => self.style.set_left(length),
+                        "width" => self.style.set_width(length),
+                        _ => self.style.set_height(length),
+                    }
+                }
+                "float" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(float) = Float::from_str(value) {
+                            self.style.set_float(float);
+                        }
+                    }
+                }
+                "clear" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(clear) = Clear::from_str(value) {
+                            self.style.set_clear(clear);
+                        }
+                    }
+                }
+                "table-layout" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(table_layout) = TableLayoutType::from_str(value) {
+                            self.style.set_table_layout(table_layout);
+                        }
+                    }
+                }
+                "border-collapse" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(border_collapse) = BorderCollapse::from_str(value) {
+                            self.style.set_border_collapse(border_collapse);
+                        }
+                    }
+                }
+                "border-spacing" => {
+                    if let Ok(Length::Px(spacing)) =
+                        Length::from_component_value(&declaration.value)
+                    {
+                        self.style.set_border_spacing(spacing);
+                    }
+                }
+                "vertical-align" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(vertical_align) = VerticalAlign::from_str(value) {
+                            self.style.set_vertical_align(vertical_align);
+                        }
+                    }
+                }
+                "z-index" => {
+                    if let Ok(z_index) = ZIndex::from_component_value(&declaration.value) {
+                        self.style.set_z_index(z_index);
+                    }
+                }
+                // 不正な値のグリッドのプロパティは無視する
+                "grid-template-columns" => {
+                    if let Ok(tracks) = parse_track_list(&declaration.values) {
+                        self.style.set_grid_template_columns(tracks);
+                    }
+                }
+                "grid-template-rows" => {
+                    if let Ok(tracks) = parse_track_list(&declaration.values) {
+                        self.style.set_grid_template_rows(tracks);
+                    }
+                }
+                "grid-template-areas" => {
+                    if let Ok(areas) = parse_template_areas(&declaration.values) {
+                        self.style.set_grid_template_areas(areas);
+                    }
+                }
+                "row-gap" => {
+                    if let Ok(gap) = parse_gap(&declaration.value) {
+                        self.style.set_row_gap(gap);
+                    }
+                }
+                "column-gap" => {
+                    if let Ok(gap) = parse_gap(&declaration.value) {
+                        self.style.set_column_gap(gap);
+                    }
+                }
+                "gap" => {
+                    // gap: 10px 20px は行、列の順
+                    let row = declaration.values.first().map(parse_gap);
+                    let column = declaration.values.get(1).map(parse_gap).or(row.clone());
+                    if let (Some(Ok(row)), Some(Ok(column))) = (row, column) {
+                        self.style.set_row_gap(row);
+                        self.style.set_column_gap(column);
+                    }
+                }
+                "grid-row-start" => {
+                    if let Ok(line) = parse_grid_line(&declaration.values) {
+                        self.style.set_grid_row_start(line);
+                    }
+                }
+                "grid-row-end" => {
+                    if let Ok(line) = parse_grid_line(&declaration.values) {
+                        self.style.set_grid_row_end(line);
+                    }
+                }
+                "grid-column-start" => {
+                    if let Ok(line) = parse_grid_line(&declaration.values) {
+                        self.style.set_grid_column_start(line);
+                    }
+                }
+                "grid-column-end" => {
+                    if let Ok(line) = parse_grid_line(&declaration.values) {
+                        self.style.set_grid_column_end(line);
+                    }
+                }
+                "grid-row" => {
+                    if let Ok((start, end)) = parse_grid_line_pair(&declaration.values) {
+                        self.style.set_grid_row_start(start);
+                        self.style.set_grid_row_end(end);
+                    }
+                }
+                "grid-column" => {
+                    if let Ok((start, end)) = parse_grid_line_pair(&declaration.values) {
+                        self.style.set_grid_column_start(start);
+                        self.style.set_grid_column_end(end);
+                    }
+                }
+                "grid-area" => {
+                    if let Ok([row_start, column_start, row_end, column_end]) =
+                        parse_grid_area(&declaration.values)
+                    {
+                        self.style.set_grid_row_start(row_start);
+                        self.style.set_grid_column_start(column_start);
+                        self.style.set_grid_row_end(row_end);
+                        self.style.set_grid_column_end(column_end);
+                    }
+                }
                 _ => {}
             }
         }
@@ -258,7 +673,15 @@ impl LayoutObject {
             NodeKind::Element(_) => {
                 let display = self.style.display();
                 match display {
-                    DisplayType::Block => self.kind = LayoutObjectKind::Block,
+                    // グリッドコンテナ自身はブロック要素として配置される
+                    // 表とその行、セルもブロック要素として配置される
+                    DisplayType::Block
+                    | DisplayType::Grid
+                    | DisplayType::FlowRoot
+                    | DisplayType::Table
+                    | DisplayType::TableRowGroup
+                    | DisplayType::TableRow
+                    | DisplayType::TableCell => self.kind = LayoutObjectKind::Block,
                     DisplayType::Inline => self.kind = LayoutObjectKind::Inline,
                     DisplayType::DisplayNone => {
                         panic!("should not create a layout object for display:none")
@@ -275,7 +698,41 @@ impl LayoutObject {
 
         match self.kind() {
             LayoutObjectKind::Block => {
-                size.set_width(parent_size.width());
+                // widthが指定されていない場合、親ノードの幅いっぱいに広がる
+                size.set_width(
+                    self.style
+                        .width()
+                        .resolve(parent_size.width())
+                        .unwrap_or(parent_size.width()),
+                );
+
+                // 表の大きさは列の幅と行の高さの合計になる
+                // 指定された高さより低い場合は指定された高さまで伸ばす
+                if let Some(table_layout) = &self.table_layout {
+                    size.set_width(table_layout.width());
+                    let height = match self.style.height() {
+                        Length::Px(height) => table_layout.height().max(height as i64),
+                        _ => table_layout.height(),
+                    };
+                    size.set_height(height);
+                    self.size = size;
+                    return;
+                }
+
+                // heightが指定されている場合はその値を使う
+                // 親ノードの高さは子ノードに依存するため、パーセントはautoとして扱う
+                if let Length::Px(height) = self.style.height() {
+                    size.set_height(height as i64);
+                    self.size = size;
+                    return;
+                }
+
+                // グリッドコンテナの高さはグリッドの行の高さの合計になる
+                if let Some(grid_layout) = &self.grid_layout {
+                    size.set_height(grid_layout.height());
+                    self.size = size;
+                    return;
+                }
 
                 // 全ての子ノードの高さの合計が高さになる
                 let mut height = 0;
@@ -287,6 +744,12 @@ impl LayoutObject {
                         None => panic!("first child should exist"),
                     };
 
+                    // 通常のフローから外れた子ノードやフロートは高さに影響しない
+                    if !c.borrow().is_in_flow() {
+                        child = c.borrow().next_sibling();
+                        continue;
+                    }
+
                     if previous_child_kind == LayoutObjectKind::Block
                         || c.borrow().kind() == LayoutObjectKind::Block
                     {
@@ -307,6 +770,11 @@ impl LayoutObject {
                         None => panic!("first child should exist"),
                     };
 
+                    if !c.borrow().is_in_flow() {
+                        child = c.borrow().next_sibling();
+                        continue;
+                    }
+
                     width += c.borrow().size.width();
                     height += c.borrow().size.height();
 
@@ -385,6 +853,93 @@ impl LayoutObject {
         self.point = point;
     }
 
+    // フロートを避けるように行を短くしながらテキストを折り返し、増えた高さを返す
+    // leftとrightは行を置くことができるブロック要素の左端と右端
+    pub fn layout_lines(&mut self, floats: &FloatContext, left: i64, right: i64) -> i64 {
+        self.line_boxes = Vec::new();
+
+        let text = match self.node_kind() {
+            NodeKind::Text(t) => t,
+            _ => return 0,
+        };
+        if !floats.intrudes(self.point.y(), self.size.height(), left, right) {
+            return 0;
+        }
+
+        let ratio = match self.style.font_size() {
+            FontSize::Medium => 1,
+            FontSize::XLarge => 2,
+            FontSize::XXLarge => 3,
+        };
+        let char_width = CHAR_WIDTH * ratio;
+        let line_height = CHAR_HEIGHT_WITH_PADDING * ratio;
+
+        let mut words: Vec<String> = text.split_whitespace().map(|w| w.to_string()).collect();
+        words.reverse();
+
+        let mut y = self.point.y();
+        let mut max_x = self.point.x();
+        let mut is_first_line = true;
+        while !words.is_empty() {
+            let (l, r) = floats.available_range(y, line_height, left, right);
+            // 最初の行は前のインライン要素の続きから始まる
+            let x = if is_first_line {
+                l.max(self.point.x())
+            } else {
+                l
+            };
+            let max_chars = ((r - x).max(0) / char_width) as usize;
+
+            let mut line = String::new();
+            while let Some(word) = words.pop() {
+                let len = if line.is_empty() {
+                    word.len()
+                } else {
+                    line.len() + 1 + word.len()
+                };
+                if len <= max_chars {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&word);
+                    continue;
+                }
+
+                if line.is_empty() && (x, r) == (left, right) {
+                    // フロートがなくても収まらない単語は途中で分ける
+                    let (head, tail) = word.split_at(max_chars.max(1).min(word.len()));
+                    line.push_str(head);
+                    if !tail.is_empty() {
+                        words.push(tail.to_string());
+                    }
+                } else {
+                    words.push(word);
+                }
+                break;
+            }
+
+            if line.is_empty() {
+                // 単語が入らない場合はフロートの下まで移動する
+                y = floats.next_bottom(y).unwrap_or(y + line_height);
+                is_first_line = false;
+                continue;
+            }
+
+            max_x = max_x.max(x + line.len() as i64 * char_width);
+            self.line_boxes.push(LineBox {
+                point: LayoutPoint::new(x, y),
+                text: line,
+            });
+            y += line_height;
+            is_first_line = false;
+        }
+
+        let height = y - self.point.y();
+        let delta = height - self.size.height();
+        self.size = LayoutSize::new(max_x - self.point.x(), height);
+        delta
+    }
+
     // ノードをDisplayItemに変換
     pub fn paint(&mut self) -> Vec<DisplayItem> {
         if self.style.display() == DisplayType::DisplayNone {
@@ -406,6 +961,19 @@ impl LayoutObject {
                 // <img>タグなどをサポートした場合はこのアームの中で処理
             }
             LayoutObjectKind::Text => {
+                // フロートを避けて折り返した行がある場合はそれを使う
+                if !self.line_boxes.is_empty() {
+                    return self
+                        .line_boxes
+                        .iter()
+                        .map(|line| DisplayItem::Text {
+                            text: line.text.clone(),
+                            style: self.style(),
+                            layout_point: line.point,
+                        })
+                        .collect();
+                }
+
                 if let NodeKind::Text(t) = self.node_kind() {
                     let mut v = vec![];
 
@@ -445,6 +1013,23 @@ impl LayoutObject {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+// テキストの1行分の位置と内容
+pub struct LineBox {
+    point: LayoutPoint,
+    text: String,
+}
+
+impl LineBox {
+    pub fn point(&self) -> LayoutPoint {
+        self.point
+    }
+
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LayoutObjectKind {
     Block,
@@ -3,16 +3,26 @@ use core::cell::RefCell;
 use alloc::{rc::Rc, vec::Vec};
 
 use crate::{
-    constants::CONTENT_AREA_WIDTH,
+    constants::{CONTENT_AREA_WIDTH, VIEWPORT_HEIGHT},
     display_item::DisplayItem,
     renderer::{
         css::cssom::StyleSheet,
         dom::{
-            api::get_target_element_node,
+            api::{clear_dirty, get_target_element_node},
             node::{ElementKind, Node},
         },
-        layout::layout_object::{
-            create_layout_object, LayoutObject, LayoutObjectKind, LayoutPoint, LayoutSize,
+        layout::{
+            computed_style::{
+                BorderCollapse, Clear, ComputedStyle, DisplayType, Length, Position,
+                TableLayoutType, VerticalAlign,
+            },
+            float::FloatContext,
+            grid::GridLayout,
+            layout_object::{
+                create_layout_object, max_content_width, min_content_width, LayoutObject,
+                LayoutObjectKind, LayoutPoint, LayoutSize,
+            },
+            table::TableLayout,
         },
     },
 };
@@ -49,7 +59,8 @@ fn build_layout_tree(
         let original_next_sibling = n.borrow().next_sibling();
         // 子、兄弟それぞれのレイアウトツリーを作成
         let mut first_child = build_layout_tree(&original_first_child, &layout_object, cssom);
-        let mut next_sibling = build_layout_tree(&original_next_sibling, &None, cssom);
+        // 兄弟ノードも同じ親ノードを持つ
+        let mut next_sibling = build_layout_tree(&original_next_sibling, parent_obj, cssom);
 
         if first_child.is_none() && original_first_child.is_some() {
             // 子ノードの兄弟ノードに対してレイアウトツリーの作成を試みる
@@ -84,7 +95,7 @@ fn build_layout_tree(
                 .next_sibling();
 
             loop {
-                next_sibling = build_layout_tree(&original_dom_node, &None, cssom);
+                next_sibling = build_layout_tree(&original_dom_node, parent_obj, cssom);
 
                 if next_sibling.is_none() && original_dom_node.is_some() {
                     original_dom_node = original_dom_node
@@ -112,6 +123,20 @@ fn build_layout_tree(
     layout_object
 }
 
+// 1つのDOMノードとその子孫からレイアウトツリーを作成する。兄弟ノードは含まない
+fn build_layout_object(
+    node: &Rc<RefCell<Node>>,
+    parent_obj: &Option<Rc<RefCell<LayoutObject>>>,
+    cssom: &StyleSheet,
+) -> Option<Rc<RefCell<LayoutObject>>> {
+    let layout_object = create_layout_object(&Some(node.clone()), parent_obj, cssom);
+    if let Some(obj) = &layout_object {
+        let first_child = build_layout_tree(&node.borrow().first_child(), &layout_object, cssom);
+        obj.borrow_mut().set_first_child(first_child);
+    }
+    layout_object
+}
+
 #[derive(Debug, Clone)]
 pub struct LayoutView {
     root: Option<Rc<RefCell<LayoutObject>>>,
@@ -121,7 +146,7 @@ impl LayoutView {
     pub fn new(root: Rc<RefCell<Node>>, cssom: &StyleSheet) -> Self {
         // レイアウトツリーは描画される要素だけを持つツリーなので、
         // <body>タグを取得し、その子要素以下をレイアウトツリーのノードに変換する
-        let body_root = get_target_element_node(Some(root), ElementKind::Body);
+        let body_root = get_target_element_node(Some(root.clone()), ElementKind::Body);
 
         let mut tree = Self {
             root: build_layout_tree(&body_root, &None, cssom),
@@ -129,9 +154,93 @@ impl LayoutView {
 
         tree.update_layout();
 
+        // 構築に使ったDOMの変更はすべて反映済み
+        clear_dirty(&root);
+
         tree
     }
 
+    // DOMの変更を反映する
+    // 変更されたノードの部分木だけスタイルを計算し直してレイアウトツリーを作り直し、
+    // その部分木と祖先のノードだけサイズを計算し直す
+    pub fn relayout(&mut self, root: Rc<RefCell<Node>>, cssom: &StyleSheet) {
+        let body_root = get_target_element_node(Some(root.clone()), ElementKind::Body);
+        let body = match &body_root {
+            Some(body) => body.clone(),
+            None => {
+                self.root = None;
+                clear_dirty(&root);
+                return;
+            }
+        };
+
+        let is_same_body = match &self.root {
+            Some(r) => Rc::ptr_eq(&r.borrow().node(), &body),
+            None => false,
+        };
+
+        if !is_same_body || body.borrow().is_dirty() {
+            self.root = build_layout_tree(&body_root, &None, cssom);
+            self.update_layout();
+        } else if body.borrow().is_child_dirty() {
+            if let Some(r) = &self.root {
+                Self::rebuild_children(r, &body, cssom);
+            }
+            self.update_layout_incrementally();
+        }
+
+        clear_dirty(&root);
+    }
+
+    // DOMの子ノードに合わせてレイアウトツリーの子ノードを作り直す
+    // 変更されていないノードのLayoutObjectはそのまま使う
+    fn rebuild_children(
+        obj: &Rc<RefCell<LayoutObject>>,
+        node: &Rc<RefCell<Node>>,
+        cssom: &StyleSheet,
+    ) {
+        let mut old_children = Vec::new();
+        let mut old_child = obj.borrow().first_child();
+        while let Some(c) = old_child {
+            old_child = c.borrow().next_sibling();
+            old_children.push(c);
+        }
+
+        let parent_obj = Some(obj.clone());
+        let mut children = Vec::new();
+        let mut dom_child = node.borrow().first_child();
+        while let Some(d) = dom_child {
+            dom_child = d.borrow().next_sibling();
+
+            let old = old_children
+                .iter()
+                .find(|c| Rc::ptr_eq(&c.borrow().node(), &d))
+                .cloned();
+            let child = match old {
+                Some(old) if !d.borrow().is_dirty() => {
+                    if d.borrow().is_child_dirty() {
+                        Self::rebuild_children(&old, &d, cssom);
+                    }
+                    Some(old)
+                }
+                _ => build_layout_object(&d, &parent_obj, cssom),
+            };
+
+            if let Some(c) = child {
+                children.push(c);
+            }
+        }
+
+        // 兄弟ノードのつながりを作り直す
+        let mut next_sibling = None;
+        for child in children.iter().rev() {
+            child.borrow_mut().set_next_sibling(next_sibling);
+            next_sibling = Some(child.clone());
+        }
+        obj.borrow_mut().set_first_child(next_sibling);
+        obj.borrow_mut().set_child_needs_layout(true);
+    }
+
     pub fn root(&self) -> Option<Rc<RefCell<LayoutObject>>> {
         self.root.clone()
     }
@@ -139,53 +248,311 @@ impl LayoutView {
     // 構築し終えたレイアウトツリーに対して各ノードのサイズと位置を計算
     fn update_layout(&mut self) {
         Self::calculate_node_size(&self.root, LayoutSize::new(CONTENT_AREA_WIDTH, 0));
+        Self::commit_layout(&self.root);
+
+        self.update_position();
+    }
+
+    // 再計算が必要なノードとその祖先のサイズだけを計算し直す
+    // 位置の計算はフロートを避けるためにサイズを変えるので、変更されていないノードも含めて
+    // 保存しておいたサイズから計算し直す
+    fn update_layout_incrementally(&mut self) {
+        Self::calculate_node_size_incrementally(&self.root, LayoutSize::new(CONTENT_AREA_WIDTH, 0));
+        Self::commit_layout(&self.root);
+
+        self.update_position();
+    }
 
+    fn update_position(&mut self) {
         Self::calculate_node_position(
             &self.root,
             LayoutPoint::new(0, 0),
             None,
             LayoutObjectKind::Block,
             None,
-        )
+            &mut FloatContext::new(),
+        );
+
+        // 通常のフローのレイアウトが終わった後に、絶対位置指定された要素を配置する
+        Self::calculate_out_of_flow_layout(&self.root);
     }
 
     // レイアウトツリーの各ノードのサイズを計算
     fn calculate_node_size(node: &Option<Rc<RefCell<LayoutObject>>>, parent_size: LayoutSize) {
         if let Some(n) = node {
-            // ノードがブロック要素の場合、子ノードのレイアウトを計算する前に横幅を決める
-            if n.borrow().kind() == LayoutObjectKind::Block {
-                n.borrow_mut().compute_size(parent_size);
+            // 通常のフローから外れた要素のサイズは包含ブロックが決まった後に計算する
+            if n.borrow().is_floating() {
+                Self::calculate_float_size(n, parent_size);
+            } else if !n.borrow().is_out_of_flow() {
+                Self::calculate_single_node_size(n, parent_size);
             }
 
-            let first_child = n.borrow().first_child();
-            Self::calculate_node_size(&first_child, n.borrow().size());
-
             let next_sibling = n.borrow().next_sibling();
             Self::calculate_node_size(&next_sibling, parent_size);
+        }
+    }
+
+    // 再計算が必要なノードだけサイズを計算し、それ以外のノードは保存したサイズに戻す
+    fn calculate_node_size_incrementally(
+        node: &Option<Rc<RefCell<LayoutObject>>>,
+        parent_size: LayoutSize,
+    ) {
+        if let Some(n) = node {
+            if n.borrow().is_floating() || !n.borrow().is_out_of_flow() {
+                if Self::needs_full_layout(n, parent_size) {
+                    if n.borrow().is_floating() {
+                        Self::calculate_float_size(n, parent_size);
+                    } else {
+                        Self::calculate_single_node_size(n, parent_size);
+                    }
+                } else if n.borrow().child_needs_layout() {
+                    if n.borrow().kind() == LayoutObjectKind::Block {
+                        n.borrow_mut().compute_size(parent_size);
+                    }
+                    let first_child = n.borrow().first_child();
+                    Self::calculate_node_size_incrementally(&first_child, n.borrow().size());
+                    n.borrow_mut().compute_size(parent_size);
+                } else {
+                    Self::restore_flow_size(n);
+                }
+            }
+
+            let next_sibling = n.borrow().next_sibling();
+            Self::calculate_node_size_incrementally(&next_sibling, parent_size);
+        }
+    }
+
+    // 部分木全体のサイズを計算し直す必要があるか
+    // グリッド、表、フロートのサイズは子孫の内容の幅に依存する
+    fn needs_full_layout(n: &Rc<RefCell<LayoutObject>>, parent_size: LayoutSize) -> bool {
+        let n = n.borrow();
+        n.needs_layout()
+            || n.last_parent_width() != Some(parent_size.width())
+            || (n.child_needs_layout()
+                && (n.is_grid_container() || n.is_table() || n.is_floating()))
+    }
+
+    fn restore_flow_size(n: &Rc<RefCell<LayoutObject>>) {
+        n.borrow_mut().restore_flow_size();
+        let mut child = n.borrow().first_child();
+        while let Some(c) = child {
+            Self::restore_flow_size(&c);
+            child = c.borrow().next_sibling();
+        }
+    }
+
+    // サイズの計算結果を保存する
+    fn commit_layout(node: &Option<Rc<RefCell<LayoutObject>>>) {
+        let mut current = node.clone();
+        while let Some(n) = current {
+            n.borrow_mut().commit_layout();
+            let first_child = n.borrow().first_child();
+            Self::commit_layout(&first_child);
+            current = n.borrow().next_sibling();
+        }
+    }
+
+    // 1つのノードとその子孫のサイズを計算
+    fn calculate_single_node_size(n: &Rc<RefCell<LayoutObject>>, parent_size: LayoutSize) {
+        Self::calculate_node_size_with_width(n, parent_size, None);
+    }
+
+    // 横幅がwidthに決まっている場合は、親ノードの幅の代わりにその値を使う
+    fn calculate_node_size_with_width(
+        n: &Rc<RefCell<LayoutObject>>,
+        parent_size: LayoutSize,
+        width: Option<i64>,
+    ) {
+        n.borrow_mut().set_last_parent_width(parent_size.width());
+        n.borrow_mut().clear_formatting_layout();
 
-            // 子ノードのサイズが決まった後にサイズを計算する
-            // ブロック要素の時、高さは子ノードの高さに依存する
-            // インライン要素の時、高さ、横幅は子ノードに依存する
+        // ノードがブロック要素の場合、子ノードのレイアウトを計算する前に横幅を決める
+        if n.borrow().kind() == LayoutObjectKind::Block {
             n.borrow_mut().compute_size(parent_size);
+            Self::override_width(n, width);
+        }
+
+        if n.borrow().is_grid_container() {
+            // グリッドコンテナの子ノードはグリッドの領域に合わせてサイズを計算する
+            Self::calculate_grid_size(n);
+        } else if n.borrow().is_table() {
+            // 表の子ノードは列の幅と行の高さに合わせてサイズを計算する
+            Self::calculate_table_size(n);
+        } else {
+            let first_child = n.borrow().first_child();
+            Self::calculate_node_size(&first_child, n.borrow().size());
+        }
+
+        // 子ノードのサイズが決まった後にサイズを計算する
+        // ブロック要素の時、高さは子ノードの高さに依存する
+        // インライン要素の時、高さ、横幅は子ノードに依存する
+        n.borrow_mut().compute_size(parent_size);
+        // 表の幅は列の幅から決まる
+        if !n.borrow().is_table() {
+            Self::override_width(n, width);
+        }
+    }
+
+    fn override_width(n: &Rc<RefCell<LayoutObject>>, width: Option<i64>) {
+        if let Some(width) = width {
+            let mut size = n.borrow().size();
+            size.set_width(width);
+            n.borrow_mut().set_size(size);
+        }
+    }
+
+    // 幅が指定されていない場合、内容の幅に縮める
+    fn shrink_to_fit_width(n: &Rc<RefCell<LayoutObject>>, available_width: i64) -> i64 {
+        max_content_width(n).min(available_width)
+    }
+
+    // フロートのサイズを計算
+    fn calculate_float_size(n: &Rc<RefCell<LayoutObject>>, parent_size: LayoutSize) {
+        let width = match n.borrow().style().width().resolve(parent_size.width()) {
+            Some(width) => width,
+            None => Self::shrink_to_fit_width(n, parent_size.width()),
+        };
+        Self::calculate_node_size_with_width(n, parent_size, Some(width));
+    }
+
+    // 通常のフローに含まれる子ノードのリスト
+    fn in_flow_children(n: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let mut children = Vec::new();
+        let mut child = n.borrow().first_child();
+        while let Some(c) = child {
+            child = c.borrow().next_sibling();
+            if c.borrow().is_in_flow() {
+                children.push(c);
+            }
+        }
+        children
+    }
+
+    // グリッドアイテムを配置し、列と行の大きさを決める
+    fn calculate_grid_size(container: &Rc<RefCell<LayoutObject>>) {
+        let items = Self::in_flow_children(container);
+
+        let style = container.borrow().style();
+        let item_styles: Vec<ComputedStyle> = items.iter().map(|i| i.borrow().style()).collect();
+        let mut grid = GridLayout::place_items(&style, &item_styles);
+
+        let widths: Vec<i64> = items.iter().map(max_content_width).collect();
+        grid.resolve_columns(
+            &style.grid_template_columns(),
+            container.borrow().size().width(),
+            &widths,
+        );
+
+        // 列の幅が決まったので、アイテムの幅を領域の幅としてサイズを計算する
+        for (i, item) in items.iter().enumerate() {
+            Self::calculate_single_node_size(item, LayoutSize::new(grid.area_width(i), 0));
+        }
+
+        let heights: Vec<i64> = items.iter().map(|i| i.borrow().size().height()).collect();
+        grid.resolve_rows(&style.grid_template_rows(), &heights);
+
+        // ブロック要素のアイテムは領域いっぱいに引き伸ばす
+        for (i, item) in items.iter().enumerate() {
+            if item.borrow().kind() == LayoutObjectKind::Block {
+                item.borrow_mut().set_size(grid.area_size(i));
+            }
         }
+
+        container.borrow_mut().set_grid_layout(grid);
     }
 
     // レイアウトツリーのノードの位置を計算
+    // フロートを避けたことによって増えた高さの合計を返す
     fn calculate_node_position(
         node: &Option<Rc<RefCell<LayoutObject>>>,
         parent_point: LayoutPoint,
         previous_sibling_point: Option<LayoutPoint>,
         previous_sibling_kind: LayoutObjectKind,
         previous_sibling_size: Option<LayoutSize>,
-    ) {
-        if let Some(n) = node {
-            n.borrow_mut().compute_position(
+        floats: &mut FloatContext,
+    ) -> i64 {
+        let n = match node {
+            Some(n) => n,
+            None => return 0,
+        };
+
+        n.borrow_mut().compute_position(
+            parent_point,
+            previous_sibling_kind,
+            previous_sibling_point,
+            previous_sibling_size,
+        );
+
+        let next_sibling = n.borrow().next_sibling();
+        if !n.borrow().is_in_flow() {
+            if n.borrow().is_floating() {
+                Self::layout_float(n, floats);
+            }
+
+            // 絶対位置指定の場合、ここで計算した位置は静的位置として使う
+            // 兄弟ノードの位置には影響しないため、受け取った情報をそのまま渡す
+            return Self::calculate_node_position(
+                &next_sibling,
                 parent_point,
-                previous_sibling_kind,
                 previous_sibling_point,
+                previous_sibling_kind,
                 previous_sibling_size,
+                floats,
             );
-            // 子ノードの位置を計算
+        }
+
+        // clearが指定されている場合、フロートの下まで移動する
+        let mut extra_height = 0;
+        let clear = n.borrow().style().clear();
+        if clear != Clear::None && n.borrow().kind() == LayoutObjectKind::Block {
+            let point = n.borrow().point();
+            let y = floats.clearance(clear, point.y());
+            extra_height += y - point.y();
+            n.borrow_mut().set_point(LayoutPoint::new(point.x(), y));
+        }
+
+        // 相対位置指定による移動は兄弟ノードの位置には影響しない
+        let flow_point = n.borrow().point();
+        Self::apply_relative_offset(n);
+
+        // 子ノードの位置を計算
+        let kind = n.borrow().kind();
+        if kind == LayoutObjectKind::Text {
+            let (left, right) = Self::line_edges(n);
+            extra_height += n.borrow_mut().layout_lines(floats, left, right);
+        } else if n.borrow().establishes_formatting_context() {
+            extra_height += Self::layout_formatting_context(n);
+        } else {
+            let children_extra_height = Self::calculate_children_position(n, floats);
+            extra_height += Self::grow_height(n, children_extra_height, None);
+        }
+
+        // 兄弟ノードの位置を計算
+        let size = n.borrow().size();
+        extra_height
+            + Self::calculate_node_position(
+                &next_sibling,
+                parent_point,
+                Some(flow_point),
+                kind,
+                Some(size),
+                floats,
+            )
+    }
+
+    fn calculate_children_position(
+        n: &Rc<RefCell<LayoutObject>>,
+        floats: &mut FloatContext,
+    ) -> i64 {
+        let grid_layout = n.borrow().grid_layout();
+        let table_layout = n.borrow().table_layout();
+        if let Some(grid) = grid_layout {
+            Self::calculate_grid_position(n, &grid);
+            0
+        } else if let Some(table) = table_layout {
+            Self::calculate_table_position(n, &table);
+            0
+        } else {
             let first_child = n.borrow().first_child();
             Self::calculate_node_position(
                 &first_child,
@@ -193,176 +560,687 @@ impl LayoutView {
                 None,
                 LayoutObjectKind::Block,
                 None,
-            );
+                floats,
+            )
+        }
+    }
 
-            // 兄弟ノードの位置を計算
-            let next_sibling = n.borrow().next_sibling();
-            Self::calculate_node_position(
-                &next_sibling,
-                parent_point,
-                Some(n.borrow().point()),
-                n.borrow().kind(),
-                Some(n.borrow().size()),
-            );
-        };
+    // 新しいブロック整形コンテキストの中に子ノードを配置する
+    // 子孫のフロートを含むように高さを伸ばし、増えた高さを返す
+    fn layout_formatting_context(n: &Rc<RefCell<LayoutObject>>) -> i64 {
+        let mut floats = FloatContext::new();
+        let extra_height = Self::calculate_children_position(n, &mut floats);
+        Self::grow_height(n, extra_height, floats.bottom())
     }
 
-    // ノードをDisplayItem列挙型のベクタに変換
-    fn paint_node(node: &Option<Rc<RefCell<LayoutObject>>>, display_items: &mut Vec<DisplayItem>) {
-        match node {
-            Some(n) => {
-                display_items.extend(n.borrow_mut().paint());
+    // 子孫の配置によって増えた分だけ高さを伸ばし、増えた高さを返す
+    // float_bottomが指定された場合は、その位置まで高さを伸ばす
+    fn grow_height(
+        n: &Rc<RefCell<LayoutObject>>,
+        extra_height: i64,
+        float_bottom: Option<i64>,
+    ) -> i64 {
+        // 高さが子ノードに依存しない場合は何もしない
+        if n.borrow().kind() == LayoutObjectKind::Text
+            || n.borrow().is_grid_container()
+            || n.borrow().is_table()
+            || matches!(n.borrow().style().height(), Length::Px(_))
+        {
+            return 0;
+        }
 
-                let first_child = n.borrow().first_child();
-                Self::paint_node(&first_child, display_items);
+        let mut size = n.borrow().size();
+        let old_height = size.height();
+        let mut height = old_height + extra_height;
+        if let Some(bottom) = float_bottom {
+            height = height.max(bottom - n.borrow().point().y());
+        }
+        size.set_height(height);
+        n.borrow_mut().set_size(size);
+        height - old_height
+    }
 
-                let next_sibling = n.borrow().next_sibling();
-                Self::paint_node(&next_sibling, display_items);
+    // 行やフロートを置くことができる左端と右端を、最も近いブロック要素の祖先から求める
+    fn line_edges(n: &Rc<RefCell<LayoutObject>>) -> (i64, i64) {
+        let mut ancestor = n.borrow().parent().upgrade();
+        while let Some(a) = ancestor {
+            if a.borrow().kind() == LayoutObjectKind::Block {
+                let point = a.borrow().point();
+                return (point.x(), point.x() + a.borrow().size().width());
             }
-            None => {}
+            ancestor = a.borrow().parent().upgrade();
         }
+        (0, CONTENT_AREA_WIDTH)
     }
 
-    pub fn paint(&self) -> Vec<DisplayItem> {
-        let mut display_items = Vec::new();
+    // フロートを包含ブロックの左端または右端に寄せて配置する
+    fn layout_float(n: &Rc<RefCell<LayoutObject>>, floats: &mut FloatContext) {
+        let style = n.borrow().style();
+        let (left, right) = Self::line_edges(n);
+        let top = floats.clearance(style.clear(), n.borrow().point().y());
+        let point = floats.place(style.float(), n.borrow().size(), top, left, right);
+        n.borrow_mut().set_point(point);
 
-        Self::paint_node(&self.root, &mut display_items);
+        Self::apply_relative_offset(n);
+        Self::layout_formatting_context(n);
 
-        display_items
+        // 子孫の配置によって高さが決まった後に、後続の内容が避ける領域として登録する
+        floats.add(style.float(), point, n.borrow().size());
     }
 
-    pub fn find_node_by_position(&self, position: (i64, i64)) -> Option<Rc<RefCell<LayoutObject>>> {
-        Self::find_node_by_position_internal(&self.root(), position)
+    // グリッドアイテムをそれぞれの領域の左上に配置する
+    fn calculate_grid_position(container: &Rc<RefCell<LayoutObject>>, grid: &GridLayout) {
+        let origin = container.borrow().point();
+        for (i, item) in Self::in_flow_children(container).iter().enumerate() {
+            let offset = grid.area_offset(i);
+            item.borrow_mut().set_point(LayoutPoint::new(
+                origin.x() + offset.x(),
+                origin.y() + offset.y(),
+            ));
+            Self::apply_relative_offset(item);
+            // グリッドアイテムは新しいブロック整形コンテキストを作る
+            Self::layout_formatting_context(item);
+        }
+
+        // グリッドアイテムにならない子ノードの静的位置はコンテナの左上とする
+        let mut child = container.borrow().first_child();
+        while let Some(c) = child {
+            if c.borrow().is_out_of_flow() {
+                c.borrow_mut().set_point(origin);
+            }
+            child = c.borrow().next_sibling();
+        }
     }
 
-    fn find_node_by_position_internal(
-        node: &Option<Rc<RefCell<LayoutObject>>>,
-        position: (i64, i64),
-    ) -> Option<Rc<RefCell<LayoutObject>>> {
-        match node {
-            Some(n) => {
-                let first_child = n.borrow().first_child();
-                let result1 = Self::find_node_by_position_internal(&first_child, position);
-                if result1.is_some() {
-                    return result1;
-                }
+    // 表の行を上から順に集める。行のグループの中の行も含む
+    // 行やセル以外の子ノードは配置しない
+    fn table_rows(table: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let mut rows = Vec::new();
+        for child in Self::in_flow_children(table) {
+            let display = child.borrow().style().display();
+            match display {
+                DisplayType::TableRow => rows.push(child),
+                DisplayType::TableRowGroup => rows.extend(
+                    Self::in_flow_children(&child)
+                        .into_iter()
+                        .filter(|r| r.borrow().style().display() == DisplayType::TableRow),
+                ),
+                _ => {}
+            }
+        }
+        rows
+    }
 
-                let next_sibling = n.borrow().next_sibling();
-                let result2 = Self::find_node_by_position_internal(&next_sibling, position);
-                if result2.is_some() {
-                    return result2;
-                }
+    fn table_cells(row: &Rc<RefCell<LayoutObject>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+        Self::in_flow_children(row)
+            .into_iter()
+            .filter(|c| c.borrow().style().display() == DisplayType::TableCell)
+            .collect()
+    }
 
-                if n.borrow().point().x() <= position.0
-                    && position.0 <= (n.borrow().point().x() + n.borrow().size().width())
-                    && n.borrow().point().y() <= position.1
-                    && position.1 <= (n.borrow().point().y() + n.borrow().size().height())
-                {
-                    return Some(n.clone());
+    // セルを配置し、列の幅と行の高さを決める
+    fn calculate_table_size(table: &Rc<RefCell<LayoutObject>>) {
+        let style = table.borrow().style();
+        let available_width = table.borrow().size().width();
+        // このブラウザは枠線を描画しないため、border-collapseはセルの間隔にだけ影響する
+        let spacing = match style.border_collapse() {
+            BorderCollapse::Separate => style.border_spacing() as i64,
+            BorderCollapse::Collapse => 0,
+        };
+
+        let rows = Self::table_rows(table);
+        let row_cells: Vec<Vec<Rc<RefCell<LayoutObject>>>> =
+            rows.iter().map(Self::table_cells).collect();
+        let spans: Vec<Vec<(usize, usize)>> = row_cells
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|c| {
+                        (
+                            c.borrow().span_attribute("colspan"),
+                            c.borrow().span_attribute("rowspan"),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells: Vec<Rc<RefCell<LayoutObject>>> = row_cells.into_iter().flatten().collect();
+        let mut layout = TableLayout::place_cells(&spans, spacing);
+
+        let table_width = style.width().resolve(available_width);
+        let cell_widths: Vec<Option<i64>> = cells
+            .iter()
+            .map(|c| c.borrow().style().width().resolve(available_width))
+            .collect();
+        match (style.table_layout(), table_width) {
+            // 固定レイアウトは表の幅が指定されている場合だけ使える
+            (TableLayoutType::Fixed, Some(width)) => {
+                let first_row_widths: Vec<Option<i64>> = layout
+                    .cells()
+                    .iter()
+                    .zip(&cell_widths)
+                    .filter(|(placement, _)| placement.row() == 0)
+                    .map(|(_, width)| *width)
+                    .collect();
+                layout.resolve_columns_fixed(width, &first_row_widths);
+            }
+            _ => {
+                // 幅が指定されたセルは、内容が収まる範囲でその幅にする
+                let mut min_widths = Vec::new();
+                let mut max_widths = Vec::new();
+                for (cell, width) in cells.iter().zip(&cell_widths) {
+                    let min = min_content_width(cell);
+                    let max = match width {
+                        Some(w) => min.max(*w),
+                        None => max_content_width(cell).max(min),
+                    };
+                    min_widths.push(if width.is_some() { max } else { min });
+                    max_widths.push(max);
                 }
-                None
+                layout.resolve_columns_auto(table_width, available_width, &min_widths, &max_widths);
             }
-            None => None,
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use alloc::{
-        string::{String, ToString},
-        vec::Vec,
-    };
+        // 列の幅が決まったので、セルの幅を使って内容の高さを計算する
+        for (i, cell) in cells.iter().enumerate() {
+            let width = layout.cell_width(i);
+            Self::calculate_node_size_with_width(cell, LayoutSize::new(width, 0), Some(width));
+        }
+        let heights: Vec<i64> = cells.iter().map(|c| c.borrow().size().height()).collect();
+        layout.resolve_rows(&heights);
 
-    use crate::renderer::{
-        css::{cssom::CssParser, token::CssTokenizer},
-        dom::{
-            api::get_style_content,
-            node::{Element, NodeKind},
-        },
-        html::{parser::HtmlParser, token::HtmlTokenizer},
-    };
+        // セルは行の高さいっぱいに引き伸ばす
+        for (i, cell) in cells.iter().enumerate() {
+            cell.borrow_mut().set_size(layout.cell_size(i));
+        }
 
-    use super::*;
+        // 行とそのグループは、表の端の間隔を除いた幅を持つ
+        let row_width = (layout.width() - spacing * 2).max(0);
+        for (i, row) in rows.iter().enumerate() {
+            row.borrow_mut()
+                .set_size(LayoutSize::new(row_width, layout.rows()[i]));
+        }
+        for child in Self::in_flow_children(table) {
+            if child.borrow().style().display() == DisplayType::TableRowGroup {
+                let group_rows = Self::table_rows_in_group(&rows, &child, &layout);
+                child
+                    .borrow_mut()
+                    .set_size(LayoutSize::new(row_width, group_rows.1));
+            }
+        }
 
-    fn create_layout_view(html: String) -> LayoutView {
-        let t = HtmlTokenizer::new(html);
-        let window = HtmlParser::new(t).construct_tree();
-        let dom = window.borrow().document();
-        let style = get_style_content(dom.clone());
-        let css_tokenizer = CssTokenizer::new(style);
-        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
-        LayoutView::new(dom, &cssom)
+        table.borrow_mut().set_table_layout(layout);
     }
 
-    #[test]
-    fn test_empty() {
-        let layout_view = create_layout_view("".to_string());
-        assert_eq!(None, layout_view.root());
+    // 行のグループに含まれる最初の行の番号と、グループの高さ
+    fn table_rows_in_group(
+        rows: &[Rc<RefCell<LayoutObject>>],
+        group: &Rc<RefCell<LayoutObject>>,
+        layout: &TableLayout,
+    ) -> (Option<usize>, i64) {
+        let indices: Vec<usize> = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| match r.borrow().parent().upgrade() {
+                Some(p) => Rc::ptr_eq(&p, group),
+                None => false,
+            })
+            .map(|(i, _)| i)
+            .collect();
+        match (indices.first(), indices.last()) {
+            (Some(&first), Some(&last)) => (
+                Some(first),
+                layout.row_offset(last) + layout.rows()[last] - layout.row_offset(first),
+            ),
+            _ => (None, 0),
+        }
     }
 
-    #[test]
-    fn test_body() {
-        let html = "<html><head></head><body></body></html>".to_string();
-        let layout_view = create_layout_view(html);
+    // セルをそれぞれの行と列の位置に配置し、vertical-alignに従って内容を配置する
+    fn calculate_table_position(table: &Rc<RefCell<LayoutObject>>, layout: &TableLayout) {
+        let origin = table.borrow().point();
+        let spacing = layout.spacing();
 
-        let root = layout_view.root();
-        assert!(root.is_some());
-        assert_eq!(
-            LayoutObjectKind::Block,
-            root.clone().expect("root should exist").borrow().kind()
-        );
-        assert_eq!(
-            NodeKind::Element(Element::new("body", Vec::new())),
-            root.clone()
-                .expect("root should exist")
-                .borrow()
-                .node_kind()
-        )
-    }
+        let rows = Self::table_rows(table);
+        for child in Self::in_flow_children(table) {
+            if child.borrow().style().display() == DisplayType::TableRowGroup {
+                let y = match Self::table_rows_in_group(&rows, &child, layout).0 {
+                    Some(first) => layout.row_offset(first),
+                    None => spacing,
+                };
+                child
+                    .borrow_mut()
+                    .set_point(LayoutPoint::new(origin.x() + spacing, origin.y() + y));
+            }
+        }
+        for (i, row) in rows.iter().enumerate() {
+            row.borrow_mut().set_point(LayoutPoint::new(
+                origin.x() + spacing,
+                origin.y() + layout.row_offset(i),
+            ));
+        }
 
-    #[test]
-    fn test_text() {
-        let html = "<html><head></head><body>text</body></html>".to_string();
-        let layout_view = create_layout_view(html);
+        let cells: Vec<Rc<RefCell<LayoutObject>>> =
+            rows.iter().flat_map(Self::table_cells).collect();
+        for (i, cell) in cells.iter().enumerate() {
+            let offset = layout.cell_offset(i);
+            let point = LayoutPoint::new(origin.x() + offset.x(), origin.y() + offset.y());
+            cell.borrow_mut().set_point(point);
 
-        let root = layout_view.root();
-        assert!(root.is_some());
-        assert_eq!(
-            LayoutObjectKind::Block,
-            root.clone().expect("root should exist").borrow().kind()
-        );
-        assert_eq!(
-            NodeKind::Element(Element::new("body", Vec::new())),
-            root.clone()
-                .expect("root should exist")
-                .borrow()
-                .node_kind()
-        );
+            let free_space = (cell.borrow().size().height() - layout.content_height(i)).max(0);
+            let content_offset = match cell.borrow().style().vertical_align() {
+                // 全てのセルの最初の行は同じ高さにあるため、ベースラインは上端に揃える
+                VerticalAlign::Baseline | VerticalAlign::Top => 0,
+                VerticalAlign::Middle => free_space / 2,
+                VerticalAlign::Bottom => free_space,
+            };
 
-        let text = root.expect("root should exist").borrow().first_child();
-        assert!(text.is_some());
-        assert_eq!(
-            LayoutObjectKind::Text,
-            text.clone()
-                .expect("text node should exist")
-                .borrow()
-                .kind()
-        );
-        assert_eq!(
-            NodeKind::Text("text".to_string()),
-            text.clone()
-                .expect("text node should exist")
-                .borrow()
-                .node_kind()
-        );
+            // セルは新しいブロック整形コンテキストを作る
+            let first_child = cell.borrow().first_child();
+            Self::calculate_node_position(
+                &first_child,
+                LayoutPoint::new(point.x(), point.y() + content_offset),
+                None,
+                LayoutObjectKind::Block,
+                None,
+                &mut FloatContext::new(),
+            );
+        }
     }
 
-    #[test]
-    fn test_display_node() {
-        let html = "<html><head><style>body{display:none;}</style></head><body>text</body></html>"
-            .to_string();
-        let layout_view = create_layout_view(html);
+    // position: relative, stickyの要素を通常のフローの位置からずらす
+    fn apply_relative_offset(n: &Rc<RefCell<LayoutObject>>) {
+        let style = n.borrow().style();
+        let point = n.borrow().point();
+        let size = n.borrow().size();
+        let parent = n.borrow().parent().upgrade();
+        let (parent_point, parent_size) = match &parent {
+            Some(p) => (p.borrow().point(), p.borrow().size()),
+            None => (LayoutPoint::new(0, 0), Self::viewport_size()),
+        };
+
+        let (dx, dy) = match style.position() {
+            Position::Relative => {
+                let dx = match style.left().resolve(parent_size.width()) {
+                    Some(left) => left,
+                    None => -style.right().resolve(parent_size.width()).unwrap_or(0),
+                };
+                let dy = match style.top().resolve(parent_size.height()) {
+                    Some(top) => top,
+                    None => -style.bottom().resolve(parent_size.height()).unwrap_or(0),
+                };
+                (dx, dy)
+            }
+            Position::Sticky => {
+                // スクロールはサポートしていないため、ビューポートは常に原点にある
+                // 親ノードの領域からはみ出さない範囲でビューポートの端に張り付く
+                let viewport = Self::viewport_size();
+                let sticky = |start: i64,
+                              length: i64,
+                              parent_start: i64,
+                              parent_length: i64,
+                              viewport_length: i64,
+                              near: Length,
+                              far: Length| {
+                    if let Some(near) = near.resolve(viewport_length) {
+                        if start < near {
+                            let limit = (parent_start + parent_length - (start + length)).max(0);
+                            return (near - start).min(limit);
+                        }
+                    }
+                    if let Some(far) = far.resolve(viewport_length) {
+                        if start + length > viewport_length - far {
+                            let limit = (start - parent_start).max(0);
+                            return -(start + length - (viewport_length - far)).min(limit);
+                        }
+                    }
+                    0
+                };
+                (
+                    sticky(
+                        point.x(),
+                        size.width(),
+                        parent_point.x(),
+                        parent_size.width(),
+                        viewport.width(),
+                        style.left(),
+                        style.right(),
+                    ),
+                    sticky(
+                        point.y(),
+                        size.height(),
+                        parent_point.y(),
+                        parent_size.height(),
+                        viewport.height(),
+                        style.top(),
+                        style.bottom(),
+                    ),
+                )
+            }
+            _ => (0, 0),
+        };
+
+        if dx != 0 || dy != 0 {
+            n.borrow_mut()
+                .set_point(LayoutPoint::new(point.x() + dx, point.y() + dy));
+        }
+    }
+
+    fn viewport_size() -> LayoutSize {
+        LayoutSize::new(CONTENT_AREA_WIDTH, VIEWPORT_HEIGHT)
+    }
+
+    // 包含ブロックの位置と大きさを求める
+    // absoluteの場合は最も近い位置指定された祖先、fixedの場合はビューポートになる
+    fn containing_block(n: &Rc<RefCell<LayoutObject>>) -> (LayoutPoint, LayoutSize) {
+        if n.borrow().style().position() == Position::Absolute {
+            let mut ancestor = n.borrow().parent().upgrade();
+            while let Some(a) = ancestor {
+                if a.borrow().style().position() != Position::Static {
+                    return (a.borrow().point(), a.borrow().size());
+                }
+                ancestor = a.borrow().parent().upgrade();
+            }
+        }
+        (LayoutPoint::new(0, 0), Self::viewport_size())
+    }
+
+    // 通常のフローから外れた要素を包含ブロックに対して配置する
+    fn calculate_out_of_flow_layout(node: &Option<Rc<RefCell<LayoutObject>>>) {
+        if let Some(n) = node {
+            if n.borrow().is_out_of_flow() {
+                Self::layout_out_of_flow_node(n);
+            }
+
+            // 子孫の包含ブロックはこのノードの配置が終わった後に決まる
+            let first_child = n.borrow().first_child();
+            Self::calculate_out_of_flow_layout(&first_child);
+
+            let next_sibling = n.borrow().next_sibling();
+            Self::calculate_out_of_flow_layout(&next_sibling);
+        }
+    }
+
+    fn layout_out_of_flow_node(n: &Rc<RefCell<LayoutObject>>) {
+        let (cb_point, cb_size) = Self::containing_block(n);
+        let style = n.borrow().style();
+
+        let left = style.left().resolve(cb_size.width());
+        let right = style.right().resolve(cb_size.width());
+        let top = style.top().resolve(cb_size.height());
+        let bottom = style.bottom().resolve(cb_size.height());
+
+        // 幅が指定されていない場合、左右の両方が指定されていれば包含ブロックに合わせ、
+        // そうでなければ内容の幅に縮める
+        let width = match (style.width().resolve(cb_size.width()), left, right) {
+            (Some(w), _, _) => w,
+            (None, Some(l), Some(r)) => (cb_size.width() - l - r).max(0),
+            (None, _, _) => Self::shrink_to_fit_width(n, cb_size.width()),
+        };
+        Self::calculate_node_size_with_width(n, cb_size, Some(width));
+        let mut size = n.borrow().size();
+
+        match (style.height().resolve(cb_size.height()), top, bottom) {
+            (Some(h), _, _) => size.set_height(h),
+            (None, Some(t), Some(b)) => size.set_height((cb_size.height() - t - b).max(0)),
+            _ => {}
+        }
+        n.borrow_mut().set_size(size);
+
+        // 左右、上下のどちらも指定されていない場合は静的位置を使う
+        let static_point = n.borrow().point();
+        let x = match (left, right) {
+            (Some(l), _) => cb_point.x() + l,
+            (None, Some(r)) => cb_point.x() + cb_size.width() - r - size.width(),
+            (None, None) => static_point.x(),
+        };
+        let y = match (top, bottom) {
+            (Some(t), _) => cb_point.y() + t,
+            (None, Some(b)) => cb_point.y() + cb_size.height() - b - size.height(),
+            (None, None) => static_point.y(),
+        };
+        n.borrow_mut().set_point(LayoutPoint::new(x, y));
+
+        Self::layout_formatting_context(n);
+    }
+
+    // スタッキングコンテキストの中で、描画順に関わる位置指定された子孫を集める
+    // z-indexがautoの位置指定された要素は新しいコンテキストを作らないため、その子孫も同じコンテキストに属する
+    fn collect_layers(
+        node: &Option<Rc<RefCell<LayoutObject>>>,
+        layers: &mut Vec<(i64, Rc<RefCell<LayoutObject>>)>,
+    ) {
+        if let Some(n) = node {
+            let style = n.borrow().style();
+            if style.creates_stacking_context() {
+                layers.push((style.z_index().order(), n.clone()));
+            } else {
+                if style.position() != Position::Static {
+                    layers.push((0, n.clone()));
+                }
+                let first_child = n.borrow().first_child();
+                Self::collect_layers(&first_child, layers);
+            }
+
+            let next_sibling = n.borrow().next_sibling();
+            Self::collect_layers(&next_sibling, layers);
+        }
+    }
+
+    // スタッキングコンテキストの中のノードを描画する順に並べる
+    // 負のz-index、通常のフロー、フロート、z-indexが0またはauto、正のz-indexの順に描画する
+    fn collect_paint_order(
+        n: &Rc<RefCell<LayoutObject>>,
+        is_stacking_context: bool,
+        result: &mut Vec<Rc<RefCell<LayoutObject>>>,
+    ) {
+        result.push(n.clone());
+
+        let mut layers = Vec::new();
+        if is_stacking_context {
+            let first_child = n.borrow().first_child();
+            Self::collect_layers(&first_child, &mut layers);
+        }
+        // 同じz-indexの場合はツリーの順番を保つ
+        layers.sort_by_key(|(z, _)| *z);
+
+        for (_, layer) in layers.iter().filter(|(z, _)| *z < 0) {
+            Self::collect_paint_order(layer, true, result);
+        }
+
+        let mut floats = Vec::new();
+        let first_child = n.borrow().first_child();
+        Self::collect_in_flow_paint_order(&first_child, result, &mut floats);
+
+        // フロートは通常のフローのブロック要素より手前に描画する
+        for float in &floats {
+            Self::collect_paint_order(float, false, result);
+        }
+
+        for (_, layer) in layers.iter().filter(|(z, _)| *z >= 0) {
+            let is_context = layer.borrow().style().creates_stacking_context();
+            Self::collect_paint_order(layer, is_context, result);
+        }
+    }
+
+    // 位置指定されていないノードをツリーの順に並べる
+    // フロートは後で描画するためfloatsに集める
+    fn collect_in_flow_paint_order(
+        node: &Option<Rc<RefCell<LayoutObject>>>,
+        result: &mut Vec<Rc<RefCell<LayoutObject>>>,
+        floats: &mut Vec<Rc<RefCell<LayoutObject>>>,
+    ) {
+        if let Some(n) = node {
+            if n.borrow().style().position() == Position::Static {
+                if n.borrow().is_floating() {
+                    floats.push(n.clone());
+                } else {
+                    result.push(n.clone());
+
+                    let first_child = n.borrow().first_child();
+                    Self::collect_in_flow_paint_order(&first_child, result, floats);
+                }
+            }
+
+            let next_sibling = n.borrow().next_sibling();
+            Self::collect_in_flow_paint_order(&next_sibling, result, floats);
+        }
+    }
+
+    // 描画される順番に並んだノードのリスト
+    fn paint_order(&self) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let mut result = Vec::new();
+        if let Some(root) = &self.root {
+            // ルート要素は常にスタッキングコンテキストを作る
+            Self::collect_paint_order(root, true, &mut result);
+        }
+        result
+    }
+
+    // ノードをDisplayItem列挙型のベクタに変換
+    pub fn paint(&self) -> Vec<DisplayItem> {
+        let mut display_items = Vec::new();
+
+        for n in self.paint_order() {
+            display_items.extend(n.borrow_mut().paint());
+        }
+
+        display_items
+    }
+
+    // 最も手前に描画されているノードを探す
+    pub fn find_node_by_position(&self, position: (i64, i64)) -> Option<Rc<RefCell<LayoutObject>>> {
+        for n in self.paint_order().iter().rev() {
+            let point = n.borrow().point();
+            let size = n.borrow().size();
+            if point.x() <= position.0
+                && position.0 <= (point.x() + size.width())
+                && point.y() <= position.1
+                && position.1 <= (point.y() + size.height())
+            {
+                return Some(n.clone());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+
+    use crate::constants::{CHAR_HEIGHT_WITH_PADDING, CHAR_WIDTH};
+    use crate::renderer::{
+        css::{cssom::CssParser, token::CssTokenizer},
+        dom::{
+            api::{
+                append_child, get_element_by_id, get_style_content, remove_child, set_attribute,
+                set_text_content,
+            },
+            node::{Element, NodeKind},
+        },
+        html::{parser::HtmlParser, token::HtmlTokenizer},
+    };
+
+    use super::*;
+
+    fn create_layout_view(html: String) -> LayoutView {
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let dom = window.borrow().document();
+        let style = get_style_content(dom.clone());
+        let css_tokenizer = CssTokenizer::new(style);
+        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
+        LayoutView::new(dom, &cssom)
+    }
+
+    fn create_dom(html: String) -> (Rc<RefCell<Node>>, StyleSheet) {
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let dom = window.borrow().document();
+        let style = get_style_content(dom.clone());
+        let css_tokenizer = CssTokenizer::new(style);
+        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
+        (dom, cssom)
+    }
+
+    fn element(dom: &Rc<RefCell<Node>>, id: &str) -> Rc<RefCell<Node>> {
+        get_element_by_id(Some(dom.clone()), &id.to_string()).expect("element should exist")
+    }
+
+    #[test]
+    fn test_empty() {
+        let layout_view = create_layout_view("".to_string());
+        assert_eq!(None, layout_view.root());
+    }
+
+    #[test]
+    fn test_body() {
+        let html = "<html><head></head><body></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root();
+        assert!(root.is_some());
+        assert_eq!(
+            LayoutObjectKind::Block,
+            root.clone().expect("root should exist").borrow().kind()
+        );
+        assert_eq!(
+            NodeKind::Element(Element::new("body", Vec::new())),
+            root.clone()
+                .expect("root should exist")
+                .borrow()
+                .node_kind()
+        )
+    }
+
+    #[test]
+    fn test_text() {
+        let html = "<html><head></head><body>text</body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root();
+        assert!(root.is_some());
+        assert_eq!(
+            LayoutObjectKind::Block,
+            root.clone().expect("root should exist").borrow().kind()
+        );
+        assert_eq!(
+            NodeKind::Element(Element::new("body", Vec::new())),
+            root.clone()
+                .expect("root should exist")
+                .borrow()
+                .node_kind()
+        );
+
+        let text = root.expect("root should exist").borrow().first_child();
+        assert!(text.is_some());
+        assert_eq!(
+            LayoutObjectKind::Text,
+            text.clone()
+                .expect("text node should exist")
+                .borrow()
+                .kind()
+        );
+        assert_eq!(
+            NodeKind::Text("text".to_string()),
+            text.clone()
+                .expect("text node should exist")
+                .borrow()
+                .node_kind()
+        );
+    }
+
+    #[test]
+    fn test_display_node() {
+        let html = "<html><head><style>body{display:none;}</style></head><body>text</body></html>"
+            .to_string();
+        let layout_view = create_layout_view(html);
 
         assert_eq!(None, layout_view.root());
     }
@@ -425,4 +1303,627 @@ mod tests {
             .next_sibling()
             .is_none());
     }
+
+    #[test]
+    fn test_grid() {
+        let html = r#"<html>
+        <head>
+        <style>
+        .grid {
+          display: grid;
+          grid-template-columns: 100px 1fr;
+          gap: 10px;
+        }
+        </style>
+        </head>
+        <body>
+          <div class="grid"><p>a</p><p>b</p><p>c</p></div>
+        </body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let grid = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("grid container should exist");
+        assert_eq!(
+            LayoutSize::new(CONTENT_AREA_WIDTH, 50),
+            grid.borrow().size()
+        );
+
+        let a = grid.borrow().first_child().expect("a should exist");
+        let b = a.borrow().next_sibling().expect("b should exist");
+        let c = b.borrow().next_sibling().expect("c should exist");
+        assert_eq!(LayoutPoint::new(0, 0), a.borrow().point());
+        assert_eq!(LayoutSize::new(100, 20), a.borrow().size());
+        assert_eq!(LayoutPoint::new(110, 0), b.borrow().point());
+        assert_eq!(
+            LayoutSize::new(CONTENT_AREA_WIDTH - 110, 20),
+            b.borrow().size()
+        );
+        assert_eq!(LayoutPoint::new(0, 30), c.borrow().point());
+    }
+
+    #[test]
+    fn test_grid_area() {
+        let html = r#"<html>
+        <head>
+        <style>
+        .grid {
+          display: grid;
+          grid-template-columns: repeat(3, 100px);
+          grid-template-areas: "h h h" "s m m";
+        }
+        #main {
+          grid-area: m;
+        }
+        #last {
+          grid-column: 1 / span 2;
+        }
+        </style>
+        </head>
+        <body>
+          <div class="grid"><p id="main">main</p><p>x</p><p id="last">y</p></div>
+        </body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let grid = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("grid container should exist");
+        let main = grid.borrow().first_child().expect("main should exist");
+        let x = main.borrow().next_sibling().expect("x should exist");
+        let last = x.borrow().next_sibling().expect("last should exist");
+
+        assert_eq!(LayoutPoint::new(100, 20), main.borrow().point());
+        assert_eq!(LayoutSize::new(200, 20), main.borrow().size());
+        assert_eq!(LayoutPoint::new(0, 0), x.borrow().point());
+        assert_eq!(LayoutPoint::new(0, 40), last.borrow().point());
+        assert_eq!(LayoutSize::new(200, 20), last.borrow().size());
+        assert_eq!(60, grid.borrow().size().height());
+    }
+
+    #[test]
+    fn test_grid_large_values() {
+        let html = r#"<html>
+        <head>
+        <style>
+        .g {
+          display: grid;
+          grid-template-columns: repeat(100000000, 1px);
+        }
+        p {
+          grid-column: 500000000;
+          grid-row: 1 / span 500000000;
+        }
+        </style>
+        </head>
+        <body>
+          <div class="g"><p>a</p></div>
+        </body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let grid = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("grid container should exist");
+        let a = grid.borrow().first_child().expect("a should exist");
+        assert_eq!(LayoutPoint::new(999, 0), a.borrow().point());
+    }
+
+    #[test]
+    fn test_position_relative() {
+        let html = r#"<html>
+        <head>
+        <style>
+        #shifted {
+          position: relative;
+          top: 5px;
+          left: 10px;
+        }
+        </style>
+        </head>
+        <body><p id="shifted">a</p><p>b</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let shifted = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("shifted should exist");
+        let next = shifted.borrow().next_sibling().expect("next should exist");
+
+        assert_eq!(LayoutPoint::new(10, 5), shifted.borrow().point());
+        // 相対位置指定は後続の兄弟ノードの位置に影響しない
+        assert_eq!(LayoutPoint::new(0, 20), next.borrow().point());
+    }
+
+    #[test]
+    fn test_position_absolute() {
+        let html = r#"<html>
+        <head>
+        <style>
+        #container {
+          position: relative;
+          top: 10px;
+          height: 200px;
+        }
+        #abs {
+          position: absolute;
+          right: 0px;
+          bottom: 10px;
+          width: 50px;
+        }
+        #fixed {
+          position: fixed;
+          top: 0px;
+          left: 0px;
+          right: 0px;
+        }
+        </style>
+        </head>
+        <body><div id="container"><p id="abs">a</p><p>b</p></div><p id="fixed">c</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let container = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("container should exist");
+        let abs = container.borrow().first_child().expect("abs should exist");
+        let b = abs.borrow().next_sibling().expect("b should exist");
+        let fixed = container
+            .borrow()
+            .next_sibling()
+            .expect("fixed should exist");
+
+        assert_eq!(LayoutPoint::new(0, 10), container.borrow().point());
+        assert_eq!(
+            LayoutSize::new(CONTENT_AREA_WIDTH, 200),
+            container.borrow().size()
+        );
+        assert_eq!(LayoutSize::new(50, 20), abs.borrow().size());
+        assert_eq!(
+            LayoutPoint::new(CONTENT_AREA_WIDTH - 50, 10 + 200 - 10 - 20),
+            abs.borrow().point()
+        );
+        // 絶対位置指定された要素は通常のフローから外れる
+        assert_eq!(LayoutPoint::new(0, 10), b.borrow().point());
+        assert_eq!(LayoutPoint::new(0, 0), fixed.borrow().point());
+        assert_eq!(
+            LayoutSize::new(CONTENT_AREA_WIDTH, 20),
+            fixed.borrow().size()
+        );
+    }
+
+    #[test]
+    fn test_z_index_paint_order() {
+        let html = r#"<html>
+        <head>
+        <style>
+        #front {
+          position: absolute;
+          top: 0px;
+          left: 0px;
+          z-index: 2;
+        }
+        #back {
+          position: absolute;
+          top: 0px;
+          left: 0px;
+          z-index: -1;
+        }
+        #middle {
+          position: relative;
+        }
+        </style>
+        </head>
+        <body><p id="front">a</p><p id="middle">b</p><p id="back">c</p><p>d</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let order: Vec<String> = layout_view
+            .paint_order()
+            .iter()
+            .filter_map(|n| match n.borrow().node_kind() {
+                NodeKind::Element(e) => e.get_attribute("id"),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "back".to_string(),
+                "middle".to_string(),
+                "front".to_string()
+            ],
+            order
+        );
+
+        // 最も手前に描画されている要素が選ばれる
+        let hit = layout_view
+            .find_node_by_position((1, 1))
+            .expect("node should exist");
+        let front = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("front should exist");
+        let hit_parent = hit
+            .borrow()
+            .parent()
+            .upgrade()
+            .expect("parent should exist");
+        assert!(Rc::ptr_eq(&front, &hit_parent));
+    }
+
+    #[test]
+    fn test_float_shortens_lines() {
+        let html = r#"<html>
+        <head>
+        <style>
+        #float {
+          float: left;
+          width: 100px;
+          height: 50px;
+        }
+        </style>
+        </head>
+        <body><div id="float"></div><p>aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa aaaaaaaaa</p><p>next</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let float = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("float should exist");
+        let p = float.borrow().next_sibling().expect("p should exist");
+        let next = p.borrow().next_sibling().expect("next should exist");
+        let text = p.borrow().first_child().expect("text should exist");
+
+        assert_eq!(LayoutPoint::new(0, 0), float.borrow().point());
+        assert_eq!(LayoutSize::new(100, 50), float.borrow().size());
+        // フロートは後続のブロック要素の位置に影響しない
+        assert_eq!(LayoutPoint::new(0, 0), p.borrow().point());
+
+        // フロートと重なる行はフロートの右から始まり、それ以降の行は左端から始まる
+        let lines = text.borrow().line_boxes();
+        assert!(!lines.is_empty());
+        for line in &lines {
+            let expected_x = if line.point().y() < 50 { 100 } else { 0 };
+            assert_eq!(expected_x, line.point().x());
+            assert!(line.point().x() + line.text().len() as i64 * CHAR_WIDTH <= CONTENT_AREA_WIDTH);
+        }
+        assert_eq!(
+            lines.len() as i64 * CHAR_HEIGHT_WITH_PADDING,
+            p.borrow().size().height()
+        );
+        assert_eq!(p.borrow().size().height(), next.borrow().point().y());
+    }
+
+    #[test]
+    fn test_float_right_and_clear() {
+        let html = r#"<html>
+        <head>
+        <style>
+        #left {
+          float: left;
+          width: 100px;
+          height: 50px;
+        }
+        #right {
+          float: right;
+          width: 100px;
+          height: 80px;
+        }
+        #cleared {
+          clear: both;
+        }
+        </style>
+        </head>
+        <body><div id="left"></div><div id="right"></div><p>a</p><p id="cleared">b</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root().expect("root should exist");
+        let left = root.borrow().first_child().expect("left should exist");
+        let right = left.borrow().next_sibling().expect("right should exist");
+        let a = right.borrow().next_sibling().expect("a should exist");
+        let cleared = a.borrow().next_sibling().expect("cleared should exist");
+
+        assert_eq!(LayoutPoint::new(0, 0), left.borrow().point());
+        assert_eq!(
+            LayoutPoint::new(CONTENT_AREA_WIDTH - 100, 0),
+            right.borrow().point()
+        );
+        assert_eq!(LayoutPoint::new(0, 0), a.borrow().point());
+        // clearが指定された要素は両方のフロートの下に置かれる
+        assert_eq!(LayoutPoint::new(0, 80), cleared.borrow().point());
+        assert_eq!(80 + 20, root.borrow().size().height());
+    }
+
+    #[test]
+    fn test_formatting_context_contains_floats() {
+        let html = r#"<html>
+        <head>
+        <style>
+        .float {
+          float: left;
+          width: 100px;
+          height: 50px;
+        }
+        .root {
+          display: flow-root;
+        }
+        </style>
+        </head>
+        <body><div class="root"><div class="float"></div></div><div><div class="float"></div></div><p>a</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let root = layout_view.root().expect("root should exist");
+        let flow_root = root.borrow().first_child().expect("flow root should exist");
+        let block = flow_root
+            .borrow()
+            .next_sibling()
+            .expect("block should exist");
+        let p = block.borrow().next_sibling().expect("p should exist");
+        let inner = block
+            .borrow()
+            .first_child()
+            .expect("inner float should exist");
+
+        // flow-rootはフロートを含むように高さが伸びる
+        assert_eq!(50, flow_root.borrow().size().height());
+        // 通常のブロック要素の高さはフロートを含まない
+        assert_eq!(LayoutPoint::new(0, 50), block.borrow().point());
+        assert_eq!(0, block.borrow().size().height());
+        // 同じブロック整形コンテキストの中のフロートは、後続のテキストを押しのける
+        assert_eq!(LayoutPoint::new(0, 50), inner.borrow().point());
+        let text = p.borrow().first_child().expect("text should exist");
+        assert_eq!(
+            LayoutPoint::new(100, 50),
+            text.borrow().line_boxes()[0].point()
+        );
+        // ルート要素は全てのフロートを含む
+        assert_eq!(100, root.borrow().size().height());
+    }
+
+    #[test]
+    fn test_table_auto_layout() {
+        let html = r#"<html>
+        <head>
+        <style>
+        table {
+          border-spacing: 2px;
+        }
+        #middle {
+          vertical-align: middle;
+        }
+        </style>
+        </head>
+        <body><table><tr><td>aa</td><td colspan=2>b</td></tr><tr><td id="middle" rowspan=2>c</td><td>dd</td><td>e</td></tr><tr><td>f</td><td>g</td></tr></table></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let table = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("table should exist");
+        assert_eq!(LayoutSize::new(48, 68), table.borrow().size());
+
+        let row1 = table.borrow().first_child().expect("row should exist");
+        let row2 = row1.borrow().next_sibling().expect("row should exist");
+        let row3 = row2.borrow().next_sibling().expect("row should exist");
+        assert_eq!(LayoutPoint::new(2, 24), row2.borrow().point());
+        assert_eq!(LayoutSize::new(44, 20), row2.borrow().size());
+
+        let aa = row1.borrow().first_child().expect("cell should exist");
+        let b = aa.borrow().next_sibling().expect("cell should exist");
+        assert_eq!(LayoutPoint::new(2, 2), aa.borrow().point());
+        assert_eq!(LayoutSize::new(16, 20), aa.borrow().size());
+        assert_eq!(LayoutPoint::new(20, 2), b.borrow().point());
+        assert_eq!(LayoutSize::new(26, 20), b.borrow().size());
+
+        // rowspanを持つセルは2行分の高さになり、内容は縦方向の中央に置かれる
+        let c = row2.borrow().first_child().expect("cell should exist");
+        assert_eq!(LayoutPoint::new(2, 24), c.borrow().point());
+        assert_eq!(LayoutSize::new(16, 42), c.borrow().size());
+        let c_text = c.borrow().first_child().expect("text should exist");
+        assert_eq!(LayoutPoint::new(2, 35), c_text.borrow().point());
+
+        // 3行目のセルはrowspanで埋まっている1列目を飛ばして配置される
+        let f = row3.borrow().first_child().expect("cell should exist");
+        let g = f.borrow().next_sibling().expect("cell should exist");
+        assert_eq!(LayoutPoint::new(20, 46), f.borrow().point());
+        assert_eq!(LayoutPoint::new(38, 46), g.borrow().point());
+    }
+
+    #[test]
+    fn test_table_fixed_layout() {
+        let html = r#"<html>
+        <head>
+        <style>
+        table {
+          table-layout: fixed;
+          width: 300px;
+          border-spacing: 4px;
+          border-collapse: collapse;
+        }
+        #first {
+          width: 100px;
+        }
+        </style>
+        </head>
+        <body><table><tbody><tr><td id="first">a</td><td>b</td><td>c</td></tr></tbody></table><p>after</p></body>
+        </html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let table = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("table should exist");
+        let tbody = table.borrow().first_child().expect("tbody should exist");
+        let row = tbody.borrow().first_child().expect("row should exist");
+        let a = row.borrow().first_child().expect("cell should exist");
+        let b = a.borrow().next_sibling().expect("cell should exist");
+        let c = b.borrow().next_sibling().expect("cell should exist");
+
+        // border-collapse: collapseの場合、セルの間隔はなくなる
+        assert_eq!(LayoutSize::new(300, 20), table.borrow().size());
+        assert_eq!(LayoutSize::new(300, 20), tbody.borrow().size());
+        assert_eq!(LayoutSize::new(100, 20), a.borrow().size());
+        assert_eq!(LayoutPoint::new(100, 0), b.borrow().point());
+        assert_eq!(LayoutSize::new(100, 20), b.borrow().size());
+        assert_eq!(LayoutPoint::new(200, 0), c.borrow().point());
+
+        let after = table.borrow().next_sibling().expect("p should exist");
+        assert_eq!(LayoutPoint::new(0, 20), after.borrow().point());
+    }
+
+    const INCREMENTAL_HTML: &str = r#"<html>
+        <head>
+        <style>
+        .hidden {
+          display: none;
+        }
+        .wide {
+          width: 200px;
+        }
+        </style>
+        </head>
+        <body>
+          <div id="first"><p>first</p></div>
+          <div id="second"><p id="text">second</p></div>
+          <div id="third"><p>third</p></div>
+        </body>
+        </html>"#;
+
+    #[test]
+    fn test_relayout_text_change() {
+        let (dom, cssom) = create_dom(INCREMENTAL_HTML.to_string());
+        let mut layout_view = LayoutView::new(dom.clone(), &cssom);
+        let root = layout_view.root().expect("root should exist");
+        let first = root.borrow().first_child().expect("first should exist");
+        let second = first.borrow().next_sibling().expect("second should exist");
+
+        set_text_content(
+            &element(&dom, "text"),
+            "a long text that does not fit in one line of the content area of the window",
+        );
+        layout_view.relayout(dom.clone(), &cssom);
+
+        // 変更されていないノードはそのまま使われる
+        let new_root = layout_view.root().expect("root should exist");
+        assert!(Rc::ptr_eq(&root, &new_root));
+        let new_first = new_root.borrow().first_child().expect("first should exist");
+        assert!(Rc::ptr_eq(&first, &new_first));
+        let new_second = new_first
+            .borrow()
+            .next_sibling()
+            .expect("second should exist");
+        assert!(Rc::ptr_eq(&second, &new_second));
+
+        // 最初から作り直したレイアウトと同じ結果になる
+        assert_eq!(
+            LayoutView::new(dom.clone(), &cssom).paint(),
+            layout_view.paint()
+        );
+        assert!(!dom.borrow().is_child_dirty());
+    }
+
+    #[test]
+    fn test_relayout_attribute_change() {
+        let (dom, cssom) = create_dom(INCREMENTAL_HTML.to_string());
+        let mut layout_view = LayoutView::new(dom.clone(), &cssom);
+        let root = layout_view.root().expect("root should exist");
+        let first = root.borrow().first_child().expect("first should exist");
+
+        set_attribute(&element(&dom, "second"), "class", "hidden");
+        layout_view.relayout(dom.clone(), &cssom);
+        assert_eq!(
+            LayoutView::new(dom.clone(), &cssom).paint(),
+            layout_view.paint()
+        );
+        let new_first = root.borrow().first_child().expect("first should exist");
+        assert!(Rc::ptr_eq(&first, &new_first));
+        let third = new_first
+            .borrow()
+            .next_sibling()
+            .expect("third should exist");
+        assert_eq!(CHAR_HEIGHT_WITH_PADDING, third.borrow().point().y());
+
+        set_attribute(&element(&dom, "second"), "class", "wide");
+        layout_view.relayout(dom.clone(), &cssom);
+        assert_eq!(
+            LayoutView::new(dom.clone(), &cssom).paint(),
+            layout_view.paint()
+        );
+        let second = first.borrow().next_sibling().expect("second should exist");
+        assert_eq!(200, second.borrow().size().width());
+    }
+
+    #[test]
+    fn test_relayout_insert_and_remove() {
+        let (dom, cssom) = create_dom(INCREMENTAL_HTML.to_string());
+        let mut layout_view = LayoutView::new(dom.clone(), &cssom);
+
+        let body = element(&dom, "first")
+            .borrow()
+            .parent()
+            .upgrade()
+            .expect("body should exist");
+        let p = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            "p",
+            Vec::new(),
+        )))));
+        append_child(&body, &p);
+        set_text_content(&p, "fourth");
+        layout_view.relayout(dom.clone(), &cssom);
+        assert_eq!(
+            LayoutView::new(dom.clone(), &cssom).paint(),
+            layout_view.paint()
+        );
+
+        remove_child(&body, &element(&dom, "first"));
+        layout_view.relayout(dom.clone(), &cssom);
+        assert_eq!(
+            LayoutView::new(dom.clone(), &cssom).paint(),
+            layout_view.paint()
+        );
+
+        let root = layout_view.root().expect("root should exist");
+        let second = root.borrow().first_child().expect("second should exist");
+        assert_eq!(0, second.borrow().point().y());
+        assert!(!dom.borrow().is_child_dirty());
+    }
 }
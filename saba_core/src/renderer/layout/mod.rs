@@ -1,3 +1,6 @@
 pub mod computed_style;
+pub mod float;
+pub mod grid;
 pub mod layout_object;
 pub mod layout_view;
+pub mod table;
@@ -0,0 +1,395 @@
+use alloc::{vec, vec::Vec};
+
+use crate::renderer::layout::layout_object::{LayoutPoint, LayoutSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// セルが置かれる行と列、またがる行数と列数
+pub struct TableCellPlacement {
+    row: usize,
+    column: usize,
+    row_span: usize,
+    column_span: usize,
+}
+
+impl TableCellPlacement {
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn row_span(&self) -> usize {
+        self.row_span
+    }
+
+    pub fn column_span(&self) -> usize {
+        self.column_span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// 表のセルの配置と、列の幅、行の高さを計算した結果
+pub struct TableLayout {
+    cells: Vec<TableCellPlacement>,
+    columns: Vec<i64>,
+    rows: Vec<i64>,
+    // セルの内容の高さ。vertical-alignに使う
+    content_heights: Vec<i64>,
+    // セルとセル、セルと表の端の間隔
+    spacing: i64,
+}
+
+impl TableLayout {
+    // rowsは各行のセルの(列数, 行数)のリスト
+    // rowspanによって上の行から伸びてきたセルがある位置は飛ばして配置する
+    pub fn place_cells(rows: &[Vec<(usize, usize)>], spacing: i64) -> Self {
+        let mut cells = Vec::new();
+        let mut occupied: Vec<Vec<bool>> = vec![Vec::new(); rows.len()];
+
+        for (row, spans) in rows.iter().enumerate() {
+            let mut column = 0;
+            for &(column_span, row_span) in spans {
+                while occupied[row].get(column).copied().unwrap_or(false) {
+                    column += 1;
+                }
+
+                // 表の最後の行を超えてまたぐことはできない
+                let row_span = row_span.max(1).min(rows.len() - row);
+                let column_span = column_span.max(1);
+                for occupied_row in occupied.iter_mut().skip(row).take(row_span) {
+                    if occupied_row.len() < column + column_span {
+                        occupied_row.resize(column + column_span, false);
+                    }
+                    for slot in occupied_row.iter_mut().skip(column).take(column_span) {
+                        *slot = true;
+                    }
+                }
+
+                cells.push(TableCellPlacement {
+                    row,
+                    column,
+                    row_span,
+                    column_span,
+                });
+                column += column_span;
+            }
+        }
+
+        let column_count = occupied.iter().map(|r| r.len()).max().unwrap_or(0);
+        Self {
+            cells,
+            columns: vec![0; column_count],
+            rows: vec![0; rows.len()],
+            content_heights: Vec::new(),
+            spacing,
+        }
+    }
+
+    pub fn cells(&self) -> &Vec<TableCellPlacement> {
+        &self.cells
+    }
+
+    pub fn columns(&self) -> &Vec<i64> {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &Vec<i64> {
+        &self.rows
+    }
+
+    pub fn spacing(&self) -> i64 {
+        self.spacing
+    }
+
+    // 列の数に応じた、全ての間隔の合計
+    fn total_spacing(count: usize, spacing: i64) -> i64 {
+        if count == 0 {
+            0
+        } else {
+            spacing * (count as i64 + 1)
+        }
+    }
+
+    // 自動レイアウトで、各セルの内容の最小幅と最大幅から列の幅を決める
+    // 表の幅が指定されていない場合、利用できる幅に収まる範囲で内容が折り返さない幅にする
+    pub fn resolve_columns_auto(
+        &mut self,
+        table_width: Option<i64>,
+        available_width: i64,
+        min_widths: &[i64],
+        max_widths: &[i64],
+    ) {
+        let count = self.columns.len();
+        let mut min_columns = vec![0; count];
+        let mut max_columns = vec![0; count];
+
+        // 1列のセルから先に決め、複数の列にまたがるセルは足りない分を均等に配る
+        let mut order: Vec<usize> = (0..self.cells.len()).collect();
+        order.sort_by_key(|&i| self.cells[i].column_span);
+        for i in order {
+            let cell = self.cells[i];
+            let range = cell.column..cell.column + cell.column_span;
+            let inner_spacing = self.spacing * (cell.column_span as i64 - 1);
+            distribute(
+                &mut min_columns[range.clone()],
+                min_widths[i] - inner_spacing,
+            );
+            distribute(&mut max_columns[range], max_widths[i] - inner_spacing);
+        }
+        for (max, min) in max_columns.iter_mut().zip(&min_columns) {
+            *max = (*max).max(*min);
+        }
+
+        let min_total: i64 = min_columns.iter().sum();
+        let max_total: i64 = max_columns.iter().sum();
+        let spacing = Self::total_spacing(count, self.spacing);
+        let width = match table_width {
+            Some(w) => (w - spacing).max(min_total),
+            None => max_total.min(available_width - spacing).max(min_total),
+        };
+
+        self.columns = if width <= max_total {
+            // 最小幅から、最大幅との差に比例して広げる
+            let range = max_total - min_total;
+            min_columns
+                .iter()
+                .zip(&max_columns)
+                .map(|(min, max)| {
+                    if range == 0 {
+                        *min
+                    } else {
+                        min + (max - min) * (width - min_total) / range
+                    }
+                })
+                .collect()
+        } else {
+            // 最大幅より広い場合、最大幅に比例して広げる
+            max_columns
+                .iter()
+                .map(|max| {
+                    if max_total == 0 {
+                        width / count as i64
+                    } else {
+                        max + (width - max_total) * max / max_total
+                    }
+                })
+                .collect()
+        };
+
+        // 端数は最後の列に足す
+        let rest = width - self.columns.iter().sum::<i64>();
+        if let Some(last) = self.columns.last_mut() {
+            *last += rest;
+        }
+    }
+
+    // 固定レイアウトで、表の幅と最初の行のセルに指定された幅から列の幅を決める
+    // first_row_widthsは最初の行の各セルに指定された幅
+    pub fn resolve_columns_fixed(&mut self, table_width: i64, first_row_widths: &[Option<i64>]) {
+        let count = self.columns.len();
+        let mut specified: Vec<Option<i64>> = vec![None; count];
+        for (cell, width) in self
+            .cells
+            .iter()
+            .filter(|c| c.row == 0)
+            .zip(first_row_widths)
+        {
+            if let Some(w) = width {
+                let inner_spacing = self.spacing * (cell.column_span as i64 - 1);
+                let per_column = (w - inner_spacing).max(0) / cell.column_span as i64;
+                for s in specified
+                    .iter_mut()
+                    .skip(cell.column)
+                    .take(cell.column_span)
+                {
+                    *s = Some(per_column);
+                }
+            }
+        }
+
+        // 幅が指定されていない列で残りの幅を均等に分ける
+        let width = table_width - Self::total_spacing(count, self.spacing);
+        let specified_total: i64 = specified.iter().flatten().sum();
+        let auto_count = specified.iter().filter(|s| s.is_none()).count() as i64;
+        let rest = (width - specified_total).max(0);
+        self.columns = specified
+            .iter()
+            .map(|s| match s {
+                Some(w) => *w,
+                None => rest / auto_count,
+            })
+            .collect();
+
+        if auto_count > 0 {
+            let remainder = rest - rest / auto_count * auto_count;
+            if let Some(i) = specified.iter().rposition(|s| s.is_none()) {
+                self.columns[i] += remainder;
+            }
+        }
+    }
+
+    // 各セルの内容の高さから行の高さを決める
+    // 複数の行にまたがるセルは足りない分をまたぐ行に均等に配る
+    pub fn resolve_rows(&mut self, heights: &[i64]) {
+        let mut rows = vec![0; self.rows.len()];
+        let mut order: Vec<usize> = (0..self.cells.len()).collect();
+        order.sort_by_key(|&i| self.cells[i].row_span);
+        for i in order {
+            let cell = self.cells[i];
+            let inner_spacing = self.spacing * (cell.row_span as i64 - 1);
+            distribute(
+                &mut rows[cell.row..cell.row + cell.row_span],
+                heights[i] - inner_spacing,
+            );
+        }
+        self.rows = rows;
+        self.content_heights = heights.to_vec();
+    }
+
+    pub fn width(&self) -> i64 {
+        self.columns.iter().sum::<i64>() + Self::total_spacing(self.columns.len(), self.spacing)
+    }
+
+    pub fn height(&self) -> i64 {
+        self.rows.iter().sum::<i64>() + Self::total_spacing(self.rows.len(), self.spacing)
+    }
+
+    // 表の上端から行の上端までの距離
+    pub fn row_offset(&self, row: usize) -> i64 {
+        self.spacing
+            + self.rows[..row]
+                .iter()
+                .map(|r| r + self.spacing)
+                .sum::<i64>()
+    }
+
+    fn column_offset(&self, column: usize) -> i64 {
+        self.spacing
+            + self.columns[..column]
+                .iter()
+                .map(|c| c + self.spacing)
+                .sum::<i64>()
+    }
+
+    // 表の左上からセルの左上までの距離
+    pub fn cell_offset(&self, i: usize) -> LayoutPoint {
+        let cell = self.cells[i];
+        LayoutPoint::new(self.column_offset(cell.column), self.row_offset(cell.row))
+    }
+
+    pub fn cell_width(&self, i: usize) -> i64 {
+        let cell = self.cells[i];
+        span_size(
+            &self.columns[cell.column..cell.column + cell.column_span],
+            self.spacing,
+        )
+    }
+
+    pub fn cell_size(&self, i: usize) -> LayoutSize {
+        let cell = self.cells[i];
+        LayoutSize::new(
+            self.cell_width(i),
+            span_size(&self.rows[cell.row..cell.row + cell.row_span], self.spacing),
+        )
+    }
+
+    pub fn content_height(&self, i: usize) -> i64 {
+        self.content_heights.get(i).copied().unwrap_or(0)
+    }
+}
+
+// 複数のトラックにまたがる大きさ。間の間隔も含む
+fn span_size(tracks: &[i64], spacing: i64) -> i64 {
+    tracks.iter().sum::<i64>() + spacing * (tracks.len() as i64 - 1).max(0)
+}
+
+// トラックの合計がsizeに足りない場合、足りない分を均等に配る
+fn distribute(tracks: &mut [i64], size: i64) {
+    let current: i64 = tracks.iter().sum();
+    if tracks.is_empty() || size <= current {
+        return;
+    }
+
+    let count = tracks.len() as i64;
+    let extra = size - current;
+    for t in tracks.iter_mut() {
+        *t += extra / count;
+    }
+    if let Some(last) = tracks.last_mut() {
+        *last += extra % count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_cells_with_spans() {
+        // | a | b b |
+        // | a | c | d |
+        let layout = TableLayout::place_cells(&[vec![(1, 2), (2, 1)], vec![(1, 1), (1, 1)]], 0);
+        let positions: Vec<(usize, usize)> = layout
+            .cells()
+            .iter()
+            .map(|c| (c.row(), c.column()))
+            .collect();
+        assert_eq!(vec![(0, 0), (0, 1), (1, 1), (1, 2)], positions);
+        assert_eq!(3, layout.columns().len());
+        assert_eq!(2, layout.rows().len());
+    }
+
+    #[test]
+    fn test_row_span_is_clamped() {
+        let layout = TableLayout::place_cells(&[vec![(1, 5)], vec![(1, 1)]], 0);
+        assert_eq!(2, layout.cells()[0].row_span());
+        assert_eq!(1, layout.cells()[1].column());
+    }
+
+    #[test]
+    fn test_resolve_columns_auto() {
+        let mut layout = TableLayout::place_cells(&[vec![(1, 1), (1, 1)]], 2);
+        // 内容が収まる場合は最大幅になる
+        layout.resolve_columns_auto(None, 500, &[10, 20], &[50, 100]);
+        assert_eq!(&vec![50, 100], layout.columns());
+        assert_eq!(156, layout.width());
+
+        // 収まらない場合は最小幅との差に比例して縮める
+        layout.resolve_columns_auto(None, 96, &[10, 20], &[50, 100]);
+        assert_eq!(&vec![30, 60], layout.columns());
+
+        // 表の幅が指定されている場合は最大幅に比例して広げる
+        layout.resolve_columns_auto(Some(306), 500, &[10, 20], &[50, 100]);
+        assert_eq!(&vec![100, 200], layout.columns());
+    }
+
+    #[test]
+    fn test_resolve_columns_auto_with_column_span() {
+        let mut layout = TableLayout::place_cells(&[vec![(2, 1)], vec![(1, 1), (1, 1)]], 0);
+        layout.resolve_columns_auto(None, 500, &[0, 0, 0], &[200, 40, 60]);
+        assert_eq!(&vec![90, 110], layout.columns());
+        assert_eq!(200, layout.cell_width(0));
+    }
+
+    #[test]
+    fn test_resolve_columns_fixed() {
+        let mut layout = TableLayout::place_cells(&[vec![(1, 1), (1, 1), (1, 1)]], 0);
+        layout.resolve_columns_fixed(300, &[Some(100), None, None]);
+        assert_eq!(&vec![100, 100, 100], layout.columns());
+
+        layout.resolve_columns_fixed(301, &[None, Some(50), None]);
+        assert_eq!(&vec![125, 50, 126], layout.columns());
+    }
+
+    #[test]
+    fn test_resolve_rows() {
+        let mut layout = TableLayout::place_cells(&[vec![(1, 2), (1, 1)], vec![(1, 1)]], 10);
+        layout.resolve_rows(&[100, 20, 30]);
+        assert_eq!(&vec![40, 50], layout.rows());
+        assert_eq!(LayoutSize::new(0, 100), layout.cell_size(0));
+        assert_eq!(LayoutPoint::new(20, 60), layout.cell_offset(2));
+        assert_eq!(40 + 50 + 30, layout.height());
+    }
+}
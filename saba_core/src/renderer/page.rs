@@ -1,26 +1,38 @@
 use core::cell::RefCell;
 
-use crate::renderer::dom::api::get_js_content;
 use crate::{
     browser::Browser,
     display_item::DisplayItem,
-    http::HttpResponse,
+    error::Error,
+    http::{HttpRequest, HttpResponse, Transport},
     renderer::{
         css::{
             cssom::{CssParser, StyleSheet},
             token::CssTokenizer,
         },
         dom::{
-            api::get_style_content,
-            node::{ElementKind, NodeKind},
+            api::{get_style_content, get_target_element_node, get_text_content},
+            node::{ElementKind, Node, NodeKind},
+        },
+        js::{
+            ast::JsParser,
+            console::{ConsoleLevel, ConsoleListener, ConsoleMessage, INLINE_SOURCE},
+            event::{dispatch_event, new_event},
+            event_loop::{
+                perform_microtask_checkpoint, run_animation_frames, run_tasks, Clock, ManualClock,
+            },
+            runtime::{JsException, JsRuntime, RuntimeValue},
+            token::{JsLexer, Position},
         },
-        js::{ast::JsParser, runtime::JsRuntime, token::JsLexer},
         layout::layout_view::LayoutView,
     },
+    url,
 };
 use alloc::{
+    collections::VecDeque,
+    format,
     rc::{Rc, Weak},
-    string::String,
+    string::{String, ToString},
     vec::Vec,
 };
 
@@ -36,6 +48,44 @@ pub struct Page {
     style: Option<StyleSheet>,
     layout_view: Option<LayoutView>,
     display_items: Vec<DisplayItem>,
+    // 捕捉されなかったJavaScriptのエラー
+    js_errors: Vec<String>,
+    // スクリプトがconsoleに出力したメッセージと、捕捉されなかったエラー
+    console_messages: Vec<ConsoleMessage>,
+    // consoleのメッセージを受け取る埋め込み側のリスナー
+    console_listener: Option<Rc<dyn ConsoleListener>>,
+    // ページのスクリプトを実行したランタイム。イベントリスナーを呼び出すために保持する
+    runtime: Option<JsRuntime>,
+    // タイマーとrequestAnimationFrameで使う時計
+    clock: Rc<dyn Clock>,
+    // ページの移動とスクリプトのリクエストで使うTransport
+    transport: Option<Rc<dyn Transport>>,
+    // 表示しているドキュメントのURL
+    url: Option<String>,
+    // 読み込みが終わり、実行を待っているasync属性のスクリプト
+    async_scripts: VecDeque<PendingScript>,
+    // loadイベントを送ったか
+    loaded: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// script要素のtype属性で決まるスクリプトの種類
+enum ScriptKind {
+    Classic,
+    Module,
+}
+
+#[derive(Debug, Clone)]
+// 実行を待っているスクリプト
+struct PendingScript {
+    element: Rc<RefCell<Node>>,
+    kind: ScriptKind,
+    // src属性で読み込んだスクリプトか
+    external: bool,
+    // スクリプトのURL。インラインのスクリプトの場合はドキュメントのURL
+    url: String,
+    // 読み込みに失敗した場合はNone
+    source: Option<String>,
 }
 
 impl Page {
@@ -46,6 +96,15 @@ impl Page {
             style: None,
             layout_view: None,
             display_items: Vec::new(),
+            js_errors: Vec::new(),
+            console_messages: Vec::new(),
+            console_listener: None,
+            runtime: None,
+            clock: Rc::new(ManualClock::new()),
+            transport: None,
+            url: None,
+            async_scripts: VecDeque::new(),
+            loaded: false,
         }
     }
 
@@ -53,26 +112,251 @@ impl Page {
         self.browser = browser;
     }
 
-    pub fn receive_response(&mut self, response: HttpResponse) {
-        self.create_frame(response.body());
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        if let Some(runtime) = &mut self.runtime {
+            runtime.event_loop().set_clock(clock.clone());
+        }
+        self.clock = clock;
+    }
+
+    pub fn set_transport(&mut self, transport: Rc<dyn Transport>) {
+        if let Some(runtime) = &mut self.runtime {
+            runtime.set_transport(transport.clone());
+        }
+        self.transport = Some(transport);
+    }
+
+    // consoleのメッセージが出力されるたびにlistenerを呼び出す
+    pub fn set_console_listener(&mut self, listener: Rc<dyn ConsoleListener>) {
+        self.console_listener = Some(listener);
+    }
+
+    pub fn url(&self) -> Option<String> {
+        self.url.clone()
+    }
 
-        self.execute_js();
+    // Transportでurlのレスポンスを取得してページを表示する
+    pub fn navigate(&mut self, url: String) -> Result<(), Error> {
+        self.navigate_request(HttpRequest::get(url))
+    }
+
+    // フォームの送信のように、GET以外のリクエストのレスポンスでページを表示する
+    pub fn navigate_request(&mut self, request: HttpRequest) -> Result<(), Error> {
+        let transport = match &self.transport {
+            Some(transport) => transport.clone(),
+            None => return Err(Error::Network("no transport is set".to_string())),
+        };
+        let response = transport.send(&request)?;
+        self.url = Some(request.url());
+        self.receive_response(response);
+        Ok(())
+    }
+
+    pub fn receive_response(&mut self, response: HttpResponse) {
+        self.load_document(response.body());
 
         self.set_layout_view();
         self.paint_tree();
     }
 
-    fn create_frame(&mut self, html: String) {
-        let html_tokenizer = HtmlTokenizer::new(html);
-        let frame = HtmlParser::new(html_tokenizer).construct_tree();
+    // HTMLを解析しながらスクリプトを実行する
+    // 解析が終わるとdeferのスクリプトを実行してDOMContentLoadedを送り、asyncのスクリプトがなければloadを送る
+    fn load_document(&mut self, html: String) {
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html));
+        let frame = parser.window();
         let dom = frame.borrow().document();
+        self.frame = Some(frame);
+        self.style = None;
+        self.layout_view = None;
+        self.js_errors.clear();
+        self.console_messages.clear();
+        self.async_scripts.clear();
+        self.loaded = false;
+        self.create_runtime(dom.clone());
+        self.set_ready_state("loading");
 
-        let style = get_style_content(dom);
+        let mut deferred = Vec::new();
+        while let Some(element) = parser.parse_until_script() {
+            self.prepare_script(element, &mut deferred);
+        }
+
+        let style = get_style_content(dom.clone());
         let css_tokenizer = CssTokenizer::new(style);
-        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
+        self.style = Some(CssParser::new(css_tokenizer).parse_stylesheet());
 
-        self.frame = Some(frame);
-        self.style = Some(cssom);
+        self.set_ready_state("interactive");
+        for script in deferred {
+            self.execute_script(script);
+        }
+        self.fire_event("DOMContentLoaded", &dom, true);
+        self.finish_loading();
+    }
+
+    fn create_runtime(&mut self, dom: Rc<RefCell<Node>>) {
+        let mut runtime = JsRuntime::new(dom);
+        runtime.event_loop().set_clock(self.clock.clone());
+        if let Some(transport) = &self.transport {
+            runtime.set_transport(transport.clone());
+        }
+        if let Some(url) = &self.url {
+            runtime.set_document_url(url.clone());
+        }
+        self.runtime = Some(runtime);
+    }
+
+    // document.readyStateを設定する
+    fn set_ready_state(&mut self, state: &str) {
+        if let Some(runtime) = &mut self.runtime {
+            let root = runtime.dom_root();
+            let document = runtime.wrap_node(&root);
+            document.set_property("readyState", RuntimeValue::StringLiteral(state.to_string()));
+        }
+    }
+
+    // asyncのスクリプトがすべて実行されていれば、documentにloadイベントを送る
+    // このブラウザにはWindowオブジェクトがないため、loadはdocumentに送る
+    fn finish_loading(&mut self) {
+        if self.loaded || !self.async_scripts.is_empty() {
+            return;
+        }
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
+        self.loaded = true;
+        self.set_ready_state("complete");
+        self.fire_event("load", &dom, false);
+    }
+
+    // 解析中に閉じたscript要素を、属性に従ってすぐに実行するか、後で実行するために保存する
+    fn prepare_script(&mut self, element: Rc<RefCell<Node>>, deferred: &mut Vec<PendingScript>) {
+        let e = match element.borrow().get_element() {
+            Some(e) => e,
+            None => return,
+        };
+        let kind = match script_kind(e.get_attribute("type")) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let is_async = e.get_attribute("async").is_some();
+        let is_defer = e.get_attribute("defer").is_some();
+
+        let base = self
+            .url
+            .clone()
+            .unwrap_or_else(|| "about:blank".to_string());
+        let src = e.get_attribute("src");
+        let url = match &src {
+            Some(src) => url::resolve(&base, src).ok(),
+            None => Some(base),
+        };
+        let script = PendingScript {
+            element: element.clone(),
+            kind,
+            external: src.is_some(),
+            source: match (&src, &url) {
+                (Some(_), Some(url)) => self.load_script(url),
+                (Some(_), None) => None,
+                (None, _) => Some(get_text_content(&element)),
+            },
+            url: url.unwrap_or_default(),
+        };
+
+        // インラインのクラシックスクリプトでは、asyncとdeferは無視される
+        if is_async && (script.external || kind == ScriptKind::Module) {
+            self.async_scripts.push_back(script);
+        } else if kind == ScriptKind::Module || (is_defer && script.external) {
+            deferred.push(script);
+        } else {
+            // 解析を止めて実行する
+            self.execute_script(script);
+        }
+    }
+
+    // src属性のスクリプトを読み込む。2xx以外のステータスは失敗とする
+    fn load_script(&self, url: &str) -> Option<String> {
+        let response = self.transport.as_ref()?.fetch(url.to_string()).ok()?;
+        if (200..300).contains(&response.status_code()) {
+            Some(response.body())
+        } else {
+            None
+        }
+    }
+
+    fn execute_script(&mut self, script: PendingScript) {
+        let source = match script.source {
+            Some(source) => source,
+            None => {
+                self.fire_event("error", &script.element, false);
+                return;
+            }
+        };
+
+        // consoleのメッセージに、外部スクリプトはURLを、インラインのスクリプトは"inline"を記録する
+        if let Some(runtime) = &mut self.runtime {
+            let url = if script.external {
+                script.url.as_str()
+            } else {
+                INLINE_SOURCE
+            };
+            runtime.set_source(Rc::from(url));
+        }
+        match script.kind {
+            ScriptKind::Classic => self.run_classic_script(&script.element, source),
+            ScriptKind::Module => self.run_module_script(&script.url, source),
+        }
+        if script.external {
+            self.fire_event("load", &script.element, false);
+        }
+    }
+
+    // 実行中はdocument.currentScriptがscript要素になる
+    fn run_classic_script(&mut self, element: &Rc<RefCell<Node>>, source: String) {
+        let program = match JsParser::new(JsLexer::new(source)).parse_ast() {
+            Ok(program) => program,
+            Err(e) => {
+                // 構文エラーがある場合はスクリプトを実行しない
+                self.record_error(
+                    format!("Uncaught {}", e),
+                    format!("Uncaught SyntaxError: {}", e.message()),
+                    Some(e.position()),
+                );
+                return;
+            }
+        };
+
+        let runtime = match &mut self.runtime {
+            Some(runtime) => runtime,
+            None => return,
+        };
+        let root = runtime.dom_root();
+        let document = runtime.wrap_node(&root);
+        let current_script = runtime.wrap_node(element);
+        document.set_property("currentScript", current_script);
+        let result = runtime.execute(&program);
+        document.set_property("currentScript", RuntimeValue::Null);
+        if let Err(e) = result {
+            self.record_exception(e);
+        }
+        if let Some(runtime) = &mut self.runtime {
+            perform_microtask_checkpoint(runtime);
+        }
+        self.take_reported_exceptions();
+    }
+
+    // importしたモジュールは、モジュールのURLを基準にして読み込む
+    fn run_module_script(&mut self, url: &str, source: String) {
+        let runtime = match &mut self.runtime {
+            Some(runtime) => runtime,
+            None => return,
+        };
+        if let Err(e) = runtime.run_module(url, source) {
+            self.record_exception(e);
+        }
+        if let Some(runtime) = &mut self.runtime {
+            perform_microtask_checkpoint(runtime);
+        }
+        self.take_reported_exceptions();
     }
 
     fn set_layout_view(&mut self) {
@@ -91,6 +375,26 @@ impl Page {
         self.layout_view = Some(layout_view);
     }
 
+    // DOMの変更を反映し、変更された部分だけレイアウトを計算し直して描画する
+    pub fn relayout(&mut self) {
+        let dom = match &self.frame {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
+
+        let style = match self.style.clone() {
+            Some(style) => style,
+            None => return,
+        };
+
+        match &mut self.layout_view {
+            Some(layout_view) => layout_view.relayout(dom, &style),
+            None => self.layout_view = Some(LayoutView::new(dom, &style)),
+        }
+
+        self.paint_tree();
+    }
+
     fn paint_tree(&mut self) {
         if let Some(layout_view) = &self.layout_view {
             self.display_items = layout_view.paint();
@@ -105,38 +409,511 @@ impl Page {
         self.display_items = Vec::new();
     }
 
-    pub fn clicked(&self, position: (i64, i64)) -> Option<String> {
+    // クリックされたノードにclickイベントを送る。キャンセルされなかった場合、リンクであれば移動先のURLを返す
+    pub fn clicked(&mut self, position: (i64, i64)) -> Option<String> {
         let view = match &self.layout_view {
             Some(v) => v,
             None => return None,
         };
+        let mut target = view.find_node_by_position(position)?.borrow().node();
+        // テキストノードがクリックされた場合は、それを含む一番近い要素をイベントの対象にする
+        while !matches!(target.borrow().kind(), NodeKind::Element(_)) {
+            let parent = target.borrow().parent().upgrade()?;
+            target = parent;
+        }
+
+        let not_canceled = self.dispatch("click", &target, |event| {
+            event.set_property("clientX", RuntimeValue::Number(position.0 as f64));
+            event.set_property("clientY", RuntimeValue::Number(position.1 as f64));
+            event.set_property("button", RuntimeValue::Number(0.0));
+        });
+        if !not_canceled {
+            return None;
+        }
 
-        if let Some(n) = view.find_node_by_position(position) {
-            if let Some(parent) = n.borrow().parent().upgrade() {
-                if let NodeKind::Element(e) = parent.borrow().node_kind() {
-                    if e.kind() == ElementKind::A {
-                        return e.get_attribute("href");
-                    }
+        // デフォルトの動作として、クリックされたノードを含むリンクへ移動する
+        let mut node = Some(target);
+        while let Some(n) = node {
+            if let NodeKind::Element(e) = n.borrow().kind() {
+                if e.kind() == ElementKind::A {
+                    return e.get_attribute("href");
                 }
             }
+            node = n.borrow().parent().upgrade();
         }
 
         None
     }
 
-    fn execute_js(&mut self) {
+    // 押されたキーのkeydownイベントをbody要素に送る
+    pub fn key_pressed(&mut self, key: char) {
         let dom = match &self.frame {
             Some(frame) => frame.borrow().document(),
             None => return,
         };
+        let target = get_target_element_node(Some(dom.clone()), ElementKind::Body).unwrap_or(dom);
 
-        let js = get_js_content(dom.clone());
-        let lexer = JsLexer::new(js);
+        let key = match key as u32 {
+            0x0A => "Enter".to_string(),
+            0x08 | 0x7F => "Backspace".to_string(),
+            _ => key.to_string(),
+        };
+        self.dispatch("keydown", &target, |event| {
+            event.set_property("key", RuntimeValue::StringLiteral(key));
+        });
+    }
 
-        let mut parser = JsParser::new(lexer);
-        let ast = parser.parse_ast();
+    // イベントを作ってtargetに送り、DOMが変更された場合は描画し直す。キャンセルされなかったかを返す
+    fn dispatch(
+        &mut self,
+        event_type: &str,
+        target: &Rc<RefCell<Node>>,
+        init: impl FnOnce(&RuntimeValue),
+    ) -> bool {
+        let not_canceled = self.send_event(event_type, target, true, true, init);
+        self.relayout();
+        not_canceled
+    }
 
-        let mut runtime = JsRuntime::new(dom);
-        runtime.execute(&ast);
+    // 読み込み中のイベントを送る。描画は読み込みが終わってから行う
+    fn fire_event(&mut self, event_type: &str, target: &Rc<RefCell<Node>>, bubbles: bool) {
+        self.send_event(event_type, target, bubbles, false, |_| {});
+    }
+
+    fn send_event(
+        &mut self,
+        event_type: &str,
+        target: &Rc<RefCell<Node>>,
+        bubbles: bool,
+        cancelable: bool,
+        init: impl FnOnce(&RuntimeValue),
+    ) -> bool {
+        let runtime = match &mut self.runtime {
+            Some(runtime) => runtime,
+            None => return true,
+        };
+
+        let event = new_event(runtime, event_type, bubbles, cancelable);
+        init(&event);
+        let not_canceled = dispatch_event(runtime, target, &event);
+        perform_microtask_checkpoint(runtime);
+        self.take_reported_exceptions();
+        not_canceled
+    }
+
+    // イベントループを1回まわす。キューのタスクと時刻が来たタイマーを実行し、描画の前にrequestAnimationFrameのコールバックを呼び出す
+    // 何か実行した場合は描画し直してtrueを返す
+    pub fn run_event_loop(&mut self) -> bool {
+        // 読み込みが終わったasyncのスクリプトは、タスクとして実行する
+        let ran_scripts = !self.async_scripts.is_empty();
+        while let Some(script) = self.async_scripts.pop_front() {
+            self.execute_script(script);
+        }
+        if ran_scripts {
+            self.finish_loading();
+        }
+
+        let runtime = match &mut self.runtime {
+            Some(runtime) => runtime,
+            None => return ran_scripts,
+        };
+
+        let ran_tasks = run_tasks(runtime);
+        let ran_frames = run_animation_frames(runtime);
+        if !ran_scripts && !ran_tasks && !ran_frames {
+            return false;
+        }
+        self.take_reported_exceptions();
+
+        self.relayout();
+        true
+    }
+
+    // イベントリスナーやタイマーで捕捉されなかった例外をエラーとして記録する
+    // 報告された例外は、ランタイムがconsoleのメッセージとしても出力している
+    fn take_reported_exceptions(&mut self) {
+        self.take_console_messages();
+        if let Some(runtime) = &mut self.runtime {
+            for e in runtime.take_reported_exceptions() {
+                self.js_errors.push(e.to_string());
+            }
+        }
+    }
+
+    fn record_exception(&mut self, e: JsException) {
+        self.record_error(
+            e.to_string(),
+            format!("Uncaught {}", e.message()),
+            e.position(),
+        );
+    }
+
+    // スクリプトから返された例外を記録する。それまでに出力されたメッセージの後にconsoleへ出力する
+    fn record_error(&mut self, error: String, text: String, position: Option<Position>) {
+        self.take_console_messages();
+        self.js_errors.push(error);
+        let source = match &self.runtime {
+            Some(runtime) => runtime.source().to_string(),
+            None => INLINE_SOURCE.to_string(),
+        };
+        self.add_console_message(ConsoleMessage::new(
+            ConsoleLevel::Error,
+            text,
+            source,
+            position,
+        ));
+    }
+
+    fn take_console_messages(&mut self) {
+        let messages = match &mut self.runtime {
+            Some(runtime) => runtime.take_console_messages(),
+            None => return,
+        };
+        for message in messages {
+            self.add_console_message(message);
+        }
+    }
+
+    fn add_console_message(&mut self, message: ConsoleMessage) {
+        if let Some(listener) = &self.console_listener {
+            listener.message(&message);
+        }
+        self.console_messages.push(message);
+    }
+
+    pub fn js_errors(&self) -> Vec<String> {
+        self.js_errors.clone()
+    }
+
+    // 表示しているドキュメントのスクリプトがconsoleに出力したメッセージ
+    pub fn console_messages(&self) -> Vec<ConsoleMessage> {
+        self.console_messages.clone()
+    }
+}
+
+// type属性からスクリプトの種類を決める。JavaScriptでない場合はNone
+fn script_kind(script_type: Option<String>) -> Option<ScriptKind> {
+    let script_type = match script_type {
+        Some(t) => t.trim().to_ascii_lowercase(),
+        None => return Some(ScriptKind::Classic),
+    };
+    match script_type.as_str() {
+        ""
+        | "text/javascript"
+        | "application/javascript"
+        | "text/ecmascript"
+        | "application/ecmascript"
+        | "application/x-javascript" => Some(ScriptKind::Classic),
+        "module" => Some(ScriptKind::Module),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::FormField;
+
+    fn load(html: &str) -> Page {
+        load_with_clock(html, ManualClock::new())
+    }
+
+    fn load_with_clock(html: &str, clock: ManualClock) -> Page {
+        let mut page = Page::new();
+        page.set_clock(Rc::new(clock));
+        let raw = format!("HTTP/1.1 200 OK\nContent-Type: text/html\n\n{}", html);
+        page.receive_response(HttpResponse::new(raw).expect("failed to parse http response"));
+        page
+    }
+
+    #[test]
+    fn test_click_link() {
+        let mut page = load(
+            r#"<html><head></head><body><a href="http://example.com/">link</a></body></html>"#,
+        );
+        assert_eq!(
+            Some("http://example.com/".to_string()),
+            page.clicked((1, 1))
+        );
+        assert_eq!(None, page.clicked((500, 500)));
+    }
+
+    #[test]
+    fn test_click_prevented() {
+        let mut page = load(
+            r#"<html><head><script>var clicks = 0; document.addEventListener("click", function (e) { clicks = clicks + 1; e.target.textContent = e.target.tagName + " clicked " + clicks; e.preventDefault(); });</script></head><body><a href="http://example.com/">link</a></body></html>"#,
+        );
+        assert_eq!(None, page.clicked((1, 1)));
+        assert!(page.js_errors().is_empty());
+        // 変更されたDOMが描画される
+        assert_eq!(Vec::from(["A clicked 1".to_string()]), texts(&page));
+    }
+
+    #[test]
+    fn test_key_pressed() {
+        let mut page = load(
+            r#"<html><head></head><body onkeydown="document.body.textContent = event.key; undefined_function();"><p>text</p></body></html>"#,
+        );
+        page.key_pressed(0x0A as char);
+        assert_eq!(1, page.js_errors().len());
+        assert_eq!(Vec::from(["Enter".to_string()]), texts(&page));
+    }
+
+    fn texts(page: &Page) -> Vec<String> {
+        page.display_items()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_event_loop() {
+        let clock = ManualClock::new();
+        let mut page = load_with_clock(
+            r#"<html><head><script>var p; setTimeout(function () { p = document.getElementById("t"); p.textContent = "timeout"; requestAnimationFrame(function (time) { p.textContent = "frame " + time; }); }, 100);</script></head><body><p id="t">start</p></body></html>"#,
+            clock.clone(),
+        );
+        assert_eq!(Vec::from(["start".to_string()]), texts(&page));
+        assert!(!page.run_event_loop());
+
+        clock.advance(100);
+        assert!(page.run_event_loop());
+        assert_eq!(Vec::from(["frame 100".to_string()]), texts(&page));
+        assert!(!page.run_event_loop());
+    }
+
+    fn serve(url: String) -> Result<HttpResponse, Error> {
+        let raw = match url.as_str() {
+            "http://example.com/app/index.html" => {
+                r#"HTTP/1.1 200 OK
+Content-Type: text/html
+
+<html><head><script>fetch("data.txt").then(function (r) { return r.text(); }).then(function (t) { document.getElementById("t").textContent = t; });</script></head><body><p id="t">loading</p></body></html>"#
+            }
+            "http://example.com/app/data.txt" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/plain\n\nloaded"
+            }
+            "http://example.com/app/scripts.html" => {
+                r#"HTTP/1.1 200 OK
+Content-Type: text/html
+
+<html><head><script>var log = "inline " + document.readyState + " " + document.body; function record(e) { log = log + "," + e.type + " " + e.target.id; } document.addEventListener("DOMContentLoaded", function () { log = log + ",DOMContentLoaded " + document.readyState; }); document.addEventListener("load", function (e) { if (e.target === document) { log = log + ",load " + document.readyState; } });</script><script src="async.js" async></script><script src="defer.js" defer></script><script id="blocking" src="/js/blocking.js" onload="record(event)"></script><script type="module">import { state } from "./module.js"; log = log + ",module " + state;</script><script type="text/template">log = log + ",template";</script><script id="missing" src="missing.js" onerror="record(event)"></script></head><body><p id="t">text</p><script id="s">log = log + ",body " + document.getElementById("t").textContent + " " + document.currentScript.id;</script></body></html>"#
+            }
+            "http://example.com/app/async.js" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\nlog = log + \",async \" + document.readyState;"
+            }
+            "http://example.com/app/defer.js" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\nlog = log + \",defer \" + document.readyState;"
+            }
+            "http://example.com/js/blocking.js" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\nlog = log + \",blocking \" + document.getElementById(\"t\");"
+            }
+            "http://example.com/app/module.js" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\nexport const state = document.readyState;"
+            }
+            "http://example.com/app/console.html" => {
+                r#"HTTP/1.1 200 OK
+Content-Type: text/html
+
+<html><body><script>function hello(n) { console.log("inline " + n); }</script><script src="console.js"></script></body></html>"#
+            }
+            "http://example.com/app/console.js" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\nconsole.log(\"external\");\nhello(1);\nmissing();"
+            }
+            "http://example.com/app/calls.html" => {
+                r#"HTTP/1.1 200 OK
+Content-Type: text/html
+
+<html><body><script src="lib2.js"></script><script>console.log("x", outer());
+var v = 1; new v(outer());</script></body></html>"#
+            }
+            "http://example.com/app/lib2.js" => {
+                "HTTP/1.1 200 OK\nContent-Type: text/javascript\n\nfunction inner() { return 1; }\nfunction outer() { return inner(); }"
+            }
+            "http://example.com/app/missing.js" => {
+                "HTTP/1.1 404 Not Found\nContent-Type: text/plain\n\nnot found"
+            }
+            _ => return Err(Error::Network(format!("failed to connect to {}", url))),
+        };
+        HttpResponse::new(raw.to_string())
+    }
+
+    #[test]
+    fn test_navigate_and_fetch() {
+        let mut page = Page::new();
+        let transport: fn(String) -> Result<HttpResponse, Error> = serve;
+        page.set_transport(Rc::new(transport));
+        assert!(page
+            .navigate("http://example.com/missing".to_string())
+            .is_err());
+
+        page.navigate("http://example.com/app/index.html".to_string())
+            .expect("failed to navigate");
+        assert_eq!(
+            Some("http://example.com/app/index.html".to_string()),
+            page.url()
+        );
+        assert_eq!(Vec::from(["loading".to_string()]), texts(&page));
+        // 相対URLはドキュメントのURLを基準にして取得される
+        assert!(page.run_event_loop());
+        assert_eq!(Vec::from(["loaded".to_string()]), texts(&page));
+        assert!(page.js_errors().is_empty());
+    }
+
+    // 送られたフォームの本文をそのまま表示する
+    fn serve_form(request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let raw = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<html><body><p>{} {}</p></body></html>",
+            request.method(),
+            String::from_utf8_lossy(&request.body())
+        );
+        HttpResponse::new(raw)
+    }
+
+    #[test]
+    fn test_navigate_request() {
+        let fields = [FormField::text("q".to_string(), "saba browser".to_string())];
+        let request = HttpRequest::form("http://example.com/search", "post", "", &fields)
+            .expect("valid form");
+
+        let mut page = Page::new();
+        let transport: fn(String) -> Result<HttpResponse, Error> = serve;
+        page.set_transport(Rc::new(transport));
+        assert!(page.navigate_request(request.clone()).is_err());
+
+        let transport: fn(&HttpRequest) -> Result<HttpResponse, Error> = serve_form;
+        page.set_transport(Rc::new(transport));
+        page.navigate_request(request).expect("failed to submit");
+        assert_eq!(Some("http://example.com/search".to_string()), page.url());
+        assert_eq!(Vec::from(["POST q=saba+browser".to_string()]), texts(&page));
+    }
+
+    fn script_log(page: &Page) -> String {
+        match &page.runtime {
+            Some(runtime) => runtime
+                .lookup_global("log")
+                .expect("log should be defined")
+                .to_string(),
+            None => panic!("runtime should be created"),
+        }
+    }
+
+    #[test]
+    fn test_script_order() {
+        let mut page = Page::new();
+        let transport: fn(String) -> Result<HttpResponse, Error> = serve;
+        page.set_transport(Rc::new(transport));
+        page.navigate("http://example.com/app/scripts.html".to_string())
+            .expect("failed to navigate");
+
+        // 解析を止めるスクリプトはその時点までのDOMしか見えず、deferとモジュールは解析後に実行される
+        assert_eq!(
+            "inline loading null,blocking null,load blocking,error missing,body text s,defer interactive,module interactive,DOMContentLoaded interactive",
+            script_log(&page)
+        );
+        assert!(page.js_errors().is_empty());
+        assert_eq!(Vec::from(["text".to_string()]), texts(&page));
+
+        // asyncのスクリプトが実行されるまでloadは遅延される
+        assert!(page.run_event_loop());
+        assert_eq!(
+            "inline loading null,blocking null,load blocking,error missing,body text s,defer interactive,module interactive,DOMContentLoaded interactive,async interactive,load complete",
+            script_log(&page)
+        );
+    }
+
+    #[derive(Debug, Default)]
+    struct ConsoleRecorder {
+        messages: RefCell<Vec<String>>,
+    }
+
+    impl ConsoleListener for ConsoleRecorder {
+        fn message(&self, message: &ConsoleMessage) {
+            self.messages.borrow_mut().push(message.to_string());
+        }
+    }
+
+    #[test]
+    fn test_console_messages() {
+        let recorder = Rc::new(ConsoleRecorder::default());
+        let mut page = Page::new();
+        page.set_console_listener(recorder.clone());
+        let raw = "HTTP/1.1 200 OK\nContent-Type: text/html\n\n<html><body><script>console.log(\"start\", [1, \"a\"]);\nsetTimeout(function () { console.warn(\"timer\"); }, 0);\nmissing();</script><script>console.log(</script></body></html>";
+        page.receive_response(HttpResponse::new(raw.to_string()).expect("failed to parse"));
+
+        // 例外とその前に出力されたメッセージは、出力された順に記録される
+        let expected = Vec::from([
+            "[log] start [1, 'a'] (inline:1:1)".to_string(),
+            "[error] Uncaught ReferenceError: missing is not defined (inline:3:1)".to_string(),
+            "[error] Uncaught SyntaxError: unexpected end of input (inline:1:12)".to_string(),
+        ]);
+        let messages: Vec<String> = page
+            .console_messages()
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(expected, messages);
+        assert_eq!(expected, *recorder.messages.borrow());
+        assert_eq!(2, page.js_errors().len());
+
+        assert!(page.run_event_loop());
+        assert_eq!(
+            Some("[warn] timer (inline:2:26)".to_string()),
+            page.console_messages().last().map(|m| m.to_string())
+        );
+        assert_eq!(4, recorder.messages.borrow().len());
+    }
+
+    #[test]
+    fn test_console_sources() {
+        let mut page = Page::new();
+        let transport: fn(String) -> Result<HttpResponse, Error> = serve;
+        page.set_transport(Rc::new(transport));
+        page.navigate("http://example.com/app/console.html".to_string())
+            .expect("failed to navigate");
+
+        // 外部スクリプトはURLを、インラインのスクリプトで定義された関数は"inline"を記録する
+        let messages: Vec<String> = page
+            .console_messages()
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(
+            Vec::from([
+                "[log] external (http://example.com/app/console.js:1:1)".to_string(),
+                "[log] inline 1 (inline:1:21)".to_string(),
+                "[error] Uncaught ReferenceError: missing is not defined (http://example.com/app/console.js:3:1)".to_string(),
+            ]),
+            messages
+        );
+        assert_eq!(
+            Some("http://example.com/app/console.js".to_string()),
+            page.console_messages().first().map(|m| m.source())
+        );
+    }
+    #[test]
+    fn test_console_sources_across_scripts() {
+        let mut page = Page::new();
+        let transport: fn(String) -> Result<HttpResponse, Error> = serve;
+        page.set_transport(Rc::new(transport));
+        page.navigate("http://example.com/app/calls.html".to_string())
+            .expect("failed to navigate");
+
+        // 引数の中で別のスクリプトの関数を呼んだ後も、呼び出し元のスクリプトを記録する
+        let messages: Vec<String> = page
+            .console_messages()
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(
+            Vec::from([
+                "[log] x 1 (inline:1:1)".to_string(),
+                "[error] Uncaught TypeError: v is not a constructor (inline:2:12)".to_string(),
+            ]),
+            messages
+        );
     }
 }
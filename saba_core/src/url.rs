@@ -1,3 +1,4 @@
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -116,6 +117,79 @@ impl Url {
     }
 }
 
+// 相対URLをbaseのURLを基準にして絶対URLにする
+pub fn resolve(base: &str, reference: &str) -> Result<String, String> {
+    let reference = reference.trim();
+    // http:のようにスキームを持つURLは絶対URL
+    if let Some(index) = reference.find(':') {
+        let scheme = &reference[..index];
+        if !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Ok(reference.to_string());
+        }
+    }
+
+    let rest = match base.strip_prefix("http://") {
+        Some(rest) => rest,
+        None => return Err(format!("cannot resolve {} against {}", reference, base)),
+    };
+    // フラグメントは取り除く
+    let rest = rest.split('#').next().unwrap_or("");
+    let (authority, path_and_query) = match rest.find(['/', '?']) {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((p, q)) => (p, Some(q)),
+        None => (path_and_query, None),
+    };
+    let path = if path.is_empty() { "/" } else { path };
+
+    let resolved = if let Some(reference) = reference.strip_prefix("//") {
+        return Ok(format!("http://{}", reference));
+    } else if reference.is_empty() || reference.starts_with('#') {
+        match query {
+            Some(q) => format!("{}?{}{}", path, q, reference),
+            None => format!("{}{}", path, reference),
+        }
+    } else if reference.starts_with('?') {
+        format!("{}{}", path, reference)
+    } else if reference.starts_with('/') {
+        remove_dot_segments(reference)
+    } else {
+        // 最後の/までをディレクトリとして相対パスをつなげる
+        let directory = &path[..path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        remove_dot_segments(&format!("{}{}", directory, reference))
+    };
+    Ok(format!("http://{}{}", authority, resolved))
+}
+
+// パスの.と..を取り除く。クエリとフラグメントはそのまま残す
+fn remove_dot_segments(reference: &str) -> String {
+    let index = reference.find(['?', '#']).unwrap_or(reference.len());
+    let (path, suffix) = reference.split_at(index);
+
+    let mut segments: Vec<&str> = Vec::new();
+    let parts: Vec<&str> = path.split('/').skip(1).collect();
+    for (i, segment) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        match *segment {
+            "." => {
+                if is_last {
+                    segments.push("");
+                }
+            }
+            ".." => {
+                segments.pop();
+                if is_last {
+                    segments.push("");
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    format!("/{}{}", segments.join("/"), suffix)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +272,29 @@ mod tests {
         let expected = Err("Only HTTP scheme is supported.".to_string());
         assert_eq!(expected, Url::new(url).parse());
     }
+
+    #[test]
+    fn test_resolve() {
+        let base = "http://example.com:8888/docs/index.html?x=1#top";
+        for (reference, expected) in [
+            ("data.json", "http://example.com:8888/docs/data.json"),
+            ("./a/../b.txt?q", "http://example.com:8888/docs/b.txt?q"),
+            ("../up/", "http://example.com:8888/up/"),
+            ("/api/items", "http://example.com:8888/api/items"),
+            ("?y=2", "http://example.com:8888/docs/index.html?y=2"),
+            (
+                "#section",
+                "http://example.com:8888/docs/index.html?x=1#section",
+            ),
+            ("//other.com/p", "http://other.com/p"),
+            ("http://other.com/", "http://other.com/"),
+        ] {
+            assert_eq!(Ok(expected.to_string()), resolve(base, reference));
+        }
+        assert_eq!(
+            Ok("http://example.com/a".to_string()),
+            resolve("http://example.com", "a")
+        );
+        assert!(resolve("about:blank", "a").is_err());
+    }
 }
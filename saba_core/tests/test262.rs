@@ -0,0 +1,442 @@
+//! test262の形式のテストをJsLexer/JsParser/JsRuntimeで実行し、ディレクトリごとの通過率を表示する
+//! tests/test262/testはこのエンジンのために書いたテスト、tests/test262/upstreamはvendor.shで上流のtest262から取り込んだテスト
+//! strict modeがないため、テストはstrict modeではないコードとして1回だけ実行し、onlyStrictのテストはスキップする
+//! それぞれのbaseline.txtに記録されたテストが失敗した場合はテスト全体を失敗させる
+//! TEST262_UPDATE_BASELINE=1 cargo test --test test262 でbaseline.txtを現在の結果で書き換える
+//! TEST262_DIR=<test262のチェックアウト> を指定すると、その中のharnessとtestを実行して通過率だけを表示する
+//! TEST262_FILTER=built-ins/JSON のように指定すると、test/の下のそのディレクトリだけを実行する
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use saba_core::renderer::{
+    dom::node::{Node, NodeKind},
+    js::{
+        ast::JsParser,
+        builtins::{arg, native},
+        event_loop::{perform_microtask_checkpoint, run_tasks},
+        runtime::{JsException, JsRuntime, RuntimeValue},
+        token::JsLexer,
+    },
+};
+
+// 非同期のテストで、タスクを実行する回数の上限
+const MAX_TASK_TURNS: usize = 100;
+
+const ASYNC_COMPLETE: &str = "Test262:AsyncTestComplete";
+
+thread_local! {
+    // print()で出力された文字列。非同期のテストは$DONEがprintで結果を出力する
+    static PRINTED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Debug, Default)]
+struct Metadata {
+    flags: Vec<String>,
+    includes: Vec<String>,
+    negative: Option<Negative>,
+}
+
+impl Metadata {
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+#[derive(Debug)]
+struct Negative {
+    phase: String,
+    error_type: String,
+}
+
+// /*--- と ---*/ で囲まれたYAMLから、実行に必要なキーだけを読み取る
+fn parse_metadata(source: &str) -> Result<Metadata, String> {
+    let start = source.find("/*---").ok_or("missing frontmatter")? + "/*---".len();
+    let end = source[start..]
+        .find("---*/")
+        .ok_or("unterminated frontmatter")?
+        + start;
+
+    let mut metadata = Metadata::default();
+    let mut negative: BTreeMap<String, String> = BTreeMap::new();
+    let mut key = String::new();
+    for line in source[start..end].lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // インデントされた行は直前のキーの値の続き
+        if line.starts_with(' ') {
+            let item = line.trim();
+            match key.as_str() {
+                "flags" | "includes" => {
+                    if let Some(value) = item.strip_prefix("- ") {
+                        list_of(&key, &mut metadata).push(value.trim().to_string());
+                    }
+                }
+                "negative" => {
+                    if let Some((k, v)) = item.split_once(':') {
+                        negative.insert(k.trim().to_string(), v.trim().to_string());
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let (k, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("invalid frontmatter line: {}", line))?;
+        key = k.trim().to_string();
+        let value = value.trim();
+        if key == "flags" || key == "includes" {
+            if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                list_of(&key, &mut metadata).extend(
+                    items
+                        .split(',')
+                        .map(|item| item.trim().to_string())
+                        .filter(|item| !item.is_empty()),
+                );
+            }
+        }
+    }
+
+    if !negative.is_empty() {
+        let phase = negative.remove("phase").ok_or("negative without phase")?;
+        let error_type = negative.remove("type").ok_or("negative without type")?;
+        metadata.negative = Some(Negative { phase, error_type });
+    }
+    Ok(metadata)
+}
+
+fn list_of<'a>(key: &str, metadata: &'a mut Metadata) -> &'a mut Vec<String> {
+    if key == "flags" {
+        &mut metadata.flags
+    } else {
+        &mut metadata.includes
+    }
+}
+
+fn print(
+    _runtime: &mut JsRuntime,
+    _this: RuntimeValue,
+    args: Vec<RuntimeValue>,
+) -> Result<RuntimeValue, JsException> {
+    let message = arg(&args, 0).to_string();
+    PRINTED.with(|printed| printed.borrow_mut().push(message));
+    Ok(RuntimeValue::Undefined)
+}
+
+fn new_runtime() -> JsRuntime {
+    let mut runtime = JsRuntime::new(Rc::new(RefCell::new(Node::new(NodeKind::Document))));
+    runtime.define_global("print", native("print", print));
+    runtime
+}
+
+// Test262Errorはnameを持たず"[object Object]"と表示されるため、messageを表示する
+fn describe(exception: &JsException) -> String {
+    let value = exception.value();
+    let message = value.get_property("message");
+    if !value.is_object()
+        || value.get_property("name") != RuntimeValue::Undefined
+        || message == RuntimeValue::Undefined
+    {
+        return exception.to_string();
+    }
+    match exception.position() {
+        Some(position) => format!("Uncaught {} ({})", message, position),
+        None => format!("Uncaught {}", message),
+    }
+}
+
+fn run_script(runtime: &mut JsRuntime, name: &str, source: &str) -> Result<(), String> {
+    let program = JsParser::new(JsLexer::new(source.to_string()))
+        .parse_ast()
+        .map_err(|e| format!("{}: SyntaxError: {} ({})", name, e.message(), e.position()))?;
+    runtime
+        .execute(&program)
+        .map_err(|e| format!("{}: {}", name, describe(&e)))
+}
+
+// 例外がnegativeで指定された型のエラーかどうか
+fn is_error_type(runtime: &mut JsRuntime, exception: &JsException, error_type: &str) -> bool {
+    let constructor = match runtime.lookup_global(error_type) {
+        Ok(constructor) => constructor,
+        Err(_) => return false,
+    };
+    runtime
+        .instance_of(&exception.value(), &constructor)
+        .unwrap_or(false)
+}
+
+fn run_test(root: &Path, source: &str) -> Result<(), String> {
+    let metadata = parse_metadata(source)?;
+    let module = metadata.has_flag("module");
+
+    // 構文解析の段階で失敗するはずのテスト
+    let parsed = if module {
+        JsParser::new(JsLexer::new(source.to_string())).parse_module()
+    } else {
+        JsParser::new(JsLexer::new(source.to_string())).parse_ast()
+    };
+    match (&metadata.negative, parsed) {
+        (Some(negative), Ok(_)) if negative.phase == "parse" => {
+            return Err(format!(
+                "expected a {} while parsing, but parsing succeeded",
+                negative.error_type
+            ));
+        }
+        (Some(negative), Err(_)) if negative.phase == "parse" => {
+            return if negative.error_type == "SyntaxError" {
+                Ok(())
+            } else {
+                Err(format!("expected a {} while parsing", negative.error_type))
+            };
+        }
+        (_, Err(e)) => {
+            return Err(format!("SyntaxError: {} ({})", e.message(), e.position()));
+        }
+        _ => {}
+    }
+
+    let mut runtime = new_runtime();
+    PRINTED.with(|printed| printed.borrow_mut().clear());
+
+    if !metadata.has_flag("raw") {
+        let mut includes = vec!["assert.js".to_string(), "sta.js".to_string()];
+        if metadata.has_flag("async") {
+            includes.push("doneprintHandle.js".to_string());
+        }
+        includes.extend(metadata.includes.iter().cloned());
+        for include in includes {
+            let path = root.join("harness").join(&include);
+            let harness = fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+            run_script(&mut runtime, &include, &harness)?;
+        }
+    }
+
+    let result = if module {
+        runtime.run_module("test262:module", source.to_string())
+    } else {
+        JsParser::new(JsLexer::new(source.to_string()))
+            .parse_ast()
+            .map_err(|e| runtime.throw_error("SyntaxError", e.message()))
+            .and_then(|program| runtime.execute(&program))
+    };
+
+    match (&metadata.negative, result) {
+        (Some(negative), Err(e)) => {
+            if is_error_type(&mut runtime, &e, &negative.error_type) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "expected a {}, but got {}",
+                    negative.error_type,
+                    describe(&e)
+                ))
+            }
+        }
+        (Some(negative), Ok(())) => Err(format!(
+            "expected a {} at {} phase, but no exception was thrown",
+            negative.error_type, negative.phase
+        )),
+        (None, Err(e)) => Err(describe(&e)),
+        (None, Ok(())) if metadata.has_flag("async") => {
+            perform_microtask_checkpoint(&mut runtime);
+            for _ in 0..MAX_TASK_TURNS {
+                if !run_tasks(&mut runtime) {
+                    break;
+                }
+            }
+            let printed = PRINTED.with(|printed| printed.borrow().clone());
+            match printed.iter().find(|line| line.starts_with("Test262:")) {
+                Some(line) if line == ASYNC_COMPLETE => Ok(()),
+                Some(line) => Err(line.clone()),
+                None => Err("$DONE was not called".to_string()),
+            }
+        }
+        (None, Ok(())) => Ok(()),
+    }
+}
+
+fn collect_tests(dir: &Path, tests: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", dir.display(), e))
+        .map(|entry| entry.expect("failed to read a directory entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_tests(&path, tests);
+        } else if path.extension().is_some_and(|ext| ext == "js")
+            && !path.to_string_lossy().contains("_FIXTURE")
+        {
+            tests.push(path);
+        }
+    }
+}
+
+fn read_baseline(path: &Path) -> BTreeSet<String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn write_baseline(path: &Path, passed: &BTreeSet<String>) {
+    let mut content = String::from(
+        "# 通過するテスト。ここにあるテストが失敗するとtests/test262.rsが失敗する\n\
+         # TEST262_UPDATE_BASELINE=1 cargo test --test test262 で更新する\n",
+    );
+    for name in passed {
+        content.push_str(name);
+        content.push('\n');
+    }
+    fs::write(path, content)
+        .unwrap_or_else(|e| panic!("failed to write {}: {}", path.display(), e));
+}
+
+// rootのtest/の下のテストを実行する。baselineを指定した場合は、記録されたテストが失敗しないかを確かめる
+fn run_suite(root: &Path, baseline_path: Option<&Path>) {
+    let test_dir = root.join("test");
+    let filter_dir = match env::var("TEST262_FILTER") {
+        Ok(filter) => test_dir.join(filter),
+        Err(_) => test_dir.clone(),
+    };
+    let mut tests = Vec::new();
+    collect_tests(&filter_dir, &mut tests);
+
+    // ディレクトリごとの(通過数, テスト数)
+    let mut directories: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let mut passed = BTreeSet::new();
+    let mut failures = BTreeMap::new();
+    let mut skipped = Vec::new();
+    for path in &tests {
+        let name = path
+            .strip_prefix(&test_dir)
+            .expect("test outside of the test directory")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let directory = name.rsplit_once('/').map_or("", |(dir, _)| dir).to_string();
+        let source = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", path.display(), e));
+        if parse_metadata(&source).is_ok_and(|metadata| metadata.has_flag("onlyStrict")) {
+            skipped.push(name);
+            continue;
+        }
+
+        let count = directories.entry(directory).or_default();
+        count.1 += 1;
+        match run_test(root, &source) {
+            Ok(()) => {
+                count.0 += 1;
+                passed.insert(name);
+            }
+            Err(message) => {
+                failures.insert(name, message);
+            }
+        }
+    }
+
+    for (directory, (pass, total)) in &directories {
+        println!(
+            "{:<56} {:>3}/{:<3} {:>6.1}%",
+            directory,
+            pass,
+            total,
+            *pass as f64 * 100.0 / *total as f64
+        );
+    }
+    println!(
+        "{:<56} {:>3}/{:<3} {:>6.1}%",
+        "total",
+        passed.len(),
+        tests.len() - skipped.len(),
+        passed.len() as f64 * 100.0 / (tests.len() - skipped.len()) as f64
+    );
+    for name in &skipped {
+        println!("SKIP {}: onlyStrict tests need strict mode", name);
+    }
+    for (name, message) in &failures {
+        println!("FAIL {}: {}", name, message);
+    }
+
+    // baseline.txtはすべてのテストの結果なので、一部のテストとは比べない
+    let baseline_path = match baseline_path {
+        Some(path) if filter_dir == test_dir => path,
+        _ => return,
+    };
+    if env::var("TEST262_UPDATE_BASELINE").is_ok_and(|value| value == "1") {
+        write_baseline(baseline_path, &passed);
+        return;
+    }
+
+    let baseline = read_baseline(baseline_path);
+    for name in passed.difference(&baseline) {
+        println!("NEW PASS {} (update the baseline to keep it passing)", name);
+    }
+    let regressions: Vec<String> = baseline
+        .difference(&passed)
+        .map(|name| match failures.get(name) {
+            Some(message) => format!("{}: {}", name, message),
+            None => format!("{}: test not found", name),
+        })
+        .collect();
+    assert!(
+        regressions.is_empty(),
+        "test262 regressions:\n{}",
+        regressions.join("\n")
+    );
+}
+
+// このエンジンのために書いたテスト。TEST262_DIRを指定した場合は、そのチェックアウトのテストを通過率だけ表示する
+#[test]
+fn test262() {
+    match env::var("TEST262_DIR") {
+        Ok(dir) => run_suite(Path::new(&dir), None),
+        Err(_) => {
+            let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test262");
+            run_suite(&root, Some(&root.join("baseline.txt")));
+        }
+    }
+}
+
+// vendor.shで取り込んだ上流のtest262のテスト。baseline.txtには上流のtest/の下のパスを記録する
+#[test]
+fn test262_upstream() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test262/upstream");
+    // 取り込んでいないまま通過したことにならないように、COMMITがない場合は失敗にする
+    let commit = fs::read_to_string(root.join("COMMIT")).unwrap_or_else(|_| {
+        panic!("upstream test262 is not vendored; run tests/test262/vendor.sh <test262 checkout> and commit tests/test262/upstream")
+    });
+    println!("test262 {}", commit.trim());
+    run_suite(&root, Some(&root.join("baseline.txt")));
+}
+
+#[test]
+fn test_parse_metadata() {
+    let metadata = parse_metadata(
+        "/*---\n\
+         description: |\n  multi: line\n\
+         flags: [module, async]\n\
+         includes:\n  - compareArray.js\n\
+         negative:\n  phase: parse\n  type: SyntaxError\n\
+         ---*/",
+    )
+    .unwrap();
+    assert_eq!(metadata.flags, vec!["module", "async"]);
+    assert_eq!(metadata.includes, vec!["compareArray.js"]);
+    let negative = metadata.negative.unwrap();
+    assert_eq!(negative.phase, "parse");
+    assert_eq!(negative.error_type, "SyntaxError");
+
+    assert!(parse_metadata("var x = 1;").is_err());
+}
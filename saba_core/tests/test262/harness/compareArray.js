@@ -0,0 +1,42 @@
+/*---
+description: |
+    Compare the contents of two arrays
+defines: [compareArray]
+---*/
+
+function compareArray(a, b) {
+  if (b.length !== a.length) {
+    return false;
+  }
+
+  for (var i = 0; i < a.length; i++) {
+    if (!compareArray.isSameValue(b[i], a[i])) {
+      return false;
+    }
+  }
+  return true;
+}
+
+compareArray.isSameValue = function (a, b) {
+  if (a === 0 && b === 0) return 1 / a === 1 / b;
+  if (a !== a && b !== b) return true;
+
+  return a === b;
+};
+
+compareArray.format = function (arrayLike) {
+  return '[' + [].map.call(arrayLike, String).join(', ') + ']';
+};
+
+assert.compareArray = function (actual, expected, message) {
+  message = message === undefined ? '' : message;
+
+  assert(actual != null, 'Actual argument shouldn\'t be nullish. ' + message);
+  assert(expected != null, 'Expected argument shouldn\'t be nullish. ' + message);
+  var format = compareArray.format;
+  var result = compareArray(actual, expected);
+
+  if (!result) {
+    assert(false, 'Actual ' + format(actual) + ' and expected ' + format(expected) + ' should have the same contents. ' + message);
+  }
+};
@@ -0,0 +1,8 @@
+/*---
+esid: sec-array.isarray
+description: isArray distinguishes arrays from other objects
+---*/
+
+assert.sameValue(Array.isArray([]), true);
+assert.sameValue(Array.isArray({ length: 0 }), false);
+assert.sameValue(Array.isArray('a'), false);
@@ -0,0 +1,8 @@
+/*---
+esid: sec-array.prototype.indexof
+description: indexOf uses strict equality
+---*/
+
+assert.sameValue([1, '1'].indexOf('1'), 1);
+assert.sameValue([NaN].indexOf(NaN), -1);
+assert.sameValue([].indexOf(1), -1);
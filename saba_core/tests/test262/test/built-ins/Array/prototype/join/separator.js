@@ -0,0 +1,8 @@
+/*---
+esid: sec-array.prototype.join
+description: join uses a comma when the separator is omitted
+---*/
+
+assert.sameValue([1, 2, 3].join(), '1,2,3');
+assert.sameValue([1, 2, 3].join('-'), '1-2-3');
+assert.sameValue([null, undefined, 1].join(), ',,1');
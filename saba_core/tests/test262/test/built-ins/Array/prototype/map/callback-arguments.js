@@ -0,0 +1,11 @@
+/*---
+esid: sec-array.prototype.map
+description: The callback receives the value and the index
+includes: [compareArray.js]
+---*/
+
+var result = [10, 20].map(function (value, index) {
+  return value + index;
+});
+
+assert.compareArray(result, [10, 21]);
@@ -0,0 +1,10 @@
+/*---
+esid: sec-array.prototype.push
+description: push returns the new length
+includes: [compareArray.js]
+---*/
+
+var a = [1];
+
+assert.sameValue(a.push(2, 3), 3);
+assert.compareArray(a, [1, 2, 3]);
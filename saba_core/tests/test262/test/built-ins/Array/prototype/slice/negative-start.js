@@ -0,0 +1,8 @@
+/*---
+esid: sec-array.prototype.slice
+description: A negative start counts from the end
+includes: [compareArray.js]
+---*/
+
+assert.compareArray([1, 2, 3, 4].slice(-2), [3, 4]);
+assert.compareArray([1, 2, 3, 4].slice(1, -1), [2, 3]);
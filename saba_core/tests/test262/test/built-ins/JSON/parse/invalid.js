@@ -0,0 +1,8 @@
+/*---
+esid: sec-json.parse
+description: Invalid JSON text throws a SyntaxError
+---*/
+
+assert.throws(SyntaxError, function () {
+  JSON.parse('{a: 1}');
+});
@@ -0,0 +1,9 @@
+/*---
+esid: sec-json.parse
+description: JSON.parse creates objects and arrays
+---*/
+
+var value = JSON.parse('{"a": [1, 2], "b": "x"}');
+
+assert.sameValue(value.a[1], 2);
+assert.sameValue(value.b, 'x');
@@ -0,0 +1,7 @@
+/*---
+esid: sec-json.stringify
+description: Nested objects and arrays are serialized
+---*/
+
+assert.sameValue(JSON.stringify({ a: [1, 'x', null], b: true }), '{"a":[1,"x",null],"b":true}');
+assert.sameValue(JSON.stringify('a"b'), '"a\\"b"');
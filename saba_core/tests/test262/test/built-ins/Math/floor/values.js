@@ -0,0 +1,8 @@
+/*---
+esid: sec-math.floor
+description: Math.floor rounds towards negative infinity
+---*/
+
+assert.sameValue(Math.floor(1.5), 1);
+assert.sameValue(Math.floor(-1.5), -2);
+assert.sameValue(Math.floor(-0), -0);
@@ -0,0 +1,7 @@
+/*---
+esid: sec-math.max
+description: Math.max without arguments is -Infinity
+---*/
+
+assert.sameValue(Math.max(), -Infinity);
+assert.sameValue(Math.max(1, NaN), NaN);
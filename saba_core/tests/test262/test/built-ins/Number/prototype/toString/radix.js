@@ -0,0 +1,7 @@
+/*---
+esid: sec-number.prototype.tostring
+description: toString with a radix
+---*/
+
+assert.sameValue((255).toString(16), 'ff');
+assert.sameValue((5).toString(2), '101');
@@ -0,0 +1,9 @@
+/*---
+esid: sec-object.keys
+description: Object.keys returns the own property names in insertion order
+includes: [compareArray.js]
+---*/
+
+var keys = Object.keys({ b: 1, a: 2 });
+
+assert.compareArray(keys, ['b', 'a']);
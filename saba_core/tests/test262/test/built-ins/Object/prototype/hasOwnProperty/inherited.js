@@ -0,0 +1,12 @@
+/*---
+esid: sec-object.prototype.hasownproperty
+description: Inherited properties are not own properties
+---*/
+
+function F() {}
+F.prototype.p = 1;
+var o = new F();
+
+assert.sameValue(o.hasOwnProperty('p'), false);
+o.q = 1;
+assert.sameValue(o.hasOwnProperty('q'), true);
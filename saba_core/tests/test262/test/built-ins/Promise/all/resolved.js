@@ -0,0 +1,10 @@
+/*---
+esid: sec-promise.all
+description: Promise.all fulfills with the values in order
+flags: [async]
+includes: [compareArray.js]
+---*/
+
+Promise.all([Promise.resolve(1), 2]).then(function (values) {
+  assert.compareArray(values, [1, 2]);
+}).then($DONE, $DONE);
@@ -0,0 +1,17 @@
+/*---
+esid: sec-promise.resolve
+description: Reactions run in the order they were registered
+flags: [async]
+---*/
+
+var log = '';
+var p = Promise.resolve();
+
+p.then(function () {
+  log += 'a';
+});
+p.then(function () {
+  log += 'b';
+}).then(function () {
+  assert.sameValue(log, 'ab');
+}).then($DONE, $DONE);
@@ -0,0 +1,8 @@
+/*---
+esid: sec-regexp-pattern-flags
+description: The RegExp constructor throws a SyntaxError for an invalid pattern
+---*/
+
+assert.throws(SyntaxError, function () {
+  new RegExp('(');
+});
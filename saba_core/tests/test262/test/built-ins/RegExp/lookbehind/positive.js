@@ -0,0 +1,7 @@
+/*---
+esid: sec-assertion
+description: Positive lookbehind assertions
+features: [regexp-lookbehind]
+---*/
+
+assert.sameValue(/(?<=\$)\d+/.exec('cost: $42')[0], '42');
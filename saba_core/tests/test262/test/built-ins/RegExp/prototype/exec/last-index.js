@@ -0,0 +1,12 @@
+/*---
+esid: sec-regexp.prototype.exec
+description: exec with the global flag advances lastIndex
+---*/
+
+var re = /a/g;
+
+assert.sameValue(re.exec('aba').index, 0);
+assert.sameValue(re.lastIndex, 1);
+assert.sameValue(re.exec('aba').index, 2);
+assert.sameValue(re.exec('aba'), null);
+assert.sameValue(re.lastIndex, 0);
@@ -0,0 +1,10 @@
+/*---
+esid: sec-regexp.prototype.test
+description: A sticky regular expression only matches at lastIndex
+---*/
+
+var re = /b/y;
+
+assert.sameValue(re.test('ab'), false);
+re.lastIndex = 1;
+assert.sameValue(re.test('ab'), true);
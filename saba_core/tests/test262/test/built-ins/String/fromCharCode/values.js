@@ -0,0 +1,6 @@
+/*---
+esid: sec-string.fromcharcode
+description: fromCharCode builds a string from code units
+---*/
+
+assert.sameValue(String.fromCharCode(97, 98), 'ab');
@@ -0,0 +1,8 @@
+/*---
+esid: sec-string.prototype.indexof
+description: indexOf starts searching at the given position
+---*/
+
+assert.sameValue('abcabc'.indexOf('b'), 1);
+assert.sameValue('abcabc'.indexOf('b', 2), 4);
+assert.sameValue('abc'.indexOf('x'), -1);
@@ -0,0 +1,7 @@
+/*---
+esid: sec-string.prototype.replace
+description: A global regular expression replaces every match
+---*/
+
+assert.sameValue('aXbXc'.replace(/X/g, '-'), 'a-b-c');
+assert.sameValue('abc'.replace(/(b)/, '[$1]'), 'a[b]c');
@@ -0,0 +1,7 @@
+/*---
+esid: sec-string.prototype.replace
+description: A string pattern replaces only the first occurrence
+---*/
+
+assert.sameValue('aXbXc'.replace('X', '-'), 'a-bXc');
+assert.sameValue('abc'.replace('b', '$&$&'), 'abbc');
@@ -0,0 +1,8 @@
+/*---
+esid: sec-string.prototype.split
+description: The limit argument truncates the result
+includes: [compareArray.js]
+---*/
+
+assert.compareArray('a,b,c'.split(',', 2), ['a', 'b']);
+assert.compareArray('a1b2c'.split(/\d/), ['a', 'b', 'c']);
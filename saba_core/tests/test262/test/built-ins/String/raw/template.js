@@ -0,0 +1,8 @@
+/*---
+esid: sec-string.raw
+description: String.raw joins the raw strings with the substitutions
+---*/
+
+assert.sameValue(String.raw`a\nb`, 'a\\nb');
+assert.sameValue(String.raw`x${1}y${2}z`, 'x1y2z');
+assert.sameValue(String.raw({ raw: ['a', 'b', 'c'] }, 1), 'a1bc');
@@ -0,0 +1,7 @@
+/*---
+esid: sec-symbol-description
+description: The description of a symbol
+features: [Symbol]
+---*/
+
+assert.sameValue(Symbol('a').description, 'a');
@@ -0,0 +1,8 @@
+/*---
+esid: sec-parseint-string-radix
+description: parseInt with an explicit radix
+---*/
+
+assert.sameValue(parseInt('ff', 16), 255);
+assert.sameValue(parseInt('10', 2), 2);
+assert.sameValue(parseInt('12px'), 12);
@@ -0,0 +1,13 @@
+/*---
+esid: sec-rules-of-automatic-semicolon-insertion
+description: Semicolons in the header of a for statement are never inserted
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+for (var i = 0
+  i < 1
+  i++) {}
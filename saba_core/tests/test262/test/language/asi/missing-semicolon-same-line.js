@@ -0,0 +1,11 @@
+/*---
+esid: sec-rules-of-automatic-semicolon-insertion
+description: No semicolon is inserted between two statements on the same line
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+var x = 1 var y = 2;
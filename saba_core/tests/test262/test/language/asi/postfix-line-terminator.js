@@ -0,0 +1,13 @@
+/*---
+esid: sec-rules-of-automatic-semicolon-insertion
+description: A line terminator before ++ makes it a prefix operator of the next line
+---*/
+
+var x = 1;
+var y = 1;
+x
+++
+y
+
+assert.sameValue(x, 1, 'x');
+assert.sameValue(y, 2, 'y');
@@ -0,0 +1,11 @@
+/*---
+esid: sec-rules-of-automatic-semicolon-insertion
+description: A line terminator after return inserts a semicolon
+---*/
+
+function f() {
+  return
+  1;
+}
+
+assert.sameValue(f(), undefined);
@@ -0,0 +1,12 @@
+/*---
+esid: sec-throw-statement
+description: A line terminator between throw and its expression is not allowed
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+throw
+1;
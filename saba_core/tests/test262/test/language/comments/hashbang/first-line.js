@@ -0,0 +1,11 @@
+#!/usr/bin/env node
+/*---
+esid: sec-hashbang
+description: A hashbang comment at the start of the source is ignored
+flags: [raw]
+---*/
+
+var x = 1;
+if (x !== 1) {
+  throw new Error('x should be 1');
+}
@@ -0,0 +1,10 @@
+/*---
+esid: sec-hashbang
+description: A hashbang comment is only allowed at the start of the source
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+#!/usr/bin/env node
@@ -0,0 +1,12 @@
+/*---
+esid: sec-html-like-comments
+description: HTML-like comments are single line comments in scripts
+---*/
+
+var x = 1; <!-- x = 2;
+--> x = 3;
+assert.sameValue(x, 1);
+
+var y = x --> 0;
+assert.sameValue(y, true);
+assert.sameValue(x, 0);
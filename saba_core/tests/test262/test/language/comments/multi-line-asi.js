@@ -0,0 +1,10 @@
+/*---
+esid: sec-comments
+description: A multi-line comment containing a line terminator acts as a line terminator
+---*/
+
+var x = 1 /*
+*/ var y = 2;
+
+assert.sameValue(x, 1);
+assert.sameValue(y, 2);
@@ -0,0 +1,10 @@
+/*---
+esid: sec-comments
+description: A single line comment ends at the line terminator
+---*/
+
+var x = 0;
+// x = 1;
+x = x + 2; // x = 3;
+
+assert.sameValue(x, 2);
@@ -0,0 +1,11 @@
+/*---
+esid: sec-comments
+description: A multi-line comment must be closed
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+/* never closed
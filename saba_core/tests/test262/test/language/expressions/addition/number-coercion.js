@@ -0,0 +1,10 @@
+/*---
+esid: sec-addition-operator-plus
+description: Non-string primitives are converted to numbers
+---*/
+
+assert.sameValue(true + 1, 2);
+assert.sameValue(null + 1, 1);
+assert.sameValue(undefined + 1, NaN);
+assert.sameValue(-0 + -0, -0);
+assert.sameValue(-0 + 0, 0);
@@ -0,0 +1,9 @@
+/*---
+esid: sec-addition-operator-plus
+description: If either operand is a string, the operands are concatenated
+---*/
+
+assert.sameValue('1' + 1, '11');
+assert.sameValue(1 + '1', '11');
+assert.sameValue('a' + null, 'anull');
+assert.sameValue('a' + undefined, 'aundefined');
@@ -0,0 +1,14 @@
+/*---
+esid: sec-arrow-function-definitions
+description: Arrow functions capture this from the enclosing function
+---*/
+
+var o = {
+  value: 1,
+  get: function () {
+    var f = () => this.value;
+    return f();
+  }
+};
+
+assert.sameValue(o.get(), 1);
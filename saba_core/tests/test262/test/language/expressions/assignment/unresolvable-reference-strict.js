@@ -0,0 +1,10 @@
+/*---
+esid: sec-putvalue
+description: Assigning to an undeclared variable throws a ReferenceError in strict mode code
+flags: [onlyStrict]
+negative:
+  phase: runtime
+  type: ReferenceError
+---*/
+
+undeclared = 1;
@@ -0,0 +1,18 @@
+/*---
+esid: sec-await
+description: await throws the rejection reason of a promise
+flags: [async]
+features: [async-functions]
+---*/
+
+async function f() {
+  try {
+    await Promise.reject(new Test262Error('reason'));
+  } catch (error) {
+    return error.message;
+  }
+}
+
+f().then(function (value) {
+  assert.sameValue(value, 'reason');
+}).then($DONE, $DONE);
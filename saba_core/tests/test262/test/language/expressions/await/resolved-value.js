@@ -0,0 +1,15 @@
+/*---
+esid: sec-await
+description: await returns the fulfilled value of a promise
+flags: [async]
+features: [async-functions]
+---*/
+
+async function f() {
+  var value = await Promise.resolve(1);
+  return value + 1;
+}
+
+f().then(function (value) {
+  assert.sameValue(value, 2);
+}).then($DONE, $DONE);
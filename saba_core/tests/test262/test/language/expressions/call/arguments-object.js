@@ -0,0 +1,10 @@
+/*---
+esid: sec-arguments-exotic-objects
+description: The arguments object contains the passed arguments
+---*/
+
+function f() {
+  return arguments.length;
+}
+
+assert.sameValue(f(1, 2, 3), 3);
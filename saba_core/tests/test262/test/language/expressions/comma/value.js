@@ -0,0 +1,8 @@
+/*---
+esid: sec-comma-operator
+description: The comma operator returns the value of its right operand
+---*/
+
+var x = (1, 2);
+
+assert.sameValue(x, 2);
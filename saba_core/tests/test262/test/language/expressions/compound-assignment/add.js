@@ -0,0 +1,12 @@
+/*---
+esid: sec-assignment-operators
+description: Compound addition assignment
+---*/
+
+var x = 1;
+x += 2;
+assert.sameValue(x, 3);
+
+var o = { p: 'a' };
+o.p += 'b';
+assert.sameValue(o.p, 'ab');
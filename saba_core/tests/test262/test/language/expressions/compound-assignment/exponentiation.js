@@ -0,0 +1,9 @@
+/*---
+esid: sec-assignment-operators
+description: Compound exponentiation assignment
+features: [exponentiation]
+---*/
+
+var x = 2;
+x **= 3;
+assert.sameValue(x, 8);
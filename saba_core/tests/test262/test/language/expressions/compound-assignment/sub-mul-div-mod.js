@@ -0,0 +1,14 @@
+/*---
+esid: sec-assignment-operators
+description: Compound arithmetic assignments
+---*/
+
+var x = 10;
+x -= 4;
+assert.sameValue(x, 6);
+x *= 2;
+assert.sameValue(x, 12);
+x /= 3;
+assert.sameValue(x, 4);
+x %= 3;
+assert.sameValue(x, 1);
@@ -0,0 +1,14 @@
+/*---
+esid: sec-conditional-operator
+description: The conditional operator evaluates only one branch
+---*/
+
+var called = false;
+function f() {
+  called = true;
+}
+
+var x = true ? 1 : f();
+
+assert.sameValue(x, 1);
+assert.sameValue(called, false);
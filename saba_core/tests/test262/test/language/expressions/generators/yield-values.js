@@ -0,0 +1,20 @@
+/*---
+esid: sec-generator-function-definitions
+description: Values produced by yield
+features: [generators]
+---*/
+
+function* g() {
+  yield 1;
+  yield 2;
+  return 3;
+}
+
+var it = g();
+var r = it.next();
+assert.sameValue(r.value, 1);
+assert.sameValue(r.done, false);
+it.next();
+r = it.next();
+assert.sameValue(r.value, 3);
+assert.sameValue(r.done, true);
@@ -0,0 +1,8 @@
+/*---
+esid: sec-instanceofoperator
+description: A non-callable right operand throws a TypeError
+---*/
+
+assert.throws(TypeError, function () {
+  ({}) instanceof {};
+});
@@ -0,0 +1,12 @@
+/*---
+esid: sec-instanceofoperator
+description: instanceof walks the prototype chain
+---*/
+
+class A {}
+class B extends A {}
+var b = new B();
+
+assert.sameValue(b instanceof B, true);
+assert.sameValue(b instanceof A, true);
+assert.sameValue({} instanceof A, false);
@@ -0,0 +1,10 @@
+/*---
+esid: sec-postfix-increment-operator
+description: Postfix increment of a property
+---*/
+
+var o = { n: '5' };
+var old = o.n++;
+
+assert.sameValue(old, 5, 'the old value is converted to a number');
+assert.sameValue(o.n, 6);
@@ -0,0 +1,11 @@
+/*---
+esid: sec-postfix-increment-operator
+description: A literal is not a valid assignment target
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+1++;
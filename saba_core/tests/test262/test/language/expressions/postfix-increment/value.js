@@ -0,0 +1,10 @@
+/*---
+esid: sec-postfix-increment-operator
+description: Postfix increment returns the old value
+---*/
+
+var x = 1;
+var y = x++;
+
+assert.sameValue(y, 1);
+assert.sameValue(x, 2);
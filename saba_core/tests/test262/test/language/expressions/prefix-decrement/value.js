@@ -0,0 +1,10 @@
+/*---
+esid: sec-prefix-decrement-operator
+description: Prefix decrement returns the new value
+---*/
+
+var x = 1;
+var y = --x;
+
+assert.sameValue(y, 0);
+assert.sameValue(x, 0);
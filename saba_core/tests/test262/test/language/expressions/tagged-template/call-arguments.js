@@ -0,0 +1,18 @@
+/*---
+esid: sec-tagged-templates
+description: The tag is called with the strings, a raw array and the substitutions
+---*/
+
+function tag(strings, a, b) {
+  return strings.join('|') + ':' + strings.raw.join('|') + ':' + a + ':' + b;
+}
+
+assert.sameValue(tag`x\t${1}y${'z'}`, 'x\t|y|:x\\t|y|:1:z');
+
+var o = {
+  p: 'P',
+  m(strings) {
+    return this.p + strings[0];
+  }
+};
+assert.sameValue(o.m`q`, 'Pq');
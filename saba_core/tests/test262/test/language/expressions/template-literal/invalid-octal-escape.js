@@ -0,0 +1,11 @@
+/*---
+esid: sec-template-literals
+description: Legacy octal escapes are not allowed in templates
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+`\01`;
@@ -0,0 +1,9 @@
+/*---
+esid: sec-template-literals
+description: Templates can span multiple lines
+---*/
+
+var s = `a
+b`;
+
+assert.sameValue(s.length, 3);
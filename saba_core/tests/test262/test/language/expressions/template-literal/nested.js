@@ -0,0 +1,9 @@
+/*---
+esid: sec-template-literals
+description: Templates nested in substitutions
+---*/
+
+var n = 2;
+
+assert.sameValue(`a${`b${n}`}c`, 'ab2c');
+assert.sameValue(`${ {p: 1}.p }`, '1');
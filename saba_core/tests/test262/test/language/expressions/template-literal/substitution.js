@@ -0,0 +1,11 @@
+/*---
+esid: sec-template-literals
+description: Substitutions are converted to strings
+---*/
+
+var a = 1;
+var b = 'x';
+
+assert.sameValue(`${a}+${a} = ${a + a}`, '1+1 = 2');
+assert.sameValue(`[${b}]`, '[x]');
+assert.sameValue(`${null}`, 'null');
@@ -0,0 +1,6 @@
+/*---
+esid: sec-typeof-operator
+description: typeof a number
+---*/
+
+assert.sameValue(typeof 1, 'number');
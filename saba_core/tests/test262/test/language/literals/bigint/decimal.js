@@ -0,0 +1,7 @@
+/*---
+esid: sec-literals-numeric-literals
+description: BigInt literals with the n suffix
+features: [BigInt]
+---*/
+
+assert.sameValue(1n + 2n, 3n);
@@ -0,0 +1,9 @@
+/*---
+esid: sec-literals-numeric-literals
+description: Binary and octal integer literals
+---*/
+
+assert.sameValue(0b101, 5);
+assert.sameValue(0B11, 3);
+assert.sameValue(0o17, 15);
+assert.sameValue(0O7, 7);
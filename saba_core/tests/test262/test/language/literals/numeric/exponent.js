@@ -0,0 +1,9 @@
+/*---
+esid: sec-literals-numeric-literals
+description: Decimal literals with an exponent part
+---*/
+
+assert.sameValue(1e3, 1000);
+assert.sameValue(2.5E-1, 0.25);
+assert.sameValue(.5, 0.5);
+assert.sameValue(5., 5);
@@ -0,0 +1,8 @@
+/*---
+esid: sec-literals-numeric-literals
+description: Hexadecimal integer literals
+---*/
+
+assert.sameValue(0x0, 0);
+assert.sameValue(0xff, 255);
+assert.sameValue(0XAbC, 2748);
@@ -0,0 +1,11 @@
+/*---
+esid: sec-literals-numeric-literals
+description: An identifier must not start immediately after a numeric literal
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+3in [];
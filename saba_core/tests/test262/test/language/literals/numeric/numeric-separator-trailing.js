@@ -0,0 +1,12 @@
+/*---
+esid: sec-literals-numeric-literals
+description: A numeric separator is not allowed at the end of a literal
+features: [numeric-separator-literal]
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+1_;
@@ -0,0 +1,9 @@
+/*---
+esid: sec-literals-numeric-literals
+description: Numeric separators between digits
+features: [numeric-separator-literal]
+---*/
+
+assert.sameValue(1_000_000, 1000000);
+assert.sameValue(0xff_ff, 65535);
+assert.sameValue(0b1_0, 2);
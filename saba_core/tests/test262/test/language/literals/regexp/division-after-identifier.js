@@ -0,0 +1,9 @@
+/*---
+esid: sec-literals-regular-expression-literals
+description: A slash after an identifier is the division operator
+---*/
+
+var a = 8, g = 2;
+var x = a / 2 / g;
+
+assert.sameValue(x, 2);
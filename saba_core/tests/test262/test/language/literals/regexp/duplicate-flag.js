@@ -0,0 +1,11 @@
+/*---
+esid: sec-literals-regular-expression-literals
+description: A flag must not appear twice
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+/a/gg;
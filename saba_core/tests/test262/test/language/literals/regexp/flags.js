@@ -0,0 +1,12 @@
+/*---
+esid: sec-literals-regular-expression-literals
+description: Flags of a regular expression literal
+---*/
+
+var re = /a/gim;
+
+assert.sameValue(re.flags, 'gim');
+assert.sameValue(re.global, true);
+assert.sameValue(re.ignoreCase, true);
+assert.sameValue(re.multiline, true);
+assert.sameValue(re.sticky, false);
@@ -0,0 +1,11 @@
+/*---
+esid: sec-literals-regular-expression-literals
+description: An invalid pattern is an early error
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+/(?<a>x)(?<a>y)/;
@@ -0,0 +1,11 @@
+/*---
+esid: sec-literals-regular-expression-literals
+description: Named capture groups are reported in the match result
+features: [regexp-named-groups]
+---*/
+
+var match = /(?<year>\d{4})-(?<month>\d{2})/.exec('2020-12');
+
+assert.sameValue(match[0], '2020-12');
+assert.sameValue(match.groups.year, '2020');
+assert.sameValue(match.groups.month, '12');
@@ -0,0 +1,9 @@
+/*---
+esid: sec-literals-string-literals
+description: Hexadecimal and Unicode escape sequences
+---*/
+
+assert.sameValue('\x41', 'A');
+assert.sameValue('\u0042', 'B');
+assert.sameValue('\u{43}', 'C');
+assert.sameValue('\u{1F600}'.length, 2, 'code points above 0xFFFF are surrogate pairs');
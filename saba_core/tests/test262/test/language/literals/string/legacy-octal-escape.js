@@ -0,0 +1,8 @@
+/*---
+esid: sec-additional-syntax-string-literals
+description: Legacy octal escape sequences in sloppy code
+flags: [noStrict]
+---*/
+
+assert.sameValue('\101', 'A');
+assert.sameValue('\0', String.fromCharCode(0));
@@ -0,0 +1,9 @@
+/*---
+esid: sec-literals-string-literals
+description: A backslash followed by a line terminator is removed from the string
+---*/
+
+var s = 'a\
+b';
+
+assert.sameValue(s, 'ab');
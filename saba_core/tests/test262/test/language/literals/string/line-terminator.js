@@ -0,0 +1,12 @@
+/*---
+esid: sec-literals-string-literals
+description: A string literal must not contain a line feed
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+var s = 'a
+b';
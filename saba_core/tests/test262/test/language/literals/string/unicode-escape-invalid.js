@@ -0,0 +1,11 @@
+/*---
+esid: sec-literals-string-literals
+description: A \u escape needs four hexadecimal digits
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+'\u00G0';
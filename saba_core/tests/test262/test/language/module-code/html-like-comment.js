@@ -0,0 +1,12 @@
+/*---
+esid: sec-html-like-comments
+description: HTML-like comments are not allowed in modules
+flags: [module]
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+var x; <!-- x
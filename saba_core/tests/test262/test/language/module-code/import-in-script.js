@@ -0,0 +1,11 @@
+/*---
+esid: sec-modules
+description: An import declaration is not allowed in a script
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+import { x } from './x.js';
@@ -0,0 +1,12 @@
+/*---
+esid: sec-modules
+description: Declarations at the top level of a module
+flags: [module]
+---*/
+
+export var x = 1;
+export function f() {
+  return x + 1;
+}
+
+assert.sameValue(f(), 2);
@@ -0,0 +1,12 @@
+/*---
+esid: sec-hostloadimportedmodule
+description: Importing from a module that cannot be loaded is an error
+flags: [module]
+negative:
+  phase: resolution
+  type: TypeError
+---*/
+
+$DONOTEVALUATE();
+
+import { x } from './missing_FIXTURE.js';
@@ -0,0 +1,10 @@
+/*---
+esid: sec-class-definitions
+description: Calling a class constructor without new throws a TypeError
+---*/
+
+class A {}
+
+assert.throws(TypeError, function () {
+  A();
+});
@@ -0,0 +1,18 @@
+/*---
+esid: sec-class-definitions
+description: The constructor initializes the instance
+---*/
+
+class Point {
+  constructor(x, y) {
+    this.x = x;
+    this.y = y;
+  }
+  sum() {
+    return this.x + this.y;
+  }
+}
+
+var p = new Point(1, 2);
+assert.sameValue(p.x, 1);
+assert.sameValue(p.sum(), 3);
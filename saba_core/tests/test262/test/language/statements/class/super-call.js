@@ -0,0 +1,23 @@
+/*---
+esid: sec-super-keyword
+description: super() calls the parent constructor and super.m() the parent method
+---*/
+
+class A {
+  constructor(v) {
+    this.v = v;
+  }
+  m() {
+    return 'A' + this.v;
+  }
+}
+class B extends A {
+  constructor() {
+    super(1);
+  }
+  m() {
+    return 'B' + super.m();
+  }
+}
+
+assert.sameValue(new B().m(), 'BA1');
@@ -0,0 +1,11 @@
+/*---
+esid: sec-let-and-const-declarations
+description: Assigning to a const binding throws a TypeError
+---*/
+
+const x = 1;
+
+assert.throws(TypeError, function () {
+  x = 2;
+});
+assert.sameValue(x, 1);
@@ -0,0 +1,12 @@
+/*---
+esid: sec-block-static-semantics-early-errors
+description: A lexical binding cannot be declared twice in the same scope
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+const x = 1;
+const x = 2;
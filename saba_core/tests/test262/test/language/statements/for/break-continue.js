@@ -0,0 +1,17 @@
+/*---
+esid: sec-for-statement
+description: break and continue in a for statement
+---*/
+
+var seen = '';
+for (var i = 0; i < 10; i++) {
+  if (i === 1) {
+    continue;
+  }
+  if (i === 4) {
+    break;
+  }
+  seen += i;
+}
+
+assert.sameValue(seen, '023');
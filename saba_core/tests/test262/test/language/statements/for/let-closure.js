@@ -0,0 +1,14 @@
+/*---
+esid: sec-for-statement
+description: Each iteration of a for statement with let has its own binding
+---*/
+
+var fs = [];
+for (let i = 0; i < 3; i++) {
+  fs.push(function () {
+    return i;
+  });
+}
+
+assert.sameValue(fs[0](), 0);
+assert.sameValue(fs[2](), 2);
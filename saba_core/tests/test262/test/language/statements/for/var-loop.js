@@ -0,0 +1,12 @@
+/*---
+esid: sec-for-statement
+description: A for statement with a var declaration
+---*/
+
+var sum = 0;
+for (var i = 0; i < 5; i++) {
+  sum += i;
+}
+
+assert.sameValue(sum, 10);
+assert.sameValue(i, 5);
@@ -0,0 +1,9 @@
+/*---
+esid: sec-let-and-const-declarations
+description: A let binding cannot be read before its declaration
+---*/
+
+assert.throws(ReferenceError, function () {
+  x;
+  let x = 1;
+});
@@ -0,0 +1,16 @@
+/*---
+esid: sec-switch-statement
+description: switch selects the matching case
+---*/
+
+var result;
+switch (2) {
+  case 1:
+    result = 'one';
+    break;
+  case 2:
+    result = 'two';
+    break;
+}
+
+assert.sameValue(result, 'two');
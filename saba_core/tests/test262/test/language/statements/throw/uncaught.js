@@ -0,0 +1,9 @@
+/*---
+esid: sec-throw-statement
+description: An uncaught exception ends the script
+negative:
+  phase: runtime
+  type: Test262Error
+---*/
+
+throw new Test262Error();
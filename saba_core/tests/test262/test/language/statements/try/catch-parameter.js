@@ -0,0 +1,13 @@
+/*---
+esid: sec-try-statement
+description: The catch parameter is bound to the thrown value
+---*/
+
+var caught;
+try {
+  throw 42;
+} catch (e) {
+  caught = e;
+}
+
+assert.sameValue(caught, 42);
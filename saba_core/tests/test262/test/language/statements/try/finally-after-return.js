@@ -0,0 +1,17 @@
+/*---
+esid: sec-try-statement
+description: The finally block runs after return in the try block
+---*/
+
+var log = '';
+function f() {
+  try {
+    log += 'try';
+    return 1;
+  } finally {
+    log += 'finally';
+  }
+}
+
+assert.sameValue(f(), 1);
+assert.sameValue(log, 'tryfinally');
@@ -0,0 +1,11 @@
+/*---
+esid: sec-while-statement
+description: A while statement repeats while the condition is true
+---*/
+
+var n = 0;
+while (n < 3) {
+  n++;
+}
+
+assert.sameValue(n, 3);
@@ -0,0 +1,41 @@
+#!/bin/sh -e
+
+# 上流のtest262のチェックアウトから、upstream/directories.txtに書いたディレクトリのテストと、
+# それらが使うハーネス、LICENSEをupstream/へコピーし、取り込んだコミットをupstream/COMMITに記録する
+# 使い方: tests/test262/vendor.sh /path/to/test262
+
+SOURCE=$1
+if [ -z "$SOURCE" ] || [ ! -d "$SOURCE/test" ] || [ ! -d "$SOURCE/harness" ]
+then
+  echo "usage: $0 <test262 checkout>"
+  exit 1
+fi
+
+DEST=$(cd "$(dirname "$0")" && pwd)/upstream
+rm -rf "$DEST/test" "$DEST/harness"
+mkdir -p "$DEST/test" "$DEST/harness"
+
+grep -v -e '^#' -e '^$' "$DEST/directories.txt" | while read -r DIR
+do
+  if [ ! -d "$SOURCE/test/$DIR" ]
+  then
+    echo "$DIR does not exist in $SOURCE/test"
+    exit 1
+  fi
+  mkdir -p "$DEST/test/$(dirname "$DIR")"
+  cp -R "$SOURCE/test/$DIR" "$DEST/test/$DIR"
+done
+
+# tests/test262.rsがすべてのテストの前に読み込むハーネスと、テストのincludesに書かれたハーネス
+INCLUDES=$(grep -rhoE '^includes: *\[[^]]*\]|^ +- +[A-Za-z0-9_.-]+\.js' "$DEST/test" \
+  | sed -e 's/^includes: *\[//' -e 's/\]$//' -e 's/^ *- *//' \
+  | tr ',' '\n' | sed 's/ //g' | sort -u)
+for FILE in assert.js sta.js doneprintHandle.js $INCLUDES
+do
+  cp "$SOURCE/harness/$FILE" "$DEST/harness/$FILE"
+done
+
+cp "$SOURCE/LICENSE" "$DEST/LICENSE"
+git -C "$SOURCE" rev-parse HEAD > "$DEST/COMMIT"
+echo "vendored test262 $(cat "$DEST/COMMIT")"
+echo "run TEST262_UPDATE_BASELINE=1 cargo test --test test262 to record the passing tests"
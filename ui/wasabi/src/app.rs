@@ -16,9 +16,10 @@ use saba_core::constants::{
 };
 use saba_core::display_item::DisplayItem;
 use saba_core::error::Error;
-use saba_core::http::HttpResponse;
+use saba_core::http::{HttpRequest, HttpResponse};
 use saba_core::renderer::layout::computed_style::{FontSize, TextDecoration};
 
+use crate::clock::TscClock;
 use crate::cursor::Cursor;
 
 #[derive(Debug)]
@@ -39,6 +40,9 @@ enum InputMode {
 
 impl WasabiUI {
     pub fn new(browser: Rc<RefCell<Browser>>) -> Self {
+        // ページのイベントループは実際の経過時間でタイマーを実行する
+        browser.borrow().set_clock(Rc::new(TscClock::new()));
+
         Self {
             browser,
             window: Window::new(
@@ -114,29 +118,37 @@ impl WasabiUI {
 
     pub fn start(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_request: fn(&HttpRequest) -> Result<HttpResponse, Error>,
     ) -> Result<(), Error> {
         self.setup()?;
 
-        self.run_app(handle_url)?;
+        // ページの移動とスクリプトのリクエストはhandle_requestで処理する
+        self.browser.borrow().set_transport(Rc::new(handle_request));
+
+        self.run_app()?;
 
         Ok(())
     }
 
-    fn run_app(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    fn run_app(&mut self) -> Result<(), Error> {
         loop {
-            self.handle_key_input(handle_url)?;
-            self.handle_mouse_input(handle_url)?;
+            self.handle_key_input()?;
+            self.handle_mouse_input()?;
+            self.run_event_loop()?;
         }
     }
 
-    fn handle_mouse_input(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    // 時刻が来たタイマーなどを実行する
+    fn run_event_loop(&mut self) -> Result<(), Error> {
+        let page = self.browser.borrow().current_page();
+        let ran = page.borrow_mut().run_event_loop();
+        if ran {
+            self.repaint_content_area()?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse_input(&mut self) -> Result<(), Error> {
         if let Some(MouseEvent { button, position }) = Api::get_mouse_cursor_info() {
             self.window.flush_area(self.cursor.rect());
             self.cursor.set_position(position.x, position.y);
@@ -180,10 +192,14 @@ impl WasabiUI {
                 let page = self.browser.borrow().current_page();
                 let next_destination = page.borrow_mut().clicked(position_in_content_area);
 
-                if let Some(url) = next_destination {
-                    self.input_url = url.clone();
-                    self.update_address_bar()?;
-                    self.start_navigation(handle_url, url)?;
+                match next_destination {
+                    Some(url) => {
+                        self.input_url = url.clone();
+                        self.update_address_bar()?;
+                        self.start_navigation(url)?;
+                    }
+                    // イベントリスナーがDOMを変更した可能性があるため描画し直す
+                    None => self.repaint_content_area()?,
                 }
             }
         }
@@ -191,21 +207,22 @@ impl WasabiUI {
         Ok(())
     }
 
-    fn handle_key_input(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    fn handle_key_input(&mut self) -> Result<(), Error> {
         match self.input_mode {
             InputMode::Normal => {
-                // キー入力を無視
-                let _ = Api::read_key();
+                // キー入力はページのkeydownイベントとして送る
+                if let Some(c) = Api::read_key() {
+                    let page = self.browser.borrow().current_page();
+                    page.borrow_mut().key_pressed(c);
+                    self.repaint_content_area()?;
+                }
             }
             InputMode::Editing => {
                 if let Some(c) = Api::read_key() {
                     if c == 0x0A as char {
                         // EnterキーはASCIIコードで0x0Aで表す
                         // Enterキーが押されたのでナビゲーションを開始
-                        self.start_navigation(handle_url, self.input_url.clone())?;
+                        self.start_navigation(self.input_url.clone())?;
 
                         self.input_url = String::new();
                         self.input_mode = InputMode::Normal;
@@ -285,22 +302,11 @@ impl WasabiUI {
         Ok(())
     }
 
-    fn start_navigation(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-        destination: String,
-    ) -> Result<(), Error> {
+    fn start_navigation(&mut self, destination: String) -> Result<(), Error> {
         self.clear_content_area()?;
 
-        match handle_url(destination) {
-            Ok(response) => {
-                let page = self.browser.borrow().current_page();
-                page.borrow_mut().receive_response(response);
-            }
-            Err(e) => {
-                return Err(e);
-            }
-        }
+        let page = self.browser.borrow().current_page();
+        page.borrow_mut().navigate(destination)?;
 
         self.update_ui()?;
 
@@ -330,6 +336,11 @@ impl WasabiUI {
         Ok(())
     }
 
+    fn repaint_content_area(&mut self) -> Result<(), Error> {
+        self.clear_content_area()?;
+        self.update_ui()
+    }
+
     fn update_ui(&mut self) -> Result<(), Error> {
         let display_items = self
             .browser
@@ -0,0 +1,50 @@
+use core::arch::x86_64::{__cpuid, _rdtsc};
+use saba_core::renderer::js::event_loop::Clock;
+
+// CPUIDで周波数がわからない場合に使う、タイムスタンプカウンタの周波数(Hz)
+const DEFAULT_TSC_FREQUENCY: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy)]
+// タイムスタンプカウンタを使う時計。作成してからの経過時間をミリ秒で返す
+// カウンタは単調に増えるため、ループの回数に関係なく実際の時間でタイマーを実行できる
+pub struct TscClock {
+    start: u64,
+    ticks_per_ms: u64,
+}
+
+impl TscClock {
+    pub fn new() -> Self {
+        Self {
+            start: read_tsc(),
+            ticks_per_ms: (tsc_frequency() / 1000).max(1),
+        }
+    }
+}
+
+impl Clock for TscClock {
+    fn now(&self) -> u64 {
+        read_tsc().saturating_sub(self.start) / self.ticks_per_ms
+    }
+}
+
+fn read_tsc() -> u64 {
+    unsafe { _rdtsc() }
+}
+
+// CPUIDのリーフ0x15(水晶発振器の周波数と比率)か、リーフ0x16(基本周波数MHz)から周波数を求める
+fn tsc_frequency() -> u64 {
+    let max_leaf = unsafe { __cpuid(0) }.eax;
+    if max_leaf >= 0x15 {
+        let leaf = unsafe { __cpuid(0x15) };
+        if leaf.eax != 0 && leaf.ebx != 0 && leaf.ecx != 0 {
+            return leaf.ecx as u64 * leaf.ebx as u64 / leaf.eax as u64;
+        }
+    }
+    if max_leaf >= 0x16 {
+        let mhz = unsafe { __cpuid(0x16) }.eax & 0xffff;
+        if mhz != 0 {
+            return mhz as u64 * 1_000_000;
+        }
+    }
+    DEFAULT_TSC_FREQUENCY
+}
@@ -3,4 +3,5 @@
 extern crate alloc;
 
 pub mod app;
+mod clock;
 mod cursor;